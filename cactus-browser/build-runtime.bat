@@ -18,7 +18,8 @@ echo.
 REM Build AOT runtime
 echo [2/2] Publishing Native AOT Runtime...
 cd ..\..\cactus-browser\minimact-runtime-aot
-dotnet publish -c Release -r win-x64
+if "%1"=="" (set RID=win-x64) else (set RID=%1)
+dotnet publish -c Release -r %RID%
 if %ERRORLEVEL% NEQ 0 (
     echo ERROR: Runtime publish failed!
     exit /b 1
@@ -28,6 +29,6 @@ echo.
 echo ========================================
 echo SUCCESS! Native AOT Runtime built!
 echo ========================================
-echo Location: bin\Release\net8.0\win-x64\publish\minimact-runtime-aot.exe
-dir bin\Release\net8.0\win-x64\publish\minimact-runtime-aot.exe
+echo Location: bin\Release\net8.0\%RID%\publish\minimact-runtime-aot.exe
+dir bin\Release\net8.0\%RID%\publish\minimact-runtime-aot.exe
 echo.
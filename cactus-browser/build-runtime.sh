@@ -0,0 +1,25 @@
+#!/usr/bin/env sh
+# Publishes the Native AOT runtime for this machine (or for the RID given as $1)
+set -e
+
+case "$(uname -s)" in
+  Darwin) os=osx ;;
+  *) os=linux ;;
+esac
+case "$(uname -m)" in
+  arm64|aarch64) arch=arm64 ;;
+  *) arch=x64 ;;
+esac
+rid="${1:-$os-$arch}"
+
+cd "$(dirname "$0")"
+
+echo "[1/2] Building Minimact.AspNetCore..."
+(cd ../src/Minimact.AspNetCore && dotnet build -c Release)
+
+echo "[2/2] Publishing Native AOT Runtime for $rid..."
+(cd minimact-runtime-aot && dotnet publish -c Release -r "$rid")
+
+echo
+echo "SUCCESS! Native AOT Runtime built:"
+echo "minimact-runtime-aot/bin/Release/net8.0/$rid/publish/minimact-runtime-aot"
@@ -6,13 +6,20 @@ namespace CactusBrowser.Runtime;
 
 public static class ComponentExecutor
 {
-    public static RenderResponse Execute(RenderRequest request)
+    public static RenderResponse Execute(RenderRequest request, bool stream = false)
     {
         try
         {
+            if (stream) RuntimeStream.Progress("compile", $"{request.CSharp.Length} bytes");
             var assembly = DynamicCompiler.Compile(request.CSharp);
             var component = DynamicCompiler.CreateInstance(assembly);
+
+            if (stream) RuntimeStream.Progress("render");
             var vnode = component.RenderComponent();
+
+            if (stream) StreamChunks(vnode);
+
+            if (stream) RuntimeStream.Progress("serialize");
             var vnodeJson = VNodeSerializer.Serialize(vnode);
             var html = VNodeToHtml(vnode);
 
@@ -36,6 +43,26 @@ public static class ComponentExecutor
         }
     }
 
+    /// <summary>
+    /// Send the root's children one by one so the host can paint them before the
+    /// whole tree is serialized (a non-element root goes as one chunk)
+    /// </summary>
+    private static void StreamChunks(VNode vnode)
+    {
+        if (vnode is not VElement root)
+        {
+            RuntimeStream.Chunk(0, 1, VNodeSerializer.ToJsonNode(vnode), VNodeToHtml(vnode));
+            return;
+        }
+
+        var total = root.Children.Count;
+        for (var i = 0; i < total; i++)
+        {
+            var child = root.Children[i];
+            RuntimeStream.Chunk(i, total, VNodeSerializer.ToJsonNode(child), VNodeToHtml(child));
+        }
+    }
+
     private static string VNodeToHtml(VNode vnode)
     {
         return vnode switch
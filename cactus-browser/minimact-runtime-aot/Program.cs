@@ -16,11 +16,19 @@ public class Program
         {
             if (args.Length == 0)
             {
-                Console.Error.WriteLine("Usage: minimact-runtime-aot <request.json>");
+                Console.Error.WriteLine("Usage: minimact-runtime-aot <request.json> [--stream] | --version");
                 return 1;
             }
 
+            if (args[0] == "--version")
+            {
+                RuntimeStream.Version();
+                return 0;
+            }
+
             var requestPath = args[0];
+            // --stream: line-delimited progress/log/chunk messages, then the result
+            var stream = Array.IndexOf(args, "--stream") > 0;
             var requestJson = File.ReadAllText(requestPath);
 
             var request = JsonSerializer.Deserialize(
@@ -34,7 +42,13 @@ public class Program
                 return 1;
             }
 
-            var result = ComponentExecutor.Execute(request);
+            var result = ComponentExecutor.Execute(request, stream);
+
+            if (stream)
+            {
+                RuntimeStream.Result(result);
+                return result.Success ? 0 : 1;
+            }
 
             var responseJson = JsonSerializer.Serialize(
                 result,
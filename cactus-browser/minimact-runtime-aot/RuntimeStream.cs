@@ -0,0 +1,73 @@
+using System;
+using System.Reflection;
+using System.Runtime.InteropServices;
+using System.Text.Json.Nodes;
+
+namespace CactusBrowser.Runtime;
+
+/// <summary>
+/// Line-delimited JSON messages on stdout (--stream mode)
+/// One message per line: progress, log, chunk (a top-level child of the rendered
+/// tree, in order) and finally the result. The host parses them as they arrive.
+/// </summary>
+public static class RuntimeStream
+{
+    public static void Progress(string stage, string? message = null)
+    {
+        Write(new JsonObject { ["type"] = "progress", ["stage"] = stage, ["message"] = message });
+    }
+
+    public static void Log(string level, string message)
+    {
+        Write(new JsonObject { ["type"] = "log", ["level"] = level, ["message"] = message });
+    }
+
+    public static void Chunk(int index, int total, JsonNode vnode, string html)
+    {
+        Write(new JsonObject
+        {
+            ["type"] = "chunk",
+            ["index"] = index,
+            ["total"] = total,
+            ["vnode"] = vnode,
+            ["html"] = html
+        });
+    }
+
+    public static void Result(RenderResponse response)
+    {
+        Write(new JsonObject
+        {
+            ["type"] = "result",
+            ["success"] = response.Success,
+            ["vnodeJson"] = response.VNodeJson,
+            ["html"] = response.Html,
+            ["error"] = response.Error
+        });
+    }
+
+    /// <summary>
+    /// Protocol version the host checks before first use (1 = --stream support)
+    /// </summary>
+    public const int ProtocolVersion = 1;
+
+    /// <summary>
+    /// --version: runtime version, platform and protocol as one JSON line
+    /// </summary>
+    public static void Version()
+    {
+        Write(new JsonObject
+        {
+            ["version"] = Assembly.GetExecutingAssembly().GetName().Version?.ToString() ?? "0.0.0",
+            ["rid"] = RuntimeInformation.RuntimeIdentifier,
+            ["protocol"] = ProtocolVersion
+        });
+    }
+
+    private static void Write(JsonObject message)
+    {
+        // Compact: one message per line
+        Console.Out.WriteLine(message.ToJsonString());
+        Console.Out.Flush();
+    }
+}
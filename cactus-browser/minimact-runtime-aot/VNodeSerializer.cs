@@ -16,6 +16,11 @@ public static class VNodeSerializer
         });
     }
 
+    /// <summary>
+    /// A node as JSON (for streamed chunks)
+    /// </summary>
+    public static JsonNode ToJsonNode(VNode vnode) => SerializeNode(vnode);
+
     private static JsonNode SerializeNode(VNode vnode)
     {
         return vnode switch
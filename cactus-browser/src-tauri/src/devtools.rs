@@ -0,0 +1,169 @@
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use lazy_static::lazy_static;
+use minimact::metrics_history::HISTORY;
+use minimact::{set_devtools_enabled, timing_breakdown, TimingBreakdown, METRICS};
+
+// ========================================
+// Devtools Window: Patch Inspector + Predictor Dashboard
+// ========================================
+
+/// Label of the secondary devtools window
+const DEVTOOLS_WINDOW: &str = "devtools";
+
+/// Patch batches kept for the live patch log
+const PATCH_LOG_CAPACITY: usize = 500;
+
+/// Seconds of metrics history shown on the dashboard
+const HISTORY_SECONDS: u64 = 300;
+
+lazy_static! {
+    static ref PATCH_LOG: Mutex<VecDeque<DevtoolsEvent>> = Mutex::new(VecDeque::new());
+    static ref COMPONENT_STATS: Mutex<HashMap<String, ComponentStats>> = Mutex::new(HashMap::new());
+}
+
+static SUBSCRIBED: AtomicBool = AtomicBool::new(false);
+static NEXT_SEQ: AtomicU64 = AtomicU64::new(1);
+
+/// One patch batch emitted to the page
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevtoolsEvent {
+    seq: u64,
+    component_id: String,
+    correlation_id: String,
+    /// Unix time in milliseconds
+    emitted_at: u64,
+    patches: Vec<serde_json::Value>,
+    annotations: Option<serde_json::Value>,
+}
+
+/// Per-component render/patch counters
+#[derive(Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ComponentStats {
+    renders: u64,
+    patches: u64,
+    last_correlation_id: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TracedEvent {
+    #[serde(flatten)]
+    event: DevtoolsEvent,
+    /// render → reconcile → apply ack spans for the batch (while still traced)
+    timings: Option<TimingBreakdown>,
+}
+
+/// Record a patch batch sent to the page (called by SignalM² after emitting)
+/// Pushed live to the devtools window while it's subscribed
+pub fn record_patches(
+    app: &AppHandle,
+    component_id: &str,
+    correlation_id: &str,
+    patches: &[serde_json::Value],
+    annotations: Option<&serde_json::Value>
+) {
+    {
+        let mut stats = COMPONENT_STATS.lock().unwrap();
+        let entry = stats.entry(component_id.to_string()).or_default();
+        entry.renders += 1;
+        entry.patches += patches.len() as u64;
+        entry.last_correlation_id = Some(correlation_id.to_string());
+    }
+
+    if patches.is_empty() {
+        return;
+    }
+
+    let event = DevtoolsEvent {
+        seq: NEXT_SEQ.fetch_add(1, Ordering::Relaxed),
+        component_id: component_id.to_string(),
+        correlation_id: correlation_id.to_string(),
+        emitted_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+        patches: patches.to_vec(),
+        annotations: annotations.cloned(),
+    };
+
+    {
+        let mut log = PATCH_LOG.lock().unwrap();
+        if log.len() == PATCH_LOG_CAPACITY {
+            log.pop_front();
+        }
+        log.push_back(event.clone());
+    }
+
+    if SUBSCRIBED.load(Ordering::Relaxed) {
+        if let Err(e) = app.emit_to(DEVTOOLS_WINDOW, "devtools-event", event) {
+            eprintln!("[Devtools] Failed to push event: {}", e);
+        }
+    }
+}
+
+/// Forget one component's counters (when its window's registry is dropped)
+pub fn forget_component(component_id: &str) {
+    COMPONENT_STATS.lock().unwrap().remove(component_id);
+}
+
+/// Forget all per-component counters (when the registry is cleared)
+pub fn clear_component_stats() {
+    COMPONENT_STATS.lock().unwrap().clear();
+}
+
+/// Everything the devtools window shows, in one call
+/// `since_seq` limits the patch log to batches after that sequence number
+#[tauri::command]
+pub fn get_devtools_snapshot(since_seq: Option<u64>) -> Result<serde_json::Value, String> {
+    let patch_log: Vec<TracedEvent> = PATCH_LOG.lock().unwrap()
+        .iter()
+        .filter(|event| event.seq > since_seq.unwrap_or(0))
+        .map(|event| TracedEvent {
+            timings: timing_breakdown(&event.correlation_id),
+            event: event.clone(),
+        })
+        .collect();
+
+    let components = COMPONENT_STATS.lock().unwrap().clone();
+
+    Ok(serde_json::json!({
+        "metrics": METRICS.snapshot(),
+        "history": HISTORY.series(HISTORY_SECONDS),
+        "components": components,
+        "patchLog": patch_log
+    }))
+}
+
+/// Start/stop pushing `devtools-event`s to the devtools window
+/// Subscribing also turns on patch annotations and metrics history
+#[tauri::command]
+pub fn subscribe_devtools_events(enabled: bool) -> Result<bool, String> {
+    SUBSCRIBED.store(enabled, Ordering::Relaxed);
+    set_devtools_enabled(enabled);
+    if enabled {
+        HISTORY.configure(true, HISTORY_SECONDS);
+    }
+    println!("[Devtools] Live events {}", if enabled { "subscribed" } else { "unsubscribed" });
+    Ok(enabled)
+}
+
+/// Open the devtools window (or focus it if it's already open)
+/// Async so the window isn't built on the main thread (deadlocks on Windows)
+#[tauri::command]
+pub async fn open_devtools_window(app: AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(DEVTOOLS_WINDOW) {
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+
+    WebviewWindowBuilder::new(&app, DEVTOOLS_WINDOW, WebviewUrl::App("index.html#devtools".into()))
+        .title("Cactus Devtools")
+        .inner_size(960.0, 640.0)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use lazy_static::lazy_static;
+
+// ========================================
+// Patch Journal (Crash Recovery)
+// ========================================
+//
+// Every window appends what it sends to the page to <app data>/journal/<label>.jsonl:
+// component snapshots, state after each change and the patch batches emitted.
+// Replaying the file (RecoverSession) rebuilds the window's registry after a crash.
+// Once a journal grows past COMPACT_AFTER entries it is rewritten as one snapshot
+// per live component.
+
+/// Entries appended before a journal is compacted
+const COMPACT_AFTER: usize = 500;
+
+lazy_static! {
+    /// Entries appended per window since its last compaction
+    static ref APPENDED: Mutex<HashMap<String, usize>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum JournalEntry {
+    /// A whole component (on Initialize and compaction)
+    Snapshot { component: serde_json::Value },
+    /// Component state after a change
+    State { component_id: String, state: HashMap<String, serde_json::Value> },
+    /// A patch batch emitted to the page
+    Patches { component_id: String, correlation_id: String, patches: Vec<serde_json::Value> },
+}
+
+fn journal_path(app: &AppHandle, window_label: &str) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join("journal")
+        .join(format!("{}.jsonl", window_label)))
+}
+
+/// Append an entry; returns true once the journal is due for compaction
+pub fn append(app: &AppHandle, window_label: &str, entry: &JournalEntry) -> Result<bool, String> {
+    let path = journal_path(app, window_label)?;
+    fs::create_dir_all(path.parent().ok_or("Invalid journal path")?).map_err(|e| e.to_string())?;
+
+    let mut line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    line.push('\n');
+
+    let mut file = OpenOptions::new().create(true).append(true).open(&path).map_err(|e| e.to_string())?;
+    file.write_all(line.as_bytes()).map_err(|e| e.to_string())?;
+
+    let mut appended = APPENDED.lock().unwrap();
+    let count = appended.entry(window_label.to_string()).or_default();
+    *count += 1;
+    Ok(*count >= COMPACT_AFTER)
+}
+
+/// Replace the journal with one snapshot per component
+pub fn compact(app: &AppHandle, window_label: &str, components: Vec<serde_json::Value>) -> Result<(), String> {
+    let path = journal_path(app, window_label)?;
+    fs::create_dir_all(path.parent().ok_or("Invalid journal path")?).map_err(|e| e.to_string())?;
+
+    let mut contents = String::new();
+    for component in components {
+        contents.push_str(&serde_json::to_string(&JournalEntry::Snapshot { component }).map_err(|e| e.to_string())?);
+        contents.push('\n');
+    }
+
+    // Write-then-rename so a crash mid-compaction keeps the old journal
+    let tmp = path.with_extension("jsonl.tmp");
+    fs::write(&tmp, contents).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, &path).map_err(|e| e.to_string())?;
+
+    APPENDED.lock().unwrap().insert(window_label.to_string(), 0);
+    println!("[Journal] Compacted journal of '{}'", window_label);
+    Ok(())
+}
+
+/// Entries of a window's journal, oldest first
+/// A torn last line (crash mid-write) is skipped
+pub fn read(app: &AppHandle, window_label: &str) -> Result<Vec<JournalEntry>, String> {
+    let path = journal_path(app, window_label)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let contents = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let mut entries = Vec::new();
+    for (number, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        match serde_json::from_str(line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => eprintln!("[Journal] Skipping line {} of '{}': {}", number + 1, window_label, e),
+        }
+    }
+    Ok(entries)
+}
+
+/// Delete a window's journal (the window closed normally)
+pub fn remove(app: &AppHandle, window_label: &str) {
+    APPENDED.lock().unwrap().remove(window_label);
+    if let Ok(path) = journal_path(app, window_label) {
+        let _ = fs::remove_file(path);
+    }
+}
@@ -1,30 +1,43 @@
 // Prevents additional console window on Windows in release
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod devtools;
+mod journal;
 mod runtime;
+mod sandbox;
 mod signalm;
 
 use std::fs;
 use std::path::PathBuf;
 use tauri::Manager;
 
+/// Cache entry (same shape as the frontend's FileCache entries)
 #[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct CachedFile {
     content: String,
     sha: String,
     cached_at: u64,
     url: String,
+    /// ETag of the response the content came from (absent for entries written by the frontend)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
 }
 
-/// Read a file from the cache
-#[tauri::command]
-fn read_cache(app: tauri::AppHandle, key: String) -> Result<Option<String>, String> {
-    let cache_path = app
+/// Path of a cache entry
+fn cache_file_path(app: &tauri::AppHandle, key: &str) -> Result<PathBuf, String> {
+    Ok(app
         .path()
         .app_data_dir()
         .map_err(|e| e.to_string())?
         .join("cactus-cache")
-        .join(format!("{}.json", key));
+        .join(format!("{}.json", key)))
+}
+
+/// Read a file from the cache
+#[tauri::command]
+fn read_cache(app: tauri::AppHandle, key: String) -> Result<Option<String>, String> {
+    let cache_path = cache_file_path(&app, &key)?;
 
     if !cache_path.exists() {
         return Ok(None);
@@ -69,6 +82,116 @@ fn clear_cache(app: tauri::AppHandle) -> Result<(), String> {
     Ok(())
 }
 
+/// Result of fetch_remote_file
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RemoteFile {
+    content: String,
+    sha: String,
+    etag: Option<String>,
+    /// "new" (nothing cached), "modified", "not-modified" (304, cache reused) or
+    /// "stale" (server unreachable, cache reused)
+    freshness: &'static str,
+    /// When the content was last confirmed against the server (ms since epoch)
+    validated_at: u64,
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Fetch a remote file, revalidating the cache entry under `key` with a conditional GET
+/// Sends If-None-Match with the cached ETag (or the cached sha, quoted, for entries
+/// without one); a 304 reuses the cached content. `headers` are added to the request
+/// (e.g. Authorization for GitHub)
+#[tauri::command]
+async fn fetch_remote_file(
+    app: tauri::AppHandle,
+    url: String,
+    key: String,
+    headers: Option<std::collections::HashMap<String, String>>
+) -> Result<RemoteFile, String> {
+    let cache_path = cache_file_path(&app, &key)?;
+    let cached: Option<CachedFile> = fs::read_to_string(&cache_path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok());
+
+    let mut request = reqwest::Client::new()
+        .get(&url)
+        .header(reqwest::header::USER_AGENT, "cactus-browser");
+    for (name, value) in headers.unwrap_or_default() {
+        request = request.header(name, value);
+    }
+    if let Some(cached) = &cached {
+        let validator = cached.etag.clone().unwrap_or_else(|| format!("\"{}\"", cached.sha));
+        request = request.header(reqwest::header::IF_NONE_MATCH, validator);
+    }
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            // Offline: serve what we have
+            let cached = cached.ok_or_else(|| format!("Failed to fetch {}: {}", url, e))?;
+            println!("[Cache] {} unreachable, serving stale copy: {}", url, e);
+            return Ok(RemoteFile {
+                content: cached.content,
+                sha: cached.sha,
+                etag: cached.etag,
+                freshness: "stale",
+                validated_at: cached.cached_at,
+            });
+        }
+    };
+
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        if let Some(mut cached) = cached {
+            cached.cached_at = now_millis();
+            let json = serde_json::to_string(&cached).map_err(|e| e.to_string())?;
+            fs::write(&cache_path, json).map_err(|e| e.to_string())?;
+            return Ok(RemoteFile {
+                content: cached.content,
+                sha: cached.sha,
+                etag: cached.etag,
+                freshness: "not-modified",
+                validated_at: cached.cached_at,
+            });
+        }
+    }
+
+    if !response.status().is_success() {
+        return Err(format!("Failed to fetch {}: HTTP {}", url, response.status()));
+    }
+
+    let etag = response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from);
+    let content = response.text().await.map_err(|e| e.to_string())?;
+
+    // The ETag without quotes/weak prefix stands in for the sha
+    let sha = etag
+        .as_deref()
+        .map(|etag| etag.trim_start_matches("W/").trim_matches('"').to_string())
+        .unwrap_or_default();
+    let freshness = if cached.is_some() { "modified" } else { "new" };
+
+    let entry = CachedFile { content, sha, cached_at: now_millis(), url, etag };
+    fs::create_dir_all(cache_path.parent().ok_or("Invalid cache path")?).map_err(|e| e.to_string())?;
+    fs::write(&cache_path, serde_json::to_string(&entry).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+
+    Ok(RemoteFile {
+        content: entry.content,
+        sha: entry.sha,
+        etag: entry.etag,
+        freshness,
+        validated_at: entry.cached_at,
+    })
+}
+
 /// Read a local TSX file
 #[tauri::command]
 fn read_local_file(path: String) -> Result<String, String> {
@@ -78,15 +201,34 @@ fn read_local_file(path: String) -> Result<String, String> {
 fn main() {
     tauri::Builder::default()
         .plugin(tauri_plugin_fs::init())
+        .on_window_event(|window, event| {
+            // A closed window's components can never receive patches again
+            if let tauri::WindowEvent::Destroyed = event {
+                signalm::clear_window_registry(window.label().to_string());
+                journal::remove(window.app_handle(), window.label());
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             read_cache,
             write_cache,
             clear_cache,
             read_local_file,
+            fetch_remote_file,
             runtime::execute_component,
+            runtime::check_runtime,
+            runtime::set_runtime_path,
+            sandbox::inspect_component_permissions,
+            sandbox::grant_component_permissions,
+            sandbox::get_sandbox_policy,
+            sandbox::set_sandbox_policy,
             signalm::signalm_invoke,
             signalm::get_component_count,
-            signalm::clear_components
+            signalm::clear_components,
+            signalm::list_window_registries,
+            signalm::clear_window_registry,
+            devtools::get_devtools_snapshot,
+            devtools::subscribe_devtools_events,
+            devtools::open_devtools_window
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -2,23 +2,77 @@ use serde::{Deserialize, Serialize};
 use std::process::Command;
 use std::fs;
 use std::path::PathBuf;
-use tauri::{AppHandle, Manager};
+use tauri::{AppHandle, Emitter, Manager};
+use crate::sandbox;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ExecuteRequest {
     pub csharp: String,
     pub templates: serde_json::Value,
     pub initial_state: serde_json::Value,
+    /// Tags the `runtime-stream` events of this execution (a fresh id when absent)
+    #[serde(default, skip_serializing)]
+    pub stream_id: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ExecuteResponse {
     pub success: bool,
+    #[serde(alias = "vnodeJson")]
     pub vnode_json: Option<String>,
     pub html: Option<String>,
     pub error: Option<String>,
 }
 
+// ========================================
+// Streaming Protocol
+// ========================================
+//
+// With --stream the runtime writes one JSON message per stdout line as it works:
+//   {"type":"progress","stage":"compile","message":"..."}
+//   {"type":"log","level":"info","message":"..."}
+//   {"type":"chunk","index":0,"total":3,"vnode":{...},"html":"..."}   (top-level children, in order)
+//   {"type":"result","success":true,"vnodeJson":"...","html":"...","error":null}
+// Everything but the result is forwarded to the frontend as `runtime-stream` events,
+// so big components paint chunk by chunk. A runtime that ignores --stream prints
+// only the response JSON, which is still accepted.
+
+/// One line of runtime output
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum RuntimeMessage {
+    Progress {
+        stage: String,
+        #[serde(default)]
+        message: Option<String>,
+    },
+    Log {
+        #[serde(default = "default_log_level")]
+        level: String,
+        message: String,
+    },
+    Chunk {
+        index: usize,
+        total: usize,
+        vnode: serde_json::Value,
+        #[serde(default)]
+        html: Option<String>,
+    },
+    Result(ExecuteResponse),
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+/// Payload of a `runtime-stream` event
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RuntimeStreamEvent<'a> {
+    stream_id: &'a str,
+    message: &'a serde_json::Value,
+}
+
 #[tauri::command]
 pub async fn execute_component(
     app: AppHandle,
@@ -27,22 +81,19 @@ pub async fn execute_component(
     println!("[Tauri] execute_component called");
     println!("[Tauri] C# code length: {} bytes", request.csharp.len());
 
-    // 1. Get path to minimact-runtime.exe
+    // 1. Get path to minimact-runtime.exe (absolute: the process runs in the sandbox directory)
     let runtime_path = get_runtime_path(&app)?;
+    let runtime_path = runtime_path.canonicalize().unwrap_or(runtime_path);
 
     println!("[Tauri] Runtime path: {}", runtime_path.display());
 
-    if !runtime_path.exists() {
-        return Err(format!(
-            "Runtime not found at: {}\nRun build-runtime.bat first!",
-            runtime_path.display()
-        ));
-    }
+    // 2. Refuse components needing permissions the user hasn't granted
+    let granted = sandbox::check_permissions(&request.csharp)?;
 
-    // 2. Write request to temp file
-    let temp_dir = std::env::temp_dir();
+    // 3. Write request into a fresh sandbox directory
     let request_id = uuid::Uuid::new_v4();
-    let request_path = temp_dir.join(format!("cactus-request-{}.json", request_id));
+    let sandbox_dir = sandbox::create_sandbox_dir(&request_id.to_string())?;
+    let request_path = sandbox_dir.join("request.json");
 
     println!("[Tauri] Writing request to: {}", request_path.display());
 
@@ -52,21 +103,46 @@ pub async fn execute_component(
     fs::write(&request_path, request_json)
         .map_err(|e| format!("Failed to write request file: {}", e))?;
 
-    // 3. Execute runtime
-    println!("[Tauri] Spawning runtime process...");
+    // 4. Execute runtime inside the sandbox
+    println!("[Tauri] Spawning sandboxed runtime process...");
+
+    let stream_id = request.stream_id.clone().unwrap_or_else(|| request_id.to_string());
+    let mut result: Option<ExecuteResponse> = None;
+    let mut on_line = |line: &str| {
+        // Non-protocol lines (old runtime, stray output) are left for the fallback parse
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(line) else { return };
+        match serde_json::from_value::<RuntimeMessage>(value.clone()) {
+            Ok(RuntimeMessage::Result(response)) => result = Some(response),
+            Ok(RuntimeMessage::Log { level, message }) => {
+                println!("[Runtime:{}] {}", level, message);
+                let _ = app.emit("runtime-stream", RuntimeStreamEvent { stream_id: &stream_id, message: &value });
+            }
+            Ok(_) => {
+                let _ = app.emit("runtime-stream", RuntimeStreamEvent { stream_id: &stream_id, message: &value });
+            }
+            Err(_) => {}
+        }
+    };
 
-    let output = Command::new(&runtime_path)
-        .arg(&request_path)
-        .output()
-        .map_err(|e| format!("Failed to execute runtime: {}\nPath: {}", e, runtime_path.display()))?;
+    let mut command = Command::new(&runtime_path);
+    command.arg(&request_path).arg("--stream");
+    let output = sandbox::run_sandboxed(command, &sandbox_dir, &granted, &mut on_line)
+        .map_err(|e| format!("{}\nPath: {}", e, runtime_path.display()));
 
-    // 4. Clean up temp file
-    let _ = fs::remove_file(&request_path);
+    // 5. Clean up the sandbox directory
+    let _ = fs::remove_dir_all(&sandbox_dir);
+    let output = output?;
 
-    // 5. Check exit code
+    // 6. Check exit code
     let exit_code = output.status.code().unwrap_or(-1);
     println!("[Tauri] Runtime exit code: {}", exit_code);
 
+    // A streamed result carries its own success flag (the runtime exits 1 on render errors)
+    if let Some(response) = result {
+        println!("[Tauri] Execution success: {} (streamed)", response.success);
+        return Ok(response);
+    }
+
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         let stdout = String::from_utf8_lossy(&output.stdout);
@@ -79,7 +155,7 @@ pub async fn execute_component(
         ));
     }
 
-    // 6. Parse response
+    // 7. Parse response (runtime without streaming support)
     let stdout = String::from_utf8_lossy(&output.stdout);
     println!("[Tauri] Runtime stdout length: {} bytes", stdout.len());
 
@@ -101,37 +177,192 @@ pub async fn execute_component(
     Ok(response)
 }
 
-fn get_runtime_path(app: &AppHandle) -> Result<PathBuf, String> {
-    // In development: use local build
-    let dev_path = PathBuf::from("minimact-runtime/bin/Release/net8.0/win-x64/publish/minimact-runtime.exe");
+// ========================================
+// Runtime Discovery
+// ========================================
+//
+// The runtime is a Native AOT binary published per .NET runtime identifier (RID).
+// Lookup order:
+// 1. the `runtimePath` override in runtime-settings.json (app data dir)
+// 2. CACTUS_RUNTIME_PATH
+// 3. development builds: minimact-runtime-aot/bin/Release/net8.0/<rid>/publish/
+// 4. the bundle: <resources>/runtime/<rid>/, then <resources>/ (older bundles)
+// The binary is minimact-runtime-aot (older builds: minimact-runtime), .exe on Windows.
+
+/// Lowest runtime protocol this host speaks (1 = --stream, --version)
+const MIN_RUNTIME_PROTOCOL: u64 = 1;
+
+const RUNTIME_NAMES: [&str; 2] = ["minimact-runtime-aot", "minimact-runtime"];
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct RuntimeSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    runtime_path: Option<PathBuf>,
+}
+
+/// .NET RID of the host, e.g. "linux-x64" or "osx-arm64"
+pub fn host_rid() -> String {
+    let os = match std::env::consts::OS {
+        "windows" => "win",
+        "macos" => "osx",
+        other => other,
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "x64",
+        "aarch64" => "arm64",
+        "x86" => "x86",
+        other => other,
+    };
+    format!("{}-{}", os, arch)
+}
+
+fn executable_names() -> impl Iterator<Item = String> {
+    RUNTIME_NAMES.iter().map(|name| format!("{}{}", name, std::env::consts::EXE_SUFFIX))
+}
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app.path().app_data_dir().map_err(|e| e.to_string())?.join("runtime-settings.json"))
+}
 
-    if dev_path.exists() {
-        println!("[Tauri] Using development runtime");
-        return Ok(dev_path);
+fn load_settings(app: &AppHandle) -> RuntimeSettings {
+    settings_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Every place the runtime may be, in lookup order, with where it came from
+fn runtime_candidates(app: &AppHandle) -> Vec<(PathBuf, &'static str)> {
+    let rid = host_rid();
+    let mut candidates = Vec::new();
+
+    if let Some(path) = load_settings(app).runtime_path {
+        candidates.push((path, "settings"));
+    }
+    if let Some(path) = std::env::var_os("CACTUS_RUNTIME_PATH") {
+        candidates.push((PathBuf::from(path), "environment"));
+    }
+    for root in ["minimact-runtime-aot", "../minimact-runtime-aot"] {
+        let publish = PathBuf::from(root).join("bin/Release/net8.0").join(&rid).join("publish");
+        candidates.extend(executable_names().map(|exe| (publish.join(exe), "development")));
+    }
+    if let Ok(resources) = app.path().resource_dir() {
+        let bundled = resources.join("runtime").join(&rid);
+        candidates.extend(executable_names().map(|exe| (bundled.join(exe), "bundled")));
+        candidates.extend(executable_names().map(|exe| (resources.join(exe), "bundled")));
     }
+    candidates
+}
 
-    // Try current directory (fallback)
-    let current_dir_path = PathBuf::from("./minimact-runtime.exe");
-    if current_dir_path.exists() {
-        println!("[Tauri] Using current directory runtime");
-        return Ok(current_dir_path);
+fn resolve_runtime(app: &AppHandle) -> Result<(PathBuf, &'static str), String> {
+    let candidates = runtime_candidates(app);
+    if let Some((path, source)) = candidates.iter().find(|(path, _)| path.is_file()) {
+        println!("[Tauri] Using {} runtime ({})", source, host_rid());
+        return Ok((path.clone(), source));
     }
 
-    // In production: runtime is bundled with app
-    let resource_path = app.path()
-        .resource_dir()
-        .map_err(|e| e.to_string())?
-        .join("minimact-runtime.exe");
+    let searched: Vec<String> = candidates.iter().map(|(path, _)| format!("- {}", path.display())).collect();
+    Err(format!("Runtime not found for {} in any location:\n{}", host_rid(), searched.join("\n")))
+}
+
+fn get_runtime_path(app: &AppHandle) -> Result<PathBuf, String> {
+    resolve_runtime(app).map(|(path, _)| path)
+}
+
+/// Point the browser at a specific runtime binary (None restores discovery)
+#[tauri::command]
+pub fn set_runtime_path(app: AppHandle, path: Option<String>) -> Result<(), String> {
+    let settings = RuntimeSettings { runtime_path: path.map(PathBuf::from) };
+    if let Some(path) = settings.runtime_path.as_ref().filter(|path| !path.is_file()) {
+        return Err(format!("No runtime at {}", path.display()));
+    }
 
-    if resource_path.exists() {
-        println!("[Tauri] Using bundled runtime");
-        return Ok(resource_path);
+    let settings_path = settings_path(&app)?;
+    if let Some(dir) = settings_path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
     }
+    let json = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+    fs::write(settings_path, json).map_err(|e| e.to_string())
+}
+
+/// Which runtime would be used and whether this host can drive it
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeCheck {
+    pub path: Option<PathBuf>,
+    /// "settings", "environment", "development" or "bundled"
+    pub source: Option<&'static str>,
+    pub host_rid: String,
+    pub runtime_rid: Option<String>,
+    pub version: Option<String>,
+    pub protocol: Option<u64>,
+    pub compatible: bool,
+    pub error: Option<String>,
+}
 
-    Err(format!(
-        "Runtime not found in any location:\n- {}\n- {}\n- {}",
-        dev_path.display(),
-        current_dir_path.display(),
-        resource_path.display()
-    ))
+/// Find the runtime and ask it for its version (--version) before first use
+#[tauri::command]
+pub async fn check_runtime(app: AppHandle) -> RuntimeCheck {
+    let mut check = RuntimeCheck {
+        path: None,
+        source: None,
+        host_rid: host_rid(),
+        runtime_rid: None,
+        version: None,
+        protocol: None,
+        compatible: false,
+        error: None,
+    };
+
+    let (path, source) = match resolve_runtime(&app) {
+        Ok(found) => found,
+        Err(e) => {
+            check.error = Some(e);
+            return check;
+        }
+    };
+    check.path = Some(path.clone());
+    check.source = Some(source);
+
+    let output = match Command::new(&path).arg("--version").output() {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            check.error = Some(format!(
+                "Runtime doesn't support --version (exit code {:?}); it predates protocol {}",
+                output.status.code(),
+                MIN_RUNTIME_PROTOCOL
+            ));
+            return check;
+        }
+        Err(e) => {
+            // Typically a binary for another OS/architecture
+            check.error = Some(format!("Runtime can't start on {}: {}", check.host_rid, e));
+            return check;
+        }
+    };
+
+    let info: serde_json::Value = match serde_json::from_slice(&output.stdout) {
+        Ok(info) => info,
+        Err(e) => {
+            check.error = Some(format!("Unreadable --version output: {}", e));
+            return check;
+        }
+    };
+    check.version = info["version"].as_str().map(String::from);
+    check.runtime_rid = info["rid"].as_str().map(String::from);
+    check.protocol = info["protocol"].as_u64();
+
+    let protocol_ok = check.protocol.is_some_and(|protocol| protocol >= MIN_RUNTIME_PROTOCOL);
+    // A non-portable RID (e.g. "ubuntu.22.04-x64") still ends with the architecture
+    let arch = check.host_rid.rsplit('-').next().unwrap_or_default().to_string();
+    let arch_ok = check.runtime_rid.as_deref().is_some_and(|rid| rid.ends_with(&format!("-{}", arch)));
+    check.compatible = protocol_ok && arch_ok;
+    if !protocol_ok {
+        check.error = Some(format!("Runtime protocol {:?} is older than {}", check.protocol, MIN_RUNTIME_PROTOCOL));
+    } else if !arch_ok {
+        check.error = Some(format!("Runtime is built for {:?}, host is {}", check.runtime_rid, check.host_rid));
+    }
+    check
 }
@@ -0,0 +1,344 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use lazy_static::lazy_static;
+
+// ========================================
+// Sandbox Policy for Executed Components
+// ========================================
+//
+// Generated C# runs in a separate runtime process. Every run gets:
+// - its own scratch working directory (deleted afterwards)
+// - a scrubbed environment (only what the .NET runtime needs to start)
+// - CPU time, memory and wall-clock limits: a job object on Windows, rlimits elsewhere
+//
+// On top of that, each component's source is scanned for the capabilities it uses
+// (network, file system, ...). A component needing a capability the user hasn't
+// granted doesn't run; the frontend shows the manifest and asks.
+
+/// Resource limits for one runtime process
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SandboxPolicy {
+    /// CPU time (seconds)
+    pub max_cpu_secs: u64,
+    /// Memory (bytes)
+    pub max_memory_bytes: u64,
+    /// Wall-clock time before the process is killed (milliseconds)
+    pub timeout_ms: u64,
+}
+
+impl Default for SandboxPolicy {
+    fn default() -> Self {
+        SandboxPolicy {
+            max_cpu_secs: 10,
+            max_memory_bytes: 512 * 1024 * 1024,
+            timeout_ms: 15_000,
+        }
+    }
+}
+
+/// Capabilities a component uses
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionManifest {
+    pub network: bool,
+    pub file_system: bool,
+    pub processes: bool,
+    /// Reading environment variables (the environment is only passed through when granted)
+    pub environment: bool,
+}
+
+impl PermissionManifest {
+    /// Capabilities the C# source uses (by the APIs it references)
+    pub fn scan(csharp: &str) -> Self {
+        let uses = |apis: &[&str]| apis.iter().any(|api| references(csharp, api));
+        PermissionManifest {
+            network: uses(&["System.Net", "HttpClient", "WebClient", "Socket", "WebRequest"]),
+            file_system: uses(&["System.IO.File", "File.", "Directory.", "FileStream", "StreamWriter", "StreamReader"]),
+            processes: uses(&["System.Diagnostics.Process", "Process.Start", "ProcessStartInfo"]),
+            environment: uses(&["Environment.GetEnvironmentVariable", "Environment.GetEnvironmentVariables"]),
+        }
+    }
+
+    /// Capabilities in `self` that `granted` doesn't cover
+    pub fn missing(&self, granted: &PermissionManifest) -> Vec<&'static str> {
+        [
+            (self.network && !granted.network, "network"),
+            (self.file_system && !granted.file_system, "fileSystem"),
+            (self.processes && !granted.processes, "processes"),
+            (self.environment && !granted.environment, "environment"),
+        ]
+        .into_iter()
+        .filter_map(|(missing, name)| missing.then_some(name))
+        .collect()
+    }
+}
+
+/// `api` appears in `csharp` as a whole identifier (so "File." doesn't match "Profile.")
+fn references(csharp: &str, api: &str) -> bool {
+    csharp.match_indices(api).any(|(at, _)| {
+        !csharp[..at].chars().next_back().is_some_and(|c| c.is_alphanumeric() || c == '_')
+    })
+}
+
+lazy_static! {
+    static ref POLICY: Mutex<SandboxPolicy> = Mutex::new(SandboxPolicy::default());
+    /// Granted permissions by component hash
+    static ref GRANTS: Mutex<HashMap<String, PermissionManifest>> = Mutex::new(HashMap::new());
+}
+
+/// Environment variables the runtime process keeps
+const ALLOWED_ENV: [&str; 5] = ["PATH", "SystemRoot", "windir", "LANG", "TZ"];
+
+/// Identifies a component's source for grants
+pub fn component_hash(csharp: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    csharp.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Fail unless the user granted everything the component needs
+pub fn check_permissions(csharp: &str) -> Result<PermissionManifest, String> {
+    let required = PermissionManifest::scan(csharp);
+    let granted = GRANTS.lock().unwrap().get(&component_hash(csharp)).copied().unwrap_or_default();
+    let missing = required.missing(&granted);
+    if !missing.is_empty() {
+        return Err(format!("Component needs permissions that weren't granted: {}", missing.join(", ")));
+    }
+    Ok(granted)
+}
+
+/// Scratch working directory for one run
+pub fn create_sandbox_dir(request_id: &str) -> Result<PathBuf, String> {
+    let dir = std::env::temp_dir().join(format!("cactus-sandbox-{}", request_id));
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create sandbox directory: {}", e))?;
+    Ok(dir)
+}
+
+/// Run the runtime under the current policy; the process is killed when it runs out of time
+/// `on_line` sees each stdout line as soon as the process writes it
+pub fn run_sandboxed(
+    mut command: Command,
+    sandbox_dir: &Path,
+    granted: &PermissionManifest,
+    on_line: &mut dyn FnMut(&str)
+) -> Result<Output, String> {
+    let policy = POLICY.lock().unwrap().clone();
+
+    command.current_dir(sandbox_dir).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    if !granted.environment {
+        command.env_clear();
+        for name in ALLOWED_ENV {
+            if let Ok(value) = std::env::var(name) {
+                command.env(name, value);
+            }
+        }
+    }
+    command
+        .env("TEMP", sandbox_dir)
+        .env("TMP", sandbox_dir)
+        .env("TMPDIR", sandbox_dir)
+        // Keep the GC inside the memory limit instead of dying on the OS limit
+        .env("DOTNET_GCHeapHardLimit", format!("{:x}", policy.max_memory_bytes));
+
+    #[cfg(unix)]
+    limits::apply_rlimits(&mut command, &policy);
+
+    let mut child = command.spawn().map_err(|e| format!("Failed to execute runtime: {}", e))?;
+
+    #[cfg(windows)]
+    let _job = limits::JobObject::assign(&child, &policy)?;
+
+    // Drain pipes on threads so a chatty process can't block on a full pipe
+    let stdout = child.stdout.take().ok_or("Runtime stdout unavailable")?;
+    let mut stderr = child.stderr.take().ok_or("Runtime stderr unavailable")?;
+    let (lines_tx, lines) = mpsc::channel::<String>();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if lines_tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let stderr_reader = std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = stderr.read_to_end(&mut buffer);
+        buffer
+    });
+
+    let deadline = Instant::now() + Duration::from_millis(policy.timeout_ms);
+    let mut stdout = Vec::new();
+    loop {
+        match lines.recv_timeout(Duration::from_millis(10)) {
+            Ok(line) => {
+                on_line(&line);
+                stdout.extend_from_slice(line.as_bytes());
+                stdout.push(b'\n');
+            }
+            // Stdout closed: the process is exiting
+            Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("Runtime killed after {}ms (sandbox timeout)", policy.timeout_ms));
+        }
+    }
+
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("Runtime killed after {}ms (sandbox timeout)", policy.timeout_ms));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+
+    Ok(Output {
+        status,
+        stdout,
+        stderr: stderr_reader.join().unwrap_or_default(),
+    })
+}
+
+#[cfg(unix)]
+mod limits {
+    use super::SandboxPolicy;
+    use std::os::unix::process::CommandExt;
+    use std::process::Command;
+
+    pub fn apply_rlimits(command: &mut Command, policy: &SandboxPolicy) {
+        let cpu = policy.max_cpu_secs as libc::rlim_t;
+        // Address space, not RSS: leave room for code and runtime reservations above the heap
+        let memory = policy.max_memory_bytes.saturating_mul(2) as libc::rlim_t;
+        // SAFETY: only async-signal-safe calls between fork and exec
+        unsafe {
+            command.pre_exec(move || {
+                let set = |resource, limit: libc::rlim_t| {
+                    let rlimit = libc::rlimit { rlim_cur: limit, rlim_max: limit };
+                    if libc::setrlimit(resource, &rlimit) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                };
+                set(libc::RLIMIT_CPU, cpu)?;
+                set(libc::RLIMIT_AS, memory)?;
+                set(libc::RLIMIT_CORE, 0)
+            });
+        }
+    }
+}
+
+#[cfg(windows)]
+mod limits {
+    use super::SandboxPolicy;
+    use std::os::windows::io::AsRawHandle;
+    use std::process::Child;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation, SetInformationJobObject,
+        JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_LIMIT_ACTIVE_PROCESS, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+        JOB_OBJECT_LIMIT_PROCESS_MEMORY, JOB_OBJECT_LIMIT_PROCESS_TIME,
+    };
+
+    /// Job object holding the runtime process; closing it kills the process
+    pub struct JobObject(HANDLE);
+
+    impl JobObject {
+        pub fn assign(child: &Child, policy: &SandboxPolicy) -> Result<JobObject, String> {
+            // SAFETY: plain Win32 calls on handles we own; the info struct outlives the call
+            unsafe {
+                let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+                if job.is_null() {
+                    return Err(format!("CreateJobObject failed: {}", std::io::Error::last_os_error()));
+                }
+                let job = JobObject(job);
+
+                let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+                info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_PROCESS_TIME
+                    | JOB_OBJECT_LIMIT_PROCESS_MEMORY
+                    | JOB_OBJECT_LIMIT_ACTIVE_PROCESS
+                    | JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+                // 100ns units
+                info.BasicLimitInformation.PerProcessUserTimeLimit = (policy.max_cpu_secs * 10_000_000) as i64;
+                info.BasicLimitInformation.ActiveProcessLimit = 1;
+                info.ProcessMemoryLimit = policy.max_memory_bytes as usize;
+
+                if SetInformationJobObject(
+                    job.0,
+                    JobObjectExtendedLimitInformation,
+                    &info as *const _ as *const _,
+                    std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+                ) == 0
+                {
+                    return Err(format!("SetInformationJobObject failed: {}", std::io::Error::last_os_error()));
+                }
+                if AssignProcessToJobObject(job.0, child.as_raw_handle() as HANDLE) == 0 {
+                    return Err(format!("AssignProcessToJobObject failed: {}", std::io::Error::last_os_error()));
+                }
+                Ok(job)
+            }
+        }
+    }
+
+    impl Drop for JobObject {
+        fn drop(&mut self) {
+            // SAFETY: the handle came from CreateJobObjectW and is closed once
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+}
+
+// ========================================
+// Commands
+// ========================================
+
+/// What a component needs, what the user granted so far, and what's missing
+#[tauri::command]
+pub fn inspect_component_permissions(csharp: String) -> serde_json::Value {
+    let component_hash = component_hash(&csharp);
+    let required = PermissionManifest::scan(&csharp);
+    let granted = GRANTS.lock().unwrap().get(&component_hash).copied().unwrap_or_default();
+    serde_json::json!({
+        "componentHash": component_hash,
+        "required": required,
+        "granted": granted,
+        "missing": required.missing(&granted)
+    })
+}
+
+/// Record the user's decision for a component (replaces earlier grants)
+#[tauri::command]
+pub fn grant_component_permissions(component_hash: String, permissions: PermissionManifest) {
+    println!("[Sandbox] Permissions for {}: {:?}", component_hash, permissions);
+    GRANTS.lock().unwrap().insert(component_hash, permissions);
+}
+
+#[tauri::command]
+pub fn get_sandbox_policy() -> SandboxPolicy {
+    POLICY.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn set_sandbox_policy(policy: SandboxPolicy) -> Result<(), String> {
+    if policy.max_cpu_secs == 0 || policy.max_memory_bytes == 0 || policy.timeout_ms == 0 {
+        return Err("Sandbox limits must be greater than zero".to_string());
+    }
+    *POLICY.lock().unwrap() = policy;
+    Ok(())
+}
@@ -1,20 +1,94 @@
 use serde::{Deserialize, Serialize};
-use tauri::{AppHandle, Manager, Emitter};
+use tauri::{AppHandle, Manager, Emitter, WebviewWindow};
 use crate::runtime::{ExecuteRequest, execute_component};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
 use lazy_static::lazy_static;
 use uuid::Uuid;
-use minimact::{reconcile, VNode, Patch};
+use crate::journal::{self, JournalEntry};
+use minimact::{reconcile_traced, record_span, timing_breakdown, generate_resync_message, check_drift, annotate_patches, devtools_enabled, set_devtools_enabled, apply_patches, tree_json, HexPath, VNode, Patch, ReconcileStrategy, TraceStage};
+use minimact::template_renderer::render_template_patch;
+use minimact::vdom::TemplateInfo;
 
 // ========================================
-// Component Registry (Global State)
+// Component Registry (Per-Window State)
 // ========================================
 
 lazy_static! {
-    static ref COMPONENT_REGISTRY: Mutex<HashMap<String, ComponentInstance>> = Mutex::new(HashMap::new());
+    /// Components by window (webview label), then by component id
+    /// Two windows rendering the same app each get their own instances, and patches
+    /// are only emitted to the window the component lives in
+    static ref COMPONENT_REGISTRY: Mutex<HashMap<String, HashMap<String, ComponentInstance>>> = Mutex::new(HashMap::new());
 }
 
+/// Look up a component in a window's registry
+fn find_component<'a>(
+    registries: &'a mut HashMap<String, HashMap<String, ComponentInstance>>,
+    window_label: &str,
+    component_id: &str
+) -> Result<&'a mut ComponentInstance, String> {
+    registries.get_mut(window_label)
+        .and_then(|registry| registry.get_mut(component_id))
+        .ok_or_else(|| format!("Component not found in window '{}': {}", window_label, component_id))
+}
+
+/// Journal a state change and the patches it sent, compacting when due
+/// Journal failures are logged - they must not fail the update itself
+fn journal_batch(
+    app: &AppHandle,
+    window_label: &str,
+    component_id: &str,
+    correlation_id: &str,
+    state: Option<HashMap<String, serde_json::Value>>,
+    patches: &[serde_json::Value]
+) {
+    let mut entries = Vec::new();
+    if let Some(state) = state {
+        entries.push(JournalEntry::State { component_id: component_id.to_string(), state });
+    }
+    if !patches.is_empty() {
+        entries.push(JournalEntry::Patches {
+            component_id: component_id.to_string(),
+            correlation_id: correlation_id.to_string(),
+            patches: patches.to_vec(),
+        });
+    }
+
+    let mut compact = false;
+    for entry in &entries {
+        match journal::append(app, window_label, entry) {
+            Ok(due) => compact |= due,
+            Err(e) => eprintln!("[SignalM²] Failed to journal {}: {}", component_id, e),
+        }
+    }
+    if compact {
+        compact_journal(app, window_label);
+    }
+}
+
+fn compact_journal(app: &AppHandle, window_label: &str) {
+    let components: Vec<serde_json::Value> = {
+        let registries = COMPONENT_REGISTRY.lock().unwrap();
+        registries.get(window_label)
+            .into_iter()
+            .flat_map(HashMap::values)
+            .filter_map(|component| serde_json::to_value(component).ok())
+            .collect()
+    };
+    if let Err(e) = journal::compact(app, window_label, components) {
+        eprintln!("[SignalM²] Failed to compact journal of '{}': {}", window_label, e);
+    }
+}
+
+/// Emit a SignalM² message to one window only
+fn emit_to_window(app: &AppHandle, window_label: &str, message: SignalMMessage) -> Result<(), String> {
+    app.emit_to(window_label, "signalm-message", message).map_err(|e| e.to_string())
+}
+
+/// State changes kept per component for time travel
+const MAX_STATE_HISTORY: usize = 200;
+
 #[derive(Clone, Serialize, Deserialize)]
 struct ComponentInstance {
     id: String,
@@ -22,6 +96,25 @@ struct ComponentInstance {
     templates: serde_json::Value,
     state: HashMap<String, serde_json::Value>,
     vnode_json: Option<String>,  // VNode as JSON string
+    // Time travel history isn't journaled
+    #[serde(skip)]
+    history: VecDeque<StateChangeRecord>,
+    #[serde(skip)]
+    next_history_index: u64,
+}
+
+/// One UpdateComponentState, as recorded for time travel
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StateChangeRecord {
+    /// Increases per component; stays valid after older records are evicted
+    index: u64,
+    correlation_id: String,
+    timestamp_ms: u128,
+    state_key: String,
+    before: HashMap<String, serde_json::Value>,
+    after: HashMap<String, serde_json::Value>,
+    patches: Vec<serde_json::Value>,
 }
 
 impl ComponentInstance {
@@ -32,8 +125,34 @@ impl ComponentInstance {
             templates,
             state: initial_state,
             vnode_json: None,
+            history: VecDeque::new(),
+            next_history_index: 0,
         }
     }
+
+    fn record_state_change(
+        &mut self,
+        correlation_id: &str,
+        state_key: &str,
+        before: HashMap<String, serde_json::Value>,
+        patches: &[serde_json::Value]
+    ) {
+        if self.history.len() == MAX_STATE_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(StateChangeRecord {
+            index: self.next_history_index,
+            correlation_id: correlation_id.to_string(),
+            timestamp_ms: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis()),
+            state_key: state_key.to_string(),
+            before,
+            after: self.state.clone(),
+            patches: patches.to_vec(),
+        });
+        self.next_history_index += 1;
+    }
 }
 
 // ========================================
@@ -54,34 +173,57 @@ struct SignalMMessage {
 #[tauri::command]
 pub async fn signalm_invoke(
     app: AppHandle,
+    window: WebviewWindow,
     method: String,
     args: Vec<serde_json::Value>
 ) -> Result<serde_json::Value, String> {
-    println!("[SignalM²] Received: {} with {} args", method, args.len());
+    let window_label = window.label().to_string();
+    println!("[SignalM²] Received: {} with {} args from '{}'", method, args.len(), window_label);
 
     // Route to appropriate handler based on method name
     match method.as_str() {
         // ========================================
         // Component Initialization
         // ========================================
-        "Initialize" => handle_initialize(app, args).await,
+        "Initialize" => handle_initialize(app, &window_label, args).await,
 
         // ========================================
         // State Management
         // ========================================
-        "UpdateComponentState" => handle_update_component_state(app, args).await,
-        "UpdateDomElementState" => handle_update_dom_element_state(app, args).await,
+        "UpdateComponentState" => handle_update_component_state(app, &window_label, args).await,
+        "UpdateDomElementState" => handle_update_dom_element_state(app, &window_label, args).await,
 
         // ========================================
         // Event Handling
         // ========================================
-        "TriggerEvent" => handle_trigger_event(app, args).await,
+        "TriggerEvent" => handle_trigger_event(app, &window_label, args).await,
+
+        // ========================================
+        // Error Recovery
+        // ========================================
+        "Resync" => handle_resync(app, &window_label, args).await,
+        "ReportChecksum" => handle_report_checksum(app, &window_label, args).await,
+        "RecoverSession" => handle_recover_session(app, &window_label),
+
+        // ========================================
+        // Time Travel
+        // ========================================
+        "GetStateHistory" => handle_get_state_history(&window_label, args),
+        "TimeTravel" => handle_time_travel(app, &window_label, args).await,
+
+        // ========================================
+        // Devtools
+        // ========================================
+        "SetDevtools" => handle_set_devtools(args),
+        "SetOptimistic" => handle_set_optimistic(args),
+        "ApplyPatchesAck" => handle_apply_patches_ack(args),
+        "GetTimings" => handle_get_timings(args),
 
         // ========================================
         // Component Registration
         // ========================================
-        "RegisterComponent" => handle_register_component(args).await,
-        "InvokeComponentMethod" => handle_invoke_component_method(app, args).await,
+        "RegisterComponent" => handle_register_component(&window_label, args).await,
+        "InvokeComponentMethod" => handle_invoke_component_method(app, &window_label, args).await,
 
         // ========================================
         // Unknown Method
@@ -99,6 +241,7 @@ pub async fn signalm_invoke(
 
 async fn handle_initialize(
     app: AppHandle,
+    window_label: &str,
     args: Vec<serde_json::Value>
 ) -> Result<serde_json::Value, String> {
     let csharp = args.get(0)
@@ -125,6 +268,7 @@ async fn handle_initialize(
         csharp: csharp.to_string(),
         templates: templates.clone(),
         initial_state,
+        stream_id: Some(component_id.clone()),
     };
 
     let response = execute_component(app.clone(), request).await?;
@@ -142,10 +286,17 @@ async fn handle_initialize(
     );
     component.vnode_json = response.vnode_json.clone();
 
+    if let Ok(snapshot) = serde_json::to_value(&component) {
+        if let Err(e) = journal::append(&app, window_label, &JournalEntry::Snapshot { component: snapshot }) {
+            eprintln!("[SignalM²] Failed to journal {}: {}", component_id, e);
+        }
+    }
+
     {
-        let mut registry = COMPONENT_REGISTRY.lock().unwrap();
+        let mut registries = COMPONENT_REGISTRY.lock().unwrap();
+        let registry = registries.entry(window_label.to_string()).or_default();
         registry.insert(component_id.clone(), component);
-        println!("[SignalM²] ✅ Component registered: {} (registry size: {})", component_id, registry.len());
+        println!("[SignalM²] ✅ Component registered: {} in '{}' (registry size: {})", component_id, window_label, registry.len());
     }
 
     Ok(serde_json::json!({
@@ -162,6 +313,7 @@ async fn handle_initialize(
 
 async fn handle_update_component_state(
     app: AppHandle,
+    window_label: &str,
     args: Vec<serde_json::Value>
 ) -> Result<serde_json::Value, String> {
     let component_id = args.get(0)
@@ -176,75 +328,174 @@ async fn handle_update_component_state(
         .ok_or("Missing value")?
         .clone();
 
-    println!("[SignalM²] UpdateComponentState: {} {} = {:?}", component_id, state_key, value);
+    // Correlation id ties render → reconcile → apply ack together (generated if the client didn't send one)
+    let correlation_id = args.get(3)
+        .and_then(|v| v.as_str())
+        .map(String::from)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    println!("[SignalM²] UpdateComponentState: {} {} = {:?} ({})", component_id, state_key, value, correlation_id);
 
     // 1. Get component from registry
-    let (old_vnode, csharp, templates, new_state) = {
-        let mut registry = COMPONENT_REGISTRY.lock().unwrap();
-        let component = registry.get_mut(component_id)
-            .ok_or_else(|| format!("Component not found: {}", component_id))?;
+    let (old_vnode, csharp, templates, old_state, new_state) = {
+        let mut registries = COMPONENT_REGISTRY.lock().unwrap();
+        let component = find_component(&mut registries, window_label, component_id)?;
 
-        // 2. Update state
+        // 2. Update state (keeping the previous state for the history)
+        let old_state = component.state.clone();
         component.state.insert(state_key.to_string(), value.clone());
 
         // Get current VNode for diffing
         let old_vnode = component.vnode_json.clone();
 
-        (old_vnode, component.csharp.clone(), component.templates.clone(), component.state.clone())
+        (old_vnode, component.csharp.clone(), component.templates.clone(), old_state, component.state.clone())
     };
 
-    // 3. Re-execute component with new state
-    let state_json = serde_json::to_value(&new_state)
-        .map_err(|e| format!("Failed to serialize state: {}", e))?;
-
-    let request = ExecuteRequest {
+    let render = StateRender {
+        app,
+        window_label: window_label.to_string(),
+        component_id: component_id.to_string(),
+        correlation_id,
+        state_key: state_key.to_string(),
         csharp,
         templates,
-        initial_state: state_json,
+        old_state,
+        new_state,
     };
 
-    let response = execute_component(app.clone(), request).await?;
-
-    if !response.success {
-        return Err(response.error.unwrap_or_else(|| "Re-render failed".to_string()));
+    // Optimistic mode: paint template patches now, render in C# in the background
+    if OPTIMISTIC_UI.load(Ordering::Relaxed) {
+        if let Some((patches, optimistic_vnode)) = materialize_optimistic(&render, old_vnode.as_deref()) {
+            {
+                let mut registries = COMPONENT_REGISTRY.lock().unwrap();
+                if let Ok(component) = find_component(&mut registries, window_label, component_id) {
+                    component.vnode_json = Some(optimistic_vnode.clone());
+                }
+            }
+
+            emit_to_window(&render.app, window_label, SignalMMessage {
+                method: "ApplyPatches".to_string(),
+                args: vec![serde_json::json!({
+                    "componentId": component_id,
+                    "correlationId": render.correlation_id,
+                    "patches": patches,
+                    "optimistic": true
+                })]
+            })?;
+
+            println!("[SignalM²] ⚡ Emitted {} optimistic template patches", patches.len());
+
+            let patches_json: Vec<serde_json::Value> = patches.iter()
+                .filter_map(|patch| serde_json::to_value(patch).ok())
+                .collect();
+            journal_batch(&render.app, window_label, component_id, &render.correlation_id, None, &patches_json);
+
+            let correlation_id = render.correlation_id.clone();
+            let patch_count = patches.len();
+            tauri::async_runtime::spawn(async move {
+                // Only differences between the optimistic tree and the C# render are sent
+                if let Err(e) = render.run(Some(optimistic_vnode), true).await {
+                    eprintln!("[SignalM²] Background render of {} failed: {}", render.component_id, e);
+                }
+            });
+
+            return Ok(serde_json::json!({
+                "success": true,
+                "correlationId": correlation_id,
+                "patchCount": patch_count,
+                "optimistic": true
+            }));
+        }
     }
 
-    let new_vnode = response.vnode_json.clone();
+    // 3. Re-execute component with new state, diff and emit
+    let patch_count = render.run(old_vnode, false).await?;
+
+    Ok(serde_json::json!({
+        "success": true,
+        "correlationId": render.correlation_id,
+        "patchCount": patch_count
+    }))
+}
 
-    // 4. Generate patches (simple diff for now - TODO: use Rust reconciler)
-    let patches = generate_simple_patches(old_vnode, new_vnode.clone())?;
+/// Everything needed to re-render a component after a state change
+struct StateRender {
+    app: AppHandle,
+    window_label: String,
+    component_id: String,
+    correlation_id: String,
+    state_key: String,
+    csharp: String,
+    templates: serde_json::Value,
+    old_state: HashMap<String, serde_json::Value>,
+    new_state: HashMap<String, serde_json::Value>,
+}
 
-    // 5. Update stored VNode
-    {
-        let mut registry = COMPONENT_REGISTRY.lock().unwrap();
-        if let Some(component) = registry.get_mut(component_id) {
-            component.vnode_json = new_vnode;
+impl StateRender {
+    /// Execute the C# component, diff against `base_vnode` and emit the patches
+    /// `correction` marks patches that fix up an optimistic update
+    async fn run(&self, base_vnode: Option<String>, correction: bool) -> Result<usize, String> {
+        let state_json = serde_json::to_value(&self.new_state)
+            .map_err(|e| format!("Failed to serialize state: {}", e))?;
+
+        let request = ExecuteRequest {
+            csharp: self.csharp.clone(),
+            templates: self.templates.clone(),
+            initial_state: state_json,
+            stream_id: Some(self.correlation_id.clone()),
+        };
+
+        let render_start = std::time::Instant::now();
+        let response = execute_component(self.app.clone(), request).await?;
+        record_span(&self.correlation_id, TraceStage::Render, render_start.elapsed(), None);
+
+        if !response.success {
+            return Err(response.error.unwrap_or_else(|| "Re-render failed".to_string()));
         }
-    }
 
-    println!("[SignalM²] ✅ Generated {} patches", patches.len());
+        let new_vnode = response.vnode_json.clone();
 
-    // 6. Emit patches to client
-    if !patches.is_empty() {
-        app.emit("signalm-message", SignalMMessage {
-            method: "ApplyPatches".to_string(),
-            args: vec![serde_json::json!({
-                "componentId": component_id,
-                "patches": patches
-            })]
-        }).map_err(|e| e.to_string())?;
+        // 4. Generate patches (simple diff for now - TODO: use Rust reconciler)
+        let (patches, annotations) = generate_simple_patches(base_vnode, new_vnode.clone(), &self.correlation_id)?;
 
-        println!("[SignalM²] ✅ Emitted patches to client");
-    }
+        // 5. Update stored VNode and record the change for time travel
+        {
+            let mut registries = COMPONENT_REGISTRY.lock().unwrap();
+            if let Ok(component) = find_component(&mut registries, &self.window_label, &self.component_id) {
+                component.vnode_json = new_vnode;
+                component.record_state_change(&self.correlation_id, &self.state_key, self.old_state.clone(), &patches);
+            }
+        }
 
-    Ok(serde_json::json!({
-        "success": true,
-        "patchCount": patches.len()
-    }))
+        println!("[SignalM²] ✅ Generated {} patches{}", patches.len(), if correction { " (corrections)" } else { "" });
+
+        journal_batch(&self.app, &self.window_label, &self.component_id, &self.correlation_id, Some(self.new_state.clone()), &patches);
+
+        // 6. Emit patches to client
+        crate::devtools::record_patches(&self.app, &self.component_id, &self.correlation_id, &patches, annotations.as_ref());
+
+        if !patches.is_empty() {
+            emit_to_window(&self.app, &self.window_label, SignalMMessage {
+                method: "ApplyPatches".to_string(),
+                args: vec![serde_json::json!({
+                    "componentId": self.component_id,
+                    "correlationId": self.correlation_id,
+                    "patches": patches,
+                    "annotations": annotations,
+                    "correction": correction
+                })]
+            })?;
+
+            println!("[SignalM²] ✅ Emitted patches to client");
+        }
+
+        Ok(patches.len())
+    }
 }
 
 async fn handle_update_dom_element_state(
     app: AppHandle,
+    window_label: &str,
     args: Vec<serde_json::Value>
 ) -> Result<serde_json::Value, String> {
     let component_id = args.get(0)
@@ -259,13 +510,17 @@ async fn handle_update_dom_element_state(
         .ok_or("Missing snapshot")?
         .clone();
 
-    println!("[SignalM²] UpdateDomElementState: {} {} = {:?}", component_id, state_key, snapshot);
+    let correlation_id = args.get(3)
+        .and_then(|v| v.as_str())
+        .map(String::from)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    println!("[SignalM²] UpdateDomElementState: {} {} = {:?} ({})", component_id, state_key, snapshot, correlation_id);
 
     // Store DOM state same as regular state
     let (old_vnode, csharp, templates, new_state) = {
-        let mut registry = COMPONENT_REGISTRY.lock().unwrap();
-        let component = registry.get_mut(component_id)
-            .ok_or_else(|| format!("Component not found: {}", component_id))?;
+        let mut registries = COMPONENT_REGISTRY.lock().unwrap();
+        let component = find_component(&mut registries, window_label, component_id)?;
 
         // Update state with DOM snapshot
         component.state.insert(state_key.to_string(), snapshot.clone());
@@ -282,9 +537,12 @@ async fn handle_update_dom_element_state(
         csharp,
         templates,
         initial_state: state_json,
+        stream_id: Some(correlation_id.clone()),
     };
 
+    let render_start = std::time::Instant::now();
     let response = execute_component(app.clone(), request).await?;
+    record_span(&correlation_id, TraceStage::Render, render_start.elapsed(), None);
 
     if !response.success {
         return Err(response.error.unwrap_or_else(|| "Re-render failed".to_string()));
@@ -293,27 +551,33 @@ async fn handle_update_dom_element_state(
     let new_vnode = response.vnode_json.clone();
 
     // Generate and emit patches
-    let patches = generate_simple_patches(old_vnode, new_vnode.clone())?;
+    let (patches, annotations) = generate_simple_patches(old_vnode, new_vnode.clone(), &correlation_id)?;
 
     {
-        let mut registry = COMPONENT_REGISTRY.lock().unwrap();
-        if let Some(component) = registry.get_mut(component_id) {
+        let mut registries = COMPONENT_REGISTRY.lock().unwrap();
+        if let Ok(component) = find_component(&mut registries, window_label, component_id) {
             component.vnode_json = new_vnode;
         }
     }
 
+    journal_batch(&app, window_label, component_id, &correlation_id, Some(new_state), &patches);
+    crate::devtools::record_patches(&app, component_id, &correlation_id, &patches, annotations.as_ref());
+
     if !patches.is_empty() {
-        app.emit("signalm-message", SignalMMessage {
+        emit_to_window(&app, window_label, SignalMMessage {
             method: "ApplyPatches".to_string(),
             args: vec![serde_json::json!({
                 "componentId": component_id,
-                "patches": patches
+                "correlationId": correlation_id,
+                "patches": patches,
+                "annotations": annotations
             })]
-        }).map_err(|e| e.to_string())?;
+        })?;
     }
 
     Ok(serde_json::json!({
         "success": true,
+        "correlationId": correlation_id,
         "patchCount": patches.len()
     }))
 }
@@ -324,6 +588,7 @@ async fn handle_update_dom_element_state(
 
 async fn handle_trigger_event(
     app: AppHandle,
+    window_label: &str,
     args: Vec<serde_json::Value>
 ) -> Result<serde_json::Value, String> {
     let component_id = args.get(0)
@@ -348,14 +613,14 @@ async fn handle_trigger_event(
     // In a full implementation, this would call into the C# component's event handler
 
     // Emit a simple acknowledgment for now
-    app.emit("signalm-message", SignalMMessage {
+    emit_to_window(&app, window_label, SignalMMessage {
         method: "EventExecuted".to_string(),
         args: vec![serde_json::json!({
             "componentId": component_id,
             "eventName": event_name,
             "eventData": event_data
         })]
-    }).map_err(|e| e.to_string())?;
+    })?;
 
     Ok(serde_json::json!({
         "success": true,
@@ -363,11 +628,424 @@ async fn handle_trigger_event(
     }))
 }
 
+// ========================================
+// Error Recovery
+// ========================================
+
+/// Client failed to apply a patch at `path` - rebuild that subtree from the stored VNode
+async fn handle_resync(
+    app: AppHandle,
+    window_label: &str,
+    args: Vec<serde_json::Value>
+) -> Result<serde_json::Value, String> {
+    let component_id = args.get(0)
+        .and_then(|v| v.as_str())
+        .ok_or("Missing componentId")?;
+
+    // Empty path (or missing) rebuilds the whole component
+    let from_path = args.get(1)
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+
+    println!("[SignalM²] Resync: {} from '{}'", component_id, from_path);
+
+    let vnode_json = {
+        let mut registries = COMPONENT_REGISTRY.lock().unwrap();
+        let component = find_component(&mut registries, window_label, component_id)?;
+        component.vnode_json.clone()
+            .ok_or_else(|| format!("Component has not rendered yet: {}", component_id))?
+    };
+
+    let vnode: VNode = serde_json::from_str(&vnode_json)
+        .map_err(|e| format!("Failed to parse stored VNode JSON: {}", e))?;
+
+    let message = generate_resync_message(&vnode, &HexPath::from(from_path))
+        .map_err(|e| format!("Resync failed: {}", e))?;
+
+    emit_to_window(&app, window_label, SignalMMessage {
+        method: "Resync".to_string(),
+        args: vec![serde_json::json!({
+            "componentId": component_id,
+            "resync": message
+        })]
+    })?;
+
+    println!("[SignalM²] ✅ Emitted resync for subtree '{}'", message.root_path);
+
+    Ok(serde_json::json!({
+        "success": true,
+        "rootPath": message.root_path,
+        "patchCount": message.patches.len()
+    }))
+}
+
+async fn handle_report_checksum(
+    app: AppHandle,
+    window_label: &str,
+    args: Vec<serde_json::Value>
+) -> Result<serde_json::Value, String> {
+    let component_id = args.get(0)
+        .and_then(|v| v.as_str())
+        .ok_or("Missing componentId")?;
+
+    // Checksums travel as 16 hex digits - u64 doesn't fit a JS number
+    let checksum_hex = args.get(1)
+        .and_then(|v| v.as_str())
+        .ok_or("Missing checksum")?;
+    let client_checksum = u64::from_str_radix(checksum_hex, 16)
+        .map_err(|e| format!("Invalid checksum '{}': {}", checksum_hex, e))?;
+
+    let path = args.get(2)
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+
+    let vnode_json = {
+        let mut registries = COMPONENT_REGISTRY.lock().unwrap();
+        let component = find_component(&mut registries, window_label, component_id)?;
+        component.vnode_json.clone()
+            .ok_or_else(|| format!("Component has not rendered yet: {}", component_id))?
+    };
+
+    let vnode: VNode = serde_json::from_str(&vnode_json)
+        .map_err(|e| format!("Failed to parse stored VNode JSON: {}", e))?;
+
+    let resync = check_drift(&vnode, &HexPath::from(path), client_checksum)
+        .map_err(|e| format!("Drift check failed: {}", e))?;
+
+    let Some(message) = resync else {
+        return Ok(serde_json::json!({ "success": true, "drift": false }));
+    };
+
+    println!("[SignalM²] ⚠️ Drift detected in {} - resyncing '{}'", component_id, message.root_path);
+
+    emit_to_window(&app, window_label, SignalMMessage {
+        method: "Resync".to_string(),
+        args: vec![serde_json::json!({
+            "componentId": component_id,
+            "resync": message
+        })]
+    })?;
+
+    Ok(serde_json::json!({
+        "success": true,
+        "drift": true,
+        "rootPath": message.root_path
+    }))
+}
+
+/// Rebuild this window's registry from its journal after a crash, then resync the page
+/// Args: []
+fn handle_recover_session(
+    app: AppHandle,
+    window_label: &str
+) -> Result<serde_json::Value, String> {
+    let entries = journal::read(&app, window_label)?;
+    let mut recovered: HashMap<String, ComponentInstance> = HashMap::new();
+
+    for entry in entries {
+        match entry {
+            JournalEntry::Snapshot { component } => {
+                match serde_json::from_value::<ComponentInstance>(component) {
+                    Ok(component) => { recovered.insert(component.id.clone(), component); }
+                    Err(e) => eprintln!("[SignalM²] Skipping unreadable snapshot: {}", e),
+                }
+            }
+            JournalEntry::State { component_id, state } => {
+                if let Some(component) = recovered.get_mut(&component_id) {
+                    component.state = state;
+                }
+            }
+            JournalEntry::Patches { component_id, patches, .. } => {
+                if let Some(component) = recovered.get_mut(&component_id) {
+                    component.vnode_json = replay_patches(component.vnode_json.as_deref(), patches);
+                }
+            }
+        }
+    }
+
+    // Components whose tree couldn't be replayed are re-rendered by the page
+    recovered.retain(|id, component| {
+        let replayed = component.vnode_json.is_some();
+        if !replayed {
+            eprintln!("[SignalM²] Dropping {} - its journal couldn't be replayed", id);
+        }
+        replayed
+    });
+
+    let component_ids: Vec<String> = recovered.keys().cloned().collect();
+    println!("[SignalM²] Recovered {} components in '{}'", component_ids.len(), window_label);
+
+    let mut vnodes = serde_json::Map::new();
+    for component in recovered.values() {
+        if let Some(vnode_json) = &component.vnode_json {
+            vnodes.insert(component.id.clone(), serde_json::from_str(vnode_json).unwrap_or_default());
+        }
+    }
+
+    COMPONENT_REGISTRY.lock().unwrap().insert(window_label.to_string(), recovered);
+    compact_journal(&app, window_label);
+
+    // The page may still show pre-crash DOM - rebuild every recovered component
+    for (component_id, vnode) in &vnodes {
+        let Ok(vnode) = serde_json::from_value::<VNode>(vnode.clone()) else { continue };
+        let message = generate_resync_message(&vnode, &HexPath::root())
+            .map_err(|e| format!("Resync failed: {}", e))?;
+
+        emit_to_window(&app, window_label, SignalMMessage {
+            method: "Resync".to_string(),
+            args: vec![serde_json::json!({
+                "componentId": component_id,
+                "resync": message
+            })]
+        })?;
+    }
+
+    Ok(serde_json::json!({
+        "success": true,
+        "recovered": component_ids,
+        "vnodes": vnodes
+    }))
+}
+
+/// Apply a journaled patch batch to a stored tree; None when it can't be replayed
+fn replay_patches(vnode_json: Option<&str>, patches: Vec<serde_json::Value>) -> Option<String> {
+    // An initial render replaces the whole tree
+    if let Some(root) = patches.iter().rev().find(|patch| patch["type"] == "ReplaceRoot") {
+        return serde_json::to_string(&root["vnode"]).ok();
+    }
+
+    let mut tree: VNode = serde_json::from_str(vnode_json?).ok()?;
+    let patches: Vec<Patch> = patches.into_iter()
+        .map(serde_json::from_value)
+        .collect::<Result<_, _>>()
+        .ok()?;
+    apply_patches(&mut tree, &patches).ok()?;
+    tree_json(&tree).ok().map(|json| json.to_string())
+}
+
+// ========================================
+// Optimistic UI
+// ========================================
+
+/// Patch from registered templates before the C# render (off by default)
+static OPTIMISTIC_UI: AtomicBool = AtomicBool::new(false);
+
+/// Toggle optimistic template patches
+/// Args: [enabled]
+fn handle_set_optimistic(args: Vec<serde_json::Value>) -> Result<serde_json::Value, String> {
+    let enabled = args.get(0)
+        .and_then(|v| v.as_bool())
+        .ok_or("Missing enabled flag")?;
+
+    OPTIMISTIC_UI.store(enabled, Ordering::Relaxed);
+    println!("[SignalM²] Optimistic UI {}", if enabled { "enabled" } else { "disabled" });
+
+    Ok(serde_json::json!({
+        "success": true,
+        "optimistic": enabled
+    }))
+}
+
+/// Materialize the templates bound to the changed state key into concrete patches
+/// Returns the patches and the tree the client shows after applying them, or None when
+/// the change isn't covered by text/attribute templates (loops need the C# render)
+fn materialize_optimistic(render: &StateRender, old_vnode: Option<&str>) -> Option<(Vec<Patch>, String)> {
+    let mut tree: VNode = serde_json::from_str(old_vnode?).ok()?;
+
+    let templates = render.templates.get("templates")?.as_object()?;
+    let bound: Vec<TemplateInfo> = templates.values()
+        .filter_map(|template| serde_json::from_value::<TemplateInfo>(template.clone()).ok())
+        .filter(|template| template.bindings.contains(&render.state_key))
+        .collect();
+
+    if bound.is_empty() || bound.iter().any(|t| !t.is_text_template() && !t.is_attribute_template()) {
+        return None;
+    }
+
+    let patches: Vec<Patch> = bound.iter()
+        .map(|template| {
+            let content = render_template_patch(&template.to_template_patch(), &render.new_state);
+            match template.get_attribute_name() {
+                Some(attribute) => Patch::UpdateProps {
+                    path: template.path.clone(),
+                    props: HashMap::from([(attribute.to_string(), content)]),
+                },
+                None => Patch::UpdateText { path: template.path.clone(), content },
+            }
+        })
+        .collect();
+
+    // A template that no longer matches the tree means the render changed shape
+    apply_patches(&mut tree, &patches).ok()?;
+    let vnode_json = tree_json(&tree).ok()?.to_string();
+
+    Some((patches, vnode_json))
+}
+
+// ========================================
+// Time Travel
+// ========================================
+
+/// Recorded state changes of a component, oldest first
+/// Args: [componentId]
+fn handle_get_state_history(
+    window_label: &str,
+    args: Vec<serde_json::Value>
+) -> Result<serde_json::Value, String> {
+    let component_id = args.get(0)
+        .and_then(|v| v.as_str())
+        .ok_or("Missing componentId")?;
+
+    let mut registries = COMPONENT_REGISTRY.lock().unwrap();
+    let component = find_component(&mut registries, window_label, component_id)?;
+
+    Ok(serde_json::json!({
+        "success": true,
+        "componentId": component_id,
+        "history": component.history
+    }))
+}
+
+/// Re-render a component at the state after history entry `index` and patch the UI there
+/// The jump itself isn't recorded; the next state change continues from the restored state
+/// Args: [componentId, index]
+async fn handle_time_travel(
+    app: AppHandle,
+    window_label: &str,
+    args: Vec<serde_json::Value>
+) -> Result<serde_json::Value, String> {
+    let component_id = args.get(0)
+        .and_then(|v| v.as_str())
+        .ok_or("Missing componentId")?;
+
+    let index = args.get(1)
+        .and_then(|v| v.as_u64())
+        .ok_or("Missing history index")?;
+
+    let correlation_id = Uuid::new_v4().to_string();
+
+    println!("[SignalM²] TimeTravel: {} to #{} ({})", component_id, index, correlation_id);
+
+    let (old_vnode, csharp, templates, target_state) = {
+        let mut registries = COMPONENT_REGISTRY.lock().unwrap();
+        let component = find_component(&mut registries, window_label, component_id)?;
+        let record = component.history.iter()
+            .find(|record| record.index == index)
+            .ok_or_else(|| format!("No history entry #{} for {} (evicted or never recorded)", index, component_id))?;
+
+        (component.vnode_json.clone(), component.csharp.clone(), component.templates.clone(), record.after.clone())
+    };
+
+    let state_json = serde_json::to_value(&target_state)
+        .map_err(|e| format!("Failed to serialize state: {}", e))?;
+
+    let request = ExecuteRequest {
+        csharp,
+        templates,
+        initial_state: state_json,
+        stream_id: Some(correlation_id.clone()),
+    };
+
+    let render_start = std::time::Instant::now();
+    let response = execute_component(app.clone(), request).await?;
+    record_span(&correlation_id, TraceStage::Render, render_start.elapsed(), None);
+
+    if !response.success {
+        return Err(response.error.unwrap_or_else(|| "Time travel render failed".to_string()));
+    }
+
+    let new_vnode = response.vnode_json.clone();
+    let (patches, annotations) = generate_simple_patches(old_vnode, new_vnode.clone(), &correlation_id)?;
+
+    {
+        let mut registries = COMPONENT_REGISTRY.lock().unwrap();
+        let component = find_component(&mut registries, window_label, component_id)?;
+        component.state = target_state.clone();
+        component.vnode_json = new_vnode;
+    }
+
+    journal_batch(&app, window_label, component_id, &correlation_id, Some(target_state), &patches);
+
+    crate::devtools::record_patches(&app, component_id, &correlation_id, &patches, annotations.as_ref());
+
+    if !patches.is_empty() {
+        emit_to_window(&app, window_label, SignalMMessage {
+            method: "ApplyPatches".to_string(),
+            args: vec![serde_json::json!({
+                "componentId": component_id,
+                "correlationId": correlation_id,
+                "patches": patches,
+                "annotations": annotations,
+                "timeTravel": index
+            })]
+        })?;
+    }
+
+    println!("[SignalM²] ✅ Travelled to #{} with {} patches", index, patches.len());
+
+    Ok(serde_json::json!({
+        "success": true,
+        "correlationId": correlation_id,
+        "index": index,
+        "patchCount": patches.len()
+    }))
+}
+
+// ========================================
+// Devtools
+// ========================================
+
+/// Toggle patch annotations for the inspector
+/// Args: [enabled]
+fn handle_set_devtools(args: Vec<serde_json::Value>) -> Result<serde_json::Value, String> {
+    let enabled = args.get(0)
+        .and_then(|v| v.as_bool())
+        .ok_or("Missing enabled flag")?;
+
+    set_devtools_enabled(enabled);
+    println!("[SignalM²] Devtools annotations {}", if enabled { "enabled" } else { "disabled" });
+
+    Ok(serde_json::json!({
+        "success": true,
+        "devtools": enabled
+    }))
+}
+
+/// Client finished applying a patch batch
+/// Args: [correlationId, applyDurationMs]
+fn handle_apply_patches_ack(args: Vec<serde_json::Value>) -> Result<serde_json::Value, String> {
+    let correlation_id = args.get(0)
+        .and_then(|v| v.as_str())
+        .ok_or("Missing correlationId")?;
+
+    let duration_ms = args.get(1)
+        .and_then(|v| v.as_f64())
+        .unwrap_or(0.0);
+
+    record_span(correlation_id, TraceStage::ApplyAck, std::time::Duration::from_secs_f64(duration_ms.max(0.0) / 1000.0), None);
+
+    Ok(serde_json::json!({ "success": true }))
+}
+
+/// Timing breakdown for one request
+/// Args: [correlationId]
+fn handle_get_timings(args: Vec<serde_json::Value>) -> Result<serde_json::Value, String> {
+    let correlation_id = args.get(0)
+        .and_then(|v| v.as_str())
+        .ok_or("Missing correlationId")?;
+
+    let breakdown = timing_breakdown(correlation_id)
+        .ok_or_else(|| format!("No timings for correlation id: {}", correlation_id))?;
+
+    serde_json::to_value(breakdown).map_err(|e| e.to_string())
+}
+
 // ========================================
 // Component Registration
 // ========================================
 
 async fn handle_register_component(
+    window_label: &str,
     args: Vec<serde_json::Value>
 ) -> Result<serde_json::Value, String> {
     let component_id = args.get(0)
@@ -378,8 +1056,8 @@ async fn handle_register_component(
 
     // Check if component exists in registry
     let exists = {
-        let registry = COMPONENT_REGISTRY.lock().unwrap();
-        registry.contains_key(component_id)
+        let registries = COMPONENT_REGISTRY.lock().unwrap();
+        registries.get(window_label).is_some_and(|registry| registry.contains_key(component_id))
     };
 
     Ok(serde_json::json!({
@@ -390,6 +1068,7 @@ async fn handle_register_component(
 
 async fn handle_invoke_component_method(
     app: AppHandle,
+    window_label: &str,
     args: Vec<serde_json::Value>
 ) -> Result<serde_json::Value, String> {
     let component_id = args.get(0)
@@ -402,7 +1081,7 @@ async fn handle_invoke_component_method(
 
     let method_args = args.get(2).cloned().unwrap_or(serde_json::json!([]));
 
-    println!("[SignalM²] InvokeComponentMethod: {} {} {:?}", component_id, method_name, method_args);
+    println!("[SignalM²] InvokeComponentMethod: {} {} {:?} (window {})", component_id, method_name, method_args, window_label);
 
     // In full implementation, this would:
     // 1. Get component from registry
@@ -423,22 +1102,24 @@ async fn handle_invoke_component_method(
 
 /// Generate surgical patches using the Minimact Rust reconciler
 /// This generates minimal, surgical DOM updates instead of replacing the whole tree
+/// Also returns patch annotations for the inspector while devtools are enabled
 fn generate_simple_patches(
     old_vnode_json: Option<String>,
-    new_vnode_json: Option<String>
-) -> Result<Vec<serde_json::Value>, String> {
+    new_vnode_json: Option<String>,
+    correlation_id: &str
+) -> Result<(Vec<serde_json::Value>, Option<serde_json::Value>), String> {
     // If old doesn't exist, it's initial render - return full tree replacement
     if old_vnode_json.is_none() {
         if let Some(new_json) = new_vnode_json {
             let vnode_value: serde_json::Value = serde_json::from_str(&new_json)
                 .map_err(|e| format!("Failed to parse new VNode JSON: {}", e))?;
 
-            return Ok(vec![serde_json::json!({
+            return Ok((vec![serde_json::json!({
                 "type": "ReplaceRoot",
                 "vnode": vnode_value
-            })]);
+            })], None));
         }
-        return Ok(vec![]);
+        return Ok((vec![], None));
     }
 
     // If both exist, use the REAL Rust reconciler for surgical patches!
@@ -453,7 +1134,7 @@ fn generate_simple_patches(
         println!("[SignalM²] 🔧 Running Rust reconciler...");
 
         // Call the REAL reconciler! This generates surgical patches
-        let rust_patches: Vec<Patch> = reconcile(&old_vnode, &new_vnode)
+        let rust_patches: Vec<Patch> = reconcile_traced(&old_vnode, &new_vnode, &ReconcileStrategy::default(), correlation_id)
             .map_err(|e| format!("Reconciliation failed: {}", e))?;
 
         println!("[SignalM²] ✅ Reconciler generated {} surgical patches", rust_patches.len());
@@ -464,28 +1145,53 @@ fn generate_simple_patches(
             .map(|patch| serde_json::to_value(patch).unwrap())
             .collect();
 
-        return Ok(patches_json);
+        let annotations = devtools_enabled()
+            .then(|| serde_json::to_value(annotate_patches(&rust_patches, &old_vnode)).unwrap());
+
+        return Ok((patches_json, annotations));
     }
 
-    Ok(vec![])
+    Ok((vec![], None))
 }
 
 // ========================================
 // Utility Functions
 // ========================================
 
-/// Get component count (for debugging)
+/// Get component count across all windows (for debugging)
 #[tauri::command]
 pub fn get_component_count() -> usize {
-    let registry = COMPONENT_REGISTRY.lock().unwrap();
-    registry.len()
+    let registries = COMPONENT_REGISTRY.lock().unwrap();
+    registries.values().map(HashMap::len).sum()
+}
+
+/// Component ids registered by each window
+#[tauri::command]
+pub fn list_window_registries() -> HashMap<String, Vec<String>> {
+    let registries = COMPONENT_REGISTRY.lock().unwrap();
+    registries.iter()
+        .map(|(label, registry)| (label.clone(), registry.keys().cloned().collect()))
+        .collect()
+}
+
+/// Drop a window's components; returns how many were removed
+#[tauri::command]
+pub fn clear_window_registry(window_label: String) -> usize {
+    let removed = COMPONENT_REGISTRY.lock().unwrap().remove(&window_label);
+    let count = removed.as_ref().map_or(0, HashMap::len);
+    for component_id in removed.iter().flat_map(HashMap::keys) {
+        crate::devtools::forget_component(component_id);
+    }
+    println!("[SignalM²] Cleared {} components of window '{}'", count, window_label);
+    count
 }
 
-/// Clear all components (for testing)
+/// Clear all components in every window (for testing)
 #[tauri::command]
 pub fn clear_components() -> Result<String, String> {
-    let mut registry = COMPONENT_REGISTRY.lock().unwrap();
-    let count = registry.len();
-    registry.clear();
+    let mut registries = COMPONENT_REGISTRY.lock().unwrap();
+    let count = registries.values().map(HashMap::len).sum::<usize>();
+    registries.clear();
+    crate::devtools::clear_component_stats();
     Ok(format!("Cleared {} components", count))
 }
@@ -4,6 +4,8 @@ import { TauriTransport } from './core/signalm/TauriTransport';
 import { Router } from './core/router';
 import { LinkInterceptor } from './core/link-interceptor';
 import { parseGhUrl, buildGhUrl } from './core/gh-protocol';
+import { ensureComponentPermissions } from './core/sandbox';
+import { listenRuntimeStream } from './core/execution-engine';
 import './App.css';
 
 export default function App() {
@@ -48,6 +50,13 @@ export default function App() {
         setStatus('✅ Connected to local runtime');
         setConnected(true);
         transportRef.current = transport;
+
+        // Rebuild components journaled before a crash (no-op after a clean exit)
+        return transport.send('RecoverSession').then((result: any) => {
+          if (result?.recovered?.length) {
+            console.log('[App] ♻️ Recovered components:', result.recovered);
+          }
+        }).catch((err) => console.warn('[App] Session recovery failed:', err));
       })
       .catch((err) => {
         console.error('[App] ❌ Failed to connect:', err);
@@ -161,16 +170,40 @@ export default function App() {
       setStatus('✅ Loaded from GitHub');
       setCompiledFiles(Array.from(result.files.keys()));
 
+      // The component runs sandboxed; ask for anything it needs beyond that
+      if (!(await ensureComponentPermissions(result.compiled.csharp, loadedPath))) {
+        setError('Permissions denied');
+        setStatus('❌ Component not run: permissions denied');
+        setLoading(false);
+        return;
+      }
+
       // Phase 5: Initialize component via SignalM²
       setStatus('⚙️ Initializing component via SignalM²...');
 
+      // Paint top-level chunks as the runtime streams them, before the full response
+      const chunks: string[] = [];
+      const stopStreaming = await listenRuntimeStream(message => {
+        if (message.type === 'progress') {
+          setStatus(`⚙️ Runtime: ${message.stage}...`);
+        } else if (message.type === 'chunk' && message.html) {
+          chunks[message.index] = message.html;
+          setHtml(chunks.join(''));
+        }
+      });
+
       console.log('[App] Calling SignalM² Initialize...');
-      const initResult = await transportRef.current.send(
-        'Initialize',
-        result.compiled.csharp,
-        result.compiled.templates,
-        {}
-      );
+      let initResult;
+      try {
+        initResult = await transportRef.current.send(
+          'Initialize',
+          result.compiled.csharp,
+          result.compiled.templates,
+          {}
+        );
+      } finally {
+        stopStreaming();
+      }
 
       console.log('[App] Initialize result:', initResult);
 
@@ -1,9 +1,32 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type Event as TauriEvent } from '@tauri-apps/api/event';
 
 export interface ExecuteRequest {
   csharp: string;
   templates: any;
   initial_state: any;
+  /** Tags this execution's runtime-stream events */
+  stream_id?: string;
+}
+
+/** A streamed runtime message (see the protocol in src-tauri/src/runtime.rs) */
+export type RuntimeMessage =
+  | { type: 'progress'; stage: string; message?: string | null }
+  | { type: 'log'; level: string; message: string }
+  | { type: 'chunk'; index: number; total: number; vnode: any; html?: string | null };
+
+/**
+ * Follow runtime executions as they progress
+ *
+ * @param handler - Called with each message and the stream id it belongs to
+ * @returns Function that stops listening
+ */
+export async function listenRuntimeStream(
+  handler: (message: RuntimeMessage, streamId: string) => void
+): Promise<() => void> {
+  return listen('runtime-stream', (event: TauriEvent<{ streamId: string; message: RuntimeMessage }>) => {
+    handler(event.payload.message, event.payload.streamId);
+  });
 }
 
 export interface ExecuteResponse {
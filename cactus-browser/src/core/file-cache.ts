@@ -14,6 +14,14 @@ export interface CachedFile {
   url: string;
 }
 
+export interface RemoteFile {
+  content: string;
+  sha: string;
+  etag: string | null;
+  freshness: 'new' | 'modified' | 'not-modified' | 'stale';
+  validatedAt: number;
+}
+
 export class FileCache {
   /**
    * Get a file from cache
@@ -116,6 +124,23 @@ export class FileCache {
     return cached.sha === sha;
   }
 
+  /**
+   * Fetch a remote file through the cache, revalidating with a conditional GET
+   * (If-None-Match against the cached ETag/sha). A 304 reuses the cached copy;
+   * when the server is unreachable the cached copy is served as "stale".
+   *
+   * @param url - http(s) URL to fetch
+   * @param headers - Extra request headers (e.g. Authorization)
+   * @returns File content and freshness metadata
+   */
+  async fetchRemote(url: string, headers?: Record<string, string>): Promise<RemoteFile> {
+    return invoke<RemoteFile>('fetch_remote_file', {
+      url,
+      key: this.hashUrl(url),
+      headers: headers ?? null
+    });
+  }
+
   /**
    * Clear all cache
    */
@@ -0,0 +1,56 @@
+/**
+ * Component permissions
+ *
+ * Components run in a sandboxed runtime process. Before running one, the backend
+ * scans its C# for the capabilities it uses; anything not yet granted is shown to
+ * the user here, and the component only runs if they allow it.
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+
+export interface PermissionManifest {
+  network: boolean;
+  fileSystem: boolean;
+  processes: boolean;
+  environment: boolean;
+}
+
+interface PermissionReport {
+  componentHash: string;
+  required: PermissionManifest;
+  granted: PermissionManifest;
+  missing: string[];
+}
+
+const DESCRIPTIONS: Record<string, string> = {
+  network: 'access the network',
+  fileSystem: 'read and write files',
+  processes: 'start other programs',
+  environment: 'read environment variables'
+};
+
+/**
+ * Make sure the user granted everything a component needs
+ *
+ * @param csharp - Generated C# for the component
+ * @param source - Where the component came from (shown to the user)
+ * @returns True if the component may run
+ */
+export async function ensureComponentPermissions(csharp: string, source: string): Promise<boolean> {
+  const report = await invoke<PermissionReport>('inspect_component_permissions', { csharp });
+  if (report.missing.length === 0) {
+    return true;
+  }
+
+  const wanted = report.missing.map(name => `  • ${DESCRIPTIONS[name] ?? name}`).join('\n');
+  const allowed = window.confirm(`${source} wants to:\n${wanted}\n\nAllow?`);
+  if (!allowed) {
+    return false;
+  }
+
+  await invoke('grant_component_permissions', {
+    componentHash: report.componentHash,
+    permissions: report.required
+  });
+  return true;
+}
@@ -7,7 +7,8 @@
  */
 
 import { invoke } from '@tauri-apps/api/core';
-import { listen, type Event as TauriEvent } from '@tauri-apps/api/event';
+import { type Event as TauriEvent } from '@tauri-apps/api/event';
+import { getCurrentWebviewWindow } from '@tauri-apps/api/webviewWindow';
 
 // Import interface from @minimact/core
 // This creates a peer dependency on client-runtime
@@ -41,7 +42,9 @@ export class TauriTransport implements ISignalMTransport {
     }
 
     // Listen for messages from Native AOT runtime
-    const unlisten = await listen('signalm-message', (event: TauriEvent<any>) => {
+    // Patches are emitted to the originating window only, so listen on this window
+    // (a global listen would also receive other windows' patches)
+    const unlisten = await getCurrentWebviewWindow().listen('signalm-message', (event: TauriEvent<any>) => {
       const { method, args } = event.payload;
       this.handleMessage(method, args);
     });
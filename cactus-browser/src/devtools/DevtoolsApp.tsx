@@ -0,0 +1,134 @@
+/**
+ * Devtools window: patch inspector + predictor dashboard
+ *
+ * Opened with the `open_devtools_window` command. Loads a full snapshot once, then
+ * appends live `devtools-event`s (one per patch batch) while subscribed.
+ */
+
+import { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type Event as TauriEvent } from '@tauri-apps/api/event';
+
+interface PatchBatch {
+  seq: number;
+  componentId: string;
+  correlationId: string;
+  emittedAt: number;
+  patches: any[];
+  annotations?: { description: string }[] | null;
+  timings?: { spans: { stage: string; duration_us: number }[] } | null;
+}
+
+interface DevtoolsSnapshot {
+  metrics: Record<string, any>;
+  history: { timestamps: number[]; hit_rate: number[]; reconcile_rate: number[] };
+  components: Record<string, { renders: number; patches: number; lastCorrelationId?: string }>;
+  patchLog: PatchBatch[];
+}
+
+const MAX_LOG = 500;
+
+export default function DevtoolsApp() {
+  const [snapshot, setSnapshot] = useState<DevtoolsSnapshot | null>(null);
+  const [log, setLog] = useState<PatchBatch[]>([]);
+  const [selected, setSelected] = useState<PatchBatch | null>(null);
+
+  useEffect(() => {
+    let unlisten: (() => void) | undefined;
+
+    (async () => {
+      await invoke('subscribe_devtools_events', { enabled: true });
+      const initial = await invoke<DevtoolsSnapshot>('get_devtools_snapshot', { sinceSeq: null });
+      setSnapshot(initial);
+      setLog(initial.patchLog);
+
+      unlisten = await listen('devtools-event', (event: TauriEvent<PatchBatch>) => {
+        setLog(prev => [...prev, event.payload].slice(-MAX_LOG));
+      });
+    })().catch(error => console.error('[Devtools] Failed to connect:', error));
+
+    // Counters and history don't stream - refresh them every second
+    const timer = setInterval(async () => {
+      const latest = await invoke<DevtoolsSnapshot>('get_devtools_snapshot', { sinceSeq: Number.MAX_SAFE_INTEGER });
+      setSnapshot(latest);
+    }, 1000);
+
+    return () => {
+      clearInterval(timer);
+      unlisten?.();
+      invoke('subscribe_devtools_events', { enabled: false });
+    };
+  }, []);
+
+  const metrics = snapshot?.metrics ?? {};
+  const hitRates = snapshot?.history.hit_rate ?? [];
+
+  return (
+    <div className="app" style={{ padding: '1rem', fontFamily: 'monospace', fontSize: '0.85rem' }}>
+      <h1>🌵 Cactus Devtools</h1>
+
+      <section>
+        <h2>Predictor</h2>
+        <p>
+          Predictions: {metrics.predictor_predictions ?? 0} · Hit rate: {formatRate(metrics.prediction_hit_rate)} ·
+          Last {hitRates.length}s avg: {formatRate(average(hitRates))}
+        </p>
+        <p>Reconciles: {metrics.reconcile_calls ?? 0} · Errors: {metrics.reconcile_errors ?? 0}</p>
+      </section>
+
+      <section>
+        <h2>Components</h2>
+        <table>
+          <thead><tr><th>Component</th><th>Renders</th><th>Patches</th><th>Last request</th></tr></thead>
+          <tbody>
+            {Object.entries(snapshot?.components ?? {}).map(([id, stats]) => (
+              <tr key={id}>
+                <td>{id}</td><td>{stats.renders}</td><td>{stats.patches}</td><td>{stats.lastCorrelationId ?? '-'}</td>
+              </tr>
+            ))}
+          </tbody>
+        </table>
+      </section>
+
+      <section style={{ display: 'flex', gap: '1rem' }}>
+        <div style={{ flex: 1, maxHeight: '50vh', overflowY: 'auto' }}>
+          <h2>Patch log ({log.length})</h2>
+          {[...log].reverse().map(batch => (
+            <div
+              key={batch.seq}
+              onClick={() => setSelected(batch)}
+              style={{ cursor: 'pointer', background: selected?.seq === batch.seq ? '#1a472a' : undefined }}
+            >
+              #{batch.seq} {new Date(batch.emittedAt).toLocaleTimeString()} {batch.componentId} – {batch.patches.length} patches
+            </div>
+          ))}
+        </div>
+
+        <div style={{ flex: 1, maxHeight: '50vh', overflowY: 'auto' }}>
+          <h2>Inspector</h2>
+          {selected ? (
+            <>
+              <p>Request {selected.correlationId}</p>
+              {selected.timings?.spans.map(span => (
+                <div key={span.stage}>{span.stage}: {(span.duration_us / 1000).toFixed(2)}ms</div>
+              ))}
+              <ul>
+                {selected.patches.map((patch, i) => (
+                  <li key={i}>{selected.annotations?.[i]?.description ?? `${patch.type} at ${patch.path ?? ''}`}</li>
+                ))}
+              </ul>
+            </>
+          ) : <p>Select a patch batch</p>}
+        </div>
+      </section>
+    </div>
+  );
+}
+
+function average(values: number[]): number | undefined {
+  return values.length ? values.reduce((a, b) => a + b, 0) / values.length : undefined;
+}
+
+function formatRate(rate: number | undefined): string {
+  return rate === undefined ? '-' : `${(rate * 100).toFixed(1)}%`;
+}
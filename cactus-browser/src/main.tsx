@@ -1,10 +1,11 @@
 import React from 'react';
 import ReactDOM from 'react-dom/client';
 import App from './App-phase2'; // Using Phase 2 App with GitHub loader
+import DevtoolsApp from './devtools/DevtoolsApp';
 import './styles/app.css';
 
 ReactDOM.createRoot(document.getElementById('root')!).render(
   <React.StrictMode>
-    <App />
+    {window.location.hash === '#devtools' ? <DevtoolsApp /> : <App />}
   </React.StrictMode>,
 );
@@ -0,0 +1,100 @@
+//! Allocation counts per reconcile (run with `cargo bench --bench allocations`)
+//!
+//! Not a timing benchmark: a counting global allocator reports how many heap
+//! allocations one reconcile call makes for typical diffs.
+//!
+//! Before/after moving reconcile scratch tables into the per-call arena (src/arena.rs):
+//!
+//! | case                 | before | after |
+//! |----------------------|--------|-------|
+//! | unkeyed, 8 items     |      5 |     5 |
+//! | keyed, 8 items       |     36 |    16 |
+//! | unkeyed, 64 items    |     17 |     5 |
+//! | keyed, 64 items      |    160 |    75 |
+//! | unkeyed, 512 items   |     23 |     5 |
+//! | keyed, 512 items     |   1068 |   528 |
+//!
+//! What remains for keyed lists is mostly the ReorderChildren patch's key list,
+//! which is output and has to be owned.
+
+use minimact::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// A <ul> with `len` <li> children, optionally keyed, with transpiler-style hex paths
+/// (spaced 0x100 apart: `HexPath::child` only has room for 15 siblings)
+fn list(len: usize, keyed: bool, changed: Option<usize>) -> VNode {
+    let root = HexPath::from("10000000");
+    let children = (0..len)
+        .map(|i| {
+            let li_path = HexPath::from(format!("{}.{:08x}", root, (i + 1) * 0x100));
+            let text = if Some(i) == changed { format!("Item {} (edited)", i) } else { format!("Item {}", i) };
+            Some(VNode::Element(VElement {
+                tag: "li".to_string(),
+                props: HashMap::new(),
+                children: vec![Some(VNode::Text(VText {
+                    content: text,
+                    path: li_path.child(0),
+                }))],
+                key: keyed.then(|| format!("k{}", i)),
+                path: li_path,
+                source: None,
+            }))
+        })
+        .collect();
+
+    VNode::Element(VElement {
+        tag: "ul".to_string(),
+        props: HashMap::new(),
+        children,
+        key: None,
+        path: root,
+        source: None,
+    })
+}
+
+/// Average allocations per reconcile over `runs` calls (after one warm-up call)
+fn allocations_per_reconcile(old: &VNode, new: &VNode, runs: usize) -> f64 {
+    reconcile(old, new).unwrap();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..runs {
+        std::hint::black_box(reconcile(std::hint::black_box(old), std::hint::black_box(new)).unwrap());
+    }
+    (ALLOCATIONS.load(Ordering::Relaxed) - before) as f64 / runs as f64
+}
+
+fn main() {
+    println!("{:<34} {:>14}", "case", "allocs/call");
+    for len in [8usize, 64, 512] {
+        for keyed in [false, true] {
+            let old = list(len, keyed, None);
+            let new = list(len, keyed, Some(len / 2));
+            let name = format!("{} {} items, one text change", if keyed { "keyed" } else { "unkeyed" }, len);
+            println!("{:<34} {:>14.1}", name, allocations_per_reconcile(&old, &new, 100));
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Predict throughput while another thread keeps learning
+//!
+//! Compares the old hosting model (one Mutex<Predictor> per component, predicts
+//! wait for learns) with ConcurrentPredictor (lock-free predicts against the
+//! last published version).
+//!
+//! Readers and the writer only overlap on a multi-core machine; on one core the
+//! numbers mostly measure scheduling.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use minimact::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const READERS: usize = 4;
+
+type PredictFn = Arc<dyn Fn(&StateChange, &VNode) -> bool + Send + Sync>;
+type LearnFn = Arc<dyn Fn(StateChange, &VNode, &VNode) + Send + Sync>;
+
+fn counter(count: i64) -> VNode {
+    let root = HexPath::from("10000000");
+    let rows = (0..32)
+        .map(|i| {
+            Some(VNode::Text(VText {
+                content: if i == 0 { format!("Count: {}", count) } else { format!("Row {}", i) },
+                path: HexPath::from(format!("{}.{:08x}", root, (i + 1) * 0x100)),
+            }))
+        })
+        .collect();
+    VNode::Element(VElement {
+        tag: "div".to_string(),
+        props: HashMap::new(),
+        children: rows,
+        key: None,
+        path: root,
+        source: None,
+    })
+}
+
+fn change(old: i64, new: i64) -> StateChange {
+    StateChange {
+        component_id: "counter".to_string(),
+        state_key: "count".to_string(),
+        old_value: serde_json::json!(old),
+        new_value: serde_json::json!(new),
+        array_operation: None,
+        locale: None,
+    }
+}
+
+/// Run `READERS` threads doing `iters` predicts each while one thread learns,
+/// returning the time until the last reader is done
+fn contended(iters: u64, predict: PredictFn, learn: LearnFn) -> Duration {
+    let stop = Arc::new(AtomicBool::new(false));
+    let writer = {
+        let stop = Arc::clone(&stop);
+        std::thread::spawn(move || {
+            let mut i = 0;
+            while !stop.load(Ordering::Relaxed) {
+                learn(change(i, i + 1), &counter(i), &counter(i + 1));
+                i += 1;
+            }
+        })
+    };
+
+    let tree = counter(7);
+    let start = Instant::now();
+    std::thread::scope(|scope| {
+        for _ in 0..READERS {
+            let predict = Arc::clone(&predict);
+            let tree = &tree;
+            scope.spawn(move || {
+                for _ in 0..iters {
+                    black_box(predict(&change(7, 8), tree));
+                }
+            });
+        }
+    });
+    let elapsed = start.elapsed();
+
+    stop.store(true, Ordering::Relaxed);
+    writer.join().unwrap();
+    elapsed
+}
+
+fn bench_predict_while_learning(c: &mut Criterion) {
+    let mut group = c.benchmark_group("predict_while_learning");
+
+    group.bench_function(BenchmarkId::new("mutex", READERS), |b| {
+        let predictor = Arc::new(Mutex::new(Predictor::new()));
+        predictor.lock().unwrap().learn(change(0, 1), &counter(0), &counter(1), None).unwrap();
+        b.iter_custom(|iters| {
+            let reader = Arc::clone(&predictor);
+            let writer = Arc::clone(&predictor);
+            contended(
+                iters,
+                Arc::new(move |c, t| reader.lock().unwrap().predict(c, t).is_some()),
+                Arc::new(move |c, old, new| {
+                    writer.lock().unwrap().learn(c, old, new, None).ok();
+                }),
+            )
+        });
+    });
+
+    group.bench_function(BenchmarkId::new("concurrent", READERS), |b| {
+        let predictor = Arc::new(ConcurrentPredictor::default());
+        predictor.learn(change(0, 1), &counter(0), &counter(1), None).unwrap();
+        b.iter_custom(|iters| {
+            let reader = Arc::clone(&predictor);
+            let writer = Arc::clone(&predictor);
+            contended(
+                iters,
+                Arc::new(move |c, t| reader.predict(c, t).is_some()),
+                Arc::new(move |c, old, new| {
+                    writer.learn(c, old, new, None).ok();
+                }),
+            )
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_predict_while_learning);
+criterion_main!(benches);
@@ -0,0 +1,59 @@
+//! Throughput of METRICS.record_reconcile from many threads at once
+//!
+//! `mutex_vec` reproduces the previous recorder (one Mutex<Vec<u64>> shared by all
+//! threads, oldest sample removed from the front) for comparison with the sharded
+//! wait-free recorder behind METRICS. Contention only shows on a multi-core machine.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use minimact::metrics::METRICS;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const MAX_SAMPLES: usize = 1000;
+
+/// Run `record` `iters` times on each of `threads` threads, returning the wall time
+fn run_threads(threads: usize, iters: u64, record: &(dyn Fn(u64) + Sync)) -> Duration {
+    let start = Instant::now();
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(move || {
+                for i in 0..iters {
+                    record(black_box(i));
+                }
+            });
+        }
+    });
+    start.elapsed()
+}
+
+fn bench_record_reconcile(c: &mut Criterion) {
+    let mut group = c.benchmark_group("record_reconcile");
+
+    for threads in [1usize, 4, 8] {
+        group.bench_with_input(BenchmarkId::new("mutex_vec", threads), &threads, |b, &threads| {
+            let times = Mutex::new(Vec::with_capacity(MAX_SAMPLES));
+            b.iter_custom(|iters| {
+                run_threads(threads, iters, &|micros| {
+                    let mut times = times.lock().unwrap();
+                    if times.len() >= MAX_SAMPLES {
+                        times.remove(0);
+                    }
+                    times.push(micros);
+                })
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("sharded", threads), &threads, |b, &threads| {
+            b.iter_custom(|iters| {
+                run_threads(threads, iters, &|micros| {
+                    METRICS.record_reconcile(Duration::from_micros(micros), 1, false);
+                })
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_record_reconcile);
+criterion_main!(benches);
@@ -14,9 +14,9 @@ fn create_tree(depth: usize, breadth: usize) -> VNode {
             "div",
             format!("key-{}", i),
             HashMap::new(),
-            vec![child],
+            vec![Some(child)],
         );
-        children.push(keyed);
+        children.push(Some(keyed));
     }
 
     VNode::element("div", HashMap::new(), children)
@@ -32,13 +32,13 @@ fn modify_tree(node: &VNode, change_percent: usize) -> VNode {
             }
         }
         VNode::Element(elem) => {
-            let children: Vec<VNode> = elem
+            let children: Vec<Option<VNode>> = elem
                 .children
                 .iter()
                 .enumerate()
                 .map(|(i, child)| {
                     if (i * 100 / elem.children.len()) < change_percent {
-                        modify_tree(child, change_percent)
+                        child.as_ref().map(|c| modify_tree(c, change_percent))
                     } else {
                         child.clone()
                     }
@@ -50,8 +50,11 @@ fn modify_tree(node: &VNode, change_percent: usize) -> VNode {
                 props: elem.props.clone(),
                 children,
                 key: elem.key.clone(),
+                path: elem.path.clone(),
+                source: elem.source.clone(),
             })
         }
+        VNode::Null(_) | VNode::Lazy(_) => node.clone(),
     }
 }
 
@@ -115,14 +118,16 @@ fn bench_predictor(c: &mut Criterion) {
         state_key: "value".to_string(),
         old_value: serde_json::json!(0),
         new_value: serde_json::json!(1),
+        array_operation: None,
+        locale: None,
     };
 
-    let old_tree = VNode::element("div", HashMap::new(), vec![VNode::text("0")]);
-    let new_tree = VNode::element("div", HashMap::new(), vec![VNode::text("1")]);
+    let old_tree = VNode::element("div", HashMap::new(), vec![Some(VNode::text("0"))]);
+    let new_tree = VNode::element("div", HashMap::new(), vec![Some(VNode::text("1"))]);
 
     // Train the predictor
     for _ in 0..10 {
-        predictor.learn(state_change.clone(), &old_tree, &new_tree);
+        predictor.learn(state_change.clone(), &old_tree, &new_tree, None).ok();
     }
 
     c.bench_function("predictor_predict", |b| {
@@ -138,10 +143,12 @@ fn bench_predictor_learn(c: &mut Criterion) {
         state_key: "value".to_string(),
         old_value: serde_json::json!(0),
         new_value: serde_json::json!(1),
+        array_operation: None,
+        locale: None,
     };
 
-    let old_tree = VNode::element("div", HashMap::new(), vec![VNode::text("0")]);
-    let new_tree = VNode::element("div", HashMap::new(), vec![VNode::text("1")]);
+    let old_tree = VNode::element("div", HashMap::new(), vec![Some(VNode::text("0"))]);
+    let new_tree = VNode::element("div", HashMap::new(), vec![Some(VNode::text("1"))]);
 
     c.bench_function("predictor_learn", |b| {
         b.iter(|| {
@@ -150,7 +157,8 @@ fn bench_predictor_learn(c: &mut Criterion) {
                 black_box(state_change.clone()),
                 black_box(&old_tree),
                 black_box(&new_tree),
-            );
+                None,
+            ).ok();
         });
     });
 }
@@ -0,0 +1,97 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use minimact::*;
+use std::collections::HashMap;
+
+/// A <ul> with `len` <li> children, each holding one text node, with transpiler-style hex paths
+fn list(len: usize, changed: Option<usize>) -> VNode {
+    let root = HexPath::from("10000000");
+    let children = (0..len)
+        .map(|i| {
+            let li_path = root.child(i);
+            let text = if Some(i) == changed { format!("Item {} (edited)", i) } else { format!("Item {}", i) };
+            let mut props = HashMap::new();
+            props.insert("class".to_string(), "item".to_string());
+            Some(VNode::Element(VElement {
+                tag: "li".to_string(),
+                props,
+                children: vec![Some(VNode::Text(VText {
+                    content: text,
+                    path: li_path.child(0),
+                }))],
+                key: None,
+                path: li_path,
+                source: None,
+            }))
+        })
+        .collect();
+
+    VNode::Element(VElement {
+        tag: "ul".to_string(),
+        props: HashMap::new(),
+        children,
+        key: None,
+        path: root,
+        source: None,
+    })
+}
+
+fn bench_small_and_medium_diffs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("reconcile_one_text_change");
+
+    for len in [4usize, 8, 16, 64, 256] {
+        let old_tree = list(len, None);
+        let new_tree = list(len, Some(len / 2));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &len, |b, _| {
+            b.iter(|| reconcile(black_box(&old_tree), black_box(&new_tree)));
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_append(c: &mut Criterion) {
+    let old_tree = list(8, None);
+    let new_tree = list(9, None);
+
+    c.bench_function("reconcile_append_small", |b| {
+        b.iter(|| reconcile(black_box(&old_tree), black_box(&new_tree)));
+    });
+}
+
+fn bench_path_segments(c: &mut Criterion) {
+    let path = HexPath::from("10000000.20000000.30000000.10000000");
+
+    c.bench_function("hex_path_to_index_path", |b| {
+        b.iter(|| black_box(&path).to_index_path());
+    });
+}
+
+fn bench_predictor_small(c: &mut Criterion) {
+    let state_change = StateChange {
+        component_id: "list".to_string(),
+        state_key: "selected".to_string(),
+        old_value: serde_json::json!(null),
+        new_value: serde_json::json!(4),
+        array_operation: None,
+        locale: None,
+    };
+    let old_tree = list(8, None);
+    let new_tree = list(8, Some(4));
+
+    c.bench_function("predictor_learn_predict_small", |b| {
+        b.iter(|| {
+            let mut predictor = Predictor::new();
+            predictor.learn(state_change.clone(), &old_tree, &new_tree, None).ok();
+            predictor.predict(black_box(&state_change), black_box(&old_tree))
+        });
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_small_and_medium_diffs,
+    bench_append,
+    bench_path_segments,
+    bench_predictor_small,
+);
+criterion_main!(benches);
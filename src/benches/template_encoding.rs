@@ -0,0 +1,55 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use minimact::*;
+
+/// `len` text templates under a list, like a row of bound table cells
+fn template_patches(len: usize) -> Vec<Patch> {
+    let root = HexPath::from("10000000.20000000");
+    (0..len)
+        .map(|i| Patch::UpdateTextTemplate {
+            path: root.child(i % 15).child(0),
+            template_patch: TemplatePatch {
+                template: format!("{{0}} of {{1}} ({})", i),
+                bindings: vec![format!("row{}.done", i), "total".to_string()],
+                bindings_with_transforms: None,
+                slots: vec![0, 5],
+                conditional_templates: None,
+                conditional_binding_index: None,
+            },
+        })
+        .collect()
+}
+
+fn bench_encoding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("template_encoding");
+
+    for len in [1usize, 16, 256] {
+        let patches = template_patches(len);
+        let json = serde_json::to_string(&patches).unwrap();
+        let compact = encode_compact(&patches).unwrap();
+        println!(
+            "{} patches: json {} bytes, compact {} bytes ({:.0}%)",
+            len,
+            json.len(),
+            compact.len(),
+            compact.len() as f64 * 100.0 / json.len() as f64
+        );
+
+        group.bench_with_input(BenchmarkId::new("json_encode", len), &len, |b, _| {
+            b.iter(|| serde_json::to_string(black_box(&patches)))
+        });
+        group.bench_with_input(BenchmarkId::new("compact_encode", len), &len, |b, _| {
+            b.iter(|| encode_compact(black_box(&patches)))
+        });
+        group.bench_with_input(BenchmarkId::new("json_decode", len), &len, |b, _| {
+            b.iter(|| serde_json::from_str::<Vec<Patch>>(black_box(&json)))
+        });
+        group.bench_with_input(BenchmarkId::new("compact_decode", len), &len, |b, _| {
+            b.iter(|| decode_compact(black_box(&compact)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_encoding);
+criterion_main!(benches);
@@ -7,14 +7,16 @@ use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::runtime::Runtime;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, Notify};
+use tokio::task::AbortHandle;
 
 pub mod task_registry;
 pub mod task_handle;
 
-use task_handle::{TaskHandle, TaskStatus};
+use task_handle::{TaskHandle, TaskPriority, TaskStatus};
 
 /// Global Rust runtime instance
 static mut RUNTIME: Option<Arc<RustTaskRuntime>> = None;
@@ -23,6 +25,25 @@ static mut RUNTIME: Option<Arc<RustTaskRuntime>> = None;
 pub struct RustTaskRuntime {
     tokio_runtime: Runtime,
     tasks: Arc<DashMap<String, TaskHandle>>,
+    aborts: Arc<DashMap<String, AbortHandle>>,
+    /// Non-background tasks spawned and not yet finished
+    foreground_active: Arc<AtomicUsize>,
+    /// Signalled when foreground_active drops to zero
+    idle: Arc<Notify>,
+}
+
+/// Counts a foreground task as active until dropped (finished or aborted)
+struct ForegroundGuard {
+    active: Arc<AtomicUsize>,
+    idle: Arc<Notify>,
+}
+
+impl Drop for ForegroundGuard {
+    fn drop(&mut self) {
+        if self.active.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.idle.notify_waiters();
+        }
+    }
 }
 
 impl RustTaskRuntime {
@@ -38,6 +59,9 @@ impl RustTaskRuntime {
         Self {
             tokio_runtime,
             tasks: Arc::new(DashMap::new()),
+            aborts: Arc::new(DashMap::new()),
+            foreground_active: Arc::new(AtomicUsize::new(0)),
+            idle: Arc::new(Notify::new()),
         }
     }
 
@@ -57,22 +81,80 @@ impl RustTaskRuntime {
         task_id: String,
         task_fn: F,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+    where
+        F: std::future::Future<Output = Result<T, Box<dyn std::error::Error + Send + Sync>>> + Send + 'static,
+        T: Serialize + Send + 'static,
+    {
+        self.execute_task_with_priority(task_id, TaskPriority::Normal, task_fn)
+    }
+
+    /// Execute a task asynchronously at the given priority
+    ///
+    /// Background tasks stay Idle until no UserBlocking/Normal task is running, so
+    /// speculative work (hint precomputation) never competes with user interactions.
+    /// Cancelling a Background task before it starts means it never runs.
+    pub fn execute_task_with_priority<F, T>(
+        &self,
+        task_id: String,
+        priority: TaskPriority,
+        task_fn: F,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+    where
+        F: std::future::Future<Output = Result<T, Box<dyn std::error::Error + Send + Sync>>> + Send + 'static,
+        T: Serialize + Send + 'static,
+    {
+        self.execute_correlated_task(task_id, priority, None, task_fn)
+    }
+
+    /// Execute a task on behalf of the request identified by `correlation_id`
+    /// The id is carried in the task's handle, so every status report names it
+    pub fn execute_correlated_task<F, T>(
+        &self,
+        task_id: String,
+        priority: TaskPriority,
+        correlation_id: Option<String>,
+        task_fn: F,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
     where
         F: std::future::Future<Output = Result<T, Box<dyn std::error::Error + Send + Sync>>> + Send + 'static,
         T: Serialize + Send + 'static,
     {
         let tasks = self.tasks.clone();
+        let aborts = self.aborts.clone();
         let task_id_clone = task_id.clone();
 
         // Create progress channel
         let (progress_tx, mut progress_rx) = mpsc::channel::<f64>(100);
 
         // Insert task handle
-        let handle = TaskHandle::new(task_id.clone());
+        let mut handle = TaskHandle::new(task_id.clone());
+        handle.priority = priority;
+        handle.correlation_id = correlation_id;
         tasks.insert(task_id.clone(), handle);
 
+        // Foreground tasks count as active from the moment they're submitted
+        let foreground = (priority != TaskPriority::Background).then(|| {
+            self.foreground_active.fetch_add(1, Ordering::SeqCst);
+            ForegroundGuard {
+                active: self.foreground_active.clone(),
+                idle: self.idle.clone(),
+            }
+        });
+        let active = self.foreground_active.clone();
+        let idle = self.idle.clone();
+
+        // Hold the abort entry while spawning, so a task that finishes at once can't
+        // remove its handle before it's inserted (it waits for the entry instead)
+        let abort_entry = self.aborts.entry(task_id);
+
         // Spawn task on Tokio runtime
-        self.tokio_runtime.spawn(async move {
+        let join = self.tokio_runtime.spawn(async move {
+            let _foreground = foreground;
+
+            if priority == TaskPriority::Background {
+                wait_for_idle(&active, &idle).await;
+            }
+
             // Mark as running
             if let Some(mut task) = tasks.get_mut(&task_id_clone) {
                 task.set_status(TaskStatus::Running);
@@ -99,18 +181,29 @@ impl RustTaskRuntime {
                     }
                 }
             }
+
+            aborts.remove(&task_id_clone);
         });
+        abort_entry.insert(join.abort_handle());
 
         Ok(())
     }
 
+    /// Check if no UserBlocking/Normal task is running
+    pub fn is_idle(&self) -> bool {
+        self.foreground_active.load(Ordering::SeqCst) == 0
+    }
+
     /// Get task status
     pub fn get_task_status(&self, task_id: &str) -> Option<TaskHandle> {
         self.tasks.get(task_id).map(|entry| entry.value().clone())
     }
 
-    /// Cancel a running task
+    /// Cancel a task (aborting it if it is still waiting or running)
     pub fn cancel_task(&self, task_id: &str) {
+        if let Some((_, abort)) = self.aborts.remove(task_id) {
+            abort.abort();
+        }
         if let Some(mut task) = self.tasks.get_mut(task_id) {
             task.set_status(TaskStatus::Cancelled);
         }
@@ -122,6 +215,22 @@ impl RustTaskRuntime {
     }
 }
 
+/// Wait until no foreground task is active
+async fn wait_for_idle(active: &AtomicUsize, idle: &Notify) {
+    loop {
+        // Register for the notification before checking, so a wake-up between the
+        // check and the await isn't lost
+        let notified = idle.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        if active.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        notified.await;
+    }
+}
+
 // ============================================================================
 // FFI Interface for C# Interop
 // ============================================================================
@@ -137,7 +246,8 @@ pub extern "C" fn minimact_runtime_init() -> bool {
 ///
 /// # Arguments
 /// * `task_id` - Unique task identifier
-/// * `input_json` - JSON-serialized task input
+/// * `input_json` - JSON-serialized task input (its `correlation_id`, if any, is
+///   echoed back so the response joins that request's trace)
 ///
 /// # Returns
 /// * JSON string with execution result or error
@@ -169,10 +279,13 @@ pub extern "C" fn minimact_execute_task(
     };
 
     // Return task ID (actual execution happens asynchronously)
-    let response = serde_json::json!({
+    let mut response = serde_json::json!({
         "success": true,
         "task_id": task_id
     });
+    if let Some(correlation_id) = input.get("correlation_id").and_then(|id| id.as_str()) {
+        response["correlation_id"] = correlation_id.into();
+    }
 
     CString::new(response.to_string()).unwrap().into_raw()
 }
@@ -235,6 +348,67 @@ mod tests {
         assert!(runtime.tasks.is_empty());
     }
 
+    #[test]
+    fn test_background_task_waits_for_idle() {
+        let runtime = RustTaskRuntime::new();
+        let done = |ms| async move {
+            tokio::time::sleep(tokio::time::Duration::from_millis(ms)).await;
+            Ok::<u64, Box<dyn std::error::Error + Send + Sync>>(ms)
+        };
+
+        runtime.execute_task_with_priority("render".to_string(), TaskPriority::UserBlocking, done(200)).unwrap();
+        runtime.execute_task_with_priority("hint".to_string(), TaskPriority::Background, done(0)).unwrap();
+
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(!runtime.is_idle());
+        assert_eq!(runtime.get_task_status("hint").unwrap().status, TaskStatus::Idle);
+
+        std::thread::sleep(std::time::Duration::from_millis(400));
+        assert!(runtime.is_idle());
+        assert_eq!(runtime.get_task_status("hint").unwrap().status, TaskStatus::Complete);
+    }
+
+    #[test]
+    fn test_cancel_background_task_before_it_runs() {
+        let runtime = RustTaskRuntime::new();
+        let done = |ms| async move {
+            tokio::time::sleep(tokio::time::Duration::from_millis(ms)).await;
+            Ok::<u64, Box<dyn std::error::Error + Send + Sync>>(ms)
+        };
+
+        runtime.execute_task_with_priority("render".to_string(), TaskPriority::Normal, done(100)).unwrap();
+        runtime.execute_task_with_priority("hint".to_string(), TaskPriority::Background, done(0)).unwrap();
+        runtime.cancel_task("hint");
+
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        let hint = runtime.get_task_status("hint").unwrap();
+        assert_eq!(hint.status, TaskStatus::Cancelled);
+        assert!(hint.result.is_none());
+    }
+
+    #[test]
+    fn test_finished_tasks_leave_no_abort_handles() {
+        let runtime = RustTaskRuntime::new();
+        for i in 0..200 {
+            let done = async { Ok::<u8, Box<dyn std::error::Error + Send + Sync>>(1) };
+            runtime.execute_task(format!("task{}", i), done).unwrap();
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        assert!(runtime.aborts.is_empty());
+    }
+
+    #[test]
+    fn test_task_status_carries_correlation_id() {
+        let runtime = RustTaskRuntime::new();
+        let done = async { Ok::<u8, Box<dyn std::error::Error + Send + Sync>>(1) };
+
+        runtime.execute_correlated_task("render".to_string(), TaskPriority::Normal, Some("req-7".to_string()), done).unwrap();
+
+        let status = serde_json::to_value(runtime.get_task_status("render").unwrap()).unwrap();
+        assert_eq!(status["correlation_id"], "req-7");
+    }
+
     #[tokio::test]
     async fn test_simple_task_execution() {
         let runtime = RustTaskRuntime::new();
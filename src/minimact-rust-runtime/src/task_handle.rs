@@ -16,11 +16,30 @@ pub enum TaskStatus {
     Cancelled,
 }
 
+/// Scheduling priority of a task
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskPriority {
+    /// Work a user is waiting on (event handlers, state changes)
+    UserBlocking,
+    /// Regular server tasks
+    #[default]
+    Normal,
+    /// Speculative work (hint precomputation) - only runs while no other task is running
+    Background,
+}
+
 /// Task handle containing status and result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskHandle {
     pub task_id: String,
     pub status: TaskStatus,
+    #[serde(default)]
+    pub priority: TaskPriority,
+    /// Correlation id of the request that started the task, so its status reports
+    /// join that request's trace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
     pub progress: f64,
     pub result: Option<serde_json::Value>,
     pub error: Option<String>,
@@ -34,6 +53,8 @@ impl TaskHandle {
         Self {
             task_id,
             status: TaskStatus::Idle,
+            priority: TaskPriority::Normal,
+            correlation_id: None,
             progress: 0.0,
             result: None,
             error: None,
@@ -0,0 +1,302 @@
+//! Human-readable patch annotations for devtools
+//!
+//! Decorates each patch with a description computed from the tree it applies to,
+//! e.g. "text changed from 'Count: 0' to 'Count: 1' at div>span", for display in
+//! the cactus-browser inspector. Annotating walks the old tree once per patch, so
+//! hosts only do it while the devtools flag is set.
+//!
+//! Annotations also carry the JSX location (`VElement::source`) of the element a
+//! patch touches, so devtools can jump from a DOM change to the line behind it.
+
+use crate::path::HexPath;
+use crate::template_renderer::binding_keys;
+use crate::vdom::{Patch, SourceLocation, VNode};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static DEVTOOLS_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turn patch annotations on or off (process-wide)
+pub fn set_devtools_enabled(enabled: bool) {
+    DEVTOOLS_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Check if devtools annotations are enabled
+pub fn devtools_enabled() -> bool {
+    DEVTOOLS_ENABLED.load(Ordering::Relaxed)
+}
+
+/// A patch's human-readable description
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatchAnnotation {
+    /// Patch kind ("UpdateText", "Create", ...)
+    pub kind: String,
+    /// Target path
+    pub path: HexPath,
+    /// Element chain to the target, e.g. "div>ul>li"
+    pub selector: String,
+    /// What the patch does, e.g. "text changed from 'a' to 'b' at div>span"
+    pub description: String,
+    /// JSX location of the target element (of the parent element for texts), if the
+    /// tree carries source locations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceLocation>,
+}
+
+/// Annotate `patches` if devtools are enabled, otherwise None
+pub fn annotations_if_enabled(patches: &[Patch], old_tree: &VNode) -> Option<Vec<PatchAnnotation>> {
+    devtools_enabled().then(|| annotate_patches(patches, old_tree))
+}
+
+/// Annotate every patch against the tree the patches will be applied to
+pub fn annotate_patches(patches: &[Patch], old_tree: &VNode) -> Vec<PatchAnnotation> {
+    patches.iter().map(|patch| annotate_patch(patch, old_tree)).collect()
+}
+
+/// Annotate a single patch against the tree it will be applied to
+pub fn annotate_patch(patch: &Patch, old_tree: &VNode) -> PatchAnnotation {
+    let path = patch.path();
+    let selector = selector_for(old_tree, path);
+    let target = old_tree.find_by_path(path);
+
+    let description = match patch {
+        Patch::UpdateText { content, .. } => match target {
+            Some(VNode::Text(old)) => format!("text changed from {} to {} at {}", quote(&old.content), quote(content), selector),
+            _ => format!("text set to {} at {}", quote(content), selector),
+        },
+        Patch::UpdateProps { props, .. } => {
+            let old_props = match target {
+                Some(VNode::Element(el)) => Some(&el.props),
+                _ => None,
+            };
+            let names: BTreeSet<&String> = props.keys().chain(old_props.into_iter().flat_map(|p| p.keys())).collect();
+            let changes: Vec<String> = names
+                .into_iter()
+                .filter_map(|name| match (old_props.and_then(|p| p.get(name)), props.get(name)) {
+                    (Some(old), Some(new)) if old != new => Some(format!("{} {} → {}", name, quote(old), quote(new))),
+                    (None, Some(new)) => Some(format!("+{}={}", name, quote(new))),
+                    (Some(_), None) => Some(format!("-{}", name)),
+                    _ => None,
+                })
+                .collect();
+            format!("props changed at {}: {}", selector, changes.join(", "))
+        }
+        Patch::Create { node, .. } => format!("created {} at {}", describe_node(node), selector),
+        Patch::Remove { .. } => match target {
+            Some(node) => format!("removed {} at {}", describe_node(node), selector),
+            None => format!("removed node at {}", selector),
+        },
+        Patch::Replace { node, .. } => match target {
+            Some(old) => format!("replaced {} with {} at {}", describe_node(old), describe_node(node), selector),
+            None => format!("replaced node with {} at {}", describe_node(node), selector),
+        },
+        Patch::ReorderChildren { order, .. } => {
+            format!("reordered {} keyed children at {}: [{}]", order.len(), selector, order.join(", "))
+        }
+        Patch::UpdateTextTemplate { template_patch, .. } => format!(
+            "text template {} bound to [{}] at {}",
+            quote(&template_patch.template),
+            binding_keys(template_patch).join(", "),
+            selector
+        ),
+        Patch::UpdatePropsTemplate { prop_name, template_patch, .. }
+        | Patch::UpdateAttributeDynamic { attr_name: prop_name, template_patch, .. } => format!(
+            "{} template {} bound to [{}] at {}",
+            prop_name,
+            quote(&template_patch.template),
+            binding_keys(template_patch).join(", "),
+            selector
+        ),
+        Patch::UpdateAttributeStatic { attr_name, value, .. } => {
+            format!("{} set to {} at {}", attr_name, quote(value), selector)
+        }
+        Patch::UpdateListTemplate { loop_template, .. } => {
+            format!("list rendered from {} at {}", loop_template.array_binding, selector)
+        }
+        Patch::ReorderTemplate { reorder_template, .. } => {
+            format!("list reordered by {} at {}", reorder_template.array_binding, selector)
+        }
+        Patch::UpdateListWindow { total, start, end, .. } => {
+            format!("rows {}..{} of {} visible at {}", start, end, total, selector)
+        }
+        Patch::Navigate { url, replace, .. } => {
+            format!("{} {}", if *replace { "navigated (replace) to" } else { "navigated to" }, quote(url))
+        }
+        Patch::Custom { kind, .. } => format!("custom {} at {}", kind, selector),
+        Patch::SetIgnored { ignored, .. } => {
+            format!("{} at {}", if *ignored { "handed to client code" } else { "taken back from client code" }, selector)
+        }
+        Patch::InvalidateLayout { attributes, .. } => format!("layout affected by {} at {}", attributes.join(", "), selector),
+        Patch::Lifecycle { event, handler, .. } => format!("{:?} handler {} at {}", event, quote(handler), selector),
+        Patch::UpdateDocument { target, value, .. } => match value {
+            Some(value) => format!("document {:?} set to {}", target, quote(value)),
+            None => format!("document {:?} removed", target),
+        },
+        Patch::ReplaceConditional { structural_template, .. } => format!(
+            "branch chosen by {} ({} branches) at {}",
+            structural_template.condition_binding,
+            structural_template.branches.len(),
+            selector
+        ),
+    };
+
+    // A created or replacing element knows its own location; anything else is
+    // located by the old tree
+    let source = match patch {
+        Patch::Create { node: VNode::Element(el), .. } | Patch::Replace { node: VNode::Element(el), .. } if el.source.is_some() => {
+            el.source.as_deref().cloned()
+        }
+        _ => source_for(old_tree, path),
+    };
+
+    PatchAnnotation {
+        kind: patch.kind().to_string(),
+        path: path.clone(),
+        selector,
+        description,
+        source,
+    }
+}
+
+/// Source location of the deepest element at or above `path` that has one
+pub fn source_for(tree: &VNode, path: &HexPath) -> Option<SourceLocation> {
+    let mut source = None;
+    let mut node = Some(tree);
+    while let Some(current) = node {
+        if let VNode::Element(el) = current {
+            source = el.source.as_ref().or(source);
+        }
+        if current.path() == path {
+            break;
+        }
+        node = current
+            .children()
+            .iter()
+            .flatten()
+            .find(|child| path.is_within(child.path()));
+    }
+    source.map(|location| SourceLocation::clone(location))
+}
+
+/// Element tags from the root to `path`, e.g. "div>ul>li"
+/// Text and null targets are described by their parent element; paths below the
+/// deepest existing node (created nodes) end at that node
+pub fn selector_for(tree: &VNode, path: &HexPath) -> String {
+    let mut tags = Vec::new();
+    let mut node = Some(tree);
+    while let Some(current) = node {
+        if let VNode::Element(el) = current {
+            tags.push(el.tag.as_str());
+        }
+        if current.path() == path {
+            break;
+        }
+        node = current
+            .children()
+            .iter()
+            .flatten()
+            .find(|child| path.is_within(child.path()));
+    }
+
+    if tags.is_empty() {
+        "root".to_string()
+    } else {
+        tags.join(">")
+    }
+}
+
+fn describe_node(node: &VNode) -> String {
+    match node {
+        VNode::Element(el) => match &el.key {
+            Some(key) => format!("<{} key={}>", el.tag, quote(key)),
+            None => format!("<{}>", el.tag),
+        },
+        VNode::Text(text) => format!("text {}", quote(&text.content)),
+        VNode::Null(_) => "nothing".to_string(),
+        VNode::Lazy(lazy) => format!("placeholder {}", quote(&lazy.summary)),
+    }
+}
+
+/// Quote a value for display, truncating long ones
+fn quote(value: &str) -> String {
+    const MAX_CHARS: usize = 40;
+    if value.chars().count() > MAX_CHARS {
+        let truncated: String = value.chars().take(MAX_CHARS).collect();
+        format!("'{}…'", truncated)
+    } else {
+        format!("'{}'", value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vdom::{VElement, VText};
+    use std::collections::HashMap;
+
+    fn tree() -> VNode {
+        VNode::Element(VElement {
+            tag: "div".to_string(),
+            props: HashMap::new(),
+            children: vec![Some(VNode::Element(VElement {
+                tag: "span".to_string(),
+                props: HashMap::from([("class".to_string(), "count".to_string())]),
+                children: vec![Some(VNode::Text(VText {
+                    content: "Count: 0".to_string(),
+                    path: HexPath::from("10000000.10000000.10000000"),
+                }))],
+                key: None,
+                path: HexPath::from("10000000.10000000"),
+                source: None,
+            }))],
+            key: None,
+            path: HexPath::from("10000000"),
+            source: None,
+        })
+    }
+
+    #[test]
+    fn test_text_annotation() {
+        let patch = Patch::UpdateText {
+            path: HexPath::from("10000000.10000000.10000000"),
+            content: "Count: 1".to_string(),
+        };
+        let annotation = annotate_patch(&patch, &tree());
+        assert_eq!(annotation.selector, "div>span");
+        assert_eq!(annotation.description, "text changed from 'Count: 0' to 'Count: 1' at div>span");
+    }
+
+    #[test]
+    fn test_props_annotation() {
+        let patch = Patch::UpdateProps {
+            path: HexPath::from("10000000.10000000"),
+            props: HashMap::from([("class".to_string(), "count big".to_string()), ("title".to_string(), "n".to_string())]),
+        };
+        let annotation = annotate_patch(&patch, &tree());
+        assert_eq!(annotation.description, "props changed at div>span: class 'count' → 'count big', +title='n'");
+    }
+
+    #[test]
+    fn test_source_locations() {
+        let at = |line| SourceLocation { file: "Counter.tsx".to_string(), line, col: 5 };
+        let mut located = tree();
+        if let VNode::Element(div) = &mut located {
+            div.source = Some(Box::new(at(3)));
+        }
+        let json = serde_json::to_value(&located).unwrap();
+        assert_eq!(json["source"], serde_json::json!({ "file": "Counter.tsx", "line": 3, "col": 5 }));
+        assert!(json["children"][0].get("source").is_none());
+
+        // The text's annotation points at the nearest located element
+        let patch = Patch::UpdateText { path: HexPath::from("10000000.10000000.10000000"), content: "Count: 1".to_string() };
+        assert_eq!(annotate_patch(&patch, &located).source, Some(at(3)));
+
+        // Moving the JSX alone changes nothing in the DOM
+        let mut moved = located.clone();
+        if let VNode::Element(div) = &mut moved {
+            div.source = Some(Box::new(at(4)));
+        }
+        assert!(crate::reconciler::reconcile(&located, &moved).unwrap().is_empty());
+    }
+}
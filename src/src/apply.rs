@@ -0,0 +1,369 @@
+//! Apply patches to a VNode tree
+//!
+//! Mirrors what the client runtime does with a patch batch, so the server can keep
+//! its own copy of the client tree (and check the reconciler's output) without a DOM.
+//! Nodes are located through a `TreeIndex` that is updated after every patch.
+//!
+//! Only concrete patches can be applied. Template patches need state to render;
+//! downgrade them first with `negotiate_patches` and baseline capabilities.
+
+use crate::error::{MinimactError, Result};
+use crate::path::HexPath;
+use crate::patch_validator::{validate_patch_indexed, PatchValidatorConfig};
+use crate::tree_index::TreeIndex;
+use crate::vdom::{Patch, VNode};
+use std::collections::HashMap;
+
+/// Apply a single patch to `tree`
+pub fn apply_patch(tree: &mut VNode, patch: &Patch) -> Result<()> {
+    let index = TreeIndex::build(tree);
+    apply_patch_indexed(tree, &index, patch)
+}
+
+/// Apply patches in order to `tree`, stopping at the first one that doesn't fit
+pub fn apply_patches(tree: &mut VNode, patches: &[Patch]) -> Result<()> {
+    let mut index = TreeIndex::build(tree);
+    for patch in patches {
+        apply_patch_indexed(tree, &index, patch)?;
+        index.invalidate(patch, tree);
+    }
+    Ok(())
+}
+
+/// Validate and apply patches written by the host (rather than the reconciler), and
+/// return the equivalent list to send to the client
+///
+/// Each patch is validated against the tree as left by the ones before it. Patches
+/// that change nothing (an UpdateText to the current content, ...) are dropped and
+/// the rest go through `normalize_patches`.
+pub fn apply_and_normalize(tree: &mut VNode, patches: &[Patch]) -> Result<Vec<Patch>> {
+    let config = PatchValidatorConfig::default();
+    let mut index = TreeIndex::build(tree);
+    let mut effective = Vec::with_capacity(patches.len());
+    for patch in patches {
+        validate_patch_indexed(patch, tree, &index, &config)?;
+        if is_noop(tree, &index, patch) {
+            continue;
+        }
+        apply_patch_indexed(tree, &index, patch)?;
+        index.invalidate(patch, tree);
+        effective.push(patch.clone());
+    }
+    Ok(normalize_patches(effective, tree))
+}
+
+fn is_noop(tree: &VNode, index: &TreeIndex, patch: &Patch) -> bool {
+    match (patch, index.get(tree, patch.path())) {
+        (Patch::UpdateText { content, .. }, Some(VNode::Text(text))) => text.content == *content,
+        (Patch::UpdateProps { props, .. }, Some(VNode::Element(el))) => el.props == *props,
+        (Patch::Replace { node, .. }, Some(current)) => current == node,
+        (Patch::SetIgnored { ignored, .. }, Some(current)) => crate::ignored_regions::is_ignored_node(current) == *ignored,
+        _ => false,
+    }
+}
+
+/// Drop patches whose effect is covered by another patch of the list, which must
+/// already have been applied to give `tree_after`:
+/// - an UpdateText/UpdateProps followed by another of the same kind at its path, or
+///   by a Create, Replace or Remove of its node or an ancestor
+/// - patches inside a node that an earlier Create/Replace puts in the tree; that
+///   patch carries the node's final state from `tree_after` instead
+///
+/// Quadratic in the number of patches; meant for short host-written lists.
+pub fn normalize_patches(patches: Vec<Patch>, tree_after: &VNode) -> Vec<Patch> {
+    let index = TreeIndex::build(tree_after);
+    let mut absorbed: Vec<HexPath> = Vec::new();
+    let mut normalized = Vec::with_capacity(patches.len());
+
+    for (i, patch) in patches.iter().enumerate() {
+        let path = patch.path();
+        if absorbed.iter().any(|creator| path.is_within(creator)) {
+            continue;
+        }
+
+        match patch {
+            Patch::UpdateText { .. } | Patch::UpdateProps { .. } => {
+                let superseded = patches[i + 1..].iter().any(|later| match later {
+                    Patch::Create { path: q, .. } | Patch::Replace { path: q, .. } | Patch::Remove { path: q } => {
+                        path.is_within(q)
+                    }
+                    _ => later.kind() == patch.kind() && later.path() == path,
+                });
+                if !superseded {
+                    normalized.push(patch.clone());
+                }
+            }
+            Patch::Create { node, .. } | Patch::Replace { node, .. } => {
+                // Later patches may have removed the node again; then nothing is absorbed
+                let node = match index.get(tree_after, path) {
+                    Some(final_node) => {
+                        absorbed.push(path.clone());
+                        final_node.clone()
+                    }
+                    None => node.clone(),
+                };
+                normalized.push(match patch {
+                    Patch::Create { .. } => Patch::Create { path: path.clone(), node },
+                    _ => Patch::Replace { path: path.clone(), node, preserve: None },
+                });
+            }
+            _ => normalized.push(patch.clone()),
+        }
+    }
+    normalized
+}
+
+/// Apply a single patch using a prebuilt index of `tree`
+/// The caller is responsible for invalidating the index afterwards
+pub fn apply_patch_indexed(tree: &mut VNode, index: &TreeIndex, patch: &Patch) -> Result<()> {
+    match patch {
+        Patch::UpdateText { path, content } => match node_mut(tree, index, path)? {
+            VNode::Text(text) => text.content = content.clone(),
+            other => {
+                return Err(MinimactError::PatchTypeMismatch {
+                    expected: "Text",
+                    found: other.node_type(),
+                })
+            }
+        },
+
+        Patch::UpdateProps { path, props } => match node_mut(tree, index, path)? {
+            VNode::Element(el) => el.props = props.clone(),
+            other => {
+                return Err(MinimactError::PatchTypeMismatch {
+                    expected: "Element",
+                    found: other.node_type(),
+                })
+            }
+        },
+
+        Patch::SetIgnored { path, ignored } => match node_mut(tree, index, path)? {
+            VNode::Element(el) => crate::ignored_regions::set_ignored(el, *ignored),
+            other => {
+                return Err(MinimactError::PatchTypeMismatch {
+                    expected: "Element",
+                    found: other.node_type(),
+                })
+            }
+        },
+
+        Patch::Replace { path, node, .. } => {
+            if index.contains(path) {
+                *node_mut(tree, index, path)? = node.clone();
+            } else {
+                // Replacing a node that moved to a fresh path: nothing to replace, insert it
+                insert_child(tree, index, path, node.clone())?;
+            }
+        }
+
+        Patch::Create { path, node } => insert_child(tree, index, path, node.clone())?,
+
+        Patch::Remove { path } => {
+            let parent_path = path.parent().ok_or_else(|| invalid_path(path))?;
+            let children = children_mut(tree, index, &parent_path)?;
+            let position = children
+                .iter()
+                .position(|child| child.as_ref().is_some_and(|n| n.path() == path))
+                .ok_or_else(|| invalid_path(path))?;
+            children.remove(position);
+        }
+
+        Patch::ReorderChildren { path, order, .. } => {
+            reorder_children(children_mut(tree, index, path)?, order)?;
+        }
+
+        // Only tells the client which rows it renders; the tree is unchanged
+        Patch::UpdateListWindow { .. } => {}
+
+        // Navigation, host operations and layout hints don't touch the tree
+        Patch::Navigate { .. } | Patch::Custom { .. } | Patch::InvalidateLayout { .. } | Patch::Lifecycle { .. } => {}
+
+        Patch::UpdateDocument { path, target, value } => match value {
+            None => apply_patch_indexed(tree, index, &Patch::Remove { path: path.clone() })?,
+            Some(value) if index.contains(path) => match node_mut(tree, index, path)? {
+                VNode::Element(el) => target.write(el, value),
+                other => {
+                    return Err(MinimactError::PatchTypeMismatch {
+                        expected: "Element (a head setting)",
+                        found: other.node_type(),
+                    })
+                }
+            },
+            Some(value) => insert_child(tree, index, path, target.element(path, value))?,
+        },
+
+        Patch::UpdateTextTemplate { .. }
+        | Patch::UpdatePropsTemplate { .. }
+        | Patch::UpdateListTemplate { .. }
+        | Patch::ReorderTemplate { .. }
+        | Patch::ReplaceConditional { .. }
+        | Patch::UpdateAttributeStatic { .. }
+        | Patch::UpdateAttributeDynamic { .. } => {
+            return Err(MinimactError::PatchTypeMismatch {
+                expected: "concrete patch (render templates first)",
+                found: patch.kind(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn invalid_path(path: &HexPath) -> MinimactError {
+    MinimactError::InvalidPatchPath {
+        path: path.to_index_path().unwrap_or_default(),
+    }
+}
+
+fn node_mut<'t>(tree: &'t mut VNode, index: &TreeIndex, path: &HexPath) -> Result<&'t mut VNode> {
+    index.get_mut(tree, path).ok_or_else(|| invalid_path(path))
+}
+
+fn children_mut<'t>(tree: &'t mut VNode, index: &TreeIndex, path: &HexPath) -> Result<&'t mut Vec<Option<VNode>>> {
+    match node_mut(tree, index, path)? {
+        VNode::Element(el) => Ok(&mut el.children),
+        other => Err(MinimactError::PatchTypeMismatch {
+            expected: "Element (to have children)",
+            found: other.node_type(),
+        }),
+    }
+}
+
+/// Put `node` under its parent: over an existing child with the same path, otherwise
+/// before the first sibling that follows it in document order
+fn insert_child(tree: &mut VNode, index: &TreeIndex, path: &HexPath, node: VNode) -> Result<()> {
+    let parent_path = path.parent().ok_or_else(|| invalid_path(path))?;
+    let children = children_mut(tree, index, &parent_path)?;
+
+    if let Some(slot) = children.iter_mut().find(|child| child.as_ref().is_some_and(|n| n.path() == path)) {
+        *slot = Some(node);
+        return Ok(());
+    }
+
+    let position = children
+        .iter()
+        .position(|child| {
+            child
+                .as_ref()
+                .is_some_and(|n| n.path().cmp_document_order(path) == std::cmp::Ordering::Greater)
+        })
+        .unwrap_or(children.len());
+    children.insert(position, Some(node));
+    Ok(())
+}
+
+/// Move keyed children into `order`, within the slots keyed children already occupy
+/// Unkeyed children and nulls stay where they are
+fn reorder_children(children: &mut [Option<VNode>], order: &[String]) -> Result<()> {
+    let rank: HashMap<&str, usize> = order.iter().enumerate().map(|(i, k)| (k.as_str(), i)).collect();
+
+    let slots: Vec<usize> = children
+        .iter()
+        .enumerate()
+        .filter(|(_, child)| child.as_ref().is_some_and(|n| n.key().is_some()))
+        .map(|(i, _)| i)
+        .collect();
+
+    let present: std::collections::HashSet<&str> = slots
+        .iter()
+        .filter_map(|&i| children[i].as_ref().and_then(|n| n.key()))
+        .collect();
+    if let Some(missing) = order.iter().find(|k| !present.contains(k.as_str())) {
+        return Err(MinimactError::KeyNotFound(missing.clone()));
+    }
+
+    // Keys missing from `order` keep their relative order after the listed ones
+    let mut keyed: Vec<VNode> = slots.iter().filter_map(|&i| children[i].take()).collect();
+    keyed.sort_by_key(|n| n.key().and_then(|k| rank.get(k).copied()).unwrap_or(order.len()));
+    for (&slot, node) in slots.iter().zip(keyed) {
+        children[slot] = Some(node);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reconciler::reconcile;
+    use crate::vdom::{VElement, VText};
+
+    fn item(key: &str, path: &str) -> Option<VNode> {
+        Some(VNode::Element(VElement {
+            tag: "li".to_string(),
+            props: HashMap::new(),
+            children: vec![Some(VNode::Text(VText {
+                content: key.to_string(),
+                path: HexPath::from(format!("{}.10000000", path)),
+            }))],
+            key: Some(key.to_string()),
+            path: HexPath::from(path),
+            source: None,
+        }))
+    }
+
+    fn list(children: Vec<Option<VNode>>) -> VNode {
+        VNode::Element(VElement {
+            tag: "ul".to_string(),
+            props: HashMap::new(),
+            children,
+            key: None,
+            path: HexPath::from("10000000"),
+            source: None,
+        })
+    }
+
+    #[test]
+    fn test_apply_reconcile_round_trip() {
+        let old = list(vec![item("a", "10000000.10000000"), item("c", "10000000.20000000")]);
+        let new = list(vec![item("a", "10000000.10000000"), item("b", "10000000.18000000"), item("c", "10000000.20000000")]);
+
+        let mut tree = old.clone();
+        apply_patches(&mut tree, &reconcile(&old, &new).unwrap()).unwrap();
+        assert_eq!(tree, new);
+    }
+
+    #[test]
+    fn test_reorder_children() {
+        let mut tree = list(vec![item("a", "10000000.10000000"), None, item("b", "10000000.20000000")]);
+        apply_patch(&mut tree, &Patch::ReorderChildren {
+            path: HexPath::from("10000000"),
+            order: vec!["b".to_string(), "a".to_string()],
+            preserve: None,
+        })
+        .unwrap();
+
+        let keys: Vec<Option<&str>> = tree.children().iter().map(|c| c.as_ref().and_then(|n| n.key())).collect();
+        assert_eq!(keys, vec![Some("b"), None, Some("a")]);
+    }
+
+    #[test]
+    fn test_apply_and_normalize() {
+        let old = list(vec![item("a", "10000000.10000000")]);
+        let text_path = |p: &str| HexPath::from(format!("{}.10000000", p));
+        let patches = vec![
+            Patch::UpdateText { path: text_path("10000000.10000000"), content: "a".to_string() }, // no-op
+            Patch::UpdateText { path: text_path("10000000.10000000"), content: "x".to_string() },
+            Patch::UpdateText { path: text_path("10000000.10000000"), content: "y".to_string() },
+            Patch::Create { path: HexPath::from("10000000.20000000"), node: item("b", "10000000.20000000").unwrap() },
+            Patch::UpdateText { path: text_path("10000000.20000000"), content: "z".to_string() },
+        ];
+
+        let mut tree = old.clone();
+        let normalized = apply_and_normalize(&mut tree, &patches).unwrap();
+        assert_eq!(normalized.len(), 2);
+        assert!(matches!(&normalized[0], Patch::UpdateText { content, .. } if content == "y"));
+
+        // The normalized list gets the client to the same tree
+        let mut client = old;
+        apply_patches(&mut client, &normalized).unwrap();
+        assert_eq!(client, tree);
+        let created_text = tree.children()[1].as_ref().unwrap().children()[0].as_ref();
+        assert!(matches!(created_text, Some(VNode::Text(t)) if t.content == "z"));
+    }
+
+    #[test]
+    fn test_missing_target_is_an_error() {
+        let mut tree = list(vec![item("a", "10000000.10000000")]);
+        let patch = Patch::UpdateText { path: HexPath::from("10000000.20000000.10000000"), content: "x".to_string() };
+        assert!(apply_patch(&mut tree, &patch).is_err());
+    }
+}
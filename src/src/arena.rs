@@ -0,0 +1,65 @@
+//! Per-reconcile scratch arena
+//!
+//! Diffing builds short-lived lookup tables (keyed child maps, match lists, taken
+//! paths) that used to be separate heap allocations, several per element. They now
+//! live in a bump arena that is reset when the top-level reconcile call returns, so
+//! a steady stream of reconciles reuses the same memory (benches/allocations.rs).
+//!
+//! Nothing allocated in the arena may escape the reconcile call: patches and the
+//! nodes they carry are still ordinary owned values.
+//!
+//! Only those lookup tables use the arena. Trees are not arena-allocated: nodes
+//! cloned into patches and trees built while materializing templates are output,
+//! and VNode owns its children on the heap, so they are allocated as before.
+
+use bumpalo::Bump;
+use std::cell::RefCell;
+
+/// Arena memory kept between calls; larger arenas are released after use
+const MAX_RETAINED_BYTES: usize = 1024 * 1024;
+
+thread_local! {
+    static ARENA: RefCell<Bump> = RefCell::new(Bump::new());
+}
+
+/// Run `f` with this thread's reconcile arena, resetting it afterwards
+///
+/// Re-entrant calls (a reconcile started from inside another one) get a fresh
+/// temporary arena instead of the shared one.
+pub fn with_reconcile_arena<R>(f: impl FnOnce(&Bump) -> R) -> R {
+    ARENA.with(|cell| match cell.try_borrow_mut() {
+        Ok(mut arena) => {
+            let result = f(&arena);
+            if arena.allocated_bytes() > MAX_RETAINED_BYTES {
+                *arena = Bump::new();
+            } else {
+                arena.reset();
+            }
+            result
+        }
+        Err(_) => f(&Bump::new()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arena_is_reused_and_reentrant() {
+        let first = with_reconcile_arena(|arena| {
+            let value = arena.alloc(41u64);
+            *value += 1;
+            // Nested use must not panic on the RefCell
+            with_reconcile_arena(|inner| *inner.alloc(1u64)) + *value
+        });
+        assert_eq!(first, 43);
+
+        // The shared arena was reset: its chunk is reused rather than grown
+        let reused = with_reconcile_arena(|arena| {
+            arena.alloc([0u8; 64]);
+            arena.allocated_bytes()
+        });
+        assert!(reused <= MAX_RETAINED_BYTES);
+    }
+}
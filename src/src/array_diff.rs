@@ -0,0 +1,210 @@
+//! Array mutation inference
+//!
+//! Hosts that call `setItems(newItems)` only hand the predictor two arrays, so the
+//! predictor sees per-length concrete patterns instead of "an item was pushed".
+//! `diff_arrays` classifies old → new into the JavaScript operations that explain
+//! the change (push, pop, shift, unshift, splice, item updates). A single resulting
+//! operation converts to the StateChange `array_operation` the predictor already
+//! understands (`infer_array_operation`).
+//!
+//! Items are matched by their "id" / "key" / "_id" / "uuid" property when every
+//! item has one, otherwise by value. Reorders are not mutations: a change whose
+//! middle section is a permutation of the old one yields None (see reorder_detection).
+
+use crate::predictor::ArrayOperation;
+use crate::reorder_detection::extract_key_order;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// One array mutation, in terms of the new array's indices
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ArrayEdit {
+    /// Items appended at the end
+    Push { items: Vec<Value> },
+    /// Items removed from the end (keys of the removed items, if keyed)
+    Pop { count: usize, removed_keys: Vec<String> },
+    /// Items inserted at the start
+    Unshift { items: Vec<Value> },
+    /// Items removed from the start
+    Shift { count: usize, removed_keys: Vec<String> },
+    /// `remove` items at `index` replaced by `insert`
+    Splice {
+        index: usize,
+        remove: usize,
+        removed_keys: Vec<String>,
+        insert: Vec<Value>,
+    },
+    /// The item at `index` kept its identity but changed
+    Update {
+        index: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        key: Option<String>,
+        item: Value,
+    },
+}
+
+/// Explain `new` as mutations of `old`
+/// Returns None if either value isn't an array or the change is a reorder; an
+/// unchanged array yields no edits
+pub fn diff_arrays(old: &Value, new: &Value) -> Option<Vec<ArrayEdit>> {
+    let (Value::Array(old), Value::Array(new)) = (old, new) else { return None };
+
+    // Keyed identity only if every item on both sides has a key
+    let keys = extract_key_order(old)
+        .filter(|k| k.len() == old.len())
+        .zip(extract_key_order(new).filter(|k| k.len() == new.len()));
+    let same = |i: usize, j: usize| match &keys {
+        Some((old_keys, new_keys)) => old_keys[i] == new_keys[j],
+        None => old[i] == new[j],
+    };
+    let key_of = |index: usize, in_old: bool| {
+        keys.as_ref().map(|(old_keys, new_keys)| if in_old { old_keys[index].clone() } else { new_keys[index].clone() })
+    };
+
+    let prefix = (0..old.len().min(new.len())).take_while(|&i| same(i, i)).count();
+    let suffix = (0..old.len().min(new.len()) - prefix)
+        .take_while(|&i| same(old.len() - 1 - i, new.len() - 1 - i))
+        .count();
+    let old_mid = prefix..old.len() - suffix;
+    let new_mid = prefix..new.len() - suffix;
+
+    let mut edits = Vec::new();
+
+    // Items that kept their identity but changed (only possible when keyed)
+    let kept = (0..prefix).map(|i| (i, i)).chain((0..suffix).map(|i| (old.len() - 1 - i, new.len() - 1 - i)));
+    let mut updates: Vec<(usize, usize)> = kept.filter(|&(i, j)| old[i] != new[j]).collect();
+    updates.sort_by_key(|&(_, j)| j);
+
+    if !old_mid.is_empty() && !new_mid.is_empty() {
+        match &keys {
+            Some((old_keys, new_keys)) => {
+                let removed: HashSet<&String> = old_keys[old_mid.clone()].iter().collect();
+                let inserted: HashSet<&String> = new_keys[new_mid.clone()].iter().collect();
+                if removed == inserted {
+                    return None;
+                }
+            }
+            // Unkeyed items replaced one for one are updates in place
+            None if old_mid.len() == new_mid.len() => {
+                updates.extend(old_mid.clone().zip(new_mid.clone()));
+                updates.sort_by_key(|&(_, j)| j);
+                return Some(updates.into_iter().map(|(_, j)| update(j, None, new)).collect());
+            }
+            None => {}
+        }
+    }
+
+    let removed_keys: Vec<String> = old_mid.clone().filter_map(|i| key_of(i, true)).collect();
+    let inserted: Vec<Value> = new[new_mid.clone()].to_vec();
+    let at_end = suffix == 0;
+    let at_start = prefix == 0;
+
+    let structural = match (old_mid.is_empty(), new_mid.is_empty()) {
+        (true, true) => None,
+        (true, false) if at_end => Some(ArrayEdit::Push { items: inserted }),
+        (true, false) if at_start => Some(ArrayEdit::Unshift { items: inserted }),
+        (false, true) if at_end => Some(ArrayEdit::Pop { count: old_mid.len(), removed_keys }),
+        (false, true) if at_start => Some(ArrayEdit::Shift { count: old_mid.len(), removed_keys }),
+        _ => Some(ArrayEdit::Splice {
+            index: prefix,
+            remove: old_mid.len(),
+            removed_keys,
+            insert: inserted,
+        }),
+    };
+
+    edits.extend(updates.into_iter().map(|(_, j)| update(j, key_of(j, false), new)));
+    edits.extend(structural);
+    Some(edits)
+}
+
+impl ArrayEdit {
+    /// Items this edit adds to the array (empty for removals and updates)
+    pub fn inserted(&self) -> &[Value] {
+        match self {
+            ArrayEdit::Push { items } | ArrayEdit::Unshift { items } => items,
+            ArrayEdit::Splice { insert, .. } => insert,
+            _ => &[],
+        }
+    }
+}
+
+fn update(index: usize, key: Option<String>, new: &[Value]) -> ArrayEdit {
+    ArrayEdit::Update { index, key, item: new[index].clone() }
+}
+
+/// The single-item ArrayOperation for a change, if it is exactly one push,
+/// unshift, insert, removal or update of one item
+pub fn infer_array_operation(old: &Value, new: &Value) -> Option<ArrayOperation> {
+    let edits = diff_arrays(old, new)?;
+    let [edit] = edits.as_slice() else { return None };
+    let old_len = old.as_array()?.len();
+
+    match edit {
+        ArrayEdit::Push { items } if items.len() == 1 => Some(ArrayOperation::Append { item: items[0].clone() }),
+        ArrayEdit::Unshift { items } if items.len() == 1 => Some(ArrayOperation::Prepend { item: items[0].clone() }),
+        ArrayEdit::Pop { count: 1, .. } => Some(ArrayOperation::RemoveAt { index: old_len - 1 }),
+        ArrayEdit::Shift { count: 1, .. } => Some(ArrayOperation::RemoveAt { index: 0 }),
+        ArrayEdit::Splice { index, remove: 0, insert, .. } if insert.len() == 1 => {
+            Some(ArrayOperation::InsertAt { index: *index, item: insert[0].clone() })
+        }
+        ArrayEdit::Splice { index, remove: 1, insert, .. } if insert.is_empty() => Some(ArrayOperation::RemoveAt { index: *index }),
+        ArrayEdit::Update { index, item, .. } => Some(ArrayOperation::UpdateAt { index: *index, item: item.clone() }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn todos(ids: &[u32]) -> Value {
+        Value::Array(ids.iter().map(|id| json!({ "id": id, "text": format!("todo {}", id) })).collect())
+    }
+
+    #[test]
+    fn test_classifies_list_mutations() {
+        let base = todos(&[1, 2, 3]);
+        let edit = |new: Value| diff_arrays(&base, &new).unwrap();
+
+        assert!(matches!(&edit(todos(&[1, 2, 3, 4, 5]))[..], [ArrayEdit::Push { items }] if items.len() == 2));
+        assert!(matches!(&edit(todos(&[0, 1, 2, 3]))[..], [ArrayEdit::Unshift { items }] if items.len() == 1));
+        assert_eq!(edit(todos(&[1])), vec![ArrayEdit::Pop { count: 2, removed_keys: vec!["2".into(), "3".into()] }]);
+        assert_eq!(edit(todos(&[2, 3])), vec![ArrayEdit::Shift { count: 1, removed_keys: vec!["1".into()] }]);
+        assert!(matches!(
+            &edit(todos(&[1, 9, 3]))[..],
+            [ArrayEdit::Splice { index: 1, remove: 1, insert, .. }] if insert.len() == 1
+        ));
+        assert_eq!(edit(base.clone()), vec![]);
+
+        // Reorders are left to reorder detection
+        assert!(diff_arrays(&base, &todos(&[3, 2, 1])).is_none());
+    }
+
+    #[test]
+    fn test_updates_keep_keys() {
+        let old = todos(&[1, 2, 3]);
+        let mut new = todos(&[1, 2, 3, 4]);
+        new[1]["text"] = json!("done");
+
+        let edits = diff_arrays(&old, &new).unwrap();
+        assert_eq!(edits[0], ArrayEdit::Update { index: 1, key: Some("2".into()), item: new[1].clone() });
+        assert!(matches!(edits[1], ArrayEdit::Push { .. }));
+
+        // Unkeyed values replaced in place are updates too
+        let edits = diff_arrays(&json!(["a", "b", "c"]), &json!(["a", "x", "c"])).unwrap();
+        assert_eq!(edits, vec![ArrayEdit::Update { index: 1, key: None, item: json!("x") }]);
+    }
+
+    #[test]
+    fn test_single_edits_become_array_operations() {
+        let old = todos(&[1, 2, 3]);
+        assert!(matches!(infer_array_operation(&old, &todos(&[1, 2, 3, 4])), Some(ArrayOperation::Append { .. })));
+        assert!(matches!(infer_array_operation(&old, &todos(&[1, 3])), Some(ArrayOperation::RemoveAt { index: 1 })));
+        assert!(matches!(infer_array_operation(&old, &todos(&[1, 2, 5, 3])), Some(ArrayOperation::InsertAt { index: 2, .. })));
+        assert!(infer_array_operation(&old, &todos(&[1, 2, 3, 4, 5])).is_none());
+    }
+}
@@ -0,0 +1,209 @@
+//! Boolean and enumerated attribute semantics
+//!
+//! Props are strings, but many attributes aren't compared as strings by the DOM:
+//! `disabled: "false"` renders like no `disabled` at all (the React convention the
+//! transpiler follows), `disabled: "true"` like `disabled: "disabled"`, and
+//! `contenteditable: ""` means `"true"`. The reconciler compares props through this
+//! table so such semantic no-ops produce no UpdateProps, and drops props that mean
+//! "absent" from the patches it does send (the client replaces the whole prop set, so
+//! a dropped prop is removed from the element).
+//!
+//! Names are looked up case-insensitively (`readOnly` and `readonly` are the same
+//! attribute). The table is process-wide and starts with the HTML built-ins; hosts can
+//! add attributes or override entries, `Plain` turning the semantics off for one.
+
+use crate::error::{FfiResult, MinimactError};
+use crate::last_error::FfiCall;
+use arc_swap::ArcSwap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::Arc;
+
+/// How an attribute's value is interpreted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum AttributeKind {
+    /// Compared verbatim
+    Plain,
+    /// Present or not: `"false"` is the same as absent, any other value as present
+    Boolean,
+    /// One of a set of keywords (case-insensitive)
+    Enumerated {
+        values: Vec<String>,
+        /// Keyword the empty string stands for
+        #[serde(default)]
+        empty: Option<String>,
+        /// Keyword that's the same as leaving the attribute out
+        #[serde(default)]
+        missing: Option<String>,
+    },
+}
+
+/// Attribute name (lowercase) -> semantics
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AttributeTable {
+    attributes: HashMap<String, AttributeKind>,
+}
+
+const BOOLEAN_ATTRIBUTES: &[&str] = &[
+    "allowfullscreen", "async", "autofocus", "autoplay", "checked", "controls", "default", "defer",
+    "disabled", "formnovalidate", "hidden", "inert", "ismap", "itemscope", "loop", "multiple", "muted",
+    "nomodule", "novalidate", "open", "playsinline", "readonly", "required", "reversed", "selected",
+];
+
+lazy_static::lazy_static! {
+    static ref ATTRIBUTE_TABLE: ArcSwap<AttributeTable> = ArcSwap::from_pointee(AttributeTable::builtin());
+}
+
+fn enumerated(values: &[&str], empty: Option<&str>, missing: Option<&str>) -> AttributeKind {
+    AttributeKind::Enumerated {
+        values: values.iter().map(|v| v.to_string()).collect(),
+        empty: empty.map(str::to_string),
+        missing: missing.map(str::to_string),
+    }
+}
+
+impl AttributeTable {
+    /// HTML boolean attributes plus contenteditable, draggable, spellcheck,
+    /// translate, dir and aria-hidden
+    pub fn builtin() -> Self {
+        let mut table = Self::default();
+        for name in BOOLEAN_ATTRIBUTES {
+            table.insert(name, AttributeKind::Boolean);
+        }
+        table.insert("contenteditable", enumerated(&["true", "false", "plaintext-only"], Some("true"), None));
+        table.insert("draggable", enumerated(&["true", "false", "auto"], None, Some("auto")));
+        table.insert("spellcheck", enumerated(&["true", "false"], Some("true"), None));
+        table.insert("translate", enumerated(&["yes", "no"], Some("yes"), None));
+        table.insert("dir", enumerated(&["ltr", "rtl", "auto"], None, None));
+        table.insert("aria-hidden", enumerated(&["true", "false"], None, Some("false")));
+        table
+    }
+
+    /// Add or override an attribute
+    pub fn insert(&mut self, name: &str, kind: AttributeKind) {
+        self.attributes.insert(name.to_lowercase(), kind);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AttributeKind> {
+        self.attributes.get(name).or_else(|| self.attributes.get(&name.to_lowercase()))
+    }
+
+    /// What `value` means for `name`; None when it's the same as leaving it out
+    pub fn canonical_value(&self, name: &str, value: &str) -> Option<String> {
+        match self.get(name) {
+            None | Some(AttributeKind::Plain) => Some(value.to_string()),
+            Some(AttributeKind::Boolean) => (!value.eq_ignore_ascii_case("false")).then(String::new),
+            Some(AttributeKind::Enumerated { values, empty, missing }) => {
+                let mut keyword = value.to_ascii_lowercase();
+                if keyword.is_empty() {
+                    keyword = empty.clone().unwrap_or_default();
+                }
+                if !values.contains(&keyword) {
+                    // Not a keyword: no semantics to go on
+                    return Some(value.to_string());
+                }
+                (missing.as_ref() != Some(&keyword)).then_some(keyword)
+            }
+        }
+    }
+
+    /// Whether two prop sets render the same
+    pub fn props_equivalent(&self, a: &HashMap<String, String>, b: &HashMap<String, String>) -> bool {
+        let same = |name: &String, value: &String, other: &HashMap<String, String>| {
+            self.canonical_value(name, value) == other.get(name).and_then(|v| self.canonical_value(name, v))
+        };
+        a.iter().all(|(name, value)| same(name, value, b)) && b.iter().all(|(name, value)| same(name, value, a))
+    }
+
+    /// `props` without the ones that mean the attribute is absent
+    pub fn strip_absent(&self, mut props: HashMap<String, String>) -> HashMap<String, String> {
+        props.retain(|name, value| self.canonical_value(name, value).is_some());
+        props
+    }
+}
+
+/// Replace the attribute table used by the reconciler (process-wide)
+pub fn set_attribute_table(table: AttributeTable) {
+    ATTRIBUTE_TABLE.store(Arc::new(table));
+}
+
+pub fn attribute_table() -> Arc<AttributeTable> {
+    ATTRIBUTE_TABLE.load_full()
+}
+
+/// Set the attribute table to the built-ins plus the given entries
+/// (`{"data-open": {"kind": "Boolean"}, "hidden": {"kind": "Plain"}}`)
+///
+/// # Safety
+/// - table_json must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_set_attribute_table(table_json: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_set_attribute_table", &[table_json]);
+    let entries = CStr::from_ptr(table_json)
+        .to_str()
+        .map_err(MinimactError::from)
+        .and_then(|json| Ok(serde_json::from_str::<HashMap<String, AttributeKind>>(json)?));
+    match entries {
+        Ok(entries) => {
+            let mut table = AttributeTable::builtin();
+            for (name, kind) in entries {
+                table.insert(&name, kind);
+            }
+            set_attribute_table(table);
+            FfiResult::success()
+        }
+        Err(e) => FfiResult::error(&e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn props(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_semantic_no_ops_are_equivalent() {
+        let table = AttributeTable::builtin();
+        let equivalent = |a: &[(&str, &str)], b: &[(&str, &str)]| table.props_equivalent(&props(a), &props(b));
+
+        assert!(equivalent(&[("disabled", "false"), ("id", "go")], &[("id", "go")]));
+        assert!(equivalent(&[("readOnly", "true")], &[("readOnly", "readonly")]));
+        assert!(equivalent(&[("contenteditable", "")], &[("contenteditable", "TRUE")]));
+        assert!(equivalent(&[("draggable", "auto")], &[]));
+        assert!(!equivalent(&[("disabled", "true")], &[]));
+        assert!(!equivalent(&[("contenteditable", "true")], &[("contenteditable", "plaintext-only")]));
+        assert!(!equivalent(&[("title", "false")], &[]));
+
+        let stripped = table.strip_absent(props(&[("checked", "false"), ("aria-hidden", "false"), ("value", "on")]));
+        assert_eq!(stripped, props(&[("value", "on")]));
+    }
+
+    #[test]
+    fn test_reconcile_skips_semantic_no_ops() {
+        use crate::vdom::{Patch, VNode};
+        let button = |pairs: &[(&str, &str)]| VNode::element("button", props(pairs), vec![]);
+
+        let patches = crate::reconciler::reconcile(&button(&[("disabled", "false")]), &button(&[])).unwrap();
+        assert!(patches.is_empty());
+
+        let patches = crate::reconciler::reconcile(&button(&[("disabled", "true")]), &button(&[("disabled", "false"), ("id", "go")])).unwrap();
+        assert!(matches!(&patches[..], [Patch::UpdateProps { props: sent, .. }] if *sent == props(&[("id", "go")])));
+    }
+
+    #[test]
+    fn test_table_entries_can_be_added_and_disabled() {
+        let mut table = AttributeTable::builtin();
+        table.insert("data-open", AttributeKind::Boolean);
+        table.insert("hidden", AttributeKind::Plain);
+
+        assert!(table.props_equivalent(&props(&[("data-open", "false")]), &props(&[])));
+        assert!(!table.props_equivalent(&props(&[("hidden", "false")]), &props(&[])));
+    }
+}
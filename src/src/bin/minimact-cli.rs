@@ -0,0 +1,170 @@
+//! Exercise the engine from the command line
+//!
+//! Usage:
+//!   minimact-cli diff OLD_TREE.json NEW_TREE.json
+//!   minimact-cli validate TREE.json PATCHES.json
+//!   minimact-cli materialize TEMPLATE_PATCHES.json STATE.json
+//!   minimact-cli bench [--tree-size N] [--changes N] [--iterations N]
+//!
+//! Prints JSON to stdout. `validate` exits 1 when any patch fails; usage and input
+//! errors exit 2.
+
+use minimact::{
+    negotiate_patches, reconcile, validate_patches_detailed, ClientCapabilities, HexPath, Patch,
+    PatchValidatorConfig, VNode,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::process::ExitCode;
+use std::time::Instant;
+
+const USAGE: &str = "Usage: minimact-cli <diff OLD NEW | validate TREE PATCHES | materialize TEMPLATES STATE | bench [--tree-size N] [--changes N] [--iterations N]>";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["diff", old, new] => diff(old, new),
+        ["validate", tree, patches] => validate(tree, patches),
+        ["materialize", templates, state] => materialize(templates, state),
+        ["bench", options @ ..] => bench(options),
+        _ => Err(USAGE.to_string()),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn load<T: DeserializeOwned>(file: &str) -> Result<T, String> {
+    std::fs::read_to_string(file)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+        .map_err(|e| format!("Can't load '{}': {}", file, e))
+}
+
+fn print(value: &impl Serialize) {
+    println!("{}", serde_json::to_string_pretty(value).expect("output serializes"));
+}
+
+/// Patches turning one tree into the other
+fn diff(old: &str, new: &str) -> Result<ExitCode, String> {
+    let (old, new): (VNode, VNode) = (load(old)?, load(new)?);
+    let patches = reconcile(&old, &new).map_err(|e| format!("Reconcile failed: {}", e))?;
+    print(&patches);
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Per-patch diagnostics for a batch against the tree it targets
+fn validate(tree: &str, patches: &str) -> Result<ExitCode, String> {
+    let (tree, patches): (VNode, Vec<Patch>) = (load(tree)?, load(patches)?);
+    let report = validate_patches_detailed(&patches, &tree, &PatchValidatorConfig::default());
+    print(&report);
+    Ok(if report.valid { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+}
+
+/// Template patches rendered to concrete ones with the given state values
+fn materialize(templates: &str, state: &str) -> Result<ExitCode, String> {
+    let (templates, state): (Vec<Patch>, HashMap<String, serde_json::Value>) = (load(templates)?, load(state)?);
+    print(&negotiate_patches(templates, &ClientCapabilities::baseline(), &state));
+    Ok(ExitCode::SUCCESS)
+}
+
+#[derive(Serialize)]
+struct BenchReport {
+    tree_size: usize,
+    changes: usize,
+    iterations: usize,
+    patches: usize,
+    mean_us: u64,
+    p50_us: u64,
+    p95_us: u64,
+    max_us: u64,
+}
+
+/// Time reconciling a generated tree against a copy with `changes` edited items
+fn bench(options: &[&str]) -> Result<ExitCode, String> {
+    let (mut tree_size, mut changes, mut iterations) = (1000, 1, 100);
+    for pair in options.chunks(2) {
+        let value = |v: Option<&&str>| v.and_then(|v| v.parse::<usize>().ok()).ok_or_else(|| USAGE.to_string());
+        match pair[0] {
+            "--tree-size" => tree_size = value(pair.get(1))?,
+            "--changes" => changes = value(pair.get(1))?,
+            "--iterations" => iterations = value(pair.get(1))?.max(1),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+
+    let old = tree(tree_size, 0);
+    let new = tree(tree_size, changes);
+    let mut samples = Vec::with_capacity(iterations);
+    let mut patches = 0;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        patches = reconcile(&old, &new).map_err(|e| format!("Reconcile failed: {}", e))?.len();
+        samples.push(start.elapsed().as_micros() as u64);
+    }
+    samples.sort_unstable();
+
+    let at = |p: f64| samples[((samples.len() - 1) as f64 * p).round() as usize];
+    print(&BenchReport {
+        tree_size,
+        changes,
+        iterations,
+        patches,
+        mean_us: samples.iter().sum::<u64>() / iterations as u64,
+        p50_us: at(0.5),
+        p95_us: at(0.95),
+        max_us: at(1.0),
+    });
+    Ok(ExitCode::SUCCESS)
+}
+
+/// About `size` nodes: <li> items and their text, nested in <div>s of at most
+/// FANOUT children (hex paths give each parent 15 child slots); the first `edited`
+/// items changed
+fn tree(size: usize, edited: usize) -> VNode {
+    let mut tree = group(0..size.div_ceil(2).max(1), edited);
+    assign_paths(&mut tree, HexPath::root());
+    tree
+}
+
+const FANOUT: usize = 8;
+
+fn group(items: std::ops::Range<usize>, edited: usize) -> VNode {
+    let children = if items.len() <= FANOUT {
+        items
+            .map(|i| {
+                let text = if i < edited { format!("Item {} (edited)", i) } else { format!("Item {}", i) };
+                Some(VNode::element("li", HashMap::new(), vec![Some(VNode::text(text))]))
+            })
+            .collect()
+    } else {
+        let chunk = items.len().div_ceil(FANOUT);
+        (items.start..items.end)
+            .step_by(chunk)
+            .map(|start| Some(group(start..(start + chunk).min(items.end), edited)))
+            .collect()
+    };
+    VNode::element("div", HashMap::new(), children)
+}
+
+fn assign_paths(node: &mut VNode, path: HexPath) {
+    match node {
+        VNode::Element(element) => {
+            for (index, child) in element.children.iter_mut().enumerate() {
+                if let Some(child) = child {
+                    assign_paths(child, path.child(index));
+                }
+            }
+            element.path = path;
+        }
+        VNode::Text(text) => text.path = path,
+        VNode::Null(null) => null.path = path,
+        VNode::Lazy(lazy) => lazy.path = path,
+    }
+}
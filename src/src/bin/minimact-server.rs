@@ -0,0 +1,16 @@
+//! Run the engine as an HTTP sidecar
+//!
+//! Usage: minimact-server [ADDR] (default 127.0.0.1:7878)
+
+use minimact::server::serve;
+use minimact::ValidationConfig;
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let addr = std::env::args().nth(1).unwrap_or_else(|| "127.0.0.1:7878".to_string());
+    let addr = addr
+        .parse()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Invalid address '{}': {}", addr, e)))?;
+    minimact::enable_logging();
+    serve(addr, ValidationConfig::default()).await
+}
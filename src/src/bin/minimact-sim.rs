@@ -0,0 +1,46 @@
+//! Apply patch batches to a tree and print the resulting HTML
+//!
+//! Usage: minimact-sim TREE_JSON_FILE < batches.jsonl
+//! Each stdin line is one batch (a JSON array of patches). Prints the page after
+//! every batch; exits non-zero on the first batch the client would reject.
+
+use minimact::sim::Simulator;
+use std::io::BufRead;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let Some(tree_file) = std::env::args().nth(1) else {
+        eprintln!("Usage: minimact-sim TREE_JSON_FILE < batches.jsonl");
+        return ExitCode::from(2);
+    };
+    let mut sim = match std::fs::read_to_string(&tree_file).map_err(|e| e.to_string()).and_then(|json| {
+        Simulator::from_json(&json).map_err(|e| e.to_string())
+    }) {
+        Ok(sim) => sim,
+        Err(e) => {
+            eprintln!("Can't load '{}': {}", tree_file, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    println!("{}", sim.html());
+    for (line_no, line) in std::io::stdin().lock().lines().enumerate() {
+        let line = match line {
+            Ok(line) if line.trim().is_empty() => continue,
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("Can't read stdin: {}", e);
+                return ExitCode::FAILURE;
+            }
+        };
+        if let Err(e) = sim.apply_batch_json(&line) {
+            eprintln!("Batch on line {} rejected: {}", line_no + 1, e);
+            return ExitCode::FAILURE;
+        }
+        if let Some(url) = sim.location() {
+            println!("<!-- location: {} -->", url);
+        }
+        println!("{}", sim.html());
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,35 @@
+//! Run a synthetic workload through a fresh predictor and print the report
+//!
+//! Usage: minimact-workload [CONFIG_JSON_FILE]
+//! The config is a WorkloadConfig; missing fields (or no file) use the defaults.
+//! Prints the WorkloadReport as JSON.
+
+use minimact::{run_workload, Predictor, WorkloadConfig};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let config = match std::env::args().nth(1) {
+        None => WorkloadConfig::default(),
+        Some(file) => match std::fs::read_to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+        {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Can't load '{}': {}", file, e);
+                return ExitCode::from(2);
+            }
+        },
+    };
+
+    match run_workload(&mut Predictor::new(), &config) {
+        Ok(report) => {
+            println!("{}", serde_json::to_string_pretty(&report).expect("report serializes"));
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Workload failed: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,399 @@
+//! Client capability negotiation
+//!
+//! Older clients don't understand newer patch variants (template patches,
+//! ReorderTemplate, ReplaceConditional, ...). The host declares which patch kinds a
+//! client supports, and unsupported patches are downgraded into baseline patches
+//! before emission - templates are materialized against the current state the same
+//! way the client's TemplateRenderer would.
+//!
+//! Baseline kinds (Create, Remove, Replace, UpdateText, UpdateProps, ReorderChildren)
+//! are always supported. Predictor output is negotiated with the predictor's
+//! capabilities; the reconciler's own newer kinds (UpdateListWindow, UpdateDocument,
+//! SetIgnored, InvalidateLayout, Lifecycle) with `ReconcileStrategy::capabilities`.
+//!
+//! Patches that can't be downgraded (e.g. their bindings aren't in the provided state)
+//! are dropped - predictions are optimistic, and the authoritative reconcile corrects
+//! the DOM afterwards.
+
+use crate::path::HexPath;
+use crate::reorder_detection::{materialize_reorder, OrderingRule};
+use crate::template_renderer::{self, StateValues};
+use crate::vdom::Patch;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+
+/// Patch kinds every client understands
+pub const BASELINE_PATCH_KINDS: [&str; 6] = [
+    "Create",
+    "Remove",
+    "Replace",
+    "UpdateText",
+    "UpdateProps",
+    "ReorderChildren",
+];
+
+/// Every patch kind this version can emit
+pub const ALL_PATCH_KINDS: [&str; 20] = [
+    "Create",
+    "Remove",
+    "Replace",
+    "UpdateText",
+    "UpdateProps",
+    "ReorderChildren",
+    "UpdateTextTemplate",
+    "UpdatePropsTemplate",
+    "UpdateListTemplate",
+    "ReorderTemplate",
+    "ReplaceConditional",
+    "UpdateAttributeStatic",
+    "UpdateAttributeDynamic",
+    "UpdateListWindow",
+    "UpdateDocument",
+    "SetIgnored",
+    "InvalidateLayout",
+    "Lifecycle",
+    "Navigate",
+    "Custom",
+];
+
+/// Patch kinds a client declared support for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientCapabilities {
+    /// Supported patch kinds (serialized "type" tags)
+    #[serde(rename = "supportedPatches")]
+    pub supported_patches: BTreeSet<String>,
+}
+
+impl ClientCapabilities {
+    /// A client supporting every patch kind (no downgrading)
+    pub fn all() -> Self {
+        Self::from_kinds(ALL_PATCH_KINDS)
+    }
+
+    /// A client supporting only the baseline patch kinds
+    pub fn baseline() -> Self {
+        Self::from_kinds(BASELINE_PATCH_KINDS)
+    }
+
+    /// Build from a list of supported kinds (baseline kinds are always included)
+    pub fn from_kinds<I, S>(kinds: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut supported_patches: BTreeSet<String> = kinds.into_iter().map(Into::into).collect();
+        supported_patches.extend(BASELINE_PATCH_KINDS.iter().map(|k| k.to_string()));
+        Self { supported_patches }
+    }
+
+    /// Check if a patch kind is supported
+    pub fn supports(&self, kind: &str) -> bool {
+        BASELINE_PATCH_KINDS.contains(&kind) || self.supported_patches.contains(kind)
+    }
+}
+
+impl Default for ClientCapabilities {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// What negotiation does with a single patch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status")]
+pub enum PatchApplicability {
+    /// Sent as-is
+    Supported,
+    /// Replaced by concrete patches of the listed kinds
+    Downgraded { into: Vec<String> },
+    /// Not sent
+    Dropped { reason: String },
+}
+
+/// Applicability of one input patch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatchReport {
+    pub kind: String,
+    pub path: HexPath,
+    #[serde(flatten)]
+    pub applicability: PatchApplicability,
+}
+
+/// Dry-run report of how a patch set would be negotiated for a client
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApplicabilityReport {
+    pub patches: Vec<PatchReport>,
+    pub supported: usize,
+    pub downgraded: usize,
+    pub dropped: usize,
+}
+
+impl ApplicabilityReport {
+    /// Check if the client can apply every patch as-is
+    pub fn is_fully_supported(&self) -> bool {
+        self.downgraded == 0 && self.dropped == 0
+    }
+}
+
+/// Downgrade unsupported patches for a client
+///
+/// `state` holds the current (post-change) state values used to materialize templates.
+pub fn negotiate_patches(patches: Vec<Patch>, capabilities: &ClientCapabilities, state: &StateValues) -> Vec<Patch> {
+    negotiate_patches_with_report(patches, capabilities, state).0
+}
+
+/// Same as `negotiate_patches`, also returning the per-patch report
+pub fn negotiate_patches_with_report(
+    patches: Vec<Patch>,
+    capabilities: &ClientCapabilities,
+    state: &StateValues,
+) -> (Vec<Patch>, ApplicabilityReport) {
+    let mut output = Vec::with_capacity(patches.len());
+    let mut report = ApplicabilityReport::default();
+
+    for patch in patches {
+        let kind = patch.kind().to_string();
+        let path = patch.path().clone();
+
+        let applicability = if capabilities.supports(&kind) {
+            report.supported += 1;
+            output.push(patch);
+            PatchApplicability::Supported
+        } else {
+            match downgrade(&patch, state) {
+                Ok(concrete) => {
+                    report.downgraded += 1;
+                    let into = concrete.iter().map(|p| p.kind().to_string()).collect();
+                    output.extend(concrete);
+                    PatchApplicability::Downgraded { into }
+                }
+                Err(reason) => {
+                    crate::log_debug!("Dropping unsupported {} at '{}': {}", kind, path, reason);
+                    report.dropped += 1;
+                    PatchApplicability::Dropped { reason }
+                }
+            }
+        };
+
+        report.patches.push(PatchReport { kind, path, applicability });
+    }
+
+    (output, report)
+}
+
+/// Dry run: report how a patch set would be negotiated without producing it
+pub fn applicability_report(patches: &[Patch], capabilities: &ClientCapabilities, state: &StateValues) -> ApplicabilityReport {
+    negotiate_patches_with_report(patches.to_vec(), capabilities, state).1
+}
+
+/// Render template patches whose bindings resolve in `state` into baseline patches
+/// Everything else is kept as it is
+pub fn materialize_templates(patches: Vec<Patch>, state: &StateValues) -> Vec<Patch> {
+    let mut output = Vec::with_capacity(patches.len());
+    for patch in patches {
+        if BASELINE_PATCH_KINDS.contains(&patch.kind()) {
+            output.push(patch);
+            continue;
+        }
+        match downgrade(&patch, state) {
+            Ok(concrete) => output.extend(concrete),
+            Err(_) => output.push(patch),
+        }
+    }
+    output
+}
+
+/// Convert a non-baseline patch into equivalent baseline patches
+fn downgrade(patch: &Patch, state: &StateValues) -> Result<Vec<Patch>, String> {
+    let path = patch.path().clone();
+
+    match patch {
+        Patch::UpdateTextTemplate { template_patch, .. } => {
+            require_bindings(&template_renderer::binding_keys(template_patch), state)?;
+            Ok(vec![Patch::UpdateText {
+                path,
+                content: template_renderer::render_template_patch(template_patch, state),
+            }])
+        }
+        Patch::UpdatePropsTemplate { prop_name, template_patch, .. }
+        | Patch::UpdateAttributeDynamic { attr_name: prop_name, template_patch, .. } => {
+            require_bindings(&template_renderer::binding_keys(template_patch), state)?;
+            let value = template_renderer::render_template_patch(template_patch, state);
+            Ok(vec![Patch::UpdateProps {
+                path,
+                props: HashMap::from([(prop_name.clone(), value)]),
+            }])
+        }
+        Patch::UpdateAttributeStatic { attr_name, value, .. } => Ok(vec![Patch::UpdateProps {
+            path,
+            props: HashMap::from([(attr_name.clone(), value.clone())]),
+        }]),
+        Patch::UpdateListTemplate { loop_template, .. } => {
+            require_bindings(std::slice::from_ref(&loop_template.array_binding), state)?;
+            let items = template_renderer::render_loop_template(loop_template, state);
+            Ok(template_renderer::loop_items_to_patches(&path, items))
+        }
+        Patch::ReorderTemplate { reorder_template, .. } => {
+            let binding = &reorder_template.array_binding;
+            match (&reorder_template.ordering, state.get(binding)) {
+                // A stored order needs no state
+                (OrderingRule::Custom { key_order }, None) => {
+                    Ok(vec![Patch::ReorderChildren { path, order: key_order.clone(), preserve: None }])
+                }
+                // Post-change state already holds the array reversed; reversing it again would undo that
+                (OrderingRule::Reverse, Some(Value::Array(items))) => {
+                    let order = crate::reorder_detection::extract_key_order(items)
+                        .ok_or_else(|| format!("items of '{}' have no keys", binding))?;
+                    Ok(vec![Patch::ReorderChildren { path, order, preserve: None }])
+                }
+                // Sorting an already sorted array keeps its order; filters are rejected
+                (_, Some(array)) => materialize_reorder(&path, reorder_template, array)
+                    .map(|patch| vec![patch])
+                    .map_err(|e| e.to_string()),
+                (_, None) => Err(format!("missing array state '{}'", binding)),
+            }
+        }
+        Patch::ReplaceConditional { structural_template, .. } => {
+            let binding = &structural_template.condition_binding;
+            let value = state.get(binding).ok_or_else(|| format!("missing state '{}'", binding))?;
+            let node = structural_template
+                .branches
+                .get(&template_renderer::condition_key(value))
+                .or(structural_template.default_branch.as_deref())
+                .ok_or_else(|| format!("no branch for {} = {}", binding, value))?;
+            Ok(vec![Patch::Replace { path, node: node.clone(), preserve: None }])
+        }
+        // The visible rows were sent as concrete patches; only the window is lost
+        Patch::UpdateListWindow { .. } => Err("client doesn't virtualize lists".to_string()),
+        // The server stops patching inside either way; unmarking is followed by a Replace
+        Patch::SetIgnored { .. } => Err("client doesn't track client-owned regions".to_string()),
+        // Advisory: nothing to render
+        Patch::InvalidateLayout { .. } => Err("client doesn't take layout hints".to_string()),
+        // Markers change nothing; the client just doesn't run the effects
+        Patch::Lifecycle { .. } => Err("client doesn't run lifecycle effects".to_string()),
+        Patch::Navigate { .. } => Err("client doesn't navigate on patches".to_string()),
+        Patch::Custom { kind, .. } => Err(format!("client doesn't handle custom '{}' patches", kind)),
+        Patch::UpdateDocument { target, value, .. } => Ok(vec![match value {
+            Some(value) => Patch::Replace { node: target.element(&path, value), path, preserve: None },
+            None => Patch::Remove { path },
+        }]),
+        // Baseline kinds are always supported
+        _ => Ok(vec![patch.clone()]),
+    }
+}
+
+fn require_bindings(keys: &[String], state: &StateValues) -> Result<(), String> {
+    let missing: Vec<&str> = keys
+        .iter()
+        .filter(|key| !state.contains_key(key.as_str()))
+        .map(|key| key.as_str())
+        .collect();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("missing state for bindings: {}", missing.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reorder_detection::{OrderingRule, ReorderTemplate};
+    use crate::vdom::{StructuralTemplate, TemplatePatch, VNode};
+    use serde_json::json;
+
+    fn text_template(path: &str) -> Patch {
+        Patch::UpdateTextTemplate {
+            path: HexPath::from(path),
+            template_patch: TemplatePatch {
+                template: "Count: {0}".to_string(),
+                bindings: vec!["count".to_string()],
+                bindings_with_transforms: None,
+                slots: vec![7],
+                conditional_templates: None,
+                conditional_binding_index: None,
+            },
+        }
+    }
+
+    fn state(pairs: &[(&str, Value)]) -> StateValues {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.clone())).collect()
+    }
+
+    #[test]
+    fn test_supported_patches_pass_through() {
+        let patches = vec![text_template("10000000")];
+        let out = negotiate_patches(patches.clone(), &ClientCapabilities::all(), &StateValues::new());
+        assert_eq!(out, patches);
+    }
+
+    #[test]
+    fn test_text_template_materialized() {
+        let (out, report) = negotiate_patches_with_report(
+            vec![text_template("10000000")],
+            &ClientCapabilities::baseline(),
+            &state(&[("count", json!(42))]),
+        );
+
+        assert_eq!(out, vec![Patch::UpdateText {
+            path: HexPath::from("10000000"),
+            content: "Count: 42".to_string(),
+        }]);
+        assert_eq!(report.downgraded, 1);
+        assert_eq!(report.patches[0].applicability, PatchApplicability::Downgraded { into: vec!["UpdateText".to_string()] });
+    }
+
+    #[test]
+    fn test_filter_reorder_is_dropped() {
+        let filter = Patch::ReorderTemplate {
+            path: HexPath::from("10000000"),
+            reorder_template: ReorderTemplate {
+                array_binding: "items".to_string(),
+                ordering: OrderingRule::Filter { property: "done".to_string(), value: json!(true) },
+            },
+        };
+        let values = state(&[("items", json!([{ "id": 1, "done": true }]))]);
+
+        let report = applicability_report(&[filter], &ClientCapabilities::baseline(), &values);
+        assert_eq!(report.dropped, 1);
+    }
+
+    #[test]
+    fn test_missing_state_drops_patch() {
+        let report = applicability_report(&[text_template("10000000")], &ClientCapabilities::baseline(), &StateValues::new());
+        assert_eq!(report.dropped, 1);
+        assert!(!report.is_fully_supported());
+    }
+
+    #[test]
+    fn test_conditional_and_reorder_downgrades() {
+        let conditional = Patch::ReplaceConditional {
+            path: HexPath::from("10000000"),
+            structural_template: StructuralTemplate {
+                condition_binding: "isLoggedIn".to_string(),
+                branches: HashMap::from([
+                    ("true".to_string(), VNode::text("Welcome")),
+                    ("false".to_string(), VNode::text("Login")),
+                ]),
+                default_branch: None,
+            },
+        };
+        let reorder = Patch::ReorderTemplate {
+            path: HexPath::from("20000000"),
+            reorder_template: ReorderTemplate {
+                array_binding: "items".to_string(),
+                ordering: OrderingRule::Reverse,
+            },
+        };
+        let caps = ClientCapabilities::from_kinds(["UpdateTextTemplate"]);
+        let values = state(&[("isLoggedIn", json!(false)), ("items", json!([{ "id": 2 }, { "id": 1 }]))]);
+
+        let out = negotiate_patches(vec![conditional, reorder], &caps, &values);
+        assert_eq!(out, vec![
+            Patch::Replace { path: HexPath::from("10000000"), node: VNode::text("Login"), preserve: None },
+            Patch::ReorderChildren { path: HexPath::from("20000000"), order: vec!["2".to_string(), "1".to_string()], preserve: None },
+        ]);
+    }
+}
@@ -0,0 +1,184 @@
+//! Tree checksums and drift detection
+//!
+//! The client periodically reports a checksum computed from its live DOM. The server
+//! computes the same checksum over its VNode tree and only sends resync patches when
+//! the two disagree, instead of blindly re-sending whole subtrees.
+//!
+//! The checksum only covers what the client can observe in the DOM, so both sides can
+//! compute it independently. It is 64-bit FNV-1a over this canonical byte stream:
+//! - Element: `E` tag `\0`, then each prop sorted by name as name `=` value `\0`,
+//!   then `[`, the children, `]`
+//! - Text: `T` content `\0`
+//! - Null children are skipped (they render nothing)
+//!
+//! Paths and keys are deliberately excluded - they are server-side bookkeeping.
+
+use crate::error::Result;
+use crate::path::HexPath;
+use crate::resync::{generate_resync_message, ResyncMessage};
+use crate::vdom::VNode;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Incremental 64-bit FNV-1a hasher
+pub(crate) struct Fnv64(u64);
+
+impl Fnv64 {
+    pub(crate) fn new() -> Self {
+        Fnv64(FNV_OFFSET_BASIS)
+    }
+
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Compute the structural checksum of a tree (see module docs for the exact format)
+pub fn tree_checksum(node: &VNode) -> u64 {
+    let mut hasher = Fnv64::new();
+    hash_node(node, &mut hasher);
+    hasher.0
+}
+
+/// Format a checksum the way it travels in JSON (16 hex digits - u64 doesn't fit a JS number)
+pub fn checksum_to_hex(checksum: u64) -> String {
+    format!("{:016x}", checksum)
+}
+
+fn hash_node(node: &VNode, hasher: &mut Fnv64) {
+    match node {
+        VNode::Element(el) => {
+            hasher.write(b"E");
+            hasher.write(el.tag.as_bytes());
+            hasher.write(b"\0");
+
+            let mut props: Vec<(&String, &String)> = el.props.iter().collect();
+            props.sort();
+            for (name, value) in props {
+                hasher.write(name.as_bytes());
+                hasher.write(b"=");
+                hasher.write(value.as_bytes());
+                hasher.write(b"\0");
+            }
+
+            hasher.write(b"[");
+            for child in el.children.iter().flatten() {
+                hash_node(child, hasher);
+            }
+            hasher.write(b"]");
+        }
+        VNode::Text(text) => {
+            hasher.write(b"T");
+            hasher.write(text.content.as_bytes());
+            hasher.write(b"\0");
+        }
+        VNode::Null(_) => {}
+        VNode::Lazy(lazy) => {
+            hasher.write(b"L");
+            hasher.write(lazy.summary.as_bytes());
+            hasher.write(b"\0");
+        }
+    }
+}
+
+/// Compare a client-reported checksum against the subtree at `path`
+///
+/// Returns `None` when they match, or the resync message to send when they differ.
+pub fn check_drift(tree: &VNode, path: &HexPath, client_checksum: u64) -> Result<Option<ResyncMessage>> {
+    let server_checksum = match tree.find_by_path(path) {
+        Some(node) => tree_checksum(node),
+        // Path is gone on the server - the client definitely has stale DOM there
+        None => !client_checksum,
+    };
+
+    if server_checksum == client_checksum {
+        return Ok(None);
+    }
+
+    crate::log_warn!(
+        "Drift detected at '{}': server {} vs client {}",
+        path,
+        checksum_to_hex(server_checksum),
+        checksum_to_hex(client_checksum)
+    );
+
+    generate_resync_message(tree, path).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vdom::{VElement, VNull, VText};
+    use std::collections::HashMap;
+
+    fn tree(text: &str, with_null: bool) -> VNode {
+        let mut props = HashMap::new();
+        props.insert("class".to_string(), "counter".to_string());
+        props.insert("id".to_string(), "c1".to_string());
+
+        let mut children = vec![Some(VNode::Text(VText {
+            content: text.to_string(),
+            path: HexPath::from("10000000.10000000"),
+        }))];
+        if with_null {
+            children.push(Some(VNode::Null(VNull { path: HexPath::from("10000000.20000000") })));
+            children.push(None);
+        }
+
+        VNode::Element(VElement {
+            tag: "div".to_string(),
+            props,
+            children,
+            key: Some("k".to_string()),
+            path: HexPath::from("10000000"),
+            source: None,
+        })
+    }
+
+    #[test]
+    fn test_checksum_ignores_nulls_paths_and_keys() {
+        let a = tree("Count: 1", false);
+        let b = tree("Count: 1", true);
+        assert_eq!(tree_checksum(&a), tree_checksum(&b));
+
+        let c = VNode::element("div", match &a { VNode::Element(el) => el.props.clone(), _ => unreachable!() },
+            vec![Some(VNode::text("Count: 1"))]);
+        assert_eq!(tree_checksum(&a), tree_checksum(&c));
+    }
+
+    #[test]
+    fn test_checksum_changes_with_content() {
+        assert_ne!(tree_checksum(&tree("Count: 1", false)), tree_checksum(&tree("Count: 2", false)));
+    }
+
+    #[test]
+    fn test_known_value() {
+        // FNV-1a("T" "a" "\0") - pinned so client implementations can check against it
+        assert_eq!(checksum_to_hex(tree_checksum(&VNode::text("a"))), checksum_to_hex({
+            let mut h = Fnv64::new();
+            h.write(b"Ta\0");
+            h.0
+        }));
+        assert_eq!(checksum_to_hex(Fnv64::new().0), "cbf29ce484222325");
+    }
+
+    #[test]
+    fn test_check_drift() {
+        let server = tree("Count: 2", false);
+        let client = tree("Count: 1", false);
+        let path = HexPath::from("10000000");
+
+        assert!(check_drift(&server, &path, tree_checksum(&server)).unwrap().is_none());
+
+        let resync = check_drift(&server, &path, tree_checksum(&client)).unwrap().unwrap();
+        assert_eq!(resync.root_path, path);
+    }
+}
@@ -0,0 +1,126 @@
+//! Injectable time source
+//!
+//! Pattern ages (LRU and oldest-first eviction), metrics uptime and log timestamps
+//! read the time through a `Clock` instead of `Instant::now`, so tests can move time
+//! forward and check eviction precisely. Predictors take the process-wide clock when
+//! they are created (or one given with `Predictor::set_clock`).
+//!
+//! A MockClock starts at the real instant it was created and only moves when
+//! advanced, so instants taken from the system clock before a swap may be later
+//! than its `now`. Ages are measured with `saturating_duration_since`, so such an
+//! instant reads as age 0.
+
+use arc_swap::ArcSwap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A source of the current instant
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// The real monotonic clock
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that stands still until advanced
+#[derive(Debug)]
+pub struct MockClock {
+    start: Instant,
+    offset_ns: AtomicU64,
+}
+
+impl MockClock {
+    pub fn new() -> Self {
+        Self { start: Instant::now(), offset_ns: AtomicU64::new(0) }
+    }
+
+    /// Move time forward by `duration`
+    pub fn advance(&self, duration: Duration) {
+        self.offset_ns.fetch_add(duration.as_nanos() as u64, Ordering::SeqCst);
+    }
+
+    /// Time advanced since the clock was created
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.offset_ns.load(Ordering::SeqCst))
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref CLOCK: ArcSwap<Arc<dyn Clock>> = ArcSwap::from_pointee(Arc::new(SystemClock) as Arc<dyn Clock>);
+    /// Mock installed through the FFI, so hosts can advance it
+    static ref FFI_MOCK: ArcSwap<Option<Arc<MockClock>>> = ArcSwap::from_pointee(None);
+}
+
+/// Replace the process-wide clock (used by predictors created afterwards, metrics and logs)
+pub fn set_clock(clock: Arc<dyn Clock>) {
+    CLOCK.store(Arc::new(clock));
+}
+
+/// The process-wide clock
+pub fn clock() -> Arc<dyn Clock> {
+    Arc::clone(&CLOCK.load())
+}
+
+/// The current instant of the process-wide clock
+pub fn now() -> Instant {
+    CLOCK.load().now()
+}
+
+/// Switch the process-wide clock between a fresh MockClock and the system clock
+#[no_mangle]
+pub extern "C" fn minimact_clock_use_mock(enabled: bool) {
+    if enabled {
+        let mock = Arc::new(MockClock::new());
+        set_clock(Arc::clone(&mock) as Arc<dyn Clock>);
+        FFI_MOCK.store(Arc::new(Some(mock)));
+    } else {
+        set_clock(Arc::new(SystemClock));
+        FFI_MOCK.store(Arc::new(None));
+    }
+}
+
+/// Advance the mock installed by minimact_clock_use_mock; returns false if none is installed
+#[no_mangle]
+pub extern "C" fn minimact_clock_advance(ms: u64) -> bool {
+    match FFI_MOCK.load().as_ref() {
+        Some(mock) => {
+            mock.advance(Duration::from_millis(ms));
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_only_moves_when_advanced() {
+        let clock = MockClock::new();
+        let before = clock.now();
+        assert_eq!(clock.now(), before);
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.now() - before, Duration::from_secs(90));
+    }
+}
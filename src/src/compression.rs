@@ -0,0 +1,274 @@
+//! Compressed FFI payloads (feature "compression")
+//!
+//! Trees and patch lists are repetitive JSON and usually shrink 5-10x. The
+//! `_compressed` FFI variants take and return byte buffers instead of C strings,
+//! each starting with an 8-byte header:
+//!
+//! | bytes | content                                  |
+//! |-------|------------------------------------------|
+//! | 0-1   | magic `MC`                               |
+//! | 2     | header version (1)                       |
+//! | 3     | CompressionFormat (0 none, 1 deflate, 2 gzip) |
+//! | 4-7   | uncompressed size, u32 little-endian     |
+//!
+//! followed by the payload (raw deflate for format 1, as written by .NET's
+//! DeflateStream). The uncompressed size is checked against
+//! `ValidationConfig::max_json_size` before anything is inflated.
+
+use crate::error::{MinimactError, Result};
+use crate::last_error::FfiCall;
+use flate2::read::{DeflateDecoder, GzDecoder};
+use flate2::write::{DeflateEncoder, GzEncoder};
+use flate2::Compression;
+use std::ffi::{CStr, CString};
+use std::io::{Read, Write};
+use std::os::raw::c_char;
+
+const MAGIC: [u8; 2] = *b"MC";
+const VERSION: u8 = 1;
+pub const HEADER_LEN: usize = 8;
+
+/// Payload encoding named in the header
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionFormat {
+    None = 0,
+    Deflate = 1,
+    Gzip = 2,
+}
+
+impl TryFrom<u8> for CompressionFormat {
+    type Error = MinimactError;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(CompressionFormat::None),
+            1 => Ok(CompressionFormat::Deflate),
+            2 => Ok(CompressionFormat::Gzip),
+            other => Err(MinimactError::Serialization(format!("Unknown compression format {}", other))),
+        }
+    }
+}
+
+/// Header followed by `data` encoded as `format`
+pub fn compress(data: &[u8], format: CompressionFormat) -> Result<Vec<u8>> {
+    let size = u32::try_from(data.len()).map_err(|_| MinimactError::JsonTooLarge { size: data.len(), max: u32::MAX as usize })?;
+
+    let mut out = Vec::with_capacity(HEADER_LEN + data.len() / 4);
+    out.extend_from_slice(&MAGIC);
+    out.push(VERSION);
+    out.push(format as u8);
+    out.extend_from_slice(&size.to_le_bytes());
+
+    let io_error = |e: std::io::Error| MinimactError::Serialization(format!("Compression failed: {}", e));
+    match format {
+        CompressionFormat::None => out.extend_from_slice(data),
+        CompressionFormat::Deflate => {
+            let mut encoder = DeflateEncoder::new(out, Compression::fast());
+            encoder.write_all(data).map_err(io_error)?;
+            out = encoder.finish().map_err(io_error)?;
+        }
+        CompressionFormat::Gzip => {
+            let mut encoder = GzEncoder::new(out, Compression::fast());
+            encoder.write_all(data).map_err(io_error)?;
+            out = encoder.finish().map_err(io_error)?;
+        }
+    }
+    Ok(out)
+}
+
+/// Decode a buffer produced by `compress` (or the C# side), refusing payloads whose
+/// uncompressed size exceeds `max_size`
+pub fn decompress(buffer: &[u8], max_size: usize) -> Result<Vec<u8>> {
+    let invalid = |msg: &str| MinimactError::Serialization(format!("Invalid compressed buffer: {}", msg));
+
+    if buffer.len() < HEADER_LEN || buffer[..2] != MAGIC {
+        return Err(invalid("missing header"));
+    }
+    if buffer[2] != VERSION {
+        return Err(invalid(&format!("unsupported header version {}", buffer[2])));
+    }
+    let format = CompressionFormat::try_from(buffer[3])?;
+    let size = u32::from_le_bytes([buffer[4], buffer[5], buffer[6], buffer[7]]) as usize;
+    if size > max_size {
+        return Err(MinimactError::JsonTooLarge { size, max: max_size });
+    }
+
+    let payload = &buffer[HEADER_LEN..];
+    let mut out = Vec::with_capacity(size);
+    // Read one byte past the declared size so a lying header is caught without inflating a bomb
+    let read = match format {
+        CompressionFormat::None => (&mut &payload[..]).take(size as u64 + 1).read_to_end(&mut out),
+        CompressionFormat::Deflate => DeflateDecoder::new(payload).take(size as u64 + 1).read_to_end(&mut out),
+        CompressionFormat::Gzip => GzDecoder::new(payload).take(size as u64 + 1).read_to_end(&mut out),
+    };
+    read.map_err(|e| invalid(&e.to_string()))?;
+
+    if out.len() != size {
+        return Err(invalid(&format!("header says {} bytes, payload has {}", size, out.len())));
+    }
+    Ok(out)
+}
+
+/// Decompress an FFI input buffer into a C string for the plain-JSON entry points
+unsafe fn input_cstring(ptr: *const u8, len: usize) -> Result<CString> {
+    if ptr.is_null() {
+        return Err(MinimactError::NullPointer("buffer"));
+    }
+    let max = crate::validation::ValidationConfig::default().max_json_size;
+    let json = decompress(std::slice::from_raw_parts(ptr, len), max)?;
+    CString::new(json).map_err(|_| MinimactError::Serialization("Payload contains a NUL byte".to_string()))
+}
+
+/// Response format plus both trees as C strings
+unsafe fn decode_inputs(
+    format: u8,
+    old_buf: *const u8,
+    old_len: usize,
+    new_buf: *const u8,
+    new_len: usize,
+) -> Result<(CompressionFormat, CString, CString)> {
+    Ok((CompressionFormat::try_from(format)?, input_cstring(old_buf, old_len)?, input_cstring(new_buf, new_len)?))
+}
+
+/// Compress a C string returned by a plain-JSON entry point (and free it)
+unsafe fn output_buffer(json: *mut c_char, format: CompressionFormat, out_len: *mut usize) -> *mut u8 {
+    if json.is_null() {
+        return std::ptr::null_mut();
+    }
+    let compressed = compress(CStr::from_ptr(json).to_bytes(), format);
+    crate::ffi::minimact_free_string(json);
+
+    match compressed {
+        Ok(buffer) => {
+            let buffer = buffer.into_boxed_slice();
+            *out_len = buffer.len();
+            Box::into_raw(buffer) as *mut u8
+        }
+        Err(e) => fail(&e),
+    }
+}
+
+fn fail(err: &MinimactError) -> *mut u8 {
+    crate::last_error::record_error(err);
+    std::ptr::null_mut()
+}
+
+/// Same as minimact_reconcile, with compressed trees in and a compressed response out
+/// Returns null (see minimact_last_error_json) if a buffer can't be decoded or the
+/// response can't be encoded; reconcile errors come back as compressed {"error": ...}
+///
+/// # Safety
+/// - old_buf / new_buf must point to old_len / new_len readable bytes (header + payload)
+/// - format is the CompressionFormat of the response
+/// - out_len must be a valid pointer; it receives the response length
+/// - The returned buffer must be freed using minimact_free_buffer with that length
+#[no_mangle]
+pub unsafe extern "C" fn minimact_reconcile_compressed(
+    old_buf: *const u8,
+    old_len: usize,
+    new_buf: *const u8,
+    new_len: usize,
+    format: u8,
+    out_len: *mut usize,
+) -> *mut u8 {
+    let _call = FfiCall::named("minimact_reconcile_compressed");
+    if out_len.is_null() {
+        return fail(&MinimactError::NullPointer("out_len"));
+    }
+    let (format, old_json, new_json) = match decode_inputs(format, old_buf, old_len, new_buf, new_len) {
+        Ok(inputs) => inputs,
+        Err(e) => return fail(&e),
+    };
+
+    output_buffer(crate::ffi::minimact_reconcile(old_json.as_ptr(), new_json.as_ptr()), format, out_len)
+}
+
+/// Same as minimact_reconcile_with_strategy, with compressed trees in and a compressed
+/// response out (the strategy stays a plain JSON string)
+///
+/// # Safety
+/// - Same requirements as minimact_reconcile_compressed
+/// - strategy_json must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_reconcile_with_strategy_compressed(
+    old_buf: *const u8,
+    old_len: usize,
+    new_buf: *const u8,
+    new_len: usize,
+    strategy_json: *const c_char,
+    format: u8,
+    out_len: *mut usize,
+) -> *mut u8 {
+    let _call = FfiCall::enter("minimact_reconcile_with_strategy_compressed", &[strategy_json]);
+    if out_len.is_null() {
+        return fail(&MinimactError::NullPointer("out_len"));
+    }
+    let (format, old_json, new_json) = match decode_inputs(format, old_buf, old_len, new_buf, new_len) {
+        Ok(inputs) => inputs,
+        Err(e) => return fail(&e),
+    };
+
+    let response = crate::ffi::minimact_reconcile_with_strategy(old_json.as_ptr(), new_json.as_ptr(), strategy_json);
+    output_buffer(response, format, out_len)
+}
+
+/// Free a buffer returned by a `_compressed` function
+///
+/// # Safety
+/// - ptr must come from a `_compressed` function and len be the length it reported
+#[no_mangle]
+pub unsafe extern "C" fn minimact_free_buffer(ptr: *mut u8, len: usize) {
+    if !ptr.is_null() {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vdom::VNode;
+
+    #[test]
+    fn test_round_trip_and_limits() {
+        let json = br#"{"type":"Text","content":"hello hello hello hello","path":"10000000"}"#;
+        for format in [CompressionFormat::None, CompressionFormat::Deflate, CompressionFormat::Gzip] {
+            let buffer = compress(json, format).unwrap();
+            assert_eq!(&buffer[..4], &[b'M', b'C', 1, format as u8]);
+            assert_eq!(decompress(&buffer, 1024).unwrap(), json);
+        }
+
+        let buffer = compress(json, CompressionFormat::Deflate).unwrap();
+        assert!(matches!(decompress(&buffer, 16), Err(MinimactError::JsonTooLarge { .. })));
+
+        // A header understating the payload is rejected, not truncated
+        let mut lying = buffer.clone();
+        lying[4..8].copy_from_slice(&10u32.to_le_bytes());
+        assert!(decompress(&lying, 1024).is_err());
+    }
+
+    #[test]
+    fn test_reconcile_compressed() {
+        let tree = |content: &str| {
+            let node = VNode::Text(crate::vdom::VText { content: content.to_string(), path: crate::path::HexPath::from("10000000") });
+            compress(serde_json::to_string(&node).unwrap().as_bytes(), CompressionFormat::Deflate).unwrap()
+        };
+        let (old, new) = (tree("a"), tree("b"));
+
+        unsafe {
+            let mut len = 0;
+            let response = minimact_reconcile_compressed(old.as_ptr(), old.len(), new.as_ptr(), new.len(), CompressionFormat::Gzip as u8, &mut len);
+            assert!(!response.is_null());
+            let json = decompress(std::slice::from_raw_parts(response, len), usize::MAX).unwrap();
+            minimact_free_buffer(response, len);
+
+            let patches: Vec<crate::vdom::Patch> = serde_json::from_slice(&json).unwrap();
+            assert!(matches!(&patches[..], [crate::vdom::Patch::UpdateText { content, .. }] if content == "b"));
+
+            let garbage = [0u8; 4];
+            let response = minimact_reconcile_compressed(garbage.as_ptr(), garbage.len(), new.as_ptr(), new.len(), 1, &mut len);
+            assert!(response.is_null());
+            assert_eq!(crate::last_error::last_error().unwrap().entry_point, "minimact_reconcile_compressed");
+        }
+    }
+}
@@ -0,0 +1,301 @@
+//! Lock-free predictions alongside learning
+//!
+//! `Predictor::learn` takes `&mut self`, so hosts used to serialize every predict
+//! and learn for a component behind one lock, and a slow learn (two reconciles plus
+//! template extraction) stalled the predictions waiting behind it.
+//!
+//! ConcurrentPredictor publishes immutable Predictor versions through an ArcSwap.
+//! Predicts load the current version without locking. Writes (learn, verify, config)
+//! are serialized among themselves, apply to a copy of the current version (cheap:
+//! pattern lists, templates and stored trees are shared copy-on-write, so only what
+//! the write changes is copied) and publish it atomically. Usage counters from
+//! predictions are queued and folded into the next version a writer publishes; they
+//! find their pattern by id, wherever learning has moved it since.
+
+use crate::error::Result;
+use crate::logging::{with_log_context, LogContext};
+use crate::prediction_bundle::{BundleOptions, PredictionBundle};
+use crate::predictor::{PatternSummary, Prediction, PredictionUse, Predictor, PredictorStats, StateChange};
+use crate::schema::{BatchItemResult, BatchResponse, LearnObservation};
+use crate::template_renderer::StateValues;
+use crate::vdom::{ComponentMetadata, VNode};
+use arc_swap::ArcSwap;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+/// A predictor that can be read and written from many threads at once
+pub struct ConcurrentPredictor {
+    current: ArcSwap<Predictor>,
+    /// Held by writers; owns the queue of usage counters from predictions
+    writer: Mutex<Receiver<PredictionUse>>,
+    uses: Sender<PredictionUse>,
+}
+
+impl ConcurrentPredictor {
+    /// Wrap a predictor
+    pub fn new(predictor: Predictor) -> Self {
+        let (uses, pending) = mpsc::channel();
+        Self {
+            current: ArcSwap::from_pointee(predictor),
+            writer: Mutex::new(pending),
+            uses,
+        }
+    }
+
+    /// The current version (stays valid while later versions are published)
+    pub fn snapshot(&self) -> Arc<Predictor> {
+        self.current.load_full()
+    }
+
+    /// Predict patches for a state change (lock-free)
+    pub fn predict(&self, state_change: &StateChange, current_tree: &VNode) -> Option<Prediction> {
+        self.predict_with_metadata(state_change, current_tree, None)
+    }
+
+    /// Predict patches with optional build-time templates (lock-free)
+    pub fn predict_with_metadata(
+        &self,
+        state_change: &StateChange,
+        current_tree: &VNode,
+        metadata: Option<&ComponentMetadata>,
+    ) -> Option<Prediction> {
+        let (prediction, used) = with_log_context(LogContext::component(&state_change.component_id), || {
+            self.current.load().predict_readonly(state_change, current_tree, metadata, None)
+        });
+        if let Some(used) = used {
+            // The receiver lives as long as self, so this can't fail
+            let _ = self.uses.send(used);
+        }
+        prediction
+    }
+
+    /// Predict patches given the component's full state (lock-free)
+    /// See `Predictor::predict_with_state`
+    pub fn predict_with_state(
+        &self,
+        state_change: &StateChange,
+        full_state: &StateValues,
+        current_tree: &VNode,
+    ) -> Option<Prediction> {
+        let (prediction, used) = with_log_context(LogContext::component(&state_change.component_id), || {
+            self.current.load().predict_readonly(state_change, current_tree, None, Some(full_state))
+        });
+        if let Some(used) = used {
+            let _ = self.uses.send(used);
+        }
+        prediction
+    }
+
+    /// Predict patches for a usePredictHint hint (lock-free)
+    pub fn predict_hint(
+        &self,
+        hint_id: &str,
+        component_id: &str,
+        state_changes: &[StateChange],
+        current_tree: &VNode,
+    ) -> Option<Prediction> {
+        let state_change = Predictor::hint_state_change(hint_id, component_id, state_changes)?;
+        self.predict(state_change, current_tree)
+    }
+
+    /// Learn from an observed state change, publishing a new version
+    pub fn learn(
+        &self,
+        state_change: StateChange,
+        old_tree: &VNode,
+        new_tree: &VNode,
+        all_state: Option<&HashMap<String, serde_json::Value>>,
+    ) -> Result<()> {
+        let context = LogContext::component(&state_change.component_id);
+        with_log_context(context, || self.update(|predictor| predictor.learn(state_change, old_tree, new_tree, all_state)))
+    }
+
+    /// Predict for many state changes against the same tree (lock-free)
+    pub fn predict_batch(&self, state_changes: &[StateChange], current_tree: &VNode) -> BatchResponse<Prediction> {
+        let results = state_changes
+            .iter()
+            .enumerate()
+            .map(|(index, state_change)| match self.predict(state_change, current_tree) {
+                Some(prediction) => BatchItemResult::success(index, state_change, Some(prediction)),
+                None => BatchItemResult::failure(index, state_change, "No prediction available"),
+            })
+            .collect();
+        BatchResponse::new(results)
+    }
+
+    /// Learn many observations in order, publishing a single new version
+    pub fn learn_batch(&self, observations: Vec<LearnObservation>) -> BatchResponse<()> {
+        let results = self.update(|predictor| {
+            observations
+                .into_iter()
+                .enumerate()
+                .map(|(index, observation)| {
+                    let state_change = observation.state_change;
+                    let result = with_log_context(LogContext::component(&state_change.component_id), || {
+                        predictor.learn(
+                            state_change.clone(),
+                            &observation.old_tree,
+                            &observation.new_tree,
+                            observation.all_state.as_ref(),
+                        )
+                    });
+                    match result {
+                        Ok(()) => BatchItemResult::success(index, &state_change, None),
+                        Err(e) => BatchItemResult::failure(index, &state_change, format!("Learn failed: {}", e)),
+                    }
+                })
+                .collect()
+        });
+        BatchResponse::new(results)
+    }
+
+    /// Seed template predictions from a component's build-time metadata, publishing a new version
+    pub fn seed_from_metadata(&self, metadata: &ComponentMetadata) -> usize {
+        with_log_context(LogContext::component(&metadata.component_id), || {
+            self.update(|predictor| predictor.seed_from_metadata(metadata))
+        })
+    }
+
+    /// Record the code hash of a component's current build, dropping patterns learned
+    /// from other builds; publishes a new version only when the hash changed
+    pub fn set_code_hash(&self, component_id: &str, code_hash: &str) -> usize {
+        if self.current.load().code_hash(component_id) == Some(code_hash) {
+            return 0;
+        }
+        with_log_context(LogContext::component(component_id), || {
+            self.update(|predictor| predictor.set_code_hash(component_id, code_hash))
+        })
+    }
+
+    /// Record whether a prediction was correct, publishing a new version
+    pub fn verify_prediction(&self, state_change: &StateChange, predicted_tree: &VNode, actual_tree: &VNode) -> Result<bool> {
+        with_log_context(LogContext::component(&state_change.component_id), || {
+            self.update(|predictor| predictor.verify_prediction(state_change, predicted_tree, actual_tree))
+        })
+    }
+
+    /// Statistics of the current version
+    pub fn stats(&self) -> PredictorStats {
+        self.current.load().stats()
+    }
+
+    /// Confident predictions of a component in the current version, for client caching
+    pub fn export_bundle(&self, component_id: &str, options: &BundleOptions) -> PredictionBundle {
+        self.current.load().export_bundle(component_id, options)
+    }
+
+    /// Templates and patterns of the current version, for the pattern inspector
+    pub fn inspect_patterns(&self) -> Vec<PatternSummary> {
+        self.current.load().inspect_patterns()
+    }
+
+    /// Apply `f` to a copy of the current version and publish the result
+    /// Writers run one at a time; readers keep using the previous version meanwhile
+    pub fn update<R>(&self, f: impl FnOnce(&mut Predictor) -> R) -> R {
+        let pending = self.writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut next = Predictor::clone(&self.current.load());
+        for used in pending.try_iter() {
+            next.record_use(&used);
+        }
+        let result = f(&mut next);
+        self.current.store(Arc::new(next));
+        result
+    }
+}
+
+impl Default for ConcurrentPredictor {
+    fn default() -> Self {
+        Self::new(Predictor::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::HexPath;
+    use crate::vdom::{VElement, VText};
+    use serde_json::json;
+
+    fn counter(count: i64) -> VNode {
+        VNode::Element(VElement {
+            tag: "span".to_string(),
+            props: HashMap::new(),
+            children: vec![Some(VNode::Text(VText {
+                content: format!("Count: {}", count),
+                path: HexPath::from("10000000.10000000"),
+            }))],
+            key: None,
+            path: HexPath::from("10000000"),
+            source: None,
+        })
+    }
+
+    fn change(old: i64, new: i64) -> StateChange {
+        StateChange {
+            component_id: "counter".to_string(),
+            state_key: "count".to_string(),
+            old_value: json!(old),
+            new_value: json!(new),
+            array_operation: None,
+            locale: None,
+        }
+    }
+
+    #[test]
+    fn test_snapshots_are_isolated_from_learning() {
+        let predictor = ConcurrentPredictor::default();
+        let before = predictor.snapshot();
+
+        for i in 0..3 {
+            predictor.learn(change(i, i + 1), &counter(i), &counter(i + 1), None).unwrap();
+        }
+
+        assert_eq!(before.stats().total_patterns, 0);
+        assert!(predictor.stats().total_observations >= 1);
+        assert!(predictor.predict(&change(5, 6), &counter(5)).is_some());
+    }
+
+    #[test]
+    fn test_predicts_from_many_threads_while_learning() {
+        let predictor = Arc::new(ConcurrentPredictor::default());
+        predictor.learn(change(0, 1), &counter(0), &counter(1), None).unwrap();
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let predictor = Arc::clone(&predictor);
+                std::thread::spawn(move || (0..200).filter(|_| predictor.predict(&change(1, 2), &counter(1)).is_some()).count())
+            })
+            .collect();
+        for i in 1..50 {
+            predictor.learn(change(i, i + 1), &counter(i), &counter(i + 1), None).unwrap();
+        }
+
+        for reader in readers {
+            assert_eq!(reader.join().unwrap(), 200);
+        }
+    }
+
+    #[test]
+    fn test_batches_report_per_entry_results() {
+        let predictor = ConcurrentPredictor::default();
+        let observations = (0..3)
+            .map(|i| LearnObservation {
+                state_change: change(i, i + 1),
+                old_tree: counter(i),
+                new_tree: counter(i + 1),
+                all_state: None,
+            })
+            .collect();
+        let learned = predictor.learn_batch(observations);
+        assert_eq!((learned.succeeded, learned.failed), (3, 0));
+
+        let mut unknown = change(0, 1);
+        unknown.state_key = "missing".to_string();
+        let predicted = predictor.predict_batch(&[change(5, 6), unknown], &counter(5));
+        assert_eq!((predicted.succeeded, predicted.failed), (1, 1));
+        assert_eq!(predicted.results[0].key, "counter::count");
+        assert_eq!(predicted.results[1].index, 1);
+        assert!(predicted.results[1].error.is_some());
+    }
+}
@@ -0,0 +1,228 @@
+//! Conflict detection between concurrent patch batches
+//!
+//! In a collaborative app two users' state changes can each produce a batch against
+//! the same baseline tree. Before applying both, the server checks every pair of
+//! patches (one from each batch):
+//! - disjoint: the paths are unrelated, so the patches can't interfere
+//! - compatible: the paths overlap but the patches write different things (a prop
+//!   on a parent and the text of a child, two different attributes, the same
+//!   change twice), so both can apply in either order
+//! - conflicting: the patches write the same thing differently, or one removes,
+//!   replaces or reorders the subtree the other writes into
+//!
+//! Hex paths are stable across sibling inserts and removals, so unrelated paths
+//! never interfere. UpdateProps carries the element's whole prop set, so it
+//! conflicts with any other prop write to the same element.
+
+use crate::document::DocumentTarget;
+use crate::error::Result;
+use crate::last_error::FfiCall;
+use crate::path::HexPath;
+use crate::tree_store::json_or_error;
+use crate::vdom::Patch;
+use serde::{Deserialize, Serialize};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+/// How two patches relate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConflictKind {
+    Disjoint,
+    Compatible,
+    Conflicting,
+}
+
+/// Two patches whose paths overlap
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatchPair {
+    /// Index in batch A
+    pub a: usize,
+    /// Index in batch B
+    pub b: usize,
+    pub kind: ConflictKind,
+    /// The shallower of the two paths
+    pub path: HexPath,
+    pub reason: String,
+}
+
+/// Every overlapping pair of two batches
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConflictReport {
+    /// Pairs with unrelated paths (not listed)
+    pub disjoint: usize,
+    pub compatible: Vec<PatchPair>,
+    pub conflicts: Vec<PatchPair>,
+}
+
+impl ConflictReport {
+    pub fn has_conflicts(&self) -> bool {
+        !self.conflicts.is_empty()
+    }
+}
+
+/// What a patch writes at its path
+#[derive(Debug, PartialEq)]
+enum Effect<'a> {
+    /// Creates, removes or replaces the node (and everything under it)
+    Subtree,
+    /// Reorders or regenerates the node's children
+    Children,
+    Text,
+    /// One attribute
+    Prop(&'a str),
+    /// The whole prop set
+    AllProps,
+    Document(&'a DocumentTarget),
+    Navigation,
+    /// Host-defined, not interpreted
+    Opaque,
+    /// Advisory, writes nothing
+    Hint,
+}
+
+fn effect(patch: &Patch) -> Effect<'_> {
+    match patch {
+        Patch::Create { .. } | Patch::Remove { .. } | Patch::Replace { .. } | Patch::ReplaceConditional { .. } => Effect::Subtree,
+        Patch::ReorderChildren { .. }
+        | Patch::ReorderTemplate { .. }
+        | Patch::UpdateListTemplate { .. }
+        | Patch::UpdateListWindow { .. } => Effect::Children,
+        Patch::UpdateText { .. } | Patch::UpdateTextTemplate { .. } => Effect::Text,
+        Patch::UpdateProps { .. } => Effect::AllProps,
+        Patch::SetIgnored { .. } => Effect::Prop(crate::ignored_regions::IGNORE_ATTR),
+        Patch::UpdatePropsTemplate { prop_name: name, .. }
+        | Patch::UpdateAttributeStatic { attr_name: name, .. }
+        | Patch::UpdateAttributeDynamic { attr_name: name, .. } => Effect::Prop(name),
+        Patch::UpdateDocument { target, .. } => Effect::Document(target),
+        Patch::Navigate { .. } => Effect::Navigation,
+        Patch::Custom { .. } => Effect::Opaque,
+        Patch::InvalidateLayout { .. } | Patch::Lifecycle { .. } => Effect::Hint,
+    }
+}
+
+/// Classify one patch of batch A against one of batch B
+/// Returns the kind and, unless disjoint, why
+pub fn classify_pair(a: &Patch, b: &Patch) -> (ConflictKind, &'static str) {
+    use ConflictKind::*;
+    let (ea, eb) = (effect(a), effect(b));
+
+    match (&ea, &eb) {
+        (Effect::Opaque | Effect::Hint, _) | (_, Effect::Opaque | Effect::Hint) => return (Disjoint, ""),
+        (Effect::Navigation, Effect::Navigation) if a != b => return (Conflicting, "both navigate"),
+        (Effect::Navigation, Effect::Navigation) => return (Compatible, "same navigation"),
+        (Effect::Document(x), Effect::Document(y)) if x == y && a != b => return (Conflicting, "both set the same document setting"),
+        (Effect::Document(x), Effect::Document(y)) if x == y => return (Compatible, "same document change"),
+        (Effect::Navigation | Effect::Document(_), _) | (_, Effect::Navigation | Effect::Document(_)) => return (Disjoint, ""),
+        _ => {}
+    }
+
+    let (pa, pb) = (a.path(), b.path());
+    if !pa.is_within(pb) && !pb.is_within(pa) {
+        return (Disjoint, "");
+    }
+    if a == b {
+        return (Compatible, "same change");
+    }
+
+    if pa == pb {
+        return match (&ea, &eb) {
+            (Effect::Subtree, _) | (_, Effect::Subtree) => (Conflicting, "both change the same node and one replaces it"),
+            (Effect::Children, Effect::Children) => (Conflicting, "both rearrange the same children"),
+            (Effect::Text, Effect::Text) => (Conflicting, "both set the same text"),
+            (Effect::AllProps, Effect::AllProps | Effect::Prop(_)) | (Effect::Prop(_), Effect::AllProps) => {
+                (Conflicting, "both set props of the same element")
+            }
+            (Effect::Prop(x), Effect::Prop(y)) if x.eq_ignore_ascii_case(y) => (Conflicting, "both set the same attribute"),
+            _ => (Compatible, "different parts of the same node"),
+        };
+    }
+
+    // One path is strictly inside the other
+    let outer = if pb.is_descendant_of(pa) { &ea } else { &eb };
+    match outer {
+        Effect::Subtree => (Conflicting, "one removes or replaces the subtree the other writes into"),
+        Effect::Children => (Conflicting, "one rearranges the children the other writes into"),
+        _ => (Compatible, "ancestor and descendant"),
+    }
+}
+
+/// Classify every pair of patches from two batches made against the same baseline
+pub fn detect_conflicts(batch_a: &[Patch], batch_b: &[Patch]) -> ConflictReport {
+    let mut report = ConflictReport::default();
+    for (i, a) in batch_a.iter().enumerate() {
+        for (j, b) in batch_b.iter().enumerate() {
+            let (kind, reason) = classify_pair(a, b);
+            let path = if a.path().depth() <= b.path().depth() { a.path() } else { b.path() };
+            let pair = || PatchPair { a: i, b: j, kind, path: path.clone(), reason: reason.to_string() };
+            match kind {
+                ConflictKind::Disjoint => report.disjoint += 1,
+                ConflictKind::Compatible => report.compatible.push(pair()),
+                ConflictKind::Conflicting => report.conflicts.push(pair()),
+            }
+        }
+    }
+    report
+}
+
+/// Detect conflicts between two patch batches
+/// Returns ConflictReport JSON (or {"error": ...})
+///
+/// # Safety
+/// - batch_a_json and batch_b_json must be valid null-terminated UTF-8 strings
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_detect_conflicts(batch_a_json: *const c_char, batch_b_json: *const c_char) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_detect_conflicts", &[batch_a_json, batch_b_json]);
+    json_or_error((|| -> Result<ConflictReport> {
+        let batch_a: Vec<Patch> = serde_json::from_str(CStr::from_ptr(batch_a_json).to_str()?)?;
+        let batch_b: Vec<Patch> = serde_json::from_str(CStr::from_ptr(batch_b_json).to_str()?)?;
+        Ok(detect_conflicts(&batch_a, &batch_b))
+    })())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn text(path: &str, content: &str) -> Patch {
+        Patch::UpdateText { path: HexPath::from(path), content: content.to_string() }
+    }
+
+    fn attr(path: &str, name: &str, value: &str) -> Patch {
+        Patch::UpdateAttributeStatic { path: HexPath::from(path), attr_name: name.to_string(), value: value.to_string() }
+    }
+
+    #[test]
+    fn test_pairs_are_classified_by_path_and_effect() {
+        let kind = |a: &Patch, b: &Patch| classify_pair(a, b).0;
+
+        assert_eq!(kind(&text("10000000.10000000", "a"), &text("10000000.20000000", "b")), ConflictKind::Disjoint);
+        assert_eq!(kind(&text("10000000.10000000", "a"), &text("10000000.10000000", "b")), ConflictKind::Conflicting);
+        assert_eq!(kind(&text("10000000.10000000", "a"), &text("10000000.10000000", "a")), ConflictKind::Compatible);
+        assert_eq!(kind(&attr("10000000", "title", "x"), &attr("10000000", "class", "y")), ConflictKind::Compatible);
+        assert_eq!(kind(&attr("10000000", "title", "x"), &attr("10000000", "title", "y")), ConflictKind::Conflicting);
+        assert_eq!(kind(&attr("10000000", "title", "x"), &text("10000000.10000000", "a")), ConflictKind::Compatible);
+
+        let props = Patch::UpdateProps { path: HexPath::from("10000000"), props: HashMap::new() };
+        assert_eq!(kind(&props, &attr("10000000", "class", "y")), ConflictKind::Conflicting);
+
+        let remove = Patch::Remove { path: HexPath::from("10000000") };
+        assert_eq!(kind(&text("10000000.10000000", "a"), &remove), ConflictKind::Conflicting);
+        let reorder = Patch::ReorderChildren { path: HexPath::from("10000000"), order: vec![], preserve: None };
+        assert_eq!(kind(&reorder, &attr("10000000", "class", "y")), ConflictKind::Compatible);
+        assert_eq!(kind(&reorder, &text("10000000.10000000", "a")), ConflictKind::Conflicting);
+    }
+
+    #[test]
+    fn test_report_lists_overlapping_pairs() {
+        let a = vec![text("10000000.10000000", "mine"), attr("10000000", "class", "open")];
+        let b = vec![text("10000000.10000000", "theirs"), text("10000000.20000000", "other")];
+        let report = detect_conflicts(&a, &b);
+
+        assert!(report.has_conflicts());
+        assert_eq!((report.disjoint, report.compatible.len(), report.conflicts.len()), (1, 2, 1));
+        assert_eq!((report.conflicts[0].a, report.conflicts[0].b), (0, 0));
+        assert_eq!(report.compatible[0].path, HexPath::from("10000000"));
+    }
+}
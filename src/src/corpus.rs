@@ -0,0 +1,154 @@
+//! Golden corpus of real-world tree pairs
+//!
+//! Each `corpus/*.json` file holds one component render before and after a user
+//! action (todo list, dashboard refresh, form wizard step...) with bounds on the
+//! patches reconcile should produce. Running a case diffs the pair, applies the
+//! patches to the old tree and reports every way the result strays from the bounds,
+//! so both patch-count regressions and wrong patches show up.
+
+use crate::apply::apply_patches;
+use crate::checksum::tree_checksum;
+use crate::error::{MinimactError, Result};
+use crate::reconciler::reconcile;
+use crate::vdom::{Patch, VNode};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// One before/after tree pair
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorpusCase {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub old: VNode,
+    pub new: VNode,
+    pub expect: PatchBounds,
+}
+
+/// What a diff of the pair may look like
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PatchBounds {
+    pub min_patches: usize,
+    pub max_patches: usize,
+    /// Allowed [min, max] count per patch kind; kinds not listed must not appear
+    #[serde(default)]
+    pub kinds: BTreeMap<String, (usize, usize)>,
+}
+
+/// Result of running one case
+#[derive(Debug, Clone, Serialize)]
+pub struct CorpusOutcome {
+    pub name: String,
+    pub patches: Vec<Patch>,
+    /// Empty when the case passed
+    pub violations: Vec<String>,
+}
+
+impl CorpusOutcome {
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl CorpusCase {
+    /// Diff the pair and check the patches against the bounds
+    pub fn run(&self) -> Result<CorpusOutcome> {
+        let patches = reconcile(&self.old, &self.new)?;
+        let mut violations = Vec::new();
+
+        let bounds = &self.expect;
+        if patches.len() < bounds.min_patches || patches.len() > bounds.max_patches {
+            violations.push(format!(
+                "{} patches, expected {}..={}",
+                patches.len(),
+                bounds.min_patches,
+                bounds.max_patches
+            ));
+        }
+
+        let mut kinds: BTreeMap<&str, usize> = BTreeMap::new();
+        for patch in &patches {
+            *kinds.entry(patch.kind()).or_default() += 1;
+        }
+        for (kind, (min, max)) in &bounds.kinds {
+            let count = kinds.get(kind.as_str()).copied().unwrap_or(0);
+            if count < *min || count > *max {
+                violations.push(format!("{} {} patches, expected {}..={}", count, kind, min, max));
+            }
+        }
+        for (kind, count) in &kinds {
+            if !bounds.kinds.contains_key(*kind) {
+                violations.push(format!("{} unexpected {} patches", count, kind));
+            }
+        }
+
+        let mut tree = self.old.clone();
+        match apply_patches(&mut tree, &patches) {
+            Ok(()) if tree_checksum(&tree) == tree_checksum(&self.new) => {}
+            Ok(()) => violations.push("applying the patches doesn't reproduce the new tree".to_string()),
+            Err(e) => violations.push(format!("patches don't apply to the old tree: {}", e)),
+        }
+
+        Ok(CorpusOutcome { name: self.name.clone(), patches, violations })
+    }
+}
+
+/// Every `*.json` case in `dir`, sorted by file name
+pub fn load_corpus(dir: &Path) -> Result<Vec<CorpusCase>> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| MinimactError::Persistence(format!("Can't read corpus '{}': {}", dir.display(), e)))?;
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+
+    files
+        .iter()
+        .map(|file| {
+            let json = std::fs::read_to_string(file)
+                .map_err(|e| MinimactError::Persistence(format!("Can't read '{}': {}", file.display(), e)))?;
+            serde_json::from_str(&json)
+                .map_err(|e| MinimactError::Serialization(format!("{}: {}", file.display(), e)))
+        })
+        .collect()
+}
+
+/// Run every case in `dir`
+pub fn run_corpus(dir: &Path) -> Result<Vec<CorpusOutcome>> {
+    load_corpus(dir)?.iter().map(CorpusCase::run).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn reports_counts_outside_the_bounds() {
+        let tree = |text: &str| {
+            let mut node = VNode::element("p", HashMap::new(), vec![Some(VNode::text(text))]);
+            if let VNode::Element(el) = &mut node {
+                el.path = crate::path::HexPath::from("10000000");
+                if let Some(Some(VNode::Text(t))) = el.children.first_mut() {
+                    t.path = crate::path::HexPath::from("10000000.10000000");
+                }
+            }
+            node
+        };
+        let mut case = CorpusCase {
+            name: "text".to_string(),
+            description: String::new(),
+            old: tree("a"),
+            new: tree("b"),
+            expect: PatchBounds { min_patches: 1, max_patches: 1, kinds: BTreeMap::from([("UpdateText".to_string(), (1, 1))]) },
+        };
+        assert!(case.run().unwrap().passed());
+
+        case.expect.kinds.clear();
+        case.expect.max_patches = 0;
+        let outcome = case.run().unwrap();
+        assert_eq!(outcome.violations, vec!["1 patches, expected 1..=0", "1 unexpected UpdateText patches"]);
+    }
+}
@@ -0,0 +1,161 @@
+//! Correlation ids and per-request timing breakdowns
+//!
+//! The host assigns a correlation id when a state change arrives and passes it
+//! along with the reconcile request, the patch batch and the tasks that follow
+//! (the task runtime keeps it in each task's handle, so status reports carry
+//! it). Every stage records a timing span against that id, so the whole path
+//! (state change → render → reconcile → patch apply ack) can be read back as one
+//! trace instead of being stitched together from timestamps.
+//!
+//! Only the most recent MAX_TRACES traces are kept.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Traces kept before the oldest is evicted
+pub const MAX_TRACES: usize = 1024;
+
+/// Spans kept per trace (a runaway loop shouldn't grow one trace forever)
+pub const MAX_SPANS_PER_TRACE: usize = 256;
+
+/// Pipeline stage a timing span belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TraceStage {
+    /// Host received the state change
+    StateChange,
+    /// Host re-rendered the component (C#)
+    Render,
+    /// Predictor lookup
+    Predict,
+    /// Tree reconciliation
+    Reconcile,
+    /// Frame aggregation of patch sets
+    Aggregate,
+    /// Patch batch sent to the client
+    Send,
+    /// Client applied the batch and acknowledged it
+    ApplyAck,
+}
+
+/// One timed stage of a trace
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimingSpan {
+    pub stage: TraceStage,
+    /// Start of the span, relative to the first span of the trace (µs)
+    pub offset_us: u64,
+    /// Span duration (µs)
+    pub duration_us: u64,
+    /// Optional extra information (e.g. patch count)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Everything recorded for one correlation id
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimingBreakdown {
+    pub correlation_id: String,
+    pub spans: Vec<TimingSpan>,
+    /// Sum of span durations (µs)
+    pub total_us: u64,
+    /// From the start of the first span to the end of the last (µs)
+    pub elapsed_us: u64,
+}
+
+struct Trace {
+    started: Instant,
+    spans: Vec<TimingSpan>,
+}
+
+#[derive(Default)]
+struct TraceStore {
+    traces: HashMap<String, Trace>,
+    /// Insertion order, for eviction
+    order: VecDeque<String>,
+}
+
+lazy_static::lazy_static! {
+    static ref TRACES: Mutex<TraceStore> = Mutex::new(TraceStore::default());
+}
+
+/// Record a span that just finished after `duration`
+pub fn record_span(correlation_id: &str, stage: TraceStage, duration: Duration, detail: Option<String>) {
+    let now = Instant::now();
+    let span_start = now.checked_sub(duration).unwrap_or(now);
+    let mut store = TRACES.lock().unwrap();
+
+    if !store.traces.contains_key(correlation_id) {
+        if store.order.len() >= MAX_TRACES {
+            if let Some(oldest) = store.order.pop_front() {
+                store.traces.remove(&oldest);
+            }
+        }
+        store.order.push_back(correlation_id.to_string());
+        store.traces.insert(correlation_id.to_string(), Trace { started: span_start, spans: Vec::new() });
+    }
+
+    let trace = store.traces.get_mut(correlation_id).unwrap();
+    if trace.spans.len() >= MAX_SPANS_PER_TRACE {
+        crate::log_warn!("Trace '{}' has {} spans, dropping {:?}", correlation_id, MAX_SPANS_PER_TRACE, stage);
+        return;
+    }
+    trace.spans.push(TimingSpan {
+        stage,
+        offset_us: span_start.saturating_duration_since(trace.started).as_micros() as u64,
+        duration_us: duration.as_micros() as u64,
+        detail,
+    });
+}
+
+/// Run `f`, recording how long it took as a span
+pub fn time_span<T>(correlation_id: &str, stage: TraceStage, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    record_span(correlation_id, stage, start.elapsed(), None);
+    result
+}
+
+/// Timing breakdown for `correlation_id`, if anything was recorded
+pub fn timing_breakdown(correlation_id: &str) -> Option<TimingBreakdown> {
+    let store = TRACES.lock().unwrap();
+    let trace = store.traces.get(correlation_id)?;
+
+    let mut spans = trace.spans.clone();
+    spans.sort_by_key(|s| s.offset_us);
+    Some(TimingBreakdown {
+        correlation_id: correlation_id.to_string(),
+        total_us: spans.iter().map(|s| s.duration_us).sum(),
+        elapsed_us: spans.iter().map(|s| s.offset_us + s.duration_us).max().unwrap_or(0),
+        spans,
+    })
+}
+
+/// Forget the trace for `correlation_id`
+pub fn clear_trace(correlation_id: &str) {
+    let mut store = TRACES.lock().unwrap();
+    if store.traces.remove(correlation_id).is_some() {
+        store.order.retain(|id| id != correlation_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breakdown_collects_spans() {
+        let id = "test-breakdown";
+        record_span(id, TraceStage::Render, Duration::from_micros(300), None);
+        time_span(id, TraceStage::Reconcile, || std::thread::sleep(Duration::from_millis(1)));
+
+        let breakdown = timing_breakdown(id).unwrap();
+        let stages: Vec<TraceStage> = breakdown.spans.iter().map(|s| s.stage).collect();
+        assert_eq!(stages, vec![TraceStage::Render, TraceStage::Reconcile]);
+        assert!(breakdown.total_us >= 1300);
+        assert!(breakdown.elapsed_us >= breakdown.spans[1].duration_us);
+
+        clear_trace(id);
+        assert!(timing_breakdown(id).is_none());
+    }
+}
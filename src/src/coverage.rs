@@ -0,0 +1,247 @@
+//! Template coverage per component
+//!
+//! How much of a component's dynamic output templates can predict, and what still
+//! needs a learned concrete pattern or a full re-render. The dynamic regions are
+//! the ones recent reconciles patched (a trace is one reconcile's patch list); a
+//! patch is covered when a template targets the same thing at its path:
+//! - UpdateText: a text template
+//! - UpdateProps: an attribute template for every prop it sets
+//! - Create / Remove (under the parent) and ReorderChildren: a loop or reorder template
+//! - Replace: a structural (conditional) template
+//!
+//! Templates come from the component's metadata (compile time) and from the
+//! predictor (learned at runtime, or seeded from metadata). Loop templates from
+//! metadata carry no path, so they only count once the predictor has placed them.
+
+use crate::error::Result;
+use crate::last_error::FfiCall;
+use crate::path::HexPath;
+use crate::predictor::{Predictor, Provenance};
+use crate::vdom::{ComponentMetadata, Patch};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+/// What a template (or a patch) changes at its path
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Target {
+    Text,
+    Prop(String),
+    Children,
+    Node,
+}
+
+/// Coverage of one patched path
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathCoverage {
+    pub path: HexPath,
+    /// Patches at this path in the traces
+    pub patches: usize,
+    /// Of those, patches a template covers
+    pub covered: usize,
+    pub coverage_percent: f64,
+    /// Where the covering templates came from
+    pub sources: Vec<Provenance>,
+    /// Kinds of the patches no template covers
+    pub uncovered_kinds: Vec<String>,
+}
+
+/// Template coverage of a component's recent reconciles
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverageReport {
+    pub component_id: String,
+    pub patches: usize,
+    pub covered: usize,
+    /// 100 when nothing was patched
+    pub coverage_percent: f64,
+    /// Patched paths in document order
+    pub paths: Vec<PathCoverage>,
+}
+
+/// Templates of a component by what they target
+#[derive(Debug, Default)]
+struct Templates {
+    targets: HashMap<(HexPath, Target), BTreeSet<Provenance>>,
+}
+
+impl Templates {
+    fn add(&mut self, path: HexPath, target: Target, source: Provenance) {
+        self.targets.entry((path, target)).or_default().insert(source);
+    }
+
+    fn add_patch(&mut self, patch: &Patch, source: Provenance) {
+        let path = patch.path().clone();
+        match patch {
+            Patch::UpdateTextTemplate { .. } => self.add(path, Target::Text, source),
+            Patch::UpdatePropsTemplate { prop_name: name, .. }
+            | Patch::UpdateAttributeStatic { attr_name: name, .. }
+            | Patch::UpdateAttributeDynamic { attr_name: name, .. } => self.add(path, Target::Prop(name.clone()), source),
+            // Loop templates seeded from metadata wait at the root for a real path
+            Patch::UpdateListTemplate { .. } if path.is_root() => {}
+            Patch::UpdateListTemplate { .. } | Patch::ReorderTemplate { .. } => self.add(path, Target::Children, source),
+            Patch::ReplaceConditional { .. } => self.add(path, Target::Node, source),
+            _ => {}
+        }
+    }
+
+    /// Sources of the templates covering all of `targets` (None if one isn't covered)
+    fn cover(&self, path: &HexPath, targets: &[Target]) -> Option<BTreeSet<Provenance>> {
+        let mut sources = BTreeSet::new();
+        for target in targets {
+            sources.extend(self.targets.get(&(path.clone(), target.clone()))?);
+        }
+        Some(sources)
+    }
+}
+
+/// Path and targets a reconcile patch changes (None for kinds the reconciler doesn't emit)
+fn patch_targets(patch: &Patch) -> Option<(HexPath, Vec<Target>)> {
+    let path = patch.path().clone();
+    match patch {
+        Patch::UpdateText { .. } => Some((path, vec![Target::Text])),
+        Patch::UpdateProps { props, .. } => Some((path, props.keys().map(|name| Target::Prop(name.clone())).collect())),
+        Patch::Create { .. } | Patch::Remove { .. } => Some((path.parent().unwrap_or_else(HexPath::root), vec![Target::Children])),
+        Patch::ReorderChildren { .. } => Some((path, vec![Target::Children])),
+        Patch::Replace { .. } => Some((path, vec![Target::Node])),
+        _ => None,
+    }
+}
+
+fn percent(covered: usize, total: usize) -> f64 {
+    if total == 0 {
+        100.0
+    } else {
+        covered as f64 * 100.0 / total as f64
+    }
+}
+
+/// Report which patches in `traces` the component's templates cover
+/// `predictor` adds the templates it holds for the component
+pub fn analyze_coverage(metadata: &ComponentMetadata, predictor: Option<&Predictor>, traces: &[Vec<Patch>]) -> CoverageReport {
+    let mut templates = Templates::default();
+    for info in metadata.templates.values() {
+        let target = match info.get_attribute_name() {
+            Some(name) if info.is_attribute_template() => Target::Prop(name.to_string()),
+            _ => Target::Text,
+        };
+        templates.add(info.path.clone(), target, Provenance::CompileTime);
+    }
+    if let Some(predictor) = predictor {
+        for (source, patch) in predictor.template_patches(&metadata.component_id) {
+            templates.add_patch(patch, source);
+        }
+    }
+
+    let mut paths: HashMap<HexPath, (PathCoverage, BTreeSet<Provenance>, BTreeSet<String>)> = HashMap::new();
+    for patch in traces.iter().flatten() {
+        let Some((path, targets)) = patch_targets(patch) else { continue };
+        let (coverage, sources, uncovered) = paths.entry(path.clone()).or_insert_with(|| {
+            let coverage = PathCoverage {
+                path: path.clone(),
+                patches: 0,
+                covered: 0,
+                coverage_percent: 0.0,
+                sources: Vec::new(),
+                uncovered_kinds: Vec::new(),
+            };
+            (coverage, BTreeSet::new(), BTreeSet::new())
+        });
+        coverage.patches += 1;
+        match templates.cover(&path, &targets) {
+            Some(covering) => {
+                coverage.covered += 1;
+                sources.extend(covering);
+            }
+            None => {
+                uncovered.insert(patch.kind().to_string());
+            }
+        }
+    }
+
+    let mut paths: Vec<PathCoverage> = paths
+        .into_values()
+        .map(|(mut coverage, sources, uncovered)| {
+            coverage.coverage_percent = percent(coverage.covered, coverage.patches);
+            coverage.sources = sources.into_iter().collect();
+            coverage.uncovered_kinds = uncovered.into_iter().collect();
+            coverage
+        })
+        .collect();
+    paths.sort_by(|a, b| a.path.cmp_document_order(&b.path));
+
+    let patches = paths.iter().map(|p| p.patches).sum();
+    let covered = paths.iter().map(|p| p.covered).sum();
+    CoverageReport {
+        component_id: metadata.component_id.clone(),
+        patches,
+        covered,
+        coverage_percent: percent(covered, patches),
+        paths,
+    }
+}
+
+/// Template coverage of a component (for the devtools dashboard)
+/// Returns a CoverageReport as JSON (or {"error": ...})
+///
+/// # Safety
+/// - metadata_json (ComponentMetadata) and traces_json (array of patch lists) must be
+///   valid null-terminated UTF-8 strings
+/// - handle is the predictor whose templates count, or 0 for compile-time templates only
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_template_coverage(
+    handle: crate::ffi::PredictorHandle,
+    metadata_json: *const c_char,
+    traces_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_template_coverage", &[metadata_json, traces_json]);
+    let result = (|| -> Result<CoverageReport> {
+        let metadata: ComponentMetadata = serde_json::from_str(CStr::from_ptr(metadata_json).to_str()?)?;
+        let traces: Vec<Vec<Patch>> = serde_json::from_str(CStr::from_ptr(traces_json).to_str()?)?;
+        let predictor = match handle {
+            0 => None,
+            handle => Some(crate::ffi::predictor(handle)?.snapshot()),
+        };
+        Ok(analyze_coverage(&metadata, predictor.as_deref(), &traces))
+    })();
+    crate::tree_store::json_or_error(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vdom::TemplateInfo;
+
+    #[test]
+    fn test_coverage_by_path() {
+        let mut metadata = ComponentMetadata::new("Counter", "Counter");
+        metadata.add_template("[0].text[0]", TemplateInfo {
+            template: "Count: {0}".to_string(),
+            bindings: vec!["count".to_string()],
+            slots: vec![7],
+            path: HexPath::from_segments(&[1, 1]),
+            template_type: "dynamic".to_string(),
+            attribute: None,
+            conditional_templates: None,
+            transform: None,
+            nullable: None,
+        });
+
+        let text = HexPath::from_segments(&[1, 1]);
+        let list = HexPath::from_segments(&[1, 2]);
+        let traces = vec![
+            vec![Patch::UpdateText { path: text.clone(), content: "Count: 1".to_string() }],
+            vec![
+                Patch::UpdateText { path: text.clone(), content: "Count: 2".to_string() },
+                Patch::Remove { path: list.child(1) },
+            ],
+        ];
+
+        let report = analyze_coverage(&metadata, None, &traces);
+        assert_eq!((report.patches, report.covered), (3, 2));
+        assert_eq!(report.paths.iter().map(|p| (p.path.clone(), p.coverage_percent)).collect::<Vec<_>>(), vec![(text, 100.0), (list, 0.0)]);
+        assert_eq!(report.paths[0].sources, vec![Provenance::CompileTime]);
+        assert_eq!(report.paths[1].uncovered_kinds, vec!["Remove"]);
+    }
+}
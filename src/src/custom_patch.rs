@@ -0,0 +1,132 @@
+//! Host-defined patch kinds
+//!
+//! Hosts can carry their own operations ("PlaySound", "ShowToast", ...) on the patch
+//! stream as `Patch::Custom`, so they stay ordered with the DOM patches around them.
+//! The engine never interprets a custom patch: it validates that the kind was
+//! registered here and that the payload isn't oversized, never merges or drops one
+//! while aggregating a frame, and doesn't change the tree when applying one.
+//!
+//! Envelope: `{"type": "Custom", "kind": "PlaySound", "path": "", "payload": {...}}`.
+//! The path is optional (root when absent) and only places the patch in the stream.
+
+use crate::error::{FfiResult, MinimactError, Result};
+use crate::last_error::FfiCall;
+use arc_swap::ArcSwap;
+use std::collections::BTreeSet;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+lazy_static::lazy_static! {
+    static ref CUSTOM_KINDS: ArcSwap<BTreeSet<String>> = ArcSwap::from_pointee(BTreeSet::new());
+}
+
+/// Allow custom patches of `kind` (process-wide)
+pub fn register_custom_patch_kind(kind: &str) {
+    CUSTOM_KINDS.rcu(|kinds| {
+        let mut kinds = BTreeSet::clone(kinds);
+        kinds.insert(kind.to_string());
+        kinds
+    });
+}
+
+/// Stop allowing `kind`; returns false if it wasn't registered
+pub fn unregister_custom_patch_kind(kind: &str) -> bool {
+    let mut removed = false;
+    CUSTOM_KINDS.rcu(|kinds| {
+        let mut kinds = BTreeSet::clone(kinds);
+        removed = kinds.remove(kind);
+        kinds
+    });
+    removed
+}
+
+pub fn is_custom_patch_kind(kind: &str) -> bool {
+    CUSTOM_KINDS.load().contains(kind)
+}
+
+/// Registered kinds, sorted
+pub fn custom_patch_kinds() -> Vec<String> {
+    CUSTOM_KINDS.load().iter().cloned().collect()
+}
+
+/// Check a custom patch's kind and payload size
+pub fn validate_custom_patch(kind: &str, payload: &serde_json::Value) -> Result<()> {
+    if !crate::flags::is_enabled(crate::flags::Flag::CustomPatches) {
+        return Err(MinimactError::InvalidVNode("Custom patches are disabled".to_string()));
+    }
+    if !is_custom_patch_kind(kind) {
+        return Err(MinimactError::InvalidVNode(format!("Unregistered custom patch kind '{}'", kind)));
+    }
+    let max = crate::validation::ValidationConfig::default().max_text_length;
+    let size = serde_json::to_vec(payload)?.len();
+    if size > max {
+        return Err(MinimactError::JsonTooLarge { size, max });
+    }
+    Ok(())
+}
+
+/// Register a custom patch kind
+///
+/// # Safety
+/// - kind must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_register_custom_patch_kind(kind: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_register_custom_patch_kind", &[kind]);
+    match CStr::from_ptr(kind).to_str() {
+        Ok(kind) if !kind.is_empty() => {
+            register_custom_patch_kind(kind);
+            FfiResult::success()
+        }
+        Ok(_) => FfiResult::error(&MinimactError::InvalidVNode("Custom patch kind cannot be empty".to_string())),
+        Err(e) => FfiResult::error(&MinimactError::from(e)),
+    }
+}
+
+/// Unregister a custom patch kind (no error if it wasn't registered)
+///
+/// # Safety
+/// - kind must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_unregister_custom_patch_kind(kind: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_unregister_custom_patch_kind", &[kind]);
+    match CStr::from_ptr(kind).to_str() {
+        Ok(kind) => {
+            unregister_custom_patch_kind(kind);
+            FfiResult::success()
+        }
+        Err(e) => FfiResult::error(&MinimactError::from(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame_aggregator::{FrameAggregator, PatchSource};
+    use crate::path::HexPath;
+    use crate::vdom::Patch;
+    use serde_json::json;
+
+    #[test]
+    fn test_custom_patches_validate_and_pass_through() {
+        let sound = |file: &str| Patch::Custom {
+            path: HexPath::root(),
+            kind: "test.PlaySound".to_string(),
+            payload: json!({ "file": file }),
+        };
+        let tree = crate::vdom::VNode::text("");
+        let config = crate::patch_validator::PatchValidatorConfig::default();
+        assert!(crate::patch_validator::validate_patch(&sound("ding.mp3"), &tree, &config).is_err());
+        register_custom_patch_kind("test.PlaySound");
+        assert!(crate::patch_validator::validate_patch(&sound("ding.mp3"), &tree, &config).is_ok());
+
+        // Stable envelope, path optional on the way in
+        let parsed: Patch = serde_json::from_value(json!({ "type": "Custom", "kind": "test.PlaySound", "payload": { "file": "a" } })).unwrap();
+        assert_eq!(parsed, sound("a"));
+
+        // Repeats are separate events, never merged
+        let mut frame = FrameAggregator::new();
+        frame.add(PatchSource::Reconcile, vec![sound("a"), sound("a")]);
+        assert_eq!(frame.finish().len(), 2);
+        unregister_custom_patch_kind("test.PlaySound");
+    }
+}
@@ -12,6 +12,7 @@
 /// Solution:
 /// Recursively traverse state object to find ALL primitive values and their paths.
 
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 
@@ -31,7 +32,7 @@ pub struct StateValueMatch {
 /// Returns all paths where this value appears
 ///
 /// Example:
-/// ```
+/// ```ignore
 /// let state = json!({
 ///     "user": {
 ///         "name": "John",
@@ -117,7 +118,7 @@ pub fn find_value_in_state(
 /// Returns a map of value → paths where it appears
 ///
 /// Example:
-/// ```
+/// ```ignore
 /// let state = json!({
 ///     "user": { "name": "John", "age": 30 },
 ///     "admin": { "name": "Jane" }
@@ -179,12 +180,44 @@ pub fn collect_all_primitive_values(
     result
 }
 
+/// State plus an entry for every nested value under its full path
+///
+/// Lets templates bind nested paths ("user.address.city", "tags[0]") the same way
+/// as top-level keys. Top-level keys win over nested paths spelled the same.
+///
+/// Example:
+/// ```ignore
+/// let state = json!({ "user": { "name": "John" } });
+///
+/// let flat = flatten_state(&state);
+/// // Returns: { "user": { "name": "John" }, "user.name": "John" }
+/// ```
+pub fn flatten_state(state: &HashMap<String, Value>) -> HashMap<String, Value> {
+    fn traverse(value: &Value, current_path: &str, flat: &mut HashMap<String, Value>) {
+        let children: Vec<(String, &Value)> = match value {
+            Value::Object(obj) => obj.iter().map(|(key, val)| (format!("{}.{}", current_path, key), val)).collect(),
+            Value::Array(arr) => arr.iter().enumerate().map(|(i, item)| (format!("{}[{}]", current_path, i), item)).collect(),
+            _ => return,
+        };
+        for (path, child) in children {
+            traverse(child, &path, flat);
+            flat.entry(path).or_insert_with(|| child.clone());
+        }
+    }
+
+    let mut flat = state.clone();
+    for (key, value) in state {
+        traverse(value, key, &mut flat);
+    }
+    flat
+}
+
 /// Find all primitive values in content and match to state paths
 ///
 /// This is the main function used by extract_multi_variable_template
 ///
 /// Example:
-/// ```
+/// ```ignore
 /// let content = "User: John from NYC";
 /// let state = json!({
 ///     "user": { "name": "John", "address": { "city": "NYC" } }
@@ -244,6 +277,97 @@ pub fn find_state_values_in_content(
     filtered
 }
 
+/// What changed between two versions of an object/array state value
+///
+/// Paths use the same notation as above ("address.city", "tags[2]"); a value whose
+/// type changed (or a replaced primitive) is listed under `changed`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDiff {
+    pub changed: Vec<String>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl StateDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.added.is_empty() && self.removed.is_empty()
+    }
+
+    /// Shape of the change, independent of the values and of array positions
+    ///
+    /// "~address.city" (changed), "+tags[]" (added), "-nickname" (removed), sorted and
+    /// joined with ",". Editing item 3 and item 5 of a list give the same signature.
+    pub fn signature(&self) -> String {
+        let mut parts: Vec<String> = [("~", &self.changed), ("+", &self.added), ("-", &self.removed)]
+            .iter()
+            .flat_map(|(sign, paths)| paths.iter().map(move |path| format!("{}{}", sign, strip_indices(path))))
+            .collect();
+        parts.sort();
+        parts.dedup();
+        parts.join(",")
+    }
+}
+
+/// Diff two state values down to their primitives
+pub fn diff_state_values(old: &Value, new: &Value) -> StateDiff {
+    let mut diff = StateDiff::default();
+    diff_into(old, new, "", &mut diff);
+    diff
+}
+
+fn diff_into(old: &Value, new: &Value, path: &str, diff: &mut StateDiff) {
+    let child = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+
+    match (old, new) {
+        (Value::Object(old_obj), Value::Object(new_obj)) => {
+            for (key, old_val) in old_obj {
+                match new_obj.get(key) {
+                    Some(new_val) => diff_into(old_val, new_val, &child(key), diff),
+                    None => diff.removed.push(child(key)),
+                }
+            }
+            for key in new_obj.keys().filter(|key| !old_obj.contains_key(*key)) {
+                diff.added.push(child(key));
+            }
+        }
+        (Value::Array(old_arr), Value::Array(new_arr)) => {
+            for (i, old_item) in old_arr.iter().enumerate() {
+                let item_path = format!("{}[{}]", path, i);
+                match new_arr.get(i) {
+                    Some(new_item) => diff_into(old_item, new_item, &item_path, diff),
+                    None => diff.removed.push(item_path),
+                }
+            }
+            for i in old_arr.len()..new_arr.len() {
+                diff.added.push(format!("{}[{}]", path, i));
+            }
+        }
+        _ if old != new => diff.changed.push(path.to_string()),
+        _ => {}
+    }
+}
+
+/// "items[3].done" → "items[].done"
+fn strip_indices(path: &str) -> String {
+    let mut result = String::with_capacity(path.len());
+    let mut in_index = false;
+    for c in path.chars() {
+        match c {
+            '[' => {
+                in_index = true;
+                result.push(c);
+            }
+            ']' => {
+                in_index = false;
+                result.push(c);
+            }
+            _ if in_index => {}
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -318,4 +442,18 @@ mod tests {
         assert_eq!(matches[1].value_str, "NYC");
         assert_eq!(matches[1].content_position, 16);
     }
+
+    #[test]
+    fn test_diff_state_values() {
+        let old = json!({ "name": "Ann", "tags": ["a"], "nickname": "A", "todos": [{ "done": false }, { "done": false }] });
+        let new = json!({ "name": "Ann", "tags": ["a", "b"], "age": 30, "todos": [{ "done": false }, { "done": true }] });
+
+        let diff = diff_state_values(&old, &new);
+        assert_eq!(diff.changed, vec!["todos[1].done"]);
+        assert_eq!(diff.removed, vec!["nickname"]);
+        let mut added = diff.added.clone();
+        added.sort();
+        assert_eq!(added, vec!["age", "tags[1]"]);
+        assert_eq!(diff.signature(), "+age,+tags[],-nickname,~todos[].done");
+    }
 }
@@ -0,0 +1,261 @@
+//! Component dependency graph for cascading state changes
+//!
+//! A parent's state often reaches its children as props; when it changes the host
+//! re-renders every child too. Diffed and sent one by one, the client shows the
+//! parent updated while its children still show the old value. The registry records
+//! which child props are bound to which parent values (a state key, or one of the
+//! parent's own props, so bindings chain down the tree); `affected` lists the
+//! components one change reaches, parents before children, and `reconcile_cascade`
+//! diffs all their new trees into a single ordered batch.
+//!
+//! The cascade is all or nothing: every tree is diffed before any is stored, and the
+//! trees are only stored if no other update reached one of the components meanwhile
+//! (VersionConflict otherwise, storing nothing).
+
+use crate::error::{FfiResult, MinimactError, Result};
+use crate::last_error::FfiCall;
+use crate::reconciler::reconcile;
+use crate::transaction::{ComponentPatches, ComponentUpdate};
+use crate::tree_store::{json_or_error, TreeStore, TREE_STORE};
+use crate::vdom::Patch;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+/// Child prop `prop` of `child` is computed from `source` of `parent`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PropBinding {
+    pub parent: String,
+    pub child: String,
+    /// A state key of the parent, or one of its props
+    pub source: String,
+    pub prop: String,
+}
+
+/// Prop bindings by parent component
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    bindings: DashMap<String, Vec<PropBinding>>,
+}
+
+/// The patches of every component one state change reached, parents first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CascadeBatch {
+    pub component_id: String,
+    pub state_key: String,
+    pub components: Vec<ComponentPatches>,
+    /// New stored version of each component, in batch order
+    pub versions: Vec<u64>,
+}
+
+impl DependencyGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a binding (once)
+    pub fn bind(&self, binding: PropBinding) {
+        let mut bindings = self.bindings.entry(binding.parent.clone()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Drop every binding from or to a component (it unmounted)
+    pub fn unbind_component(&self, component_id: &str) {
+        self.bindings.remove(component_id);
+        self.bindings.iter_mut().for_each(|mut bindings| bindings.retain(|b| b.child != component_id));
+        self.bindings.retain(|_, bindings| !bindings.is_empty());
+    }
+
+    /// Bindings whose parent is `component_id`
+    pub fn bindings_of(&self, component_id: &str) -> Vec<PropBinding> {
+        self.bindings.get(component_id).map(|b| b.clone()).unwrap_or_default()
+    }
+
+    /// Components a change of `state_key` in `component_id` re-renders: the component
+    /// itself, then the children whose props depend on it, breadth first
+    pub fn affected(&self, component_id: &str, state_key: &str) -> Vec<String> {
+        let mut order = vec![component_id.to_string()];
+        let mut seen = HashSet::from([(component_id.to_string(), state_key.to_string())]);
+        let mut queue = VecDeque::from([(component_id.to_string(), state_key.to_string())]);
+        while let Some((component, key)) = queue.pop_front() {
+            for binding in self.bindings_of(&component).into_iter().filter(|b| b.source == key) {
+                if !order.contains(&binding.child) {
+                    order.push(binding.child.clone());
+                }
+                if seen.insert((binding.child.clone(), binding.prop.clone())) {
+                    queue.push_back((binding.child, binding.prop));
+                }
+            }
+        }
+        order
+    }
+}
+
+/// Diff the new trees of a cascading change against `store` and store them together
+/// Updates are ordered as `affected` lists their components (the others last, as
+/// given); fails without storing anything if one doesn't reconcile, was updated
+/// concurrently (the versions are checked and the trees stored in one step, see
+/// `TreeStore::commit_trees`) or appears twice
+pub fn reconcile_cascade(
+    graph: &DependencyGraph,
+    store: &TreeStore,
+    component_id: &str,
+    state_key: &str,
+    mut updates: Vec<ComponentUpdate>,
+) -> Result<CascadeBatch> {
+    let mut seen = HashSet::new();
+    if let Some(duplicate) = updates.iter().find(|update| !seen.insert(update.component_id.as_str())) {
+        return Err(MinimactError::Validation(format!("Component '{}' is updated twice in one cascade", duplicate.component_id)));
+    }
+
+    let order = graph.affected(component_id, state_key);
+    updates.sort_by_key(|update| order.iter().position(|c| *c == update.component_id).unwrap_or(order.len()));
+
+    let mut components = Vec::with_capacity(updates.len());
+    let mut trees = Vec::with_capacity(updates.len());
+    for update in updates {
+        let stored = store.get(&update.component_id);
+        let patches = match &stored {
+            Some((_, old_tree)) => reconcile(old_tree, &update.tree)?,
+            None => vec![Patch::Replace { path: update.tree.path().clone(), node: update.tree.clone(), preserve: None }],
+        };
+        components.push(ComponentPatches { component_id: update.component_id.clone(), patches });
+        trees.push((update.component_id, stored.map_or(0, |(version, _)| version), update.tree));
+    }
+    let versions = store.commit_trees(trees)?;
+
+    crate::log_debug!("Cascade from '{}'.{}: {} components", component_id, state_key, components.len());
+    Ok(CascadeBatch { component_id: component_id.to_string(), state_key: state_key.to_string(), components, versions })
+}
+
+lazy_static::lazy_static! {
+    /// Bindings used by the FFI
+    pub static ref DEPENDENCIES: DependencyGraph = DependencyGraph::new();
+}
+
+/// Register a prop binding (PropBinding JSON)
+///
+/// # Safety
+/// - binding_json must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_bind_prop(binding_json: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_bind_prop", &[binding_json]);
+    match (|| -> Result<PropBinding> { Ok(serde_json::from_str(CStr::from_ptr(binding_json).to_str()?)?) })() {
+        Ok(binding) => {
+            DEPENDENCIES.bind(binding);
+            FfiResult::success()
+        }
+        Err(e) => FfiResult::error(&e),
+    }
+}
+
+/// Drop every binding from or to a component
+///
+/// # Safety
+/// - component_id must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_unbind_component(component_id: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_unbind_component", &[component_id]);
+    match CStr::from_ptr(component_id).to_str() {
+        Ok(component_id) => {
+            DEPENDENCIES.unbind_component(component_id);
+            FfiResult::success()
+        }
+        Err(e) => FfiResult::error(&MinimactError::from(e)),
+    }
+}
+
+/// Components a state change re-renders, parents first
+/// Returns a JSON array of component ids (or {"error": ...})
+///
+/// # Safety
+/// - component_id and state_key must be valid null-terminated UTF-8 strings
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_affected_components(component_id: *const c_char, state_key: *const c_char) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_affected_components", &[component_id, state_key]);
+    json_or_error((|| -> Result<Vec<String>> {
+        Ok(DEPENDENCIES.affected(CStr::from_ptr(component_id).to_str()?, CStr::from_ptr(state_key).to_str()?))
+    })())
+}
+
+/// Reconcile every component a state change re-rendered as one batch, over the tree store
+/// updates_json is a JSON array of ComponentUpdate
+/// Returns CascadeBatch JSON (or {"error": ...}; a concurrent update is error code 20)
+///
+/// # Safety
+/// - All pointers must be valid null-terminated UTF-8 strings
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_reconcile_cascade(
+    component_id: *const c_char,
+    state_key: *const c_char,
+    updates_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_reconcile_cascade", &[component_id, state_key, updates_json]);
+    json_or_error((|| -> Result<CascadeBatch> {
+        let updates: Vec<ComponentUpdate> = serde_json::from_str(CStr::from_ptr(updates_json).to_str()?)?;
+        let config = crate::validation::ValidationConfig::default();
+        for update in &updates {
+            update.tree.validate(&config)?;
+        }
+        reconcile_cascade(
+            &DEPENDENCIES,
+            &TREE_STORE,
+            CStr::from_ptr(component_id).to_str()?,
+            CStr::from_ptr(state_key).to_str()?,
+            updates,
+        )
+    })())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::HexPath;
+    use crate::vdom::{VNode, VText};
+
+    fn bind(parent: &str, child: &str, source: &str, prop: &str) -> PropBinding {
+        PropBinding { parent: parent.to_string(), child: child.to_string(), source: source.to_string(), prop: prop.to_string() }
+    }
+
+    fn update(component_id: &str, content: &str) -> ComponentUpdate {
+        let tree = VNode::Text(VText { content: content.to_string(), path: HexPath::from("10000000") });
+        ComponentUpdate { component_id: component_id.to_string(), tree }
+    }
+
+    #[test]
+    fn test_cascade_reconciles_parents_first_in_one_batch() {
+        let graph = DependencyGraph::new();
+        graph.bind(bind("App", "Header", "user", "name"));
+        graph.bind(bind("Header", "Avatar", "name", "alt"));
+        graph.bind(bind("App", "Footer", "year", "year"));
+        assert_eq!(graph.affected("App", "user"), vec!["App", "Header", "Avatar"]);
+
+        let store = TreeStore::default();
+        for component in ["App", "Header", "Avatar"] {
+            store.set_tree(component, update(component, "old").tree);
+        }
+        let updates = vec![update("Avatar", "ada"), update("App", "ada"), update("Header", "ada")];
+        let batch = reconcile_cascade(&graph, &store, "App", "user", updates).unwrap();
+        let order: Vec<&str> = batch.components.iter().map(|c| c.component_id.as_str()).collect();
+        assert_eq!(order, vec!["App", "Header", "Avatar"]);
+        assert!(batch.components.iter().all(|c| c.patches.len() == 1));
+
+        graph.unbind_component("Header");
+        assert_eq!(graph.affected("App", "user"), vec!["App"]);
+    }
+
+    #[test]
+    fn test_cascade_rejects_duplicate_components() {
+        let store = TreeStore::default();
+        let updates = vec![update("App", "a"), update("Header", "a"), update("App", "b")];
+        let result = reconcile_cascade(&DependencyGraph::new(), &store, "App", "user", updates);
+        assert!(matches!(result, Err(MinimactError::Validation(_))));
+        assert!(store.is_empty());
+    }
+}
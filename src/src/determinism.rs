@@ -0,0 +1,114 @@
+//! Deterministic mode for reproducible test runs
+//!
+//! Predictor behaviour leaks HashMap iteration order, which std randomizes per
+//! process: which of two equally scored state keys is evicted, the order patterns
+//! are dumped in, the order confidences are summed. With deterministic mode on
+//! (process-wide, off by default), predictors created afterwards hash with a fixed
+//! seed, so the same inputs give the same behaviour on every machine running the
+//! same build. Eviction ties are always broken by key.
+
+use crate::error::{FfiResult, MinimactError};
+use crate::last_error::FfiCall;
+use arc_swap::ArcSwap;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::hash::{BuildHasher, Hasher};
+use std::os::raw::c_char;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeterministicConfig {
+    pub enabled: bool,
+    /// Hash seed; runs with the same seed iterate in the same order
+    pub seed: u64,
+}
+
+lazy_static::lazy_static! {
+    static ref DETERMINISTIC: ArcSwap<DeterministicConfig> = ArcSwap::from_pointee(DeterministicConfig::default());
+}
+
+/// Turn deterministic mode on or off (process-wide)
+pub fn set_deterministic(config: DeterministicConfig) {
+    DETERMINISTIC.store(Arc::new(config));
+}
+
+pub fn deterministic() -> DeterministicConfig {
+    **DETERMINISTIC.load()
+}
+
+/// Hasher builder that uses a fixed seed in deterministic mode and random keys otherwise
+/// The mode is read when the map is created; clones keep their map's hasher
+#[derive(Debug, Clone)]
+pub struct SeededState {
+    seed: Option<u64>,
+    random: RandomState,
+}
+
+impl SeededState {
+    pub fn with_seed(seed: u64) -> Self {
+        Self { seed: Some(seed), random: RandomState::new() }
+    }
+}
+
+impl Default for SeededState {
+    fn default() -> Self {
+        let config = deterministic();
+        Self { seed: config.enabled.then_some(config.seed), random: RandomState::new() }
+    }
+}
+
+impl BuildHasher for SeededState {
+    type Hasher = DefaultHasher;
+
+    fn build_hasher(&self) -> DefaultHasher {
+        match self.seed {
+            Some(seed) => {
+                let mut hasher = DefaultHasher::new();
+                hasher.write_u64(seed);
+                hasher
+            }
+            None => self.random.build_hasher(),
+        }
+    }
+}
+
+/// HashMap whose iteration order is reproducible in deterministic mode
+pub type SeededHashMap<K, V> = HashMap<K, V, SeededState>;
+
+/// Set deterministic mode (DeterministicConfig JSON)
+///
+/// # Safety
+/// - config_json must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_set_deterministic(config_json: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_set_deterministic", &[config_json]);
+    let config = CStr::from_ptr(config_json)
+        .to_str()
+        .map_err(MinimactError::from)
+        .and_then(|json| Ok(serde_json::from_str::<DeterministicConfig>(json)?));
+    match config {
+        Ok(config) => {
+            set_deterministic(config);
+            FfiResult::success()
+        }
+        Err(e) => FfiResult::error(&e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_maps_iterate_identically() {
+        let keys = |seed: u64| {
+            let mut map: SeededHashMap<String, usize> = HashMap::with_hasher(SeededState::with_seed(seed));
+            map.extend((0..64).map(|i| (format!("Component{}::key{}", i % 7, i), i)));
+            map.into_keys().collect::<Vec<_>>()
+        };
+        assert_eq!(keys(7), keys(7));
+    }
+}
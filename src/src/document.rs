@@ -0,0 +1,178 @@
+//! Document head management
+//!
+//! Server components set the page title and meta/link tags by rendering a `head`
+//! element. The reconciler doesn't diff its settings like body markup: the title,
+//! every named meta and every rel link is one document setting, and changes to them
+//! come out as UpdateDocument patches the client applies through `document.title`
+//! and the head's tags. Other head children (a charset meta, scripts) are reconciled
+//! as usual.
+//!
+//! Metas are identified by their `name` or `property` attribute and links by `rel`
+//! (one link per rel: canonical, icon, manifest...).
+
+use crate::path::HexPath;
+use crate::vdom::{Patch, VElement, VNode, VText};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Tag name of the head section
+pub const HEAD_TAG: &str = "head";
+
+/// The document setting an UpdateDocument patch writes
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum DocumentTarget {
+    /// document.title (value: the title text)
+    Title,
+    /// `<meta name|property=... content=...>` (value: content)
+    Meta { name: String },
+    /// `<link rel=... href=...>` (value: href)
+    Link { rel: String },
+}
+
+impl DocumentTarget {
+    /// The setting a head child holds, with its value
+    pub fn of(node: &VNode) -> Option<(DocumentTarget, String)> {
+        let VNode::Element(el) = node else { return None };
+        let prop = |name: &str| el.props.get(name).cloned();
+        match el.tag.as_str() {
+            "title" => {
+                let text: String = el
+                    .children
+                    .iter()
+                    .flatten()
+                    .filter_map(|child| match child {
+                        VNode::Text(text) => Some(text.content.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                Some((DocumentTarget::Title, text))
+            }
+            "meta" => {
+                let name = prop("name").or_else(|| prop("property"))?;
+                Some((DocumentTarget::Meta { name }, prop("content").unwrap_or_default()))
+            }
+            "link" => Some((DocumentTarget::Link { rel: prop("rel")? }, prop("href").unwrap_or_default())),
+            _ => None,
+        }
+    }
+
+    /// A head element holding `value` for this setting, at `path`
+    /// Open Graph style names (with a ':') become `property` metas
+    pub fn element(&self, path: &HexPath, value: &str) -> VNode {
+        let (tag, props, children) = match self {
+            DocumentTarget::Title => {
+                let text = VNode::Text(VText { content: value.to_string(), path: path.child(0) });
+                ("title", HashMap::new(), vec![Some(text)])
+            }
+            DocumentTarget::Meta { name } => {
+                let attr = if name.contains(':') { "property" } else { "name" };
+                let props = HashMap::from([(attr.to_string(), name.clone()), ("content".to_string(), value.to_string())]);
+                ("meta", props, Vec::new())
+            }
+            DocumentTarget::Link { rel } => {
+                let props = HashMap::from([("rel".to_string(), rel.clone()), ("href".to_string(), value.to_string())]);
+                ("link", props, Vec::new())
+            }
+        };
+        VNode::Element(VElement {
+            tag: tag.to_string(),
+            props,
+            children,
+            key: None,
+            path: path.clone(),
+            source: None,
+        })
+    }
+
+    /// Write `value` into an existing element for this setting
+    pub(crate) fn write(&self, el: &mut VElement, value: &str) {
+        match self {
+            DocumentTarget::Title => {
+                let path = el.children.iter().flatten().next().map_or_else(|| el.path.child(0), |c| c.path().clone());
+                el.children = vec![Some(VNode::Text(VText { content: value.to_string(), path }))];
+            }
+            DocumentTarget::Meta { .. } => {
+                el.props.insert("content".to_string(), value.to_string());
+            }
+            DocumentTarget::Link { .. } => {
+                el.props.insert("href".to_string(), value.to_string());
+            }
+        }
+    }
+}
+
+/// Check if a head child is a document setting (diffed by `diff_head`)
+pub fn is_document_setting(node: &VNode) -> bool {
+    DocumentTarget::of(node).is_some()
+}
+
+/// Settings of a head element: target -> (element path, value)
+/// With duplicates the last element wins, as in the browser
+pub fn head_settings(head: &VElement) -> BTreeMap<DocumentTarget, (HexPath, String)> {
+    head.children
+        .iter()
+        .flatten()
+        .filter_map(|child| DocumentTarget::of(child).map(|(target, value)| (target, (child.path().clone(), value))))
+        .collect()
+}
+
+/// UpdateDocument patches turning the settings of `old` into those of `new`
+pub fn diff_head(old: &VElement, new: &VElement) -> Vec<Patch> {
+    let old_settings = head_settings(old);
+    let new_settings = head_settings(new);
+    let mut patches = Vec::new();
+
+    for (target, (old_path, _)) in &old_settings {
+        let moved = new_settings.get(target).is_some_and(|(new_path, _)| new_path != old_path);
+        if moved || !new_settings.contains_key(target) {
+            patches.push(Patch::UpdateDocument { path: old_path.clone(), target: target.clone(), value: None });
+        }
+    }
+    for (target, (new_path, value)) in new_settings {
+        let unchanged = old_settings.get(&target).is_some_and(|(old_path, old_value)| *old_path == new_path && *old_value == value);
+        if !unchanged {
+            patches.push(Patch::UpdateDocument { path: new_path, target, value: Some(value) });
+        }
+    }
+    patches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reconciler::reconcile;
+
+    fn head(title: &str, description: Option<&str>) -> VNode {
+        let path = HexPath::from("10000000");
+        let mut children = vec![Some(DocumentTarget::Title.element(&path.child(0), title))];
+        if let Some(description) = description {
+            children.push(Some(DocumentTarget::Meta { name: "description".to_string() }.element(&path.child(1), description)));
+        }
+        VNode::Element(VElement { tag: HEAD_TAG.to_string(), props: HashMap::new(), children, key: None, path, source: None })
+    }
+
+    #[test]
+    fn test_head_changes_become_document_patches() {
+        let patches = reconcile(&head("Inbox", Some("Mail")), &head("Inbox (3)", None)).unwrap();
+        assert_eq!(
+            patches,
+            vec![
+                Patch::UpdateDocument {
+                    path: HexPath::from("10000000.20000000"),
+                    target: DocumentTarget::Meta { name: "description".to_string() },
+                    value: None,
+                },
+                Patch::UpdateDocument {
+                    path: HexPath::from("10000000.10000000"),
+                    target: DocumentTarget::Title,
+                    value: Some("Inbox (3)".to_string()),
+                },
+            ]
+        );
+
+        let mut tree = head("Inbox", Some("Mail"));
+        crate::apply::apply_patches(&mut tree, &patches).unwrap();
+        assert_eq!(tree, head("Inbox (3)", None));
+    }
+}
@@ -0,0 +1,198 @@
+//! Snapshot and restore of the whole engine
+//!
+//! For blue/green deploys: the outgoing process serializes everything the FFI keeps
+//! warm (predictors by handle, the tree store, registered component metadata and the
+//! metrics counters) into one versioned JSON blob, and the replacement process
+//! restores it before taking traffic. Predictors keep their handles and stored
+//! trees keep their versions, so hosts can carry on with the ids they already hold.
+//!
+//! Restore is meant for a fresh process: it fails without changing anything if one
+//! of the snapshot's predictor handles is already in use.
+
+use crate::error::{MinimactError, Result};
+use crate::ffi::PredictorHandle;
+use crate::last_error::FfiCall;
+use crate::metrics::{MetricsSnapshot, METRICS};
+use crate::predictor::Predictor;
+use crate::tree_store::TREE_STORE;
+use crate::vdom::{ComponentMetadata, VNode};
+use serde::{Deserialize, Serialize};
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+
+/// Format version written by this build; older or newer blobs are rejected
+pub const ENGINE_SNAPSHOT_VERSION: u32 = 1;
+
+/// Everything needed to restore the engine in another process
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EngineSnapshot {
+    pub format_version: u32,
+    pub predictors: Vec<PredictorEntry>,
+    pub trees: Vec<TreeEntry>,
+    pub metadata: Vec<ComponentMetadata>,
+    pub metrics: MetricsSnapshot,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PredictorEntry {
+    pub handle: PredictorHandle,
+    pub predictor: Predictor,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeEntry {
+    pub component_id: String,
+    pub version: u64,
+    pub tree: VNode,
+}
+
+/// What a restore brought back
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestoreSummary {
+    /// Restored predictor handles, ascending
+    pub predictors: Vec<PredictorHandle>,
+    pub trees: usize,
+    pub metadata: usize,
+}
+
+/// Capture the current engine state
+pub fn snapshot_engine() -> EngineSnapshot {
+    let mut predictors: Vec<PredictorEntry> = crate::ffi::predictor_versions()
+        .into_iter()
+        .map(|(handle, predictor)| PredictorEntry { handle, predictor: Predictor::clone(&predictor) })
+        .collect();
+    predictors.sort_by_key(|entry| entry.handle);
+
+    let mut trees: Vec<TreeEntry> = TREE_STORE
+        .export()
+        .into_iter()
+        .map(|(component_id, version, tree)| TreeEntry { component_id, version, tree: VNode::clone(&tree) })
+        .collect();
+    trees.sort_by(|a, b| a.component_id.cmp(&b.component_id));
+
+    let mut metadata = crate::ffi::registered_metadata();
+    metadata.sort_by(|a, b| a.component_id.cmp(&b.component_id));
+
+    EngineSnapshot {
+        format_version: ENGINE_SNAPSHOT_VERSION,
+        predictors,
+        trees,
+        metadata,
+        metrics: METRICS.snapshot(),
+    }
+}
+
+/// Load a snapshot into this process
+pub fn restore_engine(snapshot: EngineSnapshot) -> Result<RestoreSummary> {
+    if snapshot.format_version != ENGINE_SNAPSHOT_VERSION {
+        return Err(MinimactError::Persistence(format!(
+            "Unsupported engine snapshot version {} (expected {})",
+            snapshot.format_version, ENGINE_SNAPSHOT_VERSION
+        )));
+    }
+    if let Some(entry) = snapshot.predictors.iter().find(|entry| crate::ffi::predictor_handle_in_use(entry.handle)) {
+        return Err(MinimactError::Persistence(format!("Predictor handle {} is already in use", entry.handle)));
+    }
+
+    let mut summary = RestoreSummary { predictors: Vec::new(), trees: snapshot.trees.len(), metadata: snapshot.metadata.len() };
+    for entry in snapshot.predictors {
+        if crate::ffi::restore_predictor(entry.handle, entry.predictor) {
+            summary.predictors.push(entry.handle);
+        }
+    }
+    summary.predictors.sort_unstable();
+
+    for entry in snapshot.trees {
+        TREE_STORE.restore_tree(&entry.component_id, entry.version, entry.tree);
+    }
+    for metadata in snapshot.metadata {
+        crate::ffi::register_metadata(metadata);
+    }
+    METRICS.restore_counters(&snapshot.metrics);
+
+    crate::log_info!(
+        "Restored engine snapshot: {} predictors, {} trees, {} metadata",
+        summary.predictors.len(), summary.trees, summary.metadata
+    );
+    Ok(summary)
+}
+
+/// Serialize the whole engine (EngineSnapshot JSON)
+/// Returns null on failure (see minimact_last_error_json)
+///
+/// # Safety
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_engine_snapshot() -> *mut c_char {
+    let _call = FfiCall::named("minimact_engine_snapshot");
+    match serde_json::to_string(&snapshot_engine()) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => {
+            crate::last_error::record_error(&MinimactError::from(e));
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Restore a blob from minimact_engine_snapshot into this process
+/// Returns RestoreSummary JSON (or {"error": ...})
+///
+/// # Safety
+/// - snapshot_json must be a valid null-terminated UTF-8 string
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_engine_restore(snapshot_json: *const c_char) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_engine_restore", &[snapshot_json]);
+    let result = (|| -> Result<String> {
+        let snapshot: EngineSnapshot = serde_json::from_str(CStr::from_ptr(snapshot_json).to_str()?)?;
+        Ok(serde_json::to_string(&restore_engine(snapshot)?)?)
+    })();
+    match result {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => {
+            crate::last_error::record_error(&e);
+            CString::new(serde_json::json!({ "error": e.to_string() }).to_string()).unwrap().into_raw()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree_store::TreeStore;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let tree = VNode::text("Count: 1");
+        let version = TREE_STORE.set_tree("engine-snapshot-test", tree.clone());
+
+        let mut snapshot = snapshot_engine();
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let parsed: EngineSnapshot = serde_json::from_str(&json).unwrap();
+        assert!(parsed.trees.iter().any(|t| t.component_id == "engine-snapshot-test" && t.version == version));
+        TREE_STORE.remove("engine-snapshot-test");
+
+        // Restored trees keep their versions and later versions continue above them
+        let store = TreeStore::default();
+        store.restore_tree("a", 41, tree.clone());
+        assert_eq!(store.version("a"), 41);
+        assert_eq!(store.set_tree("b", tree), 42);
+
+        snapshot.format_version = ENGINE_SNAPSHOT_VERSION + 1;
+        assert!(matches!(restore_engine(snapshot), Err(MinimactError::Persistence(_))));
+    }
+
+    #[test]
+    fn test_restore_refuses_handles_in_use() {
+        let handle = crate::ffi::minimact_predictor_new();
+        let snapshot = EngineSnapshot {
+            format_version: ENGINE_SNAPSHOT_VERSION,
+            predictors: vec![PredictorEntry { handle, predictor: Predictor::new() }],
+            trees: Vec::new(),
+            metadata: Vec::new(),
+            metrics: METRICS.snapshot(),
+        };
+        assert!(restore_engine(snapshot).is_err());
+        crate::ffi::minimact_predictor_destroy(handle);
+    }
+}
@@ -21,6 +21,9 @@ pub enum MinimactError {
     /// Invalid predictor handle
     InvalidHandle(usize),
 
+    /// Handle of a predictor that was destroyed (see `handles`)
+    StaleHandle(usize),
+
     /// Tree exceeds maximum depth
     TreeTooDeep { depth: usize, max: usize },
 
@@ -56,6 +59,24 @@ pub enum MinimactError {
 
     /// Key not found in reorder operation
     KeyNotFound(String),
+
+    /// Diff exceeds the strategy's max_patches (with PatchLimitAction::Error)
+    TooManyPatches { count: usize, max: usize },
+
+    /// Message broker unreachable or rejected a publish/subscribe
+    Broker(String),
+
+    /// Stored tree changed since the version the caller reconciled against
+    VersionConflict { expected: u64, actual: u64 },
+
+    /// JSON would expand to far more memory than its size justifies
+    ExpansionLimitExceeded { input_bytes: usize, estimated_bytes: usize, max_ratio: usize },
+
+    /// Request is well-formed JSON but breaks a rule of the call (batch too big, ...)
+    Validation(String),
+
+    /// A bounded queue or registry is full
+    CapacityExceeded { what: &'static str, max: usize },
 }
 
 impl fmt::Display for MinimactError {
@@ -72,6 +93,9 @@ impl fmt::Display for MinimactError {
             MinimactError::InvalidHandle(handle) => {
                 write!(f, "Invalid predictor handle: {}", handle)
             }
+            MinimactError::StaleHandle(handle) => {
+                write!(f, "Stale predictor handle: {} (the predictor was destroyed)", handle)
+            }
             MinimactError::TreeTooDeep { depth, max } => {
                 write!(f, "Tree too deep: {} levels exceeds max {}", depth, max)
             }
@@ -98,6 +122,22 @@ impl fmt::Display for MinimactError {
             }
             MinimactError::Persistence(msg) => write!(f, "Persistence error: {}", msg),
             MinimactError::KeyNotFound(key) => write!(f, "Key not found: {}", key),
+            MinimactError::TooManyPatches { count, max } => {
+                write!(f, "Too many patches: {} exceeds max {}", count, max)
+            }
+            MinimactError::Broker(msg) => write!(f, "Broker error: {}", msg),
+            MinimactError::VersionConflict { expected, actual } => {
+                write!(f, "Version conflict: expected version {}, stored version is {}", expected, actual)
+            }
+            MinimactError::ExpansionLimitExceeded { input_bytes, estimated_bytes, max_ratio } => {
+                write!(
+                    f,
+                    "Expansion limit exceeded: {} bytes of JSON would take ~{} bytes (max ratio {}x)",
+                    input_bytes, estimated_bytes, max_ratio
+                )
+            }
+            MinimactError::Validation(msg) => write!(f, "Validation error: {}", msg),
+            MinimactError::CapacityExceeded { what, max } => write!(f, "Capacity exceeded: at most {} {}", max, what),
         }
     }
 }
@@ -141,6 +181,13 @@ pub enum ErrorCode {
     TextTooLong = 15,
     Persistence = 16,
     KeyNotFound = 17,
+    TooManyPatches = 18,
+    Broker = 19,
+    VersionConflict = 20,
+    ExpansionLimitExceeded = 21,
+    StaleHandle = 22,
+    Validation = 23,
+    CapacityExceeded = 24,
     Unknown = 999,
 }
 
@@ -152,6 +199,7 @@ impl From<&MinimactError> for ErrorCode {
             MinimactError::PatchTypeMismatch { .. } => ErrorCode::PatchTypeMismatch,
             MinimactError::PredictorFull => ErrorCode::PredictorFull,
             MinimactError::InvalidHandle(_) => ErrorCode::InvalidHandle,
+            MinimactError::StaleHandle(_) => ErrorCode::StaleHandle,
             MinimactError::TreeTooDeep { .. } => ErrorCode::TreeTooDeep,
             MinimactError::TreeTooLarge { .. } => ErrorCode::TreeTooLarge,
             MinimactError::MemoryLimitExceeded { .. } => ErrorCode::MemoryLimitExceeded,
@@ -164,6 +212,12 @@ impl From<&MinimactError> for ErrorCode {
             MinimactError::TextTooLong { .. } => ErrorCode::TextTooLong,
             MinimactError::Persistence(_) => ErrorCode::Persistence,
             MinimactError::KeyNotFound(_) => ErrorCode::KeyNotFound,
+            MinimactError::TooManyPatches { .. } => ErrorCode::TooManyPatches,
+            MinimactError::Broker(_) => ErrorCode::Broker,
+            MinimactError::VersionConflict { .. } => ErrorCode::VersionConflict,
+            MinimactError::ExpansionLimitExceeded { .. } => ErrorCode::ExpansionLimitExceeded,
+            MinimactError::Validation(_) => ErrorCode::Validation,
+            MinimactError::CapacityExceeded { .. } => ErrorCode::CapacityExceeded,
         }
     }
 }
@@ -186,8 +240,9 @@ impl FfiResult {
     pub fn error(err: &MinimactError) -> Self {
         use std::ffi::CString;
 
-        let code = ErrorCode::from(err) as i32;
         let message_str = err.to_string();
+        crate::last_error::record(ErrorCode::from(err), &message_str);
+        let code = ErrorCode::from(err) as i32;
         let message = CString::new(message_str)
             .unwrap_or_else(|_| CString::new("Error creating error message").unwrap())
             .into_raw();
@@ -198,6 +253,7 @@ impl FfiResult {
     pub fn error_str(msg: &str) -> Self {
         use std::ffi::CString;
 
+        crate::last_error::record(ErrorCode::Unknown, msg);
         let message = CString::new(msg)
             .unwrap_or_else(|_| CString::new("Error creating error message").unwrap())
             .into_raw();
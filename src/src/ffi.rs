@@ -1,31 +1,119 @@
+use crate::concurrent_predictor::ConcurrentPredictor;
 use crate::predictor::{Predictor, StateChange, PredictorConfig};
 use crate::vdom::VNode;
 use crate::reconciler::reconcile;
-use crate::error::FfiResult;
+use crate::error::{ErrorCode, FfiResult, MinimactError};
+use crate::last_error::FfiCall;
 use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
-// Thread-safe global predictor storage with per-predictor locks
+// Thread-safe global predictor storage, by generation-tagged handle (see `handles`)
+// Predicts are lock-free and don't wait for learns on the same predictor
 lazy_static::lazy_static! {
-    static ref PREDICTORS: dashmap::DashMap<usize, Predictor> = dashmap::DashMap::new();
+    static ref PREDICTORS: crate::handles::HandleTable<ConcurrentPredictor> = crate::handles::HandleTable::new();
+    static ref HINT_SCHEDULERS: dashmap::DashMap<usize, crate::hint_scheduler::HintScheduler> = dashmap::DashMap::new();
+    /// Metadata registered with minimact_predictor_register_metadata, by component id
+    static ref METADATA: dashmap::DashMap<String, crate::vdom::ComponentMetadata> = dashmap::DashMap::new();
 }
 
-static NEXT_PREDICTOR_ID: AtomicUsize = AtomicUsize::new(1);
+/// Look up a predictor without holding the map's shard lock while using it
+/// Fails with InvalidHandle, or StaleHandle for the handle of a destroyed predictor
+pub(crate) fn predictor(handle: PredictorHandle) -> crate::error::Result<Arc<ConcurrentPredictor>> {
+    PREDICTORS.get(handle)
+}
+
+fn register_predictor(predictor: Predictor) -> PredictorHandle {
+    let handle = PREDICTORS.insert(ConcurrentPredictor::new(predictor));
+    crate::metrics::METRICS.record_predictor_created();
+    handle
+}
+
+/// Current version of every live predictor, by handle (for engine snapshots)
+pub(crate) fn predictor_versions() -> Vec<(PredictorHandle, Arc<Predictor>)> {
+    PREDICTORS.entries().into_iter().map(|(handle, predictor)| (handle, predictor.snapshot())).collect()
+}
+
+/// Register `predictor` under a handle issued by an earlier process (engine restore)
+/// Returns false if the handle's slot is taken
+pub(crate) fn restore_predictor(handle: PredictorHandle, predictor: Predictor) -> bool {
+    let restored = PREDICTORS.insert_at(handle, ConcurrentPredictor::new(predictor));
+    if restored {
+        crate::metrics::METRICS.record_predictor_created();
+    }
+    restored
+}
+
+/// Check if restoring under `handle` would collide with a live predictor
+pub(crate) fn predictor_handle_in_use(handle: PredictorHandle) -> bool {
+    PREDICTORS.slot_taken(handle)
+}
+
+pub(crate) fn registered_metadata() -> Vec<crate::vdom::ComponentMetadata> {
+    METADATA.iter().map(|entry| entry.value().clone()).collect()
+}
+
+pub(crate) fn register_metadata(metadata: crate::vdom::ComponentMetadata) {
+    METADATA.insert(metadata.component_id.clone(), metadata);
+}
 
-/// Opaque handle to a predictor instance
+/// Opaque handle to a predictor instance (slot index and generation, see `handles`)
 pub type PredictorHandle = usize;
 
+/// Record the failure as the thread's last error and return `{"error": message}`
+fn error_json(code: ErrorCode, message: String) -> *mut c_char {
+    crate::last_error::record(code, &message);
+    CString::new(serde_json::json!({ "error": message }).to_string()).unwrap().into_raw()
+}
+
+/// Record undecodable input as the thread's last error and return an empty string
+fn invalid_utf8(err: std::str::Utf8Error) -> *mut c_char {
+    crate::last_error::record_error(&MinimactError::from(err));
+    CString::new("").unwrap().into_raw()
+}
+
+/// Record the failure as the thread's last error and return null
+fn null_on_error<T>(err: impl Into<MinimactError>) -> *mut T {
+    crate::last_error::record_error(&err.into());
+    std::ptr::null_mut()
+}
+
+/// `{"ok": true, "data": prediction}`, with the predicted tree's JSON spliced in from
+/// the serialization cache rather than serialized again
+fn prediction_response(prediction: &crate::predictor::Prediction) -> *mut c_char {
+    #[derive(serde::Serialize)]
+    struct WithoutTree<'a> {
+        state_change: &'a StateChange,
+        predicted_patches: &'a [crate::vdom::Patch],
+        confidence: f32,
+        provenance: crate::predictor::Provenance,
+    }
+
+    let data = WithoutTree {
+        state_change: &prediction.state_change,
+        predicted_patches: &prediction.predicted_patches,
+        confidence: prediction.confidence,
+        provenance: prediction.provenance,
+    };
+    let tree = match prediction.predicted_tree.as_ref().map(crate::tree_json_cache::tree_json).transpose() {
+        Ok(tree) => tree,
+        Err(e) => return null_on_error(e),
+    };
+    let mut json = match serde_json::to_string(&data) {
+        Ok(json) => json,
+        Err(e) => return null_on_error(e),
+    };
+    json.pop();
+    json.push_str(",\"predicted_tree\":");
+    json.push_str(tree.as_deref().unwrap_or("null"));
+    CString::new(format!("{{\"ok\":true,\"data\":{}}}}}", json)).unwrap().into_raw()
+}
 
 /// Create a new predictor instance
 /// Returns a handle to the predictor
 #[no_mangle]
 pub extern "C" fn minimact_predictor_new() -> PredictorHandle {
-    let predictor = Predictor::new();
-    let id = NEXT_PREDICTOR_ID.fetch_add(1, Ordering::SeqCst);
-    PREDICTORS.insert(id, predictor);
-    crate::metrics::METRICS.record_predictor_created();
-    id
+    register_predictor(Predictor::new())
 }
 
 /// Create a new predictor with custom configuration
@@ -41,22 +129,25 @@ pub extern "C" fn minimact_predictor_new_with_config(
         max_state_keys: 1000,
         max_memory_bytes: 100 * 1024 * 1024, // 100 MB default
         eviction_policy: crate::predictor::EvictionPolicy::LeastFrequentlyUsed,
+        similarity: crate::predictor::PatternSimilarity::default(),
+        negative: crate::predictor::NegativeCaching::default(),
+        duplicates: crate::predictor::DuplicateSuppression::default(),
+        localization: crate::predictor::Localization::default(),
     };
-    let predictor = Predictor::with_config(config);
-    let id = NEXT_PREDICTOR_ID.fetch_add(1, Ordering::SeqCst);
-    PREDICTORS.insert(id, predictor);
-    crate::metrics::METRICS.record_predictor_created();
-    id
+    register_predictor(Predictor::with_config(config))
 }
 
 /// Destroy a predictor instance
 #[no_mangle]
 pub extern "C" fn minimact_predictor_destroy(handle: PredictorHandle) -> FfiResult {
-    if PREDICTORS.remove(&handle).is_some() {
-        crate::metrics::METRICS.record_predictor_destroyed();
-        FfiResult::success()
-    } else {
-        FfiResult::error_str("Invalid predictor handle")
+    let _call = FfiCall::named("minimact_predictor_destroy");
+    HINT_SCHEDULERS.remove(&handle);
+    match PREDICTORS.remove(handle) {
+        Ok(_) => {
+            crate::metrics::METRICS.record_predictor_destroyed();
+            FfiResult::success()
+        }
+        Err(e) => FfiResult::error(&e),
     }
 }
 
@@ -71,14 +162,15 @@ pub unsafe extern "C" fn minimact_reconcile(
     old_json: *const c_char,
     new_json: *const c_char,
 ) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_reconcile", &[old_json, new_json]);
     let old_str = match CStr::from_ptr(old_json).to_str() {
         Ok(s) => s,
-        Err(_) => return CString::new("").unwrap().into_raw(),
+        Err(e) => return invalid_utf8(e),
     };
 
     let new_str = match CStr::from_ptr(new_json).to_str() {
         Ok(s) => s,
-        Err(_) => return CString::new("").unwrap().into_raw(),
+        Err(e) => return invalid_utf8(e),
     };
 
     // Use safe deserialization with size limits
@@ -87,352 +179,1497 @@ pub unsafe extern "C" fn minimact_reconcile(
     let old_node: VNode = match crate::validation::deserialize_vnode_safe(old_str, &validation_config) {
         Ok(n) => n,
         Err(e) => {
-            let err = format!("{{\"error\": \"Failed to parse old tree: {}\"}}", e);
-            return CString::new(err).unwrap().into_raw();
+            return error_json(ErrorCode::from(&e), format!("Failed to parse old tree: {}", e));
         }
     };
 
     let new_node: VNode = match crate::validation::deserialize_vnode_safe(new_str, &validation_config) {
         Ok(n) => n,
         Err(e) => {
-            let err = format!("{{\"error\": \"Failed to parse new tree: {}\"}}", e);
-            return CString::new(err).unwrap().into_raw();
+            return error_json(ErrorCode::from(&e), format!("Failed to parse new tree: {}", e));
         }
     };
 
     let patches = match reconcile(&old_node, &new_node) {
         Ok(p) => p,
         Err(e) => {
-            let err = format!("{{\"error\": \"Reconciliation failed: {}\"}}", e);
-            return CString::new(err).unwrap().into_raw();
+            return error_json(ErrorCode::from(&e), format!("Reconciliation failed: {}", e));
         }
     };
 
     match serde_json::to_string(&patches) {
         Ok(json) => CString::new(json).unwrap().into_raw(),
         Err(e) => {
-            let err = format!("{{\"error\": \"Failed to serialize patches: {}\"}}", e);
-            CString::new(err).unwrap().into_raw()
+            error_json(ErrorCode::Serialization, format!("Failed to serialize patches: {}", e))
         }
     }
 }
 
-/// Learn from a state change
+/// Reconcile a batch of independent tree pairs in parallel
+/// pairs_json is a JSON array of {"old": tree, "new": tree}; returns a JSON array with
+/// one {"ok": true, "data": patches} or {"ok": false, "error": message} per pair, in order
+/// The payload may be as large as MAX_BATCH_SIZE pairs of trees at the single-tree limits
 ///
 /// # Safety
-/// - All JSON pointers must be valid null-terminated UTF-8 strings
-/// - all_state_json can be null if not available
+/// - pairs_json must be a valid null-terminated UTF-8 string
+/// - The returned pointer must be freed using minimact_free_string
 #[no_mangle]
-pub unsafe extern "C" fn minimact_predictor_learn(
-    handle: PredictorHandle,
-    state_change_json: *const c_char,
-    old_tree_json: *const c_char,
-    new_tree_json: *const c_char,
-    all_state_json: *const c_char,
-) -> FfiResult {
-    let state_change_str = match CStr::from_ptr(state_change_json).to_str() {
+pub unsafe extern "C" fn minimact_reconcile_bulk(pairs_json: *const c_char) -> *mut c_char {
+    #[derive(serde::Deserialize)]
+    struct TreePair {
+        old: VNode,
+        new: VNode,
+    }
+
+    let _call = FfiCall::enter("minimact_reconcile_bulk", &[pairs_json]);
+    let pairs_str = match CStr::from_ptr(pairs_json).to_str() {
         Ok(s) => s,
-        Err(_) => return FfiResult::error_str("Invalid state_change_json encoding"),
+        Err(e) => return invalid_utf8(e),
     };
 
-    let old_tree_str = match CStr::from_ptr(old_tree_json).to_str() {
+    // The payload is scanned as a whole before serde allocates it; each tree then
+    // gets the normalization and limits a single reconcile would apply
+    let validation_config = crate::validation::ValidationConfig::default();
+    let parsed = crate::validation::check_json_input(pairs_str, &validation_config.for_batch(2 * crate::schema::MAX_BATCH_SIZE))
+        .and_then(|_| Ok(serde_json::from_str::<Vec<TreePair>>(pairs_str)?));
+    let pairs = match parsed {
+        Ok(pairs) => pairs,
+        Err(e) => return error_json(ErrorCode::from(&e), format!("Failed to parse tree pairs: {}", e)),
+    };
+
+    let (valid, invalid): (Vec<_>, Vec<_>) = pairs
+        .into_iter()
+        .enumerate()
+        .map(|(i, mut pair)| {
+            let prepared = crate::validation::prepare_vnode(&mut pair.old, &validation_config)
+                .and_then(|_| crate::validation::prepare_vnode(&mut pair.new, &validation_config));
+            match prepared {
+                Ok(()) => Ok((i, (pair.old, pair.new))),
+                Err(e) => Err((i, e)),
+            }
+        })
+        .partition(|pair| pair.is_ok());
+
+    let (indices, trees): (Vec<usize>, Vec<(VNode, VNode)>) = valid.into_iter().flatten().unzip();
+    let mut results: Vec<(usize, crate::error::Result<Vec<crate::vdom::Patch>>)> = indices
+        .into_iter()
+        .zip(crate::reconciler::reconcile_bulk(trees))
+        .chain(invalid.into_iter().filter_map(|pair| pair.err()).map(|(i, e)| (i, Err(e))))
+        .collect();
+    results.sort_by_key(|(i, _)| *i);
+
+    let response: Vec<serde_json::Value> = results
+        .into_iter()
+        .map(|(_, result)| match result {
+            Ok(patches) => serde_json::json!({ "ok": true, "data": patches }),
+            Err(e) => serde_json::json!({ "ok": false, "error": e.to_string() }),
+        })
+        .collect();
+    match serde_json::to_string(&response) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => error_json(ErrorCode::Serialization, format!("Failed to serialize patches: {}", e)),
+    }
+}
+
+/// Reconcile two VNode trees with a specific strategy and return patches as JSON
+/// strategy_json is a ReconcileStrategy, or one of the presets "surgical" / "replace_heavy"
+///
+/// # Safety
+/// - old_json, new_json and strategy_json must be valid null-terminated UTF-8 strings
+/// - strategy_json may be null to use the default (surgical) strategy
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_reconcile_with_strategy(
+    old_json: *const c_char,
+    new_json: *const c_char,
+    strategy_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_reconcile_with_strategy", &[old_json, new_json, strategy_json]);
+    let old_str = match CStr::from_ptr(old_json).to_str() {
         Ok(s) => s,
-        Err(_) => return FfiResult::error_str("Invalid old_tree_json encoding"),
+        Err(e) => return invalid_utf8(e),
     };
 
-    let new_tree_str = match CStr::from_ptr(new_tree_json).to_str() {
+    let new_str = match CStr::from_ptr(new_json).to_str() {
         Ok(s) => s,
-        Err(_) => return FfiResult::error_str("Invalid new_tree_json encoding"),
+        Err(e) => return invalid_utf8(e),
     };
 
-    let state_change: StateChange = match serde_json::from_str(state_change_str) {
-        Ok(sc) => sc,
-        Err(e) => return FfiResult::error_str(&format!("Failed to parse state change: {}", e)),
+    let strategy = if strategy_json.is_null() {
+        crate::reconciler::ReconcileStrategy::default()
+    } else {
+        let strategy_str = match CStr::from_ptr(strategy_json).to_str() {
+            Ok(s) => s,
+            Err(e) => return invalid_utf8(e),
+        };
+        match strategy_str.trim() {
+            "surgical" => crate::reconciler::ReconcileStrategy::surgical(),
+            "replace_heavy" => crate::reconciler::ReconcileStrategy::replace_heavy(),
+            json => match serde_json::from_str(json) {
+                Ok(s) => s,
+                Err(e) => {
+                    return error_json(ErrorCode::Serialization, format!("Failed to parse strategy: {}", e));
+                }
+            },
+        }
     };
 
     let validation_config = crate::validation::ValidationConfig::default();
 
-    let old_tree: VNode = match crate::validation::deserialize_vnode_safe(old_tree_str, &validation_config) {
-        Ok(t) => t,
-        Err(e) => return FfiResult::error_str(&format!("Failed to parse old tree: {}", e)),
+    let old_node: VNode = match crate::validation::deserialize_vnode_safe(old_str, &validation_config) {
+        Ok(n) => n,
+        Err(e) => {
+            return error_json(ErrorCode::from(&e), format!("Failed to parse old tree: {}", e));
+        }
     };
 
-    let new_tree: VNode = match crate::validation::deserialize_vnode_safe(new_tree_str, &validation_config) {
-        Ok(t) => t,
-        Err(e) => return FfiResult::error_str(&format!("Failed to parse new tree: {}", e)),
+    let new_node: VNode = match crate::validation::deserialize_vnode_safe(new_str, &validation_config) {
+        Ok(n) => n,
+        Err(e) => {
+            return error_json(ErrorCode::from(&e), format!("Failed to parse new tree: {}", e));
+        }
     };
 
-    // Parse all_state if provided (can be null)
-    let all_state = if all_state_json.is_null() {
-        None
-    } else {
-        match CStr::from_ptr(all_state_json).to_str() {
-            Ok(s) => {
-                match serde_json::from_str::<std::collections::HashMap<String, serde_json::Value>>(s) {
-                    Ok(state) => Some(state),
-                    Err(e) => return FfiResult::error_str(&format!("Failed to parse all_state: {}", e)),
-                }
-            }
-            Err(_) => return FfiResult::error_str("Invalid all_state_json encoding"),
+    let patches = match crate::reconciler::reconcile_with_strategy(&old_node, &new_node, &strategy) {
+        Ok(p) => p,
+        Err(e) => {
+            return error_json(ErrorCode::from(&e), format!("Reconciliation failed: {}", e));
         }
     };
 
-    if let Some(mut predictor) = PREDICTORS.get_mut(&handle) {
-        match predictor.learn(state_change, &old_tree, &new_tree, all_state.as_ref()) {
-            Ok(()) => FfiResult::success(),
-            Err(e) => FfiResult::error_str(&format!("Learn failed: {}", e)),
+    match serde_json::to_string(&patches) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => {
+            error_json(ErrorCode::Serialization, format!("Failed to serialize patches: {}", e))
         }
-    } else {
-        FfiResult::error_str("Invalid predictor handle")
     }
 }
 
-/// Predict patches for a state change with metadata (Babel-extracted templates)
-/// Returns JSON string with prediction or null if no prediction available
+/// Reconcile two VNode trees with virtualized lists and return patches as JSON
+/// windows_json maps list paths to their visible rows: {"10000000.20000000": {"start": 0, "end": 30}}
 ///
 /// # Safety
-/// - All JSON pointers must be valid null-terminated UTF-8 strings
+/// - old_json, new_json and windows_json must be valid null-terminated UTF-8 strings
 /// - The returned pointer must be freed using minimact_free_string
 #[no_mangle]
-pub unsafe extern "C" fn minimact_predictor_predict_with_metadata(
-    handle: PredictorHandle,
-    state_change_json: *const c_char,
-    current_tree_json: *const c_char,
-    metadata_json: *const c_char,
+pub unsafe extern "C" fn minimact_reconcile_windowed(
+    old_json: *const c_char,
+    new_json: *const c_char,
+    windows_json: *const c_char,
 ) -> *mut c_char {
-    let state_change_str = match CStr::from_ptr(state_change_json).to_str() {
-        Ok(s) => s,
-        Err(_) => return std::ptr::null_mut(),
-    };
+    let _call = FfiCall::enter("minimact_reconcile_windowed", &[old_json, new_json, windows_json]);
+    let result = (|| -> crate::error::Result<String> {
+        let validation_config = crate::validation::ValidationConfig::default();
+        let old_node = crate::validation::deserialize_vnode_safe(CStr::from_ptr(old_json).to_str()?, &validation_config)?;
+        let new_node = crate::validation::deserialize_vnode_safe(CStr::from_ptr(new_json).to_str()?, &validation_config)?;
+        let windows: crate::reconciler::ListWindows = serde_json::from_str(CStr::from_ptr(windows_json).to_str()?)?;
+        let strategy = crate::reconciler::ReconcileStrategy::default();
+        let patches = crate::reconciler::reconcile_windowed(&old_node, &new_node, &strategy, &windows)?;
+        Ok(serde_json::to_string(&patches)?)
+    })();
 
-    let current_tree_str = match CStr::from_ptr(current_tree_json).to_str() {
-        Ok(s) => s,
-        Err(_) => return std::ptr::null_mut(),
-    };
+    match result {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => error_json(ErrorCode::from(&e), format!("Windowed reconciliation failed: {}", e)),
+    }
+}
 
-    let metadata_str = match CStr::from_ptr(metadata_json).to_str() {
+/// Enable or disable devtools patch annotations (process-wide)
+#[no_mangle]
+pub extern "C" fn minimact_set_devtools(enabled: bool) {
+    crate::annotations::set_devtools_enabled(enabled);
+}
+
+/// Reconcile two VNode trees and return {"patches": [...], "annotations": [...]} as JSON
+/// "annotations" is only present while devtools are enabled (see minimact_set_devtools)
+///
+/// # Safety
+/// - old_json and new_json must be valid null-terminated UTF-8 strings
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_reconcile_annotated(
+    old_json: *const c_char,
+    new_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_reconcile_annotated", &[old_json, new_json]);
+    let old_str = match CStr::from_ptr(old_json).to_str() {
         Ok(s) => s,
-        Err(_) => return std::ptr::null_mut(),
+        Err(e) => return invalid_utf8(e),
     };
 
-    let state_change: StateChange = match serde_json::from_str(state_change_str) {
-        Ok(sc) => sc,
-        Err(_) => return std::ptr::null_mut(),
+    let new_str = match CStr::from_ptr(new_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return invalid_utf8(e),
     };
 
     let validation_config = crate::validation::ValidationConfig::default();
 
-    let current_tree: VNode = match crate::validation::deserialize_vnode_safe(current_tree_str, &validation_config) {
-        Ok(t) => t,
-        Err(_) => return std::ptr::null_mut(),
+    let old_node: VNode = match crate::validation::deserialize_vnode_safe(old_str, &validation_config) {
+        Ok(n) => n,
+        Err(e) => {
+            return error_json(ErrorCode::from(&e), format!("Failed to parse old tree: {}", e));
+        }
     };
 
-    let metadata: crate::vdom::ComponentMetadata = match serde_json::from_str(metadata_str) {
-        Ok(m) => m,
+    let new_node: VNode = match crate::validation::deserialize_vnode_safe(new_str, &validation_config) {
+        Ok(n) => n,
         Err(e) => {
-            eprintln!("[Minimact] Failed to parse metadata: {}", e);
-            return std::ptr::null_mut();
+            return error_json(ErrorCode::from(&e), format!("Failed to parse new tree: {}", e));
         }
     };
 
-    if let Some(mut predictor) = PREDICTORS.get_mut(&handle) {
-        // Try to predict with metadata first (100% coverage from Babel templates)
-        if let Some(prediction) = predictor.predict_with_metadata(&state_change, &current_tree, Some(&metadata)) {
-            let result = serde_json::json!({
-                "ok": true,
-                "data": prediction
-            });
-            match serde_json::to_string(&result) {
-                Ok(json) => CString::new(json).unwrap().into_raw(),
-                Err(_) => std::ptr::null_mut(),
-            }
-        } else {
-            // Fallback to learned patterns if metadata doesn't have templates
-            if let Some(prediction) = predictor.predict(&state_change, &current_tree) {
-                let result = serde_json::json!({
-                    "ok": true,
-                    "data": prediction
-                });
-                match serde_json::to_string(&result) {
-                    Ok(json) => CString::new(json).unwrap().into_raw(),
-                    Err(_) => std::ptr::null_mut(),
-                }
-            } else {
-                let error_response = serde_json::json!({
-                    "ok": false,
-                    "error": "No prediction available"
-                });
-                match serde_json::to_string(&error_response) {
-                    Ok(json) => CString::new(json).unwrap().into_raw(),
-                    Err(_) => std::ptr::null_mut(),
-                }
-            }
+    let patches = match reconcile(&old_node, &new_node) {
+        Ok(p) => p,
+        Err(e) => {
+            return error_json(ErrorCode::from(&e), format!("Reconciliation failed: {}", e));
         }
-    } else {
-        let error_response = serde_json::json!({
-            "ok": false,
-            "error": "Invalid predictor handle"
-        });
-        match serde_json::to_string(&error_response) {
-            Ok(json) => CString::new(json).unwrap().into_raw(),
-            Err(_) => std::ptr::null_mut(),
+    };
+
+    let mut response = serde_json::json!({ "patches": patches });
+    if let Some(annotations) = crate::annotations::annotations_if_enabled(&patches, &old_node) {
+        response["annotations"] = serde_json::json!(annotations);
+    }
+
+    match serde_json::to_string(&response) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => {
+            error_json(ErrorCode::Serialization, format!("Failed to serialize patches: {}", e))
         }
     }
 }
 
-/// Predict patches for a state change
-/// Returns JSON string with prediction or null if no prediction available
+/// Reconcile two VNode trees as part of the request identified by correlation_id
+/// Returns patches as JSON; the reconcile time is added to the request's timing breakdown
 ///
 /// # Safety
-/// - All JSON pointers must be valid null-terminated UTF-8 strings
+/// - old_json, new_json and correlation_id must be valid null-terminated UTF-8 strings
 /// - The returned pointer must be freed using minimact_free_string
 #[no_mangle]
-pub unsafe extern "C" fn minimact_predictor_predict(
-    handle: PredictorHandle,
-    state_change_json: *const c_char,
-    current_tree_json: *const c_char,
+pub unsafe extern "C" fn minimact_reconcile_traced(
+    old_json: *const c_char,
+    new_json: *const c_char,
+    correlation_id: *const c_char,
 ) -> *mut c_char {
-    let state_change_str = match CStr::from_ptr(state_change_json).to_str() {
+    let _call = FfiCall::enter("minimact_reconcile_traced", &[old_json, new_json, correlation_id]);
+    let old_str = match CStr::from_ptr(old_json).to_str() {
         Ok(s) => s,
-        Err(_) => return std::ptr::null_mut(),
+        Err(e) => return invalid_utf8(e),
     };
 
-    let current_tree_str = match CStr::from_ptr(current_tree_json).to_str() {
+    let new_str = match CStr::from_ptr(new_json).to_str() {
         Ok(s) => s,
-        Err(_) => return std::ptr::null_mut(),
+        Err(e) => return invalid_utf8(e),
     };
 
-    let state_change: StateChange = match serde_json::from_str(state_change_str) {
-        Ok(sc) => sc,
-        Err(_) => return std::ptr::null_mut(),
+    let correlation_id = match CStr::from_ptr(correlation_id).to_str() {
+        Ok(s) => s,
+        Err(e) => return invalid_utf8(e),
     };
 
     let validation_config = crate::validation::ValidationConfig::default();
 
-    let current_tree: VNode = match crate::validation::deserialize_vnode_safe(current_tree_str, &validation_config) {
-        Ok(t) => t,
-        Err(_) => return std::ptr::null_mut(),
+    let old_node: VNode = match crate::validation::deserialize_vnode_safe(old_str, &validation_config) {
+        Ok(n) => n,
+        Err(e) => {
+            return error_json(ErrorCode::from(&e), format!("Failed to parse old tree: {}", e));
+        }
     };
 
-    if let Some(mut predictor) = PREDICTORS.get_mut(&handle) {
-        if let Some(prediction) = predictor.predict(&state_change, &current_tree) {
-            // Return successful prediction wrapped in Result format
-            let result = serde_json::json!({
-                "ok": true,
-                "data": prediction
-            });
-            match serde_json::to_string(&result) {
-                Ok(json) => CString::new(json).unwrap().into_raw(),
-                Err(_) => std::ptr::null_mut(),
-            }
-        } else {
-            // Return error response when no prediction is available
-            let error_response = serde_json::json!({
-                "ok": false,
-                "error": "No prediction available (confidence too low or no matching pattern)"
-            });
-            match serde_json::to_string(&error_response) {
-                Ok(json) => CString::new(json).unwrap().into_raw(),
-                Err(_) => std::ptr::null_mut(),
-            }
+    let new_node: VNode = match crate::validation::deserialize_vnode_safe(new_str, &validation_config) {
+        Ok(n) => n,
+        Err(e) => {
+            return error_json(ErrorCode::from(&e), format!("Failed to parse new tree: {}", e));
         }
-    } else {
-        // Return error response for invalid handle
-        let error_response = serde_json::json!({
-            "ok": false,
-            "error": "Invalid predictor handle"
-        });
-        match serde_json::to_string(&error_response) {
-            Ok(json) => CString::new(json).unwrap().into_raw(),
-            Err(_) => std::ptr::null_mut(),
+    };
+
+    let strategy = crate::reconciler::ReconcileStrategy::default();
+    let patches = match crate::reconciler::reconcile_traced(&old_node, &new_node, &strategy, correlation_id) {
+        Ok(p) => p,
+        Err(e) => {
+            return error_json(ErrorCode::from(&e), format!("Reconciliation failed: {}", e));
+        }
+    };
+
+    match serde_json::to_string(&patches) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => {
+            error_json(ErrorCode::Serialization, format!("Failed to serialize patches: {}", e))
         }
     }
 }
 
-/// Predict patches based on hint (for usePredictHint)
+/// Record a timing span measured by the host (e.g. "Render", "Send", "ApplyAck")
 ///
 /// # Safety
-/// - All JSON pointers must be valid null-terminated UTF-8 strings
-/// - The returned pointer must be freed using minimact_free_string
+/// - correlation_id and stage must be valid null-terminated UTF-8 strings
 #[no_mangle]
-pub unsafe extern "C" fn minimact_predictor_predict_hint(
-    handle: PredictorHandle,
-    hint_id: *const c_char,
-    component_id: *const c_char,
-    state_changes_json: *const c_char,
-    current_tree_json: *const c_char,
-) -> *mut c_char {
-    let hint_id_str = match CStr::from_ptr(hint_id).to_str() {
+pub unsafe extern "C" fn minimact_record_timing(
+    correlation_id: *const c_char,
+    stage: *const c_char,
+    duration_us: u64,
+) -> FfiResult {
+    let _call = FfiCall::enter("minimact_record_timing", &[correlation_id, stage]);
+    let correlation_id = match CStr::from_ptr(correlation_id).to_str() {
         Ok(s) => s,
-        Err(_) => return std::ptr::null_mut(),
+        Err(_) => return FfiResult::error_str("Invalid UTF-8 in correlation id"),
     };
 
-    let component_id_str = match CStr::from_ptr(component_id).to_str() {
+    let stage_str = match CStr::from_ptr(stage).to_str() {
         Ok(s) => s,
-        Err(_) => return std::ptr::null_mut(),
+        Err(_) => return FfiResult::error_str("Invalid UTF-8 in stage"),
     };
 
-    let state_changes_str = match CStr::from_ptr(state_changes_json).to_str() {
+    let stage: crate::correlation::TraceStage = match serde_json::from_value(serde_json::Value::String(stage_str.to_string())) {
         Ok(s) => s,
-        Err(_) => return std::ptr::null_mut(),
+        Err(_) => return FfiResult::error_str(&format!("Unknown trace stage: {}", stage_str)),
     };
 
-    let current_tree_str = match CStr::from_ptr(current_tree_json).to_str() {
-        Ok(s) => s,
-        Err(_) => return std::ptr::null_mut(),
-    };
+    crate::correlation::record_span(correlation_id, stage, std::time::Duration::from_micros(duration_us), None);
+    FfiResult::success()
+}
 
-    let state_changes: Vec<StateChange> = match serde_json::from_str(state_changes_str) {
-        Ok(sc) => sc,
-        Err(_) => return std::ptr::null_mut(),
+/// Get the timing breakdown recorded for a correlation id as JSON
+///
+/// # Safety
+/// - correlation_id must be a valid null-terminated UTF-8 string
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_get_timings(correlation_id: *const c_char) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_get_timings", &[correlation_id]);
+    let correlation_id = match CStr::from_ptr(correlation_id).to_str() {
+        Ok(s) => s,
+        Err(e) => return invalid_utf8(e),
+    };
+
+    let breakdown = match crate::correlation::timing_breakdown(correlation_id) {
+        Some(b) => b,
+        None => {
+            return error_json(ErrorCode::Unknown, format!("No timings for correlation id: {}", correlation_id));
+        }
+    };
+
+    match serde_json::to_string(&breakdown) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => {
+            error_json(ErrorCode::Serialization, format!("Failed to serialize timings: {}", e))
+        }
+    }
+}
+
+/// Configure the process-wide reconcile rate limiter
+///
+/// # Safety
+/// - config_json must be a valid null-terminated UTF-8 string (a RateLimitConfig)
+#[no_mangle]
+pub unsafe extern "C" fn minimact_rate_limit_configure(config_json: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_rate_limit_configure", &[config_json]);
+    let config_str = match CStr::from_ptr(config_json).to_str() {
+        Ok(s) => s,
+        Err(_) => return FfiResult::error_str("Invalid UTF-8 in rate limit config"),
+    };
+
+    match serde_json::from_str::<crate::rate_limit::RateLimitConfig>(config_str) {
+        Ok(config) => {
+            crate::rate_limit::RATE_LIMITER.set_config(config);
+            FfiResult::success()
+        }
+        Err(e) => FfiResult::error_str(&format!("Failed to parse rate limit config: {}", e)),
+    }
+}
+
+/// Reconcile an update for a component, subject to the rate limiter
+/// Returns {"decision": "Allow" | "Suppress" | "Flush", "patches": [...]} as JSON
+///
+/// # Safety
+/// - component_id, old_json and new_json must be valid null-terminated UTF-8 strings
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_reconcile_rate_limited(
+    component_id: *const c_char,
+    old_json: *const c_char,
+    new_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_reconcile_rate_limited", &[component_id, old_json, new_json]);
+    let component_id = match CStr::from_ptr(component_id).to_str() {
+        Ok(s) => s,
+        Err(e) => return invalid_utf8(e),
+    };
+
+    let old_str = match CStr::from_ptr(old_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return invalid_utf8(e),
+    };
+
+    let new_str = match CStr::from_ptr(new_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return invalid_utf8(e),
+    };
+
+    let validation_config = crate::validation::ValidationConfig::default();
+
+    let old_node: VNode = match crate::validation::deserialize_vnode_safe(old_str, &validation_config) {
+        Ok(n) => n,
+        Err(e) => {
+            return error_json(ErrorCode::from(&e), format!("Failed to parse old tree: {}", e));
+        }
+    };
+
+    let new_node: VNode = match crate::validation::deserialize_vnode_safe(new_str, &validation_config) {
+        Ok(n) => n,
+        Err(e) => {
+            return error_json(ErrorCode::from(&e), format!("Failed to parse new tree: {}", e));
+        }
+    };
+
+    let (decision, patches) = match crate::rate_limit::reconcile_rate_limited(component_id, &old_node, &new_node) {
+        Ok(r) => r,
+        Err(e) => {
+            return error_json(ErrorCode::from(&e), format!("Reconciliation failed: {}", e));
+        }
+    };
+
+    let response = serde_json::json!({ "decision": decision, "patches": patches });
+    match serde_json::to_string(&response) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => {
+            error_json(ErrorCode::Serialization, format!("Failed to serialize patches: {}", e))
+        }
+    }
+}
+
+/// Take pending circuit breaker events (Tripped/Reset) as a JSON array
+///
+/// # Safety
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_rate_limit_drain_events() -> *mut c_char {
+    let _call = FfiCall::named("minimact_rate_limit_drain_events");
+    let events = crate::rate_limit::RATE_LIMITER.drain_events();
+    match serde_json::to_string(&events) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => {
+            error_json(ErrorCode::Serialization, format!("Failed to serialize events: {}", e))
+        }
+    }
+}
+
+/// Close breakers whose cooldown is over; returns their component ids as a JSON array
+/// Each listed component should be sent a Replace of its latest tree
+///
+/// # Safety
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_rate_limit_close_expired() -> *mut c_char {
+    let _call = FfiCall::named("minimact_rate_limit_close_expired");
+    let closed = crate::rate_limit::RATE_LIMITER.close_expired();
+    match serde_json::to_string(&closed) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => {
+            error_json(ErrorCode::Serialization, format!("Failed to serialize component ids: {}", e))
+        }
+    }
+}
+
+/// Generate resync patches rebuilding the subtree at `from_path` from the authoritative tree
+/// Returns a ResyncMessage as JSON (or {"error": ...} on failure)
+///
+/// # Safety
+/// - tree_json and from_path must be valid null-terminated UTF-8 strings
+/// - from_path may be an empty string to rebuild the whole tree
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_generate_resync(
+    tree_json: *const c_char,
+    from_path: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_generate_resync", &[tree_json, from_path]);
+    let tree_str = match CStr::from_ptr(tree_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return invalid_utf8(e),
+    };
+
+    let from_path_str = match CStr::from_ptr(from_path).to_str() {
+        Ok(s) => s,
+        Err(e) => return invalid_utf8(e),
+    };
+
+    let validation_config = crate::validation::ValidationConfig::default();
+    let tree: VNode = match crate::validation::deserialize_vnode_safe(tree_str, &validation_config) {
+        Ok(t) => t,
+        Err(e) => {
+            return error_json(ErrorCode::from(&e), format!("Failed to parse tree: {}", e));
+        }
+    };
+
+    let message = match crate::resync::generate_resync_message(&tree, &crate::path::HexPath::from(from_path_str)) {
+        Ok(m) => m,
+        Err(e) => {
+            return error_json(ErrorCode::from(&e), format!("Resync failed: {}", e));
+        }
+    };
+
+    match serde_json::to_string(&message) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => {
+            error_json(ErrorCode::Serialization, format!("Failed to serialize resync: {}", e))
+        }
+    }
+}
+
+/// Compute the structural checksum of a VNode tree (see `checksum` module for the format)
+/// Returns 0 if the tree can't be parsed
+///
+/// # Safety
+/// - tree_json must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_tree_checksum(tree_json: *const c_char) -> u64 {
+    let _call = FfiCall::enter("minimact_tree_checksum", &[tree_json]);
+    let tree_str = match CStr::from_ptr(tree_json).to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            crate::last_error::record_error(&MinimactError::from(e));
+            return 0;
+        }
+    };
+
+    let validation_config = crate::validation::ValidationConfig::default();
+    match crate::validation::deserialize_vnode_safe(tree_str, &validation_config) {
+        Ok(tree) => crate::checksum::tree_checksum(&tree),
+        Err(e) => {
+            crate::last_error::record_error(&e);
+            0
+        }
+    }
+}
+
+/// Compare a client-reported checksum against the subtree at `path`
+/// Returns {"drift": false} when they match, or {"drift": true, "resync": ResyncMessage}
+/// (or {"error": ...} on failure)
+///
+/// # Safety
+/// - tree_json and path must be valid null-terminated UTF-8 strings
+/// - path may be an empty string to check the whole tree
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_check_drift(
+    tree_json: *const c_char,
+    path: *const c_char,
+    client_checksum: u64,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_check_drift", &[tree_json, path]);
+    let tree_str = match CStr::from_ptr(tree_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return invalid_utf8(e),
+    };
+
+    let path_str = match CStr::from_ptr(path).to_str() {
+        Ok(s) => s,
+        Err(e) => return invalid_utf8(e),
+    };
+
+    let validation_config = crate::validation::ValidationConfig::default();
+    let tree: VNode = match crate::validation::deserialize_vnode_safe(tree_str, &validation_config) {
+        Ok(t) => t,
+        Err(e) => {
+            return error_json(ErrorCode::from(&e), format!("Failed to parse tree: {}", e));
+        }
+    };
+
+    let result = match crate::checksum::check_drift(&tree, &crate::path::HexPath::from(path_str), client_checksum) {
+        Ok(None) => serde_json::json!({ "drift": false }),
+        Ok(Some(resync)) => serde_json::json!({ "drift": true, "resync": resync }),
+        Err(e) => {
+            return error_json(ErrorCode::from(&e), format!("Drift check failed: {}", e));
+        }
+    };
+
+    CString::new(result.to_string()).unwrap().into_raw()
+}
+
+/// Learn from a state change
+///
+/// # Safety
+/// - All JSON pointers must be valid null-terminated UTF-8 strings
+/// - all_state_json can be null if not available
+#[no_mangle]
+pub unsafe extern "C" fn minimact_predictor_learn(
+    handle: PredictorHandle,
+    state_change_json: *const c_char,
+    old_tree_json: *const c_char,
+    new_tree_json: *const c_char,
+    all_state_json: *const c_char,
+) -> FfiResult {
+    let _call = FfiCall::enter("minimact_predictor_learn", &[state_change_json, old_tree_json, new_tree_json, all_state_json]);
+    let state_change_str = match CStr::from_ptr(state_change_json).to_str() {
+        Ok(s) => s,
+        Err(_) => return FfiResult::error_str("Invalid state_change_json encoding"),
+    };
+
+    let old_tree_str = match CStr::from_ptr(old_tree_json).to_str() {
+        Ok(s) => s,
+        Err(_) => return FfiResult::error_str("Invalid old_tree_json encoding"),
+    };
+
+    let new_tree_str = match CStr::from_ptr(new_tree_json).to_str() {
+        Ok(s) => s,
+        Err(_) => return FfiResult::error_str("Invalid new_tree_json encoding"),
+    };
+
+    let state_change: StateChange = match serde_json::from_str(state_change_str) {
+        Ok(sc) => sc,
+        Err(e) => return FfiResult::error_str(&format!("Failed to parse state change: {}", e)),
+    };
+
+    let validation_config = crate::validation::ValidationConfig::default();
+
+    let old_tree: VNode = match crate::validation::deserialize_vnode_safe(old_tree_str, &validation_config) {
+        Ok(t) => t,
+        Err(e) => return FfiResult::error_str(&format!("Failed to parse old tree: {}", e)),
+    };
+
+    let new_tree: VNode = match crate::validation::deserialize_vnode_safe(new_tree_str, &validation_config) {
+        Ok(t) => t,
+        Err(e) => return FfiResult::error_str(&format!("Failed to parse new tree: {}", e)),
+    };
+
+    // Parse all_state if provided (can be null)
+    let all_state = if all_state_json.is_null() {
+        None
+    } else {
+        match CStr::from_ptr(all_state_json).to_str() {
+            Ok(s) => {
+                match serde_json::from_str::<std::collections::HashMap<String, serde_json::Value>>(s) {
+                    Ok(state) => Some(state),
+                    Err(e) => return FfiResult::error_str(&format!("Failed to parse all_state: {}", e)),
+                }
+            }
+            Err(_) => return FfiResult::error_str("Invalid all_state_json encoding"),
+        }
+    };
+
+    match predictor(handle) {
+        Ok(predictor) => match predictor.learn(state_change, &old_tree, &new_tree, all_state.as_ref()) {
+            Ok(()) => FfiResult::success(),
+            Err(e) => FfiResult::error_str(&format!("Learn failed: {}", e)),
+        },
+        Err(e) => FfiResult::error(&e),
+    }
+}
+
+/// Predict patches for a state change with metadata (Babel-extracted templates)
+/// Returns JSON string with prediction or null if no prediction available
+///
+/// # Safety
+/// - All JSON pointers must be valid null-terminated UTF-8 strings
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_predictor_predict_with_metadata(
+    handle: PredictorHandle,
+    state_change_json: *const c_char,
+    current_tree_json: *const c_char,
+    metadata_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_predictor_predict_with_metadata", &[state_change_json, current_tree_json, metadata_json]);
+    let state_change_str = match CStr::from_ptr(state_change_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return null_on_error(e),
+    };
+
+    let current_tree_str = match CStr::from_ptr(current_tree_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return null_on_error(e),
+    };
+
+    let metadata_str = match CStr::from_ptr(metadata_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return null_on_error(e),
+    };
+
+    let state_change: StateChange = match serde_json::from_str(state_change_str) {
+        Ok(sc) => sc,
+        Err(e) => return null_on_error(e),
+    };
+
+    let validation_config = crate::validation::ValidationConfig::default();
+
+    let current_tree: VNode = match crate::validation::deserialize_vnode_safe(current_tree_str, &validation_config) {
+        Ok(t) => t,
+        Err(e) => return null_on_error(e),
+    };
+
+    let metadata: crate::vdom::ComponentMetadata = match serde_json::from_str(metadata_str) {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("[Minimact] Failed to parse metadata: {}", e);
+            return std::ptr::null_mut();
+        }
+    };
+
+    match predictor(handle) {
+        Ok(predictor) => {
+            if let Some(code_hash) = &metadata.code_hash {
+                predictor.set_code_hash(&metadata.component_id, code_hash);
+            }
+
+            // Try to predict with metadata first (100% coverage from Babel templates)
+            if let Some(prediction) = predictor.predict_with_metadata(&state_change, &current_tree, Some(&metadata)) {
+                prediction_response(&prediction)
+            } else {
+                // Fallback to learned patterns if metadata doesn't have templates
+                if let Some(prediction) = predictor.predict(&state_change, &current_tree) {
+                    prediction_response(&prediction)
+                } else {
+                    let error_response = serde_json::json!({
+                        "ok": false,
+                        "error": "No prediction available"
+                    });
+                    match serde_json::to_string(&error_response) {
+                        Ok(json) => CString::new(json).unwrap().into_raw(),
+                        Err(e) => null_on_error(e),
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            crate::last_error::record_error(&e);
+            let error_response = serde_json::json!({
+                "ok": false,
+                "error": e.to_string()
+            });
+            match serde_json::to_string(&error_response) {
+                Ok(json) => CString::new(json).unwrap().into_raw(),
+                Err(e) => null_on_error(e),
+            }
+        }
+    }
+}
+
+/// Predict patches for a state change
+/// Returns JSON string with prediction or null if no prediction available
+///
+/// # Safety
+/// - All JSON pointers must be valid null-terminated UTF-8 strings
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_predictor_predict(
+    handle: PredictorHandle,
+    state_change_json: *const c_char,
+    current_tree_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_predictor_predict", &[state_change_json, current_tree_json]);
+    let state_change_str = match CStr::from_ptr(state_change_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return null_on_error(e),
+    };
+
+    let current_tree_str = match CStr::from_ptr(current_tree_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return null_on_error(e),
+    };
+
+    let state_change: StateChange = match serde_json::from_str(state_change_str) {
+        Ok(sc) => sc,
+        Err(e) => return null_on_error(e),
+    };
+
+    let validation_config = crate::validation::ValidationConfig::default();
+
+    let current_tree: VNode = match crate::validation::deserialize_vnode_safe(current_tree_str, &validation_config) {
+        Ok(t) => t,
+        Err(e) => return null_on_error(e),
+    };
+
+    match predictor(handle) {
+        Ok(predictor) => {
+            if let Some(prediction) = predictor.predict(&state_change, &current_tree) {
+                // Return successful prediction wrapped in Result format
+                prediction_response(&prediction)
+            } else {
+                // Return error response when no prediction is available
+                let error_response = serde_json::json!({
+                    "ok": false,
+                    "error": "No prediction available (confidence too low or no matching pattern)"
+                });
+                match serde_json::to_string(&error_response) {
+                    Ok(json) => CString::new(json).unwrap().into_raw(),
+                    Err(e) => null_on_error(e),
+                }
+            }
+        }
+        Err(e) => {
+            crate::last_error::record_error(&e);
+            let error_response = serde_json::json!({
+                "ok": false,
+                "error": e.to_string()
+            });
+            match serde_json::to_string(&error_response) {
+                Ok(json) => CString::new(json).unwrap().into_raw(),
+                Err(e) => null_on_error(e),
+            }
+        }
+    }
+}
+
+/// Predict patches for a state change given the component's full state
+/// Template patches whose bindings resolve against the state come back rendered
+/// Returns JSON string with prediction, like minimact_predictor_predict
+///
+/// # Safety
+/// - All JSON pointers must be valid null-terminated UTF-8 strings
+/// - full_state_json is an object of state values by key (before or after the change)
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_predictor_predict_with_state(
+    handle: PredictorHandle,
+    state_change_json: *const c_char,
+    full_state_json: *const c_char,
+    current_tree_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_predictor_predict_with_state", &[state_change_json, full_state_json, current_tree_json]);
+    let state_change: StateChange = match CStr::from_ptr(state_change_json).to_str().map(serde_json::from_str) {
+        Ok(Ok(sc)) => sc,
+        Ok(Err(e)) => return null_on_error(e),
+        Err(e) => return null_on_error(e),
+    };
+
+    let full_state: crate::template_renderer::StateValues = match CStr::from_ptr(full_state_json).to_str().map(serde_json::from_str) {
+        Ok(Ok(state)) => state,
+        Ok(Err(e)) => return null_on_error(e),
+        Err(e) => return null_on_error(e),
+    };
+
+    let current_tree_str = match CStr::from_ptr(current_tree_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return null_on_error(e),
+    };
+    let validation_config = crate::validation::ValidationConfig::default();
+    let current_tree: VNode = match crate::validation::deserialize_vnode_safe(current_tree_str, &validation_config) {
+        Ok(t) => t,
+        Err(e) => return null_on_error(e),
+    };
+
+    let predictor = match predictor(handle) {
+        Ok(predictor) => predictor,
+        Err(e) => return null_on_error(e),
+    };
+    match predictor.predict_with_state(&state_change, &full_state, &current_tree) {
+        Some(prediction) => prediction_response(&prediction),
+        None => {
+            let error_response = serde_json::json!({
+                "ok": false,
+                "error": "No prediction available (confidence too low or no matching pattern)"
+            });
+            match serde_json::to_string(&error_response) {
+                Ok(json) => CString::new(json).unwrap().into_raw(),
+                Err(e) => null_on_error(e),
+            }
+        }
+    }
+}
+
+/// Predict patches based on hint (for usePredictHint)
+///
+/// # Safety
+/// - All JSON pointers must be valid null-terminated UTF-8 strings
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_predictor_predict_hint(
+    handle: PredictorHandle,
+    hint_id: *const c_char,
+    component_id: *const c_char,
+    state_changes_json: *const c_char,
+    current_tree_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_predictor_predict_hint", &[hint_id, component_id, state_changes_json, current_tree_json]);
+    let hint_id_str = match CStr::from_ptr(hint_id).to_str() {
+        Ok(s) => s,
+        Err(e) => return null_on_error(e),
+    };
+
+    let component_id_str = match CStr::from_ptr(component_id).to_str() {
+        Ok(s) => s,
+        Err(e) => return null_on_error(e),
+    };
+
+    let state_changes_str = match CStr::from_ptr(state_changes_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return null_on_error(e),
+    };
+
+    let current_tree_str = match CStr::from_ptr(current_tree_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return null_on_error(e),
+    };
+
+    let state_changes: Vec<StateChange> = match serde_json::from_str(state_changes_str) {
+        Ok(sc) => sc,
+        Err(e) => return null_on_error(e),
+    };
+
+    let validation_config = crate::validation::ValidationConfig::default();
+    let current_tree: VNode = match crate::validation::deserialize_vnode_safe(current_tree_str, &validation_config) {
+        Ok(t) => t,
+        Err(e) => return null_on_error(e),
+    };
+
+    match predictor(handle) {
+        Ok(predictor) => {
+            if let Some(prediction) = predictor.predict_hint(hint_id_str, component_id_str, &state_changes, &current_tree) {
+                let result = serde_json::json!({
+                    "ok": true,
+                    "hint_id": hint_id_str,
+                    "data": prediction
+                });
+                match serde_json::to_string(&result) {
+                    Ok(json) => CString::new(json).unwrap().into_raw(),
+                    Err(e) => null_on_error(e),
+                }
+            } else {
+                let error_response = serde_json::json!({
+                    "ok": false,
+                    "error": "No prediction available for hint"
+                });
+                match serde_json::to_string(&error_response) {
+                    Ok(json) => CString::new(json).unwrap().into_raw(),
+                    Err(e) => null_on_error(e),
+                }
+            }
+        }
+        Err(e) => null_on_error(e),
+    }
+}
+
+/// Predict for many state changes against one tree in a single call
+/// Returns a BatchResponse of predictions as JSON (contract in the schema module)
+///
+/// # Safety
+/// - state_changes_json and tree_json must be valid null-terminated UTF-8 strings
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_predictor_predict_batch(
+    handle: PredictorHandle,
+    state_changes_json: *const c_char,
+    tree_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_predictor_predict_batch", &[state_changes_json, tree_json]);
+    let state_changes: Vec<StateChange> = match CStr::from_ptr(state_changes_json).to_str().map(serde_json::from_str) {
+        Ok(Ok(sc)) => sc,
+        Ok(Err(e)) => return batch_error(ErrorCode::Serialization, &format!("Failed to parse state changes: {}", e)),
+        Err(_) => return batch_error(ErrorCode::Serialization, "Invalid UTF-8 in state changes"),
     };
+    if state_changes.len() > crate::schema::MAX_BATCH_SIZE {
+        let e = MinimactError::Validation(format!("Batch of {} exceeds {} entries", state_changes.len(), crate::schema::MAX_BATCH_SIZE));
+        return batch_error(ErrorCode::from(&e), &e.to_string());
+    }
 
     let validation_config = crate::validation::ValidationConfig::default();
-    let current_tree: VNode = match crate::validation::deserialize_vnode_safe(current_tree_str, &validation_config) {
-        Ok(t) => t,
-        Err(_) => return std::ptr::null_mut(),
+    let tree: VNode = match CStr::from_ptr(tree_json).to_str() {
+        Ok(s) => match crate::validation::deserialize_vnode_safe(s, &validation_config) {
+            Ok(t) => t,
+            Err(e) => return batch_error(ErrorCode::Serialization, &e.to_string()),
+        },
+        Err(_) => return batch_error(ErrorCode::Serialization, "Invalid UTF-8 in tree"),
     };
 
-    if let Some(mut predictor) = PREDICTORS.get_mut(&handle) {
-        if let Some(prediction) = predictor.predict_hint(hint_id_str, component_id_str, state_changes, &current_tree) {
-            let result = serde_json::json!({
-                "ok": true,
-                "hint_id": hint_id_str,
-                "data": prediction
-            });
-            match serde_json::to_string(&result) {
-                Ok(json) => CString::new(json).unwrap().into_raw(),
-                Err(_) => std::ptr::null_mut(),
-            }
+    let predictor = match predictor(handle) {
+        Ok(predictor) => predictor,
+        Err(e) => return batch_error(ErrorCode::from(&e), &e.to_string()),
+    };
+    let response = predictor.predict_batch(&state_changes, &tree);
+    match serde_json::to_string(&response) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => batch_error(ErrorCode::Serialization, &e.to_string()),
+    }
+}
+
+/// Learn many observations in a single call (published to readers as one version)
+/// Returns a BatchResponse as JSON (contract in the schema module)
+///
+/// # Safety
+/// - observations_json must be a valid null-terminated UTF-8 string
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_predictor_learn_batch(
+    handle: PredictorHandle,
+    observations_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_predictor_learn_batch", &[observations_json]);
+    let observations_str = match CStr::from_ptr(observations_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return batch_error(ErrorCode::InvalidUtf8, &format!("Invalid UTF-8 in observations: {}", e)),
+    };
+    // Scanned as a whole before serde allocates it, like the bulk reconcile payload
+    let validation_config = crate::validation::ValidationConfig::default();
+    let parsed = crate::validation::check_json_input(observations_str, &validation_config.for_batch(2 * crate::schema::MAX_BATCH_SIZE))
+        .and_then(|_| Ok(serde_json::from_str::<Vec<crate::schema::LearnObservation>>(observations_str)?));
+    let mut observations = match parsed {
+        Ok(o) => o,
+        Err(e) => return batch_error(ErrorCode::from(&e), &format!("Failed to parse observations: {}", e)),
+    };
+    if observations.len() > crate::schema::MAX_BATCH_SIZE {
+        let e = MinimactError::Validation(format!("Batch of {} exceeds {} entries", observations.len(), crate::schema::MAX_BATCH_SIZE));
+        return batch_error(ErrorCode::from(&e), &e.to_string());
+    }
+
+    for (index, observation) in observations.iter_mut().enumerate() {
+        let prepared = crate::validation::prepare_vnode(&mut observation.old_tree, &validation_config)
+            .and_then(|_| crate::validation::prepare_vnode(&mut observation.new_tree, &validation_config));
+        if let Err(e) = prepared {
+            return batch_error(ErrorCode::from(&e), &format!("Invalid tree in observation {}: {}", index, e));
+        }
+    }
+
+    let predictor = match predictor(handle) {
+        Ok(predictor) => predictor,
+        Err(e) => return batch_error(ErrorCode::from(&e), &e.to_string()),
+    };
+    let response = predictor.learn_batch(observations);
+    match serde_json::to_string(&response) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => batch_error(ErrorCode::Serialization, &e.to_string()),
+    }
+}
+
+fn batch_error(code: ErrorCode, message: &str) -> *mut c_char {
+    crate::last_error::record(code, message);
+    let response = serde_json::json!({ "ok": false, "error": message });
+    CString::new(response.to_string()).unwrap().into_raw()
+}
+
+/// Queue a hint for idle-time precomputation (see minimact_hints_run_idle)
+///
+/// # Safety
+/// - All string pointers must be valid null-terminated UTF-8 strings
+#[no_mangle]
+pub unsafe extern "C" fn minimact_hints_schedule(
+    handle: PredictorHandle,
+    hint_id: *const c_char,
+    component_id: *const c_char,
+    state_changes_json: *const c_char,
+    current_tree_json: *const c_char,
+) -> FfiResult {
+    let _call = FfiCall::enter("minimact_hints_schedule", &[hint_id, component_id, state_changes_json, current_tree_json]);
+    let hint_id_str = match CStr::from_ptr(hint_id).to_str() {
+        Ok(s) => s,
+        Err(_) => return FfiResult::error_str("Invalid UTF-8 in hint id"),
+    };
+
+    let component_id_str = match CStr::from_ptr(component_id).to_str() {
+        Ok(s) => s,
+        Err(_) => return FfiResult::error_str("Invalid UTF-8 in component id"),
+    };
+
+    let state_changes: Vec<StateChange> = match CStr::from_ptr(state_changes_json).to_str().map(serde_json::from_str) {
+        Ok(Ok(sc)) => sc,
+        _ => return FfiResult::error_str("Failed to parse state changes"),
+    };
+
+    let validation_config = crate::validation::ValidationConfig::default();
+    let current_tree: VNode = match CStr::from_ptr(current_tree_json).to_str() {
+        Ok(s) => match crate::validation::deserialize_vnode_safe(s, &validation_config) {
+            Ok(t) => t,
+            Err(e) => return FfiResult::error(&e),
+        },
+        Err(_) => return FfiResult::error_str("Invalid UTF-8 in tree"),
+    };
+
+    if let Err(e) = predictor(handle) {
+        return FfiResult::error(&e);
+    }
+
+    HINT_SCHEDULERS.entry(handle).or_default().schedule(crate::hint_scheduler::HintJob {
+        hint_id: hint_id_str.to_string(),
+        component_id: component_id_str.to_string(),
+        state_changes,
+        tree: current_tree,
+    });
+    FfiResult::success()
+}
+
+/// Precompute queued hints for up to budget_us microseconds
+/// Call from a Background-priority task when the runtime is idle
+/// Returns the number of hints computed
+#[no_mangle]
+pub extern "C" fn minimact_hints_run_idle(handle: PredictorHandle, budget_us: u64) -> usize {
+    let _call = FfiCall::named("minimact_hints_run_idle");
+    let Some(mut scheduler) = HINT_SCHEDULERS.get_mut(&handle) else { return 0 };
+    let Ok(predictor) = predictor(handle) else { return 0 };
+    predictor.update(|predictor| scheduler.run_idle(predictor, std::time::Duration::from_micros(budget_us)))
+}
+
+/// Notify the hint scheduler that a real state change arrived
+/// Returns {"hit": true, "data": prediction} if a precomputed hint matches, else {"hit": false}
+/// Queued hints for the same state are cancelled
+///
+/// # Safety
+/// - state_change_json must be a valid null-terminated UTF-8 string
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_hints_on_state_change(
+    handle: PredictorHandle,
+    state_change_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_hints_on_state_change", &[state_change_json]);
+    let state_change: StateChange = match CStr::from_ptr(state_change_json).to_str().map(serde_json::from_str) {
+        Ok(Ok(sc)) => sc,
+        _ => return error_json(ErrorCode::Serialization, "Failed to parse state change".to_string()),
+    };
+
+    let prediction = HINT_SCHEDULERS
+        .get_mut(&handle)
+        .and_then(|mut scheduler| scheduler.on_state_change(&state_change));
+
+    let response = match prediction {
+        Some(prediction) => serde_json::json!({ "hit": true, "data": prediction }),
+        None => serde_json::json!({ "hit": false }),
+    };
+    match serde_json::to_string(&response) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => null_on_error(e),
+    }
+}
+
+/// Register a component's build-time metadata with a predictor
+/// Its templates are seeded as predictions, so the first state change already hits.
+/// Returns {"seeded": n} as JSON (or {"error": ...})
+///
+/// # Safety
+/// - metadata_json must be a valid null-terminated UTF-8 string (ComponentMetadata JSON)
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_predictor_register_metadata(
+    handle: PredictorHandle,
+    metadata_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_predictor_register_metadata", &[metadata_json]);
+    let result = (|| -> crate::error::Result<usize> {
+        let predictor = predictor(handle)?;
+        let metadata: crate::vdom::ComponentMetadata = serde_json::from_str(CStr::from_ptr(metadata_json).to_str()?)?;
+        let seeded = predictor.seed_from_metadata(&metadata);
+        register_metadata(metadata);
+        Ok(seeded)
+    })();
+    match result {
+        Ok(seeded) => CString::new(serde_json::json!({ "seeded": seeded }).to_string()).unwrap().into_raw(),
+        Err(e) => error_json(ErrorCode::from(&e), e.to_string()),
+    }
+}
+
+/// Export a component's confident predictions as a bundle for client-side caching
+/// options_json is BundleOptions JSON (null or "{}" for the defaults)
+/// Returns PredictionBundle JSON (or {"error": ...})
+///
+/// # Safety
+/// - component_id must be a valid null-terminated UTF-8 string
+/// - options_json must be null or a valid null-terminated UTF-8 string
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_predictor_export_bundle(
+    handle: PredictorHandle,
+    component_id: *const c_char,
+    options_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_predictor_export_bundle", &[component_id]);
+    let result = (|| -> crate::error::Result<String> {
+        let predictor = predictor(handle)?;
+        let component_id = CStr::from_ptr(component_id).to_str()?;
+        let options: crate::prediction_bundle::BundleOptions = if options_json.is_null() {
+            Default::default()
         } else {
-            let error_response = serde_json::json!({
-                "ok": false,
-                "error": "No prediction available for hint"
-            });
-            match serde_json::to_string(&error_response) {
+            serde_json::from_str(CStr::from_ptr(options_json).to_str()?)?
+        };
+        Ok(serde_json::to_string(&predictor.export_bundle(component_id, &options))?)
+    })();
+    match result {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => error_json(ErrorCode::from(&e), e.to_string()),
+    }
+}
+
+/// The metadata last registered for a component (e.g. to diff against on hot reload)
+/// Returns ComponentMetadata JSON, or null if none was registered
+///
+/// # Safety
+/// - component_id must be a valid null-terminated UTF-8 string
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_metadata_get(component_id: *const c_char) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_metadata_get", &[component_id]);
+    let component_id = match CStr::from_ptr(component_id).to_str() {
+        Ok(s) => s,
+        Err(e) => return null_on_error(e),
+    };
+    match METADATA.get(component_id).map(|metadata| serde_json::to_string(&*metadata)) {
+        Some(Ok(json)) => CString::new(json).unwrap().into_raw(),
+        Some(Err(e)) => null_on_error(e),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Get predictor statistics as JSON
+///
+/// # Safety
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_predictor_stats(handle: PredictorHandle) -> *mut c_char {
+    let _call = FfiCall::named("minimact_predictor_stats");
+    match predictor(handle) {
+        Ok(predictor) => {
+            let stats = predictor.stats();
+            match serde_json::to_string(&stats) {
                 Ok(json) => CString::new(json).unwrap().into_raw(),
-                Err(_) => std::ptr::null_mut(),
+                Err(e) => null_on_error(e),
             }
         }
-    } else {
-        std::ptr::null_mut()
+        Err(e) => null_on_error(e),
     }
 }
 
-/// Get predictor statistics as JSON
+/// List a predictor's templates and learned patterns with their provenance (devtools)
+/// Returns a JSON array of PatternSummary, or null for an invalid handle
 ///
 /// # Safety
 /// - The returned pointer must be freed using minimact_free_string
 #[no_mangle]
-pub unsafe extern "C" fn minimact_predictor_stats(handle: PredictorHandle) -> *mut c_char {
-    if let Some(predictor) = PREDICTORS.get(&handle) {
-        let stats = predictor.stats();
-        match serde_json::to_string(&stats) {
+pub unsafe extern "C" fn minimact_predictor_inspect(handle: PredictorHandle) -> *mut c_char {
+    let _call = FfiCall::named("minimact_predictor_inspect");
+    match predictor(handle) {
+        Ok(predictor) => match serde_json::to_string(&predictor.inspect_patterns()) {
             Ok(json) => CString::new(json).unwrap().into_raw(),
-            Err(_) => std::ptr::null_mut(),
+            Err(e) => null_on_error(e),
+        },
+        Err(e) => null_on_error(e),
+    }
+}
+
+/// Declare which patch kinds the predictor's client supports
+/// Pass null to clear (every patch kind supported)
+///
+/// # Safety
+/// - capabilities_json must be null or a valid null-terminated UTF-8 string
+///   of the form {"supportedPatches": ["UpdateText", ...]}
+#[no_mangle]
+pub unsafe extern "C" fn minimact_predictor_set_capabilities(
+    handle: PredictorHandle,
+    capabilities_json: *const c_char,
+) -> FfiResult {
+    let _call = FfiCall::enter("minimact_predictor_set_capabilities", &[capabilities_json]);
+    let capabilities = if capabilities_json.is_null() {
+        None
+    } else {
+        let json = match CStr::from_ptr(capabilities_json).to_str() {
+            Ok(s) => s,
+            Err(_) => return FfiResult::error_str("Invalid UTF-8 in capabilities JSON"),
+        };
+        match serde_json::from_str::<crate::capabilities::ClientCapabilities>(json) {
+            Ok(c) => Some(c),
+            Err(e) => return FfiResult::error_str(&format!("Failed to parse capabilities: {}", e)),
+        }
+    };
+
+    match predictor(handle) {
+        Ok(predictor) => {
+            predictor.update(|predictor| predictor.set_capabilities(capabilities));
+            FfiResult::success()
+        }
+        Err(e) => FfiResult::error(&e),
+    }
+}
+
+/// Downgrade patches a client doesn't support into concrete baseline patches
+/// Returns {"patches": [...], "report": ApplicabilityReport} as JSON (or {"error": ...})
+///
+/// # Safety
+/// - patches_json and capabilities_json must be valid null-terminated UTF-8 strings
+/// - state_json can be null (templates needing state are then dropped)
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_negotiate_patches(
+    patches_json: *const c_char,
+    capabilities_json: *const c_char,
+    state_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_negotiate_patches", &[patches_json, capabilities_json, state_json]);
+    let patches_str = match CStr::from_ptr(patches_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return invalid_utf8(e),
+    };
+
+    let capabilities_str = match CStr::from_ptr(capabilities_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return invalid_utf8(e),
+    };
+
+    let patches: Vec<crate::vdom::Patch> = match serde_json::from_str(patches_str) {
+        Ok(p) => p,
+        Err(e) => {
+            return error_json(ErrorCode::Serialization, format!("Failed to parse patches: {}", e));
+        }
+    };
+
+    let capabilities: crate::capabilities::ClientCapabilities = match serde_json::from_str(capabilities_str) {
+        Ok(c) => c,
+        Err(e) => {
+            return error_json(ErrorCode::Serialization, format!("Failed to parse capabilities: {}", e));
+        }
+    };
+
+    let state: std::collections::HashMap<String, serde_json::Value> = if state_json.is_null() {
+        std::collections::HashMap::new()
+    } else {
+        let state_str = match CStr::from_ptr(state_json).to_str() {
+            Ok(s) => s,
+            Err(e) => return invalid_utf8(e),
+        };
+        match serde_json::from_str(state_str) {
+            Ok(s) => s,
+            Err(e) => {
+                return error_json(ErrorCode::Serialization, format!("Failed to parse state: {}", e));
+            }
+        }
+    };
+
+    let (patches, report) = crate::capabilities::negotiate_patches_with_report(patches, &capabilities, &state);
+    let result = serde_json::json!({ "patches": patches, "report": report });
+    CString::new(result.to_string()).unwrap().into_raw()
+}
+
+/// Compute the key order an OrderingRule (from a ReorderTemplate) produces for an array
+/// Returns the keys as a JSON array (or {"error": ...}); send them as a ReorderChildren order
+///
+/// # Safety
+/// - rule_json and array_json must be valid null-terminated UTF-8 strings
+/// - array_json is the list before the reorder
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_apply_ordering(rule_json: *const c_char, array_json: *const c_char) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_apply_ordering", &[rule_json, array_json]);
+    let rule_str = match CStr::from_ptr(rule_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return invalid_utf8(e),
+    };
+
+    let array_str = match CStr::from_ptr(array_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return invalid_utf8(e),
+    };
+
+    let rule: crate::reorder_detection::OrderingRule = match serde_json::from_str(rule_str) {
+        Ok(r) => r,
+        Err(e) => {
+            return error_json(ErrorCode::Serialization, format!("Failed to parse ordering rule: {}", e));
+        }
+    };
+
+    let array: serde_json::Value = match serde_json::from_str(array_str) {
+        Ok(a) => a,
+        Err(e) => {
+            return error_json(ErrorCode::Serialization, format!("Failed to parse array: {}", e));
+        }
+    };
+
+    match crate::reorder_detection::apply_ordering(&rule, &array) {
+        Ok(order) => CString::new(serde_json::json!(order).to_string()).unwrap().into_raw(),
+        Err(e) => error_json(ErrorCode::from(&e), format!("Ordering failed: {}", e)),
+    }
+}
+
+/// Wrap patches in a PatchBatch envelope (stable patch/batch IDs, sequence number)
+/// Returns the PatchBatch JSON (or {"error": ...})
+///
+/// # Safety
+/// - stream_id and patches_json must be valid null-terminated UTF-8 strings
+/// - sequence is the batch's position in the stream, starting at 1
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_patch_batch(stream_id: *const c_char, sequence: u64, patches_json: *const c_char) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_patch_batch", &[stream_id, patches_json]);
+    let stream_str = match CStr::from_ptr(stream_id).to_str() {
+        Ok(s) => s,
+        Err(e) => return invalid_utf8(e),
+    };
+
+    let patches_str = match CStr::from_ptr(patches_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return invalid_utf8(e),
+    };
+
+    let patches: Vec<crate::vdom::Patch> = match serde_json::from_str(patches_str) {
+        Ok(p) => p,
+        Err(e) => {
+            return error_json(ErrorCode::Serialization, format!("Failed to parse patches: {}", e));
         }
+    };
+
+    let batch = crate::patch_batch::PatchBatch::new(stream_str, sequence, patches);
+    match serde_json::to_string(&batch) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => error_json(ErrorCode::Serialization, format!("Failed to serialize batch: {}", e)),
+    }
+}
+
+/// Validate a patch list against the tree it will be applied to
+/// Returns a PatchValidationReport as JSON: one diagnostic per patch with its index,
+/// pass/fail and ErrorCode (or {"error": ...} if the inputs can't be read at all)
+///
+/// # Safety
+/// - tree_json and patches_json must be valid null-terminated UTF-8 strings
+/// - config_json can be null to use the default PatchValidatorConfig
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_validate_patches(
+    tree_json: *const c_char,
+    patches_json: *const c_char,
+    config_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_validate_patches", &[tree_json, patches_json, config_json]);
+    let tree_str = match CStr::from_ptr(tree_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return invalid_utf8(e),
+    };
+
+    let patches_str = match CStr::from_ptr(patches_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return invalid_utf8(e),
+    };
+
+    let config: crate::patch_validator::PatchValidatorConfig = if config_json.is_null() {
+        Default::default()
     } else {
-        std::ptr::null_mut()
+        let config_str = match CStr::from_ptr(config_json).to_str() {
+            Ok(s) => s,
+            Err(e) => return invalid_utf8(e),
+        };
+        match serde_json::from_str(config_str) {
+            Ok(c) => c,
+            Err(e) => {
+                return error_json(ErrorCode::Serialization, format!("Failed to parse validator config: {}", e));
+            }
+        }
+    };
+
+    let validation_config = crate::validation::ValidationConfig::default();
+    let tree: VNode = match crate::validation::deserialize_vnode_safe(tree_str, &validation_config) {
+        Ok(t) => t,
+        Err(e) => {
+            return error_json(ErrorCode::from(&e), format!("Failed to parse tree: {}", e));
+        }
+    };
+
+    // Entries are parsed one by one so a malformed patch only fails its own diagnostic
+    let patches: Vec<serde_json::Value> = match serde_json::from_str(patches_str) {
+        Ok(p) => p,
+        Err(e) => {
+            return error_json(ErrorCode::Serialization, format!("Failed to parse patches: {}", e));
+        }
+    };
+
+    let report = crate::patch_validator::validate_patch_values(&patches, &tree, &config);
+    match serde_json::to_string(&report) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => {
+            error_json(ErrorCode::Serialization, format!("Failed to serialize report: {}", e))
+        }
     }
 }
 
@@ -442,13 +1679,15 @@ pub unsafe extern "C" fn minimact_predictor_stats(handle: PredictorHandle) -> *m
 /// - The returned pointer must be freed using minimact_free_string
 #[no_mangle]
 pub unsafe extern "C" fn minimact_predictor_save(handle: PredictorHandle) -> *mut c_char {
-    if let Some(predictor) = PREDICTORS.get(&handle) {
-        match predictor.save_to_json() {
-            Ok(json) => CString::new(json).unwrap().into_raw(),
-            Err(_) => std::ptr::null_mut(),
+    let _call = FfiCall::named("minimact_predictor_save");
+    match predictor(handle) {
+        Ok(predictor) => {
+            match predictor.snapshot().save_to_json() {
+                Ok(json) => CString::new(json).unwrap().into_raw(),
+                Err(e) => null_on_error(e),
+            }
         }
-    } else {
-        std::ptr::null_mut()
+        Err(e) => null_on_error(e),
     }
 }
 
@@ -460,19 +1699,21 @@ pub unsafe extern "C" fn minimact_predictor_save(handle: PredictorHandle) -> *mu
 /// - json_str must be a valid null-terminated UTF-8 string
 #[no_mangle]
 pub unsafe extern "C" fn minimact_predictor_load(json_str: *const c_char) -> PredictorHandle {
+    let _call = FfiCall::enter("minimact_predictor_load", &[json_str]);
     let json = match CStr::from_ptr(json_str).to_str() {
         Ok(s) => s,
-        Err(_) => return 0, // Return 0 as invalid handle
+        Err(e) => {
+            crate::last_error::record_error(&MinimactError::from(e));
+            return 0; // Return 0 as invalid handle
+        }
     };
 
     match Predictor::load_from_json(json) {
-        Ok(predictor) => {
-            let id = NEXT_PREDICTOR_ID.fetch_add(1, Ordering::SeqCst);
-            PREDICTORS.insert(id, predictor);
-            crate::metrics::METRICS.record_predictor_created();
-            id
+        Ok(predictor) => register_predictor(predictor),
+        Err(e) => {
+            crate::last_error::record_error(&e);
+            0 // Return 0 as invalid handle
         }
-        Err(_) => 0, // Return 0 as invalid handle
     }
 }
 
@@ -0,0 +1,121 @@
+//! Runtime feature flags
+//!
+//! Risky behaviors ship behind a flag so hosts can roll them out gradually and turn
+//! one off without a redeploy. Flags are process-wide booleans with a fixed set of
+//! names; the engine checks them where it decides between the new and the old path,
+//! and their current state is part of every metrics snapshot so a regression can be
+//! lined up with the flags that were on at the time.
+
+use crate::error::{FfiResult, MinimactError, Result};
+use crate::last_error::FfiCall;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A behavior that can be switched at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Flag {
+    /// `reconcile_bulk` spreads pairs over the thread pool (off: one after another)
+    ParallelReconcile,
+    /// `Patch::Custom` passes validation (off: rejected even for registered kinds)
+    CustomPatches,
+    /// Tree JSON comes from the content-hash cache (off: serialized every time)
+    TreeJsonCache,
+}
+
+impl Flag {
+    pub const ALL: [Flag; 3] = [Flag::ParallelReconcile, Flag::CustomPatches, Flag::TreeJsonCache];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Flag::ParallelReconcile => "parallel_reconcile",
+            Flag::CustomPatches => "custom_patches",
+            Flag::TreeJsonCache => "tree_json_cache",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Flag> {
+        Flag::ALL.into_iter().find(|flag| flag.name() == name)
+    }
+
+    /// State before anyone sets it
+    pub fn default_value(self) -> bool {
+        match self {
+            Flag::ParallelReconcile | Flag::CustomPatches | Flag::TreeJsonCache => true,
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref FLAGS: [AtomicBool; Flag::ALL.len()] = Flag::ALL.map(|flag| AtomicBool::new(flag.default_value()));
+}
+
+pub fn is_enabled(flag: Flag) -> bool {
+    FLAGS[flag as usize].load(Ordering::Relaxed)
+}
+
+/// Turn a flag on or off (process-wide)
+pub fn set_flag(flag: Flag, value: bool) {
+    FLAGS[flag as usize].store(value, Ordering::Relaxed);
+}
+
+/// Set a flag by name
+pub fn set_flag_by_name(name: &str, value: bool) -> Result<()> {
+    let flag = Flag::from_name(name).ok_or_else(|| MinimactError::KeyNotFound(format!("flag '{}'", name)))?;
+    set_flag(flag, value);
+    Ok(())
+}
+
+/// Put every flag back to its default
+pub fn reset_flags() {
+    for flag in Flag::ALL {
+        set_flag(flag, flag.default_value());
+    }
+}
+
+/// Name -> state of every flag
+pub fn flag_states() -> BTreeMap<String, bool> {
+    Flag::ALL.into_iter().map(|flag| (flag.name().to_string(), is_enabled(flag))).collect()
+}
+
+/// Turn a flag on or off by name
+///
+/// # Safety
+/// - name must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_set_flag(name: *const c_char, value: bool) -> FfiResult {
+    let _call = FfiCall::enter("minimact_set_flag", &[name]);
+    let result = CStr::from_ptr(name)
+        .to_str()
+        .map_err(MinimactError::from)
+        .and_then(|name| set_flag_by_name(name, value));
+    match result {
+        Ok(()) => FfiResult::success(),
+        Err(e) => FfiResult::error(&e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_set_by_name_and_show_in_metrics() {
+        assert!(set_flag_by_name("no_such_flag", true).is_err());
+        assert_eq!(Flag::from_name("parallel_reconcile"), Some(Flag::ParallelReconcile));
+
+        set_flag_by_name("parallel_reconcile", false).unwrap();
+        assert!(!is_enabled(Flag::ParallelReconcile));
+        assert_eq!(crate::metrics::METRICS.snapshot().flags["parallel_reconcile"], false);
+
+        // Sequential fallback still returns results in order
+        let pairs = vec![(crate::vdom::VNode::text("a"), crate::vdom::VNode::text("b")); 3];
+        assert!(crate::reconciler::reconcile_bulk(pairs).iter().all(|r| r.as_ref().unwrap().len() == 1));
+
+        set_flag(Flag::ParallelReconcile, true);
+        assert_eq!(flag_states().len(), Flag::ALL.len());
+    }
+}
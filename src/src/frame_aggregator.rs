@@ -0,0 +1,337 @@
+//! Frame-level patch aggregation
+//!
+//! Within a single frame the server may produce several patch sets for the same
+//! component: a prediction for the real state change, one or more usePredictHint
+//! results, and the authoritative reconcile output. Sending them all makes the
+//! client apply redundant (and sometimes contradictory) updates.
+//!
+//! The FrameAggregator merges those sets into one batch:
+//! - exact duplicates are dropped
+//! - patches that write the same "slot" (text, props, one attribute, the node itself)
+//!   at the same path are resolved by recency: the last one added wins
+//! - a later structural patch (Create/Replace/Remove/ReplaceConditional) supersedes
+//!   every earlier patch at or below its path
+//! - a Create followed by a Remove at the same path cancels out: neither is sent
+
+use crate::vdom::Patch;
+use crate::path::HexPath;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Where a patch set came from (kept for logging/debugging)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum PatchSource {
+    /// Prediction for an incoming state change
+    Prediction,
+    /// Pre-computed usePredictHint result
+    Hint { hint_id: String },
+    /// Authoritative reconcile output
+    Reconcile,
+}
+
+/// The part of a node that a patch writes to
+/// Two patches with the same path and slot conflict; the newer one wins
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum PatchSlot {
+    /// The node itself (Create/Replace/Remove/ReplaceConditional/UpdateDocument)
+    Node,
+    /// Text content (UpdateText/UpdateTextTemplate)
+    Text,
+    /// The full props map (UpdateProps)
+    Props,
+    /// A single prop/attribute
+    Attribute(String),
+    /// Child order (ReorderChildren/ReorderTemplate)
+    Order,
+    /// List contents (UpdateListTemplate)
+    List,
+    /// Visible rows of a virtualized list (UpdateListWindow)
+    Window,
+    /// Client ownership of the element (SetIgnored)
+    Ignored,
+    /// Layout hint (InvalidateLayout)
+    Layout,
+    /// The client's URL (Navigate)
+    Location,
+    /// Host-defined operation (Custom); never claimed, every one is kept
+    Custom,
+    /// Lifecycle marker; like Custom, every one is kept
+    Lifecycle,
+}
+
+fn slot_of(patch: &Patch) -> PatchSlot {
+    match patch {
+        Patch::Create { .. }
+        | Patch::Remove { .. }
+        | Patch::Replace { .. }
+        | Patch::ReplaceConditional { .. }
+        | Patch::UpdateDocument { .. } => PatchSlot::Node,
+        Patch::UpdateText { .. } | Patch::UpdateTextTemplate { .. } => PatchSlot::Text,
+        Patch::UpdateProps { .. } => PatchSlot::Props,
+        Patch::UpdatePropsTemplate { prop_name, .. } => PatchSlot::Attribute(prop_name.clone()),
+        Patch::UpdateAttributeStatic { attr_name, .. }
+        | Patch::UpdateAttributeDynamic { attr_name, .. } => PatchSlot::Attribute(attr_name.clone()),
+        Patch::ReorderChildren { .. } | Patch::ReorderTemplate { .. } => PatchSlot::Order,
+        Patch::UpdateListTemplate { .. } => PatchSlot::List,
+        Patch::UpdateListWindow { .. } => PatchSlot::Window,
+        Patch::SetIgnored { .. } => PatchSlot::Ignored,
+        Patch::InvalidateLayout { .. } => PatchSlot::Layout,
+        Patch::Navigate { .. } => PatchSlot::Location,
+        Patch::Custom { .. } => PatchSlot::Custom,
+        Patch::Lifecycle { .. } => PatchSlot::Lifecycle,
+    }
+}
+
+/// Statistics about a merged frame
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrameStats {
+    /// Patches submitted to the aggregator
+    pub patches_in: usize,
+    /// Patches in the merged batch
+    pub patches_out: usize,
+    /// Exact duplicates that were dropped
+    pub duplicates_dropped: usize,
+    /// Older patches overridden by a newer patch for the same slot or an enclosing structural patch
+    pub conflicts_resolved: usize,
+    /// Create/Remove pairs at the same path that cancelled out (both dropped)
+    pub created_and_removed: usize,
+}
+
+/// Collects patch sets for one frame and merges them into a single batch
+#[derive(Debug, Default)]
+pub struct FrameAggregator {
+    /// (sequence number, source, patch) - sequence gives recency
+    entries: Vec<(usize, PatchSource, Patch)>,
+    next_seq: usize,
+    /// Request this frame belongs to; merging is recorded in its timing breakdown
+    correlation_id: Option<String>,
+}
+
+impl FrameAggregator {
+    /// Create an empty aggregator
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty aggregator for the request identified by `correlation_id`
+    pub fn with_correlation_id(correlation_id: impl Into<String>) -> Self {
+        Self {
+            correlation_id: Some(correlation_id.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Correlation id of the request this frame belongs to
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.correlation_id.as_deref()
+    }
+
+    /// Add a patch set; later sets take precedence over earlier ones
+    pub fn add(&mut self, source: PatchSource, patches: Vec<Patch>) {
+        for patch in patches {
+            self.entries.push((self.next_seq, source.clone(), patch));
+            self.next_seq += 1;
+        }
+    }
+
+    /// Number of patches added so far
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if no patches were added
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Merge everything added so far into one de-duplicated batch
+    pub fn finish(self) -> Vec<Patch> {
+        self.finish_with_stats().0
+    }
+
+    /// Merge everything added so far, also returning merge statistics
+    pub fn finish_with_stats(self) -> (Vec<Patch>, FrameStats) {
+        let start = std::time::Instant::now();
+        let mut stats = FrameStats {
+            patches_in: self.entries.len(),
+            ..Default::default()
+        };
+
+        // Walk newest → oldest so the first patch seen for a slot is the winner
+        let mut kept: Vec<(usize, Patch)> = Vec::new();
+        // (path, slot) → index into `kept`
+        let mut claimed: HashMap<(HexPath, PatchSlot), usize> = HashMap::new();
+        let mut structural_roots: Vec<HexPath> = Vec::new();
+        // Indexes into `kept` of Removes whose node was created in this frame
+        let mut cancelled: HashSet<usize> = HashSet::new();
+
+        for (seq, source, patch) in self.entries.into_iter().rev() {
+            let path = patch.path().clone();
+            let slot = slot_of(&patch);
+
+            // Custom patches and lifecycle markers are events, passed through as they are
+            if slot == PatchSlot::Custom || slot == PatchSlot::Lifecycle {
+                kept.push((seq, patch));
+                continue;
+            }
+
+            // Same slot already claimed by a newer patch: either an exact duplicate or a conflict
+            if let Some(&kept_idx) = claimed.get(&(path.clone(), slot.clone())) {
+                if matches!(patch, Patch::Create { .. }) && matches!(kept[kept_idx].1, Patch::Remove { .. }) {
+                    // The node never reaches the client; an older patch may claim the slot again
+                    crate::log_debug!("Frame aggregator: {:?} create at '{}' removed in the same frame", source, path);
+                    cancelled.insert(kept_idx);
+                    claimed.remove(&(path, slot));
+                    stats.created_and_removed += 1;
+                } else if kept[kept_idx].1 == patch {
+                    stats.duplicates_dropped += 1;
+                } else {
+                    crate::log_debug!("Frame aggregator: {:?} {} at '{}' overridden by newer patch", source, patch.kind(), path);
+                    stats.conflicts_resolved += 1;
+                }
+                continue;
+            }
+
+            // Superseded by a newer structural patch at or above this path
+            if structural_roots.iter().any(|root| path.is_within(root)) {
+                crate::log_debug!("Frame aggregator: {:?} {} at '{}' superseded by structural patch", source, patch.kind(), path);
+                stats.conflicts_resolved += 1;
+                continue;
+            }
+
+            if patch.is_structural() {
+                structural_roots.push(path.clone());
+            }
+            claimed.insert((path, slot), kept.len());
+            kept.push((seq, patch));
+        }
+
+        // Restore emission order
+        let mut kept: Vec<(usize, Patch)> = kept.into_iter()
+            .enumerate()
+            .filter(|(idx, _)| !cancelled.contains(idx))
+            .map(|(_, entry)| entry)
+            .collect();
+        kept.sort_by_key(|(seq, _)| *seq);
+        let patches: Vec<Patch> = kept.into_iter().map(|(_, p)| p).collect();
+        stats.patches_out = patches.len();
+
+        crate::log_debug!(
+            "Frame aggregated: {} patches in, {} out ({} duplicates, {} conflicts)",
+            stats.patches_in,
+            stats.patches_out,
+            stats.duplicates_dropped,
+            stats.conflicts_resolved
+        );
+
+        if let Some(id) = &self.correlation_id {
+            crate::correlation::record_span(
+                id,
+                crate::correlation::TraceStage::Aggregate,
+                start.elapsed(),
+                Some(format!("{} → {} patches", stats.patches_in, stats.patches_out)),
+            );
+        }
+
+        (patches, stats)
+    }
+}
+
+/// Convenience: merge several patch sets (oldest first) into one batch
+pub fn aggregate_frame(sets: Vec<(PatchSource, Vec<Patch>)>) -> Vec<Patch> {
+    let mut aggregator = FrameAggregator::new();
+    for (source, patches) in sets {
+        aggregator.add(source, patches);
+    }
+    aggregator.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vdom::VNode;
+
+    fn text_patch(path: &str, content: &str) -> Patch {
+        Patch::UpdateText {
+            path: HexPath::from(path),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_exact_duplicates_dropped() {
+        let mut agg = FrameAggregator::new();
+        agg.add(PatchSource::Hint { hint_id: "inc".to_string() }, vec![text_patch("10000000", "Count: 1")]);
+        agg.add(PatchSource::Prediction, vec![text_patch("10000000", "Count: 1")]);
+
+        let (patches, stats) = agg.finish_with_stats();
+        assert_eq!(patches.len(), 1);
+        assert_eq!(stats.duplicates_dropped, 1);
+    }
+
+    #[test]
+    fn test_conflicts_resolved_by_recency() {
+        let patches = aggregate_frame(vec![
+            (PatchSource::Prediction, vec![text_patch("10000000", "Count: 1"), text_patch("20000000", "A")]),
+            (PatchSource::Reconcile, vec![text_patch("10000000", "Count: 2")]),
+        ]);
+
+        assert_eq!(patches.len(), 2);
+        assert_eq!(patches[0], text_patch("20000000", "A"));
+        assert_eq!(patches[1], text_patch("10000000", "Count: 2"));
+    }
+
+    #[test]
+    fn test_different_attributes_do_not_conflict() {
+        let attr = |name: &str, value: &str| Patch::UpdateAttributeStatic {
+            path: HexPath::from("10000000"),
+            attr_name: name.to_string(),
+            value: value.to_string(),
+        };
+        let patches = aggregate_frame(vec![
+            (PatchSource::Prediction, vec![attr("class", "a")]),
+            (PatchSource::Reconcile, vec![attr("title", "b")]),
+        ]);
+        assert_eq!(patches.len(), 2);
+    }
+
+    #[test]
+    fn test_structural_patch_supersedes_descendants() {
+        let replace = Patch::Replace {
+            path: HexPath::from("10000000"),
+            node: VNode::text("new"),
+            preserve: None,
+        };
+        let (patches, stats) = {
+            let mut agg = FrameAggregator::new();
+            agg.add(PatchSource::Prediction, vec![
+                text_patch("10000000.10000000", "old child"),
+                text_patch("20000000", "sibling"),
+            ]);
+            agg.add(PatchSource::Reconcile, vec![replace.clone()]);
+            agg.finish_with_stats()
+        };
+
+        assert_eq!(patches, vec![text_patch("20000000", "sibling"), replace]);
+        assert_eq!(stats.conflicts_resolved, 1);
+    }
+
+    #[test]
+    fn test_create_then_remove_cancels_out() {
+        let path = HexPath::from("30000000");
+        let (patches, stats) = {
+            let mut agg = FrameAggregator::new();
+            agg.add(PatchSource::Prediction, vec![
+                Patch::Create { path: path.clone(), node: VNode::text("toast") },
+                text_patch("30000000", "toast!"),
+                text_patch("20000000", "sibling"),
+            ]);
+            agg.add(PatchSource::Reconcile, vec![Patch::Remove { path }]);
+            agg.finish_with_stats()
+        };
+
+        assert_eq!(patches, vec![text_patch("20000000", "sibling")]);
+        assert_eq!((stats.created_and_removed, stats.conflicts_resolved), (1, 1));
+    }
+}
@@ -0,0 +1,159 @@
+//! Generation-tagged handles
+//!
+//! Hosts hold predictors by a plain `usize` handle. A handle packs a slot index
+//! (low half) and the slot's generation (high half): destroying an instance frees
+//! its slot and bumps the generation, so a handle kept past destroy is recognized
+//! as stale instead of reaching whatever reuses the slot. Lookups tell the cases
+//! apart (`MinimactError::StaleHandle` vs `InvalidHandle`) and stale uses are
+//! counted in `stale_handle_uses`.
+//!
+//! Generations wrap after 2^32 reuses of one slot (2^16 on 32-bit targets).
+
+use crate::error::{MinimactError, Result};
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+const GENERATION_SHIFT: u32 = usize::BITS / 2;
+const INDEX_MASK: usize = (1 << GENERATION_SHIFT) - 1;
+
+/// Handle of `index` at `generation`
+pub fn pack_handle(index: usize, generation: usize) -> usize {
+    (generation << GENERATION_SHIFT) | (index & INDEX_MASK)
+}
+
+/// (index, generation) of a handle
+pub fn unpack_handle(handle: usize) -> (usize, usize) {
+    (handle & INDEX_MASK, handle >> GENERATION_SHIFT)
+}
+
+/// Instances by generation-tagged handle
+/// Lookups only touch the slot's shard, so they don't wait on creates or destroys
+pub struct HandleTable<T> {
+    /// index -> (generation, instance)
+    live: DashMap<usize, (usize, Arc<T>)>,
+    /// Freed slots with the generation their next instance gets
+    free: Mutex<Vec<(usize, usize)>>,
+    /// Slots ever handed out are 1..next_index
+    next_index: AtomicUsize,
+}
+
+impl<T> Default for HandleTable<T> {
+    fn default() -> Self {
+        Self { live: DashMap::new(), free: Mutex::new(Vec::new()), next_index: AtomicUsize::new(1) }
+    }
+}
+
+impl<T> HandleTable<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store `value`, reusing a freed slot if there is one
+    pub fn insert(&self, value: T) -> usize {
+        let (index, generation) = self
+            .free
+            .lock()
+            .unwrap()
+            .pop()
+            .unwrap_or_else(|| (self.next_index.fetch_add(1, Ordering::SeqCst), 1));
+        self.live.insert(index, (generation, Arc::new(value)));
+        pack_handle(index, generation)
+    }
+
+    /// Store `value` under a handle issued earlier (e.g. by another process)
+    /// Returns false if the slot is in use
+    pub fn insert_at(&self, handle: usize, value: T) -> bool {
+        let (index, generation) = unpack_handle(handle);
+        if index == 0 {
+            return false;
+        }
+        match self.live.entry(index) {
+            dashmap::mapref::entry::Entry::Occupied(_) => false,
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                entry.insert((generation, Arc::new(value)));
+                self.free.lock().unwrap().retain(|&(free, _)| free != index);
+                self.next_index.fetch_max(index + 1, Ordering::SeqCst);
+                true
+            }
+        }
+    }
+
+    /// The instance behind `handle`
+    pub fn get(&self, handle: usize) -> Result<Arc<T>> {
+        let (index, generation) = unpack_handle(handle);
+        match self.live.get(&index) {
+            Some(slot) if slot.0 == generation => Ok(Arc::clone(&slot.1)),
+            _ => Err(self.lookup_error(handle)),
+        }
+    }
+
+    pub fn contains(&self, handle: usize) -> bool {
+        let (index, generation) = unpack_handle(handle);
+        self.live.get(&index).is_some_and(|slot| slot.0 == generation)
+    }
+
+    /// Check if `handle`'s slot holds an instance, of any generation
+    pub fn slot_taken(&self, handle: usize) -> bool {
+        self.live.contains_key(&unpack_handle(handle).0)
+    }
+
+    /// Destroy the instance behind `handle`, freeing its slot
+    pub fn remove(&self, handle: usize) -> Result<Arc<T>> {
+        let (index, generation) = unpack_handle(handle);
+        match self.live.remove_if(&index, |_, slot| slot.0 == generation) {
+            Some((_, (_, value))) => {
+                let next = (generation + 1) & (usize::MAX >> GENERATION_SHIFT);
+                self.free.lock().unwrap().push((index, next.max(1)));
+                Ok(value)
+            }
+            None => Err(self.lookup_error(handle)),
+        }
+    }
+
+    /// Every live instance by handle
+    pub fn entries(&self) -> Vec<(usize, Arc<T>)> {
+        self.live.iter().map(|entry| (pack_handle(*entry.key(), entry.value().0), Arc::clone(&entry.value().1))).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.live.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.live.is_empty()
+    }
+
+    fn lookup_error(&self, handle: usize) -> MinimactError {
+        let (index, _) = unpack_handle(handle);
+        if index != 0 && index < self.next_index.load(Ordering::SeqCst) {
+            crate::metrics::METRICS.record_stale_handle_use();
+            MinimactError::StaleHandle(handle)
+        } else {
+            MinimactError::InvalidHandle(handle)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reused_slot_rejects_old_handle() {
+        let table = HandleTable::new();
+        let first = table.insert("first");
+        assert_eq!(*table.get(first).unwrap(), "first");
+
+        table.remove(first).unwrap();
+        assert!(matches!(table.get(first), Err(MinimactError::StaleHandle(_))));
+        let second = table.insert("second");
+        assert_eq!(unpack_handle(second).0, unpack_handle(first).0);
+        assert!(matches!(table.get(first), Err(MinimactError::StaleHandle(_))));
+        assert!(table.remove(first).is_err());
+        assert_eq!(*table.get(second).unwrap(), "second");
+
+        assert!(matches!(table.get(pack_handle(99, 1)), Err(MinimactError::InvalidHandle(_))));
+        assert!(matches!(table.get(0), Err(MinimactError::InvalidHandle(_))));
+    }
+}
@@ -0,0 +1,155 @@
+//! Headless end-to-end harness for the runtime protocol
+//!
+//! Drives the engine the way the browser host and the C# runtime do, without either:
+//! each ExecuteRequest carries the tree the runtime rendered for a component, plus
+//! the state change that caused the render. The harness predicts from the change
+//! (as the host does before the render arrives), reconciles against the component's
+//! previous tree, learns the pattern, and applies the patches to a simulated client
+//! (`sim`). Expected patches and HTML can be put on each request, so a JSON fixture
+//! covers the whole pipeline.
+//!
+//! Fixture: a JSON array of requests, e.g.
+//! `[{"request_id": "1", "component_id": "Counter", "tree": {...}},
+//!   {"request_id": "2", "component_id": "Counter", "state_change": {...}, "tree": {...},
+//!    "expect_html": "<p>1</p>"}]`
+//! The first request for a component is its initial render.
+
+use crate::error::Result;
+use crate::predictor::{Predictor, StateChange};
+use crate::reconciler::reconcile;
+use crate::sim::Simulator;
+use crate::vdom::{Patch, VNode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// One render from the runtime
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecuteRequest {
+    pub request_id: String,
+    pub component_id: String,
+    /// What triggered the render (None for an initial render)
+    #[serde(default)]
+    pub state_change: Option<StateChange>,
+    /// The component's full state after the change
+    #[serde(default)]
+    pub state: Option<HashMap<String, serde_json::Value>>,
+    pub tree: VNode,
+    /// Patches the client must receive
+    #[serde(default)]
+    pub expect_patches: Option<Vec<Patch>>,
+    /// Page the client must show afterwards
+    #[serde(default)]
+    pub expect_html: Option<String>,
+    /// Whether the predictor must have had the right patches ready
+    #[serde(default)]
+    pub expect_predicted: Option<bool>,
+}
+
+/// What the pipeline did for one request
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecuteResponse {
+    pub request_id: String,
+    /// Patches sent to the client (empty for an initial render)
+    pub patches: Vec<Patch>,
+    /// Patches the predictor had ready before the render, if any
+    pub predicted: Option<Vec<Patch>>,
+    /// The client's page after the patches
+    pub html: String,
+    /// Expectations on the request that didn't hold
+    pub failures: Vec<String>,
+}
+
+impl ExecuteResponse {
+    /// The prediction matched the real patches
+    pub fn prediction_hit(&self) -> bool {
+        self.predicted.as_ref() == Some(&self.patches)
+    }
+}
+
+/// Engine plus simulated clients, one per component
+#[derive(Default)]
+pub struct Harness {
+    predictor: Predictor,
+    clients: HashMap<String, Simulator>,
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run one request through predict → reconcile → learn → client apply
+    pub fn execute(&mut self, request: &ExecuteRequest) -> Result<ExecuteResponse> {
+        let (patches, predicted) = match self.clients.get_mut(&request.component_id) {
+            None => {
+                self.clients.insert(request.component_id.clone(), Simulator::new(request.tree.clone()));
+                (Vec::new(), None)
+            }
+            Some(client) => {
+                let old_tree = client.tree().clone();
+                let patches = reconcile(&old_tree, &request.tree)?;
+                let predicted = match &request.state_change {
+                    Some(change) => {
+                        let predicted = self.predictor.predict(change, &old_tree).map(|p| p.predicted_patches);
+                        self.predictor.learn(change.clone(), &old_tree, &request.tree, request.state.as_ref())?;
+                        predicted
+                    }
+                    None => None,
+                };
+                client.apply_batch(&patches)?;
+                (patches, predicted)
+            }
+        };
+
+        let client = &self.clients[&request.component_id];
+        let mut response = ExecuteResponse {
+            request_id: request.request_id.clone(),
+            patches,
+            predicted,
+            html: client.html(),
+            failures: Vec::new(),
+        };
+        if client.tree() != &request.tree {
+            response.failures.push("client tree differs from the rendered tree".to_string());
+        }
+        if let Some(expected) = request.expect_patches.as_ref().filter(|expected| **expected != response.patches) {
+            response.failures.push(format!("expected patches {:?}, got {:?}", expected, response.patches));
+        }
+        if let Some(expected) = request.expect_html.as_ref().filter(|expected| **expected != response.html) {
+            response.failures.push(format!("expected HTML {}, got {}", expected, response.html));
+        }
+        if let Some(expected) = request.expect_predicted.filter(|expected| *expected != response.prediction_hit()) {
+            response.failures.push(format!("expected prediction hit: {}", expected));
+        }
+        Ok(response)
+    }
+
+    /// Run a fixture (JSON array of ExecuteRequest) in order
+    pub fn run_fixture(&mut self, fixture_json: &str) -> Result<Vec<ExecuteResponse>> {
+        let requests: Vec<ExecuteRequest> = serde_json::from_str(fixture_json)?;
+        requests.iter().map(|request| self.execute(request)).collect()
+    }
+}
+
+/// Run a fixture on a fresh harness and panic on the first failed expectation
+pub fn assert_fixture(fixture_json: &str) -> Vec<ExecuteResponse> {
+    let responses = Harness::new().run_fixture(fixture_json).expect("fixture failed to run");
+    for response in &responses {
+        assert!(response.failures.is_empty(), "request {}: {}", response.request_id, response.failures.join("; "));
+    }
+    responses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle_fixture() {
+        let responses = assert_fixture(include_str!("../fixtures/harness/Toggle.execute.json"));
+        assert_eq!(responses.len(), 4);
+        // Learned on the first open, predicted on the second
+        assert!(responses[0].patches.is_empty());
+        assert!(responses[3].prediction_hit());
+    }
+}
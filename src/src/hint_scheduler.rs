@@ -0,0 +1,297 @@
+//! Idle-time precomputation of usePredictHint predictions
+//!
+//! Hints used to be computed as soon as they arrived, competing with user
+//! interactions for the predictor. The HintScheduler queues them instead; the host
+//! drains the queue from a Background-priority task on the task runtime when
+//! nothing more urgent is running (`run_idle`, bounded by a time budget).
+//!
+//! When the real state change arrives, `on_state_change` hands back a matching
+//! precomputed prediction if there is one and cancels queued hints for the same
+//! state that haven't been computed yet. Utilization (hints used / hints computed)
+//! is reported in METRICS.
+//!
+//! Precomputed predictions whose state change never arrives are dropped after
+//! READY_HINT_TTL, and at most MAX_READY_HINTS are kept (the oldest goes first).
+
+use crate::clock::Clock;
+use crate::predictor::{Prediction, Predictor, StateChange};
+use crate::vdom::VNode;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Queued hints kept per scheduler before the oldest is dropped
+pub const MAX_QUEUED_HINTS: usize = 256;
+
+/// Precomputed predictions kept per scheduler before the oldest is dropped
+pub const MAX_READY_HINTS: usize = 256;
+
+/// How long a precomputed prediction waits for its state change
+pub const READY_HINT_TTL: Duration = Duration::from_secs(60);
+
+/// A hint waiting to be precomputed
+#[derive(Debug, Clone)]
+pub struct HintJob {
+    pub hint_id: String,
+    pub component_id: String,
+    pub state_changes: Vec<StateChange>,
+    /// Tree the hint's patches will apply to
+    pub tree: VNode,
+}
+
+impl HintJob {
+    fn covers(&self, change: &StateChange) -> bool {
+        self.component_id == change.component_id
+            && self.state_changes.iter().any(|c| c.state_key == change.state_key)
+    }
+}
+
+/// Background queue of hint computations plus their finished predictions
+pub struct HintScheduler {
+    queue: VecDeque<HintJob>,
+    /// (component_id, hint_id) → precomputed prediction and when it was computed
+    ready: HashMap<(String, String), (Prediction, Instant)>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for HintScheduler {
+    fn default() -> Self {
+        Self {
+            queue: VecDeque::new(),
+            ready: HashMap::new(),
+            clock: crate::clock::clock(),
+        }
+    }
+}
+
+impl std::fmt::Debug for HintScheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HintScheduler")
+            .field("queue", &self.queue)
+            .field("ready", &self.ready)
+            .finish_non_exhaustive()
+    }
+}
+
+impl HintScheduler {
+    /// Create an empty scheduler
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `clock` for budgets and prediction ages (e.g. a MockClock in tests)
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Number of hints waiting to be computed
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Number of precomputed predictions waiting for their state change
+    pub fn ready(&self) -> usize {
+        self.ready.len()
+    }
+
+    /// Queue a hint for idle-time computation
+    /// A queued hint with the same id for the same component is replaced
+    pub fn schedule(&mut self, job: HintJob) {
+        self.queue
+            .retain(|queued| !(queued.component_id == job.component_id && queued.hint_id == job.hint_id));
+        if self.queue.len() >= MAX_QUEUED_HINTS {
+            if let Some(dropped) = self.queue.pop_front() {
+                crate::log_warn!("Hint queue full, dropping '{}' for {}", dropped.hint_id, dropped.component_id);
+                crate::metrics::METRICS.record_hint_cancelled();
+            }
+        }
+        crate::metrics::METRICS.record_hint_scheduled();
+        self.queue.push_back(job);
+    }
+
+    /// Compute queued hints until the queue is empty or `budget` is spent
+    /// Returns the number of hints computed
+    pub fn run_idle(&mut self, predictor: &mut Predictor, budget: Duration) -> usize {
+        let start = self.clock.now();
+        let mut computed = 0;
+        self.expire_ready(start);
+
+        while self.clock.now().saturating_duration_since(start) < budget {
+            let Some(job) = self.queue.pop_front() else { break };
+            let job_start = self.clock.now();
+            let prediction = predictor.predict_hint(&job.hint_id, &job.component_id, job.state_changes, &job.tree);
+            let now = self.clock.now();
+            crate::metrics::METRICS.record_hint_computed(now.saturating_duration_since(job_start));
+            computed += 1;
+
+            if let Some(prediction) = prediction {
+                self.insert_ready((job.component_id, job.hint_id), prediction, now);
+            }
+        }
+
+        if computed > 0 {
+            crate::log_debug!(
+                "Precomputed {} hints in {:?} ({} still queued)",
+                computed,
+                self.clock.now().saturating_duration_since(start),
+                self.queue.len()
+            );
+        }
+        computed
+    }
+
+    fn insert_ready(&mut self, key: (String, String), prediction: Prediction, now: Instant) {
+        if self.ready.len() >= MAX_READY_HINTS && !self.ready.contains_key(&key) {
+            // Ties go to the smallest key so runs are reproducible
+            let oldest = self.ready.iter().min_by_key(|(key, (_, at))| (*at, *key)).map(|(key, _)| key.clone());
+            if let Some((component_id, hint_id)) = oldest {
+                crate::log_debug!("Too many precomputed hints, dropping '{}' for {}", hint_id, component_id);
+                self.ready.remove(&(component_id, hint_id));
+            }
+        }
+        self.ready.insert(key, (prediction, now));
+    }
+
+    /// Drop precomputed predictions older than READY_HINT_TTL
+    fn expire_ready(&mut self, now: Instant) {
+        self.ready.retain(|_, (_, at)| now.saturating_duration_since(*at) < READY_HINT_TTL);
+    }
+
+    /// The real state change arrived: return a precomputed prediction for it, if any
+    ///
+    /// Queued hints for the same state are cancelled (they lost the race), and the
+    /// component's other precomputed predictions are discarded since they were
+    /// computed against a tree that is about to change.
+    pub fn on_state_change(&mut self, change: &StateChange) -> Option<Prediction> {
+        self.expire_ready(self.clock.now());
+        let before = self.queue.len();
+        self.queue.retain(|job| !job.covers(change));
+        for _ in self.queue.len()..before {
+            crate::metrics::METRICS.record_hint_cancelled();
+        }
+
+        let hit = self
+            .ready
+            .iter()
+            .find(|((component_id, _), (prediction, _))| {
+                component_id == &change.component_id
+                    && prediction.state_change.state_key == change.state_key
+                    && prediction.state_change.new_value == change.new_value
+            })
+            .map(|(key, _)| key.clone());
+        let prediction = hit.and_then(|key| self.ready.remove(&key)).map(|(prediction, _)| prediction);
+        if prediction.is_some() {
+            crate::metrics::METRICS.record_hint_used();
+        }
+
+        self.ready.retain(|(component_id, _), _| component_id != &change.component_id);
+        prediction
+    }
+
+    /// Drop everything queued or precomputed for a component (e.g. on unmount)
+    pub fn clear_component(&mut self, component_id: &str) {
+        let before = self.queue.len();
+        self.queue.retain(|job| job.component_id != component_id);
+        for _ in self.queue.len()..before {
+            crate::metrics::METRICS.record_hint_cancelled();
+        }
+        self.ready.retain(|(id, _), _| id != component_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::HexPath;
+    use crate::vdom::{VElement, VText};
+    use serde_json::json;
+
+    fn counter(count: i64) -> VNode {
+        VNode::Element(VElement {
+            tag: "span".to_string(),
+            props: std::collections::HashMap::new(),
+            children: vec![Some(VNode::Text(VText {
+                content: format!("Count: {}", count),
+                path: HexPath::from("10000000.10000000"),
+            }))],
+            key: None,
+            path: HexPath::from("10000000"),
+            source: None,
+        })
+    }
+
+    fn change(old: i64, new: i64) -> StateChange {
+        StateChange {
+            component_id: "counter".to_string(),
+            state_key: "count".to_string(),
+            old_value: json!(old),
+            new_value: json!(new),
+            array_operation: None,
+            locale: None,
+        }
+    }
+
+    fn trained_predictor() -> Predictor {
+        let mut predictor = Predictor::new();
+        for i in 0..3 {
+            predictor.learn(change(i, i + 1), &counter(i), &counter(i + 1), None).ok();
+        }
+        predictor
+    }
+
+    fn job(hint_id: &str, old: i64, new: i64) -> HintJob {
+        HintJob {
+            hint_id: hint_id.to_string(),
+            component_id: "counter".to_string(),
+            state_changes: vec![change(old, new)],
+            tree: counter(old),
+        }
+    }
+
+    #[test]
+    fn test_precomputed_hint_is_used() {
+        let mut predictor = trained_predictor();
+        let mut scheduler = HintScheduler::new();
+        scheduler.schedule(job("increment", 5, 6));
+
+        assert_eq!(scheduler.run_idle(&mut predictor, Duration::from_secs(1)), 1);
+        assert_eq!(scheduler.ready(), 1);
+
+        let prediction = scheduler.on_state_change(&change(5, 6));
+        assert!(prediction.is_some());
+        assert_eq!(scheduler.ready(), 0);
+    }
+
+    #[test]
+    fn test_real_change_cancels_queued_hint() {
+        let mut scheduler = HintScheduler::new();
+        scheduler.schedule(job("increment", 5, 6));
+        scheduler.schedule(job("increment", 5, 6));
+        assert_eq!(scheduler.queued(), 1);
+
+        assert!(scheduler.on_state_change(&change(5, 6)).is_none());
+        assert_eq!(scheduler.queued(), 0);
+    }
+
+    #[test]
+    fn test_precomputed_hints_expire_and_are_capped() {
+        let clock = Arc::new(crate::clock::MockClock::new());
+        let mut predictor = trained_predictor();
+        let mut scheduler = HintScheduler::new();
+        scheduler.set_clock(clock.clone());
+
+        scheduler.schedule(job("increment", 5, 6));
+        scheduler.run_idle(&mut predictor, Duration::from_secs(1));
+        clock.advance(READY_HINT_TTL);
+        assert!(scheduler.on_state_change(&change(5, 6)).is_none());
+        assert_eq!(scheduler.ready(), 0);
+
+        for i in 0..=MAX_READY_HINTS {
+            scheduler.schedule(job(&format!("hint-{:04}", i), 5, 6));
+            scheduler.run_idle(&mut predictor, Duration::from_secs(1));
+            clock.advance(Duration::from_millis(1));
+        }
+        assert_eq!(scheduler.ready(), MAX_READY_HINTS);
+        assert!(!scheduler.ready.contains_key(&("counter".to_string(), "hint-0000".to_string())));
+    }
+}
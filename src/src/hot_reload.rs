@@ -0,0 +1,411 @@
+//! Hot reload patch minimizer
+//!
+//! When a component's source changes, the Babel plugin recompiles its templates
+//! (ComponentMetadata::templates). Most edits only touch a template string, and
+//! the client can take those as template patches without a re-render:
+//!
+//! - an "attribute-static" template changed → UpdateAttributeStatic
+//! - an "attribute-dynamic" template changed → UpdateAttributeDynamic
+//! - a text template changed → UpdateTextTemplate
+//! - anything else (templates added or removed, moved, changed kind, or a loop
+//!   template changed) is structural
+//!
+//! Structural changes need the rendered trees. Given the trees from before and
+//! after the reload, the plan adds every reconcile patch that the template patches
+//! don't already cover; without them it only flags `needs_rerender`.
+//!
+//! `summarize_hot_reload` describes what a reload changed in the rendered tree for
+//! the dev overlay: counts per change kind, the affected paths and the devtools
+//! annotation of every patch.
+
+use crate::annotations::{annotate_patches, PatchAnnotation};
+use crate::error::{MinimactError, Result};
+use crate::last_error::FfiCall;
+use crate::reconciler::reconcile;
+use crate::path::HexPath;
+use crate::tree_index::TreeIndex;
+use crate::vdom::{ComponentMetadata, Patch, TemplateInfo, VNode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+
+/// How a template changed between two compiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateChangeKind {
+    AttributeStatic,
+    AttributeTemplate,
+    TextTemplate,
+    Structural,
+}
+
+/// One changed template
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateChange {
+    /// Key in ComponentMetadata::templates (or "loop:{state_key}" for loop templates)
+    pub path_key: String,
+    pub kind: TemplateChangeKind,
+}
+
+/// Patches for one hot reload, cheapest kind first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HotReloadPlan {
+    /// Changed templates, sorted by path key
+    pub changes: Vec<TemplateChange>,
+    pub patches: Vec<Patch>,
+    /// Structural changes were found but no trees were given to diff
+    pub needs_rerender: bool,
+}
+
+/// Compare two compiles of a component and pick the cheapest patches
+/// `trees` are the rendered (old, new) trees, needed for structural changes
+pub fn plan_hot_reload(
+    old: &ComponentMetadata,
+    new: &ComponentMetadata,
+    trees: Option<(&VNode, &VNode)>,
+) -> Result<HotReloadPlan> {
+    let mut keys: Vec<&String> = old.templates.keys().chain(new.templates.keys()).collect::<HashSet<_>>().into_iter().collect();
+    keys.sort();
+
+    let mut changes = Vec::new();
+    let mut patches = Vec::new();
+    for key in keys {
+        let (old_template, new_template) = (old.templates.get(key), new.templates.get(key));
+        if old_template == new_template {
+            continue;
+        }
+        let (kind, patch) = classify(old_template, new_template);
+        changes.push(TemplateChange { path_key: key.clone(), kind });
+        patches.extend(patch);
+    }
+
+    let mut loop_keys: Vec<&String> = old.loop_templates.keys().chain(new.loop_templates.keys()).collect::<HashSet<_>>().into_iter().collect();
+    loop_keys.sort();
+    for key in loop_keys {
+        if old.loop_templates.get(key) != new.loop_templates.get(key) {
+            changes.push(TemplateChange { path_key: format!("loop:{}", key), kind: TemplateChangeKind::Structural });
+        }
+    }
+
+    let structural = changes.iter().any(|c| c.kind == TemplateChangeKind::Structural);
+    let needs_rerender = match trees {
+        Some((old_tree, new_tree)) => {
+            let covered = Coverage::new(&patches);
+            let old_index = TreeIndex::build(old_tree);
+            let uncovered: Vec<Patch> = reconcile(old_tree, new_tree)?
+                .into_iter()
+                .filter(|patch| !covered.covers(patch, old_tree, &old_index))
+                .collect();
+            patches.extend(uncovered);
+            false
+        }
+        None => structural,
+    };
+
+    Ok(HotReloadPlan { changes, patches, needs_rerender })
+}
+
+/// What a hot reload changed in a component's rendered tree
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HotReloadSummary {
+    /// Text nodes whose content changed
+    pub text_changes: usize,
+    /// Individual attributes added, removed or changed
+    pub attribute_changes: usize,
+    /// Nodes created, removed or replaced, and keyed lists reordered
+    pub structural_changes: usize,
+    /// Patch targets in document order, without duplicates
+    pub affected_paths: Vec<HexPath>,
+    /// One annotation per patch
+    pub changes: Vec<PatchAnnotation>,
+}
+
+/// Summarize the reconcile of the trees rendered before and after a hot reload
+pub fn summarize_hot_reload(old_tree: &VNode, new_tree: &VNode) -> Result<HotReloadSummary> {
+    let patches = reconcile(old_tree, new_tree)?;
+    let old_index = TreeIndex::build(old_tree);
+
+    let (mut text_changes, mut attribute_changes, mut structural_changes) = (0, 0, 0);
+    for patch in &patches {
+        match patch {
+            Patch::UpdateText { .. } | Patch::UpdateTextTemplate { .. } => text_changes += 1,
+            Patch::UpdateProps { path, props } => {
+                attribute_changes += match old_index.get(old_tree, path) {
+                    Some(VNode::Element(old)) => {
+                        old.props.keys().filter(|name| !props.contains_key(*name)).count()
+                            + props.iter().filter(|(name, value)| old.props.get(*name) != Some(value)).count()
+                    }
+                    _ => props.len(),
+                }
+            }
+            Patch::UpdatePropsTemplate { .. } | Patch::UpdateAttributeStatic { .. } | Patch::UpdateAttributeDynamic { .. } => {
+                attribute_changes += 1
+            }
+            _ => structural_changes += 1,
+        }
+    }
+
+    let mut affected_paths: Vec<HexPath> = patches.iter().map(|patch| patch.path().clone()).collect();
+    affected_paths.sort_by(|a, b| a.cmp_document_order(b));
+    affected_paths.dedup();
+
+    Ok(HotReloadSummary {
+        text_changes,
+        attribute_changes,
+        structural_changes,
+        affected_paths,
+        changes: annotate_patches(&patches, old_tree),
+    })
+}
+
+/// Classify one changed template and build its patch (None when structural)
+fn classify(old: Option<&TemplateInfo>, new: Option<&TemplateInfo>) -> (TemplateChangeKind, Option<Patch>) {
+    let (Some(old), Some(new)) = (old, new) else {
+        return (TemplateChangeKind::Structural, None);
+    };
+    if old.path != new.path || old.attribute != new.attribute {
+        return (TemplateChangeKind::Structural, None);
+    }
+
+    match (new.template_type.as_str(), new.get_attribute_name()) {
+        ("attribute-static", Some(attr_name)) if old.template_type == new.template_type => (
+            TemplateChangeKind::AttributeStatic,
+            Some(Patch::UpdateAttributeStatic {
+                path: new.path.clone(),
+                attr_name: attr_name.to_string(),
+                value: new.template.clone(),
+            }),
+        ),
+        ("attribute-static" | "attribute-dynamic", Some(attr_name)) if old.is_attribute_template() => (
+            TemplateChangeKind::AttributeTemplate,
+            Some(Patch::UpdateAttributeDynamic {
+                path: new.path.clone(),
+                attr_name: attr_name.to_string(),
+                template_patch: new.to_template_patch(),
+            }),
+        ),
+        _ if old.is_text_template() && new.is_text_template() => (
+            TemplateChangeKind::TextTemplate,
+            Some(Patch::UpdateTextTemplate { path: new.path.clone(), template_patch: new.to_template_patch() }),
+        ),
+        _ => (TemplateChangeKind::Structural, None),
+    }
+}
+
+/// What the template patches of a plan already update
+struct Coverage<'p> {
+    texts: HashSet<&'p crate::path::HexPath>,
+    attributes: HashSet<(&'p crate::path::HexPath, &'p str)>,
+}
+
+impl<'p> Coverage<'p> {
+    fn new(patches: &'p [Patch]) -> Self {
+        let mut coverage = Coverage { texts: HashSet::new(), attributes: HashSet::new() };
+        for patch in patches {
+            match patch {
+                Patch::UpdateTextTemplate { path, .. } => {
+                    coverage.texts.insert(path);
+                }
+                Patch::UpdateAttributeStatic { path, attr_name, .. } | Patch::UpdateAttributeDynamic { path, attr_name, .. } => {
+                    coverage.attributes.insert((path, attr_name.as_str()));
+                }
+                _ => {}
+            }
+        }
+        coverage
+    }
+
+    /// A text update at a templated text, or a props update whose changed props
+    /// all have attribute patches
+    fn covers(&self, patch: &Patch, old_tree: &VNode, old_index: &TreeIndex) -> bool {
+        match patch {
+            Patch::UpdateText { path, .. } => self.texts.contains(path),
+            Patch::UpdateProps { path, props } => {
+                let Some(VNode::Element(old_el)) = old_index.get(old_tree, path) else {
+                    return false;
+                };
+                let old_props = &old_el.props;
+                old_props
+                    .keys()
+                    .chain(props.keys())
+                    .filter(|name| old_props.get(*name) != props.get(*name))
+                    .all(|name| self.attributes.contains(&(path, name.as_str())))
+            }
+            _ => false,
+        }
+    }
+}
+
+unsafe fn parse_optional_tree(json: *const c_char) -> Result<Option<VNode>> {
+    if json.is_null() {
+        return Ok(None);
+    }
+    let config = crate::validation::ValidationConfig::default();
+    crate::validation::deserialize_vnode_safe(CStr::from_ptr(json).to_str()?, &config).map(Some)
+}
+
+/// Plan the patches of a hot reload from the old and new ComponentMetadata JSON
+/// Returns HotReloadPlan JSON (or {"error": ...})
+///
+/// # Safety
+/// - old_metadata_json / new_metadata_json must be valid null-terminated UTF-8 strings
+/// - old_tree_json / new_tree_json may both be null (no structural diff), otherwise
+///   both must be valid null-terminated UTF-8 strings
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_hot_reload_patches(
+    old_metadata_json: *const c_char,
+    new_metadata_json: *const c_char,
+    old_tree_json: *const c_char,
+    new_tree_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_hot_reload_patches", &[old_metadata_json, new_metadata_json]);
+    let result = (|| -> Result<String> {
+        let old: ComponentMetadata = serde_json::from_str(CStr::from_ptr(old_metadata_json).to_str()?)?;
+        let new: ComponentMetadata = serde_json::from_str(CStr::from_ptr(new_metadata_json).to_str()?)?;
+        let trees = match (parse_optional_tree(old_tree_json)?, parse_optional_tree(new_tree_json)?) {
+            (Some(old_tree), Some(new_tree)) => Some((old_tree, new_tree)),
+            (None, None) => None,
+            _ => return Err(MinimactError::NullPointer("old_tree_json / new_tree_json (pass both or neither)")),
+        };
+        let plan = plan_hot_reload(&old, &new, trees.as_ref().map(|(o, n)| (o, n)))?;
+        Ok(serde_json::to_string(&plan)?)
+    })();
+
+    match result {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => {
+            crate::last_error::record_error(&e);
+            CString::new(serde_json::json!({ "error": e.to_string() }).to_string()).unwrap().into_raw()
+        }
+    }
+}
+
+/// Summarize a hot reload for the dev overlay from the trees rendered before and after
+/// Returns HotReloadSummary JSON (or {"error": ...})
+///
+/// # Safety
+/// - Both pointers must be valid null-terminated UTF-8 strings
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_hot_reload_summary(old_tree_json: *const c_char, new_tree_json: *const c_char) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_hot_reload_summary", &[old_tree_json, new_tree_json]);
+    let result = (|| -> Result<String> {
+        match (parse_optional_tree(old_tree_json)?, parse_optional_tree(new_tree_json)?) {
+            (Some(old_tree), Some(new_tree)) => Ok(serde_json::to_string(&summarize_hot_reload(&old_tree, &new_tree)?)?),
+            _ => Err(MinimactError::NullPointer("old_tree_json / new_tree_json")),
+        }
+    })();
+
+    match result {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => {
+            crate::last_error::record_error(&e);
+            CString::new(serde_json::json!({ "error": e.to_string() }).to_string()).unwrap().into_raw()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::HexPath;
+    use crate::vdom::{VElement, VText};
+    use std::collections::HashMap;
+
+    fn template(template: &str, path: &str, template_type: &str, attribute: Option<&str>) -> TemplateInfo {
+        TemplateInfo {
+            template: template.to_string(),
+            bindings: if template.contains("{0}") { vec!["count".to_string()] } else { vec![] },
+            slots: vec![],
+            path: HexPath::from(path),
+            template_type: template_type.to_string(),
+            attribute: attribute.map(str::to_string),
+            conditional_templates: None,
+            transform: None,
+            nullable: None,
+        }
+    }
+
+    fn metadata(templates: Vec<(&str, TemplateInfo)>) -> ComponentMetadata {
+        let mut metadata = ComponentMetadata::new("Counter_1", "Counter");
+        for (key, info) in templates {
+            metadata.add_template(key, info);
+        }
+        metadata
+    }
+
+    fn tree(class: &str, title: &str, extra: bool) -> VNode {
+        let mut children = vec![Some(VNode::Text(VText { content: title.to_string(), path: HexPath::from("10000000.10000000") }))];
+        if extra {
+            children.push(Some(VNode::Text(VText { content: "new".to_string(), path: HexPath::from("10000000.20000000") })));
+        }
+        VNode::Element(VElement {
+            tag: "div".to_string(),
+            props: HashMap::from([("class".to_string(), class.to_string())]),
+            children,
+            key: None,
+            path: HexPath::from("10000000"),
+            source: None,
+        })
+    }
+
+    #[test]
+    fn test_template_edits_become_template_patches() {
+        let old = metadata(vec![
+            ("h1.text", template("Count: {0}", "10000000.10000000", "dynamic", None)),
+            ("button.@class", template("btn", "10000000.20000000", "attribute-static", Some("class"))),
+            ("p.text", template("Bye", "10000000.30000000", "static", None)),
+        ]);
+        let new = metadata(vec![
+            ("h1.text", template("Total: {0}", "10000000.10000000", "dynamic", None)),
+            ("button.@class", template("btn primary", "10000000.20000000", "attribute-static", Some("class"))),
+        ]);
+
+        let plan = plan_hot_reload(&old, &new, None).unwrap();
+        let kinds: Vec<(&str, TemplateChangeKind)> = plan.changes.iter().map(|c| (c.path_key.as_str(), c.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("button.@class", TemplateChangeKind::AttributeStatic),
+                ("h1.text", TemplateChangeKind::TextTemplate),
+                ("p.text", TemplateChangeKind::Structural),
+            ]
+        );
+        assert_eq!(plan.patches.iter().map(Patch::kind).collect::<Vec<_>>(), vec!["UpdateAttributeStatic", "UpdateTextTemplate"]);
+        assert!(plan.needs_rerender);
+    }
+
+    #[test]
+    fn test_structural_changes_fall_back_to_uncovered_reconcile_patches() {
+        let old = metadata(vec![
+            ("div.@class", template("a", "10000000", "attribute-static", Some("class"))),
+            ("div.text", template("Hi", "10000000.10000000", "static", None)),
+        ]);
+        let new = metadata(vec![
+            ("div.@class", template("b", "10000000", "attribute-static", Some("class"))),
+            ("div.text", template("Hello", "10000000.10000000", "static", None)),
+            ("div.text2", template("new", "10000000.20000000", "static", None)),
+        ]);
+
+        let (old_tree, new_tree) = (tree("a", "Hi", false), tree("b", "Hello", true));
+        let plan = plan_hot_reload(&old, &new, Some((&old_tree, &new_tree))).unwrap();
+        // The class and title changes stay template patches; only the new text is diffed in
+        let kinds: Vec<&str> = plan.patches.iter().map(Patch::kind).collect();
+        assert_eq!(kinds, vec!["UpdateAttributeStatic", "UpdateTextTemplate", "Create"]);
+        assert!(!plan.needs_rerender);
+    }
+
+    #[test]
+    fn test_summary_counts_changes() {
+        let summary = summarize_hot_reload(&tree("a", "Hi", false), &tree("b", "Hello", true)).unwrap();
+        assert_eq!((summary.text_changes, summary.attribute_changes, summary.structural_changes), (1, 1, 1));
+        assert_eq!(
+            summary.affected_paths,
+            vec![HexPath::from("10000000"), HexPath::from("10000000.10000000"), HexPath::from("10000000.20000000")]
+        );
+        assert_eq!(summary.changes.len(), 3);
+        assert!(summary.changes.iter().any(|c| c.description == "props changed at div: class 'a' → 'b'"));
+    }
+}
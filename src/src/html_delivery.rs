@@ -0,0 +1,258 @@
+//! Patches or rendered HTML
+//!
+//! A diff that rewrites most of a component can take more bytes, and far more client
+//! work, than the component's HTML. `choose_delivery` estimates both: patches by
+//! their JSON size plus a per-patch apply overhead, HTML by the size of the tree
+//! rendered server-side (`ssr::render_html`). Under `Auto` the HTML wins when it costs at
+//! most `html_ratio` of the patches; the margin favours patches, which keep focus,
+//! selection and scroll positions that replacing the markup loses.
+//!
+//! HTML can't carry client-owned regions (see `ignored_regions`) or unexpanded lazy
+//! subtrees, so trees containing either always get patches, whatever the mode.
+//!
+//! The mode is configurable per component, with a process-wide default; which side
+//! won is counted in the metrics.
+
+use crate::error::{FfiResult, Result};
+use crate::last_error::FfiCall;
+use crate::path::HexPath;
+use crate::ssr::{html_unsafe, render_html};
+use crate::vdom::{Patch, VNode};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+/// How a component's updates are delivered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeliveryMode {
+    /// Whichever is estimated cheaper
+    #[default]
+    Auto,
+    /// Always patches
+    Patches,
+    /// Always HTML (unless nothing changed or the tree can't be sent as HTML)
+    Html,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeliveryConfig {
+    pub mode: DeliveryMode,
+    /// HTML wins when its cost is at most this fraction of the patches' cost
+    pub html_ratio: f64,
+    /// Bytes-equivalent charged per patch for the client's work applying it
+    pub patch_overhead_bytes: usize,
+}
+
+impl Default for DeliveryConfig {
+    fn default() -> Self {
+        Self { mode: DeliveryMode::Auto, html_ratio: 0.8, patch_overhead_bytes: 32 }
+    }
+}
+
+/// What to send the client
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Delivery {
+    Patches { patches: Vec<Patch> },
+    /// Replace the node at `path` (the component's root) with `html`
+    ReplaceHtml { path: HexPath, html: String },
+}
+
+/// Estimated cost of both ways of sending one update
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeliveryCost {
+    pub patch_count: usize,
+    pub patch_bytes: usize,
+    /// 0 when HTML wasn't an option (Patches mode, nothing changed, or a tree HTML can't carry)
+    pub html_bytes: usize,
+    /// patch_bytes plus the per-patch overhead
+    pub patch_cost: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeliveryDecision {
+    pub delivery: Delivery,
+    pub cost: DeliveryCost,
+}
+
+lazy_static::lazy_static! {
+    /// Delivery configs by component (None = the default for every component)
+    static ref DELIVERY_CONFIGS: DashMap<Option<String>, DeliveryConfig> = DashMap::new();
+}
+
+/// Set the delivery config of one component, or the default (None)
+/// None as the config removes it
+pub fn set_delivery_config(component_id: Option<&str>, config: Option<DeliveryConfig>) {
+    let scope = component_id.map(str::to_string);
+    match config {
+        Some(config) => {
+            DELIVERY_CONFIGS.insert(scope, config);
+        }
+        None => {
+            DELIVERY_CONFIGS.remove(&scope);
+        }
+    }
+}
+
+/// The component's delivery config, else the default one
+pub fn delivery_config(component_id: &str) -> DeliveryConfig {
+    DELIVERY_CONFIGS
+        .get(&Some(component_id.to_string()))
+        .or_else(|| DELIVERY_CONFIGS.get(&None))
+        .map(|config| config.clone())
+        .unwrap_or_default()
+}
+
+/// Estimate both costs of updating the client to `new_tree` and pick one
+/// The tree is only rendered when HTML could be chosen
+pub fn choose_delivery(patches: Vec<Patch>, new_tree: &VNode, config: &DeliveryConfig) -> DeliveryDecision {
+    let html_possible = !patches.is_empty() && config.mode != DeliveryMode::Patches && !html_unsafe(new_tree);
+    let html = html_possible.then(|| render_html(new_tree));
+    // Patch serialization can't fail (string keys, no non-finite floats)
+    let patch_bytes = serde_json::to_vec(&patches).map_or(0, |json| json.len());
+    let cost = DeliveryCost {
+        patch_count: patches.len(),
+        patch_bytes,
+        html_bytes: html.as_ref().map_or(0, String::len),
+        patch_cost: patch_bytes + patches.len() * config.patch_overhead_bytes,
+    };
+
+    let html = html.filter(|_| match config.mode {
+        DeliveryMode::Patches => false,
+        DeliveryMode::Html => true,
+        DeliveryMode::Auto => cost.html_bytes as f64 <= cost.patch_cost as f64 * config.html_ratio,
+    });
+    crate::metrics::METRICS.record_delivery(html.is_some());
+
+    let delivery = match html {
+        Some(html) => {
+            crate::log_debug!("Delivering '{}' as HTML ({} bytes vs {} patches)", new_tree.path(), cost.html_bytes, cost.patch_count);
+            Delivery::ReplaceHtml { path: new_tree.path().clone(), html }
+        }
+        None => Delivery::Patches { patches },
+    };
+    DeliveryDecision { delivery, cost }
+}
+
+/// Reconcile a component and deliver the result as patches or HTML
+pub fn reconcile_delivery(component_id: &str, old: &VNode, new: &VNode) -> Result<DeliveryDecision> {
+    let patches = crate::reconciler::reconcile(old, new)?;
+    Ok(choose_delivery(patches, new, &delivery_config(component_id)))
+}
+
+/// Set the delivery config of a component (or the default, with a null component_id)
+///
+/// # Safety
+/// - component_id must be null or a valid null-terminated UTF-8 string
+/// - config_json (DeliveryConfig) must be null, to remove the config, or a valid
+///   null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_set_delivery_config(component_id: *const c_char, config_json: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_set_delivery_config", &[component_id, config_json]);
+    let result = (|| -> Result<()> {
+        let component_id = if component_id.is_null() { None } else { Some(CStr::from_ptr(component_id).to_str()?) };
+        let config = if config_json.is_null() {
+            None
+        } else {
+            Some(serde_json::from_str(CStr::from_ptr(config_json).to_str()?)?)
+        };
+        set_delivery_config(component_id, config);
+        Ok(())
+    })();
+    match result {
+        Ok(()) => FfiResult::success(),
+        Err(e) => FfiResult::error(&e),
+    }
+}
+
+/// Reconcile two trees of a component and return a DeliveryDecision as JSON
+/// (or {"error": ...})
+///
+/// # Safety
+/// - component_id, old_json and new_json must be valid null-terminated UTF-8 strings
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_reconcile_delivery(
+    component_id: *const c_char,
+    old_json: *const c_char,
+    new_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_reconcile_delivery", &[component_id, old_json, new_json]);
+    let result = (|| -> Result<DeliveryDecision> {
+        let validation_config = crate::validation::ValidationConfig::default();
+        let component_id = CStr::from_ptr(component_id).to_str()?;
+        let old = crate::validation::deserialize_vnode_safe(CStr::from_ptr(old_json).to_str()?, &validation_config)?;
+        let new = crate::validation::deserialize_vnode_safe(CStr::from_ptr(new_json).to_str()?, &validation_config)?;
+        reconcile_delivery(component_id, &old, &new)
+    })();
+    crate::tree_store::json_or_error(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vdom::{VElement, VText};
+    use std::collections::HashMap;
+
+    fn list(items: &[String]) -> VNode {
+        let root = HexPath::root().child(0);
+        let element = |tag: &str, path: HexPath, children| {
+            VNode::Element(VElement { tag: tag.to_string(), props: HashMap::new(), children, key: None, path, source: None })
+        };
+        let rows = items.iter().enumerate().map(|(i, item)| {
+            let path = root.child(i);
+            let text = VNode::Text(VText { content: item.clone(), path: path.child(0) });
+            Some(element("li", path, vec![Some(text)]))
+        });
+        element("ul", root.clone(), rows.collect())
+    }
+
+    fn rows(label: &str) -> Vec<String> {
+        (0..12).map(|i| format!("{} row number {}", label, i)).collect()
+    }
+
+    #[test]
+    fn test_rewritten_list_is_delivered_as_html() {
+        let config = DeliveryConfig::default();
+        let old = list(&rows("old"));
+
+        // One text change: patches are far smaller
+        let mut one_changed = rows("old");
+        one_changed[3] = "changed".to_string();
+        let one = list(&one_changed);
+        let decision = choose_delivery(crate::reconciler::reconcile(&old, &one).unwrap(), &one, &config);
+        assert!(matches!(decision.delivery, Delivery::Patches { ref patches } if patches.len() == 1));
+
+        // Every row rewritten
+        let all = list(&rows("new"));
+        let decision = choose_delivery(crate::reconciler::reconcile(&old, &all).unwrap(), &all, &config);
+        assert!(decision.cost.html_bytes < decision.cost.patch_cost);
+        assert_eq!(decision.delivery, Delivery::ReplaceHtml { path: all.path().clone(), html: render_html(&all) });
+
+        let patches_only = DeliveryConfig { mode: DeliveryMode::Patches, ..config };
+        let decision = choose_delivery(crate::reconciler::reconcile(&old, &all).unwrap(), &all, &patches_only);
+        assert!(matches!(decision.delivery, Delivery::Patches { .. }));
+        assert_eq!(decision.cost.html_bytes, 0);
+
+        // Nothing changed: nothing rendered
+        let decision = choose_delivery(Vec::new(), &all, &config);
+        assert_eq!((decision.delivery, decision.cost.html_bytes), (Delivery::Patches { patches: Vec::new() }, 0));
+    }
+
+    #[test]
+    fn test_html_mode_keeps_client_owned_regions_on_patches() {
+        let html_only = DeliveryConfig { mode: DeliveryMode::Html, ..Default::default() };
+        let old = list(&rows("old"));
+        let mut new = list(&rows("new"));
+        let decision = choose_delivery(crate::reconciler::reconcile(&old, &new).unwrap(), &new, &html_only);
+        assert!(matches!(decision.delivery, Delivery::ReplaceHtml { .. }));
+
+        if let VNode::Element(el) = &mut new {
+            el.props.insert(crate::ignored_regions::IGNORE_ATTR.to_string(), String::new());
+        }
+        let decision = choose_delivery(crate::reconciler::reconcile(&old, &new).unwrap(), &new, &html_only);
+        assert!(matches!(decision.delivery, Delivery::Patches { .. }));
+    }
+}
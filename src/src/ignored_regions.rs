@@ -0,0 +1,44 @@
+//! Client-owned regions
+//!
+//! Some DOM regions belong to client-side JS (maps, charts, rich editors) that
+//! rewrites them freely, so any server patch inside them would clobber its work.
+//! An element with the `data-minimact-ignore` attribute (any value but "false")
+//! marks such a region. When an element is ignored in both trees, the reconciler
+//! compares only the element itself: a changed tag or path still replaces it, but
+//! nothing else about it or its subtree produces patches.
+//!
+//! Marking and unmarking travel as explicit `SetIgnored` patches, so the client can
+//! hand the region to its own code or take it back:
+//! - marking sends the element's usual diff (the server's last update to it), then
+//!   `SetIgnored { ignored: true }`
+//! - unmarking sends `SetIgnored { ignored: false }`, then a Replace of the element,
+//!   since the server can't know what the client's code left in it
+//!
+//! A Replace of an ancestor (changed tag, collapsed diff) still rebuilds the region.
+
+use crate::vdom::{VElement, VNode};
+
+/// Attribute marking a client-owned region
+pub const IGNORE_ATTR: &str = "data-minimact-ignore";
+
+/// Check if `el` marks a client-owned region
+pub fn is_ignored(el: &VElement) -> bool {
+    el.props.get(IGNORE_ATTR).is_some_and(|value| value != "false")
+}
+
+/// Check if `node` is an element marking a client-owned region
+pub fn is_ignored_node(node: &VNode) -> bool {
+    matches!(node, VNode::Element(el) if is_ignored(el))
+}
+
+/// Mark or unmark `el` as a client-owned region
+pub fn set_ignored(el: &mut VElement, ignored: bool) {
+    if ignored {
+        if is_ignored(el) {
+            return;
+        }
+        el.props.insert(IGNORE_ATTR.to_string(), String::new());
+    } else {
+        el.props.remove(IGNORE_ATTR);
+    }
+}
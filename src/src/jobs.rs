@@ -0,0 +1,286 @@
+//! Background jobs for expensive FFI calls
+//!
+//! A host that can't afford to block its calling thread on a reconcile, learn or
+//! template materialization submits it as a job instead: `minimact_submit_job`
+//! returns a job id at once and the work runs on rayon's global pool (the one
+//! `reconcile_bulk` uses). Finished jobs wait in a completion queue until the host
+//! drains it with `minimact_poll_completions`, e.g. once per event loop turn.
+//!
+//! Requests are JSON tagged by "type" and carry the same arguments as the blocking
+//! call; parsing happens on the pool too, through the same size checks and tree
+//! normalization as the blocking calls. A completion holds the blocking call's
+//! result as `data`, or its error.
+//!
+//! A queue holds at most `max_jobs` jobs, running or waiting to be polled; further
+//! submissions fail with CapacityExceeded until the host drains completions.
+
+use crate::error::{MinimactError, Result};
+use crate::ffi::PredictorHandle;
+use crate::last_error::FfiCall;
+use crate::predictor::StateChange;
+use crate::reconciler::ReconcileStrategy;
+use crate::template_renderer::StateValues;
+use crate::vdom::{Patch, VNode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Id of a submitted job (never 0)
+pub type JobId = u64;
+
+/// An FFI call to run in the background
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type")]
+pub enum JobRequest {
+    /// Like minimact_reconcile_with_strategy; data is the patch list
+    Reconcile {
+        old: VNode,
+        new: VNode,
+        #[serde(default)]
+        strategy: ReconcileStrategy,
+    },
+    /// Like minimact_predictor_learn; data is null
+    Learn {
+        handle: PredictorHandle,
+        state_change: StateChange,
+        old_tree: VNode,
+        new_tree: VNode,
+        #[serde(default)]
+        all_state: Option<StateValues>,
+    },
+    /// Render template patches to baseline patches with the given state values
+    /// (see `capabilities::negotiate_patches`); data is the patch list
+    Materialize {
+        patches: Vec<Patch>,
+        #[serde(default)]
+        state: StateValues,
+    },
+}
+
+impl JobRequest {
+    /// Parse a request (JSON), scanning it before serde allocates its trees
+    pub fn parse(json: &str) -> Result<Self> {
+        let config = crate::validation::ValidationConfig::default();
+        crate::validation::check_json_input(json, &config.for_batch(2))?;
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Run the call on the current thread
+    pub fn run(self) -> Result<Value> {
+        let config = crate::validation::ValidationConfig::default();
+        match self {
+            JobRequest::Reconcile { mut old, mut new, strategy } => {
+                crate::validation::prepare_vnode(&mut old, &config)?;
+                crate::validation::prepare_vnode(&mut new, &config)?;
+                Ok(serde_json::to_value(crate::reconciler::reconcile_with_strategy(&old, &new, &strategy)?)?)
+            }
+            JobRequest::Learn { handle, state_change, mut old_tree, mut new_tree, all_state } => {
+                crate::validation::prepare_vnode(&mut old_tree, &config)?;
+                crate::validation::prepare_vnode(&mut new_tree, &config)?;
+                let predictor = crate::ffi::predictor(handle)?;
+                predictor.learn(state_change, &old_tree, &new_tree, all_state.as_ref())?;
+                Ok(Value::Null)
+            }
+            JobRequest::Materialize { patches, state } => {
+                let capabilities = crate::capabilities::ClientCapabilities::baseline();
+                Ok(serde_json::to_value(crate::capabilities::negotiate_patches(patches, &capabilities, &state))?)
+            }
+        }
+    }
+}
+
+/// A finished job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Completion {
+    pub job_id: JobId,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Completion {
+    fn new(job_id: JobId, result: Result<Value>) -> Self {
+        match result {
+            Ok(data) => Self { job_id, ok: true, data: Some(data), error: None },
+            Err(e) => Self { job_id, ok: false, data: None, error: Some(e.to_string()) },
+        }
+    }
+}
+
+/// Jobs a queue holds by default, running or waiting to be polled
+pub const DEFAULT_MAX_JOBS: usize = 1024;
+
+#[derive(Default)]
+struct Shared {
+    completed: Mutex<VecDeque<Completion>>,
+    running: AtomicUsize,
+}
+
+/// Jobs running on the thread pool and their completions
+pub struct JobQueue {
+    next_id: AtomicU64,
+    shared: Arc<Shared>,
+    /// Jobs submitted and not polled yet
+    held: AtomicUsize,
+    max_jobs: usize,
+}
+
+impl JobQueue {
+    pub fn new() -> Self {
+        Self::with_max_jobs(DEFAULT_MAX_JOBS)
+    }
+
+    /// A queue holding at most `max_jobs` jobs, running or waiting to be polled
+    pub fn with_max_jobs(max_jobs: usize) -> Self {
+        Self { next_id: AtomicU64::new(0), shared: Arc::default(), held: AtomicUsize::new(0), max_jobs }
+    }
+
+    /// Run `job` on the thread pool; its result is queued as a completion
+    /// A panicking job completes with an error; fails with CapacityExceeded if the
+    /// queue already holds `max_jobs` jobs
+    pub fn submit(&self, job: impl FnOnce() -> Result<Value> + Send + 'static) -> Result<JobId> {
+        self.held
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |held| (held < self.max_jobs).then_some(held + 1))
+            .map_err(|_| MinimactError::CapacityExceeded { what: "jobs", max: self.max_jobs })?;
+        let job_id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let shared = Arc::clone(&self.shared);
+        shared.running.fetch_add(1, Ordering::Relaxed);
+        rayon::spawn(move || {
+            let completion = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(job)) {
+                Ok(result) => Completion::new(job_id, result),
+                Err(_) => Completion { job_id, ok: false, data: None, error: Some("Job panicked".to_string()) },
+            };
+            shared.running.fetch_sub(1, Ordering::Relaxed);
+            shared.completed.lock().unwrap().push_back(completion);
+        });
+        Ok(job_id)
+    }
+
+    /// Run a request on the thread pool
+    pub fn submit_request(&self, request: JobRequest) -> Result<JobId> {
+        self.submit(move || request.run())
+    }
+
+    /// Take up to `max` completions, oldest first
+    pub fn poll(&self, max: usize) -> Vec<Completion> {
+        let mut completed = self.shared.completed.lock().unwrap();
+        let count = max.min(completed.len());
+        self.held.fetch_sub(count, Ordering::AcqRel);
+        completed.drain(..count).collect()
+    }
+
+    /// Jobs submitted but not finished yet
+    pub fn running(&self) -> usize {
+        self.shared.running.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for JobQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+lazy_static::lazy_static! {
+    /// Jobs submitted through the FFI
+    pub static ref JOBS: JobQueue = JobQueue::new();
+}
+
+/// Submit a JobRequest (JSON) to run in the background
+/// Returns the job id, or 0 if request_json isn't valid UTF-8 or the queue is full
+/// (see minimact_last_error_json); a request that doesn't parse completes with an error
+///
+/// # Safety
+/// - request_json must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_submit_job(request_json: *const c_char) -> JobId {
+    let _call = FfiCall::enter("minimact_submit_job", &[request_json]);
+    let request = match CStr::from_ptr(request_json).to_str() {
+        Ok(s) => s.to_string(),
+        Err(e) => {
+            crate::last_error::record_error(&MinimactError::from(e));
+            return 0;
+        }
+    };
+    let submitted = JOBS.submit(move || JobRequest::parse(&request)?.run());
+    submitted.unwrap_or_else(|e| {
+        crate::last_error::record_error(&e);
+        0
+    })
+}
+
+/// Take up to `max` finished jobs
+/// Returns a JSON array of Completion, oldest first
+///
+/// # Safety
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_poll_completions(max: usize) -> *mut c_char {
+    let _call = FfiCall::named("minimact_poll_completions");
+    let json = serde_json::to_string(&JOBS.poll(max)).unwrap_or_else(|_| "[]".to_string());
+    CString::new(json).unwrap().into_raw()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait_for(queue: &JobQueue, count: usize) -> Vec<Completion> {
+        let mut completions = Vec::new();
+        for _ in 0..1000 {
+            completions.extend(queue.poll(usize::MAX));
+            if completions.len() >= count {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        completions
+    }
+
+    #[test]
+    fn test_jobs_complete_through_the_queue() {
+        let queue = JobQueue::new();
+        let reconcile = queue
+            .submit_request(JobRequest::Reconcile {
+                old: VNode::text("a"),
+                new: VNode::text("b"),
+                strategy: ReconcileStrategy::default(),
+            })
+            .unwrap();
+        let learn = queue.submit(|| JobRequest::parse(r#"{"type": "Learn"}"#)?.run()).unwrap();
+        let panics = queue.submit(|| panic!("boom")).unwrap();
+
+        let mut completions = wait_for(&queue, 3);
+        completions.sort_by_key(|c| c.job_id);
+        assert_eq!(completions.iter().map(|c| (c.job_id, c.ok)).collect::<Vec<_>>(), vec![(reconcile, true), (learn, false), (panics, false)]);
+        assert_eq!(completions[0].data.as_ref().unwrap().as_array().unwrap().len(), 1);
+        assert_eq!(queue.running(), 0);
+    }
+
+    #[test]
+    fn test_full_queue_rejects_jobs_until_polled() {
+        let queue = JobQueue::with_max_jobs(1);
+        let (release, wait) = std::sync::mpsc::channel::<()>();
+        let first = queue.submit(move || {
+            wait.recv().ok();
+            Ok(Value::Null)
+        });
+        assert!(first.is_ok());
+        assert!(matches!(queue.submit(|| Ok(Value::Null)), Err(MinimactError::CapacityExceeded { max: 1, .. })));
+
+        // Finished but not polled still counts
+        release.send(()).unwrap();
+        while queue.running() > 0 {
+            std::thread::yield_now();
+        }
+        assert!(queue.submit(|| Ok(Value::Null)).is_err());
+        assert_eq!(wait_for(&queue, 1).len(), 1);
+        assert!(queue.submit(|| Ok(Value::Null)).is_ok());
+    }
+}
@@ -0,0 +1,213 @@
+//! Last-error slot for FFI calls
+//!
+//! A failed FFI call hands C# a message string and nothing else. Every failure
+//! reported through `record` is also kept in a per-thread slot together with the
+//! error code, the FFI entry point, a hash of the call's inputs and (when
+//! RUST_BACKTRACE is set) a backtrace. Right after a call fails the host can fetch
+//! it with `minimact_last_error_json` on the same thread, and attach it to a crash
+//! report. Failures are also counted per entry point in METRICS.
+//!
+//! FFI functions mark themselves with `FfiCall::enter(name, inputs)`; the input
+//! pointers are only read (and hashed) if the call actually fails.
+
+use crate::checksum::Fnv64;
+use crate::error::{ErrorCode, MinimactError};
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Everything known about the most recent failure on a thread
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LastError {
+    /// Numeric ErrorCode
+    pub code: i32,
+    /// ErrorCode name, e.g. "Serialization"
+    pub code_name: String,
+    pub message: String,
+    /// FFI function that failed ("unknown" when reported outside an FFI call)
+    pub entry_point: String,
+    /// FNV-1a over the call's string inputs (16 hex digits), to match failures
+    /// against captured requests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inputs_hash: Option<String>,
+    /// Total byte length of the call's string inputs
+    pub inputs_len: usize,
+    /// Only captured when RUST_BACKTRACE / RUST_LIB_BACKTRACE enable it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backtrace: Option<String>,
+    pub thread: Option<String>,
+    /// Wall-clock time, milliseconds since the unix epoch
+    pub timestamp_ms: u128,
+}
+
+struct CallInfo {
+    entry_point: &'static str,
+    inputs: SmallVec<[*const c_char; 4]>,
+}
+
+thread_local! {
+    static CURRENT_CALL: RefCell<Option<CallInfo>> = const { RefCell::new(None) };
+    static LAST_ERROR: RefCell<Option<LastError>> = const { RefCell::new(None) };
+}
+
+/// Marks the FFI call in progress on this thread (until dropped)
+pub struct FfiCall {
+    previous: Option<CallInfo>,
+}
+
+impl FfiCall {
+    /// Enter the FFI function `entry_point` (one without string arguments)
+    pub fn named(entry_point: &'static str) -> Self {
+        // Safety: no inputs to keep alive
+        unsafe { Self::enter(entry_point, &[]) }
+    }
+
+    /// Enter the FFI function `entry_point` with its C string arguments
+    ///
+    /// # Safety
+    /// - Non-null `inputs` must stay valid null-terminated strings until the guard is dropped
+    pub unsafe fn enter(entry_point: &'static str, inputs: &[*const c_char]) -> Self {
+        let call = CallInfo { entry_point, inputs: SmallVec::from_slice(inputs) };
+        Self { previous: CURRENT_CALL.with(|current| current.borrow_mut().replace(call)) }
+    }
+}
+
+impl Drop for FfiCall {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT_CALL.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Record a failure of the current FFI call (no-op for ErrorCode::Success)
+pub fn record(code: ErrorCode, message: &str) {
+    if code == ErrorCode::Success {
+        return;
+    }
+
+    let (entry_point, inputs_hash, inputs_len) = CURRENT_CALL.with(|current| match &*current.borrow() {
+        Some(call) => {
+            let mut hasher = Fnv64::new();
+            let mut len = 0;
+            for &input in call.inputs.iter().filter(|p| !p.is_null()) {
+                // Safety: FfiCall::enter's contract keeps the inputs alive while the call runs
+                let bytes = unsafe { CStr::from_ptr(input) }.to_bytes();
+                hasher.write(bytes);
+                hasher.write(&[0]);
+                len += bytes.len();
+            }
+            let hash = (!call.inputs.is_empty()).then(|| crate::checksum::checksum_to_hex(hasher.finish()));
+            (call.entry_point, hash, len)
+        }
+        None => ("unknown", None, 0),
+    });
+
+    let backtrace = Backtrace::capture();
+    let error = LastError {
+        code: code as i32,
+        code_name: format!("{:?}", code),
+        message: message.to_string(),
+        entry_point: entry_point.to_string(),
+        inputs_hash,
+        inputs_len,
+        backtrace: (backtrace.status() == BacktraceStatus::Captured).then(|| backtrace.to_string()),
+        thread: std::thread::current().name().map(str::to_string),
+        timestamp_ms: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis()),
+    };
+
+    crate::metrics::METRICS.record_ffi_error(entry_point);
+    crate::log_warn!("{} failed: {}", entry_point, message);
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(error));
+}
+
+/// Record a MinimactError as a failure of the current FFI call
+pub fn record_error(err: &MinimactError) {
+    record(ErrorCode::from(err), &err.to_string());
+}
+
+/// The most recent failure on this thread
+pub fn last_error() -> Option<LastError> {
+    LAST_ERROR.with(|last| last.borrow().clone())
+}
+
+/// Forget the most recent failure on this thread
+pub fn clear_last_error() {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+}
+
+/// Get the most recent failure on the calling thread as JSON (LastError), or "null"
+///
+/// # Safety
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_last_error_json() -> *mut c_char {
+    use std::ffi::CString;
+
+    match serde_json::to_string(&last_error()) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Forget the most recent failure on the calling thread
+#[no_mangle]
+pub extern "C" fn minimact_clear_last_error() {
+    clear_last_error();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn test_records_entry_point_and_inputs() {
+        let input = CString::new("{\"tag\": \"div\"}").unwrap();
+        {
+            let _call = unsafe { FfiCall::enter("minimact_test_entry", &[input.as_ptr(), std::ptr::null()]) };
+            record(ErrorCode::Serialization, "bad tree");
+        }
+
+        let error = last_error().unwrap();
+        assert_eq!(error.entry_point, "minimact_test_entry");
+        assert_eq!(error.code, ErrorCode::Serialization as i32);
+        assert_eq!(error.code_name, "Serialization");
+        assert_eq!(error.inputs_len, input.as_bytes().len());
+        assert_eq!(error.inputs_hash.as_ref().map(String::len), Some(16));
+
+        // The guard is gone, so later failures aren't attributed to it
+        record(ErrorCode::Unknown, "outside");
+        assert_eq!(last_error().unwrap().entry_point, "unknown");
+        clear_last_error();
+        assert!(last_error().is_none());
+    }
+
+    #[test]
+    fn test_ffi_failures_are_recorded() {
+        let old = CString::new("{not json").unwrap();
+        let new = CString::new("{}").unwrap();
+        unsafe {
+            let response = crate::ffi::minimact_reconcile(old.as_ptr(), new.as_ptr());
+            let body: serde_json::Value = serde_json::from_str(CStr::from_ptr(response).to_str().unwrap()).unwrap();
+            assert!(body["error"].as_str().unwrap().starts_with("Failed to parse old tree"));
+            crate::ffi::minimact_free_string(response);
+        }
+
+        let error = last_error().unwrap();
+        assert_eq!(error.entry_point, "minimact_reconcile");
+        assert_eq!(error.inputs_len, old.as_bytes().len() + new.as_bytes().len());
+        assert!(crate::metrics::METRICS.snapshot().ffi_errors["minimact_reconcile"] >= 1);
+    }
+
+    #[test]
+    fn test_slot_is_per_thread() {
+        record(ErrorCode::InvalidHandle, "here");
+        let elsewhere = std::thread::spawn(last_error).join().unwrap();
+        assert!(elsewhere.is_none());
+        assert_eq!(last_error().unwrap().message, "here");
+    }
+}
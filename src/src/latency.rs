@@ -0,0 +1,248 @@
+//! End-to-end latency and client clock skew
+//!
+//! Batches sent by sessions are stamped (`BatchStamp`) with a process-wide sequence
+//! number and the server's wall clock. Clients report back when they received and
+//! applied a batch, in their own clock; with the time the report arrives that makes
+//! an NTP-style exchange:
+//! - offset = ((received - sent) + (applied - reported)) / 2
+//! - round trip = (reported - sent) - (applied - received)
+//!
+//! A client's skew estimate is the offset of its sample with the shortest round trip
+//! among the last few (queueing only ever lengthens a round trip, and skews the
+//! offset with it). Applied times taken back to the server clock give the latency
+//! from the state change (or the send, when the stamp doesn't know it) to the
+//! patches being applied, which goes into the metrics snapshot.
+
+use crate::error::{MinimactError, Result};
+use crate::last_error::FfiCall;
+use crate::patch_batch::BatchStamp;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Round trips kept per client for the skew estimate
+const SKEW_SAMPLES: usize = 8;
+
+/// Stamped batches awaiting an apply report; the oldest are forgotten past this
+const MAX_PENDING: usize = 4096;
+
+/// A client's account of one batch, in the client's clock (unix milliseconds)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApplyReport {
+    /// Usually the session id
+    pub client_id: String,
+    pub server_sequence: u64,
+    pub received_ms: u64,
+    pub applied_ms: u64,
+}
+
+/// Estimated clock offset of a client (client clock minus server clock)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClockSkew {
+    pub client_id: String,
+    pub offset_ms: f64,
+    /// Round trip of the sample the estimate comes from
+    pub round_trip_ms: f64,
+    pub samples: usize,
+}
+
+/// What one apply report measured
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencySample {
+    pub server_sequence: u64,
+    /// State change (or send) to applied, in server time
+    pub latency_ms: f64,
+    /// Offset estimate used to convert the applied time
+    pub offset_ms: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RoundTrip {
+    offset_ms: f64,
+    round_trip_ms: f64,
+}
+
+/// Stamps batches and turns apply reports into skew and latency measurements
+#[derive(Debug, Default)]
+pub struct LatencyTracker {
+    last_sequence: AtomicU64,
+    /// server_sequence -> stamp
+    pending: Mutex<BTreeMap<u64, BatchStamp>>,
+    clients: DashMap<String, VecDeque<RoundTrip>>,
+}
+
+lazy_static::lazy_static! {
+    pub static ref LATENCY: LatencyTracker = LatencyTracker::new();
+}
+
+/// Milliseconds since the Unix epoch (wall clock)
+pub fn unix_time_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64)
+}
+
+impl LatencyTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stamp for a batch sent now
+    pub fn stamp(&self, state_change_ms: Option<u64>) -> BatchStamp {
+        self.stamp_at(unix_time_ms(), state_change_ms)
+    }
+
+    pub fn stamp_at(&self, now_ms: u64, state_change_ms: Option<u64>) -> BatchStamp {
+        let stamp = BatchStamp {
+            server_sequence: self.last_sequence.fetch_add(1, Ordering::SeqCst) + 1,
+            server_time_ms: now_ms,
+            state_change_ms,
+        };
+        let mut pending = self.pending.lock().unwrap();
+        pending.insert(stamp.server_sequence, stamp);
+        while pending.len() > MAX_PENDING {
+            pending.pop_first();
+        }
+        stamp
+    }
+
+    /// Record a client's report of a batch, received now
+    pub fn report_applied(&self, report: &ApplyReport) -> Result<LatencySample> {
+        self.report_applied_at(report, unix_time_ms())
+    }
+
+    pub fn report_applied_at(&self, report: &ApplyReport, now_ms: u64) -> Result<LatencySample> {
+        let stamp = self
+            .pending
+            .lock()
+            .unwrap()
+            .remove(&report.server_sequence)
+            .ok_or_else(|| MinimactError::KeyNotFound(format!("batch {}", report.server_sequence)))?;
+
+        let (sent, reported) = (stamp.server_time_ms as f64, now_ms as f64);
+        let (received, applied) = (report.received_ms as f64, report.applied_ms as f64);
+        let sample = RoundTrip {
+            offset_ms: ((received - sent) + (applied - reported)) / 2.0,
+            round_trip_ms: ((reported - sent) - (applied - received)).max(0.0),
+        };
+        let offset_ms = {
+            let mut samples = self.clients.entry(report.client_id.clone()).or_default();
+            samples.push_back(sample);
+            if samples.len() > SKEW_SAMPLES {
+                samples.pop_front();
+            }
+            best(&samples).offset_ms
+        };
+
+        let start = stamp.state_change_ms.unwrap_or(stamp.server_time_ms) as f64;
+        let latency_ms = (applied - offset_ms - start).max(0.0);
+        crate::metrics::METRICS.record_end_to_end_latency(Duration::from_secs_f64(latency_ms / 1000.0));
+        Ok(LatencySample { server_sequence: report.server_sequence, latency_ms, offset_ms })
+    }
+
+    /// Skew estimate of one client (None before its first report)
+    pub fn skew(&self, client_id: &str) -> Option<ClockSkew> {
+        self.clients.get(client_id).map(|samples| skew(client_id, &samples))
+    }
+
+    /// Skew estimates of every client that reported, by client id
+    pub fn skews(&self) -> Vec<ClockSkew> {
+        let mut skews: Vec<ClockSkew> = self.clients.iter().map(|entry| skew(entry.key(), entry.value())).collect();
+        skews.sort_by(|a, b| a.client_id.cmp(&b.client_id));
+        skews
+    }
+
+    /// Drop a client's samples (e.g. its session ended)
+    pub fn forget_client(&self, client_id: &str) {
+        self.clients.remove(client_id);
+    }
+}
+
+fn best(samples: &VecDeque<RoundTrip>) -> RoundTrip {
+    samples
+        .iter()
+        .copied()
+        .min_by(|a, b| a.round_trip_ms.total_cmp(&b.round_trip_ms))
+        .unwrap_or(RoundTrip { offset_ms: 0.0, round_trip_ms: 0.0 })
+}
+
+fn skew(client_id: &str, samples: &VecDeque<RoundTrip>) -> ClockSkew {
+    let best = best(samples);
+    ClockSkew {
+        client_id: client_id.to_string(),
+        offset_ms: best.offset_ms,
+        round_trip_ms: best.round_trip_ms,
+        samples: samples.len(),
+    }
+}
+
+/// Report that a client applied a stamped batch
+/// Returns a LatencySample as JSON (or {"error": ...}, KeyNotFound for a batch that
+/// wasn't stamped or was already reported)
+///
+/// # Safety
+/// - report_json (ApplyReport) must be a valid null-terminated UTF-8 string
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_report_batch_applied(report_json: *const c_char) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_report_batch_applied", &[report_json]);
+    let result = (|| -> Result<LatencySample> {
+        let report: ApplyReport = serde_json::from_str(CStr::from_ptr(report_json).to_str()?)?;
+        LATENCY.report_applied(&report)
+    })();
+    crate::tree_store::json_or_error(result)
+}
+
+/// Clock skew estimates
+/// Returns a ClockSkew as JSON for one client, or an array for every client when
+/// client_id is null (or {"error": ...})
+///
+/// # Safety
+/// - client_id must be null or a valid null-terminated UTF-8 string
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_clock_skew(client_id: *const c_char) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_clock_skew", &[client_id]);
+    if client_id.is_null() {
+        return crate::tree_store::json_or_error(Ok(LATENCY.skews()));
+    }
+    let result = (|| -> Result<ClockSkew> {
+        let client_id = CStr::from_ptr(client_id).to_str()?;
+        LATENCY.skew(client_id).ok_or_else(|| MinimactError::KeyNotFound(client_id.to_string()))
+    })();
+    crate::tree_store::json_or_error(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skew_and_latency_from_reports() {
+        let tracker = LatencyTracker::new();
+        // Client clock 500ms ahead; 20ms each way, 5ms to apply
+        let report = |stamp: &BatchStamp, out_ms: u64| ApplyReport {
+            client_id: "s1".to_string(),
+            server_sequence: stamp.server_sequence,
+            received_ms: stamp.server_time_ms + out_ms + 500,
+            applied_ms: stamp.server_time_ms + out_ms + 505,
+        };
+
+        let first = tracker.stamp_at(10_000, Some(9_990));
+        let sample = tracker.report_applied_at(&report(&first, 20), 10_045).unwrap();
+        assert_eq!(sample.offset_ms, 500.0);
+        assert_eq!(sample.latency_ms, 35.0);
+
+        // A queued report (slow way out) doesn't move the estimate
+        let second = tracker.stamp_at(20_000, None);
+        tracker.report_applied_at(&report(&second, 300), 20_325).unwrap();
+        let skew = tracker.skew("s1").unwrap();
+        assert_eq!((skew.offset_ms, skew.round_trip_ms, skew.samples), (500.0, 40.0, 2));
+
+        assert!(matches!(tracker.report_applied_at(&report(&second, 20), 20_045), Err(MinimactError::KeyNotFound(_))));
+        assert_eq!(second.server_sequence, first.server_sequence + 1);
+    }
+}
@@ -0,0 +1,130 @@
+//! Layout hints for cascading attributes
+//!
+//! Some attributes apply to a whole subtree: flipping `dir` on a container mirrors
+//! everything inside it, and `lang` changes fonts, hyphenation and quotes, although
+//! no child's props changed. With cascade rules enabled, the reconciler follows the
+//! UpdateProps of such a change on an element with children by an advisory
+//! `InvalidateLayout` patch naming the attributes, which clients may use to
+//! recalculate the layout of the subtree.
+//!
+//! The hint changes nothing in the tree: applying it is a no-op, and clients that
+//! don't declare support for it simply don't get it. The rules are process-wide and
+//! off by default.
+
+use crate::error::{FfiResult, MinimactError};
+use crate::last_error::FfiCall;
+use crate::path::HexPath;
+use crate::vdom::{Patch, VElement};
+use arc_swap::ArcSwap;
+use serde::{Deserialize, Serialize};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::Arc;
+
+/// Attributes whose changes escalate to a layout hint
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CascadeRules {
+    /// Attribute names (case-insensitive); empty turns hints off
+    #[serde(default)]
+    pub attributes: Vec<String>,
+}
+
+impl CascadeRules {
+    /// `dir` and `lang`
+    pub fn i18n() -> Self {
+        Self { attributes: vec!["dir".to_string(), "lang".to_string()] }
+    }
+
+    /// Cascading attributes whose meaning differs between the two elements
+    /// (compared through the attribute table, so `dir="RTL"` → `"rtl"` isn't a change)
+    pub fn changed(&self, old_el: &VElement, new_el: &VElement) -> Vec<String> {
+        if self.attributes.is_empty() {
+            return Vec::new();
+        }
+        let table = crate::attribute_semantics::attribute_table();
+        let value = |el: &VElement, name: &str| {
+            el.props
+                .iter()
+                .find(|(prop, _)| prop.eq_ignore_ascii_case(name))
+                .and_then(|(prop, value)| table.canonical_value(prop, value))
+        };
+        self.attributes
+            .iter()
+            .filter(|name| value(old_el, name) != value(new_el, name))
+            .cloned()
+            .collect()
+    }
+
+    /// The hint for an element changed from `old_el` to `new_el` at `path`
+    /// (None when no cascading attribute changed or nothing is under the element)
+    pub fn hint(&self, path: &HexPath, old_el: &VElement, new_el: &VElement) -> Option<Patch> {
+        if new_el.children.iter().all(Option::is_none) {
+            return None;
+        }
+        let attributes = self.changed(old_el, new_el);
+        (!attributes.is_empty()).then(|| Patch::InvalidateLayout { path: path.clone(), attributes })
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref CASCADE_RULES: ArcSwap<CascadeRules> = ArcSwap::from_pointee(CascadeRules::default());
+}
+
+/// Replace the cascade rules used by the reconciler (process-wide)
+pub fn set_cascade_rules(rules: CascadeRules) {
+    CASCADE_RULES.store(Arc::new(rules));
+}
+
+pub fn cascade_rules() -> Arc<CascadeRules> {
+    CASCADE_RULES.load_full()
+}
+
+/// Set the cascade rules (`{"attributes": ["dir", "lang"]}`; `{}` turns hints off)
+///
+/// # Safety
+/// - rules_json must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_set_cascade_rules(rules_json: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_set_cascade_rules", &[rules_json]);
+    let rules = CStr::from_ptr(rules_json)
+        .to_str()
+        .map_err(MinimactError::from)
+        .and_then(|json| Ok(serde_json::from_str::<CascadeRules>(json)?));
+    match rules {
+        Ok(rules) => {
+            set_cascade_rules(rules);
+            FfiResult::success()
+        }
+        Err(e) => FfiResult::error(&e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vdom::VNode;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_dir_flip_hints_subtree_layout() {
+        let section = |dir: &str, lang: &str| {
+            let props = HashMap::from([("dir".to_string(), dir.to_string()), ("lang".to_string(), lang.to_string())]);
+            VNode::element("section", props, vec![Some(VNode::text("שלום"))])
+        };
+        let element = |node: &VNode| match node {
+            VNode::Element(el) => el.clone(),
+            _ => unreachable!(),
+        };
+        let (ltr, rtl) = (element(&section("ltr", "en")), element(&section("RTL", "he")));
+        let rules = CascadeRules::i18n();
+        let root = HexPath::root();
+
+        assert_eq!(rules.hint(&root, &ltr, &rtl), Some(Patch::InvalidateLayout { path: root.clone(), attributes: vec!["dir".to_string(), "lang".to_string()] }));
+        assert_eq!(rules.changed(&rtl, &element(&section("rtl", "he"))), Vec::<String>::new());
+        assert_eq!(CascadeRules::default().hint(&root, &ltr, &rtl), None);
+
+        let mut empty = rtl.clone();
+        empty.children.clear();
+        assert_eq!(rules.hint(&root, &ltr, &empty), None);
+    }
+}
@@ -0,0 +1,90 @@
+//! Lazy subtree placeholders
+//!
+//! Big collapsed regions (closed tree view nodes, rows outside a virtualized list's
+//! viewport) don't need to be rendered until the user opens them. The server sends a
+//! `VNode::Lazy` in their place: the reconciler treats it as opaque, so nothing under
+//! it is diffed or patched while it stays collapsed. When the host asks for the
+//! region, `expand_lazy` swaps the full subtree in and returns the patches that bring
+//! the client from the placeholder to the subtree.
+
+use crate::error::{MinimactError, Result};
+use crate::path::HexPath;
+use crate::tree_index::TreeIndex;
+use crate::vdom::{Patch, VLazy, VNode};
+
+/// Replace the subtree at `path` with a placeholder; returns the subtree taken out
+pub fn collapse_subtree(tree: &mut VNode, path: &HexPath, summary: impl Into<String>) -> Result<VNode> {
+    let index = TreeIndex::build(tree);
+    let node = index
+        .get_mut(tree, path)
+        .ok_or_else(|| MinimactError::InvalidVNode(format!("No node at '{}' to collapse", path)))?;
+    let placeholder = VNode::Lazy(VLazy { path: path.clone(), summary: summary.into() });
+    Ok(std::mem::replace(node, placeholder))
+}
+
+/// Swap the placeholder at `path` for `subtree` (rebased to `path`)
+/// Returns the patches for the client; fails if there's no placeholder at `path`
+pub fn expand_lazy(tree: &mut VNode, path: &HexPath, mut subtree: VNode) -> Result<Vec<Patch>> {
+    let index = TreeIndex::build(tree);
+    let node = match index.get_mut(tree, path) {
+        Some(node) if node.is_lazy() => node,
+        Some(node) => {
+            return Err(MinimactError::InvalidVNode(format!(
+                "Expected a Lazy placeholder at '{}', found {}",
+                path,
+                node.node_type()
+            )))
+        }
+        None => return Err(MinimactError::InvalidVNode(format!("No placeholder at '{}' to expand", path))),
+    };
+
+    if subtree.path() != path {
+        subtree.rebase_path(path);
+    }
+    *node = subtree.clone();
+    Ok(vec![Patch::Replace { path: path.clone(), node: subtree, preserve: None }])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reconciler::reconcile;
+    use crate::vdom::VElement;
+    use std::collections::HashMap;
+
+    fn list(items: &[&str]) -> VNode {
+        VNode::Element(VElement {
+            tag: "ul".to_string(),
+            props: HashMap::new(),
+            children: items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| Some(VNode::Text(crate::vdom::VText {
+                    content: item.to_string(),
+                    path: HexPath::from("10000000").child(i),
+                })))
+                .collect(),
+            key: None,
+            path: HexPath::from("10000000"),
+            source: None,
+        })
+    }
+
+    #[test]
+    fn test_collapsed_subtree_is_opaque_until_expanded() {
+        let path = HexPath::from("10000000");
+        let mut tree = list(&["a", "b"]);
+        let full = collapse_subtree(&mut tree, &path, "2 items").unwrap();
+        assert_eq!(full, list(&["a", "b"]));
+
+        // Reconciling placeholder against placeholder sends nothing
+        assert!(reconcile(&tree, &tree.clone()).unwrap().is_empty());
+
+        let patches = expand_lazy(&mut tree, &path, full.clone()).unwrap();
+        assert_eq!(patches, vec![Patch::Replace { path: path.clone(), node: full.clone(), preserve: None }]);
+        assert_eq!(tree, full);
+
+        // Nothing left to expand
+        assert!(expand_lazy(&mut tree, &path, full).is_err());
+    }
+}
@@ -3,21 +3,155 @@ pub mod reconciler;
 pub mod predictor;
 pub mod ffi;
 pub mod error;
+pub mod last_error;
 pub mod validation;
 pub mod patch_validator;
+pub mod patch_batch;
+pub mod pubsub;
+pub mod session;
+pub mod tenant;
+pub mod tree_store;
+pub mod text_normalization;
+pub mod prop_normalization;
+pub mod flags;
+pub mod prediction_bundle;
+pub mod template_encoding;
+pub mod patch_history;
+pub mod conflicts;
+pub mod rebase;
+pub mod strict;
+pub mod lifecycle;
+pub mod memo;
+pub mod dependencies;
+pub mod html_delivery;
+pub mod ssr;
+pub mod latency;
+pub mod layout_hints;
+pub mod pluralization;
+pub mod coverage;
+pub mod handles;
+pub mod jobs;
+pub mod ignored_regions;
+pub mod patch_filter;
+pub mod attribute_semantics;
+pub mod patch_summary;
+pub mod determinism;
+pub mod clock;
+pub mod hot_reload;
+pub mod engine_snapshot;
 pub mod logging;
+pub mod log_sink;
 pub mod metrics;
 pub mod path;  // Hex-based DOM path system
 pub mod deep_state_traversal;  // Phase 7
 pub mod reorder_detection;     // Phase 8
+pub mod array_diff;
 pub mod structural_template_extraction;  // Phase 5
+pub mod frame_aggregator;
+pub mod resync;
+pub mod checksum;
+pub mod template_renderer;
+pub mod capabilities;
+pub mod tree_index;
+pub mod apply;
+pub mod progressive;
+pub mod lazy;
+pub mod document;
+pub mod preservation;
+pub mod transaction;
+pub mod custom_patch;
+pub mod routing;
+pub mod sim;
+pub mod harness;
+pub mod annotations;
+pub mod correlation;
+pub mod rate_limit;
+pub mod hint_scheduler;
+pub mod arena;
+pub mod shared_tree;
+pub mod concurrent_predictor;
+pub mod schema;
+pub mod metrics_history;
+pub mod workload;
+pub mod widget_heuristics;
+pub mod tree_json_cache;
+pub mod corpus;
+#[cfg(feature = "paranoid")]
+pub mod paranoid;
+#[cfg(feature = "compression")]
+pub mod compression;
+#[cfg(feature = "server")]
+pub mod server;
 
-pub use vdom::{VNode, VElement, VText, Patch, TemplatePatch};
-pub use reconciler::{reconcile, reconcile_with_config};
-pub use predictor::{Predictor, StateChange, Prediction, PredictorConfig, EvictionPolicy};
+pub use vdom::{VNode, VElement, VText, VLazy, Patch, PreserveHints, TemplatePatch, SourceLocation};
+pub use reconciler::{reconcile, reconcile_bulk, reconcile_with_config, reconcile_with_strategy, reconcile_traced, reconcile_windowed, ReconcileStrategy, PatchLimitAction, ListWindow, ListWindows};
+pub use predictor::{Predictor, StateChange, Prediction, PredictorConfig, EvictionPolicy, PatternSimilarity, NegativeCaching, DuplicateSuppression, Localization, Provenance, PatternSummary};
+pub use array_diff::{ArrayEdit, diff_arrays, infer_array_operation};
+pub use deep_state_traversal::{StateDiff, diff_state_values};
 pub use error::{MinimactError, Result, ErrorCode, FfiResult};
-pub use validation::{ValidationConfig, deserialize_vnode_safe, serialize_vnode_safe};
-pub use patch_validator::{validate_patch, validate_patches, PatchValidatorConfig};
-pub use logging::{LogLevel, enable_logging, disable_logging, set_log_level, get_logs, get_logs_json, clear_logs};
+pub use last_error::{LastError, last_error, clear_last_error};
+pub use validation::{ValidationConfig, JsonShape, deserialize_vnode_safe, check_json_input, prepare_vnode, scan_json_shape, serialize_vnode_safe};
+pub use patch_validator::{validate_patch, validate_patches, validate_patches_detailed, PatchValidatorConfig, PatchValidationReport, PatchDiagnostic};
+pub use patch_batch::{PatchBatch, BatchStamp, IdentifiedPatch, BatchSequencer, SequenceTracker, BatchOrder, apply_batch, dedupe_batches};
+pub use pubsub::{Broker, InMemoryBroker, PatchFanout, Subscription};
+pub use session::{Session, SessionConfig, SessionRegistry, SessionStats, SESSIONS};
+pub use tenant::{TenantQuota, TenantRegistry, TenantStats, TenantUsage};
+pub use tree_store::{TreeStore, TreeStoreConfig, TreeStoreStats, StoredReconcile, TREE_STORE};
+pub use clock::{Clock, MockClock, SystemClock, set_clock};
+pub use determinism::{DeterministicConfig, SeededHashMap, SeededState, set_deterministic, deterministic};
+pub use text_normalization::{TextNormalization, normalize_text_nodes, set_text_normalization, text_normalization};
+pub use patch_filter::PatchFilter;
+pub use ignored_regions::{is_ignored, IGNORE_ATTR};
+pub use jobs::{Completion, JobId, JobQueue, JobRequest, JOBS};
+pub use handles::{pack_handle, unpack_handle, HandleTable};
+pub use coverage::{analyze_coverage, CoverageReport, PathCoverage};
+pub use pluralization::{pluralize, singularize};
+pub use layout_hints::{cascade_rules, set_cascade_rules, CascadeRules};
+pub use latency::{ApplyReport, ClockSkew, LatencySample, LatencyTracker, LATENCY};
+pub use html_delivery::{choose_delivery, reconcile_delivery, set_delivery_config, Delivery, DeliveryConfig, DeliveryCost, DeliveryDecision, DeliveryMode};
+pub use dependencies::{reconcile_cascade, CascadeBatch, DependencyGraph, PropBinding, DEPENDENCIES};
+pub use memo::{props_hash, MemoRegistry, MEMO};
+pub use strict::{clear_divergences, divergences, set_strict_mode, strict_mode, Divergence, StrictConfig};
+pub use lifecycle::{lifecycle_config, set_lifecycle_config, LifecycleConfig, LifecycleEvent};
+pub use rebase::{rebase_patches, DroppedPatch, RebaseResult};
+pub use conflicts::{classify_pair, detect_conflicts, ConflictKind, ConflictReport, PatchPair};
+pub use patch_history::{reconstruct, PatchHistory, DEFAULT_CHECKPOINT_EVERY};
+pub use template_encoding::{decode_compact, encode_compact, COMPACT_PREFIX};
+pub use prediction_bundle::{BundleEntry, BundleOptions, PredictionBundle, BUNDLE_VERSION};
+pub use flags::{Flag, flag_states, is_enabled as flag_enabled, reset_flags, set_flag, set_flag_by_name};
+pub use prop_normalization::{PropNaming, PropNormalization, canonical_prop_name, normalize_props, normalize_prop_names, prop_normalization, set_prop_normalization};
+pub use attribute_semantics::{AttributeKind, AttributeTable, attribute_table, set_attribute_table};
+pub use patch_summary::{KindSummary, PatchSummary, summarize_patches};
+pub use engine_snapshot::{EngineSnapshot, RestoreSummary, snapshot_engine, restore_engine};
+pub use hot_reload::{HotReloadPlan, HotReloadSummary, TemplateChange, TemplateChangeKind, plan_hot_reload, summarize_hot_reload};
+pub use logging::{LogLevel, LogContext, with_log_context, enable_logging, disable_logging, set_log_level, get_logs, get_logs_json, clear_logs};
+pub use log_sink::{LogSink, LogSinkConfig, SinkConfig, LogFormat, LogRecord};
 pub use metrics::{MetricsSnapshot, METRICS};
 pub use path::{HexPath, index_path_to_hex, hex_to_index_path, HEX_GAP};
+pub use frame_aggregator::{FrameAggregator, PatchSource, aggregate_frame};
+pub use resync::{ResyncMessage, generate_resync, generate_resync_message};
+pub use checksum::{tree_checksum, check_drift};
+pub use capabilities::{ClientCapabilities, ApplicabilityReport, negotiate_patches, applicability_report};
+pub use tree_index::{TreeIndex, NodeLocation};
+pub use progressive::{PatchGroup, split_patches_by_region};
+pub use lazy::{collapse_subtree, expand_lazy};
+pub use document::{DocumentTarget, diff_head};
+pub use custom_patch::{register_custom_patch_kind, unregister_custom_patch_kind, custom_patch_kinds};
+pub use harness::{ExecuteRequest, ExecuteResponse, Harness};
+pub use sim::{Simulator, to_html};
+pub use ssr::render_html;
+pub use routing::{RouteState, ROUTE_STATE_KEY, route_change, with_navigation};
+pub use preservation::{attach_preservation_hints, preservation_hints};
+pub use transaction::{ComponentUpdate, ComponentPatches, TransactionPrepare, TransactionCommit, TransactionRollback, TransactionManager, TRANSACTIONS};
+pub use apply::{apply_patch, apply_patches, apply_and_normalize, normalize_patches};
+pub use annotations::{PatchAnnotation, annotate_patches, set_devtools_enabled, devtools_enabled};
+pub use correlation::{TraceStage, TimingBreakdown, record_span, timing_breakdown};
+pub use rate_limit::{RateLimiter, RateLimitConfig, RateDecision, BreakerEvent, reconcile_rate_limited};
+pub use hint_scheduler::{HintScheduler, HintJob};
+pub use shared_tree::SharedTree;
+pub use concurrent_predictor::ConcurrentPredictor;
+pub use schema::{LearnObservation, BatchResponse, BatchItemResult};
+pub use widget_heuristics::{WidgetHeuristic, register_widget_heuristic, unregister_widget_heuristic, widget_heuristics};
+pub use workload::{WorkloadConfig, WorkloadGenerator, WorkloadKind, WorkloadReport, run_workload};
+pub use tree_json_cache::{TreeJsonCacheStats, tree_json, tree_content_hash, configure_tree_json_cache, tree_json_cache_stats};
+pub use corpus::{CorpusCase, CorpusOutcome, PatchBounds, load_corpus, run_corpus};
@@ -0,0 +1,174 @@
+//! Lifecycle markers (onMount / onUnmount)
+//!
+//! Clients run effects when subtrees appear or disappear (focus an input on mount,
+//! stop a player on unmount). Rather than diffing on its side, the client gets
+//! `Lifecycle` patches next to the Create, Replace and Remove patches that mount
+//! or unmount elements carrying one of the designated props, with the prop's value
+//! as the handler to dispatch:
+//! - Unmount markers go before the patch removing the element, while it's still there
+//! - Mount markers go after the patch creating it
+//!
+//! Within a subtree children come before their parent, as effects run in React.
+//! Markers change nothing in the tree; clients that don't declare support for them
+//! (`ReconcileStrategy::capabilities`) don't get them, and they count against
+//! `max_patches`. The props are process-wide and none are designated by default.
+
+use crate::error::{FfiResult, MinimactError};
+use crate::last_error::FfiCall;
+use crate::tree_index::TreeIndex;
+use crate::vdom::{Patch, VNode};
+use arc_swap::ArcSwap;
+use serde::{Deserialize, Serialize};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::Arc;
+
+/// What happened to the element of a Lifecycle patch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LifecycleEvent {
+    Mount,
+    Unmount,
+}
+
+/// Props whose elements get lifecycle markers
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LifecycleConfig {
+    #[serde(default)]
+    pub mount_props: Vec<String>,
+    #[serde(default)]
+    pub unmount_props: Vec<String>,
+}
+
+impl LifecycleConfig {
+    /// `onMount` and `onUnmount`
+    pub fn standard() -> Self {
+        Self { mount_props: vec!["onMount".to_string()], unmount_props: vec!["onUnmount".to_string()] }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.mount_props.is_empty() || !self.unmount_props.is_empty()
+    }
+
+    /// Markers for `event` of every element in `subtree`, children first
+    pub fn markers(&self, subtree: &VNode, event: LifecycleEvent) -> Vec<Patch> {
+        let props = match event {
+            LifecycleEvent::Mount => &self.mount_props,
+            LifecycleEvent::Unmount => &self.unmount_props,
+        };
+        let mut markers = Vec::new();
+        if !props.is_empty() {
+            collect(subtree, props, event, &mut markers);
+        }
+        markers
+    }
+
+    /// `patches` with markers around the patches that mount or unmount elements
+    /// `old_tree` is the tree the patches apply to
+    pub fn insert_markers(&self, patches: Vec<Patch>, old_tree: &VNode) -> Vec<Patch> {
+        if !self.is_enabled() || !patches.iter().any(Patch::is_structural) {
+            return patches;
+        }
+        let index = TreeIndex::build(old_tree);
+        let mut output = Vec::with_capacity(patches.len());
+        for patch in patches {
+            let removed = match &patch {
+                Patch::Remove { path } | Patch::Replace { path, .. } => index.get(old_tree, path),
+                _ => None,
+            };
+            if let Some(removed) = removed {
+                output.extend(self.markers(removed, LifecycleEvent::Unmount));
+            }
+            let mounted = match &patch {
+                Patch::Create { node, .. } | Patch::Replace { node, .. } => self.markers(node, LifecycleEvent::Mount),
+                _ => Vec::new(),
+            };
+            output.push(patch);
+            output.extend(mounted);
+        }
+        output
+    }
+}
+
+fn collect(node: &VNode, props: &[String], event: LifecycleEvent, markers: &mut Vec<Patch>) {
+    let VNode::Element(el) = node else { return };
+    for child in el.children.iter().flatten() {
+        collect(child, props, event, markers);
+    }
+    for prop in props {
+        if let Some(handler) = el.props.get(prop) {
+            markers.push(Patch::Lifecycle { path: el.path.clone(), event, handler: handler.clone() });
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref LIFECYCLE_CONFIG: ArcSwap<LifecycleConfig> = ArcSwap::from_pointee(LifecycleConfig::default());
+}
+
+/// Replace the lifecycle props used by the reconciler (process-wide)
+pub fn set_lifecycle_config(config: LifecycleConfig) {
+    LIFECYCLE_CONFIG.store(Arc::new(config));
+}
+
+pub fn lifecycle_config() -> Arc<LifecycleConfig> {
+    LIFECYCLE_CONFIG.load_full()
+}
+
+/// Set the lifecycle props (`{"mount_props": ["onMount"], "unmount_props": ["onUnmount"]}`;
+/// `{}` turns markers off)
+///
+/// # Safety
+/// - config_json must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_set_lifecycle_config(config_json: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_set_lifecycle_config", &[config_json]);
+    let config = CStr::from_ptr(config_json)
+        .to_str()
+        .map_err(MinimactError::from)
+        .and_then(|json| Ok(serde_json::from_str::<LifecycleConfig>(json)?));
+    match config {
+        Ok(config) => {
+            set_lifecycle_config(config);
+            FfiResult::success()
+        }
+        Err(e) => FfiResult::error(&e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::HexPath;
+    use crate::vdom::VElement;
+    use std::collections::HashMap;
+
+    fn element(tag: &str, path: HexPath, props: &[(&str, &str)], children: Vec<Option<VNode>>) -> VNode {
+        let props = props.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<HashMap<_, _>>();
+        VNode::Element(VElement { tag: tag.to_string(), props, children, key: None, path, source: None })
+    }
+
+    #[test]
+    fn test_markers_surround_mounts_and_unmounts() {
+        let root = HexPath::root().child(0);
+        let form = |with_input: bool| {
+            let input = element("input", root.child(1), &[("onMount", "focus")], vec![]);
+            let dialog = element("div", root.child(0), &[("onUnmount", "stopTimer"), ("onMount", "startTimer")], vec![]);
+            element("form", root.clone(), &[], vec![Some(dialog.clone()).filter(|_| !with_input), Some(input).filter(|_| with_input)])
+        };
+        let (old, new) = (form(false), form(true));
+        let patches = crate::reconciler::reconcile(&old, &new).unwrap();
+        let config = LifecycleConfig::standard();
+
+        let marked = config.insert_markers(patches.clone(), &old);
+        let kinds: Vec<String> = marked
+            .iter()
+            .map(|p| match p {
+                Patch::Lifecycle { event, handler, .. } => format!("{:?} {}", event, handler),
+                other => other.kind().to_string(),
+            })
+            .collect();
+        assert_eq!(kinds, vec!["Create", "Mount focus", "Unmount stopTimer", "Remove"]);
+
+        assert_eq!(LifecycleConfig::default().insert_markers(patches.clone(), &old), patches);
+    }
+}
@@ -0,0 +1,411 @@
+//! Log sinks: where log entries go besides the in-memory buffer
+//!
+//! Sinks run on a dedicated writer thread fed through a bounded queue, so
+//! `Logger::log` only formats the record and does a non-blocking send. When the
+//! queue is full (a slow disk, a stalled stderr) records are dropped and counted
+//! rather than making the reconcile path wait.
+//!
+//! Configured from JSON (see `minimact_logging_configure`):
+//!
+//! ```json
+//! { "queue_capacity": 4096, "sinks": [
+//!     { "type": "stderr", "format": "text" },
+//!     { "type": "file", "path": "logs/minimact.log", "format": "json_lines",
+//!       "max_bytes": 10485760, "max_files": 5 }
+//! ] }
+//! ```
+
+use crate::logging::{LogContext, LogLevel};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+
+/// Default number of records that can wait for the writer thread
+pub const DEFAULT_QUEUE_CAPACITY: usize = 4096;
+
+/// A log entry as handed to sinks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub level: LogLevel,
+    pub module: String,
+    pub message: String,
+    /// Milliseconds since the logger was created
+    pub elapsed_ms: u128,
+    /// Wall-clock time, milliseconds since the unix epoch
+    pub timestamp_ms: u128,
+    /// `component_id` / `correlation_id` fields, when set
+    #[serde(flatten)]
+    pub context: LogContext,
+}
+
+/// How a sink renders records
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// `<timestamp_ms> <Level> <module> [<component_id>] (<correlation_id>): <message>`
+    #[default]
+    Text,
+    /// One JSON object (a LogRecord) per line
+    JsonLines,
+}
+
+impl LogFormat {
+    fn write_record(self, out: &mut impl Write, record: &LogRecord) -> io::Result<()> {
+        match self {
+            LogFormat::Text => {
+                write!(out, "{} {:?} {}", record.timestamp_ms, record.level, record.module)?;
+                if let Some(component_id) = &record.context.component_id {
+                    write!(out, " [{}]", component_id)?;
+                }
+                if let Some(correlation_id) = &record.context.correlation_id {
+                    write!(out, " ({})", correlation_id)?;
+                }
+                writeln!(out, ": {}", record.message)
+            }
+            LogFormat::JsonLines => {
+                serde_json::to_writer(&mut *out, record)?;
+                out.write_all(b"\n")
+            }
+        }
+    }
+}
+
+/// Sink settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkConfig {
+    Stderr {
+        #[serde(default)]
+        format: LogFormat,
+    },
+    /// Appends to `path`; once it would exceed `max_bytes` it is renamed to
+    /// `path.1` (shifting older files up to `path.<max_files>`) and a new file started
+    File {
+        path: PathBuf,
+        #[serde(default)]
+        format: LogFormat,
+        #[serde(default = "default_max_bytes")]
+        max_bytes: u64,
+        #[serde(default = "default_max_files")]
+        max_files: usize,
+    },
+}
+
+fn default_max_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_max_files() -> usize {
+    5
+}
+
+/// Full sink setup; an empty `sinks` list turns sink output off
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogSinkConfig {
+    pub sinks: Vec<SinkConfig>,
+    pub queue_capacity: usize,
+}
+
+impl Default for LogSinkConfig {
+    fn default() -> Self {
+        Self {
+            sinks: Vec::new(),
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+        }
+    }
+}
+
+/// A destination for log records (runs on the writer thread)
+pub trait LogSink: Send {
+    fn write(&mut self, record: &LogRecord) -> io::Result<()>;
+    fn flush(&mut self) -> io::Result<()>;
+}
+
+/// Writes to the process's stderr
+pub struct StderrSink {
+    format: LogFormat,
+}
+
+impl StderrSink {
+    pub fn new(format: LogFormat) -> Self {
+        Self { format }
+    }
+}
+
+impl LogSink for StderrSink {
+    fn write(&mut self, record: &LogRecord) -> io::Result<()> {
+        self.format.write_record(&mut io::stderr().lock(), record)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+/// Appends to a file, rotating it by size
+pub struct RotatingFileSink {
+    path: PathBuf,
+    format: LogFormat,
+    max_bytes: u64,
+    max_files: usize,
+    file: io::BufWriter<File>,
+    written: u64,
+    line: Vec<u8>,
+}
+
+impl RotatingFileSink {
+    /// Open (or create) `path` for appending
+    pub fn open(path: impl Into<PathBuf>, format: LogFormat, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let file = open_append(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path,
+            format,
+            max_bytes: max_bytes.max(1),
+            max_files,
+            file: io::BufWriter::new(file),
+            written,
+            line: Vec::new(),
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let _ = fs::remove_file(rotated_path(&self.path, self.max_files));
+            for n in (1..self.max_files).rev() {
+                let from = rotated_path(&self.path, n);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, n + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+        self.file = io::BufWriter::new(open_append(&self.path)?);
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl LogSink for RotatingFileSink {
+    fn write(&mut self, record: &LogRecord) -> io::Result<()> {
+        self.line.clear();
+        self.format.write_record(&mut self.line, record)?;
+        // A record larger than max_bytes still gets a file of its own
+        if self.written > 0 && self.written + self.line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(&self.line)?;
+        self.written += self.line.len() as u64;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// Build the sinks described by `config`
+pub fn build_sinks(config: &LogSinkConfig) -> io::Result<Vec<Box<dyn LogSink>>> {
+    config
+        .sinks
+        .iter()
+        .map(|sink| -> io::Result<Box<dyn LogSink>> {
+            Ok(match sink {
+                SinkConfig::Stderr { format } => Box::new(StderrSink::new(*format)),
+                SinkConfig::File { path, format, max_bytes, max_files } => {
+                    Box::new(RotatingFileSink::open(path, *format, *max_bytes, *max_files)?)
+                }
+            })
+        })
+        .collect()
+}
+
+enum Command {
+    Record(LogRecord),
+    Flush(SyncSender<()>),
+}
+
+/// Feeds sinks from a background thread
+pub struct SinkWriter {
+    queue: Option<SyncSender<Command>>,
+    dropped: AtomicU64,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl SinkWriter {
+    /// Start a writer thread for `sinks`
+    pub fn spawn(sinks: Vec<Box<dyn LogSink>>, queue_capacity: usize) -> io::Result<Self> {
+        let (queue, commands) = mpsc::sync_channel(queue_capacity.max(1));
+        let thread = std::thread::Builder::new()
+            .name("minimact-log-writer".to_string())
+            .spawn(move || run_writer(sinks, commands))?;
+        Ok(Self {
+            queue: Some(queue),
+            dropped: AtomicU64::new(0),
+            thread: Mutex::new(Some(thread)),
+        })
+    }
+
+    /// Queue a record without blocking; drops it if the queue is full
+    pub fn send(&self, record: LogRecord) {
+        let Some(queue) = &self.queue else { return };
+        if let Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) = queue.try_send(Command::Record(record)) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Wait until everything queued so far has been written and flushed
+    pub fn flush(&self) {
+        let Some(queue) = &self.queue else { return };
+        let (done, wait) = mpsc::sync_channel(1);
+        if queue.send(Command::Flush(done)).is_ok() {
+            let _ = wait.recv();
+        }
+    }
+
+    /// Records dropped because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for SinkWriter {
+    /// Write out whatever is still queued, then stop the thread
+    fn drop(&mut self) {
+        self.queue.take();
+        if let Some(thread) = self.thread.lock().unwrap_or_else(|p| p.into_inner()).take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn run_writer(mut sinks: Vec<Box<dyn LogSink>>, commands: Receiver<Command>) {
+    // Write errors can't be logged (that would feed back into this queue); a failing
+    // sink just loses the record
+    for command in commands {
+        match command {
+            Command::Record(record) => {
+                for sink in &mut sinks {
+                    let _ = sink.write(&record);
+                }
+            }
+            Command::Flush(done) => {
+                for sink in &mut sinks {
+                    let _ = sink.flush();
+                }
+                let _ = done.send(());
+            }
+        }
+    }
+    for sink in &mut sinks {
+        let _ = sink.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(message: &str) -> LogRecord {
+        LogRecord {
+            level: LogLevel::Info,
+            module: "test".to_string(),
+            message: message.to_string(),
+            elapsed_ms: 1,
+            timestamp_ms: 2,
+            context: LogContext::component("counter"),
+        }
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("minimact-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_file_sink_rotates_by_size() {
+        let dir = scratch_dir("rotate");
+        let path = dir.join("app.log");
+        let mut sink = RotatingFileSink::open(&path, LogFormat::Text, 64, 2).unwrap();
+        for i in 0..10 {
+            sink.write(&record(&format!("message number {}", i))).unwrap();
+        }
+        sink.flush().unwrap();
+
+        assert!(fs::metadata(&path).unwrap().len() <= 64);
+        assert!(rotated_path(&path, 1).exists());
+        assert!(rotated_path(&path, 2).exists());
+        assert!(!rotated_path(&path, 3).exists());
+        assert!(fs::read_to_string(&path).unwrap().contains("message number 9"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_writer_outputs_json_lines() {
+        let dir = scratch_dir("jsonl");
+        let path = dir.join("app.jsonl");
+        let config: LogSinkConfig = serde_json::from_str(&format!(
+            r#"{{"sinks": [{{"type": "file", "path": {:?}, "format": "json_lines"}}]}}"#,
+            path
+        ))
+        .unwrap();
+        let writer = SinkWriter::spawn(build_sinks(&config).unwrap(), config.queue_capacity).unwrap();
+        writer.send(record("first"));
+        writer.send(record("second"));
+        drop(writer);
+
+        let lines: Vec<LogRecord> = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines, vec![record("first"), record("second")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_full_queue_drops_instead_of_blocking() {
+        struct Stuck(Receiver<()>);
+        impl LogSink for Stuck {
+            fn write(&mut self, _: &LogRecord) -> io::Result<()> {
+                let _ = self.0.recv();
+                Ok(())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let (release, stuck) = mpsc::channel();
+        let writer = SinkWriter::spawn(vec![Box::new(Stuck(stuck))], 2).unwrap();
+        for i in 0..10 {
+            writer.send(record(&i.to_string()));
+        }
+        assert!(writer.dropped() >= 7);
+        drop(release);
+    }
+}
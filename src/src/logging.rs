@@ -1,9 +1,13 @@
+use crate::log_sink::{LogRecord, LogSinkConfig, SinkWriter};
+use arc_swap::ArcSwapOption;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::sync::Mutex;
-use std::time::Instant;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 /// Log levels
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[repr(C)]
 pub enum LogLevel {
     Trace = 0,
@@ -20,6 +24,72 @@ pub struct LogEntry {
     pub message: String,
     pub module: &'static str,
     pub timestamp: std::time::Instant,
+    /// Context active on the logging thread
+    pub context: LogContext,
+}
+
+/// Who a log entry is about, attached to every entry logged inside `with_log_context`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogContext {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub component_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+impl LogContext {
+    pub fn component(component_id: &str) -> Self {
+        Self { component_id: Some(component_id.to_string()), correlation_id: None }
+    }
+
+    pub fn correlation(correlation_id: &str) -> Self {
+        Self { component_id: None, correlation_id: Some(correlation_id.to_string()) }
+    }
+
+    /// The context of the current thread
+    pub fn current() -> Self {
+        CONTEXT.with(|context| context.borrow().clone())
+    }
+}
+
+thread_local! {
+    static CONTEXT: RefCell<LogContext> = RefCell::new(LogContext::default());
+}
+
+/// Run `f` with `context` attached to its log entries
+/// Fields left as None keep the value of the enclosing context; the previous
+/// context is restored afterwards (also on panic)
+///
+/// While the global LOGGER is disabled the context is not attached at all:
+/// `LogContext::current()` inside `f` sees the enclosing context, and loggers
+/// other than LOGGER don't get it either.
+pub fn with_log_context<R>(context: LogContext, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<LogContext>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            if let Some(previous) = self.0.take() {
+                CONTEXT.with(|context| *context.borrow_mut() = previous);
+            }
+        }
+    }
+
+    // Nothing would be logged, so skip the bookkeeping
+    if !LOGGER.is_enabled() {
+        return f();
+    }
+
+    let _restore = Restore(Some(CONTEXT.with(|current| {
+        let mut current = current.borrow_mut();
+        let previous = current.clone();
+        if context.component_id.is_some() {
+            current.component_id = context.component_id;
+        }
+        if context.correlation_id.is_some() {
+            current.correlation_id = context.correlation_id;
+        }
+        previous
+    })));
+    f()
 }
 
 /// Global logging state
@@ -29,6 +99,8 @@ pub struct Logger {
     entries: Mutex<Vec<LogEntry>>,
     max_entries: usize,
     start_time: Instant,
+    /// Background writer for the configured sinks, if any
+    sinks: ArcSwapOption<SinkWriter>,
 }
 
 lazy_static::lazy_static! {
@@ -42,7 +114,8 @@ impl Logger {
             min_level: AtomicUsize::new(LogLevel::Info as usize),
             entries: Mutex::new(Vec::new()),
             max_entries: 10_000,
-            start_time: Instant::now(),
+            start_time: crate::clock::now(),
+            sinks: ArcSwapOption::empty(),
         }
     }
 
@@ -88,11 +161,25 @@ impl Logger {
             return;
         }
 
+        let timestamp = crate::clock::now();
+        let context = LogContext::current();
+        if let Some(sinks) = self.sinks.load().as_ref() {
+            sinks.send(LogRecord {
+                level,
+                module: module.to_string(),
+                message: message.clone(),
+                elapsed_ms: timestamp.duration_since(self.start_time).as_millis(),
+                timestamp_ms: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis()),
+                context: context.clone(),
+            });
+        }
+
         let entry = LogEntry {
             level,
             message,
             module,
-            timestamp: Instant::now(),
+            timestamp,
+            context,
         };
 
         let mut entries = self.entries.lock().unwrap();
@@ -115,17 +202,50 @@ impl Logger {
         self.entries.lock().unwrap().clear();
     }
 
+    /// Replace the sinks entries are written to (in addition to the in-memory buffer)
+    /// Records queued for the previous sinks are written out before they close
+    pub fn configure_sinks(&self, config: &LogSinkConfig) -> std::io::Result<()> {
+        let writer = if config.sinks.is_empty() {
+            None
+        } else {
+            let sinks = crate::log_sink::build_sinks(config)?;
+            Some(Arc::new(SinkWriter::spawn(sinks, config.queue_capacity)?))
+        };
+        self.sinks.store(writer);
+        Ok(())
+    }
+
+    /// Wait until the sinks have written and flushed every entry logged so far
+    pub fn flush_sinks(&self) {
+        if let Some(sinks) = self.sinks.load_full() {
+            sinks.flush();
+        }
+    }
+
+    /// Entries the sinks dropped because their queue was full
+    pub fn dropped_sink_records(&self) -> u64 {
+        self.sinks.load().as_ref().map_or(0, |sinks| sinks.dropped())
+    }
+
     /// Get log entries as JSON
     pub fn entries_json(&self) -> String {
+        self.entries_json_for(None)
+    }
+
+    /// Get log entries as JSON, only those logged for `component_id` if given
+    pub fn entries_json_for(&self, component_id: Option<&str>) -> String {
         let entries = self.entries.lock().unwrap();
         let formatted: Vec<serde_json::Value> = entries
             .iter()
+            .filter(|e| component_id.is_none() || e.context.component_id.as_deref() == component_id)
             .map(|e| {
                 serde_json::json!({
                     "level": format!("{:?}", e.level),
                     "module": e.module,
                     "message": &e.message,
                     "elapsed_ms": e.timestamp.duration_since(self.start_time).as_millis(),
+                    "component_id": &e.context.component_id,
+                    "correlation_id": &e.context.correlation_id,
                 })
             })
             .collect();
@@ -247,11 +367,62 @@ pub unsafe extern "C" fn minimact_logging_get_logs() -> *mut std::os::raw::c_cha
     CString::new(json).unwrap().into_raw()
 }
 
+/// Get the log entries logged for one component as JSON
+///
+/// # Safety
+/// - component_id must be a valid null-terminated UTF-8 string
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_logging_get_component_logs(
+    component_id: *const std::os::raw::c_char,
+) -> *mut std::os::raw::c_char {
+    use std::ffi::{CStr, CString};
+
+    let json = match CStr::from_ptr(component_id).to_str() {
+        Ok(component_id) => LOGGER.entries_json_for(Some(component_id)),
+        Err(_) => "[]".to_string(),
+    };
+    CString::new(json).unwrap().into_raw()
+}
+
 #[no_mangle]
 pub extern "C" fn minimact_logging_clear() {
     clear_logs();
 }
 
+/// Configure log sinks (stderr, rotating files; text or JSON lines)
+/// An empty sink list turns sink output off
+///
+/// # Safety
+/// - config_json must be a valid null-terminated UTF-8 string (a LogSinkConfig)
+#[no_mangle]
+pub unsafe extern "C" fn minimact_logging_configure(config_json: *const std::os::raw::c_char) -> crate::error::FfiResult {
+    use crate::error::FfiResult;
+    use std::ffi::CStr;
+
+    let _call = crate::last_error::FfiCall::enter("minimact_logging_configure", &[config_json]);
+    let config_str = match CStr::from_ptr(config_json).to_str() {
+        Ok(s) => s,
+        Err(_) => return FfiResult::error_str("Invalid UTF-8 in logging config"),
+    };
+
+    let config = match serde_json::from_str::<LogSinkConfig>(config_str) {
+        Ok(config) => config,
+        Err(e) => return FfiResult::error_str(&format!("Failed to parse logging config: {}", e)),
+    };
+
+    match LOGGER.configure_sinks(&config) {
+        Ok(()) => FfiResult::success(),
+        Err(e) => FfiResult::error_str(&format!("Failed to open log sinks: {}", e)),
+    }
+}
+
+/// Block until queued log entries have been written by the sinks
+#[no_mangle]
+pub extern "C" fn minimact_logging_flush() {
+    LOGGER.flush_sinks();
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -300,4 +471,66 @@ mod tests {
         // Should have kept the newest ones
         assert!(entries.last().unwrap().message.contains(&format!("{}", logger.max_entries + 99)));
     }
+
+    #[test]
+    fn test_context_is_attached_and_restored() {
+        // LOGGER is process-wide: put its state back even if an assert fails
+        struct Restore(bool);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                if self.0 { LOGGER.enable() } else { LOGGER.disable() }
+            }
+        }
+        let _restore = Restore(LOGGER.is_enabled());
+        LOGGER.enable();
+        let logger = Logger::new();
+        logger.enable();
+
+        with_log_context(LogContext::correlation("req-1"), || {
+            with_log_context(LogContext::component("counter"), || {
+                logger.log(LogLevel::Info, "test", "inner".to_string());
+            });
+            logger.log(LogLevel::Info, "test", "outer".to_string());
+        });
+        logger.log(LogLevel::Info, "test", "none".to_string());
+
+        let entries = logger.entries();
+        assert_eq!(entries[0].context.component_id.as_deref(), Some("counter"));
+        assert_eq!(entries[0].context.correlation_id.as_deref(), Some("req-1"));
+        assert_eq!(entries[1].context, LogContext::correlation("req-1"));
+        assert_eq!(entries[2].context, LogContext::default());
+
+        let filtered: Vec<serde_json::Value> = serde_json::from_str(&logger.entries_json_for(Some("counter"))).unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0]["message"], "inner");
+        assert_eq!(filtered[0]["correlation_id"], "req-1");
+    }
+
+    #[test]
+    fn test_entries_reach_configured_sinks() {
+        let path = std::env::temp_dir().join(format!("minimact-logger-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let config = LogSinkConfig {
+            sinks: vec![crate::log_sink::SinkConfig::File {
+                path: path.clone(),
+                format: crate::log_sink::LogFormat::Text,
+                max_bytes: 1 << 20,
+                max_files: 1,
+            }],
+            ..LogSinkConfig::default()
+        };
+
+        let logger = Logger::new();
+        logger.enable();
+        logger.configure_sinks(&config).unwrap();
+        logger.log(LogLevel::Warn, "reconciler", "to the file".to_string());
+        logger.flush_sinks();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.contains("Warn reconciler: to the file"));
+        assert_eq!(logger.dropped_sink_records(), 0);
+
+        logger.configure_sinks(&LogSinkConfig::default()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
 }
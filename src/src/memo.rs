@@ -0,0 +1,178 @@
+//! Memoized pure components
+//!
+//! The server-side `React.memo`: a component marked pure renders the same tree for
+//! the same props, so when its props hash matches the one of its stored tree there
+//! is nothing to diff and the reconcile returns no patches without touching the
+//! tree (hosts can ask `should_skip` first and not render at all).
+//!
+//! Hashes are the host's (any u64 over the props; `props_hash` is one over their
+//! JSON). A hash only counts for the tree version it was stored with, so a tree
+//! replaced or evicted by other means is diffed again. Skips are counted in the
+//! metrics (`memo_skips`).
+
+use crate::checksum::Fnv64;
+use crate::error::{FfiResult, MinimactError, Result};
+use crate::last_error::FfiCall;
+use crate::tree_store::{json_or_error, StoredReconcile, TreeStore, TREE_STORE};
+use crate::vdom::VNode;
+use dashmap::DashMap;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+/// Pure components and the props hash of their stored tree
+#[derive(Debug, Default)]
+pub struct MemoRegistry {
+    /// component -> (props hash, tree version) of its last reconcile, if any
+    pure: DashMap<String, Option<(u64, u64)>>,
+}
+
+/// Hash of props given as JSON (object keys in any order)
+pub fn props_hash(props: &serde_json::Value) -> u64 {
+    let mut hasher = Fnv64::new();
+    // serde_json objects are key-ordered maps, so equal props serialize the same
+    hasher.write(props.to_string().as_bytes());
+    hasher.finish()
+}
+
+impl MemoRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark a component pure (or not); unmarking forgets its hash
+    pub fn set_pure(&self, component_id: &str, pure: bool) {
+        if pure {
+            self.pure.entry(component_id.to_string()).or_insert(None);
+        } else {
+            self.pure.remove(component_id);
+        }
+    }
+
+    pub fn is_pure(&self, component_id: &str) -> bool {
+        self.pure.contains_key(component_id)
+    }
+
+    /// Check if reconciling the component with these props would be skipped
+    pub fn should_skip(&self, store: &TreeStore, component_id: &str, props_hash: u64) -> bool {
+        let Some(entry) = self.pure.get(component_id) else { return false };
+        matches!(*entry, Some((hash, version)) if hash == props_hash && version == store.version(component_id) && version != 0)
+    }
+
+    /// Reconcile a component against its stored tree unless it's pure and its props
+    /// didn't change; `render` is only called when the reconcile isn't skipped
+    pub fn reconcile(
+        &self,
+        store: &TreeStore,
+        component_id: &str,
+        props_hash: u64,
+        render: impl FnOnce() -> Result<VNode>,
+    ) -> Result<StoredReconcile> {
+        if self.should_skip(store, component_id, props_hash) {
+            crate::metrics::METRICS.record_memo_skip();
+            let version = store.version(component_id);
+            return Ok(StoredReconcile { previous_version: version, version, patches: Vec::new() });
+        }
+        let result = store.reconcile_against_stored(component_id, render()?, None)?;
+        if let Some(mut entry) = self.pure.get_mut(component_id) {
+            *entry = Some((props_hash, result.version));
+        }
+        Ok(result)
+    }
+}
+
+lazy_static::lazy_static! {
+    /// Pure components of TREE_STORE, used by the FFI
+    pub static ref MEMO: MemoRegistry = MemoRegistry::new();
+}
+
+/// Mark a component of the tree store pure (or not)
+///
+/// # Safety
+/// - component_id must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_set_pure(component_id: *const c_char, pure: bool) -> FfiResult {
+    let _call = FfiCall::enter("minimact_set_pure", &[component_id]);
+    match CStr::from_ptr(component_id).to_str() {
+        Ok(component_id) => {
+            MEMO.set_pure(component_id, pure);
+            FfiResult::success()
+        }
+        Err(e) => FfiResult::error(&MinimactError::from(e)),
+    }
+}
+
+/// Check if a memoized reconcile with these props would be skipped (so the host
+/// needn't render)
+///
+/// # Safety
+/// - component_id must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_memo_should_skip(component_id: *const c_char, props_hash: u64) -> bool {
+    let _call = FfiCall::enter("minimact_memo_should_skip", &[component_id]);
+    CStr::from_ptr(component_id)
+        .to_str()
+        .is_ok_and(|component_id| MEMO.should_skip(&TREE_STORE, component_id, props_hash))
+}
+
+/// Reconcile a component against its stored tree, skipped for pure components
+/// whose props hash is unchanged
+/// Returns StoredReconcile JSON (no patches when skipped) or {"error": ...}
+///
+/// # Safety
+/// - component_id must be a valid null-terminated UTF-8 string
+/// - new_tree_json must be a valid null-terminated UTF-8 string, or null when the
+///   host knows the reconcile is skipped (see minimact_memo_should_skip)
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_reconcile_memoized(
+    component_id: *const c_char,
+    props_hash: u64,
+    new_tree_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_reconcile_memoized", &[component_id, new_tree_json]);
+    json_or_error((|| -> Result<StoredReconcile> {
+        let component_id = CStr::from_ptr(component_id).to_str()?;
+        MEMO.reconcile(&TREE_STORE, component_id, props_hash, || {
+            if new_tree_json.is_null() {
+                return Err(MinimactError::NullPointer("new_tree_json"));
+            }
+            crate::validation::deserialize_vnode_safe(
+                CStr::from_ptr(new_tree_json).to_str()?,
+                &crate::validation::ValidationConfig::default(),
+            )
+        })
+    })())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::HexPath;
+
+    fn text(content: &str) -> VNode {
+        VNode::Text(crate::vdom::VText { content: content.to_string(), path: HexPath::from("10000000") })
+    }
+
+    #[test]
+    fn test_pure_component_skips_unchanged_props() {
+        let (memo, store) = (MemoRegistry::new(), TreeStore::default());
+        let hash = props_hash(&serde_json::json!({ "b": 2, "a": 1 }));
+        assert_eq!(hash, props_hash(&serde_json::json!({ "a": 1, "b": 2 })));
+        memo.set_pure("Badge", true);
+
+        let first = memo.reconcile(&store, "Badge", hash, || Ok(text("1"))).unwrap();
+        assert_eq!(first.patches.len(), 1);
+        let skipped = memo.reconcile(&store, "Badge", hash, || panic!("rendered a memoized component")).unwrap();
+        assert!(skipped.patches.is_empty());
+        assert_eq!(skipped.version, first.version);
+
+        // New props, or a tree stored by other means, diff again
+        let changed = memo.reconcile(&store, "Badge", hash + 1, || Ok(text("2"))).unwrap();
+        assert_eq!(changed.patches.len(), 1);
+        store.set_tree("Badge", text("3"));
+        assert!(!memo.should_skip(&store, "Badge", hash + 1));
+
+        memo.set_pure("Badge", false);
+        assert!(!memo.is_pure("Badge"));
+    }
+}
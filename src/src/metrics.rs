@@ -1,8 +1,74 @@
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
-use std::sync::Mutex;
 use std::time::{Duration, Instant};
 
+/// Shards for recent timing samples; threads are spread across them round-robin
+pub(crate) const TIMING_SHARDS: usize = 8;
+
+/// Samples kept per shard (TIMING_SHARDS * this = recent samples kept overall)
+const SAMPLES_PER_SHARD: usize = 128;
+
+/// Recent timing samples, recorded wait-free from any thread
+///
+/// Each shard is a ring of atomic slots with its own cursor, so concurrent
+/// recorders mostly touch different cache lines and never wait on each other.
+/// Readers merge all shards; a slot being overwritten during a read yields either
+/// the old or the new sample.
+struct TimingSamples {
+    shards: Box<[TimingShard]>,
+}
+
+#[repr(align(64))]
+struct TimingShard {
+    cursor: AtomicUsize,
+    slots: Box<[AtomicU64]>,
+}
+
+static NEXT_TIMING_SHARD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static TIMING_SHARD: usize = NEXT_TIMING_SHARD.fetch_add(1, Ordering::Relaxed) % TIMING_SHARDS;
+}
+
+/// This thread's shard (below TIMING_SHARDS)
+pub(crate) fn thread_shard() -> usize {
+    TIMING_SHARD.with(|shard| *shard)
+}
+
+impl TimingSamples {
+    fn new() -> Self {
+        let shards = (0..TIMING_SHARDS)
+            .map(|_| TimingShard {
+                cursor: AtomicUsize::new(0),
+                slots: (0..SAMPLES_PER_SHARD).map(|_| AtomicU64::new(0)).collect(),
+            })
+            .collect();
+        Self { shards }
+    }
+
+    fn record(&self, micros: u64) {
+        let shard = &self.shards[thread_shard()];
+        let i = shard.cursor.fetch_add(1, Ordering::Relaxed);
+        shard.slots[i % SAMPLES_PER_SHARD].store(micros, Ordering::Relaxed);
+    }
+
+    /// All retained samples (in no particular order)
+    fn collect(&self) -> Vec<u64> {
+        let mut samples = Vec::with_capacity(TIMING_SHARDS * SAMPLES_PER_SHARD);
+        for shard in self.shards.iter() {
+            let filled = shard.cursor.load(Ordering::Relaxed).min(SAMPLES_PER_SHARD);
+            samples.extend(shard.slots[..filled].iter().map(|slot| slot.load(Ordering::Relaxed)));
+        }
+        samples
+    }
+
+    fn clear(&self) {
+        for shard in self.shards.iter() {
+            shard.cursor.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
 /// Global metrics collector
 pub struct Metrics {
     // Reconciliation metrics
@@ -18,30 +84,80 @@ pub struct Metrics {
     pub predictor_prediction_hits: AtomicU64,
     pub predictor_prediction_misses: AtomicU64,
     pub predictor_total_time_us: AtomicU64,
+    /// Identical transitions dropped inside the duplicate window
+    pub duplicate_learns_suppressed: AtomicU64,
+    pub duplicate_predictions_suppressed: AtomicU64,
 
     // Memory metrics
     pub current_predictors: AtomicUsize,
     pub max_predictors: AtomicUsize,
     pub evictions_performed: AtomicU64,
+    pub stale_handle_uses: AtomicU64,
+
+    // Session metrics
+    pub current_sessions: AtomicUsize,
+    pub sessions_expired: AtomicU64,
 
     // Validation metrics
     pub validation_failures: AtomicU64,
     pub patches_validated: AtomicU64,
     pub patch_validation_failures: AtomicU64,
+    pub validation_time_us: AtomicU64,
+
+    // Memo metrics
+    /// Reconciles of pure components skipped for unchanged props (see `memo`)
+    pub memo_skips: AtomicU64,
+
+    // Strict mode metrics
+    /// Operations whose two runs disagreed (see `strict`)
+    pub strict_divergences: AtomicU64,
+
+    // Anti-amplification metrics
+    pub patch_limit_exceeded: AtomicU64,
+    pub patches_collapsed_by_limit: AtomicU64,
+    pub patches_filtered: AtomicU64,
+
+    // Rate limiting metrics
+    pub breakers_tripped: AtomicU64,
+    pub breakers_open: AtomicUsize,
+    pub updates_suppressed: AtomicU64,
+
+    // Hint precompute metrics
+    pub hints_scheduled: AtomicU64,
+    pub hints_computed: AtomicU64,
+    pub hints_cancelled: AtomicU64,
+    pub hints_used: AtomicU64,
+    pub hint_precompute_time_us: AtomicU64,
+
+    // Delivery metrics (see `html_delivery`)
+    pub deliveries_as_patches: AtomicU64,
+    pub deliveries_as_html: AtomicU64,
+
+    // End-to-end latency metrics
+    /// Apply reports received from clients (see `latency`)
+    pub latency_reports: AtomicU64,
+
+    // Failed FFI calls, by entry point (errors are rare, so a map is fine here)
+    ffi_errors: dashmap::DashMap<&'static str, u64>,
 
     // Performance tracking
     start_time: Instant,
 
-    // Recent operation timings (circular buffer)
-    recent_reconcile_times: Mutex<Vec<u64>>,
-    recent_prediction_times: Mutex<Vec<u64>>,
-    max_recent_samples: usize,
+    // Recent operation timings
+    recent_reconcile_times: TimingSamples,
+    recent_prediction_times: TimingSamples,
+    recent_end_to_end_times: TimingSamples,
 }
 
 lazy_static::lazy_static! {
     pub static ref METRICS: Metrics = Metrics::new();
 }
 
+/// Check if work on this thread counts (strict mode's second runs don't)
+fn counting() -> bool {
+    !crate::strict::is_rerunning()
+}
+
 impl Metrics {
     fn new() -> Self {
         Self {
@@ -56,24 +172,57 @@ impl Metrics {
             predictor_prediction_hits: AtomicU64::new(0),
             predictor_prediction_misses: AtomicU64::new(0),
             predictor_total_time_us: AtomicU64::new(0),
+            duplicate_learns_suppressed: AtomicU64::new(0),
+            duplicate_predictions_suppressed: AtomicU64::new(0),
 
             current_predictors: AtomicUsize::new(0),
             max_predictors: AtomicUsize::new(0),
             evictions_performed: AtomicU64::new(0),
+            stale_handle_uses: AtomicU64::new(0),
+
+            current_sessions: AtomicUsize::new(0),
+            sessions_expired: AtomicU64::new(0),
 
             validation_failures: AtomicU64::new(0),
             patches_validated: AtomicU64::new(0),
             patch_validation_failures: AtomicU64::new(0),
+            validation_time_us: AtomicU64::new(0),
+            memo_skips: AtomicU64::new(0),
+            strict_divergences: AtomicU64::new(0),
+
+            patch_limit_exceeded: AtomicU64::new(0),
+            patches_collapsed_by_limit: AtomicU64::new(0),
+            patches_filtered: AtomicU64::new(0),
+
+            breakers_tripped: AtomicU64::new(0),
+            breakers_open: AtomicUsize::new(0),
+            updates_suppressed: AtomicU64::new(0),
+
+            hints_scheduled: AtomicU64::new(0),
+            hints_computed: AtomicU64::new(0),
+            hints_cancelled: AtomicU64::new(0),
+            hints_used: AtomicU64::new(0),
+            hint_precompute_time_us: AtomicU64::new(0),
+
+            deliveries_as_patches: AtomicU64::new(0),
+            deliveries_as_html: AtomicU64::new(0),
 
-            start_time: Instant::now(),
+            latency_reports: AtomicU64::new(0),
 
-            recent_reconcile_times: Mutex::new(Vec::new()),
-            recent_prediction_times: Mutex::new(Vec::new()),
-            max_recent_samples: 1000,
+            ffi_errors: dashmap::DashMap::new(),
+
+            start_time: crate::clock::now(),
+
+            recent_reconcile_times: TimingSamples::new(),
+            recent_prediction_times: TimingSamples::new(),
+            recent_end_to_end_times: TimingSamples::new(),
         }
     }
 
     pub fn record_reconcile(&self, duration: Duration, patch_count: usize, error: bool) {
+        if !counting() {
+            return;
+        }
         self.reconcile_calls.fetch_add(1, Ordering::Relaxed);
 
         if error {
@@ -84,16 +233,15 @@ impl Metrics {
 
         let micros = duration.as_micros() as u64;
         self.reconcile_total_time_us.fetch_add(micros, Ordering::Relaxed);
+        crate::metrics_history::HISTORY.record_reconcile(micros, error);
 
-        // Store recent timing
-        let mut times = self.recent_reconcile_times.lock().unwrap();
-        if times.len() >= self.max_recent_samples {
-            times.remove(0);
-        }
-        times.push(micros);
+        self.recent_reconcile_times.record(micros);
     }
 
     pub fn record_prediction(&self, duration: Duration, hit: bool) {
+        if !counting() {
+            return;
+        }
         self.predictor_predictions.fetch_add(1, Ordering::Relaxed);
 
         if hit {
@@ -104,22 +252,30 @@ impl Metrics {
 
         let micros = duration.as_micros() as u64;
         self.predictor_total_time_us.fetch_add(micros, Ordering::Relaxed);
+        crate::metrics_history::HISTORY.record_prediction(micros, hit);
 
-        // Store recent timing
-        let mut times = self.recent_prediction_times.lock().unwrap();
-        if times.len() >= self.max_recent_samples {
-            times.remove(0);
-        }
-        times.push(micros);
+        self.recent_prediction_times.record(micros);
     }
 
     pub fn record_learn(&self, error: bool) {
+        if !counting() {
+            return;
+        }
         self.predictor_learns.fetch_add(1, Ordering::Relaxed);
         if error {
             self.predictor_learn_errors.fetch_add(1, Ordering::Relaxed);
         }
     }
 
+    /// A repeat of a just-seen transition was dropped (`learn`: by learn(), else by predict())
+    pub fn record_duplicate_suppressed(&self, learn: bool) {
+        if !counting() {
+            return;
+        }
+        let counter = if learn { &self.duplicate_learns_suppressed } else { &self.duplicate_predictions_suppressed };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
     pub fn record_predictor_created(&self) {
         let current = self.current_predictors.fetch_add(1, Ordering::Relaxed) + 1;
 
@@ -142,14 +298,44 @@ impl Metrics {
         self.current_predictors.fetch_sub(1, Ordering::Relaxed);
     }
 
+    pub fn record_session_created(&self) {
+        self.current_sessions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// `expired`: dropped for idleness or the session limit rather than destroyed by the host
+    pub fn record_session_destroyed(&self, expired: bool) {
+        self.current_sessions.fetch_sub(1, Ordering::Relaxed);
+        if expired {
+            self.sessions_expired.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
     pub fn record_eviction(&self) {
+        if !counting() {
+            return;
+        }
         self.evictions_performed.fetch_add(1, Ordering::Relaxed);
     }
 
+    pub fn record_stale_handle_use(&self) {
+        self.stale_handle_uses.fetch_add(1, Ordering::Relaxed);
+    }
+
     pub fn record_validation_failure(&self) {
+        if !counting() {
+            return;
+        }
         self.validation_failures.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Time spent validating trees during a reconcile (also part of its reconcile time)
+    pub fn record_validation_time(&self, duration: Duration) {
+        if !counting() {
+            return;
+        }
+        self.validation_time_us.fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
     pub fn record_patch_validation(&self, success: bool) {
         self.patches_validated.fetch_add(1, Ordering::Relaxed);
         if !success {
@@ -157,10 +343,81 @@ impl Metrics {
         }
     }
 
+    /// A diff exceeded max_patches; `collapsed` patches were folded into Replaces
+    pub fn record_patch_limit_exceeded(&self, collapsed: usize) {
+        if !counting() {
+            return;
+        }
+        self.patch_limit_exceeded.fetch_add(1, Ordering::Relaxed);
+        self.patches_collapsed_by_limit.fetch_add(collapsed as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_patches_filtered(&self, count: usize) {
+        self.patches_filtered.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_breaker_tripped(&self) {
+        self.breakers_tripped.fetch_add(1, Ordering::Relaxed);
+        self.breakers_open.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_breaker_reset(&self) {
+        self.breakers_open.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn record_update_suppressed(&self) {
+        self.updates_suppressed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_hint_scheduled(&self) {
+        self.hints_scheduled.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_hint_computed(&self, duration: Duration) {
+        self.hints_computed.fetch_add(1, Ordering::Relaxed);
+        self.hint_precompute_time_us.fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_hint_cancelled(&self) {
+        self.hints_cancelled.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_ffi_error(&self, entry_point: &'static str) {
+        *self.ffi_errors.entry(entry_point).or_insert(0) += 1;
+    }
+
+    pub fn record_hint_used(&self) {
+        self.hints_used.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_memo_skip(&self) {
+        self.memo_skips.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_strict_divergence(&self) {
+        self.strict_divergences.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Which way an update was delivered
+    pub fn record_delivery(&self, html: bool) {
+        if html {
+            self.deliveries_as_html.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.deliveries_as_patches.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// State change to patches applied on the client, in server time
+    pub fn record_end_to_end_latency(&self, latency: Duration) {
+        self.latency_reports.fetch_add(1, Ordering::Relaxed);
+        self.recent_end_to_end_times.record(latency.as_micros() as u64);
+    }
+
     /// Get snapshot of all metrics
     pub fn snapshot(&self) -> MetricsSnapshot {
-        let reconcile_times = self.recent_reconcile_times.lock().unwrap();
-        let prediction_times = self.recent_prediction_times.lock().unwrap();
+        let reconcile_times = self.recent_reconcile_times.collect();
+        let prediction_times = self.recent_prediction_times.collect();
+        let end_to_end_times = self.recent_end_to_end_times.collect();
 
         let avg_reconcile_us = if !reconcile_times.is_empty() {
             reconcile_times.iter().sum::<u64>() / reconcile_times.len() as u64
@@ -177,6 +434,12 @@ impl Metrics {
         let p95_reconcile_us = percentile(&reconcile_times, 0.95);
         let p95_prediction_us = percentile(&prediction_times, 0.95);
 
+        let avg_end_to_end_us = if !end_to_end_times.is_empty() {
+            end_to_end_times.iter().sum::<u64>() / end_to_end_times.len() as u64
+        } else {
+            0
+        };
+
         let total_predictions = self.predictor_predictions.load(Ordering::Relaxed);
         let hit_rate = if total_predictions > 0 {
             self.predictor_prediction_hits.load(Ordering::Relaxed) as f64 / total_predictions as f64
@@ -184,8 +447,15 @@ impl Metrics {
             0.0
         };
 
+        let hints_computed = self.hints_computed.load(Ordering::Relaxed);
+        let hint_utilization = if hints_computed > 0 {
+            self.hints_used.load(Ordering::Relaxed) as f64 / hints_computed as f64
+        } else {
+            0.0
+        };
+
         MetricsSnapshot {
-            uptime_secs: self.start_time.elapsed().as_secs(),
+            uptime_secs: crate::clock::now().saturating_duration_since(self.start_time).as_secs(),
 
             reconcile_calls: self.reconcile_calls.load(Ordering::Relaxed),
             reconcile_errors: self.reconcile_errors.load(Ordering::Relaxed),
@@ -201,14 +471,49 @@ impl Metrics {
             prediction_hit_rate: hit_rate,
             avg_prediction_time_us: avg_prediction_us,
             p95_prediction_time_us: p95_prediction_us,
+            duplicate_learns_suppressed: self.duplicate_learns_suppressed.load(Ordering::Relaxed),
+            duplicate_predictions_suppressed: self.duplicate_predictions_suppressed.load(Ordering::Relaxed),
 
             current_predictors: self.current_predictors.load(Ordering::Relaxed),
             max_predictors: self.max_predictors.load(Ordering::Relaxed),
             evictions_performed: self.evictions_performed.load(Ordering::Relaxed),
+            stale_handle_uses: self.stale_handle_uses.load(Ordering::Relaxed),
+
+            current_sessions: self.current_sessions.load(Ordering::Relaxed),
+            sessions_expired: self.sessions_expired.load(Ordering::Relaxed),
 
             validation_failures: self.validation_failures.load(Ordering::Relaxed),
             patches_validated: self.patches_validated.load(Ordering::Relaxed),
             patch_validation_failures: self.patch_validation_failures.load(Ordering::Relaxed),
+            validation_time_us: self.validation_time_us.load(Ordering::Relaxed),
+            memo_skips: self.memo_skips.load(Ordering::Relaxed),
+            strict_divergences: self.strict_divergences.load(Ordering::Relaxed),
+
+            patch_limit_exceeded: self.patch_limit_exceeded.load(Ordering::Relaxed),
+            patches_collapsed_by_limit: self.patches_collapsed_by_limit.load(Ordering::Relaxed),
+            patches_filtered: self.patches_filtered.load(Ordering::Relaxed),
+
+            breakers_tripped: self.breakers_tripped.load(Ordering::Relaxed),
+            breakers_open: self.breakers_open.load(Ordering::Relaxed),
+            updates_suppressed: self.updates_suppressed.load(Ordering::Relaxed),
+
+            hints_scheduled: self.hints_scheduled.load(Ordering::Relaxed),
+            hints_computed,
+            hints_cancelled: self.hints_cancelled.load(Ordering::Relaxed),
+            hints_used: self.hints_used.load(Ordering::Relaxed),
+            hint_precompute_time_us: self.hint_precompute_time_us.load(Ordering::Relaxed),
+            hint_utilization,
+
+            deliveries_as_patches: self.deliveries_as_patches.load(Ordering::Relaxed),
+            deliveries_as_html: self.deliveries_as_html.load(Ordering::Relaxed),
+
+            latency_reports: self.latency_reports.load(Ordering::Relaxed),
+            avg_end_to_end_latency_us: avg_end_to_end_us,
+            p95_end_to_end_latency_us: percentile(&end_to_end_times, 0.95),
+
+            ffi_errors: self.ffi_errors.iter().map(|e| (e.key().to_string(), *e.value())).collect(),
+
+            flags: crate::flags::flag_states(),
         }
     }
 
@@ -225,20 +530,94 @@ impl Metrics {
         self.predictor_prediction_hits.store(0, Ordering::Relaxed);
         self.predictor_prediction_misses.store(0, Ordering::Relaxed);
         self.predictor_total_time_us.store(0, Ordering::Relaxed);
+        self.duplicate_learns_suppressed.store(0, Ordering::Relaxed);
+        self.duplicate_predictions_suppressed.store(0, Ordering::Relaxed);
 
         self.evictions_performed.store(0, Ordering::Relaxed);
+        self.stale_handle_uses.store(0, Ordering::Relaxed);
+        self.sessions_expired.store(0, Ordering::Relaxed);
 
         self.validation_failures.store(0, Ordering::Relaxed);
         self.patches_validated.store(0, Ordering::Relaxed);
         self.patch_validation_failures.store(0, Ordering::Relaxed);
+        self.validation_time_us.store(0, Ordering::Relaxed);
+        self.memo_skips.store(0, Ordering::Relaxed);
+        self.strict_divergences.store(0, Ordering::Relaxed);
 
-        self.recent_reconcile_times.lock().unwrap().clear();
-        self.recent_prediction_times.lock().unwrap().clear();
+        self.patch_limit_exceeded.store(0, Ordering::Relaxed);
+        self.patches_collapsed_by_limit.store(0, Ordering::Relaxed);
+        self.patches_filtered.store(0, Ordering::Relaxed);
+
+        self.breakers_tripped.store(0, Ordering::Relaxed);
+        self.updates_suppressed.store(0, Ordering::Relaxed);
+
+        self.hints_scheduled.store(0, Ordering::Relaxed);
+        self.hints_computed.store(0, Ordering::Relaxed);
+        self.hints_cancelled.store(0, Ordering::Relaxed);
+        self.hints_used.store(0, Ordering::Relaxed);
+        self.hint_precompute_time_us.store(0, Ordering::Relaxed);
+
+        self.deliveries_as_patches.store(0, Ordering::Relaxed);
+        self.deliveries_as_html.store(0, Ordering::Relaxed);
+
+        self.latency_reports.store(0, Ordering::Relaxed);
+
+        self.ffi_errors.clear();
+
+        self.recent_reconcile_times.clear();
+        self.recent_prediction_times.clear();
+        self.recent_end_to_end_times.clear();
+    }
+
+    /// Continue counting from an earlier process's snapshot (engine restore)
+    /// Gauges (current predictors, sessions, open breakers) and timing samples
+    /// describe the old process, so they're left alone
+    pub fn restore_counters(&self, snapshot: &MetricsSnapshot) {
+        self.reconcile_calls.store(snapshot.reconcile_calls, Ordering::Relaxed);
+        self.reconcile_errors.store(snapshot.reconcile_errors, Ordering::Relaxed);
+        self.total_patches_generated.store(snapshot.total_patches_generated, Ordering::Relaxed);
+
+        self.predictor_learns.store(snapshot.predictor_learns, Ordering::Relaxed);
+        self.predictor_learn_errors.store(snapshot.predictor_learn_errors, Ordering::Relaxed);
+        self.predictor_predictions.store(snapshot.predictor_predictions, Ordering::Relaxed);
+        self.predictor_prediction_hits.store(snapshot.predictor_prediction_hits, Ordering::Relaxed);
+        self.predictor_prediction_misses.store(snapshot.predictor_prediction_misses, Ordering::Relaxed);
+        self.duplicate_learns_suppressed.store(snapshot.duplicate_learns_suppressed, Ordering::Relaxed);
+        self.duplicate_predictions_suppressed.store(snapshot.duplicate_predictions_suppressed, Ordering::Relaxed);
+
+        self.evictions_performed.store(snapshot.evictions_performed, Ordering::Relaxed);
+        self.stale_handle_uses.store(snapshot.stale_handle_uses, Ordering::Relaxed);
+        self.sessions_expired.store(snapshot.sessions_expired, Ordering::Relaxed);
+
+        self.validation_failures.store(snapshot.validation_failures, Ordering::Relaxed);
+        self.patches_validated.store(snapshot.patches_validated, Ordering::Relaxed);
+        self.patch_validation_failures.store(snapshot.patch_validation_failures, Ordering::Relaxed);
+        self.validation_time_us.store(snapshot.validation_time_us, Ordering::Relaxed);
+        self.memo_skips.store(snapshot.memo_skips, Ordering::Relaxed);
+        self.strict_divergences.store(snapshot.strict_divergences, Ordering::Relaxed);
+
+        self.patch_limit_exceeded.store(snapshot.patch_limit_exceeded, Ordering::Relaxed);
+        self.patches_collapsed_by_limit.store(snapshot.patches_collapsed_by_limit, Ordering::Relaxed);
+        self.patches_filtered.store(snapshot.patches_filtered, Ordering::Relaxed);
+
+        self.breakers_tripped.store(snapshot.breakers_tripped, Ordering::Relaxed);
+        self.updates_suppressed.store(snapshot.updates_suppressed, Ordering::Relaxed);
+
+        self.hints_scheduled.store(snapshot.hints_scheduled, Ordering::Relaxed);
+        self.hints_computed.store(snapshot.hints_computed, Ordering::Relaxed);
+        self.hints_cancelled.store(snapshot.hints_cancelled, Ordering::Relaxed);
+        self.hints_used.store(snapshot.hints_used, Ordering::Relaxed);
+        self.hint_precompute_time_us.store(snapshot.hint_precompute_time_us, Ordering::Relaxed);
+
+        self.deliveries_as_patches.store(snapshot.deliveries_as_patches, Ordering::Relaxed);
+        self.deliveries_as_html.store(snapshot.deliveries_as_html, Ordering::Relaxed);
+
+        self.latency_reports.store(snapshot.latency_reports, Ordering::Relaxed);
     }
 }
 
 /// Calculate percentile from sorted values
-fn percentile(values: &[u64], p: f64) -> u64 {
+pub(crate) fn percentile(values: &[u64], p: f64) -> u64 {
     if values.is_empty() {
         return 0;
     }
@@ -271,16 +650,96 @@ pub struct MetricsSnapshot {
     pub prediction_hit_rate: f64,
     pub avg_prediction_time_us: u64,
     pub p95_prediction_time_us: u64,
+    /// Repeated identical transitions dropped by the duplicate window
+    #[serde(default)]
+    pub duplicate_learns_suppressed: u64,
+    #[serde(default)]
+    pub duplicate_predictions_suppressed: u64,
 
     // Memory
     pub current_predictors: usize,
     pub max_predictors: usize,
     pub evictions_performed: u64,
+    /// Calls made with the handle of a destroyed predictor
+    #[serde(default)]
+    pub stale_handle_uses: u64,
+
+    // Sessions
+    #[serde(default)]
+    pub current_sessions: usize,
+    /// Sessions dropped for idleness or the session limit
+    #[serde(default)]
+    pub sessions_expired: u64,
 
     // Validation
     pub validation_failures: u64,
     pub patches_validated: u64,
     pub patch_validation_failures: u64,
+    /// Time spent validating trees while reconciling
+    #[serde(default)]
+    pub validation_time_us: u64,
+
+    // Memo
+    /// Reconciles of pure components skipped for unchanged props
+    #[serde(default)]
+    pub memo_skips: u64,
+
+    // Strict mode
+    /// Operations whose two runs disagreed
+    #[serde(default)]
+    pub strict_divergences: u64,
+
+    // Anti-amplification
+    /// Reconciles whose diff exceeded max_patches
+    #[serde(default)]
+    pub patch_limit_exceeded: u64,
+    /// Patches removed by collapsing those diffs into Replace patches
+    #[serde(default)]
+    pub patches_collapsed_by_limit: u64,
+    /// Patches withheld by patch filters
+    #[serde(default)]
+    pub patches_filtered: u64,
+
+    // Rate limiting
+    pub breakers_tripped: u64,
+    pub breakers_open: usize,
+    pub updates_suppressed: u64,
+
+    // Hint precompute
+    pub hints_scheduled: u64,
+    pub hints_computed: u64,
+    pub hints_cancelled: u64,
+    pub hints_used: u64,
+    pub hint_precompute_time_us: u64,
+    /// Share of precomputed hints that were used by a real state change
+    pub hint_utilization: f64,
+
+    // Delivery
+    /// Updates sent as patches / as rendered HTML
+    #[serde(default)]
+    pub deliveries_as_patches: u64,
+    #[serde(default)]
+    pub deliveries_as_html: u64,
+
+    // End-to-end latency
+    /// Apply reports received from clients
+    #[serde(default)]
+    pub latency_reports: u64,
+    /// State change to patches applied, over recent reports
+    #[serde(default)]
+    pub avg_end_to_end_latency_us: u64,
+    #[serde(default)]
+    pub p95_end_to_end_latency_us: u64,
+
+    // Errors
+    /// Failed FFI calls per entry point
+    #[serde(default)]
+    pub ffi_errors: std::collections::BTreeMap<String, u64>,
+
+    // Feature flags
+    /// State of every runtime flag when the snapshot was taken
+    #[serde(default)]
+    pub flags: std::collections::BTreeMap<String, bool>,
 }
 
 /// FFI functions for metrics
@@ -332,6 +791,30 @@ mod tests {
         assert_eq!(snapshot.prediction_hit_rate, 2.0 / 3.0);
     }
 
+    #[test]
+    fn test_timing_samples_from_many_threads() {
+        let samples = TimingSamples::new();
+        std::thread::scope(|scope| {
+            for t in 0..4u64 {
+                let samples = &samples;
+                scope.spawn(move || {
+                    for i in 0..50 {
+                        samples.record(t * 1000 + i);
+                    }
+                });
+            }
+        });
+        assert_eq!(samples.collect().len(), 200);
+
+        // Each shard keeps only its most recent samples
+        for i in 0..(SAMPLES_PER_SHARD as u64 * 3) {
+            samples.record(i);
+        }
+        assert!(samples.collect().len() <= TIMING_SHARDS * SAMPLES_PER_SHARD);
+        samples.clear();
+        assert!(samples.collect().is_empty());
+    }
+
     #[test]
     fn test_percentile() {
         let values = vec![10, 20, 30, 40, 50, 60, 70, 80, 90, 100];
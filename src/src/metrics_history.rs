@@ -0,0 +1,301 @@
+//! Rolling per-second history of key metrics
+//!
+//! MetricsSnapshot is point-in-time; devtools want trends. When enabled, reconciles
+//! and predictions are also counted into 1-second buckets (keyed by unix time),
+//! keeping the last `retention_secs` seconds. `series` turns the buckets into
+//! parallel arrays (one entry per second, empty seconds as zeros) ready for graphing.
+//!
+//! Disabled by default: recording then costs a single atomic load. Enabled, threads
+//! record into their own shard of buckets (spread round-robin, like METRICS' timing
+//! samples), so concurrent recorders rarely wait on each other; `series` merges
+//! the shards.
+
+use crate::metrics::{thread_shard, TIMING_SHARDS};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default number of seconds kept
+pub const DEFAULT_RETENTION_SECS: u64 = 300;
+
+/// Upper bound on retention (one hour)
+pub const MAX_RETENTION_SECS: u64 = 3600;
+
+/// Timing samples kept per bucket for percentiles; later samples are only counted
+const MAX_SAMPLES_PER_BUCKET: usize = 1024;
+
+#[derive(Debug, Default)]
+struct Bucket {
+    second: u64,
+    reconciles: u64,
+    reconcile_errors: u64,
+    predictions: u64,
+    prediction_hits: u64,
+    reconcile_times: Vec<u64>,
+    prediction_times: Vec<u64>,
+}
+
+impl Bucket {
+    /// Add another shard's bucket for the same second
+    fn merge(&mut self, other: &Bucket) {
+        self.reconciles += other.reconciles;
+        self.reconcile_errors += other.reconcile_errors;
+        self.predictions += other.predictions;
+        self.prediction_hits += other.prediction_hits;
+        self.reconcile_times.extend_from_slice(&other.reconcile_times);
+        self.prediction_times.extend_from_slice(&other.prediction_times);
+    }
+}
+
+/// Metrics history as parallel arrays, one entry per second
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetricsSeries {
+    /// Unix time (seconds) of each entry
+    pub timestamps: Vec<u64>,
+    /// Reconciles per second
+    pub reconcile_rate: Vec<u64>,
+    pub reconcile_errors: Vec<u64>,
+    /// Predictions per second
+    pub prediction_rate: Vec<u64>,
+    /// Prediction hit rate within the second (0 when there were no predictions)
+    pub hit_rate: Vec<f64>,
+    pub p95_reconcile_time_us: Vec<u64>,
+    pub p95_prediction_time_us: Vec<u64>,
+}
+
+/// Rolling 1-second buckets for key metrics
+pub struct MetricsHistory {
+    enabled: AtomicBool,
+    retention_secs: AtomicU64,
+    /// Buckets of each thread shard, oldest first
+    shards: Box<[Mutex<VecDeque<Bucket>>]>,
+}
+
+lazy_static::lazy_static! {
+    /// Process-wide history, fed by METRICS
+    pub static ref HISTORY: MetricsHistory = MetricsHistory::new();
+}
+
+impl Default for MetricsHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricsHistory {
+    /// A disabled history with the default retention
+    pub fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            retention_secs: AtomicU64::new(DEFAULT_RETENTION_SECS),
+            shards: (0..TIMING_SHARDS).map(|_| Mutex::new(VecDeque::new())).collect(),
+        }
+    }
+
+    /// Turn recording on or off, keeping `retention_secs` seconds (capped at MAX_RETENTION_SECS)
+    /// Disabling drops the recorded history
+    pub fn configure(&self, enabled: bool, retention_secs: u64) {
+        self.retention_secs.store(retention_secs.clamp(1, MAX_RETENTION_SECS), Ordering::Relaxed);
+        if !enabled {
+            for shard in self.shards.iter() {
+                shard.lock().unwrap().clear();
+            }
+        }
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Check if the history is recording
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn record_reconcile(&self, micros: u64, error: bool) {
+        if self.is_enabled() {
+            self.record_reconcile_at(now_secs(), micros, error);
+        }
+    }
+
+    pub fn record_prediction(&self, micros: u64, hit: bool) {
+        if self.is_enabled() {
+            self.record_prediction_at(now_secs(), micros, hit);
+        }
+    }
+
+    fn record_reconcile_at(&self, second: u64, micros: u64, error: bool) {
+        self.with_bucket(second, |bucket| {
+            bucket.reconciles += 1;
+            if error {
+                bucket.reconcile_errors += 1;
+            }
+            if bucket.reconcile_times.len() < MAX_SAMPLES_PER_BUCKET {
+                bucket.reconcile_times.push(micros);
+            }
+        });
+    }
+
+    fn record_prediction_at(&self, second: u64, micros: u64, hit: bool) {
+        self.with_bucket(second, |bucket| {
+            bucket.predictions += 1;
+            if hit {
+                bucket.prediction_hits += 1;
+            }
+            if bucket.prediction_times.len() < MAX_SAMPLES_PER_BUCKET {
+                bucket.prediction_times.push(micros);
+            }
+        });
+    }
+
+    fn with_bucket(&self, second: u64, f: impl FnOnce(&mut Bucket)) {
+        let retention = self.retention_secs.load(Ordering::Relaxed);
+        let mut buckets = self.shards[thread_shard()].lock().unwrap();
+
+        // Late samples (clock went backwards, or a thread raced a bucket rollover)
+        // go to their own second as long as it is still retained
+        let index = match buckets.iter().rposition(|b| b.second <= second) {
+            Some(i) if buckets[i].second == second => i,
+            Some(i) => {
+                buckets.insert(i + 1, Bucket { second, ..Bucket::default() });
+                i + 1
+            }
+            None => {
+                buckets.push_front(Bucket { second, ..Bucket::default() });
+                0
+            }
+        };
+        f(&mut buckets[index]);
+
+        let newest = buckets.back().map_or(second, |b| b.second);
+        while buckets.front().is_some_and(|b| b.second + retention <= newest) {
+            buckets.pop_front();
+        }
+    }
+
+    /// The last `seconds` seconds (up to the retention), oldest first
+    pub fn series(&self, seconds: u64) -> MetricsSeries {
+        self.series_at(now_secs(), seconds)
+    }
+
+    fn series_at(&self, now: u64, seconds: u64) -> MetricsSeries {
+        let seconds = seconds.min(self.retention_secs.load(Ordering::Relaxed));
+        let mut series = MetricsSeries::default();
+        if seconds == 0 {
+            return series;
+        }
+
+        let first = (now + 1).saturating_sub(seconds);
+        let mut merged: BTreeMap<u64, Bucket> = BTreeMap::new();
+        for shard in self.shards.iter() {
+            for bucket in shard.lock().unwrap().iter().filter(|b| (first..=now).contains(&b.second)) {
+                merged.entry(bucket.second).or_insert_with(|| Bucket { second: bucket.second, ..Bucket::default() }).merge(bucket);
+            }
+        }
+
+        for second in first..=now {
+            let bucket = merged.get(&second);
+
+            series.timestamps.push(second);
+            let Some(bucket) = bucket else {
+                series.reconcile_rate.push(0);
+                series.reconcile_errors.push(0);
+                series.prediction_rate.push(0);
+                series.hit_rate.push(0.0);
+                series.p95_reconcile_time_us.push(0);
+                series.p95_prediction_time_us.push(0);
+                continue;
+            };
+            series.reconcile_rate.push(bucket.reconciles);
+            series.reconcile_errors.push(bucket.reconcile_errors);
+            series.prediction_rate.push(bucket.predictions);
+            series.hit_rate.push(if bucket.predictions > 0 {
+                bucket.prediction_hits as f64 / bucket.predictions as f64
+            } else {
+                0.0
+            });
+            series.p95_reconcile_time_us.push(crate::metrics::percentile(&bucket.reconcile_times, 0.95));
+            series.p95_prediction_time_us.push(crate::metrics::percentile(&bucket.prediction_times, 0.95));
+        }
+        series
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Enable (with `retention_secs` of history) or disable the metrics history
+#[no_mangle]
+pub extern "C" fn minimact_metrics_history_configure(enabled: bool, retention_secs: u64) {
+    HISTORY.configure(enabled, retention_secs);
+}
+
+/// Get the last `seconds` seconds of metrics history as JSON (MetricsSeries)
+///
+/// # Safety
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_metrics_history(seconds: u64) -> *mut std::os::raw::c_char {
+    use std::ffi::CString;
+
+    match serde_json::to_string(&HISTORY.series(seconds)) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_series_fills_gaps() {
+        let history = MetricsHistory::new();
+        history.configure(true, 60);
+        history.record_reconcile_at(100, 10, false);
+        history.record_reconcile_at(100, 30, true);
+        history.record_prediction_at(100, 5, true);
+        history.record_prediction_at(100, 5, false);
+        history.record_reconcile_at(102, 20, false);
+
+        let series = history.series_at(102, 4);
+        assert_eq!(series.timestamps, vec![99, 100, 101, 102]);
+        assert_eq!(series.reconcile_rate, vec![0, 2, 0, 1]);
+        assert_eq!(series.reconcile_errors, vec![0, 1, 0, 0]);
+        assert_eq!(series.hit_rate, vec![0.0, 0.5, 0.0, 0.0]);
+        assert_eq!(series.p95_reconcile_time_us, vec![0, 30, 0, 20]);
+    }
+
+    #[test]
+    fn test_retention_is_bounded() {
+        let history = MetricsHistory::new();
+        history.configure(true, 10);
+        for second in 0..100 {
+            history.record_reconcile_at(second, 1, false);
+        }
+
+        let kept: usize = history.shards.iter().map(|shard| shard.lock().unwrap().len()).sum();
+        assert_eq!(kept, 10);
+        let series = history.series_at(99, 1000);
+        assert_eq!(series.timestamps.len(), 10);
+        assert_eq!(series.reconcile_rate.iter().sum::<u64>(), 10);
+    }
+
+    #[test]
+    fn test_threads_record_into_one_series() {
+        let history = std::sync::Arc::new(MetricsHistory::new());
+        history.configure(true, 60);
+        let recorders: Vec<_> = (0..2 * TIMING_SHARDS)
+            .map(|_| {
+                let history = std::sync::Arc::clone(&history);
+                std::thread::spawn(move || (0..100).for_each(|i| history.record_prediction_at(100, i, i % 2 == 0)))
+            })
+            .collect();
+        recorders.into_iter().for_each(|recorder| recorder.join().unwrap());
+
+        let series = history.series_at(100, 1);
+        assert_eq!(series.prediction_rate, vec![200 * TIMING_SHARDS as u64]);
+        assert_eq!(series.hit_rate, vec![0.5]);
+        assert_eq!(series.p95_prediction_time_us, vec![95]);
+    }
+}
@@ -0,0 +1,180 @@
+//! Exhaustive reconciler self-checks (feature "paranoid")
+//!
+//! After every reconcile, the emitted patches are validated one by one against the
+//! tree they will be applied to, applied to the old tree, and the result compared
+//! with the new tree. The patched tree (what the client ends up holding) must not
+//! reuse a path for two nodes: colliding transpiled paths in the new tree are fine
+//! as long as the reconciler relocated them. Any failure panics with the trees, the
+//! patch list and the first difference.
+//!
+//! Meant for framework development only: it rebuilds and walks both trees several
+//! times per reconcile. Trees must carry transpiled paths (the `VNode::element`
+//! helpers put every node at the root path and will trip the uniqueness check).
+
+use crate::apply::apply_patch_indexed;
+use crate::attribute_semantics::AttributeTable;
+use crate::checksum::{checksum_to_hex, tree_checksum};
+use crate::patch_validator::{validate_patch_indexed, PatchValidatorConfig};
+use crate::path::HexPath;
+use crate::tree_index::TreeIndex;
+use crate::vdom::{Patch, VNode};
+use std::collections::HashMap;
+
+/// Check that `patches` turn `old` into `new`, panicking with diagnostics if not
+pub fn check_reconcile(old: &VNode, new: &VNode, patches: &[Patch]) {
+    let mut failures = Vec::new();
+    let config = PatchValidatorConfig::default();
+    let mut tree = old.clone();
+    let mut index = TreeIndex::build(&tree);
+    for (i, patch) in patches.iter().enumerate() {
+        if let Err(e) = validate_patch_indexed(patch, &tree, &index, &config) {
+            failures.push(format!("patch #{} ({} at '{}') is invalid: {}", i, patch.kind(), patch.path(), e));
+        }
+        if let Err(e) = apply_patch_indexed(&mut tree, &index, patch) {
+            failures.push(format!("patch #{} ({} at '{}') failed to apply: {}", i, patch.kind(), patch.path(), e));
+            break;
+        }
+        index.invalidate(patch, &tree);
+    }
+
+    let attributes = crate::attribute_semantics::attribute_table();
+    if let Some(diff) = first_difference(&tree, new, &attributes) {
+        failures.push(format!("patched tree differs from new tree: {}", diff));
+    }
+    failures.extend(duplicate_paths(&tree).into_iter().map(|d| format!("patched tree: {}", d)));
+
+    if !failures.is_empty() {
+        panic!(
+            "paranoid: reconcile produced bad patches\n  - {}\n\npatches: {}\n\nold tree: {}\n\nnew tree: {}\n\npatched tree: {}",
+            failures.join("\n  - "),
+            pretty(patches),
+            pretty(old),
+            pretty(new),
+            pretty(&tree),
+        );
+    }
+}
+
+fn pretty<T: serde::Serialize + ?Sized>(value: &T) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|e| format!("<unserializable: {}>", e))
+}
+
+/// Paths used by more than one node, described with both node types
+fn duplicate_paths(tree: &VNode) -> Vec<String> {
+    fn walk<'t>(node: &'t VNode, seen: &mut HashMap<&'t HexPath, &'static str>, duplicates: &mut Vec<String>) {
+        if let Some(first) = seen.insert(node.path(), node.node_type()) {
+            duplicates.push(format!("path '{}' used by both a {} and a {}", node.path(), first, node.node_type()));
+        }
+        for child in node.children().iter().flatten() {
+            walk(child, seen, duplicates);
+        }
+    }
+
+    let mut seen = HashMap::new();
+    let mut duplicates = Vec::new();
+    walk(tree, &mut seen, &mut duplicates);
+    duplicates
+}
+
+/// Children the client renders (nulls dropped)
+fn rendered(children: &[Option<VNode>]) -> Vec<&VNode> {
+    children.iter().flatten().filter(|n| !n.is_null()).collect()
+}
+
+/// First place where two trees differ as the client would render them
+/// (null children are ignored, props are compared like the reconciler does,
+/// through the attribute table), or None if they match
+fn first_difference(actual: &VNode, expected: &VNode, attributes: &AttributeTable) -> Option<String> {
+    let at = expected.path();
+    match (actual, expected) {
+        (VNode::Text(a), VNode::Text(e)) if a.content != e.content => {
+            Some(format!("at '{}': text is {:?}, expected {:?}", at, a.content, e.content))
+        }
+        (VNode::Element(a), VNode::Element(e)) => {
+            if a.tag != e.tag {
+                return Some(format!("at '{}': <{}>, expected <{}>", at, a.tag, e.tag));
+            }
+            if crate::ignored_regions::is_ignored(a) && crate::ignored_regions::is_ignored(e) {
+                // Client-owned region: never patched
+                return None;
+            }
+            if !attributes.props_equivalent(&a.props, &e.props) {
+                return Some(format!("at '{}': props {:?}, expected {:?}", at, a.props, e.props));
+            }
+            if a.key != e.key {
+                return Some(format!("at '{}': key {:?}, expected {:?}", at, a.key, e.key));
+            }
+            let (a_children, e_children) = (rendered(&a.children), rendered(&e.children));
+            if a_children.len() != e_children.len() {
+                let paths = |nodes: &[&VNode]| nodes.iter().map(|n| n.path().to_string()).collect::<Vec<_>>();
+                return Some(format!(
+                    "at '{}': children {:?}, expected {:?}",
+                    at,
+                    paths(&a_children),
+                    paths(&e_children)
+                ));
+            }
+            a_children.iter().zip(&e_children).find_map(|(a, e)| first_difference(a, e, attributes))
+        }
+        (VNode::Lazy(a), VNode::Lazy(e)) => {
+            // Opaque to the reconciler: only the placeholder itself can differ
+            let (a_sum, e_sum) = (tree_checksum(actual), tree_checksum(expected));
+            (a.summary != e.summary || a_sum != e_sum).then(|| {
+                format!(
+                    "at '{}': placeholder {:?} ({}), expected {:?} ({})",
+                    at,
+                    a.summary,
+                    checksum_to_hex(a_sum),
+                    e.summary,
+                    checksum_to_hex(e_sum)
+                )
+            })
+        }
+        (VNode::Text(_), VNode::Text(_)) | (VNode::Null(_), VNode::Null(_)) => None,
+        _ => Some(format!("at '{}': {}, expected {}", at, actual.node_type(), expected.node_type())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vdom::{VElement, VText};
+
+    fn div(path: &str, children: Vec<Option<VNode>>) -> VNode {
+        VNode::Element(VElement {
+            tag: "div".to_string(),
+            props: HashMap::new(),
+            children,
+            key: None,
+            path: HexPath::from(path),
+            source: None,
+        })
+    }
+
+    fn text(path: &str, content: &str) -> Option<VNode> {
+        Some(VNode::Text(VText { content: content.to_string(), path: HexPath::from(path) }))
+    }
+
+    #[test]
+    fn test_reconcile_passes_checks() {
+        let old = div("10000000", vec![text("10000000.10000000", "A"), text("10000000.20000000", "B")]);
+        let new = div("10000000", vec![text("10000000.10000000", "A!"), None, text("10000000.30000000", "C")]);
+        crate::reconciler::reconcile(&old, &new).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "patched tree differs")]
+    fn test_wrong_patches_panic() {
+        let old = div("10000000", vec![text("10000000.10000000", "A")]);
+        let new = div("10000000", vec![text("10000000.10000000", "B")]);
+        check_reconcile(&old, &new, &[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "placeholder \"2 items\"")]
+    fn test_lazy_placeholders_compare_by_summary() {
+        let lazy = |summary: &str| Some(VNode::Lazy(crate::vdom::VLazy { path: HexPath::from("10000000.10000000"), summary: summary.to_string() }));
+        check_reconcile(&div("10000000", vec![lazy("2 items")]), &div("10000000", vec![lazy("2 items")]), &[]);
+        check_reconcile(&div("10000000", vec![lazy("2 items")]), &div("10000000", vec![lazy("3 items")]), &[]);
+    }
+}
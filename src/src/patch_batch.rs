@@ -0,0 +1,275 @@
+//! Patch batch envelope with stable IDs
+//!
+//! Clients on flaky connections may receive the same batch twice (a retransmit
+//! after a dropped ack) or out of order. Every batch sent to a client is wrapped in
+//! a `PatchBatch` carrying the stream it belongs to (usually the component id), a
+//! per-stream sequence number starting at 1, and IDs derived from that content, so
+//! resending a batch produces byte-identical IDs.
+//!
+//! ## Idempotency rules
+//!
+//! Applying a batch twice must leave the same DOM as applying it once:
+//! - UpdateText, UpdateProps, the attribute/template updates and Replace set a value
+//!   and are idempotent
+//! - ReorderChildren sets a full key order and is idempotent
+//! - Create is guarded by an existence check: a node already at the path is
+//!   overwritten, never duplicated
+//! - Remove is not: the client must treat a missing target as already removed
+//!   (`apply_patches` reports it as an error)
+//!
+//! Rather than relying on that, receivers should track the last applied sequence
+//! per stream (`SequenceTracker`) and skip duplicates. A gap (a batch that isn't the
+//! next one) means something was lost: don't apply it, request a resync instead.
+//!
+//! Batches sent by sessions also carry a `BatchStamp` (a process-wide sequence and
+//! the server's clock) for latency measurement, see `latency`. The stamp isn't part
+//! of the IDs.
+
+use crate::checksum::{checksum_to_hex, Fnv64};
+use crate::error::Result;
+use crate::vdom::{Patch, VNode};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// A patch with its ID
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdentifiedPatch {
+    /// 16 hex digits, unique within the stream
+    pub id: String,
+    #[serde(flatten)]
+    pub patch: Patch,
+}
+
+/// Envelope around the patches of one update sent to a client
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatchBatch {
+    /// 16 hex digits over stream, sequence and patch IDs
+    pub batch_id: String,
+    /// Independent ordering domain, usually the component id
+    pub stream_id: String,
+    /// Position in the stream, starting at 1
+    pub sequence: u64,
+    pub patches: Vec<IdentifiedPatch>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stamp: Option<BatchStamp>,
+}
+
+/// When, and in what order across all streams, the server sent a batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchStamp {
+    /// Process-wide, starting at 1
+    pub server_sequence: u64,
+    /// Unix milliseconds (server clock)
+    pub server_time_ms: u64,
+    /// Unix milliseconds of the state change the batch answers, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_change_ms: Option<u64>,
+}
+
+impl PatchBatch {
+    /// Wrap `patches` as batch `sequence` of `stream_id`
+    pub fn new(stream_id: impl Into<String>, sequence: u64, patches: Vec<Patch>) -> Self {
+        let stream_id = stream_id.into();
+        let mut batch_hasher = Fnv64::new();
+        write_field(&mut batch_hasher, stream_id.as_bytes());
+        write_field(&mut batch_hasher, &sequence.to_le_bytes());
+
+        let patches: Vec<IdentifiedPatch> = patches
+            .into_iter()
+            .enumerate()
+            .map(|(index, patch)| {
+                let id = patch_id(&stream_id, sequence, index, &patch);
+                write_field(&mut batch_hasher, id.as_bytes());
+                IdentifiedPatch { id, patch }
+            })
+            .collect();
+
+        Self {
+            batch_id: checksum_to_hex(batch_hasher.finish()),
+            stream_id,
+            sequence,
+            patches,
+            stamp: None,
+        }
+    }
+
+    /// Stamp the batch for latency measurement (see `latency`)
+    pub fn stamped(mut self, state_change_ms: Option<u64>) -> Self {
+        self.stamp = Some(crate::latency::LATENCY.stamp(state_change_ms));
+        self
+    }
+
+    /// The bare patches, in order
+    pub fn into_patches(self) -> Vec<Patch> {
+        self.patches.into_iter().map(|p| p.patch).collect()
+    }
+}
+
+/// ID of the `index`th patch of a batch: FNV-1a over stream, sequence, index and the
+/// patch's JSON
+fn patch_id(stream_id: &str, sequence: u64, index: usize, patch: &Patch) -> String {
+    let mut hasher = Fnv64::new();
+    write_field(&mut hasher, stream_id.as_bytes());
+    write_field(&mut hasher, &sequence.to_le_bytes());
+    write_field(&mut hasher, &(index as u64).to_le_bytes());
+    // Patch serialization can't fail (string keys, no non-finite floats)
+    write_field(&mut hasher, &serde_json::to_vec(patch).unwrap_or_default());
+    checksum_to_hex(hasher.finish())
+}
+
+fn write_field(hasher: &mut Fnv64, bytes: &[u8]) {
+    hasher.write(bytes);
+    hasher.write(&[0]);
+}
+
+/// Hands out consecutive sequence numbers per stream (sender side)
+#[derive(Debug, Default)]
+pub struct BatchSequencer {
+    last: DashMap<String, u64>,
+}
+
+impl BatchSequencer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wrap `patches` as the next batch of `stream_id`
+    pub fn next_batch(&self, stream_id: &str, patches: Vec<Patch>) -> PatchBatch {
+        let sequence = {
+            let mut last = self.last.entry(stream_id.to_string()).or_insert(0);
+            *last += 1;
+            *last
+        };
+        PatchBatch::new(stream_id, sequence, patches)
+    }
+
+    /// Forget a stream (e.g. the component unmounted); its next batch is sequence 1
+    pub fn reset(&self, stream_id: &str) {
+        self.last.remove(stream_id);
+    }
+}
+
+/// How a received batch relates to what was already applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BatchOrder {
+    /// The next batch of its stream: apply it
+    Next,
+    /// Already applied: skip it
+    Duplicate,
+    /// Batches before this one are missing: don't apply, request a resync
+    Gap { expected: u64 },
+}
+
+/// Last applied sequence per stream (receiver side)
+#[derive(Debug, Clone, Default)]
+pub struct SequenceTracker {
+    applied: HashMap<String, u64>,
+}
+
+impl SequenceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Classify `batch` without recording it
+    pub fn check(&self, batch: &PatchBatch) -> BatchOrder {
+        let expected = self.applied.get(&batch.stream_id).copied().unwrap_or(0) + 1;
+        match batch.sequence {
+            s if s < expected => BatchOrder::Duplicate,
+            s if s == expected => BatchOrder::Next,
+            _ => BatchOrder::Gap { expected },
+        }
+    }
+
+    /// Record `batch` as applied
+    pub fn mark_applied(&mut self, batch: &PatchBatch) {
+        let applied = self.applied.entry(batch.stream_id.clone()).or_insert(0);
+        *applied = (*applied).max(batch.sequence);
+    }
+
+    /// Last applied sequence of `stream_id` (0 if none)
+    pub fn last_applied(&self, stream_id: &str) -> u64 {
+        self.applied.get(stream_id).copied().unwrap_or(0)
+    }
+}
+
+/// Apply `batch` to `tree` if it's the next one of its stream
+/// Duplicates and gaps are returned without touching the tree
+pub fn apply_batch(tree: &mut VNode, batch: &PatchBatch, tracker: &mut SequenceTracker) -> Result<BatchOrder> {
+    let order = tracker.check(batch);
+    if order == BatchOrder::Next {
+        let patches: Vec<Patch> = batch.patches.iter().map(|p| p.patch.clone()).collect();
+        crate::apply::apply_patches(tree, &patches)?;
+        tracker.mark_applied(batch);
+    } else {
+        crate::log_debug!("Skipping batch {} of '{}': {:?}", batch.sequence, batch.stream_id, order);
+    }
+    Ok(order)
+}
+
+/// Drop repeated batches (a second batch for the same stream and sequence keeps the
+/// first) and order the rest by stream, then sequence
+pub fn dedupe_batches(batches: impl IntoIterator<Item = PatchBatch>) -> Vec<PatchBatch> {
+    let mut seen = HashSet::new();
+    let mut unique: Vec<PatchBatch> = batches
+        .into_iter()
+        .filter(|batch| seen.insert((batch.stream_id.clone(), batch.sequence)))
+        .collect();
+    unique.sort_by(|a, b| a.stream_id.cmp(&b.stream_id).then(a.sequence.cmp(&b.sequence)));
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::HexPath;
+
+    fn text_patch(content: &str) -> Patch {
+        Patch::UpdateText { path: HexPath::from("10000000"), content: content.to_string() }
+    }
+
+    #[test]
+    fn test_ids_are_stable_and_distinct() {
+        let sequencer = BatchSequencer::new();
+        let first = sequencer.next_batch("counter", vec![text_patch("1"), text_patch("1")]);
+        let second = sequencer.next_batch("counter", vec![text_patch("1")]);
+
+        assert_eq!((first.sequence, second.sequence), (1, 2));
+        assert_eq!(first, PatchBatch::new("counter", 1, vec![text_patch("1"), text_patch("1")]));
+        assert_ne!(first.patches[0].id, first.patches[1].id);
+        assert_ne!(first.patches[0].id, second.patches[0].id);
+        assert_ne!(first.batch_id, second.batch_id);
+
+        // The envelope flattens each patch next to its id
+        let json = serde_json::to_value(&first).unwrap();
+        assert_eq!(json["patches"][0]["type"], "UpdateText");
+        assert_eq!(serde_json::from_value::<PatchBatch>(json).unwrap(), first);
+    }
+
+    #[test]
+    fn test_duplicates_and_gaps_are_not_applied() {
+        let mut tree = VNode::Text(crate::vdom::VText { content: "0".to_string(), path: HexPath::from("10000000") });
+        let mut tracker = SequenceTracker::new();
+        let batch = |sequence, content| PatchBatch::new("counter", sequence, vec![text_patch(content)]);
+
+        assert_eq!(apply_batch(&mut tree, &batch(1, "1"), &mut tracker).unwrap(), BatchOrder::Next);
+        assert_eq!(apply_batch(&mut tree, &batch(1, "1"), &mut tracker).unwrap(), BatchOrder::Duplicate);
+        assert_eq!(apply_batch(&mut tree, &batch(3, "3"), &mut tracker).unwrap(), BatchOrder::Gap { expected: 2 });
+        assert_eq!(tracker.last_applied("counter"), 1);
+        assert!(matches!(&tree, VNode::Text(t) if t.content == "1"));
+    }
+
+    #[test]
+    fn test_dedupe_batches() {
+        let batches = vec![
+            PatchBatch::new("b", 1, vec![]),
+            PatchBatch::new("a", 2, vec![text_patch("2")]),
+            PatchBatch::new("a", 1, vec![text_patch("1")]),
+            PatchBatch::new("a", 2, vec![text_patch("2")]),
+        ];
+        let order: Vec<(String, u64)> = dedupe_batches(batches).into_iter().map(|b| (b.stream_id, b.sequence)).collect();
+        assert_eq!(order, vec![("a".to_string(), 1), ("a".to_string(), 2), ("b".to_string(), 1)]);
+    }
+}
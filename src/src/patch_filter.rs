@@ -0,0 +1,137 @@
+//! Declarative patch filters
+//!
+//! A host embedding third-party components can restrict what their patches touch,
+//! e.g. deny anything outside a sandbox subtree. A filter allows or denies patches
+//! by path prefix (a path and everything under it) and by patch kind. Sessions run
+//! their filters after reconciling and before emitting a batch (see
+//! `Session::set_patch_filter`); withheld patches are logged and counted in
+//! `patches_filtered`.
+//!
+//! A denied path is also protected from patches above it that would rewrite it: a
+//! Replace or Remove of one of its ancestors (the reconciler collapses a changed
+//! parent into one Replace) and a ReorderChildren of its parent are withheld too.
+//!
+//! The stored tree is the full new tree, so a withheld patch leaves the client's
+//! tree behind the server's. That is the point for a sandbox; drift detection and
+//! resync treat it like any other divergence.
+
+use crate::path::HexPath;
+use crate::vdom::Patch;
+use serde::{Deserialize, Serialize};
+
+/// Which patches may be emitted
+/// Empty allow lists allow everything; deny rules win over allow rules
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PatchFilter {
+    /// Only patches at or under one of these paths
+    pub allow_paths: Vec<HexPath>,
+    /// No patches at or under these paths
+    pub deny_paths: Vec<HexPath>,
+    /// Only these kinds ("UpdateText", "Create", ...)
+    pub allow_kinds: Vec<String>,
+    pub deny_kinds: Vec<String>,
+}
+
+impl PatchFilter {
+    /// Why `patch` is withheld, or None if it may be emitted
+    pub fn check(&self, patch: &Patch) -> Option<String> {
+        let (path, kind) = (patch.path(), patch.kind());
+        if self.deny_kinds.iter().any(|k| k == kind) {
+            return Some(format!("kind {} is denied", kind));
+        }
+        if !self.allow_kinds.is_empty() && !self.allow_kinds.iter().any(|k| k == kind) {
+            return Some(format!("kind {} is not allowed", kind));
+        }
+        if let Some(denied) = self.deny_paths.iter().find(|prefix| path.is_within(prefix)) {
+            return Some(format!("path {} is under denied {}", path, denied));
+        }
+        if let Some(denied) = self.deny_paths.iter().find(|prefix| rewrites(patch, prefix)) {
+            return Some(format!("{} at {} would rewrite denied {}", kind, path, denied));
+        }
+        if !self.allow_paths.is_empty() && !self.allow_paths.iter().any(|prefix| path.is_within(prefix)) {
+            return Some(format!("path {} is outside the allowed paths", path));
+        }
+        None
+    }
+
+    /// Keep the patches the filter allows, logging and counting the rest
+    /// `scope` names where the patches come from, for the log
+    pub fn apply(&self, patches: Vec<Patch>, scope: &str) -> Vec<Patch> {
+        let before = patches.len();
+        let kept: Vec<Patch> = patches
+            .into_iter()
+            .filter(|patch| match self.check(patch) {
+                Some(reason) => {
+                    crate::log_warn!("Patch filter of {} withheld {}: {}", scope, patch.kind(), reason);
+                    false
+                }
+                None => true,
+            })
+            .collect();
+        if kept.len() < before {
+            crate::metrics::METRICS.record_patches_filtered(before - kept.len());
+        }
+        kept
+    }
+}
+
+/// Whether `patch`, at a path above `denied`, would replace, remove or move it
+fn rewrites(patch: &Patch, denied: &HexPath) -> bool {
+    match patch {
+        Patch::Replace { path, .. } | Patch::Remove { path } => denied.is_descendant_of(path),
+        Patch::ReorderChildren { path, .. } => denied.parent().as_ref() == Some(path),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vdom::{VElement, VNode};
+    use std::collections::HashMap;
+
+    fn text(path: &str) -> Patch {
+        Patch::UpdateText { path: HexPath::from(path), content: "x".to_string() }
+    }
+
+    #[test]
+    fn test_sandbox_filter() {
+        let filter = PatchFilter {
+            allow_paths: vec![HexPath::from("10000000.20000000")],
+            deny_paths: vec![HexPath::from("10000000.20000000.30000000")],
+            deny_kinds: vec!["Remove".to_string()],
+            ..Default::default()
+        };
+        assert!(filter.check(&text("10000000.20000000.10000000")).is_none());
+        assert!(filter.check(&text("10000000.20000000")).is_none());
+        assert!(filter.check(&text("10000000.10000000")).is_some());
+        assert!(filter.check(&text("10000000.20000000.30000000.10000000")).is_some());
+        assert!(filter.check(&Patch::Remove { path: HexPath::from("10000000.20000000.10000000") }).is_some());
+
+        // Rewriting the sandbox from above
+        assert!(filter.check(&Patch::Replace { path: HexPath::from("10000000"), node: VNode::text("x"), preserve: None }).is_some());
+        let reorder = |path: &str| Patch::ReorderChildren { path: HexPath::from(path), order: vec![], preserve: None };
+        assert!(filter.check(&reorder("10000000.20000000")).is_some());
+        assert!(filter.check(&reorder("10000000.20000000.10000000")).is_none());
+
+        let kept = filter.apply(vec![text("10000000.20000000.10000000"), text("20000000")], "test");
+        assert_eq!(kept, vec![text("10000000.20000000.10000000")]);
+        assert!(PatchFilter::default().check(&text("20000000")).is_none());
+    }
+
+    #[test]
+    fn test_collapsed_parent_of_sandbox_is_withheld() {
+        let element = |tag: &str, path: HexPath, children| {
+            VNode::Element(VElement { tag: tag.to_string(), props: HashMap::new(), children, key: None, path, source: None })
+        };
+        let root = HexPath::root().child(0);
+        let sandbox = root.child(0);
+        let tree = |tag: &str| element(tag, root.clone(), vec![Some(element("iframe", sandbox.clone(), vec![]))]);
+
+        let patches = crate::reconciler::reconcile(&tree("div"), &tree("main")).unwrap();
+        assert!(matches!(&patches[..], [Patch::Replace { path, .. }] if *path == root));
+        let filter = PatchFilter { deny_paths: vec![sandbox], ..Default::default() };
+        assert!(filter.apply(patches, "test").is_empty());
+    }
+}
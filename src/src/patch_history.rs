@@ -0,0 +1,152 @@
+//! Tree reconstruction from patch history
+//!
+//! When a client reports a divergence, the question is what its tree looked like
+//! after some batch n. `PatchHistory` records the batches sent since an initial tree
+//! and rebuilds the tree at any point by replaying them through the apply engine.
+//! A checkpoint (a copy of the tree) is kept every `checkpoint_every` batches, so a
+//! reconstruction replays at most that many batches instead of the whole history.
+
+use crate::apply::apply_patches;
+use crate::error::{MinimactError, Result};
+use crate::last_error::FfiCall;
+use crate::tree_store::json_or_error;
+use crate::vdom::{Patch, VNode};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+/// Default batches between checkpoints
+pub const DEFAULT_CHECKPOINT_EVERY: usize = 32;
+
+/// The batches applied to a tree since `initial`
+#[derive(Debug, Clone)]
+pub struct PatchHistory {
+    batches: Vec<Vec<Patch>>,
+    /// checkpoints[i] is the tree after i * checkpoint_every batches (checkpoints[0] = initial)
+    checkpoints: Vec<VNode>,
+    checkpoint_every: usize,
+    /// Tree after every recorded batch
+    head: VNode,
+}
+
+impl PatchHistory {
+    pub fn new(initial: VNode, checkpoint_every: usize) -> Self {
+        Self {
+            batches: Vec::new(),
+            checkpoints: vec![initial.clone()],
+            checkpoint_every: checkpoint_every.max(1),
+            head: initial,
+        }
+    }
+
+    /// Number of recorded batches
+    pub fn len(&self) -> usize {
+        self.batches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+
+    /// Tree after every recorded batch
+    pub fn head(&self) -> &VNode {
+        &self.head
+    }
+
+    /// Apply and record a batch; a batch that doesn't apply is not recorded
+    pub fn push(&mut self, batch: Vec<Patch>) -> Result<()> {
+        let mut tree = self.head.clone();
+        apply_batch(&mut tree, &batch, self.batches.len())?;
+        self.head = tree;
+        self.batches.push(batch);
+        if self.batches.len().is_multiple_of(self.checkpoint_every) {
+            self.checkpoints.push(self.head.clone());
+        }
+        Ok(())
+    }
+
+    /// The tree after the first `n` batches (0 = the initial tree)
+    pub fn reconstruct(&self, n: usize) -> Result<VNode> {
+        if n > self.batches.len() {
+            return Err(MinimactError::InvalidVNode(format!(
+                "History has {} batches, can't reconstruct after batch {}",
+                self.batches.len(),
+                n
+            )));
+        }
+        let checkpoint = n / self.checkpoint_every;
+        let mut tree = self.checkpoints[checkpoint].clone();
+        for index in checkpoint * self.checkpoint_every..n {
+            apply_batch(&mut tree, &self.batches[index], index)?;
+        }
+        Ok(tree)
+    }
+}
+
+fn apply_batch(tree: &mut VNode, batch: &[Patch], index: usize) -> Result<()> {
+    apply_patches(tree, batch).map_err(|e| MinimactError::InvalidVNode(format!("Batch {} doesn't apply: {}", index, e)))
+}
+
+/// The tree after applying `batches` to `initial` in order
+pub fn reconstruct(initial: &VNode, batches: &[Vec<Patch>]) -> Result<VNode> {
+    let mut tree = initial.clone();
+    for (index, batch) in batches.iter().enumerate() {
+        apply_batch(&mut tree, batch, index)?;
+    }
+    Ok(tree)
+}
+
+/// Reconstruct the tree after the first `count` batches
+/// batches_json is a JSON array of patch lists; returns the tree JSON (or {"error": ...})
+///
+/// # Safety
+/// - initial_json and batches_json must be valid null-terminated UTF-8 strings
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_reconstruct_tree(
+    initial_json: *const c_char,
+    batches_json: *const c_char,
+    count: usize,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_reconstruct_tree", &[initial_json, batches_json]);
+    json_or_error((|| -> Result<VNode> {
+        let initial = crate::validation::deserialize_vnode_safe(
+            CStr::from_ptr(initial_json).to_str()?,
+            &crate::validation::ValidationConfig::default(),
+        )?;
+        let batches: Vec<Vec<Patch>> = serde_json::from_str(CStr::from_ptr(batches_json).to_str()?)?;
+        if count > batches.len() {
+            return Err(MinimactError::InvalidVNode(format!("Only {} batches given, asked for {}", batches.len(), count)));
+        }
+        reconstruct(&initial, &batches[..count])
+    })())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::HexPath;
+
+    #[test]
+    fn test_reconstruct_matches_replay_at_every_point() {
+        let path = HexPath::from("10000000");
+        let mut history = PatchHistory::new(VNode::Text(crate::vdom::VText { content: "v0".to_string(), path: path.clone() }), 3);
+        let batches: Vec<Vec<Patch>> = (1..=10)
+            .map(|i| vec![Patch::UpdateText { path: path.clone(), content: format!("v{}", i) }])
+            .collect();
+        for batch in &batches {
+            history.push(batch.clone()).unwrap();
+        }
+
+        for n in 0..=10 {
+            let tree = history.reconstruct(n).unwrap();
+            assert!(matches!(&tree, VNode::Text(text) if text.content == format!("v{}", n)));
+            assert_eq!(tree, reconstruct(&history.checkpoints[0], &batches[..n]).unwrap());
+        }
+        assert_eq!(history.checkpoints.len(), 4);
+        assert!(history.reconstruct(11).is_err());
+
+        // A batch that doesn't apply isn't recorded
+        assert!(history.push(vec![Patch::Remove { path: HexPath::from("90000000") }]).is_err());
+        assert_eq!(history.len(), 10);
+    }
+}
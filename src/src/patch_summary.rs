@@ -0,0 +1,139 @@
+//! Patch list composition, for logging and capacity planning
+//!
+//! `summarize_patches` tells a host what a batch is made of: how many patches and
+//! serialized bytes of each kind, how deep into the tree they reach and which regions
+//! of the component they touch. That's what decides whether compression (big text or
+//! Replace payloads) or list windowing (many patches in one region) would pay off.
+//!
+//! A region is the subtree under one child of the component root, i.e. the patch
+//! path cut to its first REGION_DEPTH segments; patches on the root itself are their
+//! own region.
+
+use crate::error::Result;
+use crate::last_error::FfiCall;
+use crate::path::HexPath;
+use crate::vdom::Patch;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::{CStr, CString};
+use std::io;
+use std::os::raw::c_char;
+
+/// Path segments that identify a region (root element, then its child)
+pub const REGION_DEPTH: usize = 2;
+
+/// Count and serialized size of the patches of one kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KindSummary {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+/// Composition of a patch list
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatchSummary {
+    pub total_patches: usize,
+    /// Serialized JSON size of all patches
+    pub total_bytes: usize,
+    pub kinds: BTreeMap<String, KindSummary>,
+    /// Deepest path touched (segments)
+    pub max_depth: usize,
+    /// Patches per region root path (fixed-width hex, so sorted in document order)
+    pub regions: BTreeMap<String, usize>,
+}
+
+/// Counts serialized bytes without keeping them
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Summarize `patches` by kind, size, depth and region
+pub fn summarize_patches(patches: &[Patch]) -> Result<PatchSummary> {
+    let mut summary = PatchSummary { total_patches: patches.len(), ..Default::default() };
+
+    for patch in patches {
+        let mut counter = ByteCounter(0);
+        serde_json::to_writer(&mut counter, patch)?;
+
+        let kind = summary.kinds.entry(patch.kind().to_string()).or_default();
+        kind.count += 1;
+        kind.bytes += counter.0;
+        summary.total_bytes += counter.0;
+
+        let path = patch.path();
+        summary.max_depth = summary.max_depth.max(path.depth());
+        *summary.regions.entry(region_of(path)).or_default() += 1;
+    }
+    Ok(summary)
+}
+
+fn region_of(path: &HexPath) -> String {
+    let path = path.as_str();
+    match path.match_indices('.').nth(REGION_DEPTH - 1) {
+        Some((end, _)) => path[..end].to_string(),
+        None => path.to_string(),
+    }
+}
+
+/// Summarize a patch list
+/// Returns PatchSummary JSON (or {"error": ...})
+///
+/// # Safety
+/// - patches_json must be a valid null-terminated UTF-8 string
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_summarize_patches(patches_json: *const c_char) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_summarize_patches", &[patches_json]);
+    let result = (|| -> Result<String> {
+        let patches: Vec<Patch> = serde_json::from_str(CStr::from_ptr(patches_json).to_str()?)?;
+        Ok(serde_json::to_string(&summarize_patches(&patches)?)?)
+    })();
+    match result {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => {
+            crate::last_error::record_error(&e);
+            CString::new(serde_json::json!({ "error": e.to_string() }).to_string()).unwrap().into_raw()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_by_kind_depth_and_region() {
+        let text = |path: &str, content: &str| Patch::UpdateText { path: HexPath::from(path), content: content.to_string() };
+        let patches = vec![
+            text("10000000.10000000.20000000", "a"),
+            text("10000000.10000000.30000000.10000000", "bb"),
+            text("10000000.20000000", "c"),
+            Patch::Remove { path: HexPath::from("10000000") },
+        ];
+        let summary = summarize_patches(&patches).unwrap();
+
+        assert_eq!(summary.total_patches, 4);
+        assert_eq!(summary.kinds["UpdateText"].count, 3);
+        assert_eq!(summary.kinds["Remove"].count, 1);
+        let bytes: usize = patches.iter().map(|p| serde_json::to_string(p).unwrap().len()).sum();
+        assert_eq!(summary.total_bytes, bytes);
+        assert_eq!(summary.max_depth, 4);
+        assert_eq!(
+            summary.regions,
+            BTreeMap::from([
+                ("10000000".to_string(), 1),
+                ("10000000.10000000".to_string(), 2),
+                ("10000000.20000000".to_string(), 1),
+            ])
+        );
+    }
+}
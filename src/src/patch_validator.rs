@@ -1,9 +1,12 @@
 use crate::vdom::{VNode, Patch};
-use crate::error::{MinimactError, Result};
+use crate::error::{ErrorCode, MinimactError, Result};
 use crate::path::HexPath;
+use crate::tree_index::TreeIndex;
+use serde::{Deserialize, Serialize};
 
 /// Configuration for patch validation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PatchValidatorConfig {
     /// Maximum depth of path indices
     pub max_path_depth: usize,
@@ -25,13 +28,28 @@ impl Default for PatchValidatorConfig {
 
 /// Validates a single patch against a VNode tree
 pub fn validate_patch(patch: &Patch, tree: &VNode, config: &PatchValidatorConfig) -> Result<()> {
+    if config.validate_applicability {
+        validate_patch_indexed(patch, tree, &TreeIndex::build(tree), config)
+    } else {
+        validate_patch_indexed(patch, tree, &TreeIndex::default(), config)
+    }
+}
+
+/// Validates a single patch using a prebuilt index of `tree`
+/// Build the index once with `TreeIndex::build` when validating many patches
+pub fn validate_patch_indexed(
+    patch: &Patch,
+    tree: &VNode,
+    index: &TreeIndex,
+    config: &PatchValidatorConfig,
+) -> Result<()> {
     match patch {
         Patch::UpdateText { path, content } => {
             validate_path(path, config)?;
             validate_text_content(content)?;
 
             if config.validate_applicability {
-                let node = get_node_at_path(tree, path)?;
+                let node = lookup(tree, index, path)?;
                 if !node.is_text() {
                     return Err(MinimactError::PatchTypeMismatch {
                         expected: "Text",
@@ -46,7 +64,7 @@ pub fn validate_patch(patch: &Patch, tree: &VNode, config: &PatchValidatorConfig
             validate_props(props)?;
 
             if config.validate_applicability {
-                let node = get_node_at_path(tree, path)?;
+                let node = lookup(tree, index, path)?;
                 if !node.is_element() {
                     return Err(MinimactError::PatchTypeMismatch {
                         expected: "Element",
@@ -56,7 +74,7 @@ pub fn validate_patch(patch: &Patch, tree: &VNode, config: &PatchValidatorConfig
             }
         }
 
-        Patch::Replace { path, node } => {
+        Patch::Replace { path, node, .. } => {
             validate_path(path, config)?;
 
             // Validate the replacement node itself
@@ -65,7 +83,7 @@ pub fn validate_patch(patch: &Patch, tree: &VNode, config: &PatchValidatorConfig
 
             if config.validate_applicability {
                 // Just verify the path exists
-                get_node_at_path(tree, path)?;
+                lookup(tree, index, path)?;
             }
         }
 
@@ -84,14 +102,8 @@ pub fn validate_patch(patch: &Patch, tree: &VNode, config: &PatchValidatorConfig
                     });
                 }
 
-                // Get parent path and child index
-                let parent_path = path.parent().unwrap_or_else(|| HexPath::root());
-                let indices = path.to_index_path().map_err(|_| MinimactError::InvalidPatchPath {
-                    path: vec![]
-                })?;
-                let child_index = indices.last().copied().unwrap_or(0);
-
-                let parent = get_node_at_path(tree, &parent_path)?;
+                let parent_path = path.parent().unwrap_or_else(HexPath::root);
+                let parent = lookup(tree, index, &parent_path)?;
                 if !parent.is_element() {
                     return Err(MinimactError::PatchTypeMismatch {
                         expected: "Element (to have children)",
@@ -99,11 +111,25 @@ pub fn validate_patch(patch: &Patch, tree: &VNode, config: &PatchValidatorConfig
                     });
                 }
 
-                // Child index should be <= current children count (allows appending)
-                if child_index > parent.children_count() {
-                    return Err(MinimactError::InvalidPatchPath {
-                        path: path.to_index_path().unwrap_or_default()
-                    });
+                if index.contains(&parent_path) {
+                    // Hex paths: any free slot under the parent is valid (including hex gaps),
+                    // but a live node can't be created over
+                    if matches!(index.node_type(path), Some(t) if t != "Null") {
+                        return Err(MinimactError::InvalidPatchPath {
+                            path: path.to_index_path().unwrap_or_default()
+                        });
+                    }
+                } else {
+                    // Positional fallback: child index should be <= current children count (allows appending)
+                    let indices = path.to_index_path().map_err(|_| MinimactError::InvalidPatchPath {
+                        path: vec![]
+                    })?;
+                    let child_index = indices.last().copied().unwrap_or(0);
+                    if child_index > parent.children_count() {
+                        return Err(MinimactError::InvalidPatchPath {
+                            path: path.to_index_path().unwrap_or_default()
+                        });
+                    }
                 }
             }
         }
@@ -113,15 +139,15 @@ pub fn validate_patch(patch: &Patch, tree: &VNode, config: &PatchValidatorConfig
 
             if config.validate_applicability {
                 // Verify the node exists
-                get_node_at_path(tree, path)?;
+                lookup(tree, index, path)?;
             }
         }
 
-        Patch::ReorderChildren { path, order } => {
+        Patch::ReorderChildren { path, order, .. } => {
             validate_path(path, config)?;
 
             if config.validate_applicability {
-                let node = get_node_at_path(tree, path)?;
+                let node = lookup(tree, index, path)?;
                 if !node.is_element() {
                     return Err(MinimactError::PatchTypeMismatch {
                         expected: "Element (to have children)",
@@ -156,7 +182,7 @@ pub fn validate_patch(patch: &Patch, tree: &VNode, config: &PatchValidatorConfig
             }
 
             if config.validate_applicability {
-                let node = get_node_at_path(tree, path)?;
+                let node = lookup(tree, index, path)?;
                 if !node.is_text() {
                     return Err(MinimactError::PatchTypeMismatch {
                         expected: "Text",
@@ -177,7 +203,7 @@ pub fn validate_patch(patch: &Patch, tree: &VNode, config: &PatchValidatorConfig
             }
 
             if config.validate_applicability {
-                let node = get_node_at_path(tree, path)?;
+                let node = lookup(tree, index, path)?;
                 if !node.is_element() {
                     return Err(MinimactError::PatchTypeMismatch {
                         expected: "Element",
@@ -200,7 +226,7 @@ pub fn validate_patch(patch: &Patch, tree: &VNode, config: &PatchValidatorConfig
             // For Phase 4A, we just validate the path exists
             // More thorough validation can be added in future phases
             if config.validate_applicability {
-                let _node = get_node_at_path(tree, path)?;
+                let _node = lookup(tree, index, path)?;
                 // List templates can apply to any node (will replace children)
             }
         }
@@ -217,7 +243,7 @@ pub fn validate_patch(patch: &Patch, tree: &VNode, config: &PatchValidatorConfig
             }
 
             if config.validate_applicability {
-                let node = get_node_at_path(tree, path)?;
+                let node = lookup(tree, index, path)?;
                 if !node.is_element() {
                     return Err(MinimactError::PatchTypeMismatch {
                         expected: "Element (to have children)",
@@ -250,7 +276,7 @@ pub fn validate_patch(patch: &Patch, tree: &VNode, config: &PatchValidatorConfig
 
             if config.validate_applicability {
                 // Structural templates can apply to any node (they replace the entire node)
-                get_node_at_path(tree, path)?;
+                lookup(tree, index, path)?;
             }
         }
 
@@ -267,7 +293,7 @@ pub fn validate_patch(patch: &Patch, tree: &VNode, config: &PatchValidatorConfig
 
             // Value can be empty (e.g., className="")
             if config.validate_applicability {
-                let node = get_node_at_path(tree, path)?;
+                let node = lookup(tree, index, path)?;
                 if !node.is_element() {
                     return Err(MinimactError::PatchTypeMismatch {
                         expected: "Element",
@@ -295,7 +321,81 @@ pub fn validate_patch(patch: &Patch, tree: &VNode, config: &PatchValidatorConfig
             }
 
             if config.validate_applicability {
-                let node = get_node_at_path(tree, path)?;
+                let node = lookup(tree, index, path)?;
+                if !node.is_element() {
+                    return Err(MinimactError::PatchTypeMismatch {
+                        expected: "Element",
+                        found: node.node_type(),
+                    });
+                }
+            }
+        }
+
+        Patch::UpdateDocument { path, target, value } => {
+            validate_path(path, config)?;
+            if let Some(value) = value {
+                validate_text_content(value)?;
+            }
+            match target {
+                crate::document::DocumentTarget::Meta { name: key } | crate::document::DocumentTarget::Link { rel: key }
+                    if key.is_empty() =>
+                {
+                    return Err(MinimactError::InvalidVNode("Document meta name / link rel cannot be empty".to_string()));
+                }
+                _ => {}
+            }
+
+            if config.validate_applicability && value.is_none() {
+                // Removing a setting needs its element
+                lookup(tree, index, path)?;
+            }
+        }
+
+        Patch::Navigate { path, url, replace: _ } => {
+            validate_path(path, config)?;
+            validate_text_content(url)?;
+            if !crate::routing::is_navigable_url(url) {
+                return Err(MinimactError::InvalidVNode(format!("Refusing to navigate to '{}'", url)));
+            }
+        }
+
+        Patch::Custom { path, kind, payload } => {
+            validate_path(path, config)?;
+            crate::custom_patch::validate_custom_patch(kind, payload)?;
+        }
+
+        // Mount markers target nodes the batch creates, so there's nothing to look up
+        Patch::Lifecycle { path, event: _, handler } => {
+            validate_path(path, config)?;
+            validate_text_content(handler)?;
+        }
+
+        Patch::UpdateListWindow { path, total, start, end } => {
+            validate_path(path, config)?;
+
+            if start > end || end > total {
+                return Err(MinimactError::InvalidVNode(format!(
+                    "List window {}..{} doesn't fit {} rows",
+                    start, end, total
+                )));
+            }
+
+            if config.validate_applicability {
+                let node = lookup(tree, index, path)?;
+                if !node.is_element() {
+                    return Err(MinimactError::PatchTypeMismatch {
+                        expected: "Element (to have children)",
+                        found: node.node_type(),
+                    });
+                }
+            }
+        }
+
+        Patch::SetIgnored { path, ignored: _ } | Patch::InvalidateLayout { path, .. } => {
+            validate_path(path, config)?;
+
+            if config.validate_applicability {
+                let node = lookup(tree, index, path)?;
                 if !node.is_element() {
                     return Err(MinimactError::PatchTypeMismatch {
                         expected: "Element",
@@ -313,12 +413,125 @@ pub fn validate_patch(patch: &Patch, tree: &VNode, config: &PatchValidatorConfig
 pub fn validate_patches(patches: &[Patch], tree: &VNode, config: &PatchValidatorConfig) -> Result<()> {
     // For now, validate each patch independently against the original tree
     // A more sophisticated approach would simulate applying patches and validate against the evolving tree
+    let index = if config.validate_applicability {
+        TreeIndex::build(tree)
+    } else {
+        TreeIndex::default()
+    };
     for patch in patches {
-        validate_patch(patch, tree, config)?;
+        validate_patch_indexed(patch, tree, &index, config)?;
     }
     Ok(())
 }
 
+/// Outcome of validating one patch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatchDiagnostic {
+    /// Position in the patch list
+    pub index: usize,
+    /// Patch kind ("UpdateText", ...); absent if the entry isn't a patch at all
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<HexPath>,
+    pub ok: bool,
+    /// ErrorCode of the failure (0 when ok)
+    pub code: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Per-patch results of validating a patch list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatchValidationReport {
+    /// True when every patch passed
+    pub valid: bool,
+    pub passed: usize,
+    pub failed: usize,
+    /// One entry per patch, in order
+    pub diagnostics: Vec<PatchDiagnostic>,
+}
+
+impl PatchValidationReport {
+    fn new(diagnostics: Vec<PatchDiagnostic>) -> Self {
+        let passed = diagnostics.iter().filter(|d| d.ok).count();
+        Self {
+            valid: passed == diagnostics.len(),
+            passed,
+            failed: diagnostics.len() - passed,
+            diagnostics,
+        }
+    }
+}
+
+/// Validates every patch (not stopping at the first failure) and reports each result
+/// Like `validate_patches`, each patch is checked against the original tree
+pub fn validate_patches_detailed(patches: &[Patch], tree: &VNode, config: &PatchValidatorConfig) -> PatchValidationReport {
+    let index = build_index(tree, config);
+    PatchValidationReport::new(
+        patches
+            .iter()
+            .enumerate()
+            .map(|(i, patch)| diagnose(i, Ok(patch), tree, &index, config))
+            .collect(),
+    )
+}
+
+/// Like `validate_patches_detailed` for patches that haven't been parsed yet (e.g. a
+/// cached patch stream): entries that aren't valid patches fail individually
+pub fn validate_patch_values(patches: &[serde_json::Value], tree: &VNode, config: &PatchValidatorConfig) -> PatchValidationReport {
+    let index = build_index(tree, config);
+    PatchValidationReport::new(
+        patches
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let patch = Patch::deserialize(value).map_err(MinimactError::from);
+                diagnose(i, patch.as_ref(), tree, &index, config)
+            })
+            .collect(),
+    )
+}
+
+fn build_index(tree: &VNode, config: &PatchValidatorConfig) -> TreeIndex {
+    if config.validate_applicability {
+        TreeIndex::build(tree)
+    } else {
+        TreeIndex::default()
+    }
+}
+
+fn diagnose(
+    index: usize,
+    patch: std::result::Result<&Patch, &MinimactError>,
+    tree: &VNode,
+    tree_index: &TreeIndex,
+    config: &PatchValidatorConfig,
+) -> PatchDiagnostic {
+    let (kind, path, result) = match patch {
+        Ok(patch) => (
+            Some(patch.kind().to_string()),
+            Some(patch.path().clone()),
+            validate_patch_indexed(patch, tree, tree_index, config).map_err(|e| (ErrorCode::from(&e), e.to_string())),
+        ),
+        Err(e) => (None, None, Err((ErrorCode::from(e), e.to_string()))),
+    };
+    crate::metrics::METRICS.record_patch_validation(result.is_ok());
+
+    let (code, error) = match result {
+        Ok(()) => (ErrorCode::Success, None),
+        Err((code, message)) => (code, Some(message)),
+    };
+    PatchDiagnostic {
+        index,
+        kind,
+        path,
+        ok: error.is_none(),
+        code: code as i32,
+        error,
+    }
+}
+
 /// Validates a patch path
 fn validate_path(path: &HexPath, config: &PatchValidatorConfig) -> Result<()> {
     if path.depth() > config.max_path_depth {
@@ -330,13 +543,8 @@ fn validate_path(path: &HexPath, config: &PatchValidatorConfig) -> Result<()> {
     // Validate hex segments
     if let Ok(segments) = path.segments() {
         for segment in segments {
-            // Convert hex segment back to index for validation
-            if segment % crate::path::HEX_GAP != 0 {
-                return Err(MinimactError::InvalidPatchPath {
-                    path: vec![]
-                });
-            }
-            let index = (segment / crate::path::HEX_GAP - 1) as usize;
+            // Segments inside a hex gap (inserted siblings) round down to the slot they follow
+            let index = (segment / crate::path::HEX_GAP) as usize;
             if index > config.max_path_index {
                 return Err(MinimactError::InvalidPatchPath {
                     path: path.to_index_path().unwrap_or_default()
@@ -385,7 +593,16 @@ fn validate_props(props: &std::collections::HashMap<String, String>) -> Result<(
     Ok(())
 }
 
-/// Gets a node at a specific path in the tree
+/// Finds the node at `path` via the index, falling back to positional lookup for
+/// trees whose nodes don't carry their own paths
+fn lookup<'a>(tree: &'a VNode, index: &TreeIndex, path: &HexPath) -> Result<&'a VNode> {
+    match index.get(tree, path) {
+        Some(node) => Ok(node),
+        None => get_node_at_path(tree, path),
+    }
+}
+
+/// Gets a node at a specific path in the tree (positional: hex segment N*HEX_GAP is child N-1)
 fn get_node_at_path<'a>(tree: &'a VNode, path: &HexPath) -> Result<&'a VNode> {
     let mut current = tree;
 
@@ -417,8 +634,8 @@ fn get_node_at_path<'a>(tree: &'a VNode, path: &HexPath) -> Result<&'a VNode> {
                     path: indices[..=depth].to_vec(),
                 });
             }
-            VNode::Null(_) => {
-                // Null nodes have no children to navigate
+            VNode::Null(_) | VNode::Lazy(_) => {
+                // Null and unexpanded nodes have no children to navigate
                 return Err(MinimactError::InvalidPatchPath {
                     path: indices[..=depth].to_vec(),
                 });
@@ -433,15 +650,16 @@ fn get_node_at_path<'a>(tree: &'a VNode, path: &HexPath) -> Result<&'a VNode> {
 mod tests {
     use super::*;
     use std::collections::HashMap;
+    use crate::path::index_path_to_hex;
 
     #[test]
     fn test_validate_update_text_patch() {
         let tree = VNode::element("div", HashMap::new(), vec![
-            VNode::text("Hello"),
+            Some(VNode::text("Hello")),
         ]);
 
         let patch = Patch::UpdateText {
-            path: vec![0],
+            path: index_path_to_hex(&[0]),
             content: "World".to_string(),
         };
 
@@ -452,11 +670,11 @@ mod tests {
     #[test]
     fn test_validate_update_text_on_element_fails() {
         let tree = VNode::element("div", HashMap::new(), vec![
-            VNode::element("span", HashMap::new(), vec![]),
+            Some(VNode::element("span", HashMap::new(), vec![])),
         ]);
 
         let patch = Patch::UpdateText {
-            path: vec![0],
+            path: index_path_to_hex(&[0]),
             content: "World".to_string(),
         };
 
@@ -477,7 +695,7 @@ mod tests {
         let tree = VNode::element("div", HashMap::new(), vec![]);
 
         let patch = Patch::UpdateText {
-            path: vec![99],
+            path: index_path_to_hex(&[9]),
             content: "Test".to_string(),
         };
 
@@ -488,10 +706,10 @@ mod tests {
     #[test]
     fn test_validate_path_too_deep() {
         let tree = VNode::text("test");
-        let deep_path: Vec<usize> = (0..150).collect();
+        let deep_path = vec![0; 150];
 
         let patch = Patch::UpdateText {
-            path: deep_path,
+            path: index_path_to_hex(&deep_path),
             content: "Test".to_string(),
         };
 
@@ -502,11 +720,11 @@ mod tests {
     #[test]
     fn test_validate_create_patch() {
         let tree = VNode::element("div", HashMap::new(), vec![
-            VNode::text("Child 1"),
+            Some(VNode::text("Child 1")),
         ]);
 
         let patch = Patch::Create {
-            path: vec![1],
+            path: index_path_to_hex(&[1]),
             node: VNode::text("Child 2"),
         };
 
@@ -517,15 +735,71 @@ mod tests {
     #[test]
     fn test_validate_create_at_invalid_index() {
         let tree = VNode::element("div", HashMap::new(), vec![
-            VNode::text("Child 1"),
+            Some(VNode::text("Child 1")),
         ]);
 
         let patch = Patch::Create {
-            path: vec![99],  // Way beyond current children
+            path: index_path_to_hex(&[9, 0]),  // Parent slot holds nothing
             node: VNode::text("Child 2"),
         };
 
         let config = PatchValidatorConfig::default();
         assert!(validate_patch(&patch, &tree, &config).is_err());
     }
+
+    #[test]
+    fn test_validate_hex_gap_paths() {
+        let child = |path: &str, content: &str| Some(VNode::Text(crate::vdom::VText {
+            content: content.to_string(),
+            path: HexPath::from(path),
+        }));
+        // Second child was inserted into the gap after the first; third slot is a null gap
+        let tree = VNode::Element(crate::vdom::VElement {
+            tag: "div".to_string(),
+            props: HashMap::new(),
+            children: vec![child("10000000.10000000", "A"), child("10000000.18000000", "B"), None],
+            key: None,
+            path: HexPath::from("10000000"),
+            source: None,
+        });
+        let config = PatchValidatorConfig::default();
+
+        let update = Patch::UpdateText { path: HexPath::from("10000000.18000000"), content: "B2".to_string() };
+        assert!(validate_patch(&update, &tree, &config).is_ok());
+
+        let create = Patch::Create { path: HexPath::from("10000000.30000000"), node: VNode::text("C") };
+        assert!(validate_patch(&create, &tree, &config).is_ok());
+
+        let occupied = Patch::Create { path: HexPath::from("10000000.18000000"), node: VNode::text("C") };
+        assert!(validate_patch(&occupied, &tree, &config).is_err());
+    }
+
+    #[test]
+    fn test_detailed_report_covers_every_patch() {
+        let tree = VNode::Element(crate::vdom::VElement {
+            tag: "div".to_string(),
+            props: HashMap::new(),
+            children: vec![Some(VNode::Text(crate::vdom::VText {
+                content: "Hello".to_string(),
+                path: HexPath::from("10000000.10000000"),
+            }))],
+            key: None,
+            path: HexPath::from("10000000"),
+            source: None,
+        });
+        let patches = vec![
+            serde_json::json!({ "type": "UpdateText", "path": "10000000.10000000", "content": "Hi" }),
+            serde_json::json!({ "type": "UpdateText", "path": "10000000", "content": "Hi" }),
+            serde_json::json!({ "type": "NotAPatch" }),
+        ];
+
+        let report = validate_patch_values(&patches, &tree, &PatchValidatorConfig::default());
+        assert!(!report.valid);
+        assert_eq!((report.passed, report.failed), (1, 2));
+        assert!(report.diagnostics[0].ok);
+        assert_eq!(report.diagnostics[1].code, ErrorCode::PatchTypeMismatch as i32);
+        assert_eq!(report.diagnostics[1].kind.as_deref(), Some("UpdateText"));
+        assert_eq!(report.diagnostics[2].code, ErrorCode::Serialization as i32);
+        assert!(report.diagnostics[2].kind.is_none());
+    }
 }
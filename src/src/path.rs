@@ -12,6 +12,7 @@
 /// - Better performance for large component trees
 
 use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
 use std::fmt;
 
 /// Hex-based DOM path
@@ -20,6 +21,9 @@ use std::fmt;
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct HexPath(pub String);
 
+/// Parsed path segments - paths deeper than 8 levels are rare, so they stay on the stack
+pub type PathSegments = SmallVec<[u32; 8]>;
+
 /// The gap between consecutive elements (268M slots)
 /// This allows inserting 268M new elements between any two existing elements
 pub const HEX_GAP: u32 = 0x10000000;
@@ -59,6 +63,64 @@ impl HexPath {
         }
     }
 
+    /// Get the last segment of this path (None for root or an invalid segment)
+    pub fn last_segment(&self) -> Option<u32> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let last = self.0.rsplit('.').next()?;
+        u32::from_str_radix(last, 16).ok()
+    }
+
+    /// Replace the last segment, keeping the parent
+    fn with_last_segment(&self, segment: u32) -> Self {
+        match self.0.rfind('.') {
+            Some(last_dot) => HexPath(format!("{}.{:08x}", &self.0[..last_dot], segment)),
+            None => HexPath(format!("{:08x}", segment)),
+        }
+    }
+
+    /// Path for a new sibling inserted between `a` and `b`
+    /// Returns None if they aren't siblings, `a` isn't before `b`, or there's no gap left
+    /// Example: between("10000000", "20000000") = "18000000"
+    pub fn between(a: &HexPath, b: &HexPath) -> Option<HexPath> {
+        if a.parent() != b.parent() {
+            return None;
+        }
+        let (lo, hi) = (a.last_segment()?, b.last_segment()?);
+        if hi <= lo || hi - lo < 2 {
+            return None;
+        }
+        Some(a.with_last_segment(lo + (hi - lo) / 2))
+    }
+
+    /// Path for a new sibling appended right after this one
+    /// Uses a full HEX_GAP when available, otherwise half the remaining space
+    pub fn next_sibling(&self) -> Option<HexPath> {
+        let segment = self.last_segment()?;
+        let next = segment.checked_add(HEX_GAP).or_else(|| {
+            let remaining = u32::MAX - segment;
+            (remaining >= 2).then(|| segment + remaining / 2)
+        })?;
+        Some(self.with_last_segment(next))
+    }
+
+    /// Path for a new sibling inserted right before this one (halfway to zero)
+    pub fn prev_sibling(&self) -> Option<HexPath> {
+        let segment = self.last_segment()?;
+        (segment >= 2).then(|| self.with_last_segment(segment / 2))
+    }
+
+    /// Compare paths in document (pre-order) order: segment by segment numerically,
+    /// with ancestors ordered before their descendants
+    pub fn cmp_document_order(&self, other: &HexPath) -> std::cmp::Ordering {
+        match (self.segments(), other.segments()) {
+            (Ok(a), Ok(b)) => a.as_slice().cmp(b.as_slice()),
+            // Not valid hex - fall back to plain string order
+            _ => self.0.cmp(&other.0),
+        }
+    }
+
     /// Get the parent path (remove last segment)
     /// Returns None if this is the root path
     pub fn parent(&self) -> Option<Self> {
@@ -91,6 +153,22 @@ impl HexPath {
         self.0.is_empty()
     }
 
+    /// Check if this path is a strict descendant of `ancestor`
+    /// Example: "10000000.20000000" is a descendant of "10000000" and of the root
+    pub fn is_descendant_of(&self, ancestor: &HexPath) -> bool {
+        if ancestor.is_root() {
+            return !self.is_root();
+        }
+        self.0.len() > ancestor.0.len()
+            && self.0.starts_with(&ancestor.0)
+            && self.0.as_bytes()[ancestor.0.len()] == b'.'
+    }
+
+    /// Check if this path equals `other` or is one of its descendants
+    pub fn is_within(&self, other: &HexPath) -> bool {
+        self == other || self.is_descendant_of(other)
+    }
+
     /// Get the underlying string
     pub fn as_str(&self) -> &str {
         &self.0
@@ -102,9 +180,9 @@ impl HexPath {
     }
 
     /// Parse hex segments from the path
-    pub fn segments(&self) -> Result<Vec<u32>, std::num::ParseIntError> {
+    pub fn segments(&self) -> Result<PathSegments, std::num::ParseIntError> {
         if self.0.is_empty() {
-            return Ok(Vec::new());
+            return Ok(PathSegments::new());
         }
         self.0
             .split('.')
@@ -239,7 +317,21 @@ mod tests {
     fn test_segments() {
         let path = HexPath::from("10000000.20000000.30000000");
         let segments = path.segments().unwrap();
-        assert_eq!(segments, vec![0x10000000, 0x20000000, 0x30000000]);
+        assert_eq!(segments.as_slice(), &[0x10000000, 0x20000000, 0x30000000]);
+    }
+
+    #[test]
+    fn test_descendant_checks() {
+        let parent = HexPath::from("10000000");
+        let child = HexPath::from("10000000.20000000");
+        let lookalike = HexPath::from("100000001");
+
+        assert!(child.is_descendant_of(&parent));
+        assert!(child.is_descendant_of(&HexPath::root()));
+        assert!(!parent.is_descendant_of(&parent));
+        assert!(parent.is_within(&parent));
+        assert!(!lookalike.is_descendant_of(&parent));
+        assert!(!parent.is_descendant_of(&child));
     }
 
     #[test]
@@ -255,4 +347,34 @@ mod tests {
         assert!(child0.as_str() < inserted.as_str());
         assert!(inserted.as_str() < child1.as_str());
     }
+
+    #[test]
+    fn test_between_and_siblings() {
+        let a = HexPath::from("10000000.10000000");
+        let b = HexPath::from("10000000.20000000");
+
+        assert_eq!(HexPath::between(&a, &b).unwrap().as_str(), "10000000.18000000");
+        assert!(HexPath::between(&b, &a).is_none());
+        assert!(HexPath::between(&a, &HexPath::from("20000000")).is_none());
+        assert!(HexPath::between(&HexPath::from("10000000"), &HexPath::from("10000001")).is_none());
+
+        assert_eq!(a.next_sibling().unwrap(), b);
+        assert_eq!(a.prev_sibling().unwrap().as_str(), "10000000.08000000");
+        assert_eq!(HexPath::from("f0000000").next_sibling().unwrap().as_str(), "f7ffffff");
+        assert!(HexPath::root().next_sibling().is_none());
+    }
+
+    #[test]
+    fn test_cmp_document_order() {
+        use std::cmp::Ordering;
+
+        let parent = HexPath::from("10000000");
+        let child = HexPath::from("10000000.30000000");
+        let sibling = HexPath::from("18000000");
+
+        assert_eq!(parent.cmp_document_order(&child), Ordering::Less);
+        assert_eq!(child.cmp_document_order(&sibling), Ordering::Less);
+        assert_eq!(sibling.cmp_document_order(&parent), Ordering::Greater);
+        assert_eq!(child.cmp_document_order(&child.clone()), Ordering::Equal);
+    }
 }
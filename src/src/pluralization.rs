@@ -0,0 +1,213 @@
+//! Counted nouns in text ("1 item" / "5 items", "3 minutes ago")
+//!
+//! A counter label changes its noun at the singular/plural boundary, so swapping
+//! only the number mispredicts "1 items". These heuristics find a number followed
+//! by a noun, tell the singular and plural forms apart (from an observed crossing,
+//! or by English suffix rules) and produce a conditional template: the value that
+//! displays as 1 renders the singular form, any other value the plural.
+//!
+//! The displayed number may be the state value through a unit conversion (seconds
+//! shown as minutes or hours, milliseconds as seconds); the binding then carries
+//! the matching transform.
+
+use crate::template_renderer::format_value;
+use crate::vdom::{Binding, TemplatePatch};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Conversions tried between a state value and the number shown: (transform, divisor)
+const UNIT_TRANSFORMS: [(Option<&str>, f64); 4] = [(None, 1.0), (Some("/ 60"), 60.0), (Some("/ 3600"), 3600.0), (Some("/ 1000"), 1000.0)];
+
+/// (singular, plural) pairs that don't follow the suffix rules
+const IRREGULAR: [(&str, &str); 7] = [
+    ("child", "children"),
+    ("person", "people"),
+    ("man", "men"),
+    ("woman", "women"),
+    ("foot", "feet"),
+    ("tooth", "teeth"),
+    ("mouse", "mice"),
+];
+
+/// Words that follow numbers without being counted by them ("1 of 5")
+const NOT_NOUNS: [&str; 14] = ["of", "in", "on", "at", "by", "to", "from", "and", "or", "the", "per", "out", "more", "ago"];
+
+/// Singular form of a plural English noun (None if `word` doesn't look plural)
+pub fn singularize(word: &str) -> Option<String> {
+    if let Some((singular, _)) = IRREGULAR.iter().find(|(_, plural)| *plural == word) {
+        return Some(singular.to_string());
+    }
+    if let Some(stem) = word.strip_suffix("ies").filter(|stem| stem.len() > 1) {
+        return Some(format!("{}y", stem));
+    }
+    // "-es" is only unambiguous after these; "cases", "sizes" and "caches" drop just the
+    // "s" while "buses", "quizzes" and "churches" drop both, so the rest aren't guessed
+    for ending in ["sses", "xes", "shes", "zzes"] {
+        if word.ends_with(ending) {
+            return Some(word[..word.len() - 2].to_string());
+        }
+    }
+    if ["ses", "zes", "ches"].iter().any(|ending| word.ends_with(ending)) {
+        return None;
+    }
+    if word.ends_with("ss") || word.len() < 3 {
+        return None;
+    }
+    word.strip_suffix('s').map(str::to_string)
+}
+
+/// Plural form of a singular English noun
+pub fn pluralize(word: &str) -> String {
+    if let Some((_, plural)) = IRREGULAR.iter().find(|(singular, _)| *singular == word) {
+        return plural.to_string();
+    }
+    if let Some(stem) = word.strip_suffix('y') {
+        if stem.ends_with(|c: char| c.is_ascii_alphabetic() && !"aeiou".contains(c)) {
+            return format!("{}ies", stem);
+        }
+    }
+    if ["s", "x", "z", "ch", "sh"].iter().any(|ending| word.ends_with(ending)) {
+        return format!("{}es", word);
+    }
+    format!("{}s", word)
+}
+
+/// Text split around a number and the noun after it
+struct CountedNoun<'a> {
+    prefix: &'a str,
+    noun: &'a str,
+    suffix: &'a str,
+}
+
+/// Split `content` at the first standalone `number` followed by a space and a word
+fn counted_noun<'a>(content: &'a str, number: &str) -> Option<CountedNoun<'a>> {
+    content.match_indices(number).find_map(|(position, _)| {
+        let prefix = &content[..position];
+        if prefix.ends_with(|c: char| c.is_ascii_digit() || c == '.' || c == '-') {
+            return None;
+        }
+        let rest = content[position + number.len()..].strip_prefix(' ')?;
+        let noun_len = rest.find(|c: char| !c.is_alphabetic()).unwrap_or(rest.len());
+        let noun = &rest[..noun_len];
+        if noun.is_empty() || NOT_NOUNS.contains(&noun.to_lowercase().as_str()) {
+            return None;
+        }
+        Some(CountedNoun { prefix, noun, suffix: &rest[noun_len..] })
+    })
+}
+
+fn displayed(value: &Value, divisor: f64) -> Option<String> {
+    let shown = value.as_f64()? / divisor;
+    (shown.fract() == 0.0).then(|| format_value(&serde_json::json!(shown)))
+}
+
+/// Conditional template for a counted noun whose count is bound to `state_key`
+///
+/// old_content and new_content show the old and new value with the noun after it;
+/// the template renders the singular form when the binding displays as 1.
+pub fn plural_template(state_key: &str, old_value: &Value, new_value: &Value, old_content: &str, new_content: &str) -> Option<TemplatePatch> {
+    UNIT_TRANSFORMS.iter().find_map(|&(transform, divisor)| {
+        let (old_shown, new_shown) = (displayed(old_value, divisor)?, displayed(new_value, divisor)?);
+        let old = counted_noun(old_content, &old_shown)?;
+        let rest = new_content.strip_prefix(old.prefix)?.strip_prefix(new_shown.as_str())?.strip_prefix(' ')?;
+        let new_noun = rest.strip_suffix(old.suffix)?;
+        if new_noun.is_empty() || !new_noun.chars().all(char::is_alphabetic) {
+            return None;
+        }
+
+        let (singular, plural) = match (old_shown == "1", new_shown == "1") {
+            (true, false) => (old.noun.to_string(), new_noun.to_string()),
+            (false, true) => (new_noun.to_string(), old.noun.to_string()),
+            _ if old.noun == new_noun => (singularize(old.noun)?, old.noun.to_string()),
+            _ => return None,
+        };
+        if singular == plural {
+            return None;
+        }
+
+        let form = |noun: &str| format!("{}{{0}} {}{}", old.prefix, noun, old.suffix);
+        let one = format_value(&serde_json::json!(divisor));
+        Some(TemplatePatch {
+            template: form(&plural),
+            bindings: vec![state_key.to_string()],
+            bindings_with_transforms: transform.map(|transform| {
+                vec![Binding { state_key: state_key.to_string(), transform: Some(transform.to_string()) }]
+            }),
+            slots: vec![old.prefix.len()],
+            conditional_templates: Some(HashMap::from([(one, form(&singular))])),
+            conditional_binding_index: Some(0),
+        })
+    })
+}
+
+/// Replace `old_number` with `new_number` in `content`, fixing the noun after it
+/// when the count crosses 1 ("1 item" → "2 items")
+pub fn recount(content: &str, old_number: &str, new_number: &str) -> String {
+    let replaced = content.replace(old_number, new_number);
+    if (old_number == "1") == (new_number == "1") {
+        return replaced;
+    }
+    let Some(counted) = counted_noun(content, old_number) else { return replaced };
+    let noun = if new_number == "1" {
+        match singularize(counted.noun) {
+            Some(singular) => singular,
+            None => return replaced,
+        }
+    } else {
+        pluralize(counted.noun)
+    };
+    format!(
+        "{}{} {}{}",
+        counted.prefix.replace(old_number, new_number),
+        new_number,
+        noun,
+        counted.suffix.replace(old_number, new_number)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template_renderer::render_template_patch;
+    use serde_json::json;
+
+    #[test]
+    fn test_plural_template_renders_both_forms() {
+        let state = |value: Value| HashMap::from([("count".to_string(), value)]);
+
+        let crossing = plural_template("count", &json!(1), &json!(2), "Cart: 1 item", "Cart: 2 items").unwrap();
+        assert_eq!(render_template_patch(&crossing, &state(json!(1))), "Cart: 1 item");
+        assert_eq!(render_template_patch(&crossing, &state(json!(7))), "Cart: 7 items");
+
+        // Never seen at 1: the singular comes from the suffix rules
+        let plural_only = plural_template("count", &json!(2), &json!(3), "2 boxes left", "3 boxes left").unwrap();
+        assert_eq!(render_template_patch(&plural_only, &state(json!(1))), "1 box left");
+
+        // Seconds shown as minutes
+        let minutes = plural_template("elapsed", &json!(120), &json!(180), "2 minutes ago", "3 minutes ago").unwrap();
+        let elapsed = |value: Value| HashMap::from([("elapsed".to_string(), value)]);
+        assert_eq!(render_template_patch(&minutes, &elapsed(json!(60))), "1 minute ago");
+
+        assert!(plural_template("count", &json!(2), &json!(3), "2 of 10", "3 of 10").is_none());
+    }
+
+    #[test]
+    fn test_recount_fixes_the_noun() {
+        assert_eq!(recount("1 item", "1", "2"), "2 items");
+        assert_eq!(recount("2 cities", "2", "1"), "1 city");
+        assert_eq!(recount("2 children", "2", "3"), "3 children");
+        assert_eq!(recount("1 of 5", "1", "2"), "2 of 5");
+        assert_eq!(recount("2 cases", "2", "1"), "1 cases");
+    }
+
+    #[test]
+    fn test_singularize_leaves_ambiguous_endings_alone() {
+        assert_eq!(singularize("boxes").as_deref(), Some("box"));
+        assert_eq!(singularize("classes").as_deref(), Some("class"));
+        assert_eq!(singularize("dishes").as_deref(), Some("dish"));
+        assert_eq!(singularize("buzzes").as_deref(), Some("buzz"));
+        for word in ["cases", "responses", "sizes", "caches"] {
+            assert_eq!(singularize(word), None, "{}", word);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Prediction bundles for client-side caching
+//!
+//! A bundle is a self-contained snapshot of the confident predictions for one
+//! component: the web client stores it (e.g. in a service worker) and applies a
+//! matching entry the moment a state change happens, before the server's patches
+//! arrive. Template entries cover every value of their state key and are
+//! materialized with the new value on the client; learned entries cover one kind of
+//! transition and only apply to the tree they were observed on (`base_checksum`,
+//! the same checksum the client reports for drift detection).
+//!
+//! Bundles expire after `ttl_secs` and carry the component's code hash, so a client
+//! drops them when either runs out. `checksum` covers the entries, letting the client
+//! discard a cache entry that was truncated or tampered with.
+
+use crate::checksum::{checksum_to_hex, Fnv64};
+use crate::predictor::{PatternType, Provenance};
+use crate::vdom::Patch;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Bundle format version
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// What to put in a bundle
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BundleOptions {
+    /// Leave out predictions below this confidence
+    pub min_confidence: f32,
+    /// How long the client may use the bundle
+    pub ttl_secs: u64,
+}
+
+impl Default for BundleOptions {
+    fn default() -> Self {
+        Self { min_confidence: 0.9, ttl_secs: 3600 }
+    }
+}
+
+/// One cached prediction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleEntry {
+    /// State key whose change triggers the prediction
+    pub state_key: String,
+    /// Locale the prediction was learned in (None = learned without one)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// Kind of transition covered (None for templates, which cover any change)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern_type: Option<PatternType>,
+    /// Fields changed by an object mutation ("~name"), for learned entries of object state
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed_fields: Option<String>,
+    pub patches: Vec<Patch>,
+    pub confidence: f32,
+    pub provenance: Provenance,
+    /// Checksum (hex) of the tree the patches apply to (None = any tree)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_checksum: Option<String>,
+}
+
+/// Confident predictions of one component, ready to cache on the client
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PredictionBundle {
+    pub version: u32,
+    pub component_id: String,
+    /// Code hash of the build the predictions were learned from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_hash: Option<String>,
+    /// Unix seconds
+    pub created_at: u64,
+    pub ttl_secs: u64,
+    pub entries: Vec<BundleEntry>,
+    /// FNV-1a (hex) of the entries' JSON
+    pub checksum: String,
+}
+
+impl PredictionBundle {
+    /// Bundle `entries`, created now
+    pub fn new(component_id: &str, code_hash: Option<String>, ttl_secs: u64, entries: Vec<BundleEntry>) -> Self {
+        let created_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        Self {
+            version: BUNDLE_VERSION,
+            component_id: component_id.to_string(),
+            code_hash,
+            created_at,
+            ttl_secs,
+            checksum: entries_checksum(&entries),
+            entries,
+        }
+    }
+
+    /// Unix seconds after which the bundle must not be used
+    pub fn expires_at(&self) -> u64 {
+        self.created_at.saturating_add(self.ttl_secs)
+    }
+
+    pub fn is_expired(&self, now_secs: u64) -> bool {
+        now_secs >= self.expires_at()
+    }
+
+    /// Whether the entries still match the checksum
+    pub fn verify(&self) -> bool {
+        self.checksum == entries_checksum(&self.entries)
+    }
+}
+
+fn entries_checksum(entries: &[BundleEntry]) -> String {
+    let mut hasher = Fnv64::new();
+    hasher.write(&serde_json::to_vec(entries).unwrap_or_default());
+    checksum_to_hex(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::predictor::{Predictor, StateChange};
+    use crate::vdom::{ComponentMetadata, VNode};
+
+    #[test]
+    fn test_export_confident_patterns() {
+        let mut predictor = Predictor::new();
+        let mut metadata = ComponentMetadata::new("Toggle", "Toggle");
+        metadata.code_hash = Some("abc".to_string());
+        predictor.seed_from_metadata(&metadata);
+
+        let change = StateChange {
+            component_id: "Toggle".to_string(),
+            state_key: "open".to_string(),
+            old_value: serde_json::json!(false),
+            new_value: serde_json::json!(true),
+            array_operation: None,
+            locale: None,
+        };
+        let (closed, open) = (VNode::text("Closed"), VNode::text("Open"));
+        predictor.learn(change.clone(), &closed, &open, None).unwrap();
+
+        let bundle = predictor.export_bundle("Toggle", &BundleOptions::default());
+        assert_eq!(bundle.code_hash.as_deref(), Some("abc"));
+        assert_eq!(bundle.entries.len(), 1);
+        let entry = &bundle.entries[0];
+        assert_eq!(entry.state_key, "open");
+        assert_eq!(entry.base_checksum, Some(checksum_to_hex(crate::checksum::tree_checksum(&closed))));
+        assert!(bundle.verify());
+        assert!(!bundle.is_expired(bundle.created_at) && bundle.is_expired(bundle.expires_at()));
+        assert!(predictor.export_bundle("Other", &BundleOptions::default()).entries.is_empty());
+
+        // Localized patterns keep the state key and carry the locale separately
+        let localized = StateChange { locale: Some("fr-FR".to_string()), ..change };
+        predictor.learn(localized, &VNode::text("Fermé"), &VNode::text("Ouvert"), None).unwrap();
+        let bundle = predictor.export_bundle("Toggle", &BundleOptions::default());
+        let keys: Vec<_> = bundle.entries.iter().map(|e| (e.state_key.as_str(), e.locale.as_deref())).collect();
+        assert_eq!(keys, vec![("open", None), ("open", Some("fr-FR"))]);
+
+        let mut tampered = bundle.clone();
+        tampered.entries[0].patches.clear();
+        assert!(!tampered.verify());
+    }
+}
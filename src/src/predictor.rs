@@ -1,8 +1,20 @@
 use crate::vdom::{VNode, Patch, TemplatePatch, ComponentMetadata, LoopTemplate, ItemTemplate};
 use crate::reconciler::reconcile;
 use crate::path::HexPath;
+use crate::capabilities::{ClientCapabilities, materialize_templates, negotiate_patches};
+use crate::shared_tree::SharedTree;
+use crate::determinism::SeededHashMap;
+use crate::clock::Clock;
+use crate::deep_state_traversal::{diff_state_values, StateDiff};
+use crate::checksum::{checksum_to_hex, tree_checksum};
+use crate::prediction_bundle::{BundleEntry, BundleOptions, PredictionBundle};
+use crate::template_renderer::StateValues;
 use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
 /// Array operation metadata from semantic array helpers
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,6 +41,50 @@ pub struct StateChange {
     /// Optional: Semantic array operation (enables precise template extraction)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub array_operation: Option<ArrayOperation>,
+    /// Optional: Locale the rendered text was localized for (e.g. "fr-FR")
+    /// Templates and patterns are kept per locale (see `Localization`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+}
+
+impl StateChange {
+    /// What changed inside an object or array value (None for primitives and for
+    /// array changes described by `array_operation`)
+    pub fn value_diff(&self) -> Option<StateDiff> {
+        if self.array_operation.is_some() {
+            return None;
+        }
+        match (&self.old_value, &self.new_value) {
+            (serde_json::Value::Object(_), serde_json::Value::Object(_))
+            | (serde_json::Value::Array(_), serde_json::Value::Array(_)) => {
+                Some(diff_state_values(&self.old_value, &self.new_value))
+            }
+            _ => None,
+        }
+    }
+
+    /// This change with `array_operation` inferred from the old and new value when
+    /// the caller didn't describe it (`setTodos([...todos, todo])` learns and predicts
+    /// like the `append` helper)
+    pub fn with_array_operation(&self) -> Cow<'_, StateChange> {
+        match self.array_operation {
+            Some(_) => Cow::Borrowed(self),
+            None => match crate::array_diff::infer_array_operation(&self.old_value, &self.new_value) {
+                Some(operation) => Cow::Owned(StateChange { array_operation: Some(operation), ..self.clone() }),
+                None => Cow::Borrowed(self),
+            },
+        }
+    }
+
+    /// A full state snapshot before and after this change
+    /// (`state` with the changed key set to its old and new value)
+    pub fn state_snapshots(&self, state: &StateValues) -> (StateValues, StateValues) {
+        let mut old_state = state.clone();
+        let mut new_state = state.clone();
+        old_state.insert(self.state_key.clone(), self.old_value.clone());
+        new_state.insert(self.state_key.clone(), self.new_value.clone());
+        (old_state, new_state)
+    }
 }
 
 /// Pattern type detected from state changes
@@ -55,6 +111,98 @@ pub enum TemplateSource {
     BabelRefined,
 }
 
+impl TemplateSource {
+    pub fn provenance(self) -> Provenance {
+        match self {
+            TemplateSource::BabelGenerated | TemplateSource::BabelRefined => Provenance::CompileTime,
+            TemplateSource::RuntimeExtracted => Provenance::Learned,
+        }
+    }
+}
+
+/// Where a prediction came from, for attributing verification failures
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Provenance {
+    /// Babel-generated component metadata
+    CompileTime,
+    /// Learned from observed state changes
+    #[default]
+    Learned,
+    /// Built-in heuristic (e.g. numeric text replacement)
+    Heuristic,
+}
+
+impl Provenance {
+    /// Correct verifications assumed before any are observed
+    /// Compile-time templates are exact, so a few failures shouldn't disable them
+    fn prior_correct(self) -> usize {
+        match self {
+            Provenance::CompileTime => 10,
+            Provenance::Learned | Provenance::Heuristic => 0,
+        }
+    }
+}
+
+/// Pattern and verification counts for one provenance
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProvenanceStats {
+    pub patterns: usize,
+    pub predictions: usize,
+    pub correct: usize,
+    pub incorrect: usize,
+}
+
+impl ProvenanceStats {
+    fn add(&mut self, patterns: usize, predictions: usize, correct: usize, incorrect: usize) {
+        self.patterns += patterns;
+        self.predictions += predictions;
+        self.correct += correct;
+        self.incorrect += incorrect;
+    }
+}
+
+/// PredictorStats split by provenance
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProvenanceBreakdown {
+    pub compile_time: ProvenanceStats,
+    pub learned: ProvenanceStats,
+    pub heuristic: ProvenanceStats,
+}
+
+impl ProvenanceBreakdown {
+    fn get_mut(&mut self, provenance: Provenance) -> &mut ProvenanceStats {
+        match provenance {
+            Provenance::CompileTime => &mut self.compile_time,
+            Provenance::Learned => &mut self.learned,
+            Provenance::Heuristic => &mut self.heuristic,
+        }
+    }
+}
+
+/// One template or learned pattern, for the devtools pattern inspector
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternSummary {
+    /// "component::stateKey"
+    pub pattern_key: String,
+    /// "template" or "pattern"
+    pub kind: String,
+    pub provenance: Provenance,
+    /// Template origin (templates only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template_source: Option<TemplateSource>,
+    /// Detected change type (learned patterns only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern_type: Option<PatternType>,
+    pub patch_count: usize,
+    pub observations: usize,
+    pub predictions: usize,
+    pub correct: usize,
+    pub incorrect: usize,
+    pub hit_rate: f32,
+}
+
 /// Represents a prediction of how a state change will affect the DOM
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Prediction {
@@ -66,6 +214,9 @@ pub struct Prediction {
     pub confidence: f32,
     /// The predicted new VNode tree (optional, for validation)
     pub predicted_tree: Option<VNode>,
+    /// Where the predicted patches came from
+    #[serde(default)]
+    pub provenance: Provenance,
 }
 
 /// Template-based prediction (covers infinite values with one pattern)
@@ -83,6 +234,9 @@ struct TemplatePrediction {
     correct_count: usize,
     /// Number of incorrect predictions
     incorrect_count: usize,
+    /// Code generation of the component this template came from (see CodeVersion)
+    #[serde(default)]
+    code_generation: u32,
 }
 
 impl TemplatePrediction {
@@ -91,24 +245,113 @@ impl TemplatePrediction {
         if total == 0 {
             return 1.0; // Assume high confidence if never tested
         }
-        self.correct_count as f32 / total as f32
+        let prior = self.source.provenance().prior_correct();
+        (self.correct_count + prior) as f32 / (total + prior) as f32
     }
 }
 
 /// The predictor engine that learns patterns and makes predictions
-#[derive(Serialize, Deserialize)]
+///
+/// Cloning is cheap: pattern lists and templates are shared copy-on-write between
+/// clones, which is what lets ConcurrentPredictor publish a new version per learn.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Predictor {
     /// Historical patterns: maps state changes to observed patches
-    patterns: HashMap<String, Vec<PredictionPattern>>,
+    /// (lists are copy-on-write: mutate through Arc::make_mut)
+    patterns: SeededHashMap<String, Arc<PatternList>>,
     /// Template-based predictions (NEW: 98% memory reduction!)
     /// Maps state key to template patches that work for ANY value
-    template_predictions: HashMap<String, TemplatePrediction>,
+    /// (copy-on-write: mutate through Arc::make_mut)
+    template_predictions: SeededHashMap<String, Arc<TemplatePrediction>>,
     /// Configuration
     config: PredictorConfig,
+    /// Patch kinds the connected client supports (None = everything)
+    /// Per-connection, so not persisted with learned patterns
+    #[serde(skip)]
+    capabilities: Option<ClientCapabilities>,
+    /// Predictions and verifications of built-in heuristics (which store no patterns)
+    #[serde(default)]
+    heuristic: ProvenanceStats,
+    /// Time source for pattern ages
+    #[serde(skip, default = "crate::clock::clock")]
+    clock: Arc<dyn Clock>,
+    /// Miss streaks and cooldowns per component::key::pattern type (runtime only)
+    #[serde(skip)]
+    suppressions: SeededHashMap<String, Suppression>,
+    /// Predictions withheld because their pattern was suppressed
+    #[serde(default)]
+    suppressed_predictions: usize,
+    /// Last transition learned per component::key, for duplicate suppression (runtime only)
+    #[serde(skip)]
+    recent_transitions: SeededHashMap<String, RecentTransition>,
+    /// Learns and predictions dropped as duplicates
+    #[serde(default)]
+    duplicate_learns: usize,
+    #[serde(default)]
+    duplicate_predictions: usize,
+    /// Component id -> its current build (from ComponentMetadata::code_hash)
+    #[serde(default)]
+    code_versions: SeededHashMap<String, CodeVersion>,
+    /// Predictions withheld because their pattern was learned from other code
+    #[serde(default)]
+    stale_code_predictions: usize,
+    /// Patterns and templates dropped because their component's code changed
+    #[serde(default)]
+    stale_patterns_collected: usize,
+}
+
+/// The build of a component that patterns are learned from
+/// Patterns are stamped with the generation rather than the hash to stay small;
+/// generation 0 is "no hash given yet"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CodeVersion {
+    code_hash: String,
+    generation: u32,
+}
+
+/// Last old -> new transition learned for a state key
+#[derive(Debug, Clone)]
+struct RecentTransition {
+    old_value: serde_json::Value,
+    new_value: serde_json::Value,
+    at: std::time::Instant,
+}
+
+/// Negative-learning state of one component::key::pattern type
+#[derive(Debug, Clone, Default)]
+struct Suppression {
+    consecutive_misses: u32,
+    /// Don't predict before this
+    until: Option<std::time::Instant>,
+}
+
+/// Patterns observed for one state key - almost always one or two, so kept inline
+type PatternList = SmallVec<[PredictionPattern; 2]>;
+
+/// Bookkeeping left behind by a read-only prediction, applied by the next write
+/// (see `Predictor::predict_readonly` and `Predictor::record_use`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PredictionUse {
+    /// A template prediction was used
+    Template { pattern_key: String },
+    /// A learned pattern was used (found again by id: by the time the use is
+    /// recorded, the list may have been reordered or the pattern evicted)
+    Pattern { pattern_key: String, id: u64 },
+    /// A built-in heuristic was used
+    Heuristic,
+    /// Nothing was predicted because the pattern is suppressed
+    Suppressed,
+    /// Nothing was predicted because the same transition was just learned
+    Duplicate,
+    /// Nothing was predicted because the pattern was learned from other component code
+    StaleCode,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct PredictionPattern {
+    /// Identifies the pattern across predictor versions (runtime only)
+    #[serde(skip, default = "next_pattern_id")]
+    id: u64,
     /// The state change pattern
     #[allow(dead_code)]
     state_change_key: String,
@@ -118,10 +361,10 @@ struct PredictionPattern {
     patches: Vec<Patch>,
     /// Number of times this pattern was observed
     observation_count: usize,
-    /// Last VNode tree before the change
-    old_tree: Option<VNode>,
-    /// Last VNode tree after the change
-    new_tree: Option<VNode>,
+    /// Last VNode tree before the change (subtrees shared with other patterns)
+    old_tree: Option<SharedTree>,
+    /// Last VNode tree after the change (subtrees shared with other patterns)
+    new_tree: Option<SharedTree>,
     /// Seconds since pattern was last accessed (serialized from Instant)
     #[serde(skip, default = "std::time::Instant::now")]
     last_accessed: std::time::Instant,
@@ -134,6 +377,23 @@ struct PredictionPattern {
     predictions_correct: usize,
     /// Number of incorrect predictions
     predictions_incorrect: usize,
+    /// Code generation of the component this pattern was learned from (see CodeVersion)
+    #[serde(default)]
+    code_generation: u32,
+}
+
+/// A process-wide unique PredictionPattern id
+fn next_pattern_id() -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Split the "@locale" suffix `make_pattern_key` adds to a localized state key
+fn split_locale(state_key: &str) -> (&str, Option<String>) {
+    match state_key.rsplit_once('@') {
+        Some((state_key, locale)) => (state_key, Some(locale.to_string())),
+        None => (state_key, None),
+    }
 }
 
 impl PredictionPattern {
@@ -160,6 +420,158 @@ pub struct PredictorConfig {
     pub max_memory_bytes: usize,
     /// Eviction policy when limits are reached
     pub eviction_policy: EvictionPolicy,
+    /// When learn() treats a new observation as another sighting of an existing pattern
+    #[serde(default)]
+    pub similarity: PatternSimilarity,
+    /// When to stop predicting patterns that keep missing
+    #[serde(default)]
+    pub negative: NegativeCaching,
+    /// Window for ignoring repeats of an identical transition
+    #[serde(default)]
+    pub duplicates: DuplicateSuppression,
+    /// Which locales templates are learned and used for
+    #[serde(default)]
+    pub localization: Localization,
+}
+
+/// Localized text: which locales templates are learned and used for
+///
+/// Templates and patterns are always kept per StateChange locale, since the same
+/// state renders different strings in each. With `require_locale`, a change whose
+/// locale is missing (or not in `locales`, when that's non-empty) is learned
+/// literally: no runtime template is extracted and no template predicts it, only
+/// concrete patterns of that locale-less bucket.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Localization {
+    pub require_locale: bool,
+    /// Known locales (empty = any)
+    pub locales: Vec<String>,
+}
+
+impl Localization {
+    /// Whether templates may be learned and used for text in `locale`
+    pub fn templates_allowed(&self, locale: Option<&str>) -> bool {
+        if !self.require_locale {
+            return true;
+        }
+        locale.is_some_and(|locale| self.locales.is_empty() || self.locales.iter().any(|known| known == locale))
+    }
+}
+
+/// Deduplication of identical state transitions (double-clicks, render loops)
+///
+/// A change with the same component, key, old and new value as the last one learned
+/// for that key, arriving within `window_ms` of it, is neither learned nor predicted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DuplicateSuppression {
+    /// 0 disables deduplication
+    pub window_ms: u64,
+}
+
+/// Negative learning: stop predicting a component/key/pattern type after repeated misses
+///
+/// After `max_consecutive_misses` incorrect verifications in a row, predictions for that
+/// (component, state key, pattern type) are withheld for `cooldown_secs`; then they're
+/// tried again. A correct verification resets the streak.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NegativeCaching {
+    /// 0 disables suppression
+    pub max_consecutive_misses: u32,
+    pub cooldown_secs: u64,
+}
+
+impl Default for NegativeCaching {
+    fn default() -> Self {
+        Self { max_consecutive_misses: 3, cooldown_secs: 60 }
+    }
+}
+
+/// Similarity metric for merging near-identical observations in learn()
+///
+/// The score is a weighted mean of path overlap (Jaccard over patch paths), patch-kind
+/// multiset overlap and 1 - normalized edit distance between the patch-kind sequences.
+/// A threshold of 1.0 keeps exact matching: same length, same kinds in the same order.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PatternSimilarity {
+    /// Merge when the score reaches this (0.0 - 1.0)
+    pub threshold: f32,
+    pub path_weight: f32,
+    pub kind_weight: f32,
+    pub edit_weight: f32,
+}
+
+impl Default for PatternSimilarity {
+    fn default() -> Self {
+        Self { threshold: 1.0, path_weight: 0.4, kind_weight: 0.3, edit_weight: 0.3 }
+    }
+}
+
+impl PatternSimilarity {
+    /// Score two patch lists (1.0 = same paths and kinds in the same order)
+    pub fn score(&self, a: &[Patch], b: &[Patch]) -> f32 {
+        let total = self.path_weight + self.kind_weight + self.edit_weight;
+        if total <= 0.0 {
+            return 0.0;
+        }
+        let paths = |patches: &[Patch]| patches.iter().map(|p| p.path().clone()).collect::<std::collections::HashSet<_>>();
+        let kinds = |patches: &[Patch]| patches.iter().map(Patch::kind).collect::<Vec<_>>();
+        let (kinds_a, kinds_b) = (kinds(a), kinds(b));
+
+        (self.path_weight * jaccard(&paths(a), &paths(b))
+            + self.kind_weight * multiset_overlap(&kinds_a, &kinds_b)
+            + self.edit_weight * (1.0 - normalized_edit_distance(&kinds_a, &kinds_b)))
+            / total
+    }
+
+    /// Whether two observations count as the same pattern
+    pub fn matches(&self, a: &[Patch], b: &[Patch]) -> bool {
+        if self.threshold >= 1.0 {
+            return a.len() == b.len()
+                && a.iter().zip(b).all(|(p1, p2)| std::mem::discriminant(p1) == std::mem::discriminant(p2));
+        }
+        self.score(a, b) >= self.threshold
+    }
+}
+
+fn jaccard<T: Eq + std::hash::Hash>(a: &std::collections::HashSet<T>, b: &std::collections::HashSet<T>) -> f32 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(b).count() as f32 / union as f32
+}
+
+fn multiset_overlap(a: &[&str], b: &[&str]) -> f32 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let mut remaining = b.to_vec();
+    let shared = a.iter().filter(|kind| {
+        remaining.iter().position(|other| other == *kind).map(|i| remaining.swap_remove(i)).is_some()
+    }).count();
+    shared as f32 / a.len().max(b.len()) as f32
+}
+
+fn normalized_edit_distance(a: &[&str], b: &[&str]) -> f32 {
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 0.0;
+    }
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(x != y);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()] as f32 / longest as f32
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -181,6 +593,10 @@ impl Default for PredictorConfig {
             max_state_keys: 1000,
             max_memory_bytes: 100 * 1024 * 1024, // 100 MB
             eviction_policy: EvictionPolicy::LeastFrequentlyUsed,
+            similarity: PatternSimilarity::default(),
+            negative: NegativeCaching::default(),
+            duplicates: DuplicateSuppression::default(),
+            localization: Localization::default(),
         }
     }
 }
@@ -194,9 +610,20 @@ impl Predictor {
     /// Create a new predictor with custom config
     pub fn with_config(config: PredictorConfig) -> Self {
         Self {
-            patterns: HashMap::new(),
-            template_predictions: HashMap::new(),
+            patterns: SeededHashMap::default(),
+            template_predictions: SeededHashMap::default(),
             config,
+            capabilities: None,
+            heuristic: ProvenanceStats::default(),
+            clock: crate::clock::clock(),
+            suppressions: SeededHashMap::default(),
+            suppressed_predictions: 0,
+            recent_transitions: SeededHashMap::default(),
+            duplicate_learns: 0,
+            duplicate_predictions: 0,
+            code_versions: SeededHashMap::default(),
+            stale_code_predictions: 0,
+            stale_patterns_collected: 0,
         }
     }
 
@@ -246,7 +673,7 @@ impl Predictor {
 
         // PHASE 8: Try reorder template first (for ReorderChildren patches)
         if new_patches.len() == 1 {
-            if let Patch::ReorderChildren { path, order } = &new_patches[0] {
+            if let Patch::ReorderChildren { path, order, .. } = &new_patches[0] {
                 if let Some(reorder_template) = crate::reorder_detection::infer_ordering_rule(
                     &state_change.old_value,
                     &state_change.new_value,
@@ -274,10 +701,10 @@ impl Predictor {
 
         // PHASE 5: Try structural template (for Replace patches with boolean/enum state changes)
         if old_patches.len() == 1 && new_patches.len() == 1 {
-            if let Patch::Replace { path, node: new_node } = &new_patches[0] {
+            if let Patch::Replace { path, node: new_node, .. } = &new_patches[0] {
                 // Find the old node from old tree at this path
                 // For now, we'll extract from the patch itself if it's a Replace
-                if let Patch::Replace { path: old_path, node: old_node } = &old_patches[0] {
+                if let Patch::Replace { path: old_path, node: old_node, .. } = &old_patches[0] {
                     if old_path == path {
                         // Try to extract structural template
                         use crate::structural_template_extraction::{StateChange as STStateChange, extract_structural_template};
@@ -326,6 +753,39 @@ impl Predictor {
                     }
                 }
 
+                // Counted nouns: "1 item" vs "2 items" needs the singular form too
+                if let Some(template_patch) = crate::pluralization::plural_template(
+                    &state_change.state_key,
+                    &state_change.old_value,
+                    &state_change.new_value,
+                    old_content,
+                    new_content
+                ) {
+                    crate::log_info!(
+                        "📐 Plural template extracted for {}::{}: '{}'",
+                        state_change.component_id,
+                        state_change.state_key,
+                        template_patch.template
+                    );
+                    return Some(vec![Patch::UpdateTextTemplate {
+                        path: old_path.clone(),
+                        template_patch,
+                    }]);
+                }
+
+                // Full state snapshot: bind every state value the content shows
+                if let Some(template_patch) = self.extract_state_template(
+                    state_change,
+                    old_content,
+                    new_content,
+                    all_state
+                ) {
+                    return Some(vec![Patch::UpdateTextTemplate {
+                        path: old_path.clone(),
+                        template_patch,
+                    }]);
+                }
+
                 // Phase 1: Simple single-variable template
                 let old_value_str = match &state_change.old_value {
                     Value::Number(n) => n.to_string(),
@@ -451,6 +911,90 @@ impl Predictor {
         None
     }
 
+    /// Extract a template binding every state value found in the content
+    ///
+    /// Uses the full state snapshot, so constant values next to the changed one
+    /// ("{done} of {total}") and nested values of object state ("{user.name}") become
+    /// bindings too. The template must reproduce new_content from the state after the
+    /// change and bind at least one value that changed.
+    ///
+    /// Example:
+    ///   state_change: done 3 → 4, all_state: { "done": 3, "total": 10 }
+    ///   old_content: "3 of 10 done", new_content: "4 of 10 done"
+    ///   → template: "{0} of {1} done", bindings: ["done", "total"]
+    fn extract_state_template(
+        &self,
+        state_change: &StateChange,
+        old_content: &str,
+        new_content: &str,
+        all_state: &HashMap<String, serde_json::Value>
+    ) -> Option<TemplatePatch> {
+        use crate::deep_state_traversal::flatten_state;
+        use crate::template_renderer::{format_value, render_template_patch};
+
+        let (old_state, new_state) = state_change.state_snapshots(all_state);
+        let old_state = flatten_state(&old_state);
+        let new_state = flatten_state(&new_state);
+        let changed = |path: &str| old_state.get(path) != new_state.get(path);
+
+        // Every occurrence of a primitive value; at one position prefer changed values, then longer ones
+        let mut found: Vec<(usize, bool, std::cmp::Reverse<usize>, &str)> = Vec::new();
+        for (path, value) in &old_state {
+            if value.is_object() || value.is_array() || value.is_null() {
+                continue;
+            }
+            let value_str = format_value(value);
+            if value_str.is_empty() {
+                continue;
+            }
+            for (position, _) in old_content.match_indices(value_str.as_str()) {
+                found.push((position, !changed(path), std::cmp::Reverse(value_str.len()), path));
+            }
+        }
+        found.sort();
+
+        let mut template = String::new();
+        let mut bindings = Vec::new();
+        let mut slots = Vec::new();
+        let mut end = 0;
+        for (position, _, std::cmp::Reverse(len), path) in found {
+            if position < end {
+                continue;
+            }
+            template.push_str(&old_content[end..position]);
+            template.push_str(&format!("{{{}}}", bindings.len()));
+            bindings.push(path.to_string());
+            slots.push(position);
+            end = position + len;
+        }
+        template.push_str(&old_content[end..]);
+
+        if !bindings.iter().any(|path| changed(path)) {
+            return None;
+        }
+
+        let template_patch = TemplatePatch {
+            template,
+            bindings,
+            bindings_with_transforms: None,
+            slots,
+            conditional_templates: None,
+            conditional_binding_index: None,
+        };
+        if render_template_patch(&template_patch, &new_state) != new_content {
+            return None;
+        }
+
+        crate::log_info!(
+            "📐 State template extracted for {}::{} with {} bindings: '{}'",
+            state_change.component_id,
+            state_change.state_key,
+            template_patch.bindings.len(),
+            template_patch.template
+        );
+        Some(template_patch)
+    }
+
     /// Extract multi-variable template by finding changed substrings
     /// Detects multiple variable changes in content like "Hello, {firstName} {lastName}!"
     ///
@@ -855,12 +1399,20 @@ impl Predictor {
             return None; // No new nodes created, not a loop pattern
         }
 
-        // 3. Try to extract item template from first created node
+        // 3. Try to extract item template from first created node, matched against
+        //    the items that were actually inserted (not whatever is first in the array)
+        let inserted: Vec<Value> = crate::array_diff::diff_arrays(&state_change.old_value, &state_change.new_value)
+            .unwrap_or_default()
+            .iter()
+            .flat_map(|edit| edit.inserted().iter().cloned())
+            .collect();
+        let template_items = if inserted.is_empty() { new_array.as_slice() } else { inserted.as_slice() };
+
         let (first_node, first_path) = created_nodes[0];
         let item_template = self.extract_item_template(
             first_node,
             &state_change.state_key,
-            new_array
+            template_items
         )?;
 
         // 4. Get parent path (path without last element)
@@ -908,7 +1460,7 @@ impl Predictor {
                 // Extract element template with child templates
                 self.extract_element_item_template(element, array_items)
             }
-            VNode::Null(_) => {
+            VNode::Null(_) | VNode::Lazy(_) => {
                 // Null nodes in templates are not supported in loop templates
                 // They should be filtered out before template extraction
                 None
@@ -1000,8 +1552,8 @@ impl Predictor {
                             children_templates.push(element_template);
                         }
                     }
-                    VNode::Null(_) => {
-                        // Null children are skipped in loop templates
+                    VNode::Null(_) | VNode::Lazy(_) => {
+                        // Null and unexpanded children are skipped in loop templates
                     }
                 }
             }
@@ -1154,6 +1706,7 @@ impl Predictor {
             }
             VNode::Text(_) => None,
             VNode::Null(_) => None,  // Null nodes don't match selectors
+            VNode::Lazy(_) => None,
         }
     }
 
@@ -1187,9 +1740,9 @@ impl Predictor {
             ))?;
 
         // Reset Instant fields to current time since they can't be serialized
-        let now = std::time::Instant::now();
+        let now = predictor.clock.now();
         for patterns in predictor.patterns.values_mut() {
-            for pattern in patterns.iter_mut() {
+            for pattern in Arc::make_mut(patterns).iter_mut() {
                 pattern.last_accessed = now;
                 pattern.created_at = now;
             }
@@ -1212,8 +1765,53 @@ impl Predictor {
         new_tree: &VNode,
         all_state: Option<&HashMap<String, serde_json::Value>>
     ) -> crate::error::Result<()> {
+        if !crate::strict::is_checking() {
+            return self.learn_once(state_change, old_tree, new_tree, all_state);
+        }
+        // Strict mode: learn again on a copy, then both must predict the same
+        let mut twin = self.clone();
+        let result = self.learn_once(state_change.clone(), old_tree, new_tree, all_state);
+        let twin_result = crate::strict::rerun(|| twin.learn_once(state_change.clone(), old_tree, new_tree, all_state));
+        let outcome = |predictor: &Predictor, result: &crate::error::Result<()>| {
+            let prediction = crate::strict::rerun(|| predictor.predict_readonly(&state_change, old_tree, None, None).0);
+            (result.as_ref().map_err(ToString::to_string).copied(), prediction)
+        };
+        crate::strict::check(
+            "learn",
+            || serde_json::json!({ "state_change": state_change, "old": old_tree, "new": new_tree, "all_state": all_state }),
+            &outcome(self, &result),
+            &outcome(&twin, &twin_result),
+        );
+        result
+    }
+
+    fn learn_once(
+        &mut self,
+        state_change: StateChange,
+        old_tree: &VNode,
+        new_tree: &VNode,
+        all_state: Option<&HashMap<String, serde_json::Value>>
+    ) -> crate::error::Result<()> {
+        let mut state_change = state_change;
+        if state_change.array_operation.is_none() {
+            state_change.array_operation = crate::array_diff::infer_array_operation(&state_change.old_value, &state_change.new_value);
+        }
         crate::log_debug!("Learning pattern for {}::{}", state_change.component_id, state_change.state_key);
 
+        if self.is_duplicate(&state_change) {
+            crate::log_debug!("Skipping repeat of {}::{}", state_change.component_id, state_change.state_key);
+            self.duplicate_learns += 1;
+            crate::metrics::METRICS.record_duplicate_suppressed(true);
+            return Ok(());
+        }
+        if self.config.duplicates.window_ms > 0 {
+            self.recent_transitions.insert(self.make_pattern_key(&state_change), RecentTransition {
+                old_value: state_change.old_value.clone(),
+                new_value: state_change.new_value.clone(),
+                at: self.clock.now(),
+            });
+        }
+
         let new_patches = match reconcile(old_tree, new_tree) {
             Ok(p) => p,
             Err(e) => {
@@ -1223,28 +1821,37 @@ impl Predictor {
         };
 
         // Try to extract template (supports single and multi-variable)
-        let old_patches = match reconcile(old_tree, old_tree) {
-            Ok(p) => p,
-            Err(_) => vec![],
+        // With a full state snapshot, compare against what each patch overwrites
+        let old_patches = match all_state {
+            Some(_) => Self::overwritten_by(old_tree, &new_patches),
+            None => reconcile(old_tree, old_tree).unwrap_or_default(),
         };
 
         // Use all_state if provided, otherwise create empty HashMap
         let empty_state = HashMap::new();
         let state_ref = all_state.unwrap_or(&empty_state);
 
-        if let Some(template_patches) = self.extract_template(&state_change, &old_patches, &new_patches, state_ref) {
+        let code_generation = self.code_generation(&state_change.component_id);
+        let template_patches = if self.config.localization.templates_allowed(state_change.locale.as_deref()) {
+            self.extract_template(&state_change, &old_patches, &new_patches, state_ref)
+        } else {
+            crate::log_debug!("Locale of {}::{} is unknown; learning literally", state_change.component_id, state_change.state_key);
+            None
+        };
+        if let Some(template_patches) = template_patches {
             // Store template prediction
             let pattern_key = self.make_pattern_key(&state_change);
             self.template_predictions.insert(
                 pattern_key.clone(),
-                TemplatePrediction {
+                Arc::new(TemplatePrediction {
                     state_key: state_change.state_key.clone(),
                     patches: template_patches,
                     source: TemplateSource::RuntimeExtracted,
                     usage_count: 0,
                     correct_count: 0,
                     incorrect_count: 0,
-                }
+                    code_generation,
+                })
             );
             crate::log_info!("📐 Runtime-extracted template prediction stored for {}", pattern_key);
             crate::metrics::METRICS.record_learn(false);
@@ -1253,47 +1860,56 @@ impl Predictor {
 
         // Fall back to concrete patch learning
         let patches = new_patches;
-        let pattern_key = self.make_pattern_key(&state_change);
+        let pattern_key = self.make_bucket_key(&state_change);
 
         // Check memory limits before adding new patterns
         self.enforce_memory_limits()?;
 
-        let patterns = self.patterns.entry(pattern_key.clone()).or_insert_with(Vec::new);
+        let patterns = Arc::make_mut(self.patterns.entry(pattern_key.clone()).or_default());
 
         // Detect pattern type
         let pattern_type = Self::detect_pattern_type(&state_change);
 
-        // Try to find existing similar pattern OF THE SAME TYPE
-        let existing_idx = patterns.iter().position(|p| {
-            p.pattern_type == pattern_type &&
-            p.patches.len() == patches.len() &&
-            p.patches.iter().zip(patches.iter()).all(|(p1, p2)| {
-                std::mem::discriminant(p1) == std::mem::discriminant(p2)
+        // Try to find existing similar pattern OF THE SAME TYPE (the closest one when fuzzy)
+        let similarity = self.config.similarity;
+        let existing_idx = patterns.iter()
+            .enumerate()
+            .filter(|(_, p)| p.pattern_type == pattern_type && similarity.matches(&p.patches, &patches))
+            .max_by(|(_, a), (_, b)| {
+                similarity.score(&a.patches, &patches).total_cmp(&similarity.score(&b.patches, &patches))
             })
-        });
+            .map(|(idx, _)| idx);
 
-        let now = std::time::Instant::now();
+        let now = self.clock.now();
+        let old_tree = SharedTree::from_vnode(old_tree);
+        let new_tree = SharedTree::from_vnode(new_tree);
 
         if let Some(idx) = existing_idx {
             // Increment observation count for this pattern
             patterns[idx].observation_count += 1;
-            patterns[idx].old_tree = Some(old_tree.clone());
-            patterns[idx].new_tree = Some(new_tree.clone());
+            if similarity.threshold < 1.0 {
+                // A near match: predict what was seen most recently
+                patterns[idx].patches = patches.clone();
+            }
+            patterns[idx].old_tree = Some(old_tree);
+            patterns[idx].new_tree = Some(new_tree);
             patterns[idx].last_accessed = now;
         } else {
             // Add new pattern
             patterns.push(PredictionPattern {
+                id: next_pattern_id(),
                 state_change_key: pattern_key.clone(),
                 pattern_type,
                 patches: patches.clone(),
                 observation_count: 1,
-                old_tree: Some(old_tree.clone()),
-                new_tree: Some(new_tree.clone()),
+                old_tree: Some(old_tree),
+                new_tree: Some(new_tree),
                 last_accessed: now,
                 created_at: now,
                 predictions_made: 0,
                 predictions_correct: 0,
                 predictions_incorrect: 0,
+                code_generation,
             });
 
             // Limit number of patterns per key
@@ -1318,6 +1934,25 @@ impl Predictor {
         Ok(())
     }
 
+    /// What each UpdateText / Replace patch overwrites in the old tree, as the same kind of patch
+    fn overwritten_by(old_tree: &VNode, patches: &[Patch]) -> Vec<Patch> {
+        patches
+            .iter()
+            .filter_map(|patch| match patch {
+                Patch::UpdateText { path, .. } => match old_tree.find_by_path(path)? {
+                    VNode::Text(text) => Some(Patch::UpdateText { path: path.clone(), content: text.content.clone() }),
+                    _ => None,
+                },
+                Patch::Replace { path, .. } => Some(Patch::Replace {
+                    path: path.clone(),
+                    node: old_tree.find_by_path(path)?.clone(),
+                    preserve: None,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
     /// Learn with Babel-generated component metadata (NEW!)
     /// Accepts compile-time loop templates and StateX projections from Babel plugin
     /// Falls back to runtime extraction if no Babel template available
@@ -1332,6 +1967,11 @@ impl Predictor {
         crate::log_debug!("Learning pattern for {}::{} (with metadata)", state_change.component_id, state_change.state_key);
 
         if let Some(meta) = metadata {
+            if let Some(code_hash) = &meta.code_hash {
+                self.set_code_hash(&meta.component_id, code_hash);
+            }
+            let code_generation = self.code_generation(&meta.component_id);
+
             // PRIORITY 1: Try StateX projections FIRST (highest accuracy - 100% coverage!)
             if meta.has_state_x_projections(&state_change.state_key) {
                 crate::log_info!("✨ Using Babel-generated StateX projections for {}", state_change.state_key);
@@ -1344,14 +1984,15 @@ impl Predictor {
                     let pattern_key = self.make_pattern_key(&state_change);
                     self.template_predictions.insert(
                         pattern_key.clone(),
-                        TemplatePrediction {
+                        Arc::new(TemplatePrediction {
                             state_key: state_change.state_key.clone(),
                             patches: projection_patches,
                             source: TemplateSource::BabelGenerated,
                             usage_count: 0,
                             correct_count: 0,
                             incorrect_count: 0,
-                        }
+                            code_generation,
+                        })
                     );
                     crate::log_info!("✅ StateX projection template stored for {}", pattern_key);
                     crate::metrics::METRICS.record_learn(false);
@@ -1366,7 +2007,7 @@ impl Predictor {
                 let pattern_key = self.make_pattern_key(&state_change);
                 self.template_predictions.insert(
                     pattern_key.clone(),
-                    TemplatePrediction {
+                    Arc::new(TemplatePrediction {
                         state_key: state_change.state_key.clone(),
                         patches: vec![Patch::UpdateListTemplate {
                             path: HexPath::root(), // Will be determined by reconciler
@@ -1376,7 +2017,8 @@ impl Predictor {
                         usage_count: 0,
                         correct_count: 0,
                         incorrect_count: 0,
-                    }
+                        code_generation,
+                    })
                 );
                 crate::log_info!("✅ Babel template stored for {}", pattern_key);
                 crate::metrics::METRICS.record_learn(false);
@@ -1389,6 +2031,109 @@ impl Predictor {
         self.learn(state_change, old_tree, new_tree, all_state)
     }
 
+    /// Pre-seed template predictions from a component's build-time templates
+    /// The first change of every bound state key then hits without an observation.
+    /// Templates already learned at runtime are kept; returns how many keys were seeded
+    pub fn seed_from_metadata(&mut self, metadata: &ComponentMetadata) -> usize {
+        if let Some(code_hash) = &metadata.code_hash {
+            self.set_code_hash(&metadata.component_id, code_hash);
+        }
+        let code_generation = self.code_generation(&metadata.component_id);
+        let mut state_keys: std::collections::BTreeSet<&str> = metadata.templates
+            .values()
+            .flat_map(|template| template.bindings.iter().map(String::as_str))
+            .collect();
+        state_keys.extend(metadata.loop_templates.keys().map(String::as_str));
+
+        let mut seeded = 0;
+        for state_key in state_keys {
+            let mut patches = Self::patches_for_state_key(state_key, metadata).unwrap_or_default();
+            if let Some(loop_template) = metadata.parse_loop_template(state_key) {
+                patches.push(Patch::UpdateListTemplate {
+                    path: HexPath::root(), // Will be determined by reconciler
+                    loop_template,
+                });
+            }
+            if patches.is_empty() {
+                continue;
+            }
+
+            let pattern_key = format!("{}::{}", metadata.component_id, state_key);
+            if matches!(self.template_predictions.get(&pattern_key), Some(existing) if existing.source != TemplateSource::BabelGenerated) {
+                continue;
+            }
+            self.template_predictions.insert(
+                pattern_key,
+                Arc::new(TemplatePrediction {
+                    state_key: state_key.to_string(),
+                    patches,
+                    source: TemplateSource::BabelGenerated,
+                    usage_count: 0,
+                    correct_count: 0,
+                    incorrect_count: 0,
+                    code_generation,
+                })
+            );
+            seeded += 1;
+        }
+
+        crate::log_info!("🌱 Seeded {} template predictions for {}", seeded, metadata.component_id);
+        seeded
+    }
+
+    /// Code hash of the component build the predictor last saw (None = never given one)
+    pub fn code_hash(&self, component_id: &str) -> Option<&str> {
+        self.code_versions.get(component_id).map(|version| version.code_hash.as_str())
+    }
+
+    fn code_generation(&self, component_id: &str) -> u32 {
+        self.code_versions.get(component_id).map_or(0, |version| version.generation)
+    }
+
+    /// Record the code hash of a component's current build
+    /// Patterns learned from any other build (or before hashes were given) are
+    /// dropped; returns how many
+    pub fn set_code_hash(&mut self, component_id: &str, code_hash: &str) -> usize {
+        if self.code_hash(component_id) == Some(code_hash) {
+            return 0;
+        }
+        crate::log_info!("Code of {} changed; dropping patterns learned from the old build", component_id);
+        let generation = self.code_generation(component_id) + 1;
+        self.code_versions.insert(component_id.to_string(), CodeVersion { code_hash: code_hash.to_string(), generation });
+        // Miss streaks of the old build say nothing about the new one
+        self.suppressions.retain(|key, _| key.split("::").next() != Some(component_id));
+        self.collect_stale_patterns()
+    }
+
+    /// Drop templates and patterns learned from a build of their component other
+    /// than the current one; returns how many were dropped
+    pub fn collect_stale_patterns(&mut self) -> usize {
+        let code_versions = &self.code_versions;
+        let is_stale = |key: &str, generation: u32| {
+            let component_id = key.split("::").next().unwrap_or(key);
+            generation != code_versions.get(component_id).map_or(0, |version| version.generation)
+        };
+
+        let mut collected = 0;
+        self.template_predictions.retain(|key, template_pred| {
+            let stale = is_stale(key, template_pred.code_generation);
+            collected += stale as usize;
+            !stale
+        });
+        self.patterns.retain(|key, patterns| {
+            if patterns.iter().any(|pattern| is_stale(key, pattern.code_generation)) {
+                let patterns = Arc::make_mut(patterns);
+                let before = patterns.len();
+                patterns.retain(|pattern| !is_stale(key, pattern.code_generation));
+                collected += before - patterns.len();
+            }
+            !patterns.is_empty()
+        });
+
+        self.stale_patterns_collected += collected;
+        collected
+    }
+
     /// Pre-compute patches for a hinted state change (for usePredictHint)
     /// This allows developers to explicitly tell the predictor what might happen next
     pub fn predict_hint(
@@ -1398,19 +2143,10 @@ impl Predictor {
         state_changes: Vec<StateChange>,
         current_tree: &VNode
     ) -> Option<Prediction> {
-        crate::log_info!("Processing hint '{}' for component {}", hint_id, component_id);
-
-        // For now, handle single state change hints
-        // Future: support multiple simultaneous state changes
-        if state_changes.len() != 1 {
-            crate::log_warn!("Multi-state hints not yet supported");
-            return None;
-        }
-
-        let state_change = &state_changes[0];
+        let state_change = Self::hint_state_change(hint_id, component_id, &state_changes)?;
 
         // Use the same prediction logic, but mark it as a hint
-        let mut prediction = self.predict(state_change, current_tree)?;
+        let prediction = self.predict(state_change, current_tree)?;
 
         // Add hint metadata
         crate::log_info!("Hint '{}' predicted {} patches with {:.2} confidence",
@@ -1419,6 +2155,23 @@ impl Predictor {
         Some(prediction)
     }
 
+    /// The state change a hint predicts for (None if unsupported)
+    pub(crate) fn hint_state_change<'a>(
+        hint_id: &str,
+        component_id: &str,
+        state_changes: &'a [StateChange],
+    ) -> Option<&'a StateChange> {
+        crate::log_info!("Processing hint '{}' for component {}", hint_id, component_id);
+
+        // For now, handle single state change hints
+        // Future: support multiple simultaneous state changes
+        if state_changes.len() != 1 {
+            crate::log_warn!("Multi-state hints not yet supported");
+            return None;
+        }
+        state_changes.first()
+    }
+
     /// Generate patches from ComponentMetadata templates (build-time templates from Babel)
     /// This provides 100% coverage from the start, no learning phase needed!
     fn generate_patches_from_metadata(
@@ -1426,8 +2179,13 @@ impl Predictor {
         state_change: &StateChange,
         metadata: &ComponentMetadata,
     ) -> Option<Vec<Patch>> {
+        Self::patches_for_state_key(&state_change.state_key, metadata)
+    }
+
+    /// Template patches for every build-time template bound to `state_key`
+    fn patches_for_state_key(state_key: &str, metadata: &ComponentMetadata) -> Option<Vec<Patch>> {
         // Find all templates that bind to this state key
-        let templates = metadata.get_templates_for_state(&state_change.state_key);
+        let templates = metadata.get_templates_for_state(state_key);
 
         if templates.is_empty() {
             return None;
@@ -1504,6 +2262,40 @@ impl Predictor {
         self.predict_with_metadata(state_change, current_tree, None)
     }
 
+    /// Predict patches for a state change given the component's full state
+    ///
+    /// `full_state` is the state snapshot; the change is applied to it. Template
+    /// patches whose bindings all resolve against it (nested paths included) are
+    /// rendered into concrete patches.
+    pub fn predict_with_state(
+        &mut self,
+        state_change: &StateChange,
+        full_state: &StateValues,
+        current_tree: &VNode,
+    ) -> Option<Prediction> {
+        let (prediction, used) = self.predict_readonly(state_change, current_tree, None, Some(full_state));
+        if let Some(used) = used {
+            self.record_use(&used);
+        }
+        prediction
+    }
+
+    /// Use `clock` for pattern ages (e.g. a MockClock in tests)
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Declare which patch kinds the client supports
+    /// Predictions are downgraded to concrete patches the client understands
+    pub fn set_capabilities(&mut self, capabilities: Option<ClientCapabilities>) {
+        self.capabilities = capabilities;
+    }
+
+    /// Patch kinds the client supports (None = everything)
+    pub fn capabilities(&self) -> Option<&ClientCapabilities> {
+        self.capabilities.as_ref()
+    }
+
     /// Predict patches with optional ComponentMetadata (for build-time templates)
     pub fn predict_with_metadata(
         &mut self,
@@ -1511,12 +2303,179 @@ impl Predictor {
         current_tree: &VNode,
         metadata: Option<&ComponentMetadata>,
     ) -> Option<Prediction> {
-        let start = std::time::Instant::now();
-        let pattern_key = self.make_pattern_key(state_change);
+        let (prediction, used) = self.predict_readonly(state_change, current_tree, metadata, None);
+        if let Some(used) = used {
+            self.record_use(&used);
+        }
+        prediction
+    }
+
+    /// Predict without touching the predictor
+    /// Usage counters aren't updated; the returned PredictionUse must be passed
+    /// to `record_use` (possibly on a later version of the predictor)
+    /// With `full_state`, templates are rendered as in `predict_with_state`
+    pub(crate) fn predict_readonly(
+        &self,
+        state_change: &StateChange,
+        current_tree: &VNode,
+        metadata: Option<&ComponentMetadata>,
+        full_state: Option<&StateValues>,
+    ) -> (Option<Prediction>, Option<PredictionUse>) {
+        let (prediction, used) = self.predict_once(state_change, current_tree, metadata, full_state);
+        if crate::strict::is_checking() {
+            let (again, _) = crate::strict::rerun(|| self.predict_once(state_change, current_tree, metadata, full_state));
+            crate::strict::check(
+                "predict",
+                || serde_json::json!({ "state_change": state_change, "tree": current_tree, "full_state": full_state }),
+                &prediction,
+                &again,
+            );
+        }
+        (prediction, used)
+    }
+
+    fn predict_once(
+        &self,
+        state_change: &StateChange,
+        current_tree: &VNode,
+        metadata: Option<&ComponentMetadata>,
+        full_state: Option<&StateValues>,
+    ) -> (Option<Prediction>, Option<PredictionUse>) {
+        let state_change = &*state_change.with_array_operation();
+        if self.is_suppressed(state_change) {
+            crate::log_debug!("Predictions for {}::{} are suppressed", state_change.component_id, state_change.state_key);
+            return (None, Some(PredictionUse::Suppressed));
+        }
+        if self.is_duplicate(state_change) {
+            crate::log_debug!("Not predicting repeat of {}::{}", state_change.component_id, state_change.state_key);
+            return (None, Some(PredictionUse::Duplicate));
+        }
+
+        let (prediction, used) = self.predict_for_any_client(state_change, current_tree, metadata);
+        let Some(mut prediction) = prediction else { return (None, used) };
+        prediction.predicted_patches = crate::routing::with_navigation(state_change, prediction.predicted_patches);
+
+        // Without a snapshot, templates can only be materialized from the changed state value
+        let state = match full_state {
+            Some(full_state) => {
+                let state = crate::deep_state_traversal::flatten_state(&state_change.state_snapshots(full_state).1);
+                prediction.predicted_patches = materialize_templates(prediction.predicted_patches, &state);
+                state
+            }
+            None => HashMap::from([(state_change.state_key.clone(), state_change.new_value.clone())]),
+        };
+
+        if let Some(capabilities) = &self.capabilities {
+            prediction.predicted_patches = negotiate_patches(prediction.predicted_patches, capabilities, &state);
+
+            if prediction.predicted_patches.is_empty() {
+                crate::log_debug!("Prediction for '{}' has nothing the client can apply", state_change.state_key);
+                return (None, used);
+            }
+        }
+
+        (Some(prediction), used)
+    }
+
+    /// Apply the usage counters of a prediction made by `predict_readonly`
+    /// Uses of patterns that have since been evicted are ignored
+    pub(crate) fn record_use(&mut self, used: &PredictionUse) {
+        match used {
+            PredictionUse::Template { pattern_key } => {
+                if let Some(template_pred) = self.template_predictions.get_mut(pattern_key).map(Arc::make_mut) {
+                    template_pred.usage_count += 1;
+                }
+            }
+            PredictionUse::Pattern { pattern_key, id } => {
+                let Some(patterns) = self.patterns.get_mut(pattern_key) else { return };
+                // Only unshare the list if the pattern is still in it
+                if let Some(index) = patterns.iter().position(|pattern| pattern.id == *id) {
+                    Arc::make_mut(patterns)[index].predictions_made += 1;
+                }
+            }
+            PredictionUse::Heuristic => self.heuristic.predictions += 1,
+            PredictionUse::Suppressed => self.suppressed_predictions += 1,
+            PredictionUse::Duplicate => {
+                self.duplicate_predictions += 1;
+                crate::metrics::METRICS.record_duplicate_suppressed(false);
+            }
+            PredictionUse::StaleCode => self.stale_code_predictions += 1,
+        }
+    }
+
+    /// Whether this exact transition was learned for its key within the duplicate window
+    fn is_duplicate(&self, state_change: &StateChange) -> bool {
+        let window = self.config.duplicates.window_ms;
+        if window == 0 {
+            return false;
+        }
+        let now = self.clock.now();
+        self.recent_transitions.get(&self.make_pattern_key(state_change)).is_some_and(|recent| {
+            recent.old_value == state_change.old_value
+                && recent.new_value == state_change.new_value
+                && now.saturating_duration_since(recent.at) < std::time::Duration::from_millis(window)
+        })
+    }
+
+    fn suppression_key(&self, state_change: &StateChange) -> String {
+        format!("{}::{:?}", self.make_bucket_key(state_change), Self::detect_pattern_type(state_change))
+    }
+
+    /// Whether predictions for this change are withheld after repeated misses
+    fn is_suppressed(&self, state_change: &StateChange) -> bool {
+        let now = self.clock.now();
+        self.suppressions
+            .get(&self.suppression_key(state_change))
+            .and_then(|suppression| suppression.until)
+            .is_some_and(|until| now < until)
+    }
+
+    /// Track a verification result for negative learning
+    fn record_verification(&mut self, state_change: &StateChange, correct: bool) {
+        let limit = self.config.negative.max_consecutive_misses;
+        if limit == 0 {
+            return;
+        }
+        let key = self.suppression_key(state_change);
+        let now = self.clock.now();
+        let cooldown = std::time::Duration::from_secs(self.config.negative.cooldown_secs);
+
+        let suppression = self.suppressions.entry(key.clone()).or_default();
+        if correct {
+            suppression.consecutive_misses = 0;
+            return;
+        }
+        suppression.consecutive_misses += 1;
+        if suppression.consecutive_misses >= limit {
+            suppression.consecutive_misses = 0;
+            suppression.until = Some(now + cooldown);
+            crate::log_warn!("{} missed {} times in a row; not predicting it for {:?}", key, limit, cooldown);
+        }
+    }
+
+    /// Predict patches without regard to client capabilities
+    fn predict_for_any_client(
+        &self,
+        state_change: &StateChange,
+        current_tree: &VNode,
+        metadata: Option<&ComponentMetadata>,
+    ) -> (Option<Prediction>, Option<PredictionUse>) {
+        let start = std::time::Instant::now();
+        let pattern_key = self.make_pattern_key(state_change);
+
+        // Patterns from another build of the component would predict for code that's gone
+        let newer_build = metadata
+            .and_then(|meta| meta.code_hash.as_deref())
+            .is_some_and(|hash| self.code_hash(&state_change.component_id) != Some(hash));
+        let generation = self.code_generation(&state_change.component_id);
+        let is_stale = |code_generation: u32| newer_build || code_generation != generation;
+
+        // Templates render one locale's strings; with an unknown locale only patterns predict
+        let templates_allowed = self.config.localization.templates_allowed(state_change.locale.as_deref());
 
         // FIRST: Try build-time templates from Babel (if metadata provided)
         // This gives us 100% coverage from the start!
-        if let Some(meta) = metadata {
+        if let Some(meta) = metadata.filter(|_| templates_allowed) {
             if let Some(patches) = self.generate_patches_from_metadata(state_change, meta) {
                 crate::log_info!(
                     "📐 Generated {} patches from build-time templates for state key '{}'",
@@ -1526,35 +2485,40 @@ impl Predictor {
 
                 crate::metrics::METRICS.record_prediction(start.elapsed(), true);
 
-                return Some(Prediction {
+                return (Some(Prediction {
                     state_change: state_change.clone(),
                     predicted_patches: patches,
                     confidence: 1.0, // 100% confidence - these are build-time extracted!
                     predicted_tree: None,
-                });
+                    provenance: Provenance::CompileTime,
+                }), None);
             }
         }
 
         // FALLBACK: Try learned template predictions (runtime extraction)
-        if let Some(template_pred) = self.template_predictions.get_mut(&pattern_key) {
-            template_pred.usage_count += 1;
+        if let Some(template_pred) = self.template_predictions.get(&pattern_key).filter(|_| templates_allowed) {
+            if is_stale(template_pred.code_generation) {
+                crate::log_debug!("Template for {} was learned from other code", pattern_key);
+                return (None, Some(PredictionUse::StaleCode));
+            }
             let confidence = template_pred.hit_rate();
 
             if confidence >= self.config.min_confidence {
                 crate::log_info!(
                     "📐 Template prediction with {:.2} confidence (used {} times)",
                     confidence,
-                    template_pred.usage_count
+                    template_pred.usage_count + 1
                 );
 
                 crate::metrics::METRICS.record_prediction(start.elapsed(), true);
 
-                return Some(Prediction {
+                return (Some(Prediction {
                     state_change: state_change.clone(),
                     predicted_patches: template_pred.patches.clone(),
                     confidence,
                     predicted_tree: None, // Templates don't store trees
-                });
+                    provenance: template_pred.source.provenance(),
+                }), Some(PredictionUse::Template { pattern_key }));
             }
         }
 
@@ -1562,12 +2526,18 @@ impl Predictor {
         let requested_pattern_type = Self::detect_pattern_type(state_change);
 
         // Try learned patterns first
-        if let Some(patterns) = self.patterns.get_mut(&pattern_key) {
+        let pattern_key = self.make_bucket_key(state_change);
+        if let Some(patterns) = self.patterns.get(&pattern_key) {
             crate::log_debug!("Predicting for {}::{}, found {} patterns, looking for {:?}",
                              state_change.component_id, state_change.state_key, patterns.len(), requested_pattern_type);
 
+            if patterns.iter().any(|pattern| is_stale(pattern.code_generation)) {
+                crate::log_debug!("Patterns for {} were learned from other code", pattern_key);
+                return (None, Some(PredictionUse::StaleCode));
+            }
+
             // Find patterns matching the requested type
-            let matching_indices: Vec<usize> = patterns.iter()
+            let matching_indices: SmallVec<[usize; 4]> = patterns.iter()
                 .enumerate()
                 .filter(|(_, p)| p.pattern_type == requested_pattern_type)
                 .map(|(idx, _)| idx)
@@ -1575,8 +2545,8 @@ impl Predictor {
 
             if !matching_indices.is_empty() {
                 // Find the most observed pattern of the matching type
-                let best_idx = *matching_indices.iter()
-                    .max_by_key(|&&idx| patterns[idx].observation_count)?;
+                let Some(&best_idx) = matching_indices.iter()
+                    .max_by_key(|&&idx| patterns[idx].observation_count) else { return (None, None) };
 
                 // Calculate confidence based on observation frequency
                 let total_observations: usize = matching_indices.iter()
@@ -1588,19 +2558,18 @@ impl Predictor {
                     crate::log_info!("Learned prediction with confidence {:.2} ({} observations)",
                                     confidence, patterns[best_idx].observation_count);
 
-                    patterns[best_idx].predictions_made += 1;
-
                     let predicted_patches = Self::adapt_patches(&patterns[best_idx].patches, current_tree);
-                    let predicted_tree = patterns[best_idx].new_tree.clone();
+                    let predicted_tree = patterns[best_idx].new_tree.as_ref().map(SharedTree::to_vnode);
 
                     crate::metrics::METRICS.record_prediction(start.elapsed(), true);
 
-                    return Some(Prediction {
+                    return (Some(Prediction {
                         state_change: state_change.clone(),
                         predicted_patches,
                         confidence,
                         predicted_tree,
-                    });
+                        provenance: Provenance::Learned,
+                    }), Some(PredictionUse::Pattern { pattern_key, id: patterns[best_idx].id }));
                 }
             }
         }
@@ -1611,11 +2580,11 @@ impl Predictor {
 
         if builtin_prediction.is_some() {
             crate::metrics::METRICS.record_prediction(start.elapsed(), true);
-        } else {
-            crate::metrics::METRICS.record_prediction(start.elapsed(), false);
+            return (builtin_prediction, Some(PredictionUse::Heuristic));
         }
 
-        builtin_prediction
+        crate::metrics::METRICS.record_prediction(start.elapsed(), false);
+        (None, None)
     }
 
     /// Predict patches using built-in knowledge of common patterns
@@ -1627,6 +2596,9 @@ impl Predictor {
     ) -> Option<Prediction> {
         use serde_json::Value;
 
+        // Widgets recognized by tag/ARIA semantics (aria-expanded, progress, ...)
+        let mut patches = crate::widget_heuristics::predict_widgets(state_change, current_tree).unwrap_or_default();
+
         match pattern_type {
             PatternType::NumericIncrement | PatternType::NumericDecrement => {
                 // Predict text content will change to show new number
@@ -1634,29 +2606,31 @@ impl Predictor {
                     let new_text = new_val.to_string();
 
                     // Try to find text nodes in the tree that might contain the old value
-                    if let Some(patches) = Self::find_and_replace_number_text(current_tree, state_change, &new_text) {
-                        crate::log_info!("Built-in prediction for {:?}: {} patch(es)", pattern_type, patches.len());
-                        return Some(Prediction {
-                            state_change: state_change.clone(),
-                            predicted_patches: patches,
-                            confidence: 0.85, // High confidence for simple numeric changes
-                            predicted_tree: None,
-                        });
+                    if let Some(text_patches) = Self::find_and_replace_number_text(current_tree, state_change, &new_text) {
+                        patches.extend(text_patches);
                     }
                 }
             }
             PatternType::BooleanToggle => {
-                // Predict checkbox checked state or class changes
-                crate::log_info!("Built-in prediction for BooleanToggle (simplified)");
-                // For now, return None - would need more context to predict boolean changes
-                // Future: analyze tree for checkboxes, conditional classes, etc.
+                // Checkboxes and conditional classes need learned patterns; ARIA widgets are covered above
             }
             PatternType::Literal => {
                 // No built-in prediction for arbitrary changes
             }
         }
 
-        None
+        if patches.is_empty() {
+            return None;
+        }
+
+        crate::log_info!("Built-in prediction for {:?}: {} patch(es)", pattern_type, patches.len());
+        Some(Prediction {
+            state_change: state_change.clone(),
+            predicted_patches: patches,
+            confidence: 0.85, // High confidence for simple numeric changes and ARIA widgets
+            predicted_tree: None,
+            provenance: Provenance::Heuristic,
+        })
     }
 
     /// Find text nodes containing the old value and predict UpdateText patches
@@ -1695,8 +2669,8 @@ impl Predictor {
             VNode::Text(text_node) => {
                 // Check if text contains the old value
                 if text_node.content.contains(old_text) {
-                    // Replace old value with new value in the text
-                    let new_content = text_node.content.replace(old_text, new_text);
+                    // Replace old value with new value in the text (and the noun after it, "1 item" → "2 items")
+                    let new_content = crate::pluralization::recount(&text_node.content, old_text, new_text);
                     patches.push(Patch::UpdateText {
                         path: path.clone(),
                         content: new_content,
@@ -1712,8 +2686,8 @@ impl Predictor {
                     }
                 }
             }
-            VNode::Null(_) => {
-                // Null nodes have no text content
+            VNode::Null(_) | VNode::Lazy(_) => {
+                // Null and unexpanded nodes have no text content
             }
         }
     }
@@ -1727,12 +2701,23 @@ impl Predictor {
         actual_tree: &VNode
     ) -> crate::error::Result<bool> {
         let pattern_key = self.make_pattern_key(state_change);
+        let matches = Self::trees_match(predicted_tree, actual_tree);
+        self.record_verification(state_change, matches);
 
-        if let Some(patterns) = self.patterns.get_mut(&pattern_key) {
+        // Predictions come from a template first, then learned patterns, then heuristics
+        if let Some(template_pred) = self.template_predictions.get_mut(&pattern_key).map(Arc::make_mut) {
+            if matches {
+                template_pred.correct_count += 1;
+            } else {
+                template_pred.incorrect_count += 1;
+                crate::log_warn!("{:?} template for {} predicted incorrectly", template_pred.source, pattern_key);
+            }
+            return Ok(matches);
+        }
+
+        if let Some(patterns) = self.patterns.get_mut(&self.make_bucket_key(state_change)).map(Arc::make_mut) {
             // Find the pattern that was likely used for prediction
             if let Some(pattern) = patterns.iter_mut().max_by_key(|p| p.observation_count) {
-                let matches = Self::trees_match(predicted_tree, actual_tree);
-
                 if matches {
                     pattern.predictions_correct += 1;
                     crate::log_debug!("Prediction verified as CORRECT for {}::{}",
@@ -1743,14 +2728,17 @@ impl Predictor {
                                      state_change.component_id, state_change.state_key);
                 }
 
-                Ok(matches)
-            } else {
-                Ok(false)
+                return Ok(matches);
             }
+        }
+
+        // No stored pattern: the prediction was a built-in heuristic
+        if matches {
+            self.heuristic.correct += 1;
         } else {
-            // Pattern not found
-            Ok(false)
+            self.heuristic.incorrect += 1;
         }
+        Ok(matches)
     }
 
     /// Check if two VNode trees match (deep equality)
@@ -1765,7 +2753,21 @@ impl Predictor {
 
     /// Create a pattern key from a state change
     fn make_pattern_key(&self, state_change: &StateChange) -> String {
-        format!("{}::{}", state_change.component_id, state_change.state_key)
+        match &state_change.locale {
+            Some(locale) => format!("{}::{}@{}", state_change.component_id, state_change.state_key, locale),
+            None => format!("{}::{}", state_change.component_id, state_change.state_key),
+        }
+    }
+
+    /// Key of the learned-pattern bucket for a state change
+    /// Object/array changes add the shape of the change, so editing `user.name` and
+    /// adding `user.tags` learn separately
+    fn make_bucket_key(&self, state_change: &StateChange) -> String {
+        let key = self.make_pattern_key(state_change);
+        match state_change.value_diff() {
+            Some(diff) if !diff.is_empty() => format!("{}::{}", key, diff.signature()),
+            _ => key,
+        }
     }
 
     /// Check if two patch lists are similar (for pattern matching)
@@ -1789,6 +2791,7 @@ impl Predictor {
 
     /// Get statistics about learned patterns
     pub fn stats(&self) -> PredictorStats {
+        let now = self.clock.now();
         let total_patterns: usize = self.patterns.values().map(|v| v.len()).sum();
         let total_observations: usize = self.patterns.values()
             .flat_map(|patterns| patterns.iter().map(|p| p.observation_count))
@@ -1828,6 +2831,24 @@ impl Predictor {
             0.0
         };
 
+        let seeded_patterns = self.template_predictions.values()
+            .filter(|t| t.source == TemplateSource::BabelGenerated)
+            .count();
+
+        let mut provenance = ProvenanceBreakdown {
+            heuristic: self.heuristic.clone(),
+            ..Default::default()
+        };
+        provenance.learned.add(total_patterns, total_predictions, correct_predictions, incorrect_predictions);
+        for template_pred in self.template_predictions.values() {
+            provenance.get_mut(template_pred.source.provenance()).add(
+                1,
+                template_pred.usage_count,
+                template_pred.correct_count,
+                template_pred.incorrect_count,
+            );
+        }
+
         PredictorStats {
             unique_state_keys: self.patterns.len(),
             total_patterns,
@@ -1840,13 +2861,138 @@ impl Predictor {
             avg_confidence,
             active_patterns: total_patterns,
             prediction_hits: correct_predictions,
+            seeded_patterns,
+            learned_patterns: total_patterns + self.template_predictions.len() - seeded_patterns,
+            provenance,
+            suppressed_predictions: self.suppressed_predictions,
+            suppressed_patterns: self.suppressions.values()
+                .filter(|suppression| suppression.until.is_some_and(|until| now < until))
+                .count(),
+            duplicate_learns: self.duplicate_learns,
+            duplicate_predictions: self.duplicate_predictions,
+            stale_code_predictions: self.stale_code_predictions,
+            stale_patterns_collected: self.stale_patterns_collected,
         }
     }
 
+    /// Template patches held for a component, with their provenance
+    pub fn template_patches(&self, component_id: &str) -> Vec<(Provenance, &Patch)> {
+        let prefix = format!("{}::", component_id);
+        self.template_predictions
+            .iter()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .flat_map(|(_, template_pred)| template_pred.patches.iter().map(|patch| (template_pred.source.provenance(), patch)))
+            .collect()
+    }
+
+    /// Every template and learned pattern with its provenance and accuracy, sorted by key
+    pub fn inspect_patterns(&self) -> Vec<PatternSummary> {
+        let templates = self.template_predictions.iter().map(|(key, template_pred)| PatternSummary {
+            pattern_key: key.clone(),
+            kind: "template".to_string(),
+            provenance: template_pred.source.provenance(),
+            template_source: Some(template_pred.source),
+            pattern_type: None,
+            patch_count: template_pred.patches.len(),
+            observations: 0,
+            predictions: template_pred.usage_count,
+            correct: template_pred.correct_count,
+            incorrect: template_pred.incorrect_count,
+            hit_rate: template_pred.hit_rate(),
+        });
+        let patterns = self.patterns.iter().flat_map(|(key, patterns)| {
+            patterns.iter().map(move |pattern| PatternSummary {
+                pattern_key: key.clone(),
+                kind: "pattern".to_string(),
+                provenance: Provenance::Learned,
+                template_source: None,
+                pattern_type: Some(pattern.pattern_type),
+                patch_count: pattern.patches.len(),
+                observations: pattern.observation_count,
+                predictions: pattern.predictions_made,
+                correct: pattern.predictions_correct,
+                incorrect: pattern.predictions_incorrect,
+                hit_rate: pattern.hit_rate(),
+            })
+        });
+
+        let mut summaries: Vec<PatternSummary> = templates.chain(patterns).collect();
+        summaries.sort_by(|a, b| a.pattern_key.cmp(&b.pattern_key).then_with(|| a.kind.cmp(&b.kind)));
+        summaries
+    }
+
+    /// The component's templates and learned patterns that meet `options.min_confidence`,
+    /// as a bundle the client can cache (patterns from an older build are left out)
+    pub fn export_bundle(&self, component_id: &str, options: &BundleOptions) -> PredictionBundle {
+        let prefix = format!("{}::", component_id);
+        let generation = self.code_generation(component_id);
+        let mut entries = Vec::new();
+
+        for (key, template_pred) in &self.template_predictions {
+            if !key.starts_with(&prefix) || template_pred.code_generation != generation {
+                continue;
+            }
+            let confidence = template_pred.hit_rate();
+            if confidence >= options.min_confidence {
+                let (_, locale) = split_locale(&key[prefix.len()..]);
+                entries.push(BundleEntry {
+                    state_key: template_pred.state_key.clone(),
+                    locale,
+                    pattern_type: None,
+                    changed_fields: None,
+                    patches: template_pred.patches.clone(),
+                    confidence,
+                    provenance: template_pred.source.provenance(),
+                    base_checksum: None,
+                });
+            }
+        }
+
+        for (key, patterns) in &self.patterns {
+            let Some(bucket) = key.strip_prefix(&prefix) else { continue };
+            let (state_key, changed_fields) = match bucket.split_once("::") {
+                Some((state_key, signature)) => (state_key, Some(signature.to_string())),
+                None => (bucket, None),
+            };
+            let (state_key, locale) = split_locale(state_key);
+            for pattern in patterns.iter().filter(|pattern| pattern.code_generation == generation) {
+                // Same confidence as predict: share of the observations of its type
+                let total: usize = patterns.iter()
+                    .filter(|p| p.pattern_type == pattern.pattern_type)
+                    .map(|p| p.observation_count)
+                    .sum();
+                let confidence = pattern.observation_count as f32 / total.max(1) as f32;
+                if confidence < options.min_confidence {
+                    continue;
+                }
+                entries.push(BundleEntry {
+                    state_key: state_key.to_string(),
+                    locale: locale.clone(),
+                    pattern_type: Some(pattern.pattern_type),
+                    changed_fields: changed_fields.clone(),
+                    patches: pattern.patches.clone(),
+                    confidence,
+                    provenance: Provenance::Learned,
+                    base_checksum: pattern.old_tree.as_ref()
+                        .map(|tree| checksum_to_hex(tree_checksum(&tree.to_vnode()))),
+                });
+            }
+        }
+
+        // Stable order, so the same predictions give the same bundle checksum
+        entries.sort_by(|a, b| {
+            (&a.state_key, &a.locale, &a.changed_fields, a.pattern_type.map(|t| t as u8))
+                .cmp(&(&b.state_key, &b.locale, &b.changed_fields, b.pattern_type.map(|t| t as u8)))
+        });
+        PredictionBundle::new(component_id, self.code_hash(component_id).map(str::to_string), options.ttl_secs, entries)
+    }
+
     /// Estimate memory usage of the predictor
     fn estimate_memory_usage(&self) -> usize {
 
         let mut total = 0;
+        // Shared subtrees are counted once
+        let mut seen_nodes = std::collections::HashSet::new();
 
         // HashMap overhead
         total += std::mem::size_of::<HashMap<String, Vec<PredictionPattern>>>();
@@ -1858,7 +3004,7 @@ impl Predictor {
             // Vec overhead
             total += std::mem::size_of::<Vec<PredictionPattern>>();
 
-            for pattern in patterns {
+            for pattern in patterns.iter() {
                 // Pattern struct overhead
                 total += std::mem::size_of::<PredictionPattern>();
 
@@ -1870,10 +3016,10 @@ impl Predictor {
 
                 // Trees (approximate)
                 if let Some(ref tree) = pattern.old_tree {
-                    total += tree.estimate_size();
+                    total += tree.estimate_unique_size(&mut seen_nodes);
                 }
                 if let Some(ref tree) = pattern.new_tree {
-                    total += tree.estimate_size();
+                    total += tree.estimate_unique_size(&mut seen_nodes);
                 }
             }
         }
@@ -1907,37 +3053,12 @@ impl Predictor {
 
         crate::log_warn!("Evicting state keys: {} -> {}", self.patterns.len(), target_count);
 
-        // Collect keys with their scores for eviction
-        let mut key_scores: Vec<(String, u64)> = self.patterns.iter().map(|(key, patterns)| {
-            let score = match self.config.eviction_policy {
-                EvictionPolicy::LeastFrequentlyUsed => {
-                    patterns.iter().map(|p| p.observation_count as u64).sum()
-                }
-                EvictionPolicy::LeastRecentlyUsed => {
-                    patterns.iter()
-                        .map(|p| p.last_accessed.elapsed().as_secs())
-                        .max()
-                        .unwrap_or(0)
-                }
-                EvictionPolicy::OldestFirst => {
-                    patterns.iter()
-                        .map(|p| p.created_at.elapsed().as_secs())
-                        .max()
-                        .unwrap_or(0)
-                }
-            };
-            (key.clone(), score)
-        }).collect();
-
-        // Sort by score (lowest first for eviction)
-        match self.config.eviction_policy {
-            EvictionPolicy::LeastFrequentlyUsed => {
-                key_scores.sort_by_key(|(_, score)| *score);
-            }
-            EvictionPolicy::LeastRecentlyUsed | EvictionPolicy::OldestFirst => {
-                key_scores.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
-            }
-        }
+        // Highest priority first, ties by key so runs are reproducible
+        let now = self.clock.now();
+        let mut key_scores: Vec<(String, u64)> = self.patterns.iter()
+            .map(|(key, patterns)| (key.clone(), self.eviction_priority(patterns, now)))
+            .collect();
+        key_scores.sort_by(|(a_key, a), (b_key, b)| b.cmp(a).then_with(|| a_key.cmp(b_key)));
 
         // Remove keys until we reach target
         let to_remove = self.patterns.len() - target_count;
@@ -1975,25 +3096,33 @@ impl Predictor {
 
     /// Find the best key to evict based on policy
     fn find_key_to_evict(&self) -> Option<String> {
-        self.patterns.iter().min_by_key(|(_, patterns)| {
-            match self.config.eviction_policy {
-                EvictionPolicy::LeastFrequentlyUsed => {
-                    patterns.iter().map(|p| p.observation_count as u64).sum()
-                }
-                EvictionPolicy::LeastRecentlyUsed => {
-                    patterns.iter()
-                        .map(|p| p.last_accessed.elapsed().as_secs())
-                        .max()
-                        .unwrap_or(0)
-                }
-                EvictionPolicy::OldestFirst => {
-                    patterns.iter()
-                        .map(|p| p.created_at.elapsed().as_secs())
-                        .max()
-                        .unwrap_or(0)
-                }
+        let now = self.clock.now();
+        // Ties go to the smallest key so runs are reproducible
+        self.patterns.iter()
+            .min_by_key(|(key, patterns)| (std::cmp::Reverse(self.eviction_priority(patterns, now)), *key))
+            .map(|(key, _)| key.clone())
+    }
+
+    /// How strongly a state key should be evicted (higher goes first)
+    fn eviction_priority(&self, patterns: &[PredictionPattern], now: std::time::Instant) -> u64 {
+        match self.config.eviction_policy {
+            EvictionPolicy::LeastFrequentlyUsed => {
+                u64::MAX - patterns.iter().map(|p| p.observation_count as u64).sum::<u64>()
+            }
+            // A key was last used when its most recent pattern was
+            EvictionPolicy::LeastRecentlyUsed => {
+                patterns.iter()
+                    .map(|p| now.saturating_duration_since(p.last_accessed).as_secs())
+                    .min()
+                    .unwrap_or(0)
             }
-        }).map(|(key, _)| key.clone())
+            EvictionPolicy::OldestFirst => {
+                patterns.iter()
+                    .map(|p| now.saturating_duration_since(p.created_at).as_secs())
+                    .max()
+                    .unwrap_or(0)
+            }
+        }
     }
 }
 
@@ -2016,6 +3145,32 @@ pub struct PredictorStats {
     pub avg_confidence: f32,
     pub active_patterns: usize,
     pub prediction_hits: usize,
+    /// Template predictions seeded from build-time metadata
+    #[serde(default)]
+    pub seeded_patterns: usize,
+    /// Patterns and templates learned from observed state changes
+    #[serde(default)]
+    pub learned_patterns: usize,
+    /// Patterns and verification results by where they came from
+    #[serde(default)]
+    pub provenance: ProvenanceBreakdown,
+    /// Predictions withheld by negative learning
+    #[serde(default)]
+    pub suppressed_predictions: usize,
+    /// Component/key/pattern types currently in their cooldown
+    #[serde(default)]
+    pub suppressed_patterns: usize,
+    /// Repeated transitions dropped inside the duplicate window
+    #[serde(default)]
+    pub duplicate_learns: usize,
+    #[serde(default)]
+    pub duplicate_predictions: usize,
+    /// Predictions withheld because their pattern came from an older build
+    #[serde(default)]
+    pub stale_code_predictions: usize,
+    /// Patterns dropped after their component's code hash changed
+    #[serde(default)]
+    pub stale_patterns_collected: usize,
 }
 
 #[cfg(test)]
@@ -2034,14 +3189,16 @@ mod tests {
             state_key: "count".to_string(),
             old_value: serde_json::json!(0),
             new_value: serde_json::json!(1),
+            array_operation: None,
+            locale: None,
         };
 
         let old_tree = VNode::element("div", HashMap::new(), vec![
-            VNode::text("Count: 0"),
+            Some(VNode::text("Count: 0")),
         ]);
 
         let new_tree = VNode::element("div", HashMap::new(), vec![
-            VNode::text("Count: 1"),
+            Some(VNode::text("Count: 1")),
         ]);
 
         // Learn the pattern multiple times
@@ -2067,6 +3224,8 @@ mod tests {
             state_key: "value".to_string(),
             old_value: serde_json::json!(0),
             new_value: serde_json::json!(1),
+            array_operation: None,
+            locale: None,
         };
 
         let tree = VNode::text("test");
@@ -2090,6 +3249,8 @@ mod tests {
             state_key: "val".to_string(),
             old_value: serde_json::json!(0),
             new_value: serde_json::json!(1),
+            array_operation: None,
+            locale: None,
         };
 
         let tree1 = VNode::text("A");
@@ -2114,6 +3275,8 @@ mod tests {
             state_key: "count".to_string(),
             old_value: serde_json::json!(0),
             new_value: serde_json::json!(1),
+            array_operation: None,
+            locale: None,
         };
 
         let old_tree = VNode::text("Count: 0");
@@ -2142,4 +3305,453 @@ mod tests {
         assert_eq!(stats.correct_predictions, 1);
         assert_eq!(stats.hit_rate, 1.0);
     }
+
+    #[test]
+    fn test_stored_trees_share_subtrees() {
+        let change = |key: &str| StateChange {
+            component_id: "form".to_string(),
+            state_key: key.to_string(),
+            old_value: serde_json::json!(0),
+            new_value: serde_json::json!(1),
+            array_operation: None,
+            locale: None,
+        };
+        let root = HexPath::from("10000000");
+        let text = |i: usize, content: String| Some(VNode::Text(crate::vdom::VText { content, path: root.child(i) }));
+        let tree = |status: &str| {
+            let mut children: Vec<Option<VNode>> = (0..12).map(|i| text(i, format!("Row {}", i))).collect();
+            children.push(text(12, status.to_string()));
+            VNode::Element(crate::vdom::VElement {
+                tag: "div".to_string(),
+                props: HashMap::new(),
+                children,
+                key: None,
+                path: root.clone(),
+                source: None,
+            })
+        };
+
+        let mut predictor = Predictor::new();
+        predictor.learn(change("a"), &tree("a0"), &tree("a1"), None).unwrap();
+        let one_key = predictor.stats().estimated_memory_bytes;
+        predictor.learn(change("b"), &tree("a0"), &tree("b1"), None).unwrap();
+        let two_keys = predictor.stats().estimated_memory_bytes;
+
+        // Deep copies would add two whole trees; shared ones only add a root and a text each
+        let deep_copy = tree("b1").estimate_size();
+        assert!(two_keys - one_key < deep_copy, "{} -> {} (tree: {})", one_key, two_keys, deep_copy);
+    }
+
+    #[test]
+    fn test_seeded_templates_hit_on_first_change() {
+        let mut metadata = ComponentMetadata::new("Counter", "Counter");
+        metadata.add_template("[0].text[0]", crate::vdom::TemplateInfo {
+            template: "Count: {0}".to_string(),
+            bindings: vec!["count".to_string()],
+            slots: vec![7],
+            path: HexPath::from("10000000.10000000"),
+            template_type: "dynamic".to_string(),
+            attribute: None,
+            conditional_templates: None,
+            transform: None,
+            nullable: None,
+        });
+
+        let mut predictor = Predictor::new();
+        assert_eq!(predictor.seed_from_metadata(&metadata), 1);
+
+        let state_change = StateChange {
+            component_id: "Counter".to_string(),
+            state_key: "count".to_string(),
+            old_value: serde_json::json!(0),
+            new_value: serde_json::json!(1),
+            array_operation: None,
+            locale: None,
+        };
+        let prediction = predictor.predict(&state_change, &VNode::text("Count: 0")).unwrap();
+        assert!(matches!(prediction.predicted_patches[..], [Patch::UpdateTextTemplate { .. }]));
+
+        let stats = predictor.stats();
+        assert_eq!((stats.seeded_patterns, stats.learned_patterns), (1, 0));
+    }
+
+    #[test]
+    fn test_verification_attributed_by_provenance() {
+        let mut metadata = ComponentMetadata::new("Counter", "Counter");
+        metadata.add_template("[0].text[0]", crate::vdom::TemplateInfo {
+            template: "{0}".to_string(),
+            bindings: vec!["count".to_string()],
+            slots: vec![0],
+            path: HexPath::from("10000000"),
+            template_type: "dynamic".to_string(),
+            attribute: None,
+            conditional_templates: None,
+            transform: None,
+            nullable: None,
+        });
+        let change = |key: &str| StateChange {
+            component_id: "Counter".to_string(),
+            state_key: key.to_string(),
+            old_value: serde_json::json!(0),
+            new_value: serde_json::json!(1),
+            array_operation: None,
+            locale: None,
+        };
+        let (before, after) = (VNode::text("0"), VNode::text("1"));
+
+        let mut predictor = Predictor::with_config(PredictorConfig { min_confidence: 0.8, ..Default::default() });
+        predictor.seed_from_metadata(&metadata);
+
+        // A compile-time template stays trusted after a failed verification
+        assert!(!predictor.verify_prediction(&change("count"), &before, &after).unwrap());
+        let prediction = predictor.predict(&change("count"), &before).unwrap();
+        assert_eq!(prediction.provenance, Provenance::CompileTime);
+        assert!(prediction.confidence > 0.9);
+
+        // Unbound keys fall back to the numeric heuristic
+        let prediction = predictor.predict(&change("other"), &before).unwrap();
+        assert_eq!(prediction.provenance, Provenance::Heuristic);
+        assert!(predictor.verify_prediction(&change("other"), &after, &after).unwrap());
+
+        let stats = predictor.stats().provenance;
+        assert_eq!((stats.compile_time.patterns, stats.compile_time.incorrect), (1, 1));
+        assert_eq!((stats.heuristic.predictions, stats.heuristic.correct), (1, 1));
+
+        let inspected = predictor.inspect_patterns();
+        assert_eq!(inspected.len(), 1);
+        assert_eq!((inspected[0].kind.as_str(), inspected[0].provenance), ("template", Provenance::CompileTime));
+    }
+
+    #[test]
+    fn test_lru_eviction_follows_clock() {
+        let clock = Arc::new(crate::clock::MockClock::new());
+        let mut predictor = Predictor::with_config(PredictorConfig {
+            max_state_keys: 2,
+            eviction_policy: EvictionPolicy::LeastRecentlyUsed,
+            ..Default::default()
+        });
+        predictor.set_clock(clock.clone());
+
+        for key in ["a", "b", "c", "d"] {
+            let change = StateChange {
+                component_id: "List".to_string(),
+                state_key: key.to_string(),
+                old_value: serde_json::json!(false),
+                new_value: serde_json::json!(true),
+                array_operation: None,
+                locale: None,
+            };
+            predictor.learn(change, &VNode::text("open"), &VNode::text("closed"), None).unwrap();
+            clock.advance(std::time::Duration::from_secs(10));
+        }
+
+        // Learning "d" found three keys over the limit of two and kept the newest
+        let mut keys: Vec<&String> = predictor.patterns.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["List::c", "List::d"]);
+    }
+
+    #[test]
+    fn test_memory_eviction_drops_least_recently_used_key() {
+        let clock = Arc::new(crate::clock::MockClock::new());
+        let mut predictor = Predictor::with_config(PredictorConfig {
+            eviction_policy: EvictionPolicy::LeastRecentlyUsed,
+            ..Default::default()
+        });
+        predictor.set_clock(clock.clone());
+
+        let learn = |predictor: &mut Predictor, key: &str, to: VNode| {
+            let change = StateChange {
+                component_id: "List".to_string(),
+                state_key: key.to_string(),
+                old_value: serde_json::json!(false),
+                new_value: serde_json::json!(true),
+                array_operation: None,
+                locale: None,
+            };
+            predictor.learn(change, &VNode::text("open"), &to, None).unwrap();
+        };
+        // "a" has an old pattern but was used last at 20s; "b" at 10s, "c" at 15s
+        learn(&mut predictor, "a", VNode::text("closed"));
+        clock.advance(std::time::Duration::from_secs(10));
+        learn(&mut predictor, "b", VNode::text("closed"));
+        clock.advance(std::time::Duration::from_secs(5));
+        learn(&mut predictor, "c", VNode::text("closed"));
+        clock.advance(std::time::Duration::from_secs(5));
+        learn(&mut predictor, "a", VNode::element("p", HashMap::new(), vec![]));
+        clock.advance(std::time::Duration::from_secs(10));
+
+        assert_eq!(predictor.patterns["List::a"].len(), 2);
+        predictor.config.max_memory_bytes = predictor.estimate_memory_usage() - 1;
+        predictor.enforce_memory_limits().unwrap();
+
+        let mut keys: Vec<&String> = predictor.patterns.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["List::a", "List::c"]);
+    }
+
+    #[test]
+    fn test_similarity_merges_near_identical_observations() {
+        let change = StateChange {
+            component_id: "Profile".to_string(),
+            state_key: "user".to_string(),
+            old_value: serde_json::json!({ "v": 1 }),
+            new_value: serde_json::json!({ "v": 2 }),
+            array_operation: None,
+            locale: None,
+        };
+        let text = |content: &str, path: &str| Some(VNode::Text(crate::vdom::VText {
+            content: content.to_string(),
+            path: HexPath::from(path),
+        }));
+        let tree = |a: &str, b: &str| VNode::element("div", HashMap::new(), vec![text(a, "10000000"), text(b, "20000000")]);
+        let observe = |predictor: &mut Predictor| {
+            predictor.learn(change.clone(), &tree("one", "two"), &tree("ONE", "two"), None).unwrap();
+            predictor.learn(change.clone(), &tree("one", "two"), &tree("ONE", "TWO"), None).unwrap();
+        };
+
+        // Exact matching: one extra patch makes a second pattern
+        let mut exact = Predictor::new();
+        observe(&mut exact);
+        assert_eq!(exact.stats().total_patterns, 2);
+
+        let mut fuzzy = Predictor::with_config(PredictorConfig {
+            similarity: PatternSimilarity { threshold: 0.5, ..Default::default() },
+            ..Default::default()
+        });
+        observe(&mut fuzzy);
+        assert_eq!(fuzzy.stats().total_patterns, 1);
+        assert_eq!(fuzzy.stats().total_observations, 2);
+    }
+
+    #[test]
+    fn test_similarity_score() {
+        let similarity = PatternSimilarity::default();
+        let text = |path: &str| Patch::UpdateText { path: HexPath::from(path), content: "x".to_string() };
+        let a = vec![text("10000000"), text("20000000")];
+
+        assert_eq!(similarity.score(&a, &a), 1.0);
+        let partial = similarity.score(&a, &a[..1]);
+        assert!(partial > 0.0 && partial < 1.0, "score {}", partial);
+        assert_eq!(similarity.score(&a, &[]), 0.0);
+    }
+
+    #[test]
+    fn test_repeated_misses_suppress_predictions() {
+        let clock = Arc::new(crate::clock::MockClock::new());
+        let mut predictor = Predictor::new();
+        predictor.set_clock(clock.clone());
+
+        let change = StateChange {
+            component_id: "Clock".to_string(),
+            state_key: "now".to_string(),
+            old_value: serde_json::json!("a"),
+            new_value: serde_json::json!("b"),
+            array_operation: None,
+            locale: None,
+        };
+        let (old_tree, new_tree, actual) = (VNode::text("x"), VNode::text("y"), VNode::text("z"));
+        for _ in 0..3 {
+            predictor.learn(change.clone(), &old_tree, &new_tree, None).unwrap();
+        }
+
+        for _ in 0..3 {
+            assert!(predictor.predict(&change, &old_tree).is_some());
+            predictor.verify_prediction(&change, &new_tree, &actual).unwrap();
+        }
+        assert!(predictor.predict(&change, &old_tree).is_none());
+        let stats = predictor.stats();
+        assert_eq!((stats.suppressed_predictions, stats.suppressed_patterns), (1, 1));
+
+        // The cooldown passes and the pattern gets another chance
+        clock.advance(std::time::Duration::from_secs(61));
+        assert!(predictor.predict(&change, &old_tree).is_some());
+        assert_eq!(predictor.stats().suppressed_patterns, 0);
+    }
+
+    #[test]
+    fn test_duplicate_transitions_within_window_are_dropped() {
+        let clock = Arc::new(crate::clock::MockClock::new());
+        let mut predictor = Predictor::with_config(PredictorConfig {
+            duplicates: DuplicateSuppression { window_ms: 100 },
+            ..PredictorConfig::default()
+        });
+        predictor.set_clock(clock.clone());
+
+        let change = StateChange {
+            component_id: "Button".to_string(),
+            state_key: "clicks".to_string(),
+            old_value: serde_json::json!("a"),
+            new_value: serde_json::json!("b"),
+            array_operation: None,
+            locale: None,
+        };
+        let (old_tree, new_tree) = (VNode::text("x"), VNode::text("y"));
+        predictor.learn(change.clone(), &old_tree, &new_tree, None).unwrap();
+        predictor.learn(change.clone(), &old_tree, &new_tree, None).unwrap();
+        assert!(predictor.predict(&change, &old_tree).is_none());
+        let stats = predictor.stats();
+        assert_eq!((stats.total_observations, stats.duplicate_learns, stats.duplicate_predictions), (1, 1, 1));
+
+        // Outside the window the same transition counts again
+        clock.advance(std::time::Duration::from_millis(150));
+        predictor.learn(change.clone(), &old_tree, &new_tree, None).unwrap();
+        assert_eq!(predictor.stats().total_observations, 2);
+    }
+
+    #[test]
+    fn test_patterns_from_old_code_are_suppressed_and_collected() {
+        let mut predictor = Predictor::new();
+        predictor.set_code_hash("Button", "v1");
+
+        let change = StateChange {
+            component_id: "Button".to_string(),
+            state_key: "label".to_string(),
+            old_value: serde_json::json!("a"),
+            new_value: serde_json::json!("b"),
+            array_operation: None,
+            locale: None,
+        };
+        let (old_tree, new_tree) = (VNode::text("x"), VNode::text("y"));
+        predictor.learn(change.clone(), &old_tree, &new_tree, None).unwrap();
+        assert!(predictor.predict(&change, &old_tree).is_some());
+
+        // A caller on the new build doesn't get the old build's patterns
+        let mut metadata = ComponentMetadata::new("Button", "Button");
+        metadata.code_hash = Some("v2".to_string());
+        assert!(predictor.predict_with_metadata(&change, &old_tree, Some(&metadata)).is_none());
+        assert_eq!(predictor.stats().stale_code_predictions, 1);
+
+        assert_eq!(predictor.set_code_hash("Button", "v2"), 1);
+        assert_eq!(predictor.set_code_hash("Button", "v2"), 0);
+        assert!(predictor.inspect_patterns().is_empty());
+        assert_eq!(predictor.stats().stale_patterns_collected, 1);
+    }
+
+    #[test]
+    fn test_object_mutations_learn_in_separate_buckets() {
+        let mut predictor = Predictor::new();
+        let change = |old: serde_json::Value, new: serde_json::Value| StateChange {
+            component_id: "Profile".to_string(),
+            state_key: "user".to_string(),
+            old_value: old,
+            new_value: new,
+            array_operation: None,
+            locale: None,
+        };
+        let rename = change(serde_json::json!({ "name": "Ann", "age": 30 }), serde_json::json!({ "name": "Bo", "age": 30 }));
+        let birthday = change(serde_json::json!({ "name": "Ann", "age": 30 }), serde_json::json!({ "name": "Ann", "age": 31 }));
+
+        predictor.learn(rename.clone(), &VNode::text("x"), &VNode::text("y"), None).unwrap();
+        predictor.learn(birthday, &VNode::text("x"), &VNode::text("z"), None).unwrap();
+
+        let mut keys: Vec<&String> = predictor.patterns.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["Profile::user::~age", "Profile::user::~name"]);
+        assert_eq!(rename.value_diff().unwrap().changed, vec!["name"]);
+    }
+
+    #[test]
+    fn test_full_state_learns_multi_binding_and_nested_templates() {
+        let mut predictor = Predictor::new();
+        let change = |state_key: &str, old: serde_json::Value, new: serde_json::Value| StateChange {
+            component_id: "Todos".to_string(),
+            state_key: state_key.to_string(),
+            old_value: old,
+            new_value: new,
+            array_operation: None,
+            locale: None,
+        };
+        let text_template = |predictor: &Predictor, key: &str| match &predictor.template_predictions[key].patches[..] {
+            [Patch::UpdateTextTemplate { template_patch, .. }] => template_patch.clone(),
+            other => panic!("expected a text template, got {:?}", other),
+        };
+
+        let state = HashMap::from([("done".to_string(), serde_json::json!(3)), ("total".to_string(), serde_json::json!(10))]);
+        let done = change("done", serde_json::json!(3), serde_json::json!(4));
+        predictor.learn(done.clone(), &VNode::text("3 of 10 done"), &VNode::text("4 of 10 done"), Some(&state)).unwrap();
+        let template = text_template(&predictor, "Todos::done");
+        assert_eq!((template.template.as_str(), template.bindings.clone()), ("{0} of {1} done", vec!["done".to_string(), "total".to_string()]));
+
+        // The constant binding renders from the full state at prediction time
+        let state = HashMap::from([("done".to_string(), serde_json::json!(4)), ("total".to_string(), serde_json::json!(12))]);
+        let prediction = predictor.predict_with_state(&change("done", serde_json::json!(4), serde_json::json!(5)), &state, &VNode::text("4 of 12 done")).unwrap();
+        assert!(matches!(&prediction.predicted_patches[..], [Patch::UpdateText { content, .. }] if content == "5 of 12 done"));
+
+        let user = change("user", serde_json::json!({ "name": "Ann" }), serde_json::json!({ "name": "Bo" }));
+        predictor.learn(user, &VNode::text("Hi Ann"), &VNode::text("Hi Bo"), Some(&HashMap::new())).unwrap();
+        assert_eq!(text_template(&predictor, "Todos::user").bindings, vec!["user.name"]);
+    }
+
+    #[test]
+    fn test_plain_array_sets_learn_like_array_operations() {
+        let todos = |ids: &[u32]| serde_json::Value::Array(
+            ids.iter().map(|id| serde_json::json!({ "text": format!("todo {}", id) })).collect()
+        );
+        let list = |ids: &[u32]| {
+            let mut tree = VNode::element("ul", HashMap::new(), ids.iter()
+                .map(|id| Some(VNode::element("li", HashMap::new(), vec![Some(VNode::text(format!("todo {}", id)))])))
+                .collect());
+            tree.rebase_path(&HexPath::from("10000000"));
+            tree
+        };
+        let change = |old: &[u32], new: &[u32], array_operation: Option<ArrayOperation>| StateChange {
+            component_id: "Todos".to_string(),
+            state_key: "todos".to_string(),
+            old_value: todos(old),
+            new_value: todos(new),
+            array_operation,
+            locale: None,
+        };
+
+        // setTodos([...todos, todo]) with no operation vs the append helper
+        let mut plain = Predictor::new();
+        let mut described = Predictor::new();
+        let append = ArrayOperation::Append { item: serde_json::json!({ "text": "todo 3" }) };
+        plain.learn(change(&[1, 2], &[1, 2, 3], None), &list(&[1, 2]), &list(&[1, 2, 3]), None).unwrap();
+        described.learn(change(&[1, 2], &[1, 2, 3], Some(append)), &list(&[1, 2]), &list(&[1, 2, 3]), None).unwrap();
+        let learned = |predictor: &Predictor| predictor.template_predictions.get("Todos::todos").map(|t| t.patches.clone());
+        assert!(learned(&plain).is_some());
+        assert_eq!(learned(&plain), learned(&described));
+
+        // Predicting a plain set uses the operation too, whatever the list length
+        let next = change(&[1, 2, 3], &[1, 2, 3, 4], None);
+        let from_plain = plain.predict(&next, &list(&[1, 2, 3])).unwrap();
+        let from_described = described.predict(&next, &list(&[1, 2, 3])).unwrap();
+        assert!(matches!(from_plain.state_change.array_operation, Some(ArrayOperation::Append { .. })));
+        assert_eq!(from_plain.predicted_patches, from_described.predicted_patches);
+        assert!(matches!(from_plain.predicted_patches[..], [Patch::UpdateListTemplate { .. }]));
+    }
+
+    #[test]
+    fn test_pattern_uses_follow_the_pattern_not_its_position() {
+        let mut predictor = Predictor::new();
+        let change = StateChange {
+            component_id: "Light".to_string(),
+            state_key: "on".to_string(),
+            old_value: serde_json::json!(false),
+            new_value: serde_json::json!(true),
+            array_operation: None,
+            locale: None,
+        };
+        predictor.learn(change.clone(), &VNode::text("dark"), &VNode::text("bright"), None).unwrap();
+        let (prediction, used) = predictor.predict_readonly(&change, &VNode::text("dark"), None, None);
+        assert!(prediction.is_some());
+        let Some(used @ PredictionUse::Pattern { .. }) = used else { panic!("expected a pattern use, got {:?}", used) };
+
+        // Another pattern takes the used one's place before the use is recorded
+        let patterns = Arc::make_mut(predictor.patterns.get_mut("Light::on").unwrap());
+        let mut other = patterns[0].clone();
+        other.id = next_pattern_id();
+        patterns.insert(0, other);
+
+        predictor.record_use(&used);
+        let made = |predictor: &Predictor| predictor.patterns["Light::on"].iter().map(|p| p.predictions_made).collect::<Vec<_>>();
+        assert_eq!(made(&predictor), vec![0, 1]);
+
+        // Once evicted, its uses count for nothing
+        Arc::make_mut(predictor.patterns.get_mut("Light::on").unwrap()).remove(1);
+        predictor.record_use(&used);
+        assert_eq!(made(&predictor), vec![0]);
+    }
 }
@@ -0,0 +1,113 @@
+//! Focus and scroll preservation hints
+//!
+//! A Replace throws away the DOM of a subtree and a ReorderChildren moves nodes
+//! around; either can drop the focused input (with its caret) or reset a scroll
+//! container. `attach_preservation_hints` looks at the old tree under each such
+//! patch and tells the client what to save before applying it and restore after.
+//!
+//! Focus candidates are form fields, contenteditable and tabindex elements that can
+//! be found again in the new DOM: by `id`, or by tag and `name`. Scroll containers
+//! are elements styled with `overflow: auto|scroll` (inline or utility classes) or
+//! marked `data-preserve-scroll`.
+
+use crate::tree_index::TreeIndex;
+use crate::vdom::{Patch, PreserveHints, VElement, VNode};
+
+/// Hints for a structural patch over `old_subtree` (None if there's nothing to keep)
+pub fn preservation_hints(old_subtree: &VNode) -> Option<PreserveHints> {
+    let mut hints = PreserveHints::default();
+    collect(old_subtree, &mut hints);
+    (hints != PreserveHints::default()).then_some(hints)
+}
+
+/// Set the hints of every Replace and ReorderChildren in `patches`, computed from
+/// the tree the patches apply to
+pub fn attach_preservation_hints(patches: &mut [Patch], old_tree: &VNode) {
+    let index = TreeIndex::build(old_tree);
+    for patch in patches {
+        if let Patch::Replace { path, preserve, .. } | Patch::ReorderChildren { path, preserve, .. } = patch {
+            *preserve = index.get(old_tree, path).and_then(preservation_hints);
+        }
+    }
+}
+
+fn collect(node: &VNode, hints: &mut PreserveHints) {
+    let VNode::Element(el) = node else { return };
+    if hints.focus_selector.is_none() && is_focusable(el) {
+        hints.focus_selector = selector(el);
+    }
+    hints.scroll |= is_scroll_container(el);
+    for child in el.children.iter().flatten() {
+        collect(child, hints);
+    }
+}
+
+fn is_focusable(el: &VElement) -> bool {
+    matches!(el.tag.as_str(), "input" | "textarea" | "select")
+        || el.props.get("contenteditable").is_some_and(|value| value != "false")
+        || el.props.contains_key("tabindex")
+}
+
+/// A selector that finds the element again after the patch
+fn selector(el: &VElement) -> Option<String> {
+    if let Some(id) = el.props.get("id").filter(|id| !id.is_empty()) {
+        return Some(format!("#{}", id));
+    }
+    el.props
+        .get("name")
+        .filter(|name| !name.is_empty())
+        .map(|name| format!("{}[name=\"{}\"]", el.tag, name))
+}
+
+fn is_scroll_container(el: &VElement) -> bool {
+    let scrolls = |value: &str| value.contains("auto") || value.contains("scroll");
+    el.props.contains_key("data-preserve-scroll")
+        || el.props.get("style").is_some_and(|style| {
+            style
+                .split(';')
+                .filter_map(|decl| decl.split_once(':'))
+                .any(|(name, value)| name.trim().starts_with("overflow") && scrolls(value))
+        })
+        || ["className", "class"].iter().filter_map(|attr| el.props.get(*attr)).any(|classes| {
+            classes.split_whitespace().any(|class| class.starts_with("overflow-") && scrolls(class))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::HexPath;
+    use std::collections::HashMap;
+
+    fn element(tag: &str, props: &[(&str, &str)], path: &str, children: Vec<VNode>) -> VNode {
+        VNode::Element(VElement {
+            tag: tag.to_string(),
+            props: props.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<HashMap<_, _>>(),
+            children: children.into_iter().map(Some).collect(),
+            key: None,
+            path: HexPath::from(path),
+            source: None,
+        })
+    }
+
+    #[test]
+    fn test_hints_come_from_the_old_subtree() {
+        let form = element("form", &[("style", "overflow-y: auto")], "10000000", vec![
+            element("label", &[], "10000000.10000000", vec![]),
+            element("input", &[("name", "email")], "10000000.20000000", vec![]),
+        ]);
+        let mut patches = vec![
+            Patch::Replace { path: HexPath::from("10000000"), node: VNode::text("Sent!"), preserve: None },
+            Patch::Replace { path: HexPath::from("10000000.10000000"), node: VNode::text("Email"), preserve: None },
+        ];
+        attach_preservation_hints(&mut patches, &form);
+
+        let Patch::Replace { preserve, .. } = &patches[0] else { unreachable!() };
+        assert_eq!(
+            preserve.as_ref(),
+            Some(&PreserveHints { focus_selector: Some("input[name=\"email\"]".to_string()), scroll: true })
+        );
+        // Nothing to keep inside the label
+        assert!(matches!(&patches[1], Patch::Replace { preserve: None, .. }));
+    }
+}
@@ -0,0 +1,111 @@
+//! Progressive rendering: patches split by page region
+//!
+//! An initial render of a huge page produces one enormous patch list. The host can
+//! paint the above-the-fold part first by splitting the list into groups, one per
+//! region (subtree root) in priority order, and sending the groups in that order.
+//!
+//! Paths are stable ids, so patches in one region never move nodes of another and
+//! the groups can be applied one after the other. The exception is a patch on an
+//! ancestor of a region (a Replace of the page, a reorder of a region's parent):
+//! those go with the highest priority region they enclose, so nothing is applied
+//! underneath them before they land. Patches outside every region come last.
+
+use crate::error::Result;
+use crate::last_error::FfiCall;
+use crate::path::HexPath;
+use crate::vdom::Patch;
+use serde::{Deserialize, Serialize};
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+
+/// The patches of one region, in their original order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatchGroup {
+    /// Region root (None for patches outside every region)
+    pub region: Option<HexPath>,
+    pub patches: Vec<Patch>,
+}
+
+/// Split `patches` into groups by `regions` (highest priority first)
+/// A patch belongs to the first region containing it; empty groups are omitted
+pub fn split_patches_by_region(patches: Vec<Patch>, regions: Vec<HexPath>) -> Vec<PatchGroup> {
+    let mut groups: Vec<PatchGroup> = regions
+        .into_iter()
+        .map(|region| PatchGroup { region: Some(region), patches: Vec::new() })
+        .chain(std::iter::once(PatchGroup { region: None, patches: Vec::new() }))
+        .collect();
+    let rest = groups.len() - 1;
+
+    for patch in patches {
+        let path = patch.path();
+        let index = groups[..rest]
+            .iter()
+            .position(|group| group.region.as_ref().is_some_and(|region| path.is_within(region)))
+            .or_else(|| {
+                groups[..rest]
+                    .iter()
+                    .position(|group| group.region.as_ref().is_some_and(|region| region.is_descendant_of(path)))
+            })
+            .unwrap_or(rest);
+        groups[index].patches.push(patch);
+    }
+
+    groups.retain(|group| !group.patches.is_empty());
+    groups
+}
+
+/// Split a patch list by regions
+/// Returns a JSON array of PatchGroup (or {"error": ...})
+///
+/// # Safety
+/// - patches_json and regions_json (a JSON array of paths) must be valid
+///   null-terminated UTF-8 strings
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_split_patches_by_region(
+    patches_json: *const c_char,
+    regions_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_split_patches_by_region", &[patches_json, regions_json]);
+    let result = (|| -> Result<String> {
+        let patches: Vec<Patch> = serde_json::from_str(CStr::from_ptr(patches_json).to_str()?)?;
+        let regions: Vec<HexPath> = serde_json::from_str(CStr::from_ptr(regions_json).to_str()?)?;
+        Ok(serde_json::to_string(&split_patches_by_region(patches, regions))?)
+    })();
+    match result {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => {
+            crate::last_error::record_error(&e);
+            CString::new(serde_json::json!({ "error": e.to_string() }).to_string()).unwrap().into_raw()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(path: &str) -> Patch {
+        Patch::UpdateText { path: HexPath::from(path), content: path.to_string() }
+    }
+
+    #[test]
+    fn test_split_by_region_priority() {
+        let header = HexPath::from("10000000.10000000");
+        let main = HexPath::from("10000000.20000000");
+        let reorder = Patch::ReorderChildren { path: HexPath::from("10000000"), order: vec![], preserve: None };
+        let patches = vec![
+            text("10000000.30000000.10000000"),
+            text("10000000.20000000.10000000"),
+            reorder.clone(),
+            text("10000000.10000000"),
+            text("10000000.20000000.20000000"),
+        ];
+
+        let groups = split_patches_by_region(patches, vec![main.clone(), header.clone()]);
+        let summary: Vec<(Option<HexPath>, usize)> = groups.iter().map(|g| (g.region.clone(), g.patches.len())).collect();
+        assert_eq!(summary, vec![(Some(main), 3), (Some(header), 1), (None, 1)]);
+        // The reorder of the regions' parent goes with the first region, in order
+        assert_eq!(groups[0].patches[1], reorder);
+    }
+}
@@ -0,0 +1,183 @@
+//! Prop name normalization
+//!
+//! Trees come from producers that spell the same attribute differently: the Babel
+//! plugin emits React names (`className`, `htmlFor`), hand-written or server-rendered
+//! trees use HTML ones (`class`, `for`), sometimes in odd case (`TabIndex`). Diffing
+//! them verbatim patches props that didn't change. When enabled (process-wide, off by
+//! default), `deserialize_vnode_safe` renames every known alias to the client's
+//! preferred spelling, and the reconciler compares props by those names, so
+//! equivalent props are equal and UpdateProps carries the preferred naming.
+//!
+//! Lookup is case-insensitive; props not in the table are left exactly as they are.
+
+use crate::error::{FfiResult, MinimactError};
+use crate::last_error::FfiCall;
+use crate::vdom::VNode;
+use arc_swap::ArcSwap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::Arc;
+
+/// Spelling the client wants props in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PropNaming {
+    /// `className`, `htmlFor`, `tabIndex`
+    React,
+    /// `class`, `for`, `tabindex`
+    Html,
+}
+
+/// Prop normalization settings (off by default)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PropNormalization {
+    /// Rename aliases to this spelling (None = leave props alone)
+    pub naming: Option<PropNaming>,
+}
+
+impl PropNormalization {
+    pub fn is_enabled(&self) -> bool {
+        self.naming.is_some()
+    }
+}
+
+/// (React name, HTML name) of every prop whose two spellings differ
+const PROP_ALIASES: &[(&str, &str)] = &[
+    ("className", "class"),
+    ("htmlFor", "for"),
+    ("tabIndex", "tabindex"),
+    ("readOnly", "readonly"),
+    ("maxLength", "maxlength"),
+    ("minLength", "minlength"),
+    ("colSpan", "colspan"),
+    ("rowSpan", "rowspan"),
+    ("contentEditable", "contenteditable"),
+    ("spellCheck", "spellcheck"),
+    ("autoComplete", "autocomplete"),
+    ("autoFocus", "autofocus"),
+    ("autoPlay", "autoplay"),
+    ("accessKey", "accesskey"),
+    ("crossOrigin", "crossorigin"),
+    ("encType", "enctype"),
+    ("formAction", "formaction"),
+    ("noValidate", "novalidate"),
+    ("srcSet", "srcset"),
+    ("useMap", "usemap"),
+    ("dateTime", "datetime"),
+    ("charSet", "charset"),
+    ("httpEquiv", "http-equiv"),
+    ("acceptCharset", "accept-charset"),
+];
+
+lazy_static::lazy_static! {
+    static ref PROP_NORMALIZATION: ArcSwap<PropNormalization> = ArcSwap::from_pointee(PropNormalization::default());
+
+    /// Lowercased spelling (either form) -> index into PROP_ALIASES
+    static ref ALIAS_INDEX: HashMap<String, usize> = PROP_ALIASES
+        .iter()
+        .enumerate()
+        .flat_map(|(i, (react, html))| [(react.to_lowercase(), i), (html.to_string(), i)])
+        .collect();
+}
+
+/// Set the prop normalization used by deserialization and diffing (process-wide)
+pub fn set_prop_normalization(options: PropNormalization) {
+    PROP_NORMALIZATION.store(Arc::new(options));
+}
+
+pub fn prop_normalization() -> PropNormalization {
+    **PROP_NORMALIZATION.load()
+}
+
+/// The preferred spelling of `name`, or None when it isn't a known alias
+pub fn canonical_prop_name(name: &str, naming: PropNaming) -> Option<&'static str> {
+    let &i = ALIAS_INDEX.get(name.to_lowercase().as_str())?;
+    let (react, html) = PROP_ALIASES[i];
+    Some(match naming {
+        PropNaming::React => react,
+        PropNaming::Html => html,
+    })
+}
+
+/// `props` with every alias renamed; when two spellings of one prop are both
+/// present, the one already spelled the preferred way wins
+pub fn normalize_props(props: &HashMap<String, String>, naming: PropNaming) -> HashMap<String, String> {
+    let mut normalized = HashMap::with_capacity(props.len());
+    for (name, value) in props {
+        match canonical_prop_name(name, naming) {
+            Some(canonical) if canonical != name => {
+                normalized.entry(canonical.to_string()).or_insert_with(|| value.clone());
+            }
+            _ => {
+                normalized.insert(name.clone(), value.clone());
+            }
+        }
+    }
+    normalized
+}
+
+/// Normalize the props of every element in `tree`; returns how many were renamed
+pub fn normalize_prop_names(tree: &mut VNode, naming: PropNaming) -> usize {
+    let VNode::Element(el) = tree else { return 0 };
+    let renamed = el.props.keys().filter(|name| canonical_prop_name(name, naming).is_some_and(|c| c != *name)).count();
+    if renamed > 0 {
+        el.props = normalize_props(&el.props, naming);
+    }
+    renamed + el.children.iter_mut().flatten().map(|child| normalize_prop_names(child, naming)).sum::<usize>()
+}
+
+/// Set the prop normalization from PropNormalization JSON (e.g. `{"naming": "Html"}`)
+///
+/// # Safety
+/// - options_json must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_set_prop_normalization(options_json: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_set_prop_normalization", &[options_json]);
+    let options = CStr::from_ptr(options_json)
+        .to_str()
+        .map_err(MinimactError::from)
+        .and_then(|json| Ok(serde_json::from_str::<PropNormalization>(json)?));
+    match options {
+        Ok(options) => {
+            set_prop_normalization(options);
+            FfiResult::success()
+        }
+        Err(e) => FfiResult::error(&e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn props(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_aliases_normalize_to_one_spelling() {
+        let react = props(&[("className", "btn"), ("htmlFor", "email"), ("TABINDEX", "0"), ("onClick", "Handle0")]);
+        let html = props(&[("class", "btn"), ("for", "email"), ("tabindex", "0"), ("onClick", "Handle0")]);
+
+        assert_eq!(normalize_props(&react, PropNaming::Html), html);
+        assert_eq!(normalize_props(&html, PropNaming::React), normalize_props(&react, PropNaming::React));
+        assert_eq!(normalize_props(&html, PropNaming::React)["className"], "btn");
+
+        // The preferred spelling wins a collision
+        let both = props(&[("class", "html"), ("className", "react")]);
+        assert_eq!(normalize_props(&both, PropNaming::React), props(&[("className", "react")]));
+    }
+
+    #[test]
+    fn test_tree_normalization_counts_renames() {
+        let mut tree = VNode::element("label", props(&[("for", "email"), ("class", "field")]), vec![
+            Some(VNode::element("input", props(&[("readonly", "true"), ("id", "email")]), vec![])),
+        ]);
+        assert_eq!(normalize_prop_names(&mut tree, PropNaming::React), 3);
+        assert_eq!(normalize_prop_names(&mut tree, PropNaming::React), 0);
+        let VNode::Element(label) = &tree else { unreachable!() };
+        assert_eq!(label.props, props(&[("htmlFor", "email"), ("className", "field")]));
+    }
+}
@@ -0,0 +1,334 @@
+//! Pub-sub fan-out of patch batches
+//!
+//! With several minimact servers behind a load balancer, the server that computed a
+//! component's patches is often not the one holding the client connection. The
+//! PatchFanout publishes every patch batch (a PatchBatch envelope, so edges can
+//! dedupe and detect gaps) to a subject per session and component, and carries state
+//! changes the other way:
+//!
+//! - `{prefix}.patches.{session}.{component}`: PatchBatch JSON, server → edges
+//! - `{prefix}.state.{session}`: StateChange JSON, edges → server
+//!
+//! Subjects use NATS-style dotted tokens; `.`, `*`, `>` and whitespace inside ids are
+//! replaced with `_`. The transport is a `Broker`: `InMemoryBroker` for a single
+//! process and tests, `RedisBroker` (feature "redis") for Redis PUBLISH/SUBSCRIBE.
+
+use crate::error::Result;
+#[cfg(feature = "redis")]
+use crate::error::MinimactError;
+use crate::patch_batch::{BatchSequencer, PatchBatch};
+use crate::predictor::StateChange;
+use crate::vdom::Patch;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+#[cfg(feature = "redis")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "redis")]
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A message transport with subject-addressed publish/subscribe
+pub trait Broker: Send + Sync {
+    /// Deliver `payload` to every current subscriber of `subject`
+    fn publish(&self, subject: &str, payload: &[u8]) -> Result<()>;
+
+    /// Receive every payload published to `subject` from now on
+    /// Dropping the receiver ends the subscription
+    fn subscribe(&self, subject: &str) -> Result<Receiver<Vec<u8>>>;
+}
+
+/// In-process broker (single server, tests)
+#[derive(Default)]
+pub struct InMemoryBroker {
+    subscribers: Mutex<HashMap<String, Vec<Sender<Vec<u8>>>>>,
+}
+
+impl InMemoryBroker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Broker for InMemoryBroker {
+    fn publish(&self, subject: &str, payload: &[u8]) -> Result<()> {
+        let mut subscribers = self.subscribers.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(senders) = subscribers.get_mut(subject) {
+            // Drop subscribers whose receiver is gone
+            senders.retain(|sender| sender.send(payload.to_vec()).is_ok());
+        }
+        Ok(())
+    }
+
+    fn subscribe(&self, subject: &str) -> Result<Receiver<Vec<u8>>> {
+        let (sender, receiver) = mpsc::channel();
+        let mut subscribers = self.subscribers.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        subscribers.entry(subject.to_string()).or_default().push(sender);
+        Ok(receiver)
+    }
+}
+
+/// Redis PUBLISH/SUBSCRIBE broker
+///
+/// Every subscription holds its own connection and reader thread; the thread exits
+/// at the first message after the receiver is dropped. Readers poll a stop flag
+/// between reads, and dropping the broker stops and joins all of them.
+#[cfg(feature = "redis")]
+pub struct RedisBroker {
+    client: redis::Client,
+    publisher: Mutex<redis::Connection>,
+    readers: Mutex<Vec<std::thread::JoinHandle<()>>>,
+    stopping: Arc<AtomicBool>,
+}
+
+/// How long a reader blocks on its connection before checking the stop flag
+#[cfg(feature = "redis")]
+const READER_POLL: Duration = Duration::from_millis(100);
+
+#[cfg(feature = "redis")]
+impl RedisBroker {
+    /// Connect to `url` (e.g. "redis://127.0.0.1/")
+    pub fn connect(url: &str) -> Result<Self> {
+        let client = redis::Client::open(url).map_err(broker_error)?;
+        let publisher = Mutex::new(client.get_connection().map_err(broker_error)?);
+        Ok(Self { client, publisher, readers: Mutex::new(Vec::new()), stopping: Arc::new(AtomicBool::new(false)) })
+    }
+}
+
+#[cfg(feature = "redis")]
+impl Drop for RedisBroker {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::Relaxed);
+        let readers = std::mem::take(self.readers.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner()));
+        for reader in readers {
+            if reader.join().is_err() {
+                crate::log_warn!("Redis subscription reader panicked");
+            }
+        }
+    }
+}
+
+#[cfg(feature = "redis")]
+fn broker_error(err: redis::RedisError) -> MinimactError {
+    MinimactError::Broker(err.to_string())
+}
+
+#[cfg(feature = "redis")]
+impl Broker for RedisBroker {
+    fn publish(&self, subject: &str, payload: &[u8]) -> Result<()> {
+        let mut connection = self.publisher.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        redis::cmd("PUBLISH").arg(subject).arg(payload).query::<i64>(&mut *connection).map_err(broker_error)?;
+        Ok(())
+    }
+
+    fn subscribe(&self, subject: &str) -> Result<Receiver<Vec<u8>>> {
+        let mut connection = self.client.get_connection().map_err(broker_error)?;
+        let (sender, receiver) = mpsc::channel();
+        // The reader owns the PubSub for its whole life: dropping a PubSub unsubscribes
+        let (subscribed_tx, subscribed_rx) = mpsc::channel();
+        let stopping = Arc::clone(&self.stopping);
+        let subject = subject.to_string();
+        let reader = std::thread::Builder::new()
+            .name(format!("minimact-redis-{}", subject))
+            .spawn(move || {
+                let mut pubsub = connection.as_pubsub();
+                let subscribed = pubsub.subscribe(&subject).and_then(|()| pubsub.set_read_timeout(Some(READER_POLL)));
+                let failed = subscribed.is_err();
+                let _ = subscribed_tx.send(subscribed);
+                if failed {
+                    return;
+                }
+                while !stopping.load(Ordering::Relaxed) {
+                    match pubsub.get_message() {
+                        Ok(message) => {
+                            if sender.send(message.get_payload_bytes().to_vec()).is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) if e.is_timeout() => {}
+                        Err(e) => {
+                            crate::log_warn!("Redis subscription '{}' ended: {}", subject, e);
+                            break;
+                        }
+                    }
+                }
+            })
+            .map_err(|e| MinimactError::Broker(e.to_string()))?;
+
+        let mut readers = self.readers.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        readers.retain(|reader| !reader.is_finished());
+        readers.push(reader);
+        drop(readers);
+
+        // Subscribed before returning so nothing published after this call is missed
+        match subscribed_rx.recv() {
+            Ok(subscribed) => subscribed.map_err(broker_error)?,
+            Err(_) => return Err(MinimactError::Broker("Redis subscription reader exited".to_string())),
+        }
+        Ok(receiver)
+    }
+}
+
+/// Publishes patch batches and consumes state changes over a Broker
+pub struct PatchFanout<B: Broker> {
+    broker: B,
+    sequencer: BatchSequencer,
+    prefix: String,
+}
+
+impl<B: Broker> PatchFanout<B> {
+    /// Fan out over `broker` under the "minimact" subject prefix
+    pub fn new(broker: B) -> Self {
+        Self::with_prefix(broker, "minimact")
+    }
+
+    /// Fan out under a custom subject prefix (e.g. per deployment)
+    pub fn with_prefix(broker: B, prefix: impl Into<String>) -> Self {
+        Self { broker, sequencer: BatchSequencer::new(), prefix: prefix.into() }
+    }
+
+    pub fn broker(&self) -> &B {
+        &self.broker
+    }
+
+    /// Subject carrying patch batches for one component of a session
+    pub fn patch_subject(&self, session_id: &str, component_id: &str) -> String {
+        format!("{}.patches.{}.{}", self.prefix, subject_token(session_id), subject_token(component_id))
+    }
+
+    /// Subject carrying a session's state changes
+    pub fn state_subject(&self, session_id: &str) -> String {
+        format!("{}.state.{}", self.prefix, subject_token(session_id))
+    }
+
+    /// Wrap `patches` as the component's next batch and publish it
+    pub fn publish_patches(&self, session_id: &str, component_id: &str, patches: Vec<Patch>) -> Result<PatchBatch> {
+        let subject = self.patch_subject(session_id, component_id);
+        let batch = self.sequencer.next_batch(&subject, patches).stamped(None);
+        self.broker.publish(&subject, &serde_json::to_vec(&batch)?)?;
+        Ok(batch)
+    }
+
+    /// Receive the component's patch batches (edge side)
+    pub fn subscribe_patches(&self, session_id: &str, component_id: &str) -> Result<Subscription<PatchBatch>> {
+        Ok(Subscription::new(self.broker.subscribe(&self.patch_subject(session_id, component_id))?))
+    }
+
+    /// Publish a state change from a client (edge side)
+    pub fn publish_state_change(&self, session_id: &str, state_change: &StateChange) -> Result<()> {
+        self.broker.publish(&self.state_subject(session_id), &serde_json::to_vec(state_change)?)
+    }
+
+    /// Receive a session's state changes (server side)
+    pub fn subscribe_state_changes(&self, session_id: &str) -> Result<Subscription<StateChange>> {
+        Ok(Subscription::new(self.broker.subscribe(&self.state_subject(session_id))?))
+    }
+
+    /// Forget the session's sequence counters (its next batches start at 1 again)
+    pub fn end_session(&self, session_id: &str, component_ids: &[&str]) {
+        for component_id in component_ids {
+            self.sequencer.reset(&self.patch_subject(session_id, component_id));
+        }
+    }
+}
+
+/// Decoded messages of one subject; malformed payloads are logged and skipped
+pub struct Subscription<T> {
+    receiver: Receiver<Vec<u8>>,
+    _message: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T: serde::de::DeserializeOwned> Subscription<T> {
+    fn new(receiver: Receiver<Vec<u8>>) -> Self {
+        Self { receiver, _message: std::marker::PhantomData }
+    }
+
+    /// Wait up to `timeout` for the next message (None on timeout or when the broker is gone)
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<T> {
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+            match self.receiver.recv_timeout(remaining) {
+                Ok(payload) => {
+                    if let Some(message) = decode(&payload) {
+                        return Some(message);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+    }
+
+    /// Messages already delivered, without blocking
+    pub fn try_iter(&self) -> impl Iterator<Item = T> + '_ {
+        self.receiver.try_iter().filter_map(|payload| decode(&payload))
+    }
+}
+
+impl<T: serde::de::DeserializeOwned> Iterator for Subscription<T> {
+    type Item = T;
+
+    /// Block for the next message (None once the broker side is gone)
+    fn next(&mut self) -> Option<T> {
+        self.receiver.iter().find_map(|payload| decode(&payload))
+    }
+}
+
+fn decode<T: serde::de::DeserializeOwned>(payload: &[u8]) -> Option<T> {
+    match serde_json::from_slice(payload) {
+        Ok(message) => Some(message),
+        Err(e) => {
+            crate::log_warn!("Dropping malformed pub-sub message: {}", e);
+            None
+        }
+    }
+}
+
+/// An id as a single subject token
+fn subject_token(id: &str) -> String {
+    id.chars().map(|c| if c == '.' || c == '*' || c == '>' || c.is_whitespace() { '_' } else { c }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::HexPath;
+
+    #[test]
+    fn test_patch_batches_reach_subscribers_in_sequence() {
+        let fanout = PatchFanout::new(InMemoryBroker::new());
+        let subscription = fanout.subscribe_patches("session-1", "Counter").unwrap();
+        let other = fanout.subscribe_patches("session-2", "Counter").unwrap();
+
+        for content in ["1", "2"] {
+            let patch = Patch::UpdateText { path: HexPath::from("10000000"), content: content.to_string() };
+            fanout.publish_patches("session-1", "Counter", vec![patch]).unwrap();
+        }
+
+        let batches: Vec<PatchBatch> = subscription.try_iter().collect();
+        assert_eq!(batches.iter().map(|b| b.sequence).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(batches[0].stream_id, "minimact.patches.session-1.Counter");
+        assert!(other.try_iter().next().is_none());
+    }
+
+    #[test]
+    fn test_state_changes_flow_back() {
+        let fanout = PatchFanout::with_prefix(InMemoryBroker::new(), "edge");
+        assert_eq!(fanout.state_subject("a.b *c"), "edge.state.a_b__c");
+
+        let subscription = fanout.subscribe_state_changes("s").unwrap();
+        let state_change = StateChange {
+            component_id: "Counter".to_string(),
+            state_key: "count".to_string(),
+            old_value: serde_json::json!(0),
+            new_value: serde_json::json!(1),
+            array_operation: None,
+            locale: None,
+        };
+        fanout.broker().publish(&fanout.state_subject("s"), b"not json").unwrap();
+        fanout.publish_state_change("s", &state_change).unwrap();
+
+        let received = subscription.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!((received.state_key.as_str(), received.new_value), ("count", serde_json::json!(1)));
+    }
+}
@@ -0,0 +1,326 @@
+//! Per-component rate limiting with a circuit breaker for reconcile storms
+//!
+//! A buggy component (an effect that sets state on every render, a runaway timer)
+//! can trigger hundreds of reconciles per second. Each component gets a budget of
+//! reconciles per window; exceeding it trips that component's breaker. While the
+//! breaker is open, updates are neither diffed nor sent. Once the cooldown is over,
+//! a single Replace of the latest tree brings the client up to date.
+//!
+//! Trips and resets are logged, counted in METRICS and queued as structured events
+//! for the host to drain.
+
+use crate::error::Result;
+use crate::reconciler::{reconcile_with_strategy, ReconcileStrategy};
+use crate::vdom::{Patch, VNode};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Breaker events kept before the oldest is dropped
+const MAX_PENDING_EVENTS: usize = 1000;
+
+/// Rate limit and breaker settings (shared by all components)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Reconciles allowed per component per window
+    pub max_reconciles: u32,
+    /// Window length in milliseconds
+    pub window_ms: u64,
+    /// How long a tripped breaker stays open, in milliseconds
+    pub cooldown_ms: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_reconciles: 60,
+            window_ms: 1000,
+            cooldown_ms: 2000,
+        }
+    }
+}
+
+/// What to do with an update
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RateDecision {
+    /// Reconcile normally
+    Allow,
+    /// Breaker is open: skip the update
+    Suppress,
+    /// Breaker just closed: send the whole tree as one Replace
+    Flush,
+}
+
+/// Structured breaker event for the host
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum BreakerEvent {
+    /// A component exceeded its budget; updates are suppressed for `cooldown_ms`
+    Tripped {
+        component_id: String,
+        reconciles_in_window: u32,
+        window_ms: u64,
+        cooldown_ms: u64,
+    },
+    /// A component's breaker closed after suppressing `suppressed_updates` updates
+    Reset {
+        component_id: String,
+        suppressed_updates: u32,
+    },
+}
+
+#[derive(Debug)]
+struct ComponentRate {
+    window_start: Instant,
+    count: u32,
+    open_until: Option<Instant>,
+    suppressed: u32,
+}
+
+/// Per-component rate limiter and circuit breaker
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    config: Mutex<RateLimitConfig>,
+    components: dashmap::DashMap<String, ComponentRate>,
+    events: Mutex<Vec<BreakerEvent>>,
+}
+
+lazy_static::lazy_static! {
+    /// Process-wide limiter used by the FFI and `reconcile_rate_limited`
+    pub static ref RATE_LIMITER: RateLimiter = RateLimiter::default();
+}
+
+impl RateLimiter {
+    /// Create a limiter with `config`
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config: Mutex::new(config),
+            ..Self::default()
+        }
+    }
+
+    /// Current settings
+    pub fn config(&self) -> RateLimitConfig {
+        self.config.lock().unwrap().clone()
+    }
+
+    /// Replace the settings (existing windows and open breakers are kept)
+    pub fn set_config(&self, config: RateLimitConfig) {
+        *self.config.lock().unwrap() = config;
+    }
+
+    /// Decide what to do with an update for `component_id` arriving now
+    pub fn check(&self, component_id: &str) -> RateDecision {
+        self.check_at(component_id, Instant::now())
+    }
+
+    /// Decide what to do with an update for `component_id` arriving at `now`
+    pub fn check_at(&self, component_id: &str, now: Instant) -> RateDecision {
+        let config = self.config();
+        let mut rate = self.components.entry(component_id.to_string()).or_insert_with(|| ComponentRate {
+            window_start: now,
+            count: 0,
+            open_until: None,
+            suppressed: 0,
+        });
+
+        if let Some(until) = rate.open_until {
+            if now < until {
+                rate.suppressed += 1;
+                crate::metrics::METRICS.record_update_suppressed();
+                return RateDecision::Suppress;
+            }
+            let suppressed = rate.suppressed;
+            *rate = ComponentRate { window_start: now, count: 1, open_until: None, suppressed: 0 };
+            drop(rate);
+            self.close_breaker(component_id, suppressed);
+            return RateDecision::Flush;
+        }
+
+        if now.saturating_duration_since(rate.window_start) >= Duration::from_millis(config.window_ms) {
+            rate.window_start = now;
+            rate.count = 0;
+        }
+        rate.count += 1;
+
+        if rate.count <= config.max_reconciles {
+            return RateDecision::Allow;
+        }
+
+        let reconciles_in_window = rate.count;
+        rate.open_until = Some(now + Duration::from_millis(config.cooldown_ms));
+        rate.suppressed = 1;
+        drop(rate);
+
+        crate::log_warn!(
+            "Rate limit: component '{}' reconciled {} times in {}ms, suppressing updates for {}ms",
+            component_id,
+            reconciles_in_window,
+            config.window_ms,
+            config.cooldown_ms
+        );
+        crate::metrics::METRICS.record_breaker_tripped();
+        crate::metrics::METRICS.record_update_suppressed();
+        self.push_event(BreakerEvent::Tripped {
+            component_id: component_id.to_string(),
+            reconciles_in_window,
+            window_ms: config.window_ms,
+            cooldown_ms: config.cooldown_ms,
+        });
+        RateDecision::Suppress
+    }
+
+    /// Check if `component_id`'s breaker is currently open
+    pub fn is_open(&self, component_id: &str) -> bool {
+        self.components
+            .get(component_id)
+            .and_then(|rate| rate.open_until)
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    /// Close every breaker whose cooldown is over, returning those components
+    /// The host should send each of them a Replace of its latest tree, since no
+    /// further update may arrive to trigger a Flush
+    pub fn close_expired(&self) -> Vec<String> {
+        self.close_expired_at(Instant::now())
+    }
+
+    /// `close_expired` as of `now`
+    pub fn close_expired_at(&self, now: Instant) -> Vec<String> {
+        let mut closed = Vec::new();
+        for mut entry in self.components.iter_mut() {
+            if entry.open_until.is_some_and(|until| now >= until) {
+                closed.push((entry.key().clone(), entry.suppressed));
+                entry.open_until = None;
+                entry.suppressed = 0;
+                entry.window_start = now;
+                entry.count = 0;
+            }
+        }
+        closed
+            .into_iter()
+            .map(|(component_id, suppressed)| {
+                self.close_breaker(&component_id, suppressed);
+                component_id
+            })
+            .collect()
+    }
+
+    /// Take all pending breaker events
+    pub fn drain_events(&self) -> Vec<BreakerEvent> {
+        std::mem::take(&mut *self.events.lock().unwrap())
+    }
+
+    /// Forget a component (e.g. when it unmounts)
+    pub fn remove(&self, component_id: &str) {
+        if let Some((_, rate)) = self.components.remove(component_id) {
+            if rate.open_until.is_some() {
+                crate::metrics::METRICS.record_breaker_reset();
+            }
+        }
+    }
+
+    fn close_breaker(&self, component_id: &str, suppressed_updates: u32) {
+        crate::log_info!("Rate limit: breaker for '{}' closed after suppressing {} updates", component_id, suppressed_updates);
+        crate::metrics::METRICS.record_breaker_reset();
+        self.push_event(BreakerEvent::Reset {
+            component_id: component_id.to_string(),
+            suppressed_updates,
+        });
+    }
+
+    fn push_event(&self, event: BreakerEvent) {
+        let mut events = self.events.lock().unwrap();
+        if events.len() >= MAX_PENDING_EVENTS {
+            events.remove(0);
+        }
+        events.push(event);
+    }
+}
+
+/// Reconcile an update for `component_id`, subject to `limiter`
+///
+/// Suppressed updates produce no patches; the update that closes a breaker
+/// produces a single Replace of `new` (covering everything that was suppressed).
+pub fn reconcile_with_limiter(
+    limiter: &RateLimiter,
+    component_id: &str,
+    old: &VNode,
+    new: &VNode,
+    strategy: &ReconcileStrategy,
+) -> Result<(RateDecision, Vec<Patch>)> {
+    let decision = limiter.check(component_id);
+    let patches = match decision {
+        RateDecision::Allow => reconcile_with_strategy(old, new, strategy)?,
+        RateDecision::Suppress => Vec::new(),
+        RateDecision::Flush => vec![Patch::Replace {
+            path: new.path().clone(),
+            node: new.clone(),
+            preserve: None,
+        }],
+    };
+    Ok((decision, patches))
+}
+
+/// Reconcile an update for `component_id` using the process-wide limiter
+pub fn reconcile_rate_limited(component_id: &str, old: &VNode, new: &VNode) -> Result<(RateDecision, Vec<Patch>)> {
+    crate::logging::with_log_context(crate::logging::LogContext::component(component_id), || {
+        reconcile_with_limiter(&RATE_LIMITER, component_id, old, new, &ReconcileStrategy::default())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(RateLimitConfig { max_reconciles: 3, window_ms: 100, cooldown_ms: 500 })
+    }
+
+    #[test]
+    fn test_breaker_trips_and_flushes() {
+        let limiter = limiter();
+        let start = Instant::now();
+
+        for i in 0..3 {
+            assert_eq!(limiter.check_at("storm", start + Duration::from_millis(i)), RateDecision::Allow);
+        }
+        assert_eq!(limiter.check_at("storm", start + Duration::from_millis(3)), RateDecision::Suppress);
+        assert_eq!(limiter.check_at("storm", start + Duration::from_millis(200)), RateDecision::Suppress);
+        // Other components are unaffected
+        assert_eq!(limiter.check_at("calm", start + Duration::from_millis(200)), RateDecision::Allow);
+        assert_eq!(limiter.check_at("storm", start + Duration::from_millis(600)), RateDecision::Flush);
+        assert_eq!(limiter.check_at("storm", start + Duration::from_millis(601)), RateDecision::Allow);
+
+        let events = limiter.drain_events();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], BreakerEvent::Tripped { reconciles_in_window: 4, .. }));
+        assert!(matches!(&events[1], BreakerEvent::Reset { suppressed_updates: 2, .. }));
+    }
+
+    #[test]
+    fn test_window_resets_budget() {
+        let limiter = limiter();
+        let start = Instant::now();
+
+        for window in 0..5u64 {
+            for i in 0..3 {
+                let at = start + Duration::from_millis(window * 100 + i);
+                assert_eq!(limiter.check_at("steady", at), RateDecision::Allow);
+            }
+        }
+    }
+
+    #[test]
+    fn test_close_expired() {
+        let limiter = limiter();
+        let start = Instant::now();
+        for i in 0..4 {
+            limiter.check_at("storm", start + Duration::from_millis(i));
+        }
+
+        assert!(limiter.close_expired_at(start + Duration::from_millis(100)).is_empty());
+        assert_eq!(limiter.close_expired_at(start + Duration::from_millis(600)), vec!["storm".to_string()]);
+    }
+}
@@ -0,0 +1,233 @@
+//! Rebasing a patch batch onto a newer tree
+//!
+//! A batch computed against `old_base` may arrive after another change already
+//! turned the tree into `new_base` (see `conflicts` for detecting the overlap).
+//! Hex paths don't shift when siblings come and go, so most patches still point at
+//! the right node; `rebase_patches` re-anchors the rest:
+//! - UpdateProps carries a whole prop set, so only the props it changed relative to
+//!   the old tree are applied on top of the new tree's props
+//! - ReorderChildren leaves out keys that are gone from the new tree
+//! - a patch whose target was removed, a Create whose position was taken, a Remove
+//!   of a node that's already gone, and anything that otherwise doesn't apply to the
+//!   new tree are dropped and reported
+//!
+//! Patches are checked against the new tree as left by the rebased patches before
+//! them, so a batch that creates a node and then updates inside it still works.
+
+use crate::apply::apply_patch_indexed;
+use crate::error::Result;
+use crate::last_error::FfiCall;
+use crate::path::HexPath;
+use crate::tree_index::TreeIndex;
+use crate::tree_store::json_or_error;
+use crate::vdom::{Patch, VNode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+/// A patch that couldn't be re-anchored
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DroppedPatch {
+    /// Index in the original batch
+    pub index: usize,
+    pub patch: Patch,
+    pub reason: String,
+}
+
+/// A batch re-anchored onto a newer tree
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RebaseResult {
+    pub patches: Vec<Patch>,
+    pub dropped: Vec<DroppedPatch>,
+}
+
+/// Tree with its index, kept in step as patches are applied
+struct Working {
+    tree: VNode,
+    index: TreeIndex,
+}
+
+impl Working {
+    fn new(tree: &VNode) -> Self {
+        Self { tree: tree.clone(), index: TreeIndex::build(tree) }
+    }
+
+    fn get(&self, path: &HexPath) -> Option<&VNode> {
+        self.index.get(&self.tree, path)
+    }
+
+    fn apply(&mut self, patch: &Patch) -> Result<()> {
+        apply_patch_indexed(&mut self.tree, &self.index, patch)?;
+        self.index.invalidate(patch, &self.tree);
+        Ok(())
+    }
+}
+
+fn is_template(patch: &Patch) -> bool {
+    matches!(
+        patch,
+        Patch::UpdateTextTemplate { .. }
+            | Patch::UpdatePropsTemplate { .. }
+            | Patch::UpdateListTemplate { .. }
+            | Patch::ReorderTemplate { .. }
+            | Patch::ReplaceConditional { .. }
+            | Patch::UpdateAttributeStatic { .. }
+            | Patch::UpdateAttributeDynamic { .. }
+    )
+}
+
+/// Re-anchor `patches`, computed against `old_base`, onto `new_base`
+pub fn rebase_patches(patches: &[Patch], old_base: &VNode, new_base: &VNode) -> RebaseResult {
+    let mut old = Working::new(old_base);
+    let mut new = Working::new(new_base);
+    let mut result = RebaseResult::default();
+
+    for (index, patch) in patches.iter().enumerate() {
+        let rebased = rebase_one(patch, &old, &new);
+        if !is_template(patch) {
+            // Keep the batch's own view of the tree for the next deltas
+            let _ = old.apply(patch);
+        }
+
+        let rebased = rebased.and_then(|rebased| {
+            if !is_template(&rebased) {
+                new.apply(&rebased).map_err(|e| e.to_string())?;
+            }
+            Ok(rebased)
+        });
+        match rebased {
+            Ok(rebased) => result.patches.push(rebased),
+            Err(reason) => result.dropped.push(DroppedPatch { index, patch: patch.clone(), reason }),
+        }
+    }
+    result
+}
+
+fn rebase_one(patch: &Patch, old: &Working, new: &Working) -> std::result::Result<Patch, String> {
+    let path = patch.path();
+    match patch {
+        Patch::Create { .. } => {
+            if new.index.contains(path) {
+                return Err("position already taken in the new tree".to_string());
+            }
+            match path.parent() {
+                Some(parent) if !new.index.contains(&parent) => Err("parent removed in the new tree".to_string()),
+                _ => Ok(patch.clone()),
+            }
+        }
+
+        Patch::Remove { .. } if !new.index.contains(path) => Err("already removed in the new tree".to_string()),
+
+        Patch::UpdateProps { props, .. } => {
+            let Some(VNode::Element(current)) = new.get(path) else {
+                return Err("target element removed in the new tree".to_string());
+            };
+            let before = match old.get(path) {
+                Some(VNode::Element(el)) => el.props.clone(),
+                _ => HashMap::new(),
+            };
+            let mut rebased = current.props.clone();
+            rebased.retain(|name, _| !before.contains_key(name) || props.contains_key(name));
+            for (name, value) in props {
+                if before.get(name) != Some(value) {
+                    rebased.insert(name.clone(), value.clone());
+                }
+            }
+            Ok(Patch::UpdateProps { path: path.clone(), props: rebased })
+        }
+
+        Patch::ReorderChildren { order, preserve, .. } => {
+            let Some(VNode::Element(current)) = new.get(path) else {
+                return Err("target element removed in the new tree".to_string());
+            };
+            let present: std::collections::HashSet<&str> =
+                current.children.iter().flatten().filter_map(VNode::key).collect();
+            Ok(Patch::ReorderChildren {
+                path: path.clone(),
+                order: order.iter().filter(|key| present.contains(key.as_str())).cloned().collect(),
+                preserve: preserve.clone(),
+            })
+        }
+
+        Patch::Navigate { .. } | Patch::Custom { .. } | Patch::UpdateDocument { .. } | Patch::Replace { .. } => Ok(patch.clone()),
+
+        _ if !new.index.contains(path) => Err("target removed in the new tree".to_string()),
+        _ => Ok(patch.clone()),
+    }
+}
+
+/// Rebase a patch batch onto a newer tree
+/// Returns RebaseResult JSON (or {"error": ...})
+///
+/// # Safety
+/// - patches_json, old_base_json and new_base_json must be valid null-terminated UTF-8 strings
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_rebase_patches(
+    patches_json: *const c_char,
+    old_base_json: *const c_char,
+    new_base_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_rebase_patches", &[patches_json, old_base_json, new_base_json]);
+    json_or_error((|| -> Result<RebaseResult> {
+        let config = crate::validation::ValidationConfig::default();
+        let patches: Vec<Patch> = serde_json::from_str(CStr::from_ptr(patches_json).to_str()?)?;
+        let old_base = crate::validation::deserialize_vnode_safe(CStr::from_ptr(old_base_json).to_str()?, &config)?;
+        let new_base = crate::validation::deserialize_vnode_safe(CStr::from_ptr(new_base_json).to_str()?, &config)?;
+        Ok(rebase_patches(&patches, &old_base, &new_base))
+    })())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vdom::{VElement, VText};
+
+    fn props(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    /// <div {div_props}>{texts at child slots}</div>
+    fn tree(div_props: &[(&str, &str)], texts: &[(usize, &str)]) -> VNode {
+        let root = HexPath::from("10000000");
+        VNode::Element(VElement {
+            tag: "div".to_string(),
+            props: props(div_props),
+            children: texts
+                .iter()
+                .map(|&(slot, content)| Some(VNode::Text(VText { content: content.to_string(), path: root.child(slot) })))
+                .collect(),
+            key: None,
+            path: root,
+            source: None,
+        })
+    }
+
+    #[test]
+    fn test_rebase_merges_props_and_drops_removed_targets() {
+        let old_base = tree(&[("class", "a"), ("id", "x")], &[(0, "one"), (1, "two")]);
+        // Someone else changed id and removed the second text
+        let new_base = tree(&[("class", "a"), ("id", "y")], &[(0, "one")]);
+
+        let root = HexPath::from("10000000");
+        let batch = vec![
+            Patch::UpdateProps { path: root.clone(), props: props(&[("class", "b"), ("id", "x")]) },
+            Patch::UpdateText { path: root.child(1), content: "TWO".to_string() },
+            Patch::UpdateText { path: root.child(0), content: "ONE".to_string() },
+            Patch::Create { path: root.child(2), node: VNode::text("three") },
+            Patch::Remove { path: root.child(1) },
+        ];
+        let result = rebase_patches(&batch, &old_base, &new_base);
+
+        // The other change's id survives, ours to class applies
+        assert_eq!(result.patches[0], Patch::UpdateProps { path: root.clone(), props: props(&[("class", "b"), ("id", "y")]) });
+        assert_eq!(result.patches.len(), 3);
+        assert_eq!(result.dropped.iter().map(|d| d.index).collect::<Vec<_>>(), vec![1, 4]);
+
+        let mut rebuilt = new_base.clone();
+        crate::apply::apply_patches(&mut rebuilt, &result.patches).unwrap();
+        let VNode::Element(div) = &rebuilt else { unreachable!() };
+        assert_eq!(div.children.len(), 2);
+    }
+}
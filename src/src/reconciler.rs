@@ -1,32 +1,269 @@
 use crate::vdom::{VNode, VElement, Patch};
-use crate::error::Result;
+use crate::capabilities::{negotiate_patches, ClientCapabilities};
+use crate::document::{diff_head, is_document_setting, HEAD_TAG};
+use crate::error::{MinimactError, Result};
+use crate::ignored_regions::is_ignored;
 use crate::validation::ValidationConfig;
 use crate::path::HexPath;
+use bumpalo::collections::Vec as BumpVec;
+use bumpalo::Bump;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+use std::borrow::Cow;
+use std::cell::Cell;
 use std::collections::HashMap;
 
+/// Trade-off between few large patches and many surgical ones
+///
+/// Browsers want surgical patches (the default); terminals and other clients that
+/// repaint whole regions prefer fewer, bigger Replace patches.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReconcileStrategy {
+    /// Collapse an element's diff into a single Replace once it needs more than this many patches
+    pub max_subtree_patches: Option<usize>,
+    /// Collapse an element's diff into a single Replace once patches / subtree nodes reaches this ratio
+    pub replace_ratio: Option<f64>,
+    /// Match children by key when keys are present (otherwise always match by path)
+    pub use_keys: bool,
+    /// Only use keyed matching for child lists at least this long
+    pub keyed_min_children: usize,
+    /// Upper bound on the patches one reconcile may emit (anti-amplification guard)
+    pub max_patches: Option<usize>,
+    /// What to do when a diff exceeds `max_patches`
+    pub on_patch_limit: PatchLimitAction,
+    /// Attach focus/scroll preservation hints to Replace and ReorderChildren patches
+    pub preservation_hints: bool,
+    /// Patch kinds the client supports (None = everything)
+    /// Other kinds (lifecycle markers, list windows, ...) are downgraded or dropped
+    /// before `max_patches` is applied
+    pub capabilities: Option<ClientCapabilities>,
+}
+
+/// Handling of diffs that exceed `ReconcileStrategy::max_patches`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PatchLimitAction {
+    /// Collapse busy subtrees into Replace patches until the diff fits
+    #[default]
+    Collapse,
+    /// Fail the reconcile with MinimactError::TooManyPatches
+    Error,
+}
+
+impl Default for ReconcileStrategy {
+    fn default() -> Self {
+        Self::surgical()
+    }
+}
+
+impl ReconcileStrategy {
+    /// Never collapse; keyed matching whenever keys are present (browser default)
+    pub fn surgical() -> Self {
+        Self {
+            max_subtree_patches: None,
+            replace_ratio: None,
+            use_keys: true,
+            keyed_min_children: 0,
+            max_patches: None,
+            on_patch_limit: PatchLimitAction::Collapse,
+            preservation_hints: false,
+            capabilities: None,
+        }
+    }
+
+    /// Collapse busy subtrees into Replace patches (terminal-style clients)
+    pub fn replace_heavy() -> Self {
+        Self {
+            max_subtree_patches: Some(4),
+            replace_ratio: Some(0.5),
+            use_keys: true,
+            keyed_min_children: 8,
+            max_patches: None,
+            on_patch_limit: PatchLimitAction::Collapse,
+            preservation_hints: false,
+            capabilities: None,
+        }
+    }
+
+    /// Check if `emitted` patches for the subtree `node` should become one Replace
+    fn should_collapse(&self, emitted: usize, node: &VNode) -> bool {
+        // A single patch is already as small as a Replace gets
+        if emitted <= 1 {
+            return false;
+        }
+        self.max_subtree_patches.is_some_and(|max| emitted > max)
+            || self.replace_ratio.is_some_and(|ratio| emitted as f64 / node.count_nodes() as f64 >= ratio)
+    }
+
+    /// Check if a child list of `len` children (some keyed) should be matched by key
+    fn use_keyed_matching(&self, len: usize) -> bool {
+        self.use_keys && len >= self.keyed_min_children
+    }
+}
+
+/// Visible rows of a virtualized list: child indices `start..end`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListWindow {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Visible windows, by the path of the list element
+pub type ListWindows = HashMap<HexPath, ListWindow>;
+
+/// Per-call state threaded through the diff
+struct ReconcileCtx<'a> {
+    strategy: &'a ReconcileStrategy,
+    /// Scratch space for lookup tables; reset when the call returns
+    arena: &'a Bump,
+    /// Subtree never collapsed by the strategy (the root, while enforcing max_patches)
+    keep_whole: Option<&'a HexPath>,
+    /// Virtualized lists (see `reconcile_windowed`)
+    windows: Option<&'a ListWindows>,
+    /// Validates the new tree's nodes as the diff visits them
+    validation: Option<&'a DiffValidation<'a>>,
+}
+
+/// Validation of the nodes of the new tree that changed
+///
+/// A subtree equal to its old counterpart was already valid, so only nodes the
+/// diff visits (those that differ) and the subtrees it sends whole (Create,
+/// Replace) are checked, plus the node count of the whole tree.
+struct DiffValidation<'a> {
+    config: &'a ValidationConfig,
+    /// Depth of the new tree's root path
+    root_depth: usize,
+    time: Cell<std::time::Duration>,
+}
+
+impl<'a> DiffValidation<'a> {
+    fn new(config: &'a ValidationConfig, new: &VNode) -> Self {
+        Self { config, root_depth: new.path().depth(), time: Cell::new(std::time::Duration::ZERO) }
+    }
+
+    fn check(&self, validate: impl FnOnce(&ValidationConfig) -> Result<()>) -> Result<()> {
+        let start = std::time::Instant::now();
+        let result = validate(self.config);
+        self.time.set(self.time.get() + start.elapsed());
+        if result.is_err() {
+            crate::metrics::METRICS.record_validation_failure();
+        }
+        result
+    }
+
+    /// Validate the subtrees `patches` send whole
+    fn check_sent_subtrees(&self, patches: &[Patch]) -> Result<()> {
+        for patch in patches {
+            if let Patch::Create { path, node } | Patch::Replace { path, node, .. } = patch {
+                let depth = path.depth().saturating_sub(self.root_depth);
+                self.check(|config| node.validate_subtree(depth, config))?;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Reconcile two virtual DOM trees and produce a list of patches
 /// Now returns Result to handle validation errors
 pub fn reconcile(old: &VNode, new: &VNode) -> Result<Vec<Patch>> {
+    reconcile_with_strategy(old, new, &ReconcileStrategy::default())
+}
+
+/// Reconcile many independent tree pairs (e.g. an SSR farm's renders) in parallel
+/// on rayon's work-stealing pool; results are in the order of `pairs`
+///
+/// With the `parallel_reconcile` flag off the pairs are diffed one after another.
+pub fn reconcile_bulk(pairs: Vec<(VNode, VNode)>) -> Vec<Result<Vec<Patch>>> {
+    if !crate::flags::is_enabled(crate::flags::Flag::ParallelReconcile) {
+        return pairs.iter().map(|(old, new)| reconcile(old, new)).collect();
+    }
+    pairs.into_par_iter().map(|(old, new)| reconcile(&old, &new)).collect()
+}
+
+/// Reconcile two virtual DOM trees using a specific strategy
+pub fn reconcile_with_strategy(old: &VNode, new: &VNode, strategy: &ReconcileStrategy) -> Result<Vec<Patch>> {
+    reconcile_inner(old, new, strategy, None)
+}
+
+/// Reconcile with virtualized lists: for each list in `windows` only the visible
+/// rows are diffed into concrete patches
+///
+/// A window applies to both trees (the client shows those rows of the old list).
+/// Rows outside it aren't diffed; if any of them changed, or the row count did, the
+/// list gets one UpdateListWindow descriptor instead. Windowed lists are matched by
+/// path, never collapsed into a Replace by the strategy.
+pub fn reconcile_windowed(
+    old: &VNode,
+    new: &VNode,
+    strategy: &ReconcileStrategy,
+    windows: &ListWindows,
+) -> Result<Vec<Patch>> {
+    reconcile_inner(old, new, strategy, Some(windows))
+}
+
+fn reconcile_inner(
+    old: &VNode,
+    new: &VNode,
+    strategy: &ReconcileStrategy,
+    windows: Option<&ListWindows>,
+) -> Result<Vec<Patch>> {
+    let result = reconcile_once(old, new, strategy, windows);
+    if crate::strict::is_checking() {
+        let again = crate::strict::rerun(|| reconcile_once(old, new, strategy, windows));
+        crate::strict::check(
+            "reconcile",
+            || serde_json::json!({ "old": old, "new": new, "strategy": strategy, "windows": windows }),
+            &result.as_ref().map_err(ToString::to_string),
+            &again.as_ref().map_err(ToString::to_string),
+        );
+    }
+    result
+}
+
+fn reconcile_once(
+    old: &VNode,
+    new: &VNode,
+    strategy: &ReconcileStrategy,
+    windows: Option<&ListWindows>,
+) -> Result<Vec<Patch>> {
     let start = std::time::Instant::now();
     crate::log_debug!("Starting reconciliation");
 
-    // Validate both trees first
+    // The old tree is what the client already has, so only what changed is validated
     let config = ValidationConfig::default();
-    if let Err(e) = old.validate(&config) {
-        crate::metrics::METRICS.record_validation_failure();
-        return Err(e);
-    }
-    if let Err(e) = new.validate(&config) {
-        crate::metrics::METRICS.record_validation_failure();
-        return Err(e);
-    }
+    let validation = DiffValidation::new(&config, new);
 
     let mut patches = Vec::new();
-    let result = reconcile_node(old, new, &mut patches);
+    let result = validation
+        .check(|config| new.validate_node_count(config))
+        .and_then(|()| {
+            crate::arena::with_reconcile_arena(|arena| {
+                let ctx = ReconcileCtx { strategy, arena, keep_whole: None, windows, validation: Some(&validation) };
+                reconcile_node(old, new, &ctx, &mut patches)
+            })
+        })
+        .and_then(|()| {
+            // Markers count against the limit too
+            patches = for_client(std::mem::take(&mut patches), old, strategy);
+            enforce_patch_limit(old, new, strategy, windows, &mut patches)
+        })
+        .and_then(|()| validation.check_sent_subtrees(&patches));
+    if result.is_ok() && strategy.preservation_hints {
+        crate::preservation::attach_preservation_hints(&mut patches, old);
+    }
+    crate::metrics::METRICS.record_validation_time(validation.time.get());
 
     let duration = start.elapsed();
     match result {
         Ok(()) => {
+            // Hidden rows of windowed lists are deliberately left unpatched, and
+            // negotiation may drop patches a client can do without (region markers)
+            #[cfg(feature = "paranoid")]
+            if windows.is_none() && strategy.capabilities.is_none() {
+                crate::paranoid::check_reconcile(old, new, &patches);
+            }
+
             crate::log_info!("Reconciliation complete: {} patches generated", patches.len());
             crate::metrics::METRICS.record_reconcile(duration, patches.len(), false);
             Ok(patches)
@@ -38,6 +275,88 @@ pub fn reconcile(old: &VNode, new: &VNode) -> Result<Vec<Patch>> {
     }
 }
 
+/// A raw diff as the client gets it: lifecycle markers inserted, then negotiated
+/// down to `strategy.capabilities`
+fn for_client(patches: Vec<Patch>, old: &VNode, strategy: &ReconcileStrategy) -> Vec<Patch> {
+    let patches = crate::lifecycle::lifecycle_config().insert_markers(patches, old);
+    match &strategy.capabilities {
+        // Reconciler output is concrete, so no state is needed to downgrade it
+        Some(capabilities) => negotiate_patches(patches, capabilities, &HashMap::new()),
+        None => patches,
+    }
+}
+
+/// Apply `strategy.max_patches` to a finished diff (as the client gets it)
+/// Re-diffs with a shrinking per-subtree limit (the root exempt), so the busiest
+/// subtrees collapse into a single Replace first; if even that doesn't fit, the
+/// whole tree becomes one Replace, without its markers if those don't fit either
+fn enforce_patch_limit(
+    old: &VNode,
+    new: &VNode,
+    strategy: &ReconcileStrategy,
+    windows: Option<&ListWindows>,
+    patches: &mut Vec<Patch>,
+) -> Result<()> {
+    let Some(max) = strategy.max_patches.map(|max| max.max(1)) else { return Ok(()) };
+    let emitted = patches.len();
+    if emitted <= max {
+        return Ok(());
+    }
+
+    crate::log_warn!("Reconcile: {} patches exceed the limit of {}", emitted, max);
+    if strategy.on_patch_limit == PatchLimitAction::Error {
+        crate::metrics::METRICS.record_patch_limit_exceeded(0);
+        return Err(MinimactError::TooManyPatches { count: emitted, max });
+    }
+
+    let mut subtree_limit = max;
+    while patches.len() > max && subtree_limit > 1 {
+        subtree_limit = (subtree_limit / 2).max(1);
+        let collapsing = ReconcileStrategy {
+            max_subtree_patches: Some(strategy.max_subtree_patches.map_or(subtree_limit, |m| m.min(subtree_limit))),
+            max_patches: None,
+            ..strategy.clone()
+        };
+        patches.clear();
+        crate::arena::with_reconcile_arena(|arena| {
+            let ctx = ReconcileCtx { strategy: &collapsing, arena, keep_whole: Some(new.path()), windows, validation: None };
+            reconcile_node(old, new, &ctx, patches)
+        })?;
+        *patches = for_client(std::mem::take(patches), old, strategy);
+    }
+    if patches.len() > max {
+        let replace = Patch::Replace { path: new.path().clone(), node: new.clone(), preserve: None };
+        *patches = for_client(vec![replace.clone()], old, strategy);
+        if patches.len() > max {
+            crate::log_warn!("Reconcile: lifecycle markers of the replaced tree exceed the limit of {}", max);
+            *patches = vec![replace];
+        }
+    }
+
+    crate::metrics::METRICS.record_patch_limit_exceeded(emitted - patches.len());
+    Ok(())
+}
+
+/// Reconcile as part of the request identified by `correlation_id`
+/// The reconcile time and patch count are recorded in that request's timing breakdown
+pub fn reconcile_traced(
+    old: &VNode,
+    new: &VNode,
+    strategy: &ReconcileStrategy,
+    correlation_id: &str,
+) -> Result<Vec<Patch>> {
+    let start = std::time::Instant::now();
+    let result = crate::logging::with_log_context(crate::logging::LogContext::correlation(correlation_id), || {
+        reconcile_with_strategy(old, new, strategy)
+    });
+    let detail = match &result {
+        Ok(patches) => format!("{} patches", patches.len()),
+        Err(e) => format!("failed: {}", e),
+    };
+    crate::correlation::record_span(correlation_id, crate::correlation::TraceStage::Reconcile, start.elapsed(), Some(detail));
+    result
+}
+
 /// Reconcile with custom validation config
 pub fn reconcile_with_config(
     old: &VNode,
@@ -48,11 +367,18 @@ pub fn reconcile_with_config(
     new.validate(config)?;
 
     let mut patches = Vec::new();
-    reconcile_node(old, new, &mut patches)?;
+    crate::arena::with_reconcile_arena(|arena| {
+        let strategy = ReconcileStrategy::default();
+        reconcile_node(old, new, &ReconcileCtx { strategy: &strategy, arena, keep_whole: None, windows: None, validation: None }, &mut patches)
+    })?;
+
+    #[cfg(feature = "paranoid")]
+    crate::paranoid::check_reconcile(old, new, &patches);
+
     Ok(patches)
 }
 
-fn reconcile_node(old: &VNode, new: &VNode, patches: &mut Vec<Patch>) -> Result<()> {
+fn reconcile_node(old: &VNode, new: &VNode, ctx: &ReconcileCtx, patches: &mut Vec<Patch>) -> Result<()> {
     // Get path from new VNode (paths come from transpilation)
     let path = new.path();
 
@@ -61,6 +387,7 @@ fn reconcile_node(old: &VNode, new: &VNode, patches: &mut Vec<Patch>) -> Result<
         patches.push(Patch::Replace {
             path: path.clone(),
             node: new.clone(),
+            preserve: None,
         });
         return Ok(());
     }
@@ -71,6 +398,9 @@ fn reconcile_node(old: &VNode, new: &VNode, patches: &mut Vec<Patch>) -> Result<
     if nodes_equal {
         return Ok(());
     }
+    if let Some(validation) = ctx.validation {
+        validation.check(|config| new.validate_local(config))?;
+    }
 
     match (old, new) {
         // Both are text nodes
@@ -88,18 +418,75 @@ fn reconcile_node(old: &VNode, new: &VNode, patches: &mut Vec<Patch>) -> Result<
             // No-op: null → null
         }
 
-        // Both are elements with the same tag
-        (VNode::Element(old_el), VNode::Element(new_el)) if old_el.tag == new_el.tag => {
-            // Check if props changed
+        // The document head: settings become UpdateDocument patches
+        (VNode::Element(old_el), VNode::Element(new_el)) if old_el.tag == HEAD_TAG && new_el.tag == HEAD_TAG => {
             if old_el.props != new_el.props {
                 patches.push(Patch::UpdateProps {
                     path: path.clone(),
                     props: new_el.props.clone(),
                 });
             }
+            patches.extend(diff_head(old_el, new_el));
+
+            // Everything else in the head (charset, scripts) is diffed as usual
+            let others = |el: &VElement| -> Vec<Option<VNode>> {
+                el.children.iter().map(|child| child.clone().filter(|node| !is_document_setting(node))).collect()
+            };
+            reconcile_children_by_path(&others(old_el), &others(new_el), ctx, patches)?;
+        }
+
+        // A client-owned region (see ignored_regions): nothing inside is compared
+        (VNode::Element(old_el), VNode::Element(new_el))
+            if old_el.tag == new_el.tag && is_ignored(old_el) && is_ignored(new_el) => {}
+
+        // Region handed back: the client's code may have changed anything in it, so resend it whole
+        (VNode::Element(old_el), VNode::Element(new_el)) if old_el.tag == new_el.tag && is_ignored(old_el) => {
+            patches.push(Patch::SetIgnored { path: path.clone(), ignored: false });
+            patches.push(Patch::Replace {
+                path: path.clone(),
+                node: new.clone(),
+                preserve: None,
+            });
+        }
+
+        // Both are elements with the same tag
+        (VNode::Element(old_el), VNode::Element(new_el)) if old_el.tag == new_el.tag => {
+            let first_patch = patches.len();
+
+            // Check if props changed
+            if old_el.props != new_el.props {
+                if let Some(props) = changed_props(old_el, new_el) {
+                    patches.push(Patch::UpdateProps { path: path.clone(), props });
+                }
+            }
 
             // Reconcile children
-            reconcile_children(old_el, new_el, patches)?;
+            let window = ctx.windows.and_then(|windows| windows.get(path));
+            match window {
+                Some(&window) => reconcile_windowed_children(old_el, new_el, window, ctx, patches)?,
+                None => reconcile_children(old_el, new_el, ctx, patches)?,
+            }
+
+            if window.is_none()
+                && ctx.keep_whole != Some(path)
+                && ctx.strategy.should_collapse(patches.len() - first_patch, new)
+            {
+                crate::log_debug!("Reconcile: collapsing {} patches at '{}' into Replace", patches.len() - first_patch, path);
+                patches.truncate(first_patch);
+                patches.push(Patch::Replace {
+                    path: path.clone(),
+                    node: new.clone(),
+                    preserve: None,
+                });
+            } else if old_el.props != new_el.props {
+                // A cascading attribute changed the look of the children too (see layout_hints)
+                patches.extend(crate::layout_hints::cascade_rules().hint(path, old_el, new_el));
+            }
+
+            // Region handed to the client's code, after this last update from the server
+            if is_ignored(new_el) {
+                patches.push(Patch::SetIgnored { path: path.clone(), ignored: true });
+            }
         }
 
         // Different node types or different tags - replace entire subtree
@@ -107,91 +494,230 @@ fn reconcile_node(old: &VNode, new: &VNode, patches: &mut Vec<Patch>) -> Result<
             patches.push(Patch::Replace {
                 path: path.clone(),
                 node: new.clone(),
+                preserve: None,
             });
         }
     }
     Ok(())
 }
 
+/// The props to send for an element whose props differ verbatim; None when they
+/// only differ in spelling (see prop_normalization) or in values that mean the same
+/// (see attribute_semantics)
+fn changed_props(old_el: &VElement, new_el: &VElement) -> Option<HashMap<String, String>> {
+    let (old_props, new_props) = match crate::prop_normalization::prop_normalization().naming {
+        Some(naming) => (
+            Cow::Owned(crate::prop_normalization::normalize_props(&old_el.props, naming)),
+            Cow::Owned(crate::prop_normalization::normalize_props(&new_el.props, naming)),
+        ),
+        None => (Cow::Borrowed(&old_el.props), Cow::Borrowed(&new_el.props)),
+    };
+    // Marking a region: the SetIgnored that follows carries the marker attribute
+    let new_props = if is_ignored(new_el) {
+        let mut props = new_props.into_owned();
+        props.remove(crate::ignored_regions::IGNORE_ATTR);
+        Cow::Owned(props)
+    } else {
+        new_props
+    };
+    let attributes = crate::attribute_semantics::attribute_table();
+    if attributes.props_equivalent(&old_props, &new_props) {
+        return None;
+    }
+    Some(attributes.strip_absent(new_props.into_owned()))
+}
+
 fn reconcile_children(
     old_el: &VElement,
     new_el: &VElement,
+    ctx: &ReconcileCtx,
     patches: &mut Vec<Patch>,
 ) -> Result<()> {
     let old_children = &old_el.children;
     let new_children = &new_el.children;
 
     // Check if we can use keyed reconciliation
-    // Skip null children when building keyed maps
-    let old_keyed: HashMap<&str, (usize, &VNode)> = old_children
+    let has_keys = old_children
         .iter()
-        .enumerate()
-        .filter_map(|(i, opt_node)| {
-            opt_node.as_ref().and_then(|node| node.key().map(|k| (k, (i, node))))
-        })
-        .collect();
+        .chain(new_children.iter())
+        .any(|child| child.as_ref().is_some_and(|node| node.key().is_some()));
+    if !has_keys || !ctx.strategy.use_keyed_matching(new_children.len()) {
+        // Path-based reconciliation (optimized - no index tracking!)
+        return reconcile_children_by_path(old_children, new_children, ctx, patches);
+    }
 
-    let new_keyed: HashMap<&str, (usize, &VNode)> = new_children
-        .iter()
-        .enumerate()
-        .filter_map(|(i, opt_node)| {
-            opt_node.as_ref().and_then(|node| node.key().map(|k| (k, (i, node))))
-        })
-        .collect();
+    // Skip null children when building keyed maps
+    let old_keyed = keyed_children(old_children, ctx.arena);
+    let new_keyed = keyed_children(new_children, ctx.arena);
 
-    // If we have keys, use keyed reconciliation
-    if !old_keyed.is_empty() || !new_keyed.is_empty() {
-        reconcile_keyed_children(old_children, new_children, &old_keyed, &new_keyed, patches)?;
-    } else {
-        // Path-based reconciliation (optimized - no index tracking!)
-        reconcile_children_by_path(old_children, new_children, patches)?;
+    reconcile_keyed_children(&new_el.path, old_children, new_children, &old_keyed, &new_keyed, ctx, patches)
+}
+
+/// Diff the visible rows of a virtualized list; hidden rows only get a descriptor
+fn reconcile_windowed_children(
+    old_el: &VElement,
+    new_el: &VElement,
+    window: ListWindow,
+    ctx: &ReconcileCtx,
+    patches: &mut Vec<Patch>,
+) -> Result<()> {
+    let split = |children: &[Option<VNode>]| {
+        let end = window.end.min(children.len());
+        let start = window.start.min(end);
+        (start, end)
+    };
+    let (old_start, old_end) = split(&old_el.children);
+    let (new_start, new_end) = split(&new_el.children);
+
+    reconcile_children_by_path(
+        &old_el.children[old_start..old_end],
+        &new_el.children[new_start..new_end],
+        ctx,
+        patches,
+    )?;
+
+    let hidden_changed = old_el.children.len() != new_el.children.len()
+        || old_el.children[..old_start] != new_el.children[..new_start]
+        || old_el.children[old_end..] != new_el.children[new_end..];
+    if hidden_changed {
+        patches.push(Patch::UpdateListWindow {
+            path: new_el.path.clone(),
+            total: new_el.children.len(),
+            start: new_start,
+            end: new_end,
+        });
     }
     Ok(())
 }
 
+/// Keyed children sorted by key (binary-searchable), allocated in the reconcile arena
+/// With duplicate keys the last child wins
+type KeyedChildren<'b, 'n> = BumpVec<'b, (&'n str, &'n VNode)>;
+
+fn keyed_children<'b, 'n>(children: &'n [Option<VNode>], arena: &'b Bump) -> KeyedChildren<'b, 'n> {
+    let mut keyed = BumpVec::from_iter_in(
+        children
+            .iter()
+            .rev()
+            .filter_map(|opt_node| opt_node.as_ref().and_then(|node| node.key().map(|k| (k, node)))),
+        arena,
+    );
+    // Stable sort on the reversed list keeps the last duplicate first; dedup drops the rest
+    keyed.sort_by(|a, b| a.0.cmp(b.0));
+    keyed.dedup_by(|a, b| a.0 == b.0);
+    keyed
+}
+
+fn find_keyed<'n>(keyed: &[(&str, &'n VNode)], key: &str) -> Option<&'n VNode> {
+    keyed.binary_search_by(|(k, _)| (*k).cmp(key)).ok().map(|i| keyed[i].1)
+}
+
+/// Child lists up to this size are matched by linear scan over stack-allocated
+/// vectors instead of hash maps (benches/small_diffs.rs)
+const SMALL_CHILD_LIST: usize = 16;
+
+type SmallChildList<'a> = SmallVec<[(&'a HexPath, &'a VNode); SMALL_CHILD_LIST]>;
+
 /// Path-based child reconciliation - OPTIMIZED
 /// Uses VNode paths directly for O(1) lookup instead of index-based matching
 fn reconcile_children_by_path(
     old_children: &[Option<VNode>],
     new_children: &[Option<VNode>],
+    ctx: &ReconcileCtx,
+    patches: &mut Vec<Patch>,
+) -> Result<()> {
+    if old_children.len() <= SMALL_CHILD_LIST && new_children.len() <= SMALL_CHILD_LIST {
+        reconcile_small_children_by_path(old_children, new_children, ctx, patches)
+    } else {
+        reconcile_large_children_by_path(old_children, new_children, ctx, patches)
+    }
+}
+
+/// Children of a node sorted by path, allocated in `arena`
+fn children_by_path<'b, 'n>(children: &'n [Option<VNode>], arena: &'b Bump) -> BumpVec<'b, (&'n HexPath, &'n VNode)> {
+    let mut table = BumpVec::from_iter_in(
+        children.iter().filter_map(|opt| opt.as_ref()).map(|node| (node.path(), node)),
+        arena,
+    );
+    table.sort_unstable_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+    table
+}
+
+fn find_by_path(table: &[(&HexPath, &VNode)], path: &HexPath) -> std::result::Result<usize, usize> {
+    table.binary_search_by(|(p, _)| p.as_str().cmp(path.as_str()))
+}
+
+/// Sorted-table matching for long child lists
+fn reconcile_large_children_by_path(
+    old_children: &[Option<VNode>],
+    new_children: &[Option<VNode>],
+    ctx: &ReconcileCtx,
     patches: &mut Vec<Patch>,
 ) -> Result<()> {
-    // Build path-based maps for O(1) lookup
-    let old_by_path: HashMap<&HexPath, &VNode> = old_children
+    // Path-sorted tables in the reconcile arena, matched by binary search
+    let old_by_path = children_by_path(old_children, ctx.arena);
+    let new_by_path = children_by_path(new_children, ctx.arena);
+
+    // Check for creates (in new but not old) or updates
+    for &(path, new_node) in new_by_path.iter() {
+        match find_by_path(&old_by_path, path) {
+            // Both exist at this path - reconcile them
+            Ok(i) => reconcile_node(old_by_path[i].1, new_node, ctx, patches)?,
+            // New node at this path - create it (unless it's VNull)
+            Err(_) if !new_node.is_null() => patches.push(Patch::Create {
+                path: path.clone(),
+                node: new_node.clone(),
+            }),
+            Err(_) => {}
+        }
+    }
+
+    // Check for removes (in old but not new)
+    for &(path, old_node) in old_by_path.iter() {
+        if !old_node.is_null() && find_by_path(&new_by_path, path).is_err() {
+            // Old node removed or became null
+            patches.push(Patch::Remove {
+                path: path.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Linear-scan matching for short child lists: no hashing, no heap
+fn reconcile_small_children_by_path(
+    old_children: &[Option<VNode>],
+    new_children: &[Option<VNode>],
+    ctx: &ReconcileCtx,
+    patches: &mut Vec<Patch>,
+) -> Result<()> {
+    let old_by_path: SmallChildList = old_children
         .iter()
         .filter_map(|opt| opt.as_ref())
         .map(|node| (node.path(), node))
         .collect();
 
-    let new_by_path: HashMap<&HexPath, &VNode> = new_children
+    let new_by_path: SmallChildList = new_children
         .iter()
         .filter_map(|opt| opt.as_ref())
         .map(|node| (node.path(), node))
         .collect();
 
-    // Check for creates (in new but not old) or updates
-    for (path, new_node) in &new_by_path {
-        if let Some(old_node) = old_by_path.get(path) {
-            // Both exist at this path - reconcile them
-            reconcile_node(old_node, new_node, patches)?;
-        } else {
-            // New node at this path - create it (unless it's VNull)
-            if !new_node.is_null() {
-                patches.push(Patch::Create {
-                    path: (*path).clone(),
-                    node: (*new_node).clone(),
-                });
-            }
+    for &(path, new_node) in &new_by_path {
+        match old_by_path.iter().find(|(old_path, _)| *old_path == path) {
+            Some(&(_, old_node)) => reconcile_node(old_node, new_node, ctx, patches)?,
+            None if !new_node.is_null() => patches.push(Patch::Create {
+                path: path.clone(),
+                node: new_node.clone(),
+            }),
+            None => {}
         }
     }
 
-    // Check for removes (in old but not new)
-    for (path, old_node) in &old_by_path {
-        if !new_by_path.contains_key(path) && !old_node.is_null() {
-            // Old node removed or became null
-            patches.push(Patch::Remove {
-                path: (*path).clone(),
-            });
+    for &(path, old_node) in &old_by_path {
+        if !old_node.is_null() && !new_by_path.iter().any(|(new_path, _)| *new_path == path) {
+            patches.push(Patch::Remove { path: path.clone() });
         }
     }
 
@@ -202,6 +728,7 @@ fn reconcile_children_by_path(
 fn reconcile_indexed_children(
     old_children: &[Option<VNode>],
     new_children: &[Option<VNode>],
+    ctx: &ReconcileCtx,
     patches: &mut Vec<Patch>,
 ) -> Result<()> {
     let old_len = old_children.len();
@@ -213,7 +740,7 @@ fn reconcile_indexed_children(
         match (&old_children[i], &new_children[i]) {
             (Some(old_node), Some(new_node)) => {
                 // Both exist - reconcile (path comes from VNode)
-                reconcile_node(old_node, new_node, patches)?;
+                reconcile_node(old_node, new_node, ctx, patches)?;
             }
             (None, Some(new_node)) => {
                 // Old was null, new exists - create
@@ -256,101 +783,226 @@ fn reconcile_indexed_children(
 }
 
 fn reconcile_keyed_children(
+    parent_path: &HexPath,
     old_children: &[Option<VNode>],
     new_children: &[Option<VNode>],
-    old_keyed: &HashMap<&str, (usize, &VNode)>,
-    new_keyed: &HashMap<&str, (usize, &VNode)>,
+    old_keyed: &[(&str, &VNode)],
+    new_keyed: &[(&str, &VNode)],
+    ctx: &ReconcileCtx,
     patches: &mut Vec<Patch>,
 ) -> Result<()> {
     let mut old_idx = 0;
-    let mut new_idx = 0;
-
-    // Build a map of new keys for quick lookup
-    let new_keys_set: std::collections::HashSet<&str> = new_keyed.keys().copied().collect();
-
-    // Process all new children (preserving VNode indices including nulls)
-    while new_idx < new_children.len() {
-        if let Some(new_child) = &new_children[new_idx] {
-            if let Some(new_key) = new_child.key() {
-                // This child has a key
-                if let Some(&(_old_pos, old_node)) = old_keyed.get(new_key) {
-                    // Key exists in old children - reconcile (path from VNode)
-                    reconcile_node(old_node, new_child, patches)?;
-                } else {
-                    // New key - create node
-                    patches.push(Patch::Create {
-                        path: new_child.path().clone(),
-                        node: new_child.clone(),
-                    });
-                }
-            } else {
-                // No key - try to match with old non-keyed children
+
+    // First pass: match each new child to the old child it updates (None = created)
+    // Preserves VNode indices including nulls
+    let mut matched: BumpVec<Option<&VNode>> = BumpVec::with_capacity_in(new_children.len(), ctx.arena);
+    for new_child in new_children {
+        let old_match = match new_child {
+            // Key exists in old children - reconcile (path from VNode)
+            Some(new_child) if new_child.key().is_some() => {
+                new_child.key().and_then(|key| find_keyed(old_keyed, key))
+            }
+            // No key - try to match with old non-keyed children
+            Some(_) => {
                 while old_idx < old_children.len() && old_children[old_idx].is_none() {
                     old_idx += 1; // Skip nulls in old children
                 }
-
-                if old_idx < old_children.len() {
-                    if let Some(old_child) = &old_children[old_idx] {
-                        if old_child.key().is_none() {
-                            reconcile_node(old_child, new_child, patches)?;
-                            old_idx += 1;
-                        } else {
-                            // Create new child
-                            patches.push(Patch::Create {
-                                path: new_child.path().clone(),
-                                node: new_child.clone(),
-                            });
-                        }
+                match old_children.get(old_idx) {
+                    Some(Some(old_child)) if old_child.key().is_none() => {
+                        old_idx += 1;
+                        Some(old_child)
                     }
-                } else {
-                    // Create new child
-                    patches.push(Patch::Create {
-                        path: new_child.path().clone(),
-                        node: new_child.clone(),
-                    });
+                    _ => None,
                 }
             }
+            // If new_child is None, skip it (null in new VNode)
+            None => None,
+        };
+        matched.push(old_match);
+    }
+
+    // A matched child whose path moved (a list that renders paths by position) can't
+    // be patched where the client has it: another child may own its new path. It's
+    // removed from the old path and created at the new one. When that happens, or a
+    // created child's path belongs to a removed one, the Removes go first so the new
+    // paths are free
+    let by_path = |a: &&HexPath, b: &&HexPath| a.as_str().cmp(b.as_str());
+    let mut moved_out: BumpVec<&HexPath> = BumpVec::new_in(ctx.arena);
+    for (new_child, old_match) in new_children.iter().zip(matched.iter_mut()) {
+        if let (Some(new_child), Some(old_node)) = (new_child, *old_match) {
+            if old_node.path() != new_child.path() {
+                moved_out.push(old_node.path());
+                *old_match = None;
+            }
         }
-        // If new_child is None, skip it (null in new VNode)
+    }
+    let mut removed: BumpVec<&HexPath> = BumpVec::from_iter_in(
+        old_keyed
+            .iter()
+            .filter(|&&(old_key, _)| find_keyed(new_keyed, old_key).is_none())
+            .map(|&(_, old_node)| old_node.path()),
+        ctx.arena,
+    );
+    let mut removed_sorted = removed.clone();
+    removed_sorted.sort_unstable_by(by_path);
+    let reuses_removed_path = new_children.iter().zip(matched.iter()).any(|(new_child, old_match)| {
+        old_match.is_none() && new_child.as_ref().is_some_and(|n| removed_sorted.binary_search_by(|p| by_path(p, &n.path())).is_ok())
+    });
+    let removes_first = !moved_out.is_empty() || reuses_removed_path;
+    if removes_first {
+        moved_out.append(&mut removed);
+        patches.extend(moved_out.iter().map(|&path| Patch::Remove { path: path.clone() }));
+        moved_out.sort_unstable_by(by_path);
+    }
 
-        new_idx += 1;
+    // Paths of surviving children are fixed; created children must land between them.
+    // Paths of removed children stay off limits too, unless their Remove came first
+    let mut taken = TakenPaths::new_in(ctx.arena);
+    for old_child in old_children.iter().flatten() {
+        if moved_out.binary_search_by(|p| by_path(p, &old_child.path())).is_err() {
+            taken.insert(Cow::Borrowed(old_child.path()));
+        }
+    }
+    for (new_child, old_match) in new_children.iter().zip(matched.iter()) {
+        if let Some(new_child) = old_match.and(new_child.as_ref()) {
+            taken.insert(Cow::Borrowed(new_child.path()));
+        }
+    }
+    let mut next_fixed: BumpVec<Option<&HexPath>> = BumpVec::from_iter_in(new_children.iter().map(|_| None), ctx.arena);
+    let mut following = None;
+    for i in (0..new_children.len()).rev() {
+        next_fixed[i] = following;
+        if let (Some(new_child), Some(_)) = (&new_children[i], matched[i]) {
+            following = Some(new_child.path());
+        }
     }
 
-    // Remove old children that don't exist in new children
-    for (old_key, &(old_pos, old_node)) in old_keyed.iter() {
-        if !new_keys_set.contains(old_key) {
-            patches.push(Patch::Remove {
-                path: old_node.path().clone(),
-            });
+    // Second pass: emit patches in new order
+    let mut prev_path: Option<Cow<HexPath>> = None;
+    for (i, new_child) in new_children.iter().enumerate() {
+        let Some(new_child) = new_child else { continue };
+
+        if let Some(old_node) = matched[i] {
+            reconcile_node(old_node, new_child, ctx, patches)?;
+            prev_path = Some(Cow::Borrowed(new_child.path()));
+            continue;
         }
+
+        let path = insertion_path(parent_path, new_child.path(), prev_path.as_deref(), next_fixed[i], &taken);
+        let mut node = new_child.clone();
+        if &path != new_child.path() {
+            crate::log_debug!("Reconcile: placing new child at '{}' (transpiled path '{}')", path, new_child.path());
+            node.rebase_path(&path);
+        }
+        patches.push(Patch::Create { path: path.clone(), node });
+        // Later siblings must not reuse this path or the transpiled one
+        taken.insert(Cow::Owned(path.clone()));
+        taken.insert(Cow::Borrowed(new_child.path()));
+        prev_path = Some(Cow::Owned(path));
     }
 
-    // Check if we need to reorder (only for non-null keyed children)
+    // Remove old children that don't exist in new children
+    patches.extend(removed.iter().map(|&path| Patch::Remove { path: path.clone() }));
+
+    // ReorderChildren is only needed when survivors change their relative order or a
+    // keyed child is created (Create places by path, which a past reorder may have
+    // decoupled from position). Pure updates and removals keep the order as it is.
     let new_key_order: Vec<String> = new_children
         .iter()
         .filter_map(|opt_n| opt_n.as_ref().and_then(|n| n.key().map(String::from)))
         .collect();
+    let created_keyed = new_children
+        .iter()
+        .zip(matched.iter())
+        .any(|(new_child, old_match)| old_match.is_none() && new_child.as_ref().is_some_and(|n| n.key().is_some()));
+    let survivors_in_order = old_children
+        .iter()
+        .filter_map(|opt_n| opt_n.as_ref().and_then(|n| n.key()))
+        .filter(|key| find_keyed(new_keyed, key).is_some())
+        .eq(new_key_order.iter().map(String::as_str));
 
-    // Get parent path from first non-null child
-    if !new_key_order.is_empty() {
-        if let Some(Some(first_child)) = new_children.first() {
-            // Get parent path by removing last segment
-            let child_path = first_child.path();
-            if let Some(last_dot) = child_path.0.rfind('.') {
-                let parent_path = HexPath(child_path.0[..last_dot].to_string());
-                patches.push(Patch::ReorderChildren {
-                    path: parent_path,
-                    order: new_key_order,
-                });
-            }
-        }
+    if created_keyed || !survivors_in_order {
+        patches.push(Patch::ReorderChildren {
+            path: parent_path.clone(),
+            order: new_key_order,
+            preserve: None,
+        });
     }
     Ok(())
 }
 
+/// Paths already used under a parent, kept sorted for binary search
+struct TakenPaths<'b, 'n>(BumpVec<'b, Cow<'n, HexPath>>);
+
+impl<'b, 'n> TakenPaths<'b, 'n> {
+    fn new_in(arena: &'b Bump) -> Self {
+        Self(BumpVec::new_in(arena))
+    }
+
+    fn position(&self, path: &HexPath) -> std::result::Result<usize, usize> {
+        self.0.binary_search_by(|p| p.as_str().cmp(path.as_str()))
+    }
+
+    fn contains(&self, path: &HexPath) -> bool {
+        self.position(path).is_ok()
+    }
+
+    fn insert(&mut self, path: Cow<'n, HexPath>) {
+        if let Err(i) = self.position(&path) {
+            self.0.insert(i, path);
+        }
+    }
+
+    /// The used child of `parent` that comes last in document order
+    fn last_under(&self, parent: &HexPath) -> Option<&HexPath> {
+        self.0.iter().rev().map(|p| p.as_ref()).find(|p| p.parent().as_ref() == Some(parent))
+    }
+}
+
+/// Pick the path for a newly created child
+///
+/// The transpiled path is kept when it's a free slot of `parent` that lies between the
+/// previous sibling and the next surviving sibling; otherwise (runtime-rendered nodes,
+/// colliding or out-of-order paths) a path is allocated in the hex gap between them.
+/// In a permuted list the neighbours aren't in document order and there is no such gap;
+/// the child then goes after every used path (the ReorderChildren patch places it).
+fn insertion_path(
+    parent: &HexPath,
+    transpiled: &HexPath,
+    prev: Option<&HexPath>,
+    next: Option<&HexPath>,
+    taken: &TakenPaths,
+) -> HexPath {
+    use std::cmp::Ordering;
+
+    let fits = transpiled.parent().as_ref() == Some(parent)
+        && !taken.contains(transpiled)
+        && prev.is_none_or(|p| transpiled.cmp_document_order(p) == Ordering::Greater)
+        && next.is_none_or(|n| transpiled.cmp_document_order(n) == Ordering::Less);
+    if fits {
+        return transpiled.clone();
+    }
+
+    let allocated = match (prev, next) {
+        (Some(prev), Some(next)) => HexPath::between(prev, next),
+        (Some(prev), None) => prev.next_sibling(),
+        (None, Some(next)) => next.prev_sibling(),
+        (None, None) => Some(parent.child(0)),
+    };
+
+    allocated
+        .filter(|path| !taken.contains(path))
+        .or_else(|| taken.last_under(parent).and_then(HexPath::next_sibling))
+        .unwrap_or_else(|| {
+            crate::log_warn!("Reconcile: no gap left under '{}', keeping transpiled path '{}'", parent, transpiled);
+            transpiled.clone()
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::vdom::VText;
     use std::collections::HashMap;
 
     #[test]
@@ -362,7 +1014,7 @@ mod tests {
         assert_eq!(patches.len(), 1);
         match &patches[0] {
             Patch::UpdateText { path, content } => {
-                assert_eq!(path, &Vec::<usize>::new());
+                assert_eq!(path, &HexPath::root());
                 assert_eq!(content, "World");
             }
             _ => panic!("Expected UpdateText patch"),
@@ -378,7 +1030,7 @@ mod tests {
         assert_eq!(patches.len(), 1);
         match &patches[0] {
             Patch::Replace { path, .. } => {
-                assert_eq!(path, &Vec::<usize>::new());
+                assert_eq!(path, &HexPath::root());
             }
             _ => panic!("Expected Replace patch"),
         }
@@ -399,21 +1051,89 @@ mod tests {
         assert_eq!(patches.len(), 1);
         match &patches[0] {
             Patch::UpdateProps { path, props } => {
-                assert_eq!(path, &Vec::<usize>::new());
+                assert_eq!(path, &HexPath::root());
                 assert_eq!(props.get("class"), Some(&"new".to_string()));
             }
             _ => panic!("Expected UpdateProps patch"),
         }
     }
 
+    /// Text child of a root test element, at its positional path
+    fn text_child(content: &str, index: usize) -> Option<VNode> {
+        Some(VNode::Text(VText { content: content.to_string(), path: HexPath::root().child(index) }))
+    }
+
+    #[test]
+    fn test_ignored_regions_are_never_patched() {
+        let root = HexPath::from("10000000");
+        let map = |ignored: bool, marker: &str| {
+            let props = if ignored { HashMap::from([(crate::ignored_regions::IGNORE_ATTR.to_string(), String::new())]) } else { HashMap::new() };
+            let text = VNode::Text(crate::vdom::VText { content: marker.to_string(), path: root.child(0) });
+            VNode::Element(VElement { tag: "div".to_string(), props, children: vec![Some(text)], key: None, path: root.clone(), source: None })
+        };
+
+        // Marking sends the last update, then hands the region over (the marker
+        // attribute travels in SetIgnored only)
+        let patches = reconcile(&map(false, "a"), &map(true, "b")).unwrap();
+        assert!(matches!(&patches[..], [Patch::UpdateText { .. }, Patch::SetIgnored { ignored: true, .. }]));
+
+        assert!(reconcile(&map(true, "b"), &map(true, "c")).unwrap().is_empty());
+
+        let patches = reconcile(&map(true, "c"), &map(false, "d")).unwrap();
+        assert_eq!(patches[0], Patch::SetIgnored { path: root.clone(), ignored: false });
+        assert!(matches!(&patches[1], Patch::Replace { node, .. } if *node == map(false, "d")));
+
+        // A client that doesn't track regions gets the concrete patches only
+        let baseline = ReconcileStrategy { capabilities: Some(ClientCapabilities::baseline()), ..ReconcileStrategy::surgical() };
+        let patches = reconcile_with_strategy(&map(false, "a"), &map(true, "b"), &baseline).unwrap();
+        assert!(matches!(&patches[..], [Patch::UpdateText { .. }]));
+        let patches = reconcile_with_strategy(&map(true, "c"), &map(false, "d"), &baseline).unwrap();
+        assert!(matches!(&patches[..], [Patch::Replace { .. }]));
+    }
+
+    #[test]
+    fn test_only_changed_nodes_are_validated() {
+        let root = HexPath::from("10000000");
+        let list = |texts: &[&str]| {
+            let children = texts
+                .iter()
+                .enumerate()
+                .map(|(i, content)| Some(VNode::Text(crate::vdom::VText { content: content.to_string(), path: root.child(i) })))
+                .collect();
+            VNode::Element(VElement { tag: "ul".to_string(), props: HashMap::new(), children, key: None, path: root.clone(), source: None })
+        };
+        let too_long = "x".repeat(ValidationConfig::default().max_text_length + 1);
+
+        // An unchanged node isn't checked again; changed and created ones are
+        assert_eq!(reconcile(&list(&[&too_long, "a"]), &list(&[&too_long, "b"])).unwrap().len(), 1);
+        assert!(matches!(reconcile(&list(&["a"]), &list(&[&too_long])), Err(MinimactError::TextTooLong { .. })));
+        assert!(matches!(reconcile(&list(&["a"]), &list(&["a", &too_long])), Err(MinimactError::TextTooLong { .. })));
+    }
+
+    #[test]
+    fn test_reconcile_bulk_keeps_input_order() {
+        let pairs: Vec<(VNode, VNode)> = (0..64)
+            .map(|i| (VNode::text(format!("old {}", i)), VNode::text(format!("new {}", i))))
+            .collect();
+        let results = reconcile_bulk(pairs);
+
+        assert_eq!(results.len(), 64);
+        for (i, result) in results.into_iter().enumerate() {
+            match &result.unwrap()[..] {
+                [Patch::UpdateText { content, .. }] => assert_eq!(content, &format!("new {}", i)),
+                other => panic!("Expected one UpdateText patch, got {:?}", other),
+            }
+        }
+    }
+
     #[test]
     fn test_children_additions() {
         let old = VNode::element("div", HashMap::new(), vec![
-            VNode::text("A"),
+            text_child("A", 0),
         ]);
         let new = VNode::element("div", HashMap::new(), vec![
-            VNode::text("A"),
-            VNode::text("B"),
+            text_child("A", 0),
+            text_child("B", 1),
         ]);
         let patches = reconcile(&old, &new).unwrap();
 
@@ -424,15 +1144,389 @@ mod tests {
     #[test]
     fn test_children_removals() {
         let old = VNode::element("div", HashMap::new(), vec![
-            VNode::text("A"),
-            VNode::text("B"),
+            text_child("A", 0),
+            text_child("B", 1),
         ]);
         let new = VNode::element("div", HashMap::new(), vec![
-            VNode::text("A"),
+            text_child("A", 0),
         ]);
         let patches = reconcile(&old, &new).unwrap();
 
         // Should have a Remove patch
         assert!(patches.iter().any(|p| matches!(p, Patch::Remove { .. })));
     }
+
+    #[test]
+    fn test_keyed_children_with_positional_paths() {
+        let root = HexPath::from("10000000");
+        // Paths follow position, as in a list rendered by index
+        let list = |keys: &[&str]| {
+            let mut list = VNode::element("ul", HashMap::new(), keys.iter().map(|key| {
+                Some(VNode::keyed_element("li", *key, HashMap::new(), vec![Some(VNode::text(*key))]))
+            }).collect());
+            list.rebase_path(&root);
+            list
+        };
+
+        for (old, new) in [(list(&["a", "b", "c"]), list(&["b", "c"])), (list(&["a", "c"]), list(&["a", "b", "c"])), (list(&["a", "b"]), list(&["b", "a"]))] {
+            let patches = reconcile(&old, &new).unwrap();
+            let mut tree = old.clone();
+            crate::apply::apply_patches(&mut tree, &patches).unwrap();
+            assert_eq!(tree, new);
+        }
+    }
+
+    #[test]
+    fn test_small_child_lists_match_hashed_path() {
+        let text = |path: &str, content: &str| Some(VNode::Text(crate::vdom::VText {
+            content: content.to_string(),
+            path: HexPath::from(path),
+        }));
+        let old = vec![text("10000000", "A"), text("20000000", "B"), text("30000000", "C")];
+        let new = vec![text("10000000", "A"), text("30000000", "C!"), text("40000000", "D")];
+
+        let arena = Bump::new();
+        let ctx = ReconcileCtx { strategy: &ReconcileStrategy::default(), arena: &arena, keep_whole: None, windows: None, validation: None };
+        let mut small = Vec::new();
+        reconcile_small_children_by_path(&old, &new, &ctx, &mut small).unwrap();
+        let mut hashed = Vec::new();
+        reconcile_large_children_by_path(&old, &new, &ctx, &mut hashed).unwrap();
+
+        let key = |p: &Patch| (p.kind(), p.path().as_str().to_string());
+        small.sort_by_key(key);
+        hashed.sort_by_key(key);
+        assert_eq!(small, hashed);
+        assert_eq!(small.len(), 3); // UpdateText C!, Create D, Remove B
+    }
+
+    #[test]
+    fn test_keyed_insert_lands_between_neighbors() {
+        let item = |key: &str, path: &str| Some(VNode::Element(VElement {
+            tag: "li".to_string(),
+            props: HashMap::new(),
+            children: vec![Some(VNode::Text(crate::vdom::VText {
+                content: key.to_string(),
+                path: HexPath::from(format!("{}.10000000", path)),
+            }))],
+            key: Some(key.to_string()),
+            path: HexPath::from(path),
+            source: None,
+        }));
+        let list = |children| VNode::Element(VElement {
+            tag: "ul".to_string(),
+            props: HashMap::new(),
+            children,
+            key: None,
+            path: HexPath::from("10000000"),
+            source: None,
+        });
+
+        let old = list(vec![item("a", "10000000.10000000"), item("c", "10000000.20000000")]);
+        // "b" was rendered from the same template slot as "a" - its transpiled path collides
+        let new = list(vec![item("a", "10000000.10000000"), item("b", "10000000.10000000"), item("c", "10000000.20000000")]);
+
+        let patches = reconcile(&old, &new).unwrap();
+        let created: Vec<&Patch> = patches.iter().filter(|p| matches!(p, Patch::Create { .. })).collect();
+        assert_eq!(created.len(), 1);
+        match created[0] {
+            Patch::Create { path, node } => {
+                assert_eq!(path.as_str(), "10000000.18000000");
+                assert_eq!(node.path(), path);
+                assert_eq!(node.children()[0].as_ref().unwrap().path().as_str(), "10000000.18000000.10000000");
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_replace_heavy_collapses_busy_subtree() {
+        let text = |path: &str, content: &str| Some(VNode::Text(crate::vdom::VText {
+            content: content.to_string(),
+            path: HexPath::from(path),
+        }));
+        let list = |suffix: &str| VNode::Element(VElement {
+            tag: "ul".to_string(),
+            props: HashMap::new(),
+            children: (1..=3).map(|i| text(&format!("10000000.{}0000000", i), &format!("{}{}", i, suffix))).collect(),
+            key: None,
+            path: HexPath::from("10000000"),
+            source: None,
+        });
+        let (old, new) = (list(""), list("!"));
+
+        let surgical = reconcile_with_strategy(&old, &new, &ReconcileStrategy::surgical()).unwrap();
+        assert_eq!(surgical.len(), 3);
+        assert!(surgical.iter().all(|p| matches!(p, Patch::UpdateText { .. })));
+
+        let heavy = reconcile_with_strategy(&old, &new, &ReconcileStrategy::replace_heavy()).unwrap();
+        assert_eq!(heavy, vec![Patch::Replace { path: HexPath::from("10000000"), node: new.clone(), preserve: None }]);
+    }
+
+    #[test]
+    fn test_patch_limit_collapses_or_fails() {
+        let text = |path: String, content: String| Some(VNode::Text(crate::vdom::VText { content, path: HexPath::from(path) }));
+        let list = |suffix: &str| VNode::Element(VElement {
+            tag: "div".to_string(),
+            props: HashMap::new(),
+            children: (1..=4)
+                .map(|i| Some(VNode::Element(VElement {
+                    tag: "ul".to_string(),
+                    props: HashMap::new(),
+                    children: (1..=4).map(|j| text(format!("10000000.{:x}0000000.{:x}0000000", i, j), format!("{}{}", j, suffix))).collect(),
+                    key: None,
+                    path: HexPath::from(format!("10000000.{:x}0000000", i)),
+                    source: None,
+                })))
+                .collect(),
+            key: None,
+            path: HexPath::from("10000000"),
+            source: None,
+        });
+        let (old, new) = (list(""), list("!"));
+
+        let limited = ReconcileStrategy { max_patches: Some(4), ..ReconcileStrategy::surgical() };
+        let patches = reconcile_with_strategy(&old, &new, &limited).unwrap();
+        assert_eq!(patches.len(), 4);
+        assert!(patches.iter().all(|p| matches!(p, Patch::Replace { .. })));
+
+        let strict = ReconcileStrategy { on_patch_limit: PatchLimitAction::Error, ..limited };
+        assert!(matches!(
+            reconcile_with_strategy(&old, &new, &strict),
+            Err(MinimactError::TooManyPatches { count: 16, max: 4 })
+        ));
+    }
+
+    #[test]
+    fn test_patch_limit_counts_lifecycle_markers() {
+        // The config is process-wide: put the previous one back even if an assert fails
+        struct Restore(std::sync::Arc<crate::lifecycle::LifecycleConfig>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                crate::lifecycle::set_lifecycle_config((*self.0).clone());
+            }
+        }
+        let _restore = Restore(crate::lifecycle::lifecycle_config());
+        crate::lifecycle::set_lifecycle_config(crate::lifecycle::LifecycleConfig {
+            mount_props: vec!["onLimitMount".to_string()],
+            unmount_props: vec![],
+        });
+        let list = |count: usize| VNode::Element(VElement {
+            tag: "ul".to_string(),
+            props: HashMap::new(),
+            children: (1..=count)
+                .map(|i| Some(VNode::Element(VElement {
+                    tag: "li".to_string(),
+                    props: HashMap::from([("onLimitMount".to_string(), format!("Focus{}", i))]),
+                    children: vec![],
+                    key: None,
+                    path: HexPath::from(format!("10000000.{:x}0000000", i)),
+                    source: None,
+                })))
+                .collect(),
+            key: None,
+            path: HexPath::from("10000000"),
+            source: None,
+        });
+        let (old, new) = (list(0), list(3));
+        let count = |patches: &[Patch], kind: &str| patches.iter().filter(|p| p.kind() == kind).count();
+
+        // 3 Creates and their 3 markers
+        let patches = reconcile(&old, &new).unwrap();
+        assert_eq!((count(&patches, "Create"), count(&patches, "Lifecycle")), (3, 3));
+
+        let limited = ReconcileStrategy { max_patches: Some(4), ..ReconcileStrategy::surgical() };
+        let patches = reconcile_with_strategy(&old, &new, &limited).unwrap();
+        assert_eq!((count(&patches, "Replace"), count(&patches, "Lifecycle")), (1, 3));
+
+        // Markers that can't fit next to the Replace are dropped
+        let tighter = ReconcileStrategy { max_patches: Some(3), ..ReconcileStrategy::surgical() };
+        assert_eq!(reconcile_with_strategy(&old, &new, &tighter).unwrap().len(), 1);
+
+        // Clients that don't run effects never get markers
+        let baseline = ReconcileStrategy { capabilities: Some(ClientCapabilities::baseline()), ..limited };
+        let patches = reconcile_with_strategy(&old, &new, &baseline).unwrap();
+        assert_eq!((count(&patches, "Create"), count(&patches, "Lifecycle")), (3, 0));
+    }
+
+    #[test]
+    fn test_windowed_list_patches_visible_rows_only() {
+        let rows = |count: usize, changed: usize| VNode::Element(VElement {
+            tag: "ul".to_string(),
+            props: HashMap::new(),
+            children: (0..count)
+                .map(|i| {
+                    let content = if i == changed { "changed".to_string() } else { format!("row {}", i) };
+                    Some(VNode::Text(crate::vdom::VText { content, path: HexPath::from(format!("10000000.{:08x}", i + 1)) }))
+                })
+                .collect(),
+            key: None,
+            path: HexPath::from("10000000"),
+            source: None,
+        });
+        let windows = ListWindows::from([(HexPath::from("10000000"), ListWindow { start: 100, end: 130 })]);
+        let strategy = ReconcileStrategy::surgical();
+
+        // A visible row changed: one concrete patch, nothing about the hidden rows
+        let patches = reconcile_windowed(&rows(900, usize::MAX), &rows(900, 110), &strategy, &windows).unwrap();
+        assert_eq!(patches.len(), 1);
+        assert!(matches!(&patches[0], Patch::UpdateText { content, .. } if content == "changed"));
+
+        // Hidden rows changed: just the descriptor
+        let patches = reconcile_windowed(&rows(900, usize::MAX), &rows(901, 500), &strategy, &windows).unwrap();
+        assert_eq!(
+            patches,
+            vec![Patch::UpdateListWindow { path: HexPath::from("10000000"), total: 901, start: 100, end: 130 }]
+        );
+    }
+
+    /// xorshift64*, so the keyed suite is reproducible without a rand dependency
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    fn keyed_list(items: &[(u32, String)]) -> VNode {
+        keyed_list_with(items, |key| format!("item {}", key))
+    }
+
+    fn keyed_list_with(items: &[(u32, String)], content: impl Fn(u32) -> String) -> VNode {
+        VNode::Element(VElement {
+            tag: "ul".to_string(),
+            props: HashMap::new(),
+            children: items
+                .iter()
+                .map(|(key, path)| Some(VNode::Element(VElement {
+                    tag: "li".to_string(),
+                    props: HashMap::new(),
+                    children: vec![Some(VNode::Text(crate::vdom::VText {
+                        content: content(*key),
+                        path: HexPath::from(format!("{}.10000000", path)),
+                    }))],
+                    key: Some(key.to_string()),
+                    path: HexPath::from(path.as_str()),
+                    source: None,
+                })))
+                .collect(),
+            key: None,
+            path: HexPath::from("10000000"),
+            source: None,
+        })
+    }
+
+    fn slot(index: usize) -> String {
+        format!("10000000.{:08x}", (index + 1) * 0x10_0000)
+    }
+
+    fn key_order(tree: &VNode) -> Vec<String> {
+        tree.children().iter().flatten().filter_map(|n| n.key().map(String::from)).collect()
+    }
+
+    /// Keys and texts in document order, what the client renders
+    fn rendered_items(tree: &VNode) -> Vec<(String, String)> {
+        tree.children()
+            .iter()
+            .flatten()
+            .map(|item| {
+                let text = match item.children().first() {
+                    Some(Some(VNode::Text(text))) => text.content.clone(),
+                    other => panic!("item without text: {:?}", other),
+                };
+                (item.key().unwrap_or_default().to_string(), text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_keyed_lists_survive_random_edits() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        let mut next_key = 0;
+
+        for round in 0..500 {
+            // Every other round renders paths by position, so survivors shift
+            let positional = round % 2 == 1;
+
+            // Old list: keyed items in their transpiled slots
+            let len = rng.below(12);
+            let old_items: Vec<(u32, String)> = (0..len).map(|i| (next_key + i as u32, slot(i))).collect();
+            next_key += len as u32;
+
+            // New list: shuffle, drop some, insert fresh keys. Survivors keep their path
+            // (or take their new index's slot when positional); fresh items get the
+            // transpiled slot of their new index (which may collide)
+            let mut new_keys: Vec<Option<(u32, String)>> = old_items.iter().cloned().map(Some).collect();
+            for i in (1..new_keys.len()).rev() {
+                new_keys.swap(i, rng.below(i + 1));
+            }
+            new_keys.retain(|_| rng.below(4) != 0);
+            for _ in 0..rng.below(4) {
+                new_keys.insert(rng.below(new_keys.len() + 1), None);
+            }
+            let new_items: Vec<(u32, String)> = new_keys
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| match item {
+                    Some((key, _)) if positional => (key, slot(i)),
+                    Some(item) => item,
+                    None => {
+                        next_key += 1;
+                        (next_key + 1000, slot(i))
+                    }
+                })
+                .collect();
+            // Some survivors get new text too
+            let edited: Vec<u32> = new_items.iter().map(|&(key, _)| key).filter(|_| rng.below(3) == 0).collect();
+
+            let old = keyed_list(&old_items);
+            let new = keyed_list_with(&new_items, |key| {
+                if edited.contains(&key) { format!("item {} (edited)", key) } else { format!("item {}", key) }
+            });
+            let patches = reconcile(&old, &new).unwrap();
+            let mut tree = old.clone();
+            crate::apply::apply_patches(&mut tree, &patches)
+                .unwrap_or_else(|e| panic!("round {}: {} applying {:?}", round, e, patches));
+            if positional {
+                // Paths are unique and ordered: the client holds exactly the new tree
+                assert_eq!(tree, new, "round {}: {:?}", round, patches);
+            } else {
+                // Colliding fresh paths are relocated, so only what renders must match
+                assert_eq!(key_order(&tree), key_order(&new), "round {}: {:?}", round, patches);
+                assert_eq!(rendered_items(&tree), rendered_items(&new), "round {}: {:?}", round, patches);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reorder_children_only_when_order_changes() {
+        let items = |keys: &[u32]| keys.iter().map(|&k| (k, slot(k as usize))).collect::<Vec<_>>();
+        let reorders = |old: &[u32], new: &[u32]| {
+            reconcile(&keyed_list(&items(old)), &keyed_list(&items(new)))
+                .unwrap()
+                .into_iter()
+                .filter(|p| matches!(p, Patch::ReorderChildren { .. }))
+                .collect::<Vec<_>>()
+        };
+
+        // Unchanged and removal-only lists keep their order without a ReorderChildren
+        assert!(reorders(&[0, 1, 2], &[0, 1, 2]).is_empty());
+        assert!(reorders(&[0, 1, 2], &[0, 2]).is_empty());
+
+        // Moves and creations carry the full new key order
+        let order = |keys: &[&str]| vec![Patch::ReorderChildren {
+            path: HexPath::from("10000000"),
+            order: keys.iter().map(|k| k.to_string()).collect(),
+            preserve: None,
+        }];
+        assert_eq!(reorders(&[0, 1, 2], &[2, 0, 1]), order(&["2", "0", "1"]));
+        assert_eq!(reorders(&[0, 2], &[0, 1, 2]), order(&["0", "1", "2"]));
+    }
 }
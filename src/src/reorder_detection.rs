@@ -12,9 +12,11 @@
 /// Solution:
 /// Infer the ordering rule from observed changes and create a ReorderTemplate.
 
+use crate::error::{MinimactError, Result};
+use crate::path::HexPath;
 use crate::vdom::{Patch, VNode, VElement};
 use serde::{Serialize, Deserialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// Reorder template that describes how to reorder a list
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -211,47 +213,106 @@ fn is_sorted_version(
     sorted: &[Option<&serde_json::Value>],
     descending: bool
 ) -> bool {
-    use serde_json::Value;
-
     // Create a sorted version of original
     let mut expected = original.to_vec();
-    expected.sort_by(|a, b| {
-        match (a, b) {
-            (Some(Value::String(s1)), Some(Value::String(s2))) => {
-                if descending {
-                    s2.cmp(s1)
-                } else {
-                    s1.cmp(s2)
-                }
-            }
-            (Some(Value::Number(n1)), Some(Value::Number(n2))) => {
-                let f1 = n1.as_f64().unwrap_or(0.0);
-                let f2 = n2.as_f64().unwrap_or(0.0);
-                if descending {
-                    f2.partial_cmp(&f1).unwrap_or(std::cmp::Ordering::Equal)
-                } else {
-                    f1.partial_cmp(&f2).unwrap_or(std::cmp::Ordering::Equal)
-                }
-            }
-            (Some(Value::Bool(b1)), Some(Value::Bool(b2))) => {
-                if descending {
-                    b2.cmp(b1)
-                } else {
-                    b1.cmp(b2)
-                }
-            }
-            _ => std::cmp::Ordering::Equal,
-        }
-    });
+    expected.sort_by(|a, b| compare_values(*a, *b, descending));
 
     // Compare expected vs actual
     expected.iter().zip(sorted.iter()).all(|(a, b)| a == b)
 }
 
+/// Sort order of two property values: strings, numbers and bools compare within
+/// their own type, anything else (mixed types, missing values) counts as equal
+fn compare_values(a: Option<&serde_json::Value>, b: Option<&serde_json::Value>, descending: bool) -> std::cmp::Ordering {
+    use serde_json::Value;
+
+    let ordering = match (a, b) {
+        (Some(Value::String(s1)), Some(Value::String(s2))) => s1.cmp(s2),
+        (Some(Value::Number(n1)), Some(Value::Number(n2))) => {
+            let f1 = n1.as_f64().unwrap_or(0.0);
+            let f2 = n2.as_f64().unwrap_or(0.0);
+            f1.partial_cmp(&f2).unwrap_or(std::cmp::Ordering::Equal)
+        }
+        (Some(Value::Bool(b1)), Some(Value::Bool(b2))) => b1.cmp(b2),
+        _ => std::cmp::Ordering::Equal,
+    };
+    if descending {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+/// Compute the key order `rule` produces from the array's current order
+///
+/// `array` is the list before the reorder (e.g. the state's old value). Items are
+/// identified by their "id" / "key" / "_id" / "uuid" property, the same keys the
+/// rendered children carry. Sorting is stable, so items that compare equal keep
+/// their relative order - the same as JavaScript's Array.prototype.sort.
+pub fn apply_ordering(rule: &OrderingRule, array: &serde_json::Value) -> Result<Vec<String>> {
+    use serde_json::Value;
+
+    let items = match array {
+        Value::Array(items) => items,
+        _ => return Err(MinimactError::InvalidVNode("ordering needs an array".to_string())),
+    };
+    if items.is_empty() {
+        return Ok(Vec::new());
+    }
+    let keys = extract_key_order(items)
+        .filter(|keys| keys.len() == items.len())
+        .ok_or_else(|| MinimactError::KeyNotFound("array items need an id or key property".to_string()))?;
+
+    let order = match rule {
+        OrderingRule::SortByProperty { property, direction } => {
+            let descending = *direction == SortDirection::Descending;
+            let mut indexed: Vec<(usize, Option<&Value>)> =
+                items.iter().map(|item| item.get(property)).enumerate().collect();
+            indexed.sort_by(|(_, a), (_, b)| compare_values(*a, *b, descending));
+            indexed.into_iter().map(|(i, _)| keys[i].clone()).collect()
+        }
+        OrderingRule::Reverse => keys.into_iter().rev().collect(),
+        OrderingRule::Filter { property, value } => items
+            .iter()
+            .zip(keys)
+            .filter(|(item, _)| item.get(property) == Some(value))
+            .map(|(_, key)| key)
+            .collect(),
+        OrderingRule::Custom { key_order } => {
+            // Stored keys that still exist, then new items in their current order
+            let present: HashSet<&str> = keys.iter().map(String::as_str).collect();
+            let stored: HashSet<&str> = key_order.iter().map(String::as_str).collect();
+            let mut order: Vec<String> = key_order.iter().filter(|k| present.contains(k.as_str())).cloned().collect();
+            order.extend(keys.iter().filter(|k| !stored.contains(k.as_str())).cloned());
+            order
+        }
+    };
+    Ok(order)
+}
+
+/// Turn a ReorderTemplate into the concrete ReorderChildren patch for `array`
+/// (the list before the reorder; see `apply_ordering`)
+///
+/// Filter rules are rejected: they drop children, and ReorderChildren can only
+/// move them.
+pub fn materialize_reorder(path: &HexPath, template: &ReorderTemplate, array: &serde_json::Value) -> Result<Patch> {
+    if let OrderingRule::Filter { property, .. } = &template.ordering {
+        return Err(MinimactError::InvalidVNode(format!(
+            "filtering '{}' by '{}' removes children; it can't be a reorder",
+            template.array_binding, property
+        )));
+    }
+    Ok(Patch::ReorderChildren {
+        path: path.clone(),
+        order: apply_ordering(&template.ordering, array)?,
+        preserve: None,
+    })
+}
+
 /// Extract key order from array items
 ///
 /// Looks for "id" or "key" property in each item
-fn extract_key_order(items: &[serde_json::Value]) -> Option<Vec<String>> {
+pub(crate) fn extract_key_order(items: &[serde_json::Value]) -> Option<Vec<String>> {
     use serde_json::Value;
 
     let mut keys = Vec::new();
@@ -298,7 +359,7 @@ pub fn extract_reorder_template_from_patch(
     state_change: &crate::predictor::StateChange,
 ) -> Option<ReorderTemplate> {
     // Only handle ReorderChildren patches
-    if let Patch::ReorderChildren { path, order } = patch {
+    if let Patch::ReorderChildren { path, order, .. } = patch {
         // Try to infer ordering rule from state change
         if let Some(template) = infer_ordering_rule(
             &state_change.old_value,
@@ -395,4 +456,50 @@ mod tests {
         // Note: This could be detected as either Reverse OR SortByProperty(id, Descending)
         // Our algorithm prefers Reverse (simpler pattern)
     }
+
+    #[test]
+    fn test_apply_ordering_rules() {
+        let items = json!([
+            { "id": 1, "name": "Charlie", "done": true },
+            { "id": 2, "name": "Alice", "done": false },
+            { "id": 3, "name": "Bob", "done": true },
+        ]);
+        let order = |rule: OrderingRule| apply_ordering(&rule, &items).unwrap();
+
+        assert_eq!(order(OrderingRule::SortByProperty { property: "name".to_string(), direction: SortDirection::Ascending }), ["2", "3", "1"]);
+        assert_eq!(order(OrderingRule::SortByProperty { property: "done".to_string(), direction: SortDirection::Descending }), ["1", "3", "2"]);
+        assert_eq!(order(OrderingRule::Reverse), ["3", "2", "1"]);
+        assert_eq!(order(OrderingRule::Filter { property: "done".to_string(), value: json!(true) }), ["1", "3"]);
+        assert_eq!(order(OrderingRule::Custom { key_order: vec!["3".to_string(), "9".to_string(), "1".to_string()] }), ["3", "1", "2"]);
+
+        assert!(apply_ordering(&OrderingRule::Reverse, &json!([{ "name": "no key" }])).is_err());
+    }
+
+    #[test]
+    fn test_inferred_rule_reproduces_observed_order() {
+        let old = json!([
+            { "id": 1, "name": "Charlie" },
+            { "id": 2, "name": "Alice" },
+            { "id": 3, "name": "Bob" }
+        ]);
+        let new = json!([
+            { "id": 2, "name": "Alice" },
+            { "id": 3, "name": "Bob" },
+            { "id": 1, "name": "Charlie" }
+        ]);
+
+        let template = infer_ordering_rule(&old, &new, "items").unwrap();
+        let patch = materialize_reorder(&HexPath::from("10000000"), &template, &old).unwrap();
+        assert_eq!(patch, Patch::ReorderChildren {
+            path: HexPath::from("10000000"),
+            order: vec!["2".to_string(), "3".to_string(), "1".to_string()],
+            preserve: None,
+        });
+
+        let filter = ReorderTemplate {
+            array_binding: "items".to_string(),
+            ordering: OrderingRule::Filter { property: "name".to_string(), value: json!("Bob") },
+        };
+        assert!(materialize_reorder(&HexPath::from("10000000"), &filter, &old).is_err());
+    }
 }
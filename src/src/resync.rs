@@ -0,0 +1,137 @@
+//! Error recovery (resync) patches
+//!
+//! When the client fails to apply a patch its DOM has diverged from the server's
+//! VNode tree. Rather than reloading the whole component, the client reports the
+//! path where application failed and the server answers with a Replace-rooted
+//! patch set rebuilding only the affected subtree from the authoritative tree.
+
+use crate::error::{MinimactError, Result};
+use crate::path::HexPath;
+use crate::vdom::{Patch, VNode};
+use serde::{Deserialize, Serialize};
+
+/// Protocol message sent to the client to recover from divergence
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResyncMessage {
+    /// Path the client reported as failing
+    #[serde(rename = "requestedPath")]
+    pub requested_path: HexPath,
+    /// Root of the subtree actually being rebuilt (the requested path or its nearest
+    /// rebuildable ancestor)
+    #[serde(rename = "rootPath")]
+    pub root_path: HexPath,
+    /// Patches rebuilding the subtree (currently a single Replace)
+    pub patches: Vec<Patch>,
+}
+
+/// Generate a minimal Replace-rooted patch set that rebuilds the subtree at `from_path`
+///
+/// If `from_path` doesn't exist in the authoritative tree (the node was removed) or
+/// points to a null placeholder, the nearest existing element ancestor is rebuilt instead.
+/// An empty path rebuilds the whole tree.
+pub fn generate_resync(tree: &VNode, from_path: &HexPath) -> Result<Vec<Patch>> {
+    Ok(generate_resync_message(tree, from_path)?.patches)
+}
+
+/// Same as `generate_resync`, wrapped in the protocol message sent to clients
+pub fn generate_resync_message(tree: &VNode, from_path: &HexPath) -> Result<ResyncMessage> {
+    let mut candidate = Some(from_path.clone());
+
+    while let Some(path) = candidate {
+        if let Some(node) = tree.find_by_path(&path) {
+            if !node.is_null() {
+                crate::log_info!("Resync: rebuilding subtree at '{}' (requested '{}')", path, from_path);
+                return Ok(ResyncMessage {
+                    requested_path: from_path.clone(),
+                    root_path: path.clone(),
+                    patches: vec![Patch::Replace {
+                        path,
+                        node: node.clone(),
+                        preserve: None,
+                    }],
+                });
+            }
+        }
+        candidate = path.parent();
+    }
+
+    // Empty path requested but the tree root carries a transpiled path - rebuild the whole tree
+    if from_path.is_root() {
+        return Ok(ResyncMessage {
+            requested_path: from_path.clone(),
+            root_path: tree.path().clone(),
+            patches: vec![Patch::Replace {
+                path: tree.path().clone(),
+                node: tree.clone(),
+                preserve: None,
+            }],
+        });
+    }
+
+    Err(MinimactError::InvalidPatchPath {
+        path: from_path.to_index_path().unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vdom::{VElement, VNull, VText};
+    use std::collections::HashMap;
+
+    fn tree() -> VNode {
+        VNode::Element(VElement {
+            tag: "div".to_string(),
+            props: HashMap::new(),
+            key: None,
+            path: HexPath::from("10000000"),
+            children: vec![
+                Some(VNode::Text(VText {
+                    content: "Count: 1".to_string(),
+                    path: HexPath::from("10000000.10000000"),
+                })),
+                Some(VNode::Null(VNull {
+                    path: HexPath::from("10000000.20000000"),
+                })),
+            ],
+            source: None,
+        })
+    }
+
+    #[test]
+    fn test_resync_existing_node() {
+        let tree = tree();
+        let patches = generate_resync(&tree, &HexPath::from("10000000.10000000")).unwrap();
+        assert_eq!(patches.len(), 1);
+        match &patches[0] {
+            Patch::Replace { path, node, .. } => {
+                assert_eq!(path.as_str(), "10000000.10000000");
+                assert!(node.is_text());
+            }
+            _ => panic!("Expected Replace patch"),
+        }
+    }
+
+    #[test]
+    fn test_resync_null_or_missing_escalates_to_parent() {
+        let tree = tree();
+        for requested in ["10000000.20000000", "10000000.30000000.10000000"] {
+            let msg = generate_resync_message(&tree, &HexPath::from(requested)).unwrap();
+            assert_eq!(msg.root_path.as_str(), "10000000");
+            assert_eq!(msg.requested_path.as_str(), requested);
+        }
+    }
+
+    #[test]
+    fn test_resync_unrelated_path_fails() {
+        let tree = tree();
+        assert!(generate_resync(&tree, &HexPath::from("20000000")).is_err());
+    }
+
+    #[test]
+    fn test_resync_root() {
+        let tree = tree();
+        let msg = generate_resync_message(&tree, &HexPath::root()).unwrap();
+        assert_eq!(msg.root_path.as_str(), "10000000");
+    }
+}
@@ -0,0 +1,94 @@
+//! Server-driven navigation
+//!
+//! The current route is ordinary component state under the `ROUTE_STATE_KEY` key,
+//! so the predictor learns route change → tree change patterns like any other state
+//! and can pre-render the next page. Whenever patches answer a route change (a
+//! prediction or the host's authoritative reconcile via `with_navigation`), a
+//! `Patch::Navigate` goes first so the client updates the URL bar and history along
+//! with the DOM.
+//!
+//! The route value is the URL as a string, or `{"url": "...", "replace": true}` to
+//! replace the current history entry instead of pushing one.
+
+use crate::path::HexPath;
+use crate::predictor::StateChange;
+use crate::vdom::Patch;
+use serde::{Deserialize, Serialize};
+
+/// State key holding a component's route
+pub const ROUTE_STATE_KEY: &str = "$route";
+
+/// A route state value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteState {
+    pub url: String,
+    /// Replace the current history entry instead of pushing one
+    #[serde(default)]
+    pub replace: bool,
+}
+
+impl RouteState {
+    /// Read a route from a state value (a URL string or a RouteState object)
+    pub fn from_value(value: &serde_json::Value) -> Option<RouteState> {
+        match value {
+            serde_json::Value::String(url) => Some(RouteState { url: url.clone(), replace: false }),
+            other => serde_json::from_value(other.clone()).ok(),
+        }
+    }
+
+    /// The Navigate patch that takes the client to this route
+    pub fn patch(&self) -> Patch {
+        Patch::Navigate { path: HexPath::root(), url: self.url.clone(), replace: self.replace }
+    }
+}
+
+/// The state change for navigating `component_id` from `from` to `to`
+pub fn route_change(component_id: &str, from: &str, to: &str) -> StateChange {
+    StateChange {
+        component_id: component_id.to_string(),
+        state_key: ROUTE_STATE_KEY.to_string(),
+        old_value: serde_json::Value::String(from.to_string()),
+        new_value: serde_json::Value::String(to.to_string()),
+        array_operation: None,
+        locale: None,
+    }
+}
+
+/// Put a Navigate patch in front of `patches` if they answer a route change
+pub fn with_navigation(state_change: &StateChange, mut patches: Vec<Patch>) -> Vec<Patch> {
+    if state_change.state_key != ROUTE_STATE_KEY || patches.iter().any(|p| matches!(p, Patch::Navigate { .. })) {
+        return patches;
+    }
+    if let Some(route) = RouteState::from_value(&state_change.new_value) {
+        patches.insert(0, route.patch());
+    }
+    patches
+}
+
+/// Check that a Navigate URL is safe to hand to the browser (no script URLs)
+pub fn is_navigable_url(url: &str) -> bool {
+    let scheme = url.split_once(':').map(|(scheme, _)| scheme.trim().to_ascii_lowercase());
+    !url.trim().is_empty() && !matches!(scheme.as_deref(), Some("javascript" | "data" | "vbscript"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::predictor::Predictor;
+    use crate::vdom::VNode;
+
+    #[test]
+    fn test_route_changes_are_learned_and_navigate() {
+        let mut predictor = Predictor::new();
+        let home = VNode::text("Home");
+        let about = VNode::text("About");
+        predictor.learn(route_change("App", "/", "/about"), &home, &about, None).unwrap();
+
+        let prediction = predictor.predict(&route_change("App", "/", "/about"), &home).unwrap();
+        assert_eq!(prediction.predicted_patches[0], Patch::Navigate { path: HexPath::root(), url: "/about".to_string(), replace: false });
+        assert!(prediction.predicted_patches[1..].iter().any(|p| matches!(p, Patch::UpdateText { content, .. } if content == "About")));
+
+        assert!(is_navigable_url("/about?tab=1"));
+        assert!(!is_navigable_url("JavaScript:alert(1)"));
+    }
+}
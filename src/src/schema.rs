@@ -0,0 +1,94 @@
+//! JSON interop contract for the batch FFI calls
+//!
+//! Every FFI string is UTF-8 JSON, returned strings are freed with
+//! `minimact_free_string`, and field names are snake_case (the serde defaults of the
+//! Rust types, e.g. StateChange's `component_id`, `state_key`, `old_value`, `new_value`).
+//!
+//! ## `minimact_predictor_predict_batch(handle, state_changes_json, tree_json)`
+//!
+//! - `state_changes_json`: array of StateChange, at most MAX_BATCH_SIZE entries
+//! - `tree_json`: the current VNode tree, shared by every entry
+//!
+//! ## `minimact_predictor_learn_batch(handle, observations_json)`
+//!
+//! - `observations_json`: array of LearnObservation, at most MAX_BATCH_SIZE entries,
+//!   learned in order and published to readers as one predictor version
+//!
+//! ## Response (both calls)
+//!
+//! ```json
+//! { "ok": true, "succeeded": 1, "failed": 1, "results": [
+//!     { "index": 0, "key": "counter::count", "ok": true, "data": { ...Prediction } },
+//!     { "index": 1, "key": "counter::step", "ok": false, "error": "No prediction available" }
+//! ] }
+//! ```
+//!
+//! Results come back in input order; `key` is `component_id::state_key` and `index`
+//! the position in the input, so entries with the same key stay distinguishable.
+//! Learn results carry no `data`. A malformed batch (bad JSON, too many entries,
+//! invalid tree) fails as a whole with `{ "ok": false, "error": "..." }`.
+
+use crate::predictor::StateChange;
+use crate::vdom::VNode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Largest batch accepted by one call
+pub const MAX_BATCH_SIZE: usize = 1024;
+
+/// One entry of a learn batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LearnObservation {
+    pub state_change: StateChange,
+    pub old_tree: VNode,
+    pub new_tree: VNode,
+    /// Complete component state (for multi-variable template extraction)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub all_state: Option<HashMap<String, serde_json::Value>>,
+}
+
+/// Outcome of one batch entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchItemResult<T> {
+    /// Position in the input array
+    pub index: usize,
+    /// `component_id::state_key` of the entry's state change
+    pub key: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl<T> BatchItemResult<T> {
+    pub fn success(index: usize, state_change: &StateChange, data: Option<T>) -> Self {
+        Self { index, key: batch_key(state_change), ok: true, data, error: None }
+    }
+
+    pub fn failure(index: usize, state_change: &StateChange, error: impl Into<String>) -> Self {
+        Self { index, key: batch_key(state_change), ok: false, data: None, error: Some(error.into()) }
+    }
+}
+
+/// Results of a whole batch, in input order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResponse<T> {
+    /// Always true; a batch that can't be processed at all is reported as an error
+    pub ok: bool,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BatchItemResult<T>>,
+}
+
+impl<T> BatchResponse<T> {
+    pub fn new(results: Vec<BatchItemResult<T>>) -> Self {
+        let succeeded = results.iter().filter(|r| r.ok).count();
+        Self { ok: true, succeeded, failed: results.len() - succeeded, results }
+    }
+}
+
+/// The key results are reported under
+pub fn batch_key(state_change: &StateChange) -> String {
+    format!("{}::{}", state_change.component_id, state_change.state_key)
+}
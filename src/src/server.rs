@@ -0,0 +1,299 @@
+//! HTTP service mode (feature "server")
+//!
+//! For hosts that can't P/Invoke the cdylib (containers, non-.NET stacks) the engine
+//! runs as a sidecar and is called over HTTP with the same JSON as the FFI:
+//!
+//! | route                              | body                          | response |
+//! |------------------------------------|-------------------------------|----------|
+//! | `POST /reconcile`                  | `{old_tree, new_tree, strategy?}` | patch array |
+//! | `POST /predictors/{id}/predict`    | `{state_change, current_tree}` | `{ok, data}` / `{ok: false, error}` |
+//! | `POST /predictors/{id}/learn`      | LearnObservation              | `{ok}` |
+//! | `POST /predictors/{id}/predict_batch` | `{state_changes, tree}`    | BatchResponse |
+//! | `POST /predictors/{id}/learn_batch`   | LearnObservation array     | BatchResponse |
+//! | `GET /predictors/{id}/stats`       |                               | PredictorStats |
+//! | `DELETE /predictors/{id}`          |                               | `{ok}` |
+//! | `GET /metrics`                     |                               | MetricsSnapshot |
+//!
+//! Predictors are named by the caller (typically one per component type) and created
+//! on first use, up to `max_predictors`; naming one more gets status 503. Malformed
+//! requests get status 400 and `{"error": ...}` (`{"ok": false, "error": ...}` on the
+//! predictor routes).
+//!
+//! Bodies go through the same size check, pre-scan and tree normalization as FFI
+//! input, and reconciles and learns run on tokio's blocking pool, so a large diff
+//! doesn't stall the async workers.
+
+use crate::concurrent_predictor::ConcurrentPredictor;
+use crate::error::{MinimactError, Result};
+use crate::predictor::StateChange;
+use crate::reconciler::{reconcile_with_strategy, ReconcileStrategy};
+use crate::schema::{LearnObservation, MAX_BATCH_SIZE};
+use crate::validation::ValidationConfig;
+use crate::vdom::VNode;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use dashmap::DashMap;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Response body and status of one request
+pub type Reply = (StatusCode, Json<Value>);
+
+/// Predictors a server keeps by default
+pub const DEFAULT_MAX_PREDICTORS: usize = 1024;
+
+/// Named predictors and limits shared by all requests
+pub struct ServerState {
+    predictors: DashMap<String, Arc<ConcurrentPredictor>>,
+    validation: ValidationConfig,
+    max_predictors: usize,
+}
+
+#[derive(Deserialize)]
+struct ReconcileRequest {
+    old_tree: VNode,
+    new_tree: VNode,
+    #[serde(default)]
+    strategy: ReconcileStrategy,
+}
+
+#[derive(Deserialize)]
+struct PredictRequest {
+    state_change: StateChange,
+    current_tree: VNode,
+}
+
+#[derive(Deserialize)]
+struct PredictBatchRequest {
+    state_changes: Vec<StateChange>,
+    tree: VNode,
+}
+
+impl ServerState {
+    pub fn new(validation: ValidationConfig) -> Self {
+        Self { predictors: DashMap::new(), validation, max_predictors: DEFAULT_MAX_PREDICTORS }
+    }
+
+    /// Keep at most `max_predictors` predictors
+    pub fn with_max_predictors(mut self, max_predictors: usize) -> Self {
+        self.max_predictors = max_predictors;
+        self
+    }
+
+    /// The predictor named `id`, created if there's room for one more
+    fn predictor(&self, id: &str) -> Result<Arc<ConcurrentPredictor>> {
+        if let Some(predictor) = self.predictors.get(id) {
+            return Ok(Arc::clone(&predictor));
+        }
+        if self.predictors.len() >= self.max_predictors {
+            return Err(MinimactError::CapacityExceeded { what: "predictors", max: self.max_predictors });
+        }
+        Ok(self.predictors.entry(id.to_string()).or_default().clone())
+    }
+
+    /// Parse a body carrying up to `trees` trees, scanning it before serde allocates them
+    fn parse<T: serde::de::DeserializeOwned>(&self, body: &str, trees: usize) -> Result<T> {
+        crate::validation::check_json_input(body, &self.validation.for_batch(trees))?;
+        Ok(serde_json::from_str(body)?)
+    }
+
+    /// Normalize and validate parsed trees, as `deserialize_vnode_safe` would
+    fn prepare(&self, trees: &mut [&mut VNode]) -> Result<()> {
+        trees.iter_mut().try_for_each(|tree| crate::validation::prepare_vnode(tree, &self.validation))
+    }
+
+    pub fn reconcile(&self, body: &str) -> Reply {
+        let result = self.parse::<ReconcileRequest>(body, 2).and_then(|mut request| {
+            self.prepare(&mut [&mut request.old_tree, &mut request.new_tree])?;
+            reconcile_with_strategy(&request.old_tree, &request.new_tree, &request.strategy)
+        });
+        match result {
+            Ok(patches) => (StatusCode::OK, Json(json!(patches))),
+            Err(e) => (StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))),
+        }
+    }
+
+    pub fn predict(&self, id: &str, body: &str) -> Reply {
+        let request = self.parse::<PredictRequest>(body, 1).and_then(|mut r| self.prepare(&mut [&mut r.current_tree]).map(|_| r));
+        let (request, predictor) = match request.and_then(|r| Ok((r, self.predictor(id)?))) {
+            Ok(found) => found,
+            Err(e) => return error_reply(e),
+        };
+        match predictor.predict(&request.state_change, &request.current_tree) {
+            Some(prediction) => (StatusCode::OK, Json(json!({ "ok": true, "data": prediction }))),
+            None => (
+                StatusCode::OK,
+                Json(json!({ "ok": false, "error": "No prediction available (confidence too low or no matching pattern)" })),
+            ),
+        }
+    }
+
+    pub fn learn(&self, id: &str, body: &str) -> Reply {
+        let result = self.parse::<LearnObservation>(body, 2).and_then(|mut o| {
+            self.prepare(&mut [&mut o.old_tree, &mut o.new_tree])?;
+            self.predictor(id)?.learn(o.state_change, &o.old_tree, &o.new_tree, o.all_state.as_ref())
+        });
+        match result {
+            Ok(()) => (StatusCode::OK, Json(json!({ "ok": true }))),
+            Err(e) => error_reply(e),
+        }
+    }
+
+    pub fn predict_batch(&self, id: &str, body: &str) -> Reply {
+        let result = self.parse::<PredictBatchRequest>(body, 1).and_then(|mut request| {
+            check_batch_size(request.state_changes.len())?;
+            self.prepare(&mut [&mut request.tree])?;
+            Ok(self.predictor(id)?.predict_batch(&request.state_changes, &request.tree))
+        });
+        match result {
+            Ok(response) => (StatusCode::OK, Json(json!(response))),
+            Err(e) => error_reply(e),
+        }
+    }
+
+    pub fn learn_batch(&self, id: &str, body: &str) -> Reply {
+        let result = self.parse::<Vec<LearnObservation>>(body, 2 * MAX_BATCH_SIZE).and_then(|mut observations| {
+            check_batch_size(observations.len())?;
+            for observation in &mut observations {
+                self.prepare(&mut [&mut observation.old_tree, &mut observation.new_tree])?;
+            }
+            Ok(self.predictor(id)?.learn_batch(observations))
+        });
+        match result {
+            Ok(response) => (StatusCode::OK, Json(json!(response))),
+            Err(e) => error_reply(e),
+        }
+    }
+
+    pub fn stats(&self, id: &str) -> Reply {
+        match self.predictors.get(id) {
+            Some(predictor) => (StatusCode::OK, Json(json!(predictor.stats()))),
+            None => (StatusCode::NOT_FOUND, Json(json!({ "ok": false, "error": "Unknown predictor" }))),
+        }
+    }
+
+    pub fn remove(&self, id: &str) -> Reply {
+        let removed = self.predictors.remove(id).is_some();
+        (StatusCode::OK, Json(json!({ "ok": removed })))
+    }
+}
+
+fn check_batch_size(len: usize) -> Result<()> {
+    if len > MAX_BATCH_SIZE {
+        return Err(MinimactError::Serialization(format!("Batch of {} exceeds {} entries", len, MAX_BATCH_SIZE)));
+    }
+    Ok(())
+}
+
+/// 503 when out of predictors, 400 for anything wrong with the request
+fn error_reply(err: MinimactError) -> Reply {
+    let status = match err {
+        MinimactError::CapacityExceeded { .. } => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::BAD_REQUEST,
+    };
+    (status, Json(json!({ "ok": false, "error": err.to_string() })))
+}
+
+impl Default for ServerState {
+    fn default() -> Self {
+        Self::new(ValidationConfig::default())
+    }
+}
+
+type Shared = State<Arc<ServerState>>;
+
+/// Run a CPU-bound handler on the blocking pool
+async fn blocking(handler: impl FnOnce() -> Reply + Send + 'static) -> Reply {
+    tokio::task::spawn_blocking(handler)
+        .await
+        .unwrap_or_else(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))))
+}
+
+/// Routes of the service (see module docs)
+pub fn router(state: Arc<ServerState>) -> Router {
+    Router::new()
+        .route("/reconcile", post(|State(s): Shared, body: String| blocking(move || s.reconcile(&body))))
+        .route("/predictors/:id/predict", post(|State(s): Shared, Path(id): Path<String>, body: String| blocking(move || s.predict(&id, &body))))
+        .route("/predictors/:id/learn", post(|State(s): Shared, Path(id): Path<String>, body: String| blocking(move || s.learn(&id, &body))))
+        .route(
+            "/predictors/:id/predict_batch",
+            post(|State(s): Shared, Path(id): Path<String>, body: String| blocking(move || s.predict_batch(&id, &body))),
+        )
+        .route(
+            "/predictors/:id/learn_batch",
+            post(|State(s): Shared, Path(id): Path<String>, body: String| blocking(move || s.learn_batch(&id, &body))),
+        )
+        .route("/predictors/:id/stats", get(|State(s): Shared, Path(id): Path<String>| async move { s.stats(&id) }))
+        .route("/predictors/:id", axum::routing::delete(|State(s): Shared, Path(id): Path<String>| async move { s.remove(&id) }))
+        .route("/metrics", get(|| async { Json(json!(crate::metrics::METRICS.snapshot())) }))
+        .with_state(state)
+}
+
+/// Serve the engine on `addr` until the process exits
+pub async fn serve(addr: std::net::SocketAddr, validation: ValidationConfig) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    crate::log_info!("minimact server listening on {}", listener.local_addr()?);
+    axum::serve(listener, router(Arc::new(ServerState::new(validation)))).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(content: &str) -> Value {
+        json!({ "type": "Text", "content": content, "path": "10000000" })
+    }
+
+    #[test]
+    fn test_reconcile_matches_ffi_schema() {
+        let state = ServerState::default();
+        let (status, Json(body)) = state.reconcile(&json!({ "old_tree": text("a"), "new_tree": text("b") }).to_string());
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!([{ "type": "UpdateText", "path": "10000000", "content": "b" }]));
+
+        let (status, Json(body)) = state.reconcile("{not json");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"].is_string());
+    }
+
+    #[test]
+    fn test_predictors_are_created_on_demand() {
+        let state = ServerState::default();
+        assert_eq!(state.stats("counter").0, StatusCode::NOT_FOUND);
+
+        let state_change = json!({ "component_id": "counter", "state_key": "count", "old_value": 0, "new_value": 1 });
+        let observation = json!({ "state_change": state_change, "old_tree": text("0"), "new_tree": text("1") });
+        let (status, Json(body)) = state.learn("counter", &observation.to_string());
+        assert_eq!((status, body), (StatusCode::OK, json!({ "ok": true })));
+
+        let (status, Json(body)) = state.stats("counter");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total_observations"], 1);
+
+        let batch = json!({ "state_changes": [state_change], "tree": text("1") });
+        let (status, Json(body)) = state.predict_batch("counter", &batch.to_string());
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["results"][0]["key"], "counter::count");
+
+        assert_eq!(state.remove("counter").1 .0, json!({ "ok": true }));
+    }
+
+    #[test]
+    fn test_predictor_count_is_capped() {
+        let state = ServerState::default().with_max_predictors(1);
+        let state_change = json!({ "component_id": "a", "state_key": "count", "old_value": 0, "new_value": 1 });
+        let observation = json!({ "state_change": state_change, "old_tree": text("0"), "new_tree": text("1") }).to_string();
+        assert_eq!(state.learn("a", &observation).0, StatusCode::OK);
+        assert_eq!(state.learn("b", &observation).0, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(state.learn("a", &observation).0, StatusCode::OK);
+
+        // Bodies are pre-scanned like FFI input
+        let deep = format!("{}{}", "[".repeat(10_000), "]".repeat(10_000));
+        let (status, Json(body)) = state.reconcile(&deep);
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"].as_str().unwrap().contains("too deep"), "{}", body);
+    }
+}
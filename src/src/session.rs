@@ -0,0 +1,913 @@
+//! Client sessions
+//!
+//! A server holds one Session per connected client. The session owns what used to
+//! be spread over host-side dictionaries: a predictor per component, the last tree
+//! sent to the client for each component (so reconciles only need the new tree) and
+//! the patch sequence counters of the batches sent to it.
+//!
+//! Sessions live in a `SessionRegistry`. Sessions nobody touched for
+//! `idle_timeout_ms` are dropped automatically: registry calls sweep for idle
+//! sessions at most every quarter timeout, so no background thread is needed.
+//!
+//! Each session's stored trees are bounded by `SessionConfig::trees`, and for a
+//! tenant's session by its memory quota too; dropped sessions release their trees at
+//! once. Sessions created for a tenant are subject to its quota (see `tenant`),
+//! enforced by `SessionRegistry::reconcile`.
+//!
+//! Patch filters (see `patch_filter`) set for the session, or for one of its
+//! components, run on every batch before it's emitted.
+//!
+//! A session can carry its client's locale; changes learned and predicted through
+//! the session are tagged with it, so each locale gets its own templates.
+//!
+//! Batches are stamped for latency measurement (see `latency`), with the time of the
+//! component's last state change seen by `predict` (or `note_state_change`); the
+//! session id is the client id of its apply reports.
+
+use crate::concurrent_predictor::ConcurrentPredictor;
+use crate::error::{FfiResult, MinimactError, Result};
+use crate::last_error::FfiCall;
+use crate::patch_batch::{BatchSequencer, PatchBatch};
+use crate::patch_filter::PatchFilter;
+use crate::predictor::{Prediction, Predictor, PredictorConfig, StateChange};
+use crate::rate_limit::RateDecision;
+use crate::tenant::{TenantQuota, TenantRegistry, TenantStats, TenantUsage};
+use crate::tree_store::{TreeStore, TreeStoreConfig};
+use crate::vdom::{Patch, VNode};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+/// Limits for the sessions of a registry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionConfig {
+    /// Drop sessions untouched for this long (default 30 minutes)
+    pub idle_timeout_ms: u64,
+    /// Most sessions kept at once; creating one more drops the longest idle (default 10,000)
+    pub max_sessions: usize,
+    /// Limits of each session's stored trees (default 1,000 trees, 16 MB)
+    pub trees: TreeStoreConfig,
+    /// Config of the per-component predictors
+    pub predictor: PredictorConfig,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout_ms: 30 * 60 * 1000,
+            max_sessions: 10_000,
+            trees: TreeStoreConfig { max_trees: 1_000, max_memory_bytes: 16 * 1024 * 1024 },
+            predictor: PredictorConfig::default(),
+        }
+    }
+}
+
+/// Per-session counters (see `Session::stats`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionStats {
+    pub session_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    pub components: usize,
+    pub predictors: usize,
+    pub reconciles: u64,
+    pub patches_sent: u64,
+    pub age_ms: u64,
+    pub idle_ms: u64,
+}
+
+/// State of one connected client
+pub struct Session {
+    id: String,
+    tenant_id: Option<String>,
+    predictor_config: PredictorConfig,
+    predictors: DashMap<String, Arc<ConcurrentPredictor>>,
+    /// Patch filters by component (None = every component)
+    patch_filters: DashMap<Option<String>, Arc<PatchFilter>>,
+    /// Locale the client's text is rendered in
+    locale: RwLock<Option<String>>,
+    /// Unix milliseconds of each component's state change awaiting its batch
+    state_changes: DashMap<String, u64>,
+    trees: TreeStore,
+    sequencer: BatchSequencer,
+    reconciles: AtomicU64,
+    patches_sent: AtomicU64,
+    created: Instant,
+    /// Milliseconds after `created` of the last use
+    last_active_ms: AtomicU64,
+}
+
+impl Session {
+    fn new(id: String, tenant_id: Option<String>, predictor_config: PredictorConfig, trees: TreeStoreConfig) -> Self {
+        Self {
+            id,
+            tenant_id,
+            predictor_config,
+            predictors: DashMap::new(),
+            patch_filters: DashMap::new(),
+            locale: RwLock::new(None),
+            state_changes: DashMap::new(),
+            trees: TreeStore::new(trees),
+            sequencer: BatchSequencer::new(),
+            reconciles: AtomicU64::new(0),
+            patches_sent: AtomicU64::new(0),
+            created: Instant::now(),
+            last_active_ms: AtomicU64::new(0),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn tenant_id(&self) -> Option<&str> {
+        self.tenant_id.as_deref()
+    }
+
+    /// Mark the session as used now
+    pub fn touch(&self) {
+        self.last_active_ms.fetch_max(self.created.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    fn idle_ms(&self) -> u64 {
+        (self.created.elapsed().as_millis() as u64).saturating_sub(self.last_active_ms.load(Ordering::Relaxed))
+    }
+
+    /// The component's predictor, created on first use
+    pub fn predictor(&self, component_id: &str) -> Arc<ConcurrentPredictor> {
+        self.touch();
+        let predictor = self
+            .predictors
+            .entry(component_id.to_string())
+            .or_insert_with(|| Arc::new(ConcurrentPredictor::new(Predictor::with_config(self.predictor_config.clone()))));
+        Arc::clone(&predictor)
+    }
+
+    /// Set the locale the client's text is rendered in (None = unknown)
+    pub fn set_locale(&self, locale: Option<&str>) {
+        *self.locale.write().unwrap() = locale.map(str::to_string);
+    }
+
+    pub fn locale(&self) -> Option<String> {
+        self.locale.read().unwrap().clone()
+    }
+
+    /// `state_change` tagged with the session's locale, unless it names one
+    fn localize(&self, mut state_change: StateChange) -> StateChange {
+        if state_change.locale.is_none() {
+            state_change.locale = self.locale();
+        }
+        state_change
+    }
+
+    /// Learn a change of one of the session's components, in the session's locale
+    pub fn learn(
+        &self,
+        component_id: &str,
+        state_change: StateChange,
+        old_tree: &VNode,
+        new_tree: &VNode,
+        all_state: Option<&crate::template_renderer::StateValues>,
+    ) -> Result<()> {
+        self.predictor(component_id).learn(self.localize(state_change), old_tree, new_tree, all_state)
+    }
+
+    /// Predict a change of one of the session's components, in the session's locale
+    pub fn predict(&self, component_id: &str, state_change: &StateChange, current_tree: &VNode) -> Option<Prediction> {
+        self.note_state_change(component_id);
+        self.predictor(component_id).predict(&self.localize(state_change.clone()), current_tree)
+    }
+
+    /// Record that a state change of the component happened now; its next batch
+    /// measures latency from here
+    pub fn note_state_change(&self, component_id: &str) {
+        self.state_changes.entry(component_id.to_string()).or_insert_with(crate::latency::unix_time_ms);
+    }
+
+    /// The component's next batch, stamped
+    fn next_batch(&self, component_id: &str, patches: Vec<Patch>) -> PatchBatch {
+        let state_change_ms = self.state_changes.remove(component_id).map(|(_, ms)| ms);
+        self.sequencer.next_batch(component_id, patches).stamped(state_change_ms)
+    }
+
+    /// Last tree sent to the client for `component_id`
+    pub fn tree(&self, component_id: &str) -> Option<Arc<VNode>> {
+        self.trees.get(component_id).map(|(_, tree)| tree)
+    }
+
+    /// Record the tree the client now shows for `component_id`
+    pub fn set_tree(&self, component_id: &str, tree: VNode) {
+        self.touch();
+        self.trees.set_tree(component_id, tree);
+    }
+
+    /// Set the patch filter of one component, or of every component (None)
+    /// A component's own filter runs after the session-wide one; None as the filter removes it
+    pub fn set_patch_filter(&self, component_id: Option<&str>, filter: Option<PatchFilter>) {
+        let scope = component_id.map(str::to_string);
+        match filter {
+            Some(filter) => {
+                self.patch_filters.insert(scope, Arc::new(filter));
+            }
+            None => {
+                self.patch_filters.remove(&scope);
+            }
+        }
+    }
+
+    fn filter_patches(&self, component_id: &str, mut patches: Vec<Patch>) -> Vec<Patch> {
+        for scope in [None, Some(component_id.to_string())] {
+            let filter = self.patch_filters.get(&scope).map(|f| Arc::clone(&f));
+            if let Some(filter) = filter {
+                patches = filter.apply(patches, &format!("session '{}' component '{}'", self.id, component_id));
+            }
+        }
+        patches
+    }
+
+    /// Diff `new_tree` against the component's last tree, store it, and wrap the
+    /// patches as the component's next batch
+    /// Without a previous tree the batch is a single Replace of the whole tree
+    pub fn reconcile(&self, component_id: &str, new_tree: VNode) -> Result<PatchBatch> {
+        self.touch();
+        let patches = self.trees.reconcile_against_stored(component_id, new_tree, None)?.patches;
+        let patches = self.filter_patches(component_id, patches);
+
+        self.reconciles.fetch_add(1, Ordering::Relaxed);
+        self.patches_sent.fetch_add(patches.len() as u64, Ordering::Relaxed);
+        Ok(self.next_batch(component_id, patches))
+    }
+
+    /// Send the component's whole tree as a single Replace (e.g. after suppressed updates)
+    pub fn flush(&self, component_id: &str, new_tree: VNode) -> PatchBatch {
+        self.touch();
+        let patches = vec![Patch::Replace { path: new_tree.path().clone(), node: new_tree.clone(), preserve: None }];
+        let patches = self.filter_patches(component_id, patches);
+        self.trees.set_tree(component_id, new_tree);
+
+        self.reconciles.fetch_add(1, Ordering::Relaxed);
+        self.patches_sent.fetch_add(patches.len() as u64, Ordering::Relaxed);
+        self.next_batch(component_id, patches)
+    }
+
+    /// Approximate memory and pattern count of the session's trees and predictors
+    pub fn usage(&self) -> TenantUsage {
+        let mut usage = TenantUsage { sessions: 1, memory_bytes: self.trees.stats().memory_bytes, patterns: 0 };
+        for predictor in self.predictors.iter() {
+            let stats = predictor.stats();
+            usage.memory_bytes += stats.estimated_memory_bytes;
+            usage.patterns += stats.learned_patterns + stats.seeded_patterns;
+        }
+        usage
+    }
+
+    /// Drop every predictor (learned patterns); returns how many there were
+    fn clear_predictors(&self) -> usize {
+        let count = self.predictors.len();
+        self.predictors.clear();
+        count
+    }
+
+    /// Drop every stored tree; returns how many there were
+    /// The components' next reconciles send their whole trees
+    fn clear_trees(&self) -> usize {
+        self.trees.clear()
+    }
+
+    /// Free what the session holds once it's dropped from the registry (callers may
+    /// still hold the Arc for a while)
+    fn release(&self) {
+        self.clear_trees();
+        self.clear_predictors();
+    }
+
+    /// Forget a component (it unmounted): its tree, predictor and sequence counter
+    pub fn remove_component(&self, component_id: &str) {
+        self.trees.remove(component_id);
+        self.predictors.remove(component_id);
+        self.state_changes.remove(component_id);
+        self.sequencer.reset(component_id);
+    }
+
+    pub fn stats(&self) -> SessionStats {
+        SessionStats {
+            session_id: self.id.clone(),
+            tenant_id: self.tenant_id.clone(),
+            components: self.trees.len(),
+            predictors: self.predictors.len(),
+            reconciles: self.reconciles.load(Ordering::Relaxed),
+            patches_sent: self.patches_sent.load(Ordering::Relaxed),
+            age_ms: self.created.elapsed().as_millis() as u64,
+            idle_ms: self.idle_ms(),
+        }
+    }
+}
+
+/// All sessions of a server
+pub struct SessionRegistry {
+    sessions: DashMap<String, Arc<Session>>,
+    config: arc_swap::ArcSwap<SessionConfig>,
+    tenants: TenantRegistry,
+    started: Instant,
+    /// Milliseconds after `started` of the last idle sweep
+    last_sweep_ms: AtomicU64,
+}
+
+impl SessionRegistry {
+    pub fn new(config: SessionConfig) -> Self {
+        Self {
+            sessions: DashMap::new(),
+            config: arc_swap::ArcSwap::from_pointee(config),
+            tenants: TenantRegistry::new(),
+            started: Instant::now(),
+            last_sweep_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// Replace the config (applies to sessions created from now on, and to idle sweeps)
+    pub fn configure(&self, config: SessionConfig) {
+        self.config.store(Arc::new(config));
+    }
+
+    /// Create the session `id`, or return it if it already exists
+    pub fn create(&self, id: &str) -> Arc<Session> {
+        self.create_for_tenant(id, None)
+    }
+
+    /// Create the session `id` for a tenant, or return it if it already exists
+    /// (an existing session keeps its tenant)
+    pub fn create_for_tenant(&self, id: &str, tenant_id: Option<&str>) -> Arc<Session> {
+        self.sweep_if_due();
+        if let Some(session) = self.get(id) {
+            return session;
+        }
+
+        let config = self.config.load();
+        if self.sessions.len() >= config.max_sessions {
+            self.evict_longest_idle();
+        }
+        let trees = self.tree_limits(&config, tenant_id);
+        let session = self.sessions.entry(id.to_string()).or_insert_with(|| {
+            crate::metrics::METRICS.record_session_created();
+            Arc::new(Session::new(id.to_string(), tenant_id.map(str::to_string), config.predictor.clone(), trees))
+        });
+        Arc::clone(&session)
+    }
+
+    /// Tree limits of a session: the registry's, within the tenant's memory quota
+    fn tree_limits(&self, config: &SessionConfig, tenant_id: Option<&str>) -> TreeStoreConfig {
+        let mut trees = config.trees.clone();
+        if let Some(tenant_id) = tenant_id {
+            trees.max_memory_bytes = trees.max_memory_bytes.min(self.tenants.quota(tenant_id).max_memory_bytes);
+        }
+        trees
+    }
+
+    /// Look up a session, marking it as used
+    pub fn get(&self, id: &str) -> Option<Arc<Session>> {
+        self.sweep_if_due();
+        let session = self.sessions.get(id).map(|s| Arc::clone(&s))?;
+        session.touch();
+        Some(session)
+    }
+
+    /// Drop a session; returns false if it didn't exist
+    pub fn destroy(&self, id: &str) -> bool {
+        match self.sessions.remove(id) {
+            Some((_, session)) => {
+                session.release();
+                crate::latency::LATENCY.forget_client(id);
+                crate::metrics::METRICS.record_session_destroyed(false);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reconcile a component of a session, subject to the session's tenant quota
+    /// Suppressed updates produce an empty batch; the update closing the tenant's
+    /// breaker produces a Replace of the whole tree
+    pub fn reconcile(&self, id: &str, component_id: &str, new_tree: VNode) -> Result<PatchBatch> {
+        let session = self.get(id).ok_or_else(|| MinimactError::KeyNotFound(id.to_string()))?;
+        let Some(tenant_id) = session.tenant_id() else {
+            return session.reconcile(component_id, new_tree);
+        };
+
+        let batch = match self.tenants.check_rate(tenant_id) {
+            RateDecision::Allow => session.reconcile(component_id, new_tree)?,
+            RateDecision::Suppress => {
+                crate::log_debug!("Tenant '{}' over its reconcile rate, suppressing update", tenant_id);
+                session.set_tree(component_id, new_tree);
+                session.next_batch(component_id, Vec::new())
+            }
+            RateDecision::Flush => session.flush(component_id, new_tree),
+        };
+        self.enforce_tenant_quota(tenant_id);
+        Ok(batch)
+    }
+
+    /// Set a tenant's quota
+    pub fn configure_tenant(&self, tenant_id: &str, quota: TenantQuota) {
+        self.tenants.configure(tenant_id, quota);
+        let config = self.config.load();
+        for session in self.tenant_sessions(tenant_id) {
+            session.trees.configure(self.tree_limits(&config, Some(tenant_id)));
+        }
+        self.enforce_tenant_quota(tenant_id);
+    }
+
+    /// Current usage of a tenant's sessions
+    pub fn tenant_usage(&self, tenant_id: &str) -> TenantUsage {
+        self.tenant_sessions(tenant_id).iter().fold(TenantUsage::default(), |total, session| {
+            let usage = session.usage();
+            TenantUsage {
+                sessions: total.sessions + usage.sessions,
+                memory_bytes: total.memory_bytes + usage.memory_bytes,
+                patterns: total.patterns + usage.patterns,
+            }
+        })
+    }
+
+    /// Drop predictors of the tenant's longest idle sessions until it fits its quota,
+    /// then, if predictors weren't enough, their stored trees
+    /// Returns how many predictors and trees were dropped
+    pub fn enforce_tenant_quota(&self, tenant_id: &str) -> usize {
+        let quota = self.tenants.quota(tenant_id);
+        if !self.tenant_usage(tenant_id).exceeds(&quota) {
+            return 0;
+        }
+
+        let mut sessions = self.tenant_sessions(tenant_id);
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.idle_ms()));
+        let mut evicted = 0;
+        let clears: [fn(&Session) -> usize; 2] = [Session::clear_predictors, Session::clear_trees];
+        'clearing: for clear in clears {
+            for session in &sessions {
+                evicted += clear(session);
+                if !self.tenant_usage(tenant_id).exceeds(&quota) {
+                    break 'clearing;
+                }
+            }
+        }
+
+        crate::log_warn!("Tenant '{}' over quota, dropped {} predictors and trees", tenant_id, evicted);
+        self.tenants.record_quota_evictions(tenant_id, evicted);
+        evicted
+    }
+
+    /// Usage and counters of every tenant, by id
+    pub fn tenant_stats(&self) -> Vec<TenantStats> {
+        let mut tenant_ids = self.tenants.tenant_ids();
+        tenant_ids.extend(self.sessions.iter().filter_map(|s| s.tenant_id.clone()));
+        tenant_ids.sort();
+        tenant_ids.dedup();
+        tenant_ids
+            .into_iter()
+            .map(|tenant_id| {
+                let usage = self.tenant_usage(&tenant_id);
+                self.tenants.stats(&tenant_id, usage)
+            })
+            .collect()
+    }
+
+    fn tenant_sessions(&self, tenant_id: &str) -> Vec<Arc<Session>> {
+        self.sessions
+            .iter()
+            .filter(|session| session.tenant_id() == Some(tenant_id))
+            .map(|session| Arc::clone(&session))
+            .collect()
+    }
+
+    /// Stats of every session
+    pub fn list(&self) -> Vec<SessionStats> {
+        self.sweep_if_due();
+        let mut sessions: Vec<SessionStats> = self.sessions.iter().map(|s| s.stats()).collect();
+        sessions.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+        sessions
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    /// Drop sessions idle for longer than the timeout; returns how many were dropped
+    pub fn evict_idle(&self) -> usize {
+        let timeout = self.config.load().idle_timeout_ms;
+        let before = self.sessions.len();
+        self.sessions.retain(|id, session| {
+            let keep = session.idle_ms() <= timeout;
+            if !keep {
+                crate::log_info!("Session '{}' expired after {} ms idle", id, session.idle_ms());
+                session.release();
+                crate::latency::LATENCY.forget_client(id);
+                crate::metrics::METRICS.record_session_destroyed(true);
+            }
+            keep
+        });
+        before.saturating_sub(self.sessions.len())
+    }
+
+    fn sweep_if_due(&self) {
+        let now = self.started.elapsed().as_millis() as u64;
+        let interval = (self.config.load().idle_timeout_ms / 4).max(1);
+        let last = self.last_sweep_ms.load(Ordering::Relaxed);
+        // One caller wins the sweep; the rest carry on
+        if now.saturating_sub(last) >= interval
+            && self.last_sweep_ms.compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed).is_ok()
+        {
+            self.evict_idle();
+        }
+    }
+
+    fn evict_longest_idle(&self) {
+        let oldest = self.sessions.iter().max_by_key(|s| s.idle_ms()).map(|s| s.key().clone());
+        if let Some(id) = oldest {
+            crate::log_warn!("Session limit reached, dropping longest idle session '{}'", id);
+            if let Some((_, session)) = self.sessions.remove(&id) {
+                session.release();
+                crate::latency::LATENCY.forget_client(&id);
+                crate::metrics::METRICS.record_session_destroyed(true);
+            }
+        }
+    }
+}
+
+impl Default for SessionRegistry {
+    fn default() -> Self {
+        Self::new(SessionConfig::default())
+    }
+}
+
+lazy_static::lazy_static! {
+    /// Sessions managed through the FFI
+    pub static ref SESSIONS: SessionRegistry = SessionRegistry::default();
+}
+
+unsafe fn session_id_arg<'a>(ptr: *const c_char) -> std::result::Result<&'a str, FfiResult> {
+    str_arg(ptr, "session_id").map_err(|e| FfiResult::error(&e))
+}
+
+unsafe fn str_arg<'a>(ptr: *const c_char, name: &'static str) -> Result<&'a str> {
+    if ptr.is_null() {
+        return Err(MinimactError::NullPointer(name));
+    }
+    Ok(CStr::from_ptr(ptr).to_str()?)
+}
+
+/// Set the session registry's config (SessionConfig JSON)
+///
+/// # Safety
+/// - config_json must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_sessions_configure(config_json: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_sessions_configure", &[config_json]);
+    let config_str = match CStr::from_ptr(config_json).to_str() {
+        Ok(s) => s,
+        Err(e) => return FfiResult::error(&MinimactError::from(e)),
+    };
+    match serde_json::from_str::<SessionConfig>(config_str) {
+        Ok(config) => {
+            SESSIONS.configure(config);
+            FfiResult::success()
+        }
+        Err(e) => FfiResult::error(&MinimactError::from(e)),
+    }
+}
+
+/// Create a session (no-op if it exists)
+///
+/// # Safety
+/// - session_id must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_session_create(session_id: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_session_create", &[session_id]);
+    match session_id_arg(session_id) {
+        Ok(id) => {
+            SESSIONS.create(id);
+            FfiResult::success()
+        }
+        Err(result) => result,
+    }
+}
+
+/// Create a session belonging to a tenant (no-op if it exists)
+///
+/// # Safety
+/// - session_id and tenant_id must be valid null-terminated UTF-8 strings
+#[no_mangle]
+pub unsafe extern "C" fn minimact_session_create_for_tenant(session_id: *const c_char, tenant_id: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_session_create_for_tenant", &[session_id, tenant_id]);
+    let id = match session_id_arg(session_id) {
+        Ok(id) => id,
+        Err(result) => return result,
+    };
+    match CStr::from_ptr(tenant_id).to_str() {
+        Ok(tenant_id) => {
+            SESSIONS.create_for_tenant(id, Some(tenant_id));
+            FfiResult::success()
+        }
+        Err(e) => FfiResult::error(&MinimactError::from(e)),
+    }
+}
+
+/// Set a tenant's quota (TenantQuota JSON)
+///
+/// # Safety
+/// - tenant_id and quota_json must be valid null-terminated UTF-8 strings
+#[no_mangle]
+pub unsafe extern "C" fn minimact_tenant_configure(tenant_id: *const c_char, quota_json: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_tenant_configure", &[tenant_id, quota_json]);
+    let result = (|| -> Result<()> {
+        let tenant_id = CStr::from_ptr(tenant_id).to_str()?;
+        let quota: TenantQuota = serde_json::from_str(CStr::from_ptr(quota_json).to_str()?)?;
+        SESSIONS.configure_tenant(tenant_id, quota);
+        Ok(())
+    })();
+    match result {
+        Ok(()) => FfiResult::success(),
+        Err(e) => FfiResult::error(&e),
+    }
+}
+
+/// Usage and counters of every tenant as a JSON array of TenantStats
+///
+/// # Safety
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_tenant_stats() -> *mut c_char {
+    let _call = FfiCall::named("minimact_tenant_stats");
+    match serde_json::to_string(&SESSIONS.tenant_stats()) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Destroy a session with its predictors and trees
+///
+/// # Safety
+/// - session_id must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_session_destroy(session_id: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_session_destroy", &[session_id]);
+    match session_id_arg(session_id) {
+        Ok(id) if SESSIONS.destroy(id) => FfiResult::success(),
+        Ok(id) => FfiResult::error(&MinimactError::KeyNotFound(id.to_string())),
+        Err(result) => result,
+    }
+}
+
+/// Set a session's patch filter (PatchFilter JSON) for one component, or for all
+/// of them when component_id is null; a null filter_json removes the filter
+///
+/// # Safety
+/// - session_id must be a valid null-terminated UTF-8 string
+/// - component_id and filter_json must be null or valid null-terminated UTF-8 strings
+#[no_mangle]
+pub unsafe extern "C" fn minimact_session_set_patch_filter(
+    session_id: *const c_char,
+    component_id: *const c_char,
+    filter_json: *const c_char,
+) -> FfiResult {
+    let _call = FfiCall::enter("minimact_session_set_patch_filter", &[session_id, component_id, filter_json]);
+    let id = match session_id_arg(session_id) {
+        Ok(id) => id,
+        Err(result) => return result,
+    };
+    let result = (|| -> Result<()> {
+        let session = SESSIONS.get(id).ok_or_else(|| MinimactError::KeyNotFound(id.to_string()))?;
+        let component_id = if component_id.is_null() { None } else { Some(CStr::from_ptr(component_id).to_str()?) };
+        let filter = if filter_json.is_null() {
+            None
+        } else {
+            Some(serde_json::from_str::<PatchFilter>(CStr::from_ptr(filter_json).to_str()?)?)
+        };
+        session.set_patch_filter(component_id, filter);
+        Ok(())
+    })();
+    match result {
+        Ok(()) => FfiResult::success(),
+        Err(e) => FfiResult::error(&e),
+    }
+}
+
+/// Set a session's locale; a null locale makes it unknown
+///
+/// # Safety
+/// - session_id must be a valid null-terminated UTF-8 string
+/// - locale must be null or a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_session_set_locale(session_id: *const c_char, locale: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_session_set_locale", &[session_id, locale]);
+    let id = match session_id_arg(session_id) {
+        Ok(id) => id,
+        Err(result) => return result,
+    };
+    let result = (|| -> Result<()> {
+        let session = SESSIONS.get(id).ok_or_else(|| MinimactError::KeyNotFound(id.to_string()))?;
+        let locale = if locale.is_null() { None } else { Some(CStr::from_ptr(locale).to_str()?) };
+        session.set_locale(locale);
+        Ok(())
+    })();
+    match result {
+        Ok(()) => FfiResult::success(),
+        Err(e) => FfiResult::error(&e),
+    }
+}
+
+/// List sessions as a JSON array of SessionStats
+///
+/// # Safety
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_session_list() -> *mut c_char {
+    let _call = FfiCall::named("minimact_session_list");
+    match serde_json::to_string(&SESSIONS.list()) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Reconcile a component of a session against its last tree (subject to its tenant's quota)
+/// Returns a PatchBatch as JSON (or {"error": ...})
+///
+/// # Safety
+/// - All pointers must be valid null-terminated UTF-8 strings
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_session_reconcile(
+    session_id: *const c_char,
+    component_id: *const c_char,
+    new_tree_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_session_reconcile", &[session_id, component_id, new_tree_json]);
+    let result = (|| -> Result<PatchBatch> {
+        let id = str_arg(session_id, "session_id")?;
+        let component_id = str_arg(component_id, "component_id")?;
+        let tree = crate::validation::deserialize_vnode_safe(
+            str_arg(new_tree_json, "new_tree_json")?,
+            &crate::validation::ValidationConfig::default(),
+        )?;
+        SESSIONS.reconcile(id, component_id, tree)
+    })();
+
+    match result.and_then(|batch| Ok(serde_json::to_string(&batch)?)) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => {
+            crate::last_error::record_error(&e);
+            CString::new(serde_json::json!({ "error": e.to_string() }).to_string()).unwrap().into_raw()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::HexPath;
+
+    fn text(content: &str) -> VNode {
+        VNode::Text(crate::vdom::VText { content: content.to_string(), path: HexPath::from("10000000") })
+    }
+
+    #[test]
+    fn test_session_reconciles_against_last_tree() {
+        let registry = SessionRegistry::default();
+        let session = registry.create("client-1");
+
+        let first = session.reconcile("Counter", text("0")).unwrap();
+        assert_eq!(first.sequence, 1);
+        assert!(matches!(&first.patches[0].patch, Patch::Replace { .. }));
+
+        let second = session.reconcile("Counter", text("1")).unwrap();
+        assert_eq!(second.sequence, 2);
+        assert_eq!(second.into_patches(), vec![Patch::UpdateText { path: HexPath::from("10000000"), content: "1".to_string() }]);
+
+        let stats = &registry.list()[0];
+        assert_eq!((stats.components, stats.reconciles, stats.patches_sent), (1, 2, 2));
+        assert!(Arc::ptr_eq(&session.predictor("Counter"), &session.predictor("Counter")));
+
+        assert!(registry.destroy("client-1"));
+        assert!(registry.get("client-1").is_none());
+    }
+
+    #[test]
+    fn test_patch_filters_withhold_patches() {
+        let session = SessionRegistry::default().create("client-1");
+        session.reconcile("Widget", text("0")).unwrap();
+        session.set_patch_filter(Some("Widget"), Some(PatchFilter { deny_kinds: vec!["UpdateText".to_string()], ..Default::default() }));
+
+        assert!(session.reconcile("Widget", text("1")).unwrap().patches.is_empty());
+        assert_eq!(session.tree("Widget").as_deref(), Some(&text("1")));
+        assert_eq!(session.reconcile("Other", text("1")).unwrap().patches.len(), 1);
+
+        session.set_patch_filter(Some("Widget"), None);
+        assert_eq!(session.reconcile("Widget", text("2")).unwrap().patches.len(), 1);
+    }
+
+    #[test]
+    fn test_idle_sessions_expire() {
+        let registry = SessionRegistry::new(SessionConfig { idle_timeout_ms: 20, max_sessions: 2, ..Default::default() });
+        registry.create("a");
+        registry.create("b");
+        registry.create("c"); // over the limit: the longest idle goes
+        assert_eq!(registry.len(), 2);
+
+        std::thread::sleep(std::time::Duration::from_millis(40));
+        registry.get("c");
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_tenant_quota_only_evicts_own_predictors() {
+        let registry = SessionRegistry::default();
+        registry.create_for_tenant("big-1", Some("big"));
+        registry.create_for_tenant("small-1", Some("small"));
+        let change = |key: &str| crate::predictor::StateChange {
+            component_id: "App".to_string(),
+            state_key: key.to_string(),
+            old_value: serde_json::json!(0),
+            new_value: serde_json::json!(1),
+            array_operation: None,
+            locale: None,
+        };
+        for id in ["big-1", "small-1"] {
+            let predictor = registry.get(id).unwrap().predictor("App");
+            predictor.learn(change("a"), &text("a"), &text("b"), None).unwrap();
+        }
+
+        registry.configure_tenant("big", TenantQuota { max_patterns: 0, ..Default::default() });
+        assert_eq!(registry.tenant_usage("big").patterns, 0);
+        assert!(registry.tenant_usage("small").patterns > 0);
+
+        let stats = registry.tenant_stats();
+        assert_eq!(stats.iter().map(|s| (s.tenant_id.as_str(), s.quota_evictions)).collect::<Vec<_>>(), vec![("big", 1), ("small", 0)]);
+        assert!(registry.reconcile("big-1", "App", text("x")).is_ok());
+    }
+
+    #[test]
+    fn test_trees_are_bounded_and_released() {
+        let registry = SessionRegistry::default();
+        let session = registry.create_for_tenant("t-1", Some("t"));
+        session.reconcile("A", text("a")).unwrap();
+        session.reconcile("B", text("b")).unwrap();
+
+        // Predictors alone can't bring stored trees under a tiny memory quota
+        registry.configure_tenant("t", TenantQuota { max_memory_bytes: 1, ..Default::default() });
+        assert_eq!(session.stats().components, 0);
+        assert!(matches!(&registry.reconcile("t-1", "A", text("a")).unwrap().patches[0].patch, Patch::Replace { .. }));
+
+        let config = SessionConfig { trees: TreeStoreConfig { max_trees: 2, ..Default::default() }, ..Default::default() };
+        let registry = SessionRegistry::new(config);
+        let session = registry.create("client-1");
+        for component in ["A", "B", "C"] {
+            session.reconcile(component, text("x")).unwrap();
+        }
+        assert!(session.stats().components <= 2);
+
+        // A dropped session frees its trees even while still referenced
+        assert!(registry.destroy("client-1"));
+        assert_eq!(session.stats().components, 0);
+    }
+
+    #[test]
+    fn test_locales_learn_separate_templates() {
+        let template = |prediction: Option<Prediction>| match prediction.map(|p| p.predicted_patches).as_deref() {
+            Some([Patch::UpdateTextTemplate { template_patch, .. }]) => Some(template_patch.template.clone()),
+            _ => None,
+        };
+        let change = StateChange {
+            component_id: "Cart".to_string(),
+            state_key: "count".to_string(),
+            old_value: serde_json::json!(1),
+            new_value: serde_json::json!(2),
+            array_operation: None,
+            locale: None,
+        };
+        let state = std::collections::HashMap::from([("count".to_string(), serde_json::json!(1))]);
+        let next = StateChange { old_value: serde_json::json!(2), new_value: serde_json::json!(3), ..change.clone() };
+
+        let session = SessionRegistry::default().create("client-1");
+        for (locale, one, two) in [("en", "1 item", "2 items"), ("fr", "1 article", "2 articles")] {
+            session.set_locale(Some(locale));
+            session.learn("Cart", change.clone(), &text(one), &text(two), Some(&state)).unwrap();
+        }
+        assert_eq!(template(session.predict("Cart", &next, &text("2 articles"))).as_deref(), Some("{0} articles"));
+        session.set_locale(Some("en"));
+        assert_eq!(template(session.predict("Cart", &next, &text("2 items"))).as_deref(), Some("{0} items"));
+
+        // Without a locale, text is learned literally
+        let mut config = SessionConfig::default();
+        config.predictor.localization.require_locale = true;
+        let session = SessionRegistry::new(config).create("client-2");
+        session.learn("Cart", change, &text("1 item"), &text("2 items"), Some(&state)).unwrap();
+        assert!(session.predictor("Cart").snapshot().template_patches("Cart").is_empty());
+    }
+}
@@ -0,0 +1,317 @@
+//! Immutable, structurally shared VNode trees for long-lived storage
+//!
+//! The predictor keeps the last old/new tree of every pattern. Stored as plain
+//! VNodes, each pattern owned a full deep copy, even though consecutive renders of
+//! a component (and different components rendering the same markup) are mostly
+//! identical. A SharedTree is an Arc-based copy whose subtrees are hash-consed
+//! through a process-wide interner: converting a tree reuses every subtree that is
+//! already stored somewhere, so identical subtrees exist in memory once.
+//!
+//! Shared trees are read-only. The interner only holds weak references, so
+//! subtrees are freed when the last pattern holding them is evicted.
+
+use crate::vdom::{SourceLocation, VElement, VLazy, VNode, VNull, VText};
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, Weak};
+
+/// A shared, immutable VNode subtree (cheap to clone)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "VNode", from = "VNode")]
+pub struct SharedTree(Arc<SharedNode>);
+
+/// One node of a shared tree
+#[derive(Debug)]
+pub struct SharedNode {
+    /// Hash of the whole subtree (content, keys and paths)
+    hash: u64,
+    pub kind: SharedKind,
+}
+
+#[derive(Debug)]
+pub enum SharedKind {
+    Element(SharedElement),
+    Text(VText),
+    Null(VNull),
+    Lazy(VLazy),
+}
+
+#[derive(Debug)]
+pub struct SharedElement {
+    pub tag: String,
+    pub props: HashMap<String, String>,
+    pub children: Vec<Option<SharedTree>>,
+    pub key: Option<String>,
+    pub path: crate::path::HexPath,
+    pub source: Option<Box<SourceLocation>>,
+}
+
+/// Interned nodes by subtree hash (collisions are resolved by comparison)
+#[derive(Default)]
+struct Interner {
+    nodes: HashMap<u64, SmallVec<[Weak<SharedNode>; 1]>>,
+    /// Entry count at which dead weak references are next swept
+    next_sweep: usize,
+}
+
+/// Entries below this count are never swept
+const MIN_SWEEP: usize = 1024;
+
+lazy_static::lazy_static! {
+    static ref INTERNER: Mutex<Interner> = Mutex::new(Interner::default());
+}
+
+impl Interner {
+    fn intern(&mut self, node: SharedNode) -> SharedTree {
+        let candidates = self.nodes.entry(node.hash).or_default();
+        if let Some(existing) = candidates
+            .iter()
+            .filter_map(Weak::upgrade)
+            .find(|existing| existing.same_shallow(&node))
+        {
+            return SharedTree(existing);
+        }
+
+        let shared = Arc::new(node);
+        candidates.retain(|weak| weak.strong_count() > 0);
+        candidates.push(Arc::downgrade(&shared));
+
+        if self.nodes.len() >= self.next_sweep.max(MIN_SWEEP) {
+            self.sweep();
+        }
+        SharedTree(shared)
+    }
+
+    /// Drop entries whose subtrees have been freed
+    fn sweep(&mut self) {
+        self.nodes.retain(|_, candidates| {
+            candidates.retain(|weak| weak.strong_count() > 0);
+            !candidates.is_empty()
+        });
+        self.next_sweep = self.nodes.len() * 2;
+    }
+}
+
+impl SharedNode {
+    /// Equal content, with children compared by identity (they are interned first)
+    fn same_shallow(&self, other: &SharedNode) -> bool {
+        match (&self.kind, &other.kind) {
+            (SharedKind::Element(a), SharedKind::Element(b)) => {
+                a.tag == b.tag
+                    && a.key == b.key
+                    && a.path == b.path
+                    && a.source == b.source
+                    && a.props == b.props
+                    && a.children.len() == b.children.len()
+                    && a.children.iter().zip(&b.children).all(|pair| match pair {
+                        (Some(x), Some(y)) => Arc::ptr_eq(&x.0, &y.0),
+                        (None, None) => true,
+                        _ => false,
+                    })
+            }
+            (SharedKind::Text(a), SharedKind::Text(b)) => a == b,
+            (SharedKind::Null(a), SharedKind::Null(b)) => a == b,
+            (SharedKind::Lazy(a), SharedKind::Lazy(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl SharedTree {
+    /// Convert a tree, sharing every subtree that is already stored elsewhere
+    pub fn from_vnode(node: &VNode) -> Self {
+        let mut interner = INTERNER.lock().unwrap();
+        Self::build(node, &mut interner)
+    }
+
+    fn build(node: &VNode, interner: &mut Interner) -> Self {
+        let mut hasher = DefaultHasher::new();
+        let kind = match node {
+            VNode::Element(el) => {
+                let children: Vec<Option<SharedTree>> = el
+                    .children
+                    .iter()
+                    .map(|child| child.as_ref().map(|c| Self::build(c, interner)))
+                    .collect();
+
+                b'E'.hash(&mut hasher);
+                el.tag.hash(&mut hasher);
+                el.key.hash(&mut hasher);
+                el.path.as_str().hash(&mut hasher);
+                el.source.hash(&mut hasher);
+                let mut props: Vec<(&String, &String)> = el.props.iter().collect();
+                props.sort();
+                props.hash(&mut hasher);
+                for child in &children {
+                    child.as_ref().map(|c| c.0.hash).hash(&mut hasher);
+                }
+
+                SharedKind::Element(SharedElement {
+                    tag: el.tag.clone(),
+                    props: el.props.clone(),
+                    children,
+                    key: el.key.clone(),
+                    path: el.path.clone(),
+                    source: el.source.clone(),
+                })
+            }
+            VNode::Text(text) => {
+                b'T'.hash(&mut hasher);
+                text.content.hash(&mut hasher);
+                text.path.as_str().hash(&mut hasher);
+                SharedKind::Text(text.clone())
+            }
+            VNode::Null(null) => {
+                b'N'.hash(&mut hasher);
+                null.path.as_str().hash(&mut hasher);
+                SharedKind::Null(null.clone())
+            }
+            VNode::Lazy(lazy) => {
+                b'L'.hash(&mut hasher);
+                lazy.summary.hash(&mut hasher);
+                lazy.path.as_str().hash(&mut hasher);
+                SharedKind::Lazy(lazy.clone())
+            }
+        };
+
+        interner.intern(SharedNode { hash: hasher.finish(), kind })
+    }
+
+    /// Materialize an owned VNode copy
+    pub fn to_vnode(&self) -> VNode {
+        match &self.0.kind {
+            SharedKind::Element(el) => VNode::Element(VElement {
+                tag: el.tag.clone(),
+                props: el.props.clone(),
+                children: el.children.iter().map(|c| c.as_ref().map(SharedTree::to_vnode)).collect(),
+                key: el.key.clone(),
+                path: el.path.clone(),
+                source: el.source.clone(),
+            }),
+            SharedKind::Text(text) => VNode::Text(text.clone()),
+            SharedKind::Null(null) => VNode::Null(null.clone()),
+            SharedKind::Lazy(lazy) => VNode::Lazy(lazy.clone()),
+        }
+    }
+
+    /// The root node
+    pub fn node(&self) -> &SharedNode {
+        &self.0
+    }
+
+    /// Check if two trees are the same shared allocation
+    pub fn ptr_eq(&self, other: &SharedTree) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Approximate heap size of the nodes not yet in `seen`, adding them to it
+    /// Summing over several trees with one `seen` set counts shared subtrees once
+    pub fn estimate_unique_size(&self, seen: &mut HashSet<usize>) -> usize {
+        if !seen.insert(Arc::as_ptr(&self.0) as usize) {
+            return 0;
+        }
+        std::mem::size_of::<SharedNode>()
+            + match &self.0.kind {
+                SharedKind::Element(el) => {
+                    el.tag.capacity()
+                        + el.key.as_ref().map_or(0, |k| k.capacity())
+                        + el.props.iter().map(|(k, v)| k.capacity() + v.capacity()).sum::<usize>()
+                        + el.children.capacity() * std::mem::size_of::<Option<SharedTree>>()
+                        + el.children.iter().flatten().map(|c| c.estimate_unique_size(seen)).sum::<usize>()
+                }
+                SharedKind::Text(text) => text.content.capacity(),
+                SharedKind::Null(_) => 0,
+                SharedKind::Lazy(lazy) => lazy.summary.capacity(),
+            }
+    }
+}
+
+impl From<VNode> for SharedTree {
+    fn from(node: VNode) -> Self {
+        SharedTree::from_vnode(&node)
+    }
+}
+
+impl From<SharedTree> for VNode {
+    fn from(tree: SharedTree) -> Self {
+        tree.to_vnode()
+    }
+}
+
+impl PartialEq for SharedTree {
+    fn eq(&self, other: &Self) -> bool {
+        // Interned trees are equal exactly when they share the allocation
+        self.ptr_eq(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::HexPath;
+
+    fn list(items: &[&str]) -> VNode {
+        let root = HexPath::from("10000000");
+        VNode::Element(VElement {
+            tag: "ul".to_string(),
+            props: HashMap::new(),
+            children: items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let path = root.child(i);
+                    Some(VNode::Element(VElement {
+                        tag: "li".to_string(),
+                        props: HashMap::new(),
+                        children: vec![Some(VNode::Text(VText { content: item.to_string(), path: path.child(0) }))],
+                        key: None,
+                        path,
+                        source: None,
+                    }))
+                })
+                .collect(),
+            key: None,
+            path: root,
+            source: None,
+        })
+    }
+
+    fn child(tree: &SharedTree, index: usize) -> SharedTree {
+        match &tree.node().kind {
+            SharedKind::Element(el) => el.children[index].clone().unwrap(),
+            _ => panic!("not an element"),
+        }
+    }
+
+    #[test]
+    fn test_identical_subtrees_are_shared() {
+        let before = SharedTree::from_vnode(&list(&["a", "b", "c"]));
+        let after = SharedTree::from_vnode(&list(&["a", "B", "c"]));
+
+        assert!(!before.ptr_eq(&after));
+        assert!(child(&before, 0).ptr_eq(&child(&after, 0)));
+        assert!(!child(&before, 1).ptr_eq(&child(&after, 1)));
+        assert!(child(&before, 2).ptr_eq(&child(&after, 2)));
+        assert!(before.ptr_eq(&SharedTree::from_vnode(&list(&["a", "b", "c"]))));
+
+        let mut seen = HashSet::new();
+        let first = before.estimate_unique_size(&mut seen);
+        let second = after.estimate_unique_size(&mut seen);
+        assert!(second < first);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let tree = list(&["x", "y"]);
+        let shared = SharedTree::from_vnode(&tree);
+        assert_eq!(shared.to_vnode(), tree);
+
+        let json = serde_json::to_string(&shared).unwrap();
+        assert_eq!(json, serde_json::to_string(&tree).unwrap());
+        let back: SharedTree = serde_json::from_str(&json).unwrap();
+        assert!(back.ptr_eq(&shared));
+    }
+}
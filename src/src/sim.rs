@@ -0,0 +1,151 @@
+//! Client simulator
+//!
+//! Plays the browser's part without a browser: keeps the tree the client would have,
+//! applies the server's patch batches to it with the apply engine (validating each
+//! patch against the tree the ones before it left, as the client runtime does) and
+//! renders it back to HTML. Integration
+//! tests can then assert on the page the user would see instead of on patch lists.
+//!
+//! Navigate patches don't change the tree; the simulator keeps the last URL in
+//! `location` instead. The `minimact-sim` binary wraps this for the command line.
+
+use crate::apply::apply_patch_indexed;
+use crate::error::Result;
+use crate::patch_validator::{validate_patch_indexed, PatchValidatorConfig};
+use crate::ssr::{escape, VOID_ELEMENTS};
+use crate::tree_index::TreeIndex;
+use crate::vdom::{Patch, VNode};
+
+/// An in-memory client
+pub struct Simulator {
+    tree: VNode,
+    location: Option<String>,
+    batches: usize,
+    config: PatchValidatorConfig,
+}
+
+impl Simulator {
+    /// Start from the tree the client first rendered
+    pub fn new(tree: VNode) -> Self {
+        Self { tree, location: None, batches: 0, config: PatchValidatorConfig::default() }
+    }
+
+    pub fn from_json(tree_json: &str) -> Result<Self> {
+        Ok(Self::new(serde_json::from_str(tree_json)?))
+    }
+
+    /// Apply one batch; a batch that fails validation leaves the tree unchanged
+    pub fn apply_batch(&mut self, patches: &[Patch]) -> Result<()> {
+        let mut tree = self.tree.clone();
+        let mut index = TreeIndex::build(&tree);
+        for patch in patches {
+            validate_patch_indexed(patch, &tree, &index, &self.config)?;
+            apply_patch_indexed(&mut tree, &index, patch)?;
+            index.invalidate(patch, &tree);
+        }
+        self.tree = tree;
+        if let Some(url) = patches.iter().rev().find_map(|p| match p {
+            Patch::Navigate { url, .. } => Some(url),
+            _ => None,
+        }) {
+            self.location = Some(url.clone());
+        }
+        self.batches += 1;
+        Ok(())
+    }
+
+    /// Apply one batch given as a JSON array of patches
+    pub fn apply_batch_json(&mut self, patches_json: &str) -> Result<()> {
+        let patches: Vec<Patch> = serde_json::from_str(patches_json)?;
+        self.apply_batch(&patches)
+    }
+
+    pub fn tree(&self) -> &VNode {
+        &self.tree
+    }
+
+    /// Last URL navigated to, if any
+    pub fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+
+    /// Number of batches applied
+    pub fn batches(&self) -> usize {
+        self.batches
+    }
+
+    /// The current page as HTML
+    pub fn html(&self) -> String {
+        to_html(&self.tree)
+    }
+}
+
+/// Render a tree as HTML for assertions (attributes sorted, so output is stable)
+/// Props are written as they are; what the client is sent comes from `ssr`
+pub fn to_html(node: &VNode) -> String {
+    let mut out = String::new();
+    write_html(node, &mut out);
+    out
+}
+
+fn write_html(node: &VNode, out: &mut String) {
+    match node {
+        VNode::Element(el) => {
+            out.push('<');
+            out.push_str(&el.tag);
+            let mut props: Vec<_> = el.props.iter().collect();
+            props.sort();
+            for (name, value) in props {
+                let name = if name == "className" { "class" } else { name.as_str() };
+                out.push_str(&format!(" {}=\"{}\"", name, escape(value)));
+            }
+            out.push('>');
+            if VOID_ELEMENTS.contains(&el.tag.as_str()) {
+                return;
+            }
+            for child in el.children.iter().flatten() {
+                write_html(child, out);
+            }
+            out.push_str(&format!("</{}>", el.tag));
+        }
+        VNode::Text(text) => out.push_str(&escape(&text.content)),
+        VNode::Null(_) => {}
+        VNode::Lazy(lazy) => out.push_str(&format!("<!--lazy: {}-->", lazy.summary.replace("--", "- -"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reconciler::reconcile;
+    use crate::vdom::VElement;
+    use std::collections::HashMap;
+
+    fn counter(count: &str) -> VNode {
+        let path = crate::path::HexPath::from("10000000");
+        VNode::Element(VElement {
+            tag: "p".to_string(),
+            props: HashMap::from([("className".to_string(), "count".to_string())]),
+            children: vec![Some(VNode::Text(crate::vdom::VText { content: count.to_string(), path: path.child(0) }))],
+            key: None,
+            path,
+            source: None,
+        })
+    }
+
+    #[test]
+    fn test_server_patches_render_the_new_page() {
+        let mut sim = Simulator::new(counter("0"));
+        assert_eq!(sim.html(), "<p class=\"count\">0</p>");
+
+        let patches = reconcile(&counter("0"), &counter("1 < 2")).unwrap();
+        sim.apply_batch_json(&serde_json::to_string(&patches).unwrap()).unwrap();
+        assert_eq!(sim.html(), "<p class=\"count\">1 &lt; 2</p>");
+        assert_eq!(sim.tree(), &counter("1 < 2"));
+
+        // Rejected batches don't count and don't touch the page
+        let bad = vec![Patch::UpdateText { path: crate::path::HexPath::from("f0000000"), content: "x".to_string() }];
+        assert!(sim.apply_batch(&bad).is_err());
+        assert_eq!(sim.batches(), 1);
+    }
+}
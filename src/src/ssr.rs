@@ -0,0 +1,116 @@
+//! Server-side HTML rendering
+//!
+//! Renders a tree as the markup the client runtime would have built from it, so the
+//! client can take the HTML in place of patches (see `html_delivery`):
+//! - event props (`onClick: "Handle0"`) become the `data-onclick` attributes event
+//!   delegation reads; they never become inline `on*` handlers
+//! - `className` becomes `class`, keys become `data-key`
+//! - every element carries its path in `data-minimact-path`, so later patches find
+//!   it; text nodes are found through their parent's marker
+//!
+//! Client-owned regions and lazy placeholders can't be rendered faithfully; callers
+//! must keep such trees on patches (`html_unsafe`).
+
+use crate::vdom::VNode;
+
+/// Attribute carrying an element's hex path
+pub const PATH_ATTR: &str = "data-minimact-path";
+
+/// Elements with no closing tag
+pub(crate) const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// Render `node` as HTML (attributes sorted, so output is stable)
+pub fn render_html(node: &VNode) -> String {
+    let mut out = String::new();
+    write_node(node, &mut out);
+    out
+}
+
+/// Check if the tree has parts HTML can't carry (client-owned regions, lazy subtrees)
+pub fn html_unsafe(node: &VNode) -> bool {
+    match node {
+        VNode::Element(el) => crate::ignored_regions::is_ignored(el) || el.children.iter().flatten().any(html_unsafe),
+        VNode::Lazy(_) => true,
+        VNode::Text(_) | VNode::Null(_) => false,
+    }
+}
+
+fn write_node(node: &VNode, out: &mut String) {
+    match node {
+        VNode::Element(el) => {
+            let mut attributes: Vec<(String, &str)> = el
+                .props
+                .iter()
+                .filter(|(name, _)| name.as_str() != PATH_ATTR && name.as_str() != "data-key")
+                .map(|(name, value)| (attribute_name(name), value.as_str()))
+                .collect();
+            if let Some(key) = &el.key {
+                attributes.push(("data-key".to_string(), key));
+            }
+            attributes.push((PATH_ATTR.to_string(), el.path.as_str()));
+            attributes.sort();
+
+            out.push('<');
+            out.push_str(&el.tag);
+            for (name, value) in attributes {
+                out.push_str(&format!(" {}=\"{}\"", name, escape(value)));
+            }
+            out.push('>');
+            if VOID_ELEMENTS.contains(&el.tag.as_str()) {
+                return;
+            }
+            for child in el.children.iter().flatten() {
+                write_node(child, out);
+            }
+            out.push_str(&format!("</{}>", el.tag));
+        }
+        VNode::Text(text) => out.push_str(&escape(&text.content)),
+        VNode::Null(_) | VNode::Lazy(_) => {}
+    }
+}
+
+/// The DOM attribute a prop is rendered as (the same mapping the client runtime uses)
+fn attribute_name(prop: &str) -> String {
+    if prop == "className" {
+        "class".to_string()
+    } else if prop.starts_with("on") {
+        format!("data-{}", prop.to_lowercase())
+    } else {
+        prop.to_string()
+    }
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::HexPath;
+    use crate::vdom::{VElement, VText};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_handlers_become_delegation_attributes() {
+        let path = HexPath::from("10000000");
+        let button = VNode::Element(VElement {
+            tag: "button".to_string(),
+            props: HashMap::from([
+                ("onClick".to_string(), "Handle0".to_string()),
+                ("className".to_string(), "btn \"primary\"".to_string()),
+            ]),
+            children: vec![Some(VNode::Text(VText { content: "<Go>".to_string(), path: path.child(0) }))],
+            key: Some("go".to_string()),
+            path,
+            source: None,
+        });
+
+        assert_eq!(
+            render_html(&button),
+            "<button class=\"btn &quot;primary&quot;\" data-key=\"go\" data-minimact-path=\"10000000\" data-onclick=\"Handle0\">&lt;Go&gt;</button>"
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! Strict mode: double-run consistency checks for development
+//!
+//! Nondeterminism in the engine (HashMap iteration order leaking into patch order,
+//! the node equality check in the reconciler that once misbehaved when optimized)
+//! shows up as different output for the same input. With strict mode on
+//! (process-wide, off by default):
+//! - every reconcile runs twice and the two results are compared
+//! - every prediction is made twice from the same predictor state
+//! - every learn is repeated on a copy of the predictor taken before it, and the two
+//!   predictors' predictions for the learned change are compared
+//!
+//! A divergence is logged with the inputs, both outputs and where they first differ,
+//! counted in the metrics and kept (the most recent ones) for `divergences`; with
+//! `panic_on_divergence` it panics instead, like the paranoid checks. The second runs
+//! aren't counted in the metrics. Meant for development only: everything runs twice.
+//! A time window (e.g. duplicate suppression) closing between the two runs shows up
+//! as a spurious divergence.
+
+use crate::error::{FfiResult, MinimactError};
+use crate::last_error::FfiCall;
+use arc_swap::ArcSwap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cell::Cell;
+use std::collections::{BTreeSet, VecDeque};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::{Arc, Mutex};
+
+/// Divergences kept for `divergences`; the oldest are dropped past this
+const MAX_DIVERGENCES: usize = 32;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StrictConfig {
+    pub enabled: bool,
+    /// Panic on a divergence instead of logging it
+    pub panic_on_divergence: bool,
+}
+
+/// Two runs of one operation over the same input that disagreed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Divergence {
+    /// "reconcile", "predict" or "learn"
+    pub operation: String,
+    /// Where the outputs first differ (a JSON path into them)
+    pub difference: String,
+    pub first: Value,
+    pub second: Value,
+    /// The operation's input
+    pub context: Value,
+}
+
+lazy_static::lazy_static! {
+    static ref STRICT: ArcSwap<StrictConfig> = ArcSwap::from_pointee(StrictConfig::default());
+    static ref DIVERGENCES: Mutex<VecDeque<Divergence>> = Mutex::new(VecDeque::new());
+}
+
+thread_local! {
+    /// Set while an operation runs a second time, so nothing nested in it is checked
+    static RERUNNING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Turn strict mode on or off (process-wide)
+pub fn set_strict_mode(config: StrictConfig) {
+    STRICT.store(Arc::new(config));
+}
+
+pub fn strict_mode() -> StrictConfig {
+    **STRICT.load()
+}
+
+/// Check if operations on this thread should run twice
+pub(crate) fn is_checking() -> bool {
+    strict_mode().enabled && !is_rerunning()
+}
+
+/// Check if this thread is running an operation a second time
+pub(crate) fn is_rerunning() -> bool {
+    RERUNNING.with(Cell::get)
+}
+
+/// Run an operation a second time (nested operations aren't checked or counted)
+pub(crate) fn rerun<T>(run: impl FnOnce() -> T) -> T {
+    // Restores the flag also when `run` panics, so the thread keeps being checked
+    struct Restore(bool);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            RERUNNING.with(|r| r.set(self.0));
+        }
+    }
+
+    let _restore = Restore(RERUNNING.with(|r| r.replace(true)));
+    run()
+}
+
+/// Report a divergence if the outputs of two runs differ
+/// `context` describes the input and is only built on a divergence
+pub(crate) fn check<T: Serialize + ?Sized>(operation: &str, context: impl FnOnce() -> Value, first: &T, second: &T) {
+    if let Some(divergence) = diverge(operation, first, second, context) {
+        report(divergence);
+    }
+}
+
+/// The divergence between the outputs of two runs, if they differ
+pub fn diverge<T: Serialize + ?Sized>(
+    operation: &str,
+    first: &T,
+    second: &T,
+    context: impl FnOnce() -> Value,
+) -> Option<Divergence> {
+    let to_value = |output: &T| serde_json::to_value(output).unwrap_or_else(|e| Value::String(format!("<unserializable: {}>", e)));
+    let (first, second) = (to_value(first), to_value(second));
+    let difference = first_difference(&first, &second, "")?;
+    Some(Divergence { operation: operation.to_string(), difference, first, second, context: context() })
+}
+
+fn report(divergence: Divergence) {
+    crate::metrics::METRICS.record_strict_divergence();
+    if strict_mode().panic_on_divergence {
+        panic!(
+            "strict: {} gave different results for the same input, {}\n\nfirst: {:#}\n\nsecond: {:#}\n\ninput: {:#}",
+            divergence.operation, divergence.difference, divergence.first, divergence.second, divergence.context
+        );
+    }
+    crate::log_error!(
+        "Strict mode: {} gave different results for the same input, {} (input: {})",
+        divergence.operation,
+        divergence.difference,
+        divergence.context
+    );
+    let mut divergences = DIVERGENCES.lock().unwrap();
+    divergences.push_back(divergence);
+    while divergences.len() > MAX_DIVERGENCES {
+        divergences.pop_front();
+    }
+}
+
+/// Divergences found so far (the most recent ones), oldest first
+pub fn divergences() -> Vec<Divergence> {
+    DIVERGENCES.lock().unwrap().iter().cloned().collect()
+}
+
+pub fn clear_divergences() {
+    DIVERGENCES.lock().unwrap().clear();
+}
+
+/// First place where two outputs differ, as a JSON path (None if they're equal)
+fn first_difference(first: &Value, second: &Value, at: &str) -> Option<String> {
+    match (first, second) {
+        (Value::Array(a), Value::Array(b)) => a
+            .iter()
+            .zip(b)
+            .enumerate()
+            .find_map(|(i, (a, b))| first_difference(a, b, &format!("{}/{}", at, i)))
+            .or_else(|| (a.len() != b.len()).then(|| format!("at '{}': {} items, then {}", at, a.len(), b.len()))),
+        (Value::Object(a), Value::Object(b)) => {
+            let keys: BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+            keys.into_iter().find_map(|key| {
+                let (a, b) = (a.get(key).unwrap_or(&Value::Null), b.get(key).unwrap_or(&Value::Null));
+                first_difference(a, b, &format!("{}/{}", at, key))
+            })
+        }
+        _ if first != second => Some(format!("at '{}': {}, then {}", at, first, second)),
+        _ => None,
+    }
+}
+
+/// Set strict mode (StrictConfig JSON)
+///
+/// # Safety
+/// - config_json must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_set_strict_mode(config_json: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_set_strict_mode", &[config_json]);
+    let config = CStr::from_ptr(config_json)
+        .to_str()
+        .map_err(MinimactError::from)
+        .and_then(|json| Ok(serde_json::from_str::<StrictConfig>(json)?));
+    match config {
+        Ok(config) => {
+            set_strict_mode(config);
+            FfiResult::success()
+        }
+        Err(e) => FfiResult::error(&e),
+    }
+}
+
+/// Divergences found by strict mode, as a JSON array of Divergence
+/// With `clear`, they're forgotten once returned
+/// The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub extern "C" fn minimact_strict_divergences(clear: bool) -> *mut c_char {
+    let found = if clear {
+        DIVERGENCES.lock().unwrap().drain(..).collect()
+    } else {
+        divergences()
+    };
+    crate::tree_store::json_or_error(Ok(found))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::HexPath;
+    use crate::vdom::Patch;
+
+    #[test]
+    fn test_divergence_points_at_first_difference() {
+        let remove = |path: &str| Patch::Remove { path: HexPath::from(path) };
+        let first = vec![remove("10000000.1"), remove("10000000.2")];
+        assert!(diverge("reconcile", &first, &first.clone(), || Value::Null).is_none());
+
+        let swapped = vec![remove("10000000.2"), remove("10000000.1")];
+        let divergence = diverge("reconcile", &first, &swapped, || serde_json::json!({ "old": "tree" })).unwrap();
+        assert_eq!(divergence.difference, "at '/0/path': \"10000000.1\", then \"10000000.2\"");
+        assert_eq!(divergence.context["old"], "tree");
+
+        let shorter = &first[..1];
+        assert_eq!(diverge("reconcile", &first[..], shorter, || Value::Null).unwrap().difference, "at '': 2 items, then 1");
+    }
+
+    #[test]
+    fn test_second_runs_are_marked_while_they_run() {
+        assert!(!is_rerunning());
+        let nested = rerun(|| (is_rerunning(), rerun(is_rerunning)));
+        assert_eq!(nested, (true, true));
+        assert!(!is_rerunning());
+    }
+
+    #[test]
+    fn test_panicking_rerun_clears_the_flag() {
+        let result = std::panic::catch_unwind(|| rerun(|| panic!("second run failed")));
+        assert!(result.is_err());
+        assert!(!is_rerunning());
+    }
+}
@@ -106,6 +106,10 @@ fn is_structural_change(old_node: &VNode, new_node: &VNode) -> bool {
         // Both null = no structural change
         (VNode::Null(_), VNode::Null(_)) => false,
 
+        // A placeholder is expanded or collapsed = structural change
+        (VNode::Lazy(old_lazy), VNode::Lazy(new_lazy)) => old_lazy.summary != new_lazy.summary,
+        (VNode::Lazy(_), _) | (_, VNode::Lazy(_)) => true,
+
         // Text to Element or vice versa = structural change
         (VNode::Text(_), VNode::Element(_)) => true,
         (VNode::Element(_), VNode::Text(_)) => true,
@@ -212,28 +216,36 @@ mod tests {
         let old_node = VNode::Element(VElement {
             tag: "div".to_string(),
             props: HashMap::new(),
-            children: vec![VNode::Text(VText {
+            children: vec![Some(VNode::Text(VText {
                 content: "Please log in".to_string(),
-            })],
+                path: HexPath::root(),
+            }))],
             key: None,
+            source: None,
+            path: HexPath::root(),
         });
 
         // New: <div><h1>Welcome!</h1></div>
         let new_node = VNode::Element(VElement {
             tag: "div".to_string(),
             props: HashMap::new(),
-            children: vec![VNode::Element(VElement {
+            children: vec![Some(VNode::Element(VElement {
                 tag: "h1".to_string(),
                 props: HashMap::new(),
-                children: vec![VNode::Text(VText {
+                children: vec![Some(VNode::Text(VText {
                     content: "Welcome!".to_string(),
-                })],
+                    path: HexPath::root(),
+                }))],
                 key: None,
-            })],
+                source: None,
+                path: HexPath::root(),
+            }))],
             key: None,
+            source: None,
+            path: HexPath::root(),
         });
 
-        let result = extract_structural_template(&state_change, &vec![0], &old_node, &new_node);
+        let result = extract_structural_template(&state_change, &HexPath::root().child(0), &old_node, &new_node);
 
         assert!(result.is_some());
         if let Some(Patch::ReplaceConditional { structural_template, .. }) = result {
@@ -259,14 +271,16 @@ mod tests {
         // Old: <div>Loading...</div>
         let old_node = VNode::Text(VText {
             content: "Loading...".to_string(),
+            path: HexPath::root(),
         });
 
         // New: <div>Data loaded!</div>
         let new_node = VNode::Text(VText {
             content: "Data loaded!".to_string(),
+            path: HexPath::root(),
         });
 
-        let result = extract_structural_template(&state_change, &vec![0], &old_node, &new_node);
+        let result = extract_structural_template(&state_change, &HexPath::root().child(0), &old_node, &new_node);
 
         assert!(result.is_some());
         if let Some(Patch::ReplaceConditional { structural_template, .. }) = result {
@@ -284,12 +298,15 @@ mod tests {
         // Text to Element
         let text = VNode::Text(VText {
             content: "Hello".to_string(),
+            path: HexPath::root(),
         });
         let element = VNode::Element(VElement {
             tag: "div".to_string(),
             props: HashMap::new(),
             children: vec![],
             key: None,
+            source: None,
+            path: HexPath::root(),
         });
         assert!(is_structural_change(&text, &element));
 
@@ -299,12 +316,16 @@ mod tests {
             props: HashMap::new(),
             children: vec![],
             key: None,
+            source: None,
+            path: HexPath::root(),
         });
         let span = VNode::Element(VElement {
             tag: "span".to_string(),
             props: HashMap::new(),
             children: vec![],
             key: None,
+            source: None,
+            path: HexPath::root(),
         });
         assert!(is_structural_change(&div, &span));
 
@@ -314,12 +335,16 @@ mod tests {
             props: HashMap::new(),
             children: vec![],
             key: None,
+            source: None,
+            path: HexPath::root(),
         });
         let div_with_children = VNode::Element(VElement {
             tag: "div".to_string(),
             props: HashMap::new(),
-            children: vec![text.clone(), text.clone()],
+            children: vec![Some(text.clone()), Some(text.clone())],
             key: None,
+            source: None,
+            path: HexPath::root(),
         });
         assert!(is_structural_change(&empty_div, &div_with_children));
     }
@@ -0,0 +1,298 @@
+//! Compact encoding of template patches for client storage
+//!
+//! Template patches are cached by the client (IndexedDB, localStorage) and their
+//! JSON is mostly field names, variant tags and fixed-width hex paths. The compact
+//! form is still JSON, so it's a plain string for either store, with:
+//! - known field names and variant tags shortened (`templatePatch` -> `T`,
+//!   `UpdateTextTemplate` -> `UTT`)
+//! - paths with each segment's trailing zeros trimmed (`10000000.20000000` -> `1.2`)
+//! - a `mc1:` prefix carrying the format version
+//!
+//! The mapping is a bijection over all strings: a key, tag or path that already
+//! looks like an encoded one, or starts with `~`, is escaped with a `~`. Decoding
+//! therefore reproduces the input exactly, including free-form maps (props,
+//! branches) whose keys happen to collide with a short name.
+//!
+//! `encode_compact` / `decode_compact` take no FFI types, so a WASM build can export
+//! them as they are.
+
+use crate::error::{MinimactError, Result};
+use crate::last_error::FfiCall;
+use crate::vdom::Patch;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+
+/// Prefix of the current encoding version
+pub const COMPACT_PREFIX: &str = "mc1:";
+
+const ESCAPE: char = '~';
+
+/// (field name, short name)
+const FIELDS: &[(&str, &str)] = &[
+    ("type", "t"),
+    ("path", "p"),
+    ("node", "n"),
+    ("content", "c"),
+    ("props", "r"),
+    ("children", "h"),
+    ("tag", "g"),
+    ("key", "k"),
+    ("template", "m"),
+    ("bindings", "b"),
+    ("bindings_with_transforms", "bt"),
+    ("slots", "s"),
+    ("conditional_templates", "ct"),
+    ("conditional_binding_index", "ci"),
+    ("templatePatch", "T"),
+    ("propName", "pn"),
+    ("loopTemplate", "L"),
+    ("array_binding", "ab"),
+    ("item_template", "it"),
+    ("index_var", "iv"),
+    ("separator", "sp"),
+    ("props_templates", "pt"),
+    ("children_templates", "ht"),
+    ("key_binding", "kb"),
+    ("structuralTemplate", "S"),
+    ("condition_binding", "cb"),
+    ("branches", "br"),
+    ("default_branch", "db"),
+    ("attrName", "an"),
+    ("value", "v"),
+    ("state_key", "sk"),
+    ("transform", "tf"),
+    ("preserve", "pv"),
+    ("order", "o"),
+];
+
+/// (variant tag, short tag) of patches, item templates and nodes
+const TAGS: &[(&str, &str)] = &[
+    ("Create", "C"),
+    ("Remove", "R"),
+    ("Replace", "RP"),
+    ("UpdateText", "UT"),
+    ("UpdateProps", "UP"),
+    ("ReorderChildren", "RC"),
+    ("UpdateTextTemplate", "UTT"),
+    ("UpdatePropsTemplate", "UPT"),
+    ("UpdateListTemplate", "ULT"),
+    ("ReorderTemplate", "RT"),
+    ("ReplaceConditional", "RCT"),
+    ("UpdateAttributeStatic", "UAS"),
+    ("UpdateAttributeDynamic", "UAD"),
+    ("Element", "E"),
+    ("Text", "X"),
+    ("Null", "N"),
+];
+
+struct Dictionary {
+    long_to_short: HashMap<&'static str, &'static str>,
+    short_to_long: HashMap<&'static str, &'static str>,
+}
+
+impl Dictionary {
+    fn new(pairs: &[(&'static str, &'static str)]) -> Self {
+        Self {
+            long_to_short: pairs.iter().copied().collect(),
+            short_to_long: pairs.iter().map(|&(long, short)| (short, long)).collect(),
+        }
+    }
+
+    fn encode(&self, s: &str) -> String {
+        match self.long_to_short.get(s) {
+            Some(short) => short.to_string(),
+            None if s.starts_with(ESCAPE) || self.short_to_long.contains_key(s) => format!("{}{}", ESCAPE, s),
+            None => s.to_string(),
+        }
+    }
+
+    fn decode(&self, s: &str) -> String {
+        match s.strip_prefix(ESCAPE) {
+            Some(escaped) => escaped.to_string(),
+            None => self.short_to_long.get(s).map_or_else(|| s.to_string(), |long| long.to_string()),
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref FIELD_NAMES: Dictionary = Dictionary::new(FIELDS);
+    static ref TAG_NAMES: Dictionary = Dictionary::new(TAGS);
+}
+
+fn is_full_width_path(path: &str) -> bool {
+    path.split('.').all(|segment| segment.len() == 8 && segment.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+fn encode_path(path: &str) -> String {
+    if path.is_empty() {
+        return String::new();
+    }
+    if !is_full_width_path(path) {
+        return format!("{}{}", ESCAPE, path);
+    }
+    let segments: Vec<&str> = path
+        .split('.')
+        .map(|segment| match segment.trim_end_matches('0') {
+            "" => "0",
+            trimmed => trimmed,
+        })
+        .collect();
+    segments.join(".")
+}
+
+fn decode_path(path: &str) -> String {
+    if let Some(escaped) = path.strip_prefix(ESCAPE) {
+        return escaped.to_string();
+    }
+    if path.is_empty() {
+        return String::new();
+    }
+    let segments: Vec<String> = path.split('.').map(|segment| format!("{:0<8}", segment)).collect();
+    segments.join(".")
+}
+
+fn encode_value(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| {
+                    let value = match (key.as_str(), value) {
+                        ("type", Value::String(tag)) => Value::String(TAG_NAMES.encode(&tag)),
+                        ("path", Value::String(path)) => Value::String(encode_path(&path)),
+                        (_, value) => encode_value(value),
+                    };
+                    (FIELD_NAMES.encode(&key), value)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(encode_value).collect()),
+        value => value,
+    }
+}
+
+fn decode_value(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let decoded: Map<String, Value> = map
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = FIELD_NAMES.decode(&key);
+                    let value = match (key.as_str(), value) {
+                        ("type", Value::String(tag)) => Value::String(TAG_NAMES.decode(&tag)),
+                        ("path", Value::String(path)) => Value::String(decode_path(&path)),
+                        (_, value) => decode_value(value),
+                    };
+                    (key, value)
+                })
+                .collect();
+            Value::Object(decoded)
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(decode_value).collect()),
+        value => value,
+    }
+}
+
+/// Encode patches (typically template patches) in the compact form
+pub fn encode_compact(patches: &[Patch]) -> Result<String> {
+    let encoded = encode_value(serde_json::to_value(patches)?);
+    Ok(format!("{}{}", COMPACT_PREFIX, serde_json::to_string(&encoded)?))
+}
+
+/// Decode patches from `encode_compact`'s output
+pub fn decode_compact(encoded: &str) -> Result<Vec<Patch>> {
+    let json = encoded
+        .strip_prefix(COMPACT_PREFIX)
+        .ok_or_else(|| MinimactError::Serialization(format!("Not a compact patch encoding (expected '{}' prefix)", COMPACT_PREFIX)))?;
+    Ok(serde_json::from_value(decode_value(serde_json::from_str(json)?))?)
+}
+
+/// Encode a patch list (JSON) in the compact form
+/// Returns the encoded string (or {"error": ...})
+///
+/// # Safety
+/// - patches_json must be a valid null-terminated UTF-8 string
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_encode_compact(patches_json: *const c_char) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_encode_compact", &[patches_json]);
+    let result = (|| -> Result<String> {
+        let patches: Vec<Patch> = serde_json::from_str(CStr::from_ptr(patches_json).to_str()?)?;
+        encode_compact(&patches)
+    })();
+    string_or_error(result)
+}
+
+/// Decode a compact patch encoding back to patch list JSON
+/// Returns the patches JSON (or {"error": ...})
+///
+/// # Safety
+/// - encoded must be a valid null-terminated UTF-8 string
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_decode_compact(encoded: *const c_char) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_decode_compact", &[encoded]);
+    let result = (|| -> Result<String> {
+        let patches = decode_compact(CStr::from_ptr(encoded).to_str()?)?;
+        Ok(serde_json::to_string(&patches)?)
+    })();
+    string_or_error(result)
+}
+
+fn string_or_error(result: Result<String>) -> *mut c_char {
+    match result {
+        Ok(s) => CString::new(s).unwrap().into_raw(),
+        Err(e) => {
+            crate::last_error::record_error(&e);
+            CString::new(serde_json::json!({ "error": e.to_string() }).to_string()).unwrap().into_raw()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::HexPath;
+    use crate::vdom::{TemplatePatch, VNode};
+
+    fn template_patches() -> Vec<Patch> {
+        let template_patch = TemplatePatch {
+            template: "Count: {0}".to_string(),
+            bindings: vec!["count".to_string()],
+            bindings_with_transforms: None,
+            slots: vec![7],
+            conditional_templates: None,
+            conditional_binding_index: None,
+        };
+        vec![
+            Patch::UpdateTextTemplate { path: HexPath::from("10000000.20000000.00000000"), template_patch: template_patch.clone() },
+            Patch::UpdatePropsTemplate { path: HexPath::from("10000000"), prop_name: "className".to_string(), template_patch },
+        ]
+    }
+
+    #[test]
+    fn test_round_trip_is_smaller_and_exact() {
+        let patches = template_patches();
+        let encoded = encode_compact(&patches).unwrap();
+        assert!(encoded.contains(r#""p":"1.2.0""#), "{}", encoded);
+        assert!(encoded.len() < serde_json::to_string(&patches).unwrap().len() * 2 / 3);
+        assert_eq!(decode_compact(&encoded).unwrap(), patches);
+        assert!(decode_compact("[]").is_err());
+    }
+
+    #[test]
+    fn test_colliding_names_survive() {
+        // Prop names and values that look like encoded ones, odd paths
+        let props = [("t", "x"), ("type", "UT"), ("~k", "v"), ("path", "1.2")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let mut node = VNode::element("input", props, vec![Some(VNode::text("~"))]);
+        if let VNode::Element(el) = &mut node {
+            el.path = HexPath::from("~odd");
+        }
+        let patches = vec![Patch::Replace { path: HexPath::root(), node, preserve: None }];
+        assert_eq!(decode_compact(&encode_compact(&patches).unwrap()).unwrap(), patches);
+    }
+}
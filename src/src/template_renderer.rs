@@ -0,0 +1,342 @@
+//! Server-side template rendering
+//!
+//! Mirrors the client runtime's TemplateRenderer (client-runtime/src/template-renderer.ts)
+//! so template patches can be materialized into concrete patches on the server, e.g. for
+//! clients that don't understand template patch variants.
+//!
+//! Rendering must stay in sync with the client: {0}, {1}, ... placeholders, the same
+//! whitelisted transforms, and the same value formatting.
+
+use crate::path::HexPath;
+use crate::vdom::{ItemTemplate, LoopTemplate, Patch, TemplatePatch, VElement, VNode, VText};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// State values keyed by binding name (e.g. "count", "item.text")
+pub type StateValues = HashMap<String, Value>;
+
+/// Render a template string, replacing the first occurrence of each {i} with params[i]
+pub fn render_template(template: &str, params: &[Value]) -> String {
+    let mut result = template.to_string();
+    for (index, param) in params.iter().enumerate() {
+        let placeholder = format!("{{{}}}", index);
+        result = result.replacen(&placeholder, &format_value(param), 1);
+    }
+    result
+}
+
+/// Render a template patch with the given state values
+///
+/// Missing bindings render as empty strings (same as the client).
+pub fn render_template_patch(template_patch: &TemplatePatch, state: &StateValues) -> String {
+    let params = binding_params(template_patch, state);
+
+    // Conditional templates: pick the template matching the condition binding's value
+    if let (Some(templates), Some(index)) = (
+        &template_patch.conditional_templates,
+        template_patch.conditional_binding_index,
+    ) {
+        if let Some(key) = binding_keys(template_patch).get(index) {
+            let condition = state.get(key).map(condition_key).unwrap_or_else(|| "undefined".to_string());
+            if let Some(conditional) = templates.get(&condition) {
+                if !conditional.contains('{') {
+                    return conditional.clone();
+                }
+                return render_template(conditional, &params);
+            }
+        }
+    }
+
+    render_template(&template_patch.template, &params)
+}
+
+/// State keys referenced by a template patch (rich bindings take precedence)
+pub fn binding_keys(template_patch: &TemplatePatch) -> Vec<String> {
+    match &template_patch.bindings_with_transforms {
+        Some(bindings) => bindings.iter().map(|b| b.state_key.clone()).collect(),
+        None => template_patch.bindings.clone(),
+    }
+}
+
+fn binding_params(template_patch: &TemplatePatch, state: &StateValues) -> Vec<Value> {
+    match &template_patch.bindings_with_transforms {
+        Some(bindings) => bindings
+            .iter()
+            .map(|binding| {
+                let value = state.get(&binding.state_key).cloned().unwrap_or(Value::Null);
+                match &binding.transform {
+                    Some(transform) => apply_transform(&value, transform),
+                    None => value,
+                }
+            })
+            .collect(),
+        None => template_patch
+            .bindings
+            .iter()
+            .map(|key| state.get(key).cloned().unwrap_or(Value::Null))
+            .collect(),
+    }
+}
+
+/// Apply a whitelisted transform (toFixed(n), arithmetic, case, trim, negation)
+///
+/// Unknown transforms return the value unchanged.
+pub fn apply_transform(value: &Value, transform: &str) -> Value {
+    if transform.starts_with("toFixed(") {
+        let decimals: usize = transform
+            .chars()
+            .filter(|c| c.is_ascii_digit())
+            .collect::<String>()
+            .parse()
+            .unwrap_or(0);
+        return Value::String(format!("{:.*}", decimals, as_number(value)));
+    }
+
+    for (prefix, op) in [("* ", '*'), ("/ ", '/'), ("+ ", '+'), ("- ", '-')] {
+        if let Some(operand) = transform.strip_prefix(prefix) {
+            let operand: f64 = operand.trim().parse().unwrap_or(f64::NAN);
+            let n = as_number(value);
+            let result = match op {
+                '*' => n * operand,
+                '/' => n / operand,
+                '+' => n + operand,
+                _ => n - operand,
+            };
+            return number_value(result);
+        }
+    }
+
+    match transform {
+        "toUpperCase()" | "toUpperCase" => Value::String(format_value(value).to_uppercase()),
+        "toLowerCase()" | "toLowerCase" => Value::String(format_value(value).to_lowercase()),
+        "trim()" | "trim" => Value::String(format_value(value).trim().to_string()),
+        "!" => Value::Bool(!is_truthy(value)),
+        _ => {
+            crate::log_warn!("Unknown template transform: {}", transform);
+            value.clone()
+        }
+    }
+}
+
+/// Render a loop template against the array bound in state
+///
+/// Each item sees the full state plus `item`, `index`, the optional index variable and
+/// flattened `item.<prop>` keys.
+pub fn render_loop_template(loop_template: &LoopTemplate, state: &StateValues) -> Vec<VNode> {
+    let Some(Value::Array(items)) = state.get(&loop_template.array_binding) else {
+        crate::log_warn!("Expected array for '{}'", loop_template.array_binding);
+        return Vec::new();
+    };
+
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let mut item_state = state.clone();
+            item_state.insert("item".to_string(), item.clone());
+            item_state.insert("index".to_string(), Value::from(index));
+            if let Some(index_var) = &loop_template.index_var {
+                item_state.insert(index_var.clone(), Value::from(index));
+            }
+            if let Value::Object(fields) = item {
+                for (key, value) in fields {
+                    item_state.insert(format!("item.{}", key), value.clone());
+                }
+            }
+            render_item_template(&loop_template.item_template, &item_state)
+        })
+        .collect()
+}
+
+fn render_item_template(item_template: &ItemTemplate, state: &StateValues) -> VNode {
+    match item_template {
+        ItemTemplate::Text { template_patch } => VNode::Text(VText {
+            content: render_template_patch(template_patch, state),
+            path: HexPath::root(),
+        }),
+        ItemTemplate::Element { tag, props_templates, children_templates, key_binding } => {
+            let props = props_templates
+                .iter()
+                .flatten()
+                .map(|(name, template)| (name.clone(), render_template_patch(template, state)))
+                .collect();
+            let children = children_templates
+                .iter()
+                .flatten()
+                .map(|child| Some(render_item_template(child, state)))
+                .collect();
+            let key = key_binding
+                .as_ref()
+                .map(|binding| format_value(state.get(binding).unwrap_or(&Value::Null)));
+
+            VNode::Element(VElement {
+                tag: tag.clone(),
+                props,
+                children,
+                key,
+                path: HexPath::root(),
+                source: None,
+            })
+        }
+    }
+}
+
+/// Convert rendered loop items into Create patches under `parent` (same as the client)
+pub fn loop_items_to_patches(parent: &HexPath, items: Vec<VNode>) -> Vec<Patch> {
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(index, mut node)| {
+            let path = parent.child(index);
+            node.rebase_path(&path);
+            Patch::Create { path, node }
+        })
+        .collect()
+}
+
+/// Format a value for template substitution (JS String() semantics)
+pub fn format_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => match n.as_f64() {
+            Some(f) if n.is_f64() => format_number(f),
+            _ => n.to_string(),
+        },
+        Value::Array(items) => items.iter().map(format_value).collect::<Vec<_>>().join(", "),
+        Value::Object(_) => value.to_string(),
+    }
+}
+
+/// Key used to look up conditional templates / structural branches for a value
+pub(crate) fn condition_key(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        _ => format_value(value),
+    }
+}
+
+fn format_number(f: f64) -> String {
+    if f.is_nan() {
+        "NaN".to_string()
+    } else if f.is_infinite() {
+        if f > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+    } else if f.fract() == 0.0 && f.abs() < 1e15 {
+        format!("{}", f as i64)
+    } else {
+        f.to_string()
+    }
+}
+
+fn as_number(value: &Value) -> f64 {
+    match value {
+        Value::Number(n) => n.as_f64().unwrap_or(f64::NAN),
+        Value::String(s) => s.trim().parse().unwrap_or(f64::NAN),
+        Value::Bool(b) => if *b { 1.0 } else { 0.0 },
+        Value::Null => 0.0,
+        _ => f64::NAN,
+    }
+}
+
+fn number_value(f: f64) -> Value {
+    serde_json::Number::from_f64(f).map(Value::Number).unwrap_or(Value::Null)
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().map(|f| f != 0.0 && !f.is_nan()).unwrap_or(false),
+        Value::String(s) => !s.is_empty(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vdom::Binding;
+    use serde_json::json;
+
+    fn template(template: &str, bindings: &[&str]) -> TemplatePatch {
+        TemplatePatch {
+            template: template.to_string(),
+            bindings: bindings.iter().map(|b| b.to_string()).collect(),
+            bindings_with_transforms: None,
+            slots: vec![],
+            conditional_templates: None,
+            conditional_binding_index: None,
+        }
+    }
+
+    fn state(pairs: &[(&str, Value)]) -> StateValues {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.clone())).collect()
+    }
+
+    #[test]
+    fn test_render_simple_and_missing_bindings() {
+        let tp = template("Hello, {0} {1}!", &["first", "last"]);
+        assert_eq!(render_template_patch(&tp, &state(&[("first", json!("John")), ("last", json!("Doe"))])), "Hello, John Doe!");
+        assert_eq!(render_template_patch(&tp, &state(&[("first", json!("John"))])), "Hello, John !");
+    }
+
+    #[test]
+    fn test_render_conditional() {
+        let mut tp = template("{0}", &["isActive"]);
+        tp.conditional_templates = Some(HashMap::from([
+            ("true".to_string(), "Active".to_string()),
+            ("false".to_string(), "Inactive".to_string()),
+        ]));
+        tp.conditional_binding_index = Some(0);
+        assert_eq!(render_template_patch(&tp, &state(&[("isActive", json!(false))])), "Inactive");
+    }
+
+    #[test]
+    fn test_transforms() {
+        let mut tp = template("{0}% / ${1}", &[]);
+        tp.bindings_with_transforms = Some(vec![
+            Binding { state_key: "ratio".to_string(), transform: Some("* 100".to_string()) },
+            Binding { state_key: "price".to_string(), transform: Some("toFixed(2)".to_string()) },
+        ]);
+        let values = state(&[("ratio", json!(0.5)), ("price", json!(9.5))]);
+        assert_eq!(render_template_patch(&tp, &values), "50% / $9.50");
+        assert_eq!(apply_transform(&json!("hi"), "toUpperCase()"), json!("HI"));
+        assert_eq!(apply_transform(&json!(0), "!"), json!(true));
+    }
+
+    #[test]
+    fn test_render_loop_template() {
+        let loop_template = LoopTemplate {
+            array_binding: "todos".to_string(),
+            item_template: ItemTemplate::Element {
+                tag: "li".to_string(),
+                props_templates: None,
+                children_templates: Some(vec![ItemTemplate::Text { template_patch: template("{0}", &["item.text"]) }]),
+                key_binding: Some("item.id".to_string()),
+            },
+            index_var: None,
+            separator: None,
+        };
+        let values = state(&[("todos", json!([{ "id": 1, "text": "A" }, { "id": 2, "text": "B" }]))]);
+
+        let items = render_loop_template(&loop_template, &values);
+        assert_eq!(items.len(), 2);
+        match &items[1] {
+            VNode::Element(el) => {
+                assert_eq!(el.key.as_deref(), Some("2"));
+                assert!(matches!(&el.children[0], Some(VNode::Text(t)) if t.content == "B"));
+            }
+            _ => panic!("Expected element"),
+        }
+
+        let patches = loop_items_to_patches(&HexPath::from("10000000"), items);
+        assert_eq!(patches[1].path().as_str(), "10000000.20000000");
+        match &patches[1] {
+            Patch::Create { node: VNode::Element(el), .. } => {
+                assert_eq!(el.children[0].as_ref().unwrap().path().as_str(), "10000000.20000000.10000000");
+            }
+            _ => panic!("Expected Create patch"),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Per-tenant quotas for shared hosts
+//!
+//! One process may serve many customers. A session can belong to a tenant, and
+//! everything it holds (stored trees, its components' predictors) counts against
+//! that tenant's TenantQuota. The SessionRegistry enforces quotas centrally:
+//!
+//! - memory and pattern caps: a tenant over its cap loses the predictors of its own
+//!   longest idle sessions until it fits, so one tenant's growth never evicts another
+//!   tenant's learned patterns. If that isn't enough the same sessions lose their
+//!   stored trees too (their next reconciles send whole trees); each session's store
+//!   is also capped at the tenant's memory quota
+//! - reconcile rate: each tenant has its own breaker (see `rate_limit`); suppressed
+//!   updates send nothing and the update closing the breaker sends a Replace
+//!
+//! Sessions without a tenant are unrestricted.
+
+use crate::rate_limit::{RateDecision, RateLimitConfig, RateLimiter};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Resource limits of one tenant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TenantQuota {
+    /// Trees and predictors of all the tenant's sessions (approximate; default 64 MB)
+    pub max_memory_bytes: usize,
+    /// Learned and seeded patterns of all the tenant's predictors (default 50,000)
+    pub max_patterns: usize,
+    /// Reconciles allowed across the tenant's sessions (None = unlimited)
+    pub rate_limit: Option<RateLimitConfig>,
+}
+
+impl Default for TenantQuota {
+    fn default() -> Self {
+        Self {
+            max_memory_bytes: 64 * 1024 * 1024,
+            max_patterns: 50_000,
+            rate_limit: None,
+        }
+    }
+}
+
+/// Usage and counters of one tenant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TenantStats {
+    pub tenant_id: String,
+    pub sessions: usize,
+    pub memory_bytes: usize,
+    pub patterns: usize,
+    pub reconciles: u64,
+    pub updates_suppressed: u64,
+    /// Predictors and stored trees dropped to bring the tenant back under its quota
+    pub quota_evictions: u64,
+    pub quota: TenantQuota,
+}
+
+/// Resources of a tenant, as measured by the SessionRegistry
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TenantUsage {
+    pub sessions: usize,
+    pub memory_bytes: usize,
+    pub patterns: usize,
+}
+
+impl TenantUsage {
+    pub fn exceeds(&self, quota: &TenantQuota) -> bool {
+        self.memory_bytes > quota.max_memory_bytes || self.patterns > quota.max_patterns
+    }
+}
+
+struct Tenant {
+    quota: TenantQuota,
+    limiter: Option<RateLimiter>,
+    reconciles: AtomicU64,
+    updates_suppressed: AtomicU64,
+    quota_evictions: AtomicU64,
+}
+
+impl Tenant {
+    fn new(quota: TenantQuota) -> Self {
+        Self {
+            limiter: quota.rate_limit.clone().map(RateLimiter::new),
+            quota,
+            reconciles: AtomicU64::new(0),
+            updates_suppressed: AtomicU64::new(0),
+            quota_evictions: AtomicU64::new(0),
+        }
+    }
+}
+
+/// Quotas and counters by tenant id
+#[derive(Default)]
+pub struct TenantRegistry {
+    tenants: DashMap<String, Tenant>,
+}
+
+impl TenantRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set a tenant's quota (counters are kept; the rate limit window restarts)
+    pub fn configure(&self, tenant_id: &str, quota: TenantQuota) {
+        match self.tenants.get_mut(tenant_id) {
+            Some(mut tenant) => {
+                tenant.limiter = quota.rate_limit.clone().map(RateLimiter::new);
+                tenant.quota = quota;
+            }
+            None => {
+                self.tenants.insert(tenant_id.to_string(), Tenant::new(quota));
+            }
+        }
+    }
+
+    /// The tenant's quota (the default quota for tenants never configured)
+    pub fn quota(&self, tenant_id: &str) -> TenantQuota {
+        self.tenants.get(tenant_id).map(|tenant| tenant.quota.clone()).unwrap_or_default()
+    }
+
+    /// Count a reconcile for the tenant and decide whether it may go ahead
+    pub fn check_rate(&self, tenant_id: &str) -> RateDecision {
+        let tenant = self.tenants.entry(tenant_id.to_string()).or_insert_with(|| Tenant::new(TenantQuota::default()));
+        tenant.reconciles.fetch_add(1, Ordering::Relaxed);
+        let decision = tenant.limiter.as_ref().map_or(RateDecision::Allow, |limiter| limiter.check(tenant_id));
+        if decision == RateDecision::Suppress {
+            tenant.updates_suppressed.fetch_add(1, Ordering::Relaxed);
+        }
+        decision
+    }
+
+    pub(crate) fn record_quota_evictions(&self, tenant_id: &str, evicted: usize) {
+        if let Some(tenant) = self.tenants.get(tenant_id) {
+            tenant.quota_evictions.fetch_add(evicted as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Ids of every tenant with a quota or counters
+    pub fn tenant_ids(&self) -> Vec<String> {
+        self.tenants.iter().map(|tenant| tenant.key().clone()).collect()
+    }
+
+    pub(crate) fn stats(&self, tenant_id: &str, usage: TenantUsage) -> TenantStats {
+        let tenant = self.tenants.get(tenant_id);
+        let counter = |f: fn(&Tenant) -> &AtomicU64| tenant.as_ref().map_or(0, |t| f(t).load(Ordering::Relaxed));
+        TenantStats {
+            tenant_id: tenant_id.to_string(),
+            sessions: usage.sessions,
+            memory_bytes: usage.memory_bytes,
+            patterns: usage.patterns,
+            reconciles: counter(|t| &t.reconciles),
+            updates_suppressed: counter(|t| &t.updates_suppressed),
+            quota_evictions: counter(|t| &t.quota_evictions),
+            quota: tenant.as_ref().map(|t| t.quota.clone()).unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_is_per_tenant() {
+        let tenants = TenantRegistry::new();
+        let strict = RateLimitConfig { max_reconciles: 2, window_ms: 60_000, cooldown_ms: 60_000 };
+        tenants.configure("noisy", TenantQuota { rate_limit: Some(strict), ..Default::default() });
+
+        let decisions: Vec<RateDecision> = (0..3).map(|_| tenants.check_rate("noisy")).collect();
+        assert_eq!(decisions, vec![RateDecision::Allow, RateDecision::Allow, RateDecision::Suppress]);
+        assert_eq!(tenants.check_rate("quiet"), RateDecision::Allow);
+
+        let stats = tenants.stats("noisy", TenantUsage::default());
+        assert_eq!((stats.reconciles, stats.updates_suppressed), (3, 1));
+    }
+}
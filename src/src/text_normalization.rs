@@ -0,0 +1,238 @@
+//! Text node normalization
+//!
+//! JSX transpilation leaves adjacent text nodes (`Count: {count}` becomes two) and
+//! whitespace-only nodes from indentation, which inflate trees and patch counts.
+//! When enabled (process-wide, off by default), `deserialize_vnode_safe` runs
+//! `normalize_text_nodes` on every tree it parses.
+//!
+//! Paths are stable ids rather than positions, so nothing needs renumbering: a
+//! merged text keeps the path of its first part, and the paths of dropped nodes are
+//! simply gone. Texts are never merged across a Null placeholder, since the
+//! placeholder may render an element between them later.
+//!
+//! Whitespace inside `<pre>` and `<textarea>` renders as written, so below those
+//! texts are only merged, never dropped, collapsed or trimmed.
+
+use crate::error::{FfiResult, MinimactError};
+use crate::last_error::FfiCall;
+use crate::vdom::VNode;
+use arc_swap::ArcSwap;
+use serde::{Deserialize, Serialize};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::Arc;
+
+/// Which normalizations to apply (all off by default)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextNormalization {
+    /// Drop whitespace-only texts that contain a line break (indentation between
+    /// tags); a lone space between inline elements is kept, as in JSX
+    pub drop_blank_lines: bool,
+    /// Merge adjacent sibling texts into the first one
+    pub merge_adjacent: bool,
+    /// Replace runs of whitespace with a single space
+    pub collapse_whitespace: bool,
+    /// Strip leading and trailing whitespace (changes rendering between inline
+    /// elements; only for trees where that doesn't matter)
+    pub trim: bool,
+}
+
+impl TextNormalization {
+    /// Everything but trimming
+    pub fn jsx() -> Self {
+        Self { drop_blank_lines: true, merge_adjacent: true, collapse_whitespace: true, trim: false }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        *self != Self::default()
+    }
+}
+
+/// Elements whose text keeps its whitespace when rendered
+const WHITESPACE_SENSITIVE: [&str; 2] = ["pre", "textarea"];
+
+lazy_static::lazy_static! {
+    static ref TEXT_NORMALIZATION: ArcSwap<TextNormalization> = ArcSwap::from_pointee(TextNormalization::default());
+}
+
+/// Set the normalization applied on deserialization (process-wide)
+pub fn set_text_normalization(options: TextNormalization) {
+    TEXT_NORMALIZATION.store(Arc::new(options));
+}
+
+pub fn text_normalization() -> TextNormalization {
+    **TEXT_NORMALIZATION.load()
+}
+
+/// Normalize the texts of `tree` in place; returns how many text nodes were removed
+pub fn normalize_text_nodes(tree: &mut VNode, options: &TextNormalization) -> usize {
+    normalize_node(tree, options, false)
+}
+
+/// `keep_whitespace`: the node is inside a whitespace-sensitive element
+fn normalize_node(tree: &mut VNode, options: &TextNormalization, keep_whitespace: bool) -> usize {
+    match tree {
+        VNode::Text(text) => {
+            if !keep_whitespace {
+                normalize_content(&mut text.content, options);
+            }
+            0
+        }
+        VNode::Null(_) | VNode::Lazy(_) => 0,
+        VNode::Element(el) => {
+            let keep_whitespace = keep_whitespace || WHITESPACE_SENSITIVE.contains(&el.tag.as_str());
+            let before = el.children.len();
+            if options.drop_blank_lines && !keep_whitespace {
+                el.children.retain(|child| !matches!(child, Some(VNode::Text(t)) if is_blank_line(&t.content)));
+            }
+            if options.merge_adjacent {
+                merge_adjacent_texts(&mut el.children);
+            }
+            let removed = before - el.children.len();
+
+            removed
+                + el.children
+                    .iter_mut()
+                    .flatten()
+                    .map(|child| normalize_node(child, options, keep_whitespace))
+                    .sum::<usize>()
+        }
+    }
+}
+
+fn is_blank_line(content: &str) -> bool {
+    content.contains('\n') && content.trim().is_empty()
+}
+
+fn merge_adjacent_texts(children: &mut Vec<Option<VNode>>) {
+    let mut merged: Vec<Option<VNode>> = Vec::with_capacity(children.len());
+    for child in children.drain(..) {
+        if let (Some(Some(VNode::Text(previous))), Some(VNode::Text(text))) = (merged.last_mut(), &child) {
+            previous.content.push_str(&text.content);
+            continue;
+        }
+        merged.push(child);
+    }
+    *children = merged;
+}
+
+fn normalize_content(content: &mut String, options: &TextNormalization) {
+    if options.collapse_whitespace && content.chars().any(char::is_whitespace) {
+        let mut collapsed = String::with_capacity(content.len());
+        let mut in_whitespace = false;
+        for c in content.chars() {
+            if c.is_whitespace() {
+                if !in_whitespace {
+                    collapsed.push(' ');
+                }
+                in_whitespace = true;
+            } else {
+                collapsed.push(c);
+                in_whitespace = false;
+            }
+        }
+        *content = collapsed;
+    }
+    if options.trim && content.trim() != content.as_str() {
+        *content = content.trim().to_string();
+    }
+}
+
+/// Set the normalization applied to trees parsed by the FFI (TextNormalization JSON)
+///
+/// # Safety
+/// - options_json must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_set_text_normalization(options_json: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_set_text_normalization", &[options_json]);
+    let options = CStr::from_ptr(options_json)
+        .to_str()
+        .map_err(MinimactError::from)
+        .and_then(|json| Ok(serde_json::from_str::<TextNormalization>(json)?));
+    match options {
+        Ok(options) => {
+            set_text_normalization(options);
+            FfiResult::success()
+        }
+        Err(e) => FfiResult::error(&e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::HexPath;
+    use crate::vdom::{VElement, VNull, VText};
+
+    fn text(content: &str, path: &str) -> Option<VNode> {
+        Some(VNode::Text(VText { content: content.to_string(), path: HexPath::from(path) }))
+    }
+
+    fn contents(tree: &VNode) -> Vec<(String, String)> {
+        tree.children()
+            .iter()
+            .flatten()
+            .map(|child| match child {
+                VNode::Text(t) => (t.path.to_string(), t.content.clone()),
+                other => (other.path().to_string(), other.node_type().to_string()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_jsx_normalization() {
+        let mut tree = VNode::Element(VElement {
+            tag: "p".to_string(),
+            props: Default::default(),
+            children: vec![
+                text("\n    ", "10000000.10000000"),
+                text("Count:  ", "10000000.20000000"),
+                text("3", "10000000.30000000"),
+                Some(VNode::Null(VNull { path: HexPath::from("10000000.40000000") })),
+                text(" items", "10000000.50000000"),
+                text("\n", "10000000.60000000"),
+            ],
+            key: None,
+            path: HexPath::from("10000000"),
+            source: None,
+        });
+
+        assert_eq!(normalize_text_nodes(&mut tree, &TextNormalization::jsx()), 3);
+        assert_eq!(
+            contents(&tree),
+            vec![
+                ("10000000.20000000".to_string(), "Count: 3".to_string()),
+                ("10000000.40000000".to_string(), "Null".to_string()),
+                ("10000000.50000000".to_string(), " items".to_string()),
+            ]
+        );
+
+        // Disabled by default: trees pass through untouched
+        let before = tree.clone();
+        assert_eq!(normalize_text_nodes(&mut tree, &TextNormalization::default()), 0);
+        assert_eq!(tree, before);
+    }
+
+    #[test]
+    fn test_whitespace_sensitive_elements_keep_their_whitespace() {
+        let mut tree = VNode::Element(VElement {
+            tag: "pre".to_string(),
+            props: Default::default(),
+            children: vec![text("fn main() {\n", "10000000.10000000"), text("    run();\n}", "10000000.20000000"), text("\n", "10000000.30000000")],
+            key: None,
+            path: HexPath::from("10000000"),
+            source: None,
+        });
+
+        assert_eq!(normalize_text_nodes(&mut tree, &TextNormalization::jsx()), 2);
+        assert_eq!(contents(&tree), vec![("10000000.10000000".to_string(), "fn main() {\n    run();\n}\n".to_string())]);
+    }
+
+    #[test]
+    fn test_trim() {
+        let mut tree = text("  a \t b  ", "10000000").unwrap();
+        normalize_text_nodes(&mut tree, &TextNormalization { trim: true, collapse_whitespace: true, ..Default::default() });
+        assert!(matches!(&tree, VNode::Text(t) if t.content == "a b"));
+    }
+}
@@ -0,0 +1,291 @@
+//! Two-phase patch protocol for updates spanning several components
+//!
+//! When one state change re-renders several components, applying their patches one
+//! component at a time shows the page half updated (tearing). Instead:
+//!
+//! 1. `prepare` diffs every component against the tree store and returns one
+//!    TransactionPrepare with all the patches under one transaction id. Nothing is
+//!    stored yet. The client applies the patches off screen (or buffers them)
+//! 2. once every component applied, `commit` stores the new trees and returns the
+//!    commit marker that tells the client to make the update visible
+//! 3. if a component fails on the client, `rollback` returns the patches that take
+//!    every component back to its old tree, and the store keeps the old trees
+//!
+//! Commit fails with VersionConflict (storing nothing) if another update reached one
+//! of the components since prepare; the host then rolls back and prepares again.
+
+use crate::error::{MinimactError, Result};
+use crate::last_error::FfiCall;
+use crate::reconciler::reconcile;
+use crate::tree_store::{json_or_error, TreeStore, TREE_STORE};
+use crate::vdom::{Patch, VNode};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// One component's new tree in a transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentUpdate {
+    pub component_id: String,
+    pub tree: VNode,
+}
+
+/// One component's patches in a prepare or rollback
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComponentPatches {
+    pub component_id: String,
+    pub patches: Vec<Patch>,
+}
+
+/// Phase one: every component's patches, to apply without showing them yet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionPrepare {
+    pub transaction_id: String,
+    pub components: Vec<ComponentPatches>,
+}
+
+/// Phase two: show the prepared update
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionCommit {
+    pub transaction_id: String,
+    /// New stored version of each component, in prepare order
+    pub versions: Vec<u64>,
+}
+
+/// Patches taking every component of a transaction back to its old tree
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionRollback {
+    pub transaction_id: String,
+    pub components: Vec<ComponentPatches>,
+}
+
+struct PendingComponent {
+    component_id: String,
+    previous_version: u64,
+    old_tree: Option<Arc<VNode>>,
+    new_tree: VNode,
+}
+
+struct PendingTransaction {
+    components: Vec<PendingComponent>,
+    prepared_at: Instant,
+}
+
+/// Transactions prepared but not yet committed or rolled back
+#[derive(Default)]
+pub struct TransactionManager {
+    pending: DashMap<String, PendingTransaction>,
+    next_id: AtomicU64,
+}
+
+impl TransactionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Diff every update against `store` and hold the result as a pending transaction
+    /// Fails without creating a transaction if any component fails to reconcile
+    pub fn prepare(&self, store: &TreeStore, updates: Vec<ComponentUpdate>) -> Result<TransactionPrepare> {
+        let mut components = Vec::with_capacity(updates.len());
+        let mut pending = Vec::with_capacity(updates.len());
+        for update in updates {
+            let stored = store.get(&update.component_id);
+            let patches = match &stored {
+                Some((_, old_tree)) => reconcile(old_tree, &update.tree)?,
+                None => vec![Patch::Replace { path: update.tree.path().clone(), node: update.tree.clone(), preserve: None }],
+            };
+            components.push(ComponentPatches { component_id: update.component_id.clone(), patches });
+            pending.push(PendingComponent {
+                component_id: update.component_id,
+                previous_version: stored.as_ref().map_or(0, |(version, _)| *version),
+                old_tree: stored.map(|(_, tree)| tree),
+                new_tree: update.tree,
+            });
+        }
+
+        let transaction_id = format!("{:016x}", self.next_id.fetch_add(1, Ordering::Relaxed) + 1);
+        self.pending.insert(
+            transaction_id.clone(),
+            PendingTransaction { components: pending, prepared_at: crate::clock::now() },
+        );
+        crate::log_debug!("Prepared transaction {} ({} components)", transaction_id, components.len());
+        Ok(TransactionPrepare { transaction_id, components })
+    }
+
+    /// Store the transaction's new trees and end it
+    /// On VersionConflict nothing is stored and the transaction stays pending, so it
+    /// can still be rolled back
+    pub fn commit(&self, store: &TreeStore, transaction_id: &str) -> Result<TransactionCommit> {
+        let (_, transaction) = self
+            .pending
+            .remove(transaction_id)
+            .ok_or_else(|| MinimactError::KeyNotFound(transaction_id.to_string()))?;
+
+        let trees = transaction
+            .components
+            .iter()
+            .map(|c| (c.component_id.clone(), c.previous_version, c.new_tree.clone()))
+            .collect();
+        match store.commit_trees(trees) {
+            Ok(versions) => Ok(TransactionCommit { transaction_id: transaction_id.to_string(), versions }),
+            Err(e) => {
+                self.pending.insert(transaction_id.to_string(), transaction);
+                Err(e)
+            }
+        }
+    }
+
+    /// End the transaction without storing anything; returns the patches that undo
+    /// the prepare on the client
+    /// Components in `failed` may be half patched, so they get their old tree whole
+    pub fn rollback(&self, transaction_id: &str, failed: &[String]) -> Result<TransactionRollback> {
+        let (_, transaction) = self
+            .pending
+            .remove(transaction_id)
+            .ok_or_else(|| MinimactError::KeyNotFound(transaction_id.to_string()))?;
+
+        let components = transaction
+            .components
+            .into_iter()
+            .map(|c| {
+                let root = c.new_tree.path().clone();
+                let patches = match c.old_tree {
+                    None => vec![Patch::Remove { path: root }],
+                    Some(old) if failed.contains(&c.component_id) => {
+                        vec![Patch::Replace { path: old.path().clone(), node: VNode::clone(&old), preserve: None }]
+                    }
+                    Some(old) => reconcile(&c.new_tree, &old)?,
+                };
+                Ok(ComponentPatches { component_id: c.component_id, patches })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        crate::log_debug!("Rolled back transaction {}", transaction_id);
+        Ok(TransactionRollback { transaction_id: transaction_id.to_string(), components })
+    }
+
+    /// Drop transactions prepared more than `max_age` ago; returns how many
+    pub fn expire(&self, max_age: Duration) -> usize {
+        let now = crate::clock::now();
+        let before = self.pending.len();
+        self.pending.retain(|_, transaction| now.duration_since(transaction.prepared_at) <= max_age);
+        before - self.pending.len()
+    }
+
+    /// Number of pending transactions
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+lazy_static::lazy_static! {
+    /// Transactions over TREE_STORE, used by the FFI
+    pub static ref TRANSACTIONS: TransactionManager = TransactionManager::new();
+}
+
+/// Prepare a transaction over the tree store (JSON array of ComponentUpdate)
+/// Returns TransactionPrepare JSON (or {"error": ...})
+///
+/// # Safety
+/// - updates_json must be a valid null-terminated UTF-8 string
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_txn_prepare(updates_json: *const c_char) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_txn_prepare", &[updates_json]);
+    json_or_error((|| -> Result<TransactionPrepare> {
+        let updates: Vec<ComponentUpdate> = serde_json::from_str(CStr::from_ptr(updates_json).to_str()?)?;
+        let config = crate::validation::ValidationConfig::default();
+        for update in &updates {
+            update.tree.validate(&config)?;
+        }
+        TRANSACTIONS.prepare(&TREE_STORE, updates)
+    })())
+}
+
+/// Commit a prepared transaction
+/// Returns TransactionCommit JSON (or {"error": ...}; a conflict is error code 20)
+///
+/// # Safety
+/// - transaction_id must be a valid null-terminated UTF-8 string
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_txn_commit(transaction_id: *const c_char) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_txn_commit", &[transaction_id]);
+    json_or_error((|| -> Result<TransactionCommit> {
+        TRANSACTIONS.commit(&TREE_STORE, CStr::from_ptr(transaction_id).to_str()?)
+    })())
+}
+
+/// Roll back a prepared transaction
+/// failed_json is a JSON array of the component ids that failed on the client (may be null)
+/// Returns TransactionRollback JSON (or {"error": ...})
+///
+/// # Safety
+/// - transaction_id must be a valid null-terminated UTF-8 string
+/// - failed_json must be null or a valid null-terminated UTF-8 string
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_txn_rollback(transaction_id: *const c_char, failed_json: *const c_char) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_txn_rollback", &[transaction_id, failed_json]);
+    json_or_error((|| -> Result<TransactionRollback> {
+        let failed: Vec<String> = if failed_json.is_null() {
+            Vec::new()
+        } else {
+            serde_json::from_str(CStr::from_ptr(failed_json).to_str()?)?
+        };
+        TRANSACTIONS.rollback(CStr::from_ptr(transaction_id).to_str()?, &failed)
+    })())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(component_id: &str, text: &str) -> ComponentUpdate {
+        ComponentUpdate { component_id: component_id.to_string(), tree: VNode::text(text) }
+    }
+
+    #[test]
+    fn test_commit_stores_all_components_at_once() {
+        let store = TreeStore::default();
+        store.set_tree("cart", VNode::text("0 items"));
+        let transactions = TransactionManager::new();
+
+        let prepare = transactions.prepare(&store, vec![update("cart", "1 item"), update("badge", "1")]).unwrap();
+        assert_eq!(prepare.components.len(), 2);
+        // Nothing is stored before commit
+        assert_eq!(store.version("badge"), 0);
+
+        let commit = transactions.commit(&store, &prepare.transaction_id).unwrap();
+        assert_eq!(commit.versions, vec![store.version("cart"), store.version("badge")]);
+        assert_eq!(*store.get("cart").unwrap().1, VNode::text("1 item"));
+        assert!(transactions.commit(&store, &prepare.transaction_id).is_err());
+    }
+
+    #[test]
+    fn test_rollback_undoes_the_prepare() {
+        let store = TreeStore::default();
+        store.set_tree("cart", VNode::text("0 items"));
+        let transactions = TransactionManager::new();
+        let prepare = transactions.prepare(&store, vec![update("cart", "1 item"), update("badge", "1")]).unwrap();
+
+        // Another update reaches the cart first: commit conflicts and stores nothing
+        store.set_tree("cart", VNode::text("2 items"));
+        assert!(matches!(
+            transactions.commit(&store, &prepare.transaction_id),
+            Err(MinimactError::VersionConflict { .. })
+        ));
+        assert_eq!(store.version("badge"), 0);
+
+        let rollback = transactions.rollback(&prepare.transaction_id, &[]).unwrap();
+        let mut cart = VNode::text("1 item");
+        crate::apply::apply_patches(&mut cart, &rollback.components[0].patches).unwrap();
+        assert_eq!(cart, VNode::text("0 items"));
+        assert!(matches!(rollback.components[1].patches.as_slice(), [Patch::Remove { .. }]));
+        assert_eq!(transactions.pending(), 0);
+    }
+}
@@ -0,0 +1,231 @@
+//! Path index for O(1) node lookup by HexPath
+//!
+//! Patch validation and application used to find nodes by converting the hex path
+//! to child positions and walking the tree, which breaks as soon as paths aren't
+//! positional (null children, nodes inserted into hex gaps). A TreeIndex is built
+//! once per tree and maps every node's HexPath to its location (the route of child
+//! positions from the root), so lookups are a hash probe plus a short walk.
+//!
+//! After a patch is applied, `invalidate` updates only the affected part of the index.
+
+use crate::path::HexPath;
+use crate::vdom::{Patch, VNode};
+use smallvec::SmallVec;
+use std::collections::HashMap;
+
+/// Child positions from the root to a node
+pub type NodeRoute = SmallVec<[usize; 8]>;
+
+/// Where a node lives in the tree, plus what a validator needs without touching it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeLocation {
+    /// Child positions from the root
+    pub route: NodeRoute,
+    /// "Element", "Text" or "Null"
+    pub node_type: &'static str,
+    /// Element key, if any
+    pub key: Option<String>,
+}
+
+/// HexPath → node location map for one tree
+#[derive(Debug, Clone, Default)]
+pub struct TreeIndex {
+    nodes: HashMap<HexPath, NodeLocation>,
+}
+
+impl TreeIndex {
+    /// Index every node in `tree`
+    ///
+    /// If several nodes share a path (e.g. trees built without transpiled paths),
+    /// an ancestor wins over its descendants.
+    pub fn build(tree: &VNode) -> Self {
+        let mut index = TreeIndex::default();
+        index.index_subtree(tree, NodeRoute::new());
+        index
+    }
+
+    /// Number of indexed paths
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Check if nothing is indexed
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Check if a node exists at `path`
+    pub fn contains(&self, path: &HexPath) -> bool {
+        self.nodes.contains_key(path)
+    }
+
+    /// Location of the node at `path`
+    pub fn location(&self, path: &HexPath) -> Option<&NodeLocation> {
+        self.nodes.get(path)
+    }
+
+    /// Node type ("Element", "Text", "Null") at `path`
+    pub fn node_type(&self, path: &HexPath) -> Option<&'static str> {
+        self.nodes.get(path).map(|loc| loc.node_type)
+    }
+
+    /// Resolve `path` to the node in `tree` (the tree this index was built for)
+    pub fn get<'t>(&self, tree: &'t VNode, path: &HexPath) -> Option<&'t VNode> {
+        let route = &self.nodes.get(path)?.route;
+        route.iter().try_fold(tree, |node, &i| node.children().get(i)?.as_ref())
+    }
+
+    /// Resolve `path` to a mutable node in `tree` (the tree this index was built for)
+    pub fn get_mut<'t>(&self, tree: &'t mut VNode, path: &HexPath) -> Option<&'t mut VNode> {
+        let route = &self.nodes.get(path)?.route;
+        route.iter().try_fold(tree, |node, &i| match node {
+            VNode::Element(el) => el.children.get_mut(i)?.as_mut(),
+            _ => None,
+        })
+    }
+
+    /// Update the index after `patch` was applied, producing `tree_after`
+    ///
+    /// Content updates leave the index untouched. Replacing a node re-indexes its
+    /// subtree; inserting, removing or reordering children re-indexes the parent's
+    /// subtree (sibling positions may have shifted). Falls back to a full rebuild
+    /// when the affected node can't be located.
+    pub fn invalidate(&mut self, patch: &Patch, tree_after: &VNode) {
+        let root = match patch {
+            Patch::Replace { path, .. }
+            | Patch::ReplaceConditional { path, .. }
+            | Patch::UpdateListTemplate { path, .. }
+            | Patch::ReorderChildren { path, .. }
+            | Patch::ReorderTemplate { path, .. } => path.clone(),
+            Patch::Create { path, .. } | Patch::Remove { path } | Patch::UpdateDocument { path, .. } => {
+                path.parent().unwrap_or_else(HexPath::root)
+            }
+            Patch::UpdateText { .. }
+            | Patch::UpdateProps { .. }
+            | Patch::UpdateTextTemplate { .. }
+            | Patch::UpdatePropsTemplate { .. }
+            | Patch::UpdateAttributeStatic { .. }
+            | Patch::UpdateAttributeDynamic { .. }
+            | Patch::UpdateListWindow { .. }
+            | Patch::SetIgnored { .. }
+            | Patch::InvalidateLayout { .. }
+            | Patch::Lifecycle { .. }
+            | Patch::Navigate { .. }
+            | Patch::Custom { .. } => return,
+        };
+        self.reindex_subtree(&root, tree_after);
+    }
+
+    /// Re-index the subtree at `path` in `tree_after`
+    pub fn reindex_subtree(&mut self, path: &HexPath, tree_after: &VNode) {
+        let Some(route) = self.nodes.get(path).map(|loc| loc.route.clone()) else {
+            *self = TreeIndex::build(tree_after);
+            return;
+        };
+
+        let node = route.iter().try_fold(tree_after, |node, &i| node.children().get(i)?.as_ref());
+        let Some(node) = node else {
+            *self = TreeIndex::build(tree_after);
+            return;
+        };
+
+        // Drop stale entries at and below `path` (the subtree's own paths may have changed too)
+        let old_root = path.clone();
+        self.nodes.retain(|p, loc| !(p.is_within(&old_root) || loc.route.starts_with(&route)));
+        self.index_subtree(node, route);
+    }
+
+    fn index_subtree(&mut self, node: &VNode, route: NodeRoute) {
+        if let VNode::Element(el) = node {
+            for (i, child) in el.children.iter().enumerate() {
+                if let Some(child) = child {
+                    let mut child_route = route.clone();
+                    child_route.push(i);
+                    self.index_subtree(child, child_route);
+                }
+            }
+        }
+
+        // Insert after children so a parent sharing a path with its children still wins
+        self.nodes.insert(
+            node.path().clone(),
+            NodeLocation {
+                route,
+                node_type: node.node_type(),
+                key: node.key().map(String::from),
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vdom::{VElement, VNull, VText};
+    use std::collections::HashMap;
+
+    fn text(path: &str, content: &str) -> Option<VNode> {
+        Some(VNode::Text(VText { content: content.to_string(), path: HexPath::from(path) }))
+    }
+
+    fn div(path: &str, children: Vec<Option<VNode>>) -> VNode {
+        VNode::Element(VElement {
+            tag: "div".to_string(),
+            props: HashMap::new(),
+            children,
+            key: None,
+            path: HexPath::from(path),
+            source: None,
+        })
+    }
+
+    fn tree() -> VNode {
+        div("10000000", vec![
+            text("10000000.10000000", "A"),
+            None,
+            Some(VNode::Null(VNull { path: HexPath::from("10000000.30000000") })),
+            Some(div("10000000.40000000", vec![text("10000000.40000000.10000000", "B")])),
+        ])
+    }
+
+    #[test]
+    fn test_lookup_by_hex_path() {
+        let tree = tree();
+        let index = TreeIndex::build(&tree);
+
+        assert_eq!(index.len(), 5);
+        assert_eq!(index.node_type(&HexPath::from("10000000.30000000")), Some("Null"));
+        let node = index.get(&tree, &HexPath::from("10000000.40000000.10000000")).unwrap();
+        assert!(matches!(node, VNode::Text(t) if t.content == "B"));
+        assert!(index.get(&tree, &HexPath::from("10000000.20000000")).is_none());
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let mut tree = tree();
+        let index = TreeIndex::build(&tree);
+
+        if let Some(VNode::Text(t)) = index.get_mut(&mut tree, &HexPath::from("10000000.10000000")) {
+            t.content = "A2".to_string();
+        }
+        assert!(matches!(tree.find_by_path(&HexPath::from("10000000.10000000")), Some(VNode::Text(t)) if t.content == "A2"));
+    }
+
+    #[test]
+    fn test_invalidate_after_create() {
+        let mut tree = tree();
+        let mut index = TreeIndex::build(&tree);
+
+        // Insert a node into the hex gap before the nested div, shifting its position
+        let created = div("10000000.38000000", vec![]);
+        if let VNode::Element(el) = &mut tree {
+            el.children.insert(3, Some(created.clone()));
+        }
+        index.invalidate(&Patch::Create { path: HexPath::from("10000000.38000000"), node: created }, &tree);
+
+        assert_eq!(index.len(), 6);
+        assert_eq!(index.location(&HexPath::from("10000000.40000000")).unwrap().route.as_slice(), &[4]);
+        assert!(index.get(&tree, &HexPath::from("10000000.40000000.10000000")).is_some());
+        assert!(index.get(&tree, &HexPath::from("10000000.38000000")).is_some());
+    }
+}
@@ -0,0 +1,176 @@
+//! Tree serialization cache
+//!
+//! Patterns keep full trees, and the same predicted tree is often serialized again
+//! and again - once per client, per prediction. This process-wide LRU maps a tree's
+//! content hash to its JSON so repeats cost one hashing pass instead of a full
+//! serialization. Bounded by entry count and total bytes.
+//!
+//! The content hash is 64-bit SipHash over the tree's own serde JSON, streamed
+//! without building the string, plus the JSON length. SipHash is keyed with a random
+//! per-process key, so clients can't craft trees that collide with another client's
+//! and be served its JSON.
+
+use crate::error::Result;
+use crate::vdom::VNode;
+use serde::Serialize;
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::sync::{Arc, Mutex};
+
+lazy_static::lazy_static! {
+    static ref TREE_JSON_CACHE: Mutex<TreeJsonCache> = Mutex::new(TreeJsonCache::new(256, 16 * 1024 * 1024));
+    /// Random SipHash key of the content hash, fixed for the process
+    static ref CONTENT_HASH_KEY: RandomState = RandomState::new();
+}
+
+/// Counters for the shared cache
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TreeJsonCacheStats {
+    pub entries: usize,
+    pub bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+struct CachedJson {
+    json: Arc<str>,
+    last_used: u64,
+}
+
+struct TreeJsonCache {
+    entries: HashMap<(u64, usize), CachedJson>,
+    max_entries: usize,
+    max_bytes: usize,
+    bytes: usize,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl TreeJsonCache {
+    fn new(max_entries: usize, max_bytes: usize) -> Self {
+        Self { entries: HashMap::new(), max_entries, max_bytes, bytes: 0, tick: 0, hits: 0, misses: 0 }
+    }
+
+    fn get(&mut self, key: (u64, usize)) -> Option<Arc<str>> {
+        self.tick += 1;
+        let entry = self.entries.get_mut(&key)?;
+        entry.last_used = self.tick;
+        Some(entry.json.clone())
+    }
+
+    fn insert(&mut self, key: (u64, usize), json: Arc<str>) {
+        // Trees bigger than the whole budget aren't worth evicting everything for
+        if json.len() > self.max_bytes || self.max_entries == 0 {
+            return;
+        }
+        self.bytes += json.len();
+        if let Some(old) = self.entries.insert(key, CachedJson { json, last_used: self.tick }) {
+            self.bytes -= old.json.len();
+        }
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.entries.len() > self.max_entries || self.bytes > self.max_bytes {
+            let Some(&oldest) = self.entries.iter().min_by_key(|(_, e)| e.last_used).map(|(key, _)| key) else { break };
+            if let Some(evicted) = self.entries.remove(&oldest) {
+                self.bytes -= evicted.json.len();
+            }
+        }
+    }
+}
+
+/// Writer that hashes and counts bytes instead of storing them
+struct HashWriter {
+    hasher: DefaultHasher,
+    len: usize,
+}
+
+impl io::Write for HashWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.hasher.write(buf);
+        self.len += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Content hash and JSON length of a tree, without serializing it to a string
+/// The hash is keyed per process: equal trees hash equal within one process only
+pub fn tree_content_hash(tree: &VNode) -> Result<(u64, usize)> {
+    let mut writer = HashWriter { hasher: CONTENT_HASH_KEY.build_hasher(), len: 0 };
+    serde_json::to_writer(&mut writer, tree)?;
+    Ok((writer.hasher.finish(), writer.len))
+}
+
+/// A tree's JSON, from the shared cache when the same tree was serialized before
+pub fn tree_json(tree: &VNode) -> Result<Arc<str>> {
+    if !crate::flags::is_enabled(crate::flags::Flag::TreeJsonCache) {
+        return Ok(serde_json::to_string(tree)?.into());
+    }
+    let key = tree_content_hash(tree)?;
+    {
+        let mut cache = TREE_JSON_CACHE.lock().unwrap();
+        if let Some(json) = cache.get(key) {
+            cache.hits += 1;
+            return Ok(json);
+        }
+        cache.misses += 1;
+    }
+
+    let json: Arc<str> = serde_json::to_string(tree)?.into();
+    TREE_JSON_CACHE.lock().unwrap().insert(key, json.clone());
+    Ok(json)
+}
+
+/// Resize the shared cache (evicting least recently used entries as needed)
+pub fn configure_tree_json_cache(max_entries: usize, max_bytes: usize) {
+    let mut cache = TREE_JSON_CACHE.lock().unwrap();
+    cache.max_entries = max_entries;
+    cache.max_bytes = max_bytes;
+    cache.evict();
+}
+
+pub fn tree_json_cache_stats() -> TreeJsonCacheStats {
+    let cache = TREE_JSON_CACHE.lock().unwrap();
+    TreeJsonCacheStats { entries: cache.entries.len(), bytes: cache.bytes, hits: cache.hits, misses: cache.misses }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap as Props;
+
+    #[test]
+    fn repeated_trees_are_served_from_cache() {
+        let tree = VNode::element("section", Props::new(), vec![Some(VNode::text("tree_json_cache test"))]);
+
+        let first = tree_json(&tree).unwrap();
+        let hits_before = tree_json_cache_stats().hits;
+        let second = tree_json(&tree.clone()).unwrap();
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(tree_json_cache_stats().hits > hits_before);
+        assert_eq!(&*first, serde_json::to_string(&tree).unwrap());
+    }
+
+    #[test]
+    fn lru_respects_entry_and_byte_bounds() {
+        let mut cache = TreeJsonCache::new(2, 10);
+        cache.insert((1, 4), "aaaa".into());
+        cache.insert((2, 4), "bbbb".into());
+        cache.get((1, 4));
+        cache.insert((3, 4), "cccc".into());
+
+        // (2) was least recently used; 12 bytes > 10 would also have evicted it
+        assert!(cache.get((2, 4)).is_none());
+        assert!(cache.get((1, 4)).is_some() && cache.get((3, 4)).is_some());
+        assert_eq!(cache.bytes, 8);
+    }
+}
@@ -0,0 +1,590 @@
+//! Last-known-tree store
+//!
+//! Without it hosts keep the last tree of every component and pass it back as the
+//! old tree of each reconcile. The store keeps it instead: `set_tree` records what
+//! the client shows, `reconcile_against_stored` diffs a new tree against it and
+//! stores the new one. When the host already knows what changed (a single text
+//! binding), `apply_and_reconcile` skips the diff: it applies the host's patches to
+//! the stored tree and returns them validated and normalized for the client.
+//!
+//! Every stored tree gets a version, unique within the store and increasing. A host
+//! that passes the version it last saw to `reconcile_against_stored` gets a
+//! VersionConflict instead of a diff if another update got there first (version 0
+//! means "nothing stored yet"). `commit_trees` stores several trees at once if none
+//! of them moved: it holds every other update off while it checks and replaces them.
+//!
+//! Trees are accounted by `VNode::estimate_size`. When the store holds more than
+//! `max_trees` trees or `max_memory_bytes`, the least recently used trees are
+//! evicted down to 90% of the limit; their next reconcile starts from scratch.
+
+use crate::error::{FfiResult, MinimactError, Result};
+use crate::last_error::FfiCall;
+use crate::path::HexPath;
+use crate::reconciler::reconcile;
+use crate::vdom::{Patch, VNode};
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+
+/// Limits of a TreeStore
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TreeStoreConfig {
+    /// Most trees kept (default 10,000)
+    pub max_trees: usize,
+    /// Most estimated bytes kept (default 256 MB)
+    pub max_memory_bytes: usize,
+}
+
+impl Default for TreeStoreConfig {
+    fn default() -> Self {
+        Self {
+            max_trees: 10_000,
+            max_memory_bytes: 256 * 1024 * 1024,
+        }
+    }
+}
+
+/// Outcome of `reconcile_against_stored`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredReconcile {
+    /// Version the patches apply to (0 if nothing was stored)
+    pub previous_version: u64,
+    /// Version of the new tree, now stored
+    pub version: u64,
+    pub patches: Vec<Patch>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreeStoreStats {
+    pub trees: usize,
+    pub memory_bytes: usize,
+    pub evictions: u64,
+}
+
+struct StoredTree {
+    version: u64,
+    tree: Arc<VNode>,
+    size: usize,
+    /// Store clock at the last use (for LRU eviction)
+    last_used: AtomicU64,
+}
+
+/// Last tree per component, with versions and a memory budget
+pub struct TreeStore {
+    entries: DashMap<String, StoredTree>,
+    config: arc_swap::ArcSwap<TreeStoreConfig>,
+    memory_bytes: AtomicUsize,
+    next_version: AtomicU64,
+    clock: AtomicU64,
+    evictions: AtomicU64,
+    /// Shared by single-tree updates, exclusive for `commit_trees`
+    commit_gate: RwLock<()>,
+}
+
+impl TreeStore {
+    pub fn new(config: TreeStoreConfig) -> Self {
+        Self {
+            entries: DashMap::new(),
+            config: arc_swap::ArcSwap::from_pointee(config),
+            memory_bytes: AtomicUsize::new(0),
+            next_version: AtomicU64::new(1),
+            clock: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            commit_gate: RwLock::new(()),
+        }
+    }
+
+    /// Hold off `commit_trees` while one tree is updated
+    /// The gate guards no data, so a panic while holding it leaves nothing to recover
+    fn update_gate(&self) -> RwLockReadGuard<'_, ()> {
+        self.commit_gate.read().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Replace the limits (enforced from the next store)
+    pub fn configure(&self, config: TreeStoreConfig) {
+        self.config.store(Arc::new(config));
+    }
+
+    fn stored(&self, tree: VNode) -> StoredTree {
+        StoredTree {
+            version: self.next_version.fetch_add(1, Ordering::Relaxed),
+            size: tree.estimate_size(),
+            tree: Arc::new(tree),
+            last_used: AtomicU64::new(self.clock.fetch_add(1, Ordering::Relaxed)),
+        }
+    }
+
+    /// Store `tree` as what the client shows for `component_id`; returns its version
+    pub fn set_tree(&self, component_id: &str, tree: VNode) -> u64 {
+        let _gate = self.update_gate();
+        let stored = self.stored(tree);
+        let version = stored.version;
+        self.insert(component_id, stored);
+        version
+    }
+
+    fn insert(&self, component_id: &str, stored: StoredTree) {
+        self.memory_bytes.fetch_add(stored.size, Ordering::Relaxed);
+        if let Some(old) = self.entries.insert(component_id.to_string(), stored) {
+            self.memory_bytes.fetch_sub(old.size, Ordering::Relaxed);
+        }
+        self.enforce_limits(component_id);
+    }
+
+    /// The stored tree and its version
+    pub fn get(&self, component_id: &str) -> Option<(u64, Arc<VNode>)> {
+        let entry = self.entries.get(component_id)?;
+        entry.last_used.store(self.clock.fetch_add(1, Ordering::Relaxed), Ordering::Relaxed);
+        Some((entry.version, Arc::clone(&entry.tree)))
+    }
+
+    /// Version of the stored tree (0 if none)
+    pub fn version(&self, component_id: &str) -> u64 {
+        self.entries.get(component_id).map_or(0, |entry| entry.version)
+    }
+
+    /// Diff `new_tree` against the stored tree and store it
+    ///
+    /// With `expected_version`, fails with VersionConflict unless the stored version
+    /// is that one. Without a stored tree the patches are a single Replace of the
+    /// whole tree.
+    pub fn reconcile_against_stored(
+        &self,
+        component_id: &str,
+        new_tree: VNode,
+        expected_version: Option<u64>,
+    ) -> Result<StoredReconcile> {
+        let _gate = self.update_gate();
+        let check = |actual: u64| match expected_version {
+            Some(expected) if expected != actual => Err(MinimactError::VersionConflict { expected, actual }),
+            _ => Ok(()),
+        };
+
+        // The entry stays locked while diffing, so concurrent reconciles of one
+        // component are serialized and each diffs against its predecessor
+        let result = match self.entries.entry(component_id.to_string()) {
+            Entry::Occupied(mut entry) => {
+                let previous_version = entry.get().version;
+                check(previous_version)?;
+                let patches = reconcile(&entry.get().tree, &new_tree)?;
+                let stored = self.stored(new_tree);
+                let version = stored.version;
+                self.memory_bytes.fetch_add(stored.size, Ordering::Relaxed);
+                let old = entry.insert(stored);
+                self.memory_bytes.fetch_sub(old.size, Ordering::Relaxed);
+                StoredReconcile { previous_version, version, patches }
+            }
+            Entry::Vacant(entry) => {
+                check(0)?;
+                let patches = vec![Patch::Replace { path: new_tree.path().clone(), node: new_tree.clone(), preserve: None }];
+                let stored = self.stored(new_tree);
+                let version = stored.version;
+                self.memory_bytes.fetch_add(stored.size, Ordering::Relaxed);
+                entry.insert(stored);
+                StoredReconcile { previous_version: 0, version, patches }
+            }
+        };
+
+        self.enforce_limits(component_id);
+        Ok(result)
+    }
+
+    /// Apply host-written patches to the stored tree instead of diffing a full new
+    /// tree; returns the patches to send (see `apply::apply_and_normalize`)
+    ///
+    /// Fails without changing the store if a patch doesn't fit the stored tree or the
+    /// result isn't a valid tree. Version checks are as for `reconcile_against_stored`.
+    pub fn apply_and_reconcile(
+        &self,
+        component_id: &str,
+        input_patches: &[Patch],
+        expected_version: Option<u64>,
+    ) -> Result<StoredReconcile> {
+        let _gate = self.update_gate();
+        let result = {
+            let mut entry = self
+                .entries
+                .get_mut(component_id)
+                .ok_or_else(|| MinimactError::KeyNotFound(component_id.to_string()))?;
+            let previous_version = entry.version;
+            if let Some(expected) = expected_version.filter(|&expected| expected != previous_version) {
+                return Err(MinimactError::VersionConflict { expected, actual: previous_version });
+            }
+
+            let mut tree = VNode::clone(&entry.tree);
+            let patches = crate::apply::apply_and_normalize(&mut tree, input_patches)?;
+            tree.validate(&crate::validation::ValidationConfig::default())?;
+
+            let stored = self.stored(tree);
+            let version = stored.version;
+            self.memory_bytes.fetch_add(stored.size, Ordering::Relaxed);
+            let old = std::mem::replace(&mut *entry, stored);
+            self.memory_bytes.fetch_sub(old.size, Ordering::Relaxed);
+            StoredReconcile { previous_version, version, patches }
+        };
+
+        self.enforce_limits(component_id);
+        Ok(result)
+    }
+
+    /// Store several trees if every stored version is still the expected one (0 for
+    /// "nothing stored"); returns the new versions, in order
+    /// All versions are checked before any tree is stored, so a conflict stores nothing;
+    /// no other update runs between the check and the last tree stored
+    pub fn commit_trees(&self, trees: Vec<(String, u64, VNode)>) -> Result<Vec<u64>> {
+        let _gate = self.commit_gate.write().unwrap_or_else(std::sync::PoisonError::into_inner);
+        for (component_id, expected, _) in &trees {
+            let actual = self.version(component_id);
+            if actual != *expected {
+                return Err(MinimactError::VersionConflict { expected: *expected, actual });
+            }
+        }
+        Ok(trees
+            .into_iter()
+            .map(|(component_id, _, tree)| {
+                let stored = self.stored(tree);
+                let version = stored.version;
+                self.insert(&component_id, stored);
+                version
+            })
+            .collect())
+    }
+
+    /// Expand the placeholder at `path` in the stored tree (see `lazy::expand_lazy`)
+    /// Returns the patches to send; fails without changing the store if there's no
+    /// placeholder at `path`
+    pub fn expand_lazy(&self, component_id: &str, path: &HexPath, subtree: VNode) -> Result<StoredReconcile> {
+        let _gate = self.update_gate();
+        let result = {
+            let mut entry = self
+                .entries
+                .get_mut(component_id)
+                .ok_or_else(|| MinimactError::KeyNotFound(component_id.to_string()))?;
+            let previous_version = entry.version;
+
+            let mut tree = VNode::clone(&entry.tree);
+            let patches = crate::lazy::expand_lazy(&mut tree, path, subtree)?;
+
+            let stored = self.stored(tree);
+            let version = stored.version;
+            self.memory_bytes.fetch_add(stored.size, Ordering::Relaxed);
+            let old = std::mem::replace(&mut *entry, stored);
+            self.memory_bytes.fetch_sub(old.size, Ordering::Relaxed);
+            StoredReconcile { previous_version, version, patches }
+        };
+
+        self.enforce_limits(component_id);
+        Ok(result)
+    }
+
+    /// Every stored tree with its version (for engine snapshots)
+    pub fn export(&self) -> Vec<(String, u64, Arc<VNode>)> {
+        self.entries
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.version, Arc::clone(&entry.tree)))
+            .collect()
+    }
+
+    /// Store `tree` under a version issued by an earlier store (engine restore)
+    /// Versions issued afterwards continue above it, so hosts' expected versions stay valid
+    pub fn restore_tree(&self, component_id: &str, version: u64, tree: VNode) {
+        let _gate = self.update_gate();
+        self.next_version.fetch_max(version + 1, Ordering::Relaxed);
+        let stored = StoredTree {
+            version,
+            size: tree.estimate_size(),
+            tree: Arc::new(tree),
+            last_used: AtomicU64::new(self.clock.fetch_add(1, Ordering::Relaxed)),
+        };
+        self.insert(component_id, stored);
+    }
+
+    /// Forget a component's tree; returns false if none was stored
+    pub fn remove(&self, component_id: &str) -> bool {
+        let _gate = self.update_gate();
+        self.remove_entry(component_id)
+    }
+
+    fn remove_entry(&self, component_id: &str) -> bool {
+        match self.entries.remove(component_id) {
+            Some((_, old)) => {
+                self.memory_bytes.fetch_sub(old.size, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forget every tree; returns how many were stored
+    pub fn clear(&self) -> usize {
+        let _gate = self.commit_gate.write().unwrap_or_else(std::sync::PoisonError::into_inner);
+        let count = self.entries.len();
+        self.entries.clear();
+        self.memory_bytes.store(0, Ordering::Relaxed);
+        count
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn stats(&self) -> TreeStoreStats {
+        TreeStoreStats {
+            trees: self.entries.len(),
+            memory_bytes: self.memory_bytes.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Evict least recently used trees (never `keep`, the one just stored) until
+    /// both limits are met with 10% headroom
+    fn enforce_limits(&self, keep: &str) {
+        let config = self.config.load();
+        let over = |trees: usize, bytes: usize| trees > config.max_trees || bytes > config.max_memory_bytes;
+        if !over(self.entries.len(), self.memory_bytes.load(Ordering::Relaxed)) {
+            return;
+        }
+
+        let target_trees = config.max_trees * 9 / 10;
+        let target_bytes = config.max_memory_bytes * 9 / 10;
+        let mut candidates: Vec<(u64, String)> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.key() != keep)
+            .map(|entry| (entry.last_used.load(Ordering::Relaxed), entry.key().clone()))
+            .collect();
+        candidates.sort_unstable();
+
+        for (_, component_id) in candidates {
+            if self.entries.len() <= target_trees && self.memory_bytes.load(Ordering::Relaxed) <= target_bytes {
+                break;
+            }
+            if self.remove_entry(&component_id) {
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        crate::log_debug!("Tree store evicted down to {} trees, {} bytes", self.entries.len(), self.memory_bytes.load(Ordering::Relaxed));
+    }
+}
+
+impl Default for TreeStore {
+    fn default() -> Self {
+        Self::new(TreeStoreConfig::default())
+    }
+}
+
+lazy_static::lazy_static! {
+    /// Tree store used by the FFI
+    pub static ref TREE_STORE: TreeStore = TreeStore::default();
+}
+
+pub(crate) fn json_or_error<T: Serialize>(result: Result<T>) -> *mut c_char {
+    match result.and_then(|value| Ok(serde_json::to_string(&value)?)) {
+        Ok(json) => CString::new(json).unwrap().into_raw(),
+        Err(e) => {
+            crate::last_error::record_error(&e);
+            CString::new(serde_json::json!({ "error": e.to_string() }).to_string()).unwrap().into_raw()
+        }
+    }
+}
+
+unsafe fn parse_tree(tree_json: *const c_char) -> Result<VNode> {
+    crate::validation::deserialize_vnode_safe(CStr::from_ptr(tree_json).to_str()?, &crate::validation::ValidationConfig::default())
+}
+
+/// Store a component's tree
+/// Returns {"version": n} (or {"error": ...})
+///
+/// # Safety
+/// - All pointers must be valid null-terminated UTF-8 strings
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_tree_store_set(component_id: *const c_char, tree_json: *const c_char) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_tree_store_set", &[component_id, tree_json]);
+    json_or_error((|| -> Result<serde_json::Value> {
+        let component_id = CStr::from_ptr(component_id).to_str()?;
+        let version = TREE_STORE.set_tree(component_id, parse_tree(tree_json)?);
+        Ok(serde_json::json!({ "version": version }))
+    })())
+}
+
+/// Reconcile a component's new tree against its stored tree
+/// Returns StoredReconcile JSON (or {"error": ...}; a conflict is error code 20 in
+/// minimact_last_error_json)
+///
+/// # Safety
+/// - All pointers must be valid null-terminated UTF-8 strings
+/// - expected_version is the version last seen by the host, or -1 to skip the check
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_reconcile_stored(
+    component_id: *const c_char,
+    new_tree_json: *const c_char,
+    expected_version: i64,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_reconcile_stored", &[component_id, new_tree_json]);
+    json_or_error((|| -> Result<StoredReconcile> {
+        let component_id = CStr::from_ptr(component_id).to_str()?;
+        let expected_version = u64::try_from(expected_version).ok();
+        TREE_STORE.reconcile_against_stored(component_id, parse_tree(new_tree_json)?, expected_version)
+    })())
+}
+
+/// Apply host-written patches (JSON array) to a component's stored tree
+/// Returns StoredReconcile JSON with the normalized patches (or {"error": ...})
+///
+/// # Safety
+/// - All pointers must be valid null-terminated UTF-8 strings
+/// - expected_version is the version last seen by the host, or -1 to skip the check
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_apply_and_reconcile(
+    component_id: *const c_char,
+    patches_json: *const c_char,
+    expected_version: i64,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_apply_and_reconcile", &[component_id, patches_json]);
+    json_or_error((|| -> Result<StoredReconcile> {
+        let component_id = CStr::from_ptr(component_id).to_str()?;
+        let patches: Vec<Patch> = serde_json::from_str(CStr::from_ptr(patches_json).to_str()?)?;
+        TREE_STORE.apply_and_reconcile(component_id, &patches, u64::try_from(expected_version).ok())
+    })())
+}
+
+/// Expand a Lazy placeholder in a component's stored tree with the full subtree
+/// Returns StoredReconcile JSON with the expansion patches (or {"error": ...})
+///
+/// # Safety
+/// - All pointers must be valid null-terminated UTF-8 strings
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_tree_store_expand(
+    component_id: *const c_char,
+    path: *const c_char,
+    subtree_json: *const c_char,
+) -> *mut c_char {
+    let _call = FfiCall::enter("minimact_tree_store_expand", &[component_id, path, subtree_json]);
+    json_or_error((|| -> Result<StoredReconcile> {
+        let component_id = CStr::from_ptr(component_id).to_str()?;
+        let path = HexPath::from(CStr::from_ptr(path).to_str()?);
+        TREE_STORE.expand_lazy(component_id, &path, parse_tree(subtree_json)?)
+    })())
+}
+
+/// Forget a component's stored tree (no error if none was stored)
+///
+/// # Safety
+/// - component_id must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_tree_store_remove(component_id: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_tree_store_remove", &[component_id]);
+    match CStr::from_ptr(component_id).to_str() {
+        Ok(component_id) => {
+            TREE_STORE.remove(component_id);
+            FfiResult::success()
+        }
+        Err(e) => FfiResult::error(&MinimactError::from(e)),
+    }
+}
+
+/// Set the tree store's limits (TreeStoreConfig JSON)
+///
+/// # Safety
+/// - config_json must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_tree_store_configure(config_json: *const c_char) -> FfiResult {
+    let _call = FfiCall::enter("minimact_tree_store_configure", &[config_json]);
+    let config = CStr::from_ptr(config_json)
+        .to_str()
+        .map_err(MinimactError::from)
+        .and_then(|json| Ok(serde_json::from_str::<TreeStoreConfig>(json)?));
+    match config {
+        Ok(config) => {
+            TREE_STORE.configure(config);
+            FfiResult::success()
+        }
+        Err(e) => FfiResult::error(&e),
+    }
+}
+
+/// Tree store counters as TreeStoreStats JSON
+///
+/// # Safety
+/// - The returned pointer must be freed using minimact_free_string
+#[no_mangle]
+pub unsafe extern "C" fn minimact_tree_store_stats() -> *mut c_char {
+    let _call = FfiCall::named("minimact_tree_store_stats");
+    json_or_error(Ok(TREE_STORE.stats()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(content: &str) -> VNode {
+        VNode::Text(crate::vdom::VText { content: content.to_string(), path: HexPath::from("10000000") })
+    }
+
+    #[test]
+    fn test_versions_detect_concurrent_updates() {
+        let store = TreeStore::default();
+        let first = store.reconcile_against_stored("Counter", text("0"), Some(0)).unwrap();
+        assert_eq!(first.previous_version, 0);
+        assert!(matches!(&first.patches[..], [Patch::Replace { .. }]));
+
+        let second = store.reconcile_against_stored("Counter", text("1"), Some(first.version)).unwrap();
+        assert_eq!(second.previous_version, first.version);
+        assert!(second.version > first.version);
+        assert_eq!(second.patches, vec![Patch::UpdateText { path: HexPath::from("10000000"), content: "1".to_string() }]);
+
+        // A host still holding the first version lost the race
+        let stale = store.reconcile_against_stored("Counter", text("2"), Some(first.version));
+        assert!(matches!(stale, Err(MinimactError::VersionConflict { actual, .. }) if actual == second.version));
+        assert_eq!(store.version("Counter"), second.version);
+
+        let version = store.set_tree("Counter", text("5"));
+        assert_eq!(store.reconcile_against_stored("Counter", text("5"), None).unwrap().previous_version, version);
+    }
+
+    #[test]
+    fn test_apply_and_reconcile_skips_the_diff() {
+        let store = TreeStore::default();
+        assert!(matches!(store.apply_and_reconcile("Counter", &[], None), Err(MinimactError::KeyNotFound(_))));
+
+        let version = store.set_tree("Counter", text("0"));
+        let update = |content: &str| Patch::UpdateText { path: HexPath::from("10000000"), content: content.to_string() };
+        let result = store.apply_and_reconcile("Counter", &[update("1"), update("2")], Some(version)).unwrap();
+        assert_eq!((result.previous_version, result.patches), (version, vec![update("2")]));
+        assert_eq!(*store.get("Counter").unwrap().1, text("2"));
+
+        // A patch that doesn't fit leaves the stored tree alone
+        let bad = Patch::UpdateText { path: HexPath::from("20000000"), content: "x".to_string() };
+        assert!(store.apply_and_reconcile("Counter", &[bad], None).is_err());
+        assert_eq!(store.version("Counter"), result.version);
+    }
+
+    #[test]
+    fn test_least_recently_used_trees_are_evicted() {
+        let size = text("x").estimate_size();
+        let store = TreeStore::new(TreeStoreConfig { max_trees: 10, max_memory_bytes: size * 3 });
+        store.set_tree("a", text("a"));
+        store.set_tree("b", text("b"));
+        store.set_tree("c", text("c"));
+        store.get("a");
+        store.set_tree("d", text("d"));
+
+        assert_eq!(store.stats(), TreeStoreStats { trees: 2, memory_bytes: size * 2, evictions: 2 });
+        assert!(store.get("a").is_some() && store.get("d").is_some());
+        assert!(store.remove("a") && !store.remove("b"));
+        assert_eq!(store.stats().memory_bytes, size);
+    }
+}
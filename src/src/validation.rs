@@ -24,6 +24,10 @@ pub struct ValidationConfig {
 
     /// Maximum JSON size for deserialization (default: 1MB)
     pub max_json_size: usize,
+
+    /// Maximum estimated in-memory bytes per byte of JSON (default: 16)
+    /// Checked by a pre-scan, before anything is allocated
+    pub max_expansion_ratio: usize,
 }
 
 impl Default for ValidationConfig {
@@ -36,6 +40,21 @@ impl Default for ValidationConfig {
             max_prop_value_length: 4_096,
             max_text_length: 1024 * 1024, // 1MB
             max_json_size: 1024 * 1024,   // 1MB
+            max_expansion_ratio: 16,
+        }
+    }
+}
+
+impl ValidationConfig {
+    /// Limits for the whole of a payload carrying up to `trees` trees that are each
+    /// held to these limits: `trees` times the size and nodes, and room for the
+    /// array and object wrapped around each tree
+    pub fn for_batch(&self, trees: usize) -> Self {
+        Self {
+            max_tree_depth: self.max_tree_depth + 1,
+            max_node_count: self.max_node_count.saturating_mul(trees),
+            max_json_size: self.max_json_size.saturating_mul(trees),
+            ..self.clone()
         }
     }
 }
@@ -68,13 +87,30 @@ impl VNode {
             }
             VNode::Text(_) => {}
             VNode::Null(_) => {}  // Null nodes have no depth
+            VNode::Lazy(_) => {}
         }
 
         Ok(())
     }
 
+    /// Validate this subtree, rooted `depth` levels below the tree's root: its depth
+    /// and content sizes in one pass (the node count is a whole-tree limit)
+    pub fn validate_subtree(&self, depth: usize, config: &ValidationConfig) -> Result<()> {
+        if depth > config.max_tree_depth {
+            return Err(MinimactError::TreeTooDeep {
+                depth,
+                max: config.max_tree_depth,
+            });
+        }
+        self.validate_local(config)?;
+        for child in self.children().iter().flatten() {
+            child.validate_subtree(depth + 1, config)?;
+        }
+        Ok(())
+    }
+
     /// Validate total node count doesn't exceed maximum
-    fn validate_node_count(&self, config: &ValidationConfig) -> Result<()> {
+    pub(crate) fn validate_node_count(&self, config: &ValidationConfig) -> Result<()> {
         let count = self.count_nodes();
         if count > config.max_node_count {
             return Err(MinimactError::TreeTooLarge {
@@ -95,11 +131,23 @@ impl VNode {
                     .sum::<usize>()
             }
             VNode::Null(_) => 1,  // Null nodes count as 1 (placeholder)
+            VNode::Lazy(_) => 1,
         }
     }
 
     /// Validate content sizes (text length, prop lengths, children count)
     fn validate_content_sizes(&self, config: &ValidationConfig) -> Result<()> {
+        self.validate_local(config)?;
+
+        // Recursively validate children (skip nulls)
+        for child in self.children().iter().flatten() {
+            child.validate_content_sizes(config)?;
+        }
+        Ok(())
+    }
+
+    /// Validate this node's own content sizes, not its children's
+    pub(crate) fn validate_local(&self, config: &ValidationConfig) -> Result<()> {
         match self {
             VNode::Text(text) => {
                 if text.content.len() > config.max_text_length {
@@ -135,15 +183,16 @@ impl VNode {
                         });
                     }
                 }
-
-                // Recursively validate children (skip nulls)
-                for opt_child in &el.children {
-                    if let Some(child) = opt_child {
-                        child.validate_content_sizes(config)?;
-                    }
-                }
             }
             VNode::Null(_) => {}  // Null nodes have no content to validate
+            VNode::Lazy(lazy) => {
+                if lazy.summary.len() > config.max_text_length {
+                    return Err(MinimactError::TextTooLong {
+                        length: lazy.summary.len(),
+                        max: config.max_text_length,
+                    });
+                }
+            }
         }
 
         Ok(())
@@ -169,12 +218,25 @@ impl VNode {
                 base + tag + key + props + children
             }
             VNode::Null(_) => std::mem::size_of::<crate::vdom::VNull>(),  // Just the struct size
+            VNode::Lazy(lazy) => std::mem::size_of::<crate::vdom::VLazy>() + lazy.summary.capacity(),
         }
     }
 }
 
 /// Deserialize VNode with validation
 pub fn deserialize_vnode_safe(json: &str, config: &ValidationConfig) -> Result<VNode> {
+    check_json_input(json, config)?;
+
+    // Deserialize
+    let mut node: VNode = serde_json::from_str(json)?;
+
+    prepare_vnode(&mut node, config)?;
+    Ok(node)
+}
+
+/// Size check and shape pre-scan of raw JSON, before serde allocates anything
+/// (the first half of `deserialize_vnode_safe`, for payloads that carry trees)
+pub fn check_json_input(json: &str, config: &ValidationConfig) -> Result<JsonShape> {
     // Check JSON size first
     if json.len() > config.max_json_size {
         return Err(MinimactError::JsonTooLarge {
@@ -183,13 +245,95 @@ pub fn deserialize_vnode_safe(json: &str, config: &ValidationConfig) -> Result<V
         });
     }
 
-    // Deserialize
-    let node: VNode = serde_json::from_str(json)?;
+    // Reject pathological shapes before serde allocates the tree
+    scan_json_shape(json, config)
+}
+
+/// Normalize and validate a tree parsed out of a larger payload
+/// (the second half of `deserialize_vnode_safe`)
+pub fn prepare_vnode(node: &mut VNode, config: &ValidationConfig) -> Result<()> {
+    // Merge/drop transpiler text nodes if enabled (see text_normalization)
+    let text_options = crate::text_normalization::text_normalization();
+    if text_options.is_enabled() {
+        crate::text_normalization::normalize_text_nodes(node, &text_options);
+    }
+
+    // Rename prop aliases to the client's spelling if enabled (see prop_normalization)
+    if let Some(naming) = crate::prop_normalization::prop_normalization().naming {
+        crate::prop_normalization::normalize_prop_names(node, naming);
+    }
 
     // Validate structure
-    node.validate(config)?;
+    node.validate(config)
+}
 
-    Ok(node)
+/// Inputs estimated below this never fail the expansion ratio (small trees are cheap
+/// whatever their ratio)
+const EXPANSION_GRACE_BYTES: usize = 64 * 1024;
+
+/// Shape of a JSON document, from `scan_json_shape`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JsonShape {
+    /// Objects and nulls: each one becomes at most one VNode (or child slot)
+    pub values: usize,
+    /// Deepest object/array nesting
+    pub max_nesting: usize,
+}
+
+/// Scan `json` without parsing it, aborting as soon as it gets deeper, larger or
+/// more expansive than `config` allows
+///
+/// Every tree level is two nesting levels (node object, children array), and an
+/// element takes up to four values (itself, props, source, key). Bare `null` children
+/// are the cheapest way to inflate a tree: five bytes each, a full VNode slot in memory.
+pub fn scan_json_shape(json: &str, config: &ValidationConfig) -> Result<JsonShape> {
+    let max_nesting = config.max_tree_depth.saturating_mul(2).saturating_add(4);
+    let max_values = config.max_node_count.saturating_mul(4);
+    let max_estimate = json.len().saturating_mul(config.max_expansion_ratio).max(EXPANSION_GRACE_BYTES);
+
+    let mut shape = JsonShape::default();
+    let (mut nesting, mut in_string, mut escaped) = (0usize, false, false);
+    let bytes = json.as_bytes();
+    for (i, &byte) in bytes.iter().enumerate() {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'{' | b'[' => {
+                nesting += 1;
+                shape.max_nesting = shape.max_nesting.max(nesting);
+                if nesting > max_nesting {
+                    return Err(MinimactError::TreeTooDeep { depth: nesting / 2, max: config.max_tree_depth });
+                }
+                if byte == b'{' {
+                    shape.values += 1;
+                }
+            }
+            b'}' | b']' => nesting = nesting.saturating_sub(1),
+            b'n' if bytes[i..].starts_with(b"null") => shape.values += 1,
+            _ => continue,
+        }
+
+        if shape.values > max_values {
+            return Err(MinimactError::TreeTooLarge { nodes: shape.values, max: config.max_node_count });
+        }
+        let estimated_bytes = shape.values * std::mem::size_of::<Option<VNode>>();
+        if estimated_bytes > max_estimate {
+            return Err(MinimactError::ExpansionLimitExceeded {
+                input_bytes: json.len(),
+                estimated_bytes,
+                max_ratio: config.max_expansion_ratio,
+            });
+        }
+    }
+    Ok(shape)
 }
 
 /// Serialize VNode with safety checks
@@ -211,6 +355,7 @@ pub fn serialize_vnode_safe(node: &VNode) -> Result<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::path::HexPath;
 
     #[test]
     fn test_validate_depth_pass() {
@@ -218,16 +363,21 @@ mod tests {
             tag: "div".to_string(),
             props: Default::default(),
             children: vec![
-                VNode::Element(VElement {
+                Some(VNode::Element(VElement {
                     tag: "p".to_string(),
                     props: Default::default(),
-                    children: vec![VNode::Text(VText {
+                    children: vec![Some(VNode::Text(VText {
                         content: "Hello".to_string(),
-                    })],
+                        path: HexPath::root(),
+                    }))],
                     key: None,
-                })
+                    source: None,
+                    path: HexPath::root(),
+                }))
             ],
             key: None,
+            source: None,
+            path: HexPath::root(),
         });
 
         let config = ValidationConfig::default();
@@ -243,16 +393,21 @@ mod tests {
             tag: "div".to_string(),
             props: Default::default(),
             children: vec![
-                VNode::Element(VElement {
+                Some(VNode::Element(VElement {
                     tag: "p".to_string(),
                     props: Default::default(),
-                    children: vec![VNode::Text(VText {
+                    children: vec![Some(VNode::Text(VText {
                         content: "Too deep".to_string(),
-                    })],
+                        path: HexPath::root(),
+                    }))],
                     key: None,
-                })
+                    source: None,
+                    path: HexPath::root(),
+                }))
             ],
             key: None,
+            source: None,
+            path: HexPath::root(),
         });
 
         assert!(matches!(
@@ -268,6 +423,7 @@ mod tests {
 
         let node = VNode::Text(VText {
             content: "This text is way too long".to_string(),
+            path: HexPath::root(),
         });
 
         assert!(matches!(
@@ -282,15 +438,19 @@ mod tests {
             tag: "div".to_string(),
             props: Default::default(),
             children: vec![
-                VNode::Text(VText { content: "1".to_string() }),
-                VNode::Element(VElement {
+                Some(VNode::Text(VText { content: "1".to_string(), path: HexPath::root() })),
+                Some(VNode::Element(VElement {
                     tag: "p".to_string(),
                     props: Default::default(),
-                    children: vec![VNode::Text(VText { content: "2".to_string() })],
+                    children: vec![Some(VNode::Text(VText { content: "2".to_string(), path: HexPath::root() }))],
                     key: None,
-                }),
+                    source: None,
+                    path: HexPath::root(),
+                })),
             ],
             key: None,
+            source: None,
+            path: HexPath::root(),
         });
 
         // 1 (div) + 1 (text) + 1 (p) + 1 (text) = 4
@@ -306,11 +466,13 @@ mod tests {
             tag: "div".to_string(),
             props: Default::default(),
             children: vec![
-                VNode::Text(VText { content: "1".to_string() }),
-                VNode::Text(VText { content: "2".to_string() }),
-                VNode::Text(VText { content: "3".to_string() }),
+                Some(VNode::Text(VText { content: "1".to_string(), path: HexPath::root() })),
+                Some(VNode::Text(VText { content: "2".to_string(), path: HexPath::root() })),
+                Some(VNode::Text(VText { content: "3".to_string(), path: HexPath::root() })),
             ],
             key: None,
+            source: None,
+            path: HexPath::root(),
         });
 
         assert!(matches!(
@@ -318,4 +480,55 @@ mod tests {
             Err(MinimactError::TooManyChildren { .. })
         ));
     }
+
+    fn null_flood(children: usize) -> String {
+        let nulls = vec!["null"; children].join(",");
+        format!(r#"{{"type":"Element","tag":"ul","props":{{}},"key":null,"path":"10000000","children":[{}]}}"#, nulls)
+    }
+
+    #[test]
+    fn test_scan_rejects_pathological_inputs_early() {
+        let config = ValidationConfig { max_node_count: 1_000_000, max_children_per_node: 1_000_000, ..Default::default() };
+
+        // Five bytes of JSON per 152-byte child slot
+        let err = deserialize_vnode_safe(&null_flood(50_000), &config).unwrap_err();
+        assert!(matches!(err, MinimactError::ExpansionLimitExceeded { max_ratio: 16, .. }), "{}", err);
+        assert_eq!(crate::error::ErrorCode::from(&err), crate::error::ErrorCode::ExpansionLimitExceeded);
+
+        // Nesting aborts at the depth limit, long before the end of the input
+        let deep = "[".repeat(100_000);
+        assert!(matches!(scan_json_shape(&deep, &config), Err(MinimactError::TreeTooDeep { max: 100, .. })));
+
+        let wide = format!("[{}]", vec!["{}"; 50_000].join(","));
+        let config = ValidationConfig { max_node_count: 1_000, ..Default::default() };
+        assert!(matches!(scan_json_shape(&wide, &config), Err(MinimactError::TreeTooLarge { nodes: 4_001, .. })));
+    }
+
+    #[test]
+    fn test_batch_payloads_are_scanned_as_a_whole() {
+        let config = ValidationConfig::default();
+        let tree = serde_json::to_string(&VNode::text("a")).unwrap();
+        let pairs = format!(r#"[{{"old":{0},"new":{0}}}]"#, tree);
+        check_json_input(&pairs, &config.for_batch(2)).unwrap();
+
+        // A null flood hidden in one pair is caught before serde sees the payload
+        let flooded = format!(r#"[{{"old":{},"new":{}}}]"#, tree, null_flood(50_000));
+        let err = check_json_input(&flooded, &config.for_batch(2)).unwrap_err();
+        assert!(matches!(err, MinimactError::ExpansionLimitExceeded { .. }), "{}", err);
+    }
+
+    #[test]
+    fn test_scan_accepts_realistic_trees() {
+        let corpus: serde_json::Value = serde_json::from_str(include_str!("../corpus/dashboard.json")).unwrap();
+        let json = serde_json::to_string(&corpus["old"]).unwrap();
+        let shape = scan_json_shape(&json, &ValidationConfig::default()).unwrap();
+        assert!(shape.values > 100 && shape.max_nesting > 10);
+
+        // Still fine once well past the grace size
+        let config = ValidationConfig { max_json_size: usize::MAX, max_node_count: usize::MAX / 8, ..Default::default() };
+        let rows = vec![json.as_str(); 400].join(",");
+        let big = format!(r#"{{"type":"Element","tag":"main","props":{{}},"key":null,"path":"","children":[{}]}}"#, rows);
+        assert!(big.len() * 16 > EXPANSION_GRACE_BYTES);
+        scan_json_shape(&big, &config).unwrap();
+    }
 }
@@ -9,6 +9,7 @@ pub enum VNode {
     Element(VElement),
     Text(VText),
     Null(VNull),
+    Lazy(VLazy),
 }
 
 /// Represents a Virtual DOM element
@@ -24,6 +25,21 @@ pub struct VElement {
     pub key: Option<String>,
     /// Hex-based path from transpilation (e.g., "10000000.20000000")
     pub path: HexPath,
+    /// JSX location of the element (dev builds of the Babel plugin)
+    /// Not part of the DOM: changing it alone never produces a patch
+    /// (Boxed so elements without one only pay for a pointer)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<Box<SourceLocation>>,
+}
+
+/// Where an element was written in the component's JSX
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SourceLocation {
+    pub file: String,
+    /// 1-based
+    pub line: u32,
+    /// 1-based
+    pub col: u32,
 }
 
 /// Represents a text node
@@ -43,6 +59,16 @@ pub struct VNull {
     pub path: HexPath,
 }
 
+/// Placeholder for a subtree the client hasn't expanded yet (collapsed tree view
+/// nodes, virtualized rows); the reconciler treats it as opaque (see `lazy`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VLazy {
+    pub path: HexPath,
+    /// What the client shows in its place (e.g. "42 items")
+    #[serde(default)]
+    pub summary: String,
+}
+
 /// Binding with optional transform (Phase 6: Expression Templates)
 /// Represents a state variable and how to transform it for display
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -140,8 +166,22 @@ pub struct StructuralTemplate {
     pub default_branch: Option<Box<VNode>>,
 }
 
+/// UX state the client should carry across a Replace or ReorderChildren
+/// Computed from the old tree (see `preservation`)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreserveHints {
+    /// Element of the old subtree that may have focus; refocus it (and restore its
+    /// selection) if it had focus before the patch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focus_selector: Option<String>,
+    /// The old subtree has scroll containers; record scroll offsets before the patch
+    /// and restore them after
+    #[serde(default)]
+    pub scroll: bool,
+}
+
 /// Represents a change operation for the DOM
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum Patch {
     /// Create a new node at the given path
@@ -157,6 +197,8 @@ pub enum Patch {
     Replace {
         path: HexPath,
         node: VNode,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        preserve: Option<PreserveHints>,
     },
     /// Update text content
     UpdateText {
@@ -172,6 +214,8 @@ pub enum Patch {
     ReorderChildren {
         path: HexPath,
         order: Vec<String>, // keys in new order
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        preserve: Option<PreserveHints>,
     },
     /// Update text using template (runtime prediction)
     /// Enables 100% coverage with minimal memory (2KB vs 100KB per component)
@@ -226,6 +270,121 @@ pub enum Patch {
         #[serde(rename = "templatePatch")]
         template_patch: TemplatePatch,
     },
+    /// Virtualized list: rows `start..end` get concrete patches, the rest of the
+    /// `total` rows aren't rendered by the client (it only reserves space for them)
+    UpdateListWindow {
+        path: HexPath,
+        total: usize,
+        start: usize,
+        end: usize,
+    },
+    /// Set (or with no value, remove) a document setting held by the head element
+    /// at `path`: the title, a meta or a link (see `document`)
+    UpdateDocument {
+        path: HexPath,
+        target: crate::document::DocumentTarget,
+        value: Option<String>,
+    },
+    /// Mark (or unmark) the element at `path` as owned by client-side code: the
+    /// server stops patching inside it (see `ignored_regions`)
+    SetIgnored {
+        path: HexPath,
+        ignored: bool,
+    },
+    /// Advisory: cascading `attributes` of the element at `path` changed, so its
+    /// subtree may need a layout recalculation (see `layout_hints`); changes nothing
+    InvalidateLayout {
+        path: HexPath,
+        attributes: Vec<String>,
+    },
+    /// Effect marker: the element at `path` was just mounted, or is about to be
+    /// unmounted; `handler` is the value of its lifecycle prop (see `lifecycle`).
+    /// Changes nothing
+    Lifecycle {
+        path: HexPath,
+        event: crate::lifecycle::LifecycleEvent,
+        handler: String,
+    },
+    /// Move the client to `url` (see `routing`); `replace` replaces the current
+    /// history entry instead of pushing one
+    Navigate {
+        #[serde(default = "HexPath::root")]
+        path: HexPath,
+        url: String,
+        #[serde(default)]
+        replace: bool,
+    },
+    /// Host-defined operation carried on the patch stream (see `custom_patch`)
+    Custom {
+        #[serde(default = "HexPath::root")]
+        path: HexPath,
+        kind: String,
+        #[serde(default)]
+        payload: serde_json::Value,
+    },
+}
+
+impl Patch {
+    /// Get the hex path this patch targets
+    pub fn path(&self) -> &HexPath {
+        match self {
+            Patch::Create { path, .. }
+            | Patch::Remove { path }
+            | Patch::Replace { path, .. }
+            | Patch::UpdateText { path, .. }
+            | Patch::UpdateProps { path, .. }
+            | Patch::ReorderChildren { path, .. }
+            | Patch::UpdateTextTemplate { path, .. }
+            | Patch::UpdatePropsTemplate { path, .. }
+            | Patch::UpdateListTemplate { path, .. }
+            | Patch::ReorderTemplate { path, .. }
+            | Patch::ReplaceConditional { path, .. }
+            | Patch::UpdateAttributeStatic { path, .. }
+            | Patch::UpdateAttributeDynamic { path, .. }
+            | Patch::UpdateListWindow { path, .. }
+            | Patch::UpdateDocument { path, .. }
+            | Patch::SetIgnored { path, .. }
+            | Patch::InvalidateLayout { path, .. }
+            | Patch::Lifecycle { path, .. }
+            | Patch::Navigate { path, .. }
+            | Patch::Custom { path, .. } => path,
+        }
+    }
+
+    /// Get the patch kind as a string (matches the serialized "type" tag)
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Patch::Create { .. } => "Create",
+            Patch::Remove { .. } => "Remove",
+            Patch::Replace { .. } => "Replace",
+            Patch::UpdateText { .. } => "UpdateText",
+            Patch::UpdateProps { .. } => "UpdateProps",
+            Patch::ReorderChildren { .. } => "ReorderChildren",
+            Patch::UpdateTextTemplate { .. } => "UpdateTextTemplate",
+            Patch::UpdatePropsTemplate { .. } => "UpdatePropsTemplate",
+            Patch::UpdateListTemplate { .. } => "UpdateListTemplate",
+            Patch::ReorderTemplate { .. } => "ReorderTemplate",
+            Patch::ReplaceConditional { .. } => "ReplaceConditional",
+            Patch::UpdateAttributeStatic { .. } => "UpdateAttributeStatic",
+            Patch::UpdateAttributeDynamic { .. } => "UpdateAttributeDynamic",
+            Patch::UpdateListWindow { .. } => "UpdateListWindow",
+            Patch::UpdateDocument { .. } => "UpdateDocument",
+            Patch::SetIgnored { .. } => "SetIgnored",
+            Patch::InvalidateLayout { .. } => "InvalidateLayout",
+            Patch::Lifecycle { .. } => "Lifecycle",
+            Patch::Navigate { .. } => "Navigate",
+            Patch::Custom { .. } => "Custom",
+        }
+    }
+
+    /// Check if this patch replaces, inserts or removes a whole node
+    /// (as opposed to updating content or attributes in place)
+    pub fn is_structural(&self) -> bool {
+        matches!(
+            self,
+            Patch::Create { .. } | Patch::Remove { .. } | Patch::Replace { .. } | Patch::ReplaceConditional { .. }
+        )
+    }
 }
 
 impl VNode {
@@ -237,6 +396,7 @@ impl VNode {
             children,
             key: None,
             path: HexPath::root(),  // Empty path for test nodes
+            source: None,
         })
     }
 
@@ -253,6 +413,7 @@ impl VNode {
             children,
             key: Some(key.into()),
             path: HexPath::root(),  // Empty path for test nodes
+            source: None,
         })
     }
 
@@ -268,8 +429,7 @@ impl VNode {
     pub fn key(&self) -> Option<&str> {
         match self {
             VNode::Element(el) => el.key.as_deref(),
-            VNode::Text(_) => None,
-            VNode::Null(_) => None,
+            VNode::Text(_) | VNode::Null(_) | VNode::Lazy(_) => None,
         }
     }
 
@@ -279,9 +439,31 @@ impl VNode {
             VNode::Element(el) => &el.path,
             VNode::Text(txt) => &txt.path,
             VNode::Null(null) => &null.path,
+            VNode::Lazy(lazy) => &lazy.path,
         }
     }
 
+    /// Find the node whose hex path equals `path`, descending through
+    /// children whose paths are ancestors of the target
+    pub fn find_by_path(&self, path: &HexPath) -> Option<&VNode> {
+        if self.path() == path {
+            return Some(self);
+        }
+        self.children()
+            .iter()
+            .flatten()
+            .find(|child| path.is_within(child.path()))
+            .and_then(|child| child.find_by_path(path))
+    }
+
+    /// Move this node to `path`, rebasing its subtree
+    /// Descendants under the old path keep their relative segments; any others
+    /// (e.g. runtime-rendered nodes without paths) get positional child paths
+    pub fn rebase_path(&mut self, path: &HexPath) {
+        let old_root = self.path().clone();
+        rebase_node(self, &old_root, path);
+    }
+
     /// Check if this node is a text node
     pub fn is_text(&self) -> bool {
         matches!(self, VNode::Text(_))
@@ -297,21 +479,26 @@ impl VNode {
         matches!(self, VNode::Null(_))
     }
 
+    /// Check if this node is an unexpanded placeholder
+    pub fn is_lazy(&self) -> bool {
+        matches!(self, VNode::Lazy(_))
+    }
+
     /// Get the node type as a string (for error messages)
     pub fn node_type(&self) -> &'static str {
         match self {
             VNode::Element(_) => "Element",
             VNode::Text(_) => "Text",
             VNode::Null(_) => "Null",
+            VNode::Lazy(_) => "Lazy",
         }
     }
 
-    /// Get the children of this node (empty vec for text/null nodes)
+    /// Get the children of this node (empty for text/null/lazy nodes)
     pub fn children(&self) -> &[Option<VNode>] {
         match self {
             VNode::Element(el) => &el.children,
-            VNode::Text(_) => &[],
-            VNode::Null(_) => &[],
+            VNode::Text(_) | VNode::Null(_) | VNode::Lazy(_) => &[],
         }
     }
 
@@ -319,8 +506,7 @@ impl VNode {
     pub fn children_count(&self) -> usize {
         match self {
             VNode::Element(el) => el.children.len(),
-            VNode::Text(_) => 0,
-            VNode::Null(_) => 0,
+            VNode::Text(_) | VNode::Null(_) | VNode::Lazy(_) => 0,
         }
     }
 
@@ -328,9 +514,31 @@ impl VNode {
     pub fn non_null_children_count(&self) -> usize {
         match self {
             VNode::Element(el) => el.children.iter().filter(|c| c.is_some()).count(),
-            VNode::Text(_) => 0,
-            VNode::Null(_) => 0,
+            VNode::Text(_) | VNode::Null(_) | VNode::Lazy(_) => 0,
+        }
+    }
+}
+
+fn rebase_node(node: &mut VNode, old_root: &HexPath, new_path: &HexPath) {
+    match node {
+        VNode::Element(el) => {
+            el.path = new_path.clone();
+            for (index, child) in el.children.iter_mut().enumerate() {
+                if let Some(child) = child {
+                    // Grandchildren are rebased relative to this child's old path
+                    let child_old = child.path().clone();
+                    let child_path = if !old_root.is_root() && child_old.is_descendant_of(old_root) {
+                        HexPath(format!("{}{}", new_path, &child_old.0[old_root.len()..]))
+                    } else {
+                        new_path.child(index)
+                    };
+                    rebase_node(child, &child_old, &child_path);
+                }
+            }
         }
+        VNode::Text(text) => text.path = new_path.clone(),
+        VNode::Null(null) => null.path = new_path.clone(),
+        VNode::Lazy(lazy) => lazy.path = new_path.clone(),
     }
 }
 
@@ -377,7 +585,7 @@ impl StateXProjection {
 
 /// Template metadata extracted by Babel plugin
 /// Describes a single template (text, attribute, etc.) for hot reload and prediction
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TemplateInfo {
     /// Template string with {0}, {1} placeholders
     pub template: String,
@@ -405,7 +613,7 @@ pub struct TemplateInfo {
 }
 
 /// Transform metadata for templates with method calls (e.g., toFixed(2))
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TransformInfo {
     /// Transform method name (e.g., "toFixed")
     pub method: String,
@@ -463,6 +671,9 @@ pub struct ComponentMetadata {
     /// Maps path string (e.g., "[0].h1[0].text[0]", "[0].button[0].@style") to template info
     #[serde(default)]
     pub templates: HashMap<String, TemplateInfo>,
+    /// Hash of the component's transpiled code; learned patterns are tied to it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_hash: Option<String>,
 }
 
 impl ComponentMetadata {
@@ -474,6 +685,7 @@ impl ComponentMetadata {
             loop_templates: HashMap::new(),
             state_x_projections: Vec::new(),
             templates: HashMap::new(),
+            code_hash: None,
         }
     }
 
@@ -560,4 +772,31 @@ mod tests {
         let deserialized: VNode = serde_json::from_str(&json).unwrap();
         assert_eq!(node, deserialized);
     }
+
+    #[test]
+    fn test_rebase_path_keeps_relative_segments_at_depth() {
+        let element = |path: &str, children: Vec<Option<VNode>>| VNode::Element(VElement {
+            tag: "div".to_string(),
+            props: HashMap::new(),
+            children,
+            key: None,
+            path: HexPath::from(path),
+            source: None,
+        });
+        let text = VNode::Text(VText {
+            content: "deep".to_string(),
+            path: HexPath::from("10000000.10000000.20000000.30000000"),
+        });
+        let mut node = element("10000000.10000000", vec![Some(element(
+            "10000000.10000000.20000000",
+            vec![Some(text)],
+        ))]);
+
+        node.rebase_path(&HexPath::from("10000000.18000000"));
+
+        let child = node.children()[0].as_ref().unwrap();
+        assert_eq!(child.path(), &HexPath::from("10000000.18000000.20000000"));
+        let grandchild = child.children()[0].as_ref().unwrap();
+        assert_eq!(grandchild.path(), &HexPath::from("10000000.18000000.20000000.30000000"));
+    }
 }
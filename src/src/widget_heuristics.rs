@@ -0,0 +1,233 @@
+//! Cold-start widget heuristics
+//!
+//! Before the predictor has seen a state key change, it can still recognize common
+//! widgets from their tag and ARIA semantics and guess the attribute patches: a
+//! button whose `aria-expanded` shows the old boolean flips it, a `<progress>` whose
+//! `value` shows the old number moves to the new one. A widget is "bound" to the
+//! changed key when its attribute holds the old value; if several widgets qualify,
+//! only those that mention the key (id, name, aria-controls, data-state, class) count,
+//! and an ambiguous match predicts nothing.
+//!
+//! Heuristics live in a process-wide registry seeded with the built-ins; hosts can
+//! register their own for app-specific widgets.
+
+use crate::predictor::StateChange;
+use crate::vdom::{Patch, VElement, VNode};
+use arc_swap::ArcSwap;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Predicts patches for one kind of widget
+pub trait WidgetHeuristic: Send + Sync {
+    /// Unique name, used to unregister
+    fn name(&self) -> &str;
+
+    /// Patches for `state_change` applied to `tree`, or None when no widget matches
+    fn predict(&self, state_change: &StateChange, tree: &VNode) -> Option<Vec<Patch>>;
+}
+
+lazy_static::lazy_static! {
+    static ref HEURISTICS: ArcSwap<Vec<Arc<dyn WidgetHeuristic>>> = ArcSwap::from_pointee(builtin_heuristics());
+}
+
+fn builtin_heuristics() -> Vec<Arc<dyn WidgetHeuristic>> {
+    vec![
+        Arc::new(BooleanAttribute { name: "aria-expanded", attribute: "aria-expanded" }),
+        Arc::new(BooleanAttribute { name: "aria-pressed", attribute: "aria-pressed" }),
+        Arc::new(BooleanAttribute { name: "aria-checked", attribute: "aria-checked" }),
+        Arc::new(BooleanAttribute { name: "aria-selected", attribute: "aria-selected" }),
+        Arc::new(NumericAttribute { name: "progress", tags: &["progress", "meter"], attribute: "value" }),
+        Arc::new(NumericAttribute { name: "progressbar", tags: &[], attribute: "aria-valuenow" }),
+    ]
+}
+
+/// Add a heuristic (replacing one with the same name); tried after the existing ones
+pub fn register_widget_heuristic(heuristic: Arc<dyn WidgetHeuristic>) {
+    HEURISTICS.rcu(|heuristics| {
+        let mut heuristics: Vec<_> = heuristics.iter().filter(|h| h.name() != heuristic.name()).cloned().collect();
+        heuristics.push(heuristic.clone());
+        heuristics
+    });
+}
+
+/// Remove a heuristic by name; returns false if none had that name
+pub fn unregister_widget_heuristic(name: &str) -> bool {
+    let mut removed = false;
+    HEURISTICS.rcu(|heuristics| {
+        let kept: Vec<_> = heuristics.iter().filter(|h| h.name() != name).cloned().collect();
+        removed = kept.len() != heuristics.len();
+        kept
+    });
+    removed
+}
+
+/// Names of the registered heuristics, in the order they're tried
+pub fn widget_heuristics() -> Vec<String> {
+    HEURISTICS.load().iter().map(|h| h.name().to_string()).collect()
+}
+
+/// Patches from every heuristic that recognizes a widget bound to the change
+pub fn predict_widgets(state_change: &StateChange, tree: &VNode) -> Option<Vec<Patch>> {
+    let patches: Vec<Patch> = HEURISTICS
+        .load()
+        .iter()
+        .filter_map(|heuristic| {
+            let patches = heuristic.predict(state_change, tree)?;
+            crate::log_debug!("Widget heuristic '{}' matched {}", heuristic.name(), state_change.state_key);
+            Some(patches)
+        })
+        .flatten()
+        .collect();
+    (!patches.is_empty()).then_some(patches)
+}
+
+/// Flips a boolean ARIA attribute that shows the old value
+struct BooleanAttribute {
+    name: &'static str,
+    attribute: &'static str,
+}
+
+impl WidgetHeuristic for BooleanAttribute {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn predict(&self, state_change: &StateChange, tree: &VNode) -> Option<Vec<Patch>> {
+        let (Value::Bool(old), Value::Bool(new)) = (&state_change.old_value, &state_change.new_value) else {
+            return None;
+        };
+        let old = old.to_string();
+        let widget = bound_widget(tree, state_change, |el| el.props.get(self.attribute) == Some(&old))?;
+        Some(vec![set_attribute(widget, self.attribute, new.to_string())])
+    }
+}
+
+/// Moves a numeric attribute (progress value, aria-valuenow) from the old number
+struct NumericAttribute {
+    name: &'static str,
+    /// Tags the attribute is recognized on (empty = role="progressbar")
+    tags: &'static [&'static str],
+    attribute: &'static str,
+}
+
+impl WidgetHeuristic for NumericAttribute {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn predict(&self, state_change: &StateChange, tree: &VNode) -> Option<Vec<Patch>> {
+        let (Value::Number(old), Value::Number(new)) = (&state_change.old_value, &state_change.new_value) else {
+            return None;
+        };
+        let old = old.as_f64()?;
+        let widget = bound_widget(tree, state_change, |el| {
+            let recognized = if self.tags.is_empty() {
+                el.props.get("role").map(String::as_str) == Some("progressbar")
+            } else {
+                self.tags.contains(&el.tag.as_str())
+            };
+            recognized && el.props.get(self.attribute).and_then(|v| v.parse::<f64>().ok()) == Some(old)
+        })?;
+        Some(vec![set_attribute(widget, self.attribute, new.to_string())])
+    }
+}
+
+/// The one element matching `is_widget`, narrowed by key hints when there are several
+fn bound_widget<'a>(
+    tree: &'a VNode,
+    state_change: &StateChange,
+    is_widget: impl Fn(&VElement) -> bool,
+) -> Option<&'a VElement> {
+    let mut candidates = Vec::new();
+    collect_elements(tree, &is_widget, &mut candidates);
+
+    if candidates.len() > 1 {
+        let key = state_change.state_key.to_lowercase();
+        candidates.retain(|el| {
+            ["id", "name", "aria-controls", "data-state", "className"]
+                .iter()
+                .filter_map(|attr| el.props.get(*attr))
+                .any(|value| value.to_lowercase().contains(&key))
+        });
+    }
+    match candidates.as_slice() {
+        [widget] => Some(widget),
+        _ => None,
+    }
+}
+
+fn collect_elements<'a>(node: &'a VNode, is_widget: &impl Fn(&VElement) -> bool, found: &mut Vec<&'a VElement>) {
+    if let VNode::Element(el) = node {
+        if is_widget(el) {
+            found.push(el);
+        }
+        for child in el.children.iter().flatten() {
+            collect_elements(child, is_widget, found);
+        }
+    }
+}
+
+/// UpdateProps carries the element's full props
+fn set_attribute(el: &VElement, attribute: &str, value: String) -> Patch {
+    let mut props = el.props.clone();
+    props.insert(attribute.to_string(), value);
+    Patch::UpdateProps { path: el.path.clone(), props }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::HexPath;
+    use std::collections::HashMap;
+
+    fn change(key: &str, old: Value, new: Value) -> StateChange {
+        StateChange {
+            component_id: "Widget".to_string(),
+            state_key: key.to_string(),
+            old_value: old,
+            new_value: new,
+            array_operation: None,
+            locale: None,
+        }
+    }
+
+    fn element(tag: &str, path: &str, props: &[(&str, &str)]) -> VNode {
+        VNode::Element(VElement {
+            tag: tag.to_string(),
+            props: props.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            children: vec![],
+            key: None,
+            path: HexPath::from(path),
+            source: None,
+        })
+    }
+
+    #[test]
+    fn recognizes_disclosure_button_and_progress() {
+        let tree = VNode::element("div", HashMap::new(), vec![
+            Some(element("button", "10000000", &[("aria-expanded", "false"), ("aria-controls", "menu")])),
+            Some(element("progress", "20000000", &[("value", "40"), ("max", "100")])),
+        ]);
+
+        let patches = predict_widgets(&change("menuOpen", Value::Bool(false), Value::Bool(true)), &tree).unwrap();
+        assert_eq!(patches, vec![Patch::UpdateProps {
+            path: HexPath::from("10000000"),
+            props: HashMap::from([
+                ("aria-expanded".to_string(), "true".to_string()),
+                ("aria-controls".to_string(), "menu".to_string()),
+            ]),
+        }]);
+
+        let patches = predict_widgets(&change("uploaded", serde_json::json!(40), serde_json::json!(55)), &tree).unwrap();
+        assert!(matches!(&patches[..], [Patch::UpdateProps { props, .. }] if props["value"] == "55"));
+    }
+
+    #[test]
+    fn ambiguous_widgets_predict_nothing() {
+        let tree = VNode::element("div", HashMap::new(), vec![
+            Some(element("button", "10000000", &[("aria-pressed", "false")])),
+            Some(element("button", "20000000", &[("aria-pressed", "false")])),
+        ]);
+        assert!(predict_widgets(&change("bold", Value::Bool(false), Value::Bool(true)), &tree).is_none());
+    }
+}
@@ -0,0 +1,438 @@
+//! Synthetic predictor workloads
+//!
+//! Generates reproducible streams of the state changes real apps produce (counters,
+//! toggles, list edits, route changes) with a configurable mix and noise, renders
+//! each component before and after, and drives `Predictor::predict` + `learn` with
+//! them. `run_workload` reports hit rate, latency percentiles and predictor memory,
+//! so predictor changes can be compared by numbers instead of by feel.
+//!
+//! A prediction is a hit when its patches, applied to the old tree, give the new one.
+//! Each event's reconcile is checked the same way, through a `Simulator`: a workload
+//! whose reconciler patches don't reproduce the new tree fails instead of reporting
+//! numbers measured against wrong patches.
+
+use crate::apply::apply_patches;
+use crate::error::{MinimactError, Result};
+use crate::metrics::percentile;
+use crate::path::HexPath;
+use crate::predictor::{ArrayOperation, Predictor, StateChange};
+use crate::reconciler::reconcile;
+use crate::routing::ROUTE_STATE_KEY;
+use crate::sim::Simulator;
+use crate::vdom::VNode;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Pages route changes pick from
+const ROUTES: [&str; 5] = ["/", "/about", "/docs", "/blog", "/pricing"];
+
+/// Kind of component a workload drives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkloadKind {
+    /// `count` steps by one
+    Counter,
+    /// `open` flips
+    Toggle,
+    /// `items` gets appends, removals and edits
+    List,
+    /// `$route` moves between pages
+    Route,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkloadConfig {
+    /// Same seed, same stream
+    pub seed: u64,
+    pub events: usize,
+    /// Component instances per kind
+    pub components_per_kind: usize,
+    /// Relative weight of each kind; kinds left out don't occur
+    pub mix: Vec<(WorkloadKind, u32)>,
+    /// Probability that a change breaks its component's usual pattern
+    /// (a counter jumps, a list is reset)
+    pub noise: f64,
+    /// Items a list starts with
+    pub initial_list_len: usize,
+}
+
+impl Default for WorkloadConfig {
+    fn default() -> Self {
+        Self {
+            seed: 42,
+            events: 1000,
+            components_per_kind: 2,
+            mix: vec![
+                (WorkloadKind::Counter, 4),
+                (WorkloadKind::Toggle, 3),
+                (WorkloadKind::List, 2),
+                (WorkloadKind::Route, 1),
+            ],
+            noise: 0.05,
+            initial_list_len: 5,
+        }
+    }
+}
+
+/// One state change with the component's trees around it
+#[derive(Debug, Clone)]
+pub struct WorkloadEvent {
+    pub kind: WorkloadKind,
+    pub state_change: StateChange,
+    pub old_tree: VNode,
+    pub new_tree: VNode,
+    /// Component state after the change
+    pub state: HashMap<String, Value>,
+}
+
+struct SyntheticComponent {
+    id: String,
+    kind: WorkloadKind,
+    value: Value,
+    next_item: u64,
+}
+
+/// Endless, reproducible stream of workload events
+pub struct WorkloadGenerator {
+    config: WorkloadConfig,
+    rng: Rng,
+    components: Vec<SyntheticComponent>,
+}
+
+impl WorkloadGenerator {
+    pub fn new(config: WorkloadConfig) -> Self {
+        let mut components = Vec::new();
+        for &(kind, weight) in &config.mix {
+            if weight == 0 {
+                continue;
+            }
+            for n in 0..config.components_per_kind.max(1) {
+                let id = format!("{}-{}", state_key(kind).trim_start_matches('$'), n);
+                let initial = config.initial_list_len as u64;
+                let value = match kind {
+                    WorkloadKind::Counter => json!(0),
+                    WorkloadKind::Toggle => json!(false),
+                    WorkloadKind::List => Value::Array((0..initial).map(|i| json!(format!("Item {}", i))).collect()),
+                    WorkloadKind::Route => json!(ROUTES[0]),
+                };
+                components.push(SyntheticComponent { id, kind, value, next_item: initial });
+            }
+        }
+        let rng = Rng::new(config.seed);
+        Self { config, rng, components }
+    }
+
+    fn pick_kind(&mut self) -> Option<WorkloadKind> {
+        let total: u64 = self.config.mix.iter().map(|&(_, w)| w as u64).sum();
+        if total == 0 || self.components.is_empty() {
+            return None;
+        }
+        let mut roll = self.rng.below(total);
+        self.config.mix.iter().find_map(|&(kind, weight)| {
+            if roll < weight as u64 {
+                Some(kind)
+            } else {
+                roll -= weight as u64;
+                None
+            }
+        })
+    }
+}
+
+impl Iterator for WorkloadGenerator {
+    type Item = WorkloadEvent;
+
+    fn next(&mut self) -> Option<WorkloadEvent> {
+        let kind = self.pick_kind()?;
+        let candidates: Vec<usize> = (0..self.components.len()).filter(|&i| self.components[i].kind == kind).collect();
+        let index = candidates[self.rng.below(candidates.len() as u64) as usize];
+        let noisy = self.rng.chance(self.config.noise);
+
+        let component = &self.components[index];
+        let old_value = component.value.clone();
+        let mut next_item = component.next_item;
+        let mut array_operation = None;
+
+        let new_value = match kind {
+            WorkloadKind::Counter => {
+                let count = old_value.as_i64().unwrap_or(0);
+                if noisy {
+                    json!(count + 2 + self.rng.below(10) as i64)
+                } else {
+                    json!(count + 1)
+                }
+            }
+            WorkloadKind::Toggle => json!(!old_value.as_bool().unwrap_or(false)),
+            WorkloadKind::List => {
+                let mut items = old_value.as_array().cloned().unwrap_or_default();
+                let roll = self.rng.below(4);
+                if noisy {
+                    items.clear();
+                } else if roll < 2 || items.is_empty() {
+                    let item = json!(format!("Item {}", next_item));
+                    next_item += 1;
+                    items.push(item.clone());
+                    array_operation = Some(ArrayOperation::Append { item });
+                } else if roll == 2 {
+                    let at = self.rng.below(items.len() as u64) as usize;
+                    items.remove(at);
+                    array_operation = Some(ArrayOperation::RemoveAt { index: at });
+                } else {
+                    let at = self.rng.below(items.len() as u64) as usize;
+                    let item = json!(format!("{} (edited)", items[at].as_str().unwrap_or_default()));
+                    items[at] = item.clone();
+                    array_operation = Some(ArrayOperation::UpdateAt { index: at, item });
+                }
+                Value::Array(items)
+            }
+            WorkloadKind::Route => {
+                let others: Vec<&str> = ROUTES.iter().copied().filter(|r| Some(*r) != old_value.as_str()).collect();
+                json!(others[self.rng.below(others.len() as u64) as usize])
+            }
+        };
+
+        let component = &mut self.components[index];
+        component.value = new_value.clone();
+        component.next_item = next_item;
+
+        Some(WorkloadEvent {
+            kind,
+            state_change: StateChange {
+                component_id: component.id.clone(),
+                state_key: state_key(kind).to_string(),
+                old_value: old_value.clone(),
+                new_value: new_value.clone(),
+                array_operation,
+                locale: None,
+            },
+            old_tree: render(kind, &old_value),
+            new_tree: render(kind, &new_value),
+            state: HashMap::from([(state_key(kind).to_string(), new_value)]),
+        })
+    }
+}
+
+fn state_key(kind: WorkloadKind) -> &'static str {
+    match kind {
+        WorkloadKind::Counter => "count",
+        WorkloadKind::Toggle => "open",
+        WorkloadKind::List => "items",
+        WorkloadKind::Route => ROUTE_STATE_KEY,
+    }
+}
+
+/// What each kind of component renders for a state value
+fn render(kind: WorkloadKind, value: &Value) -> VNode {
+    let props = |pairs: &[(&str, String)]| pairs.iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
+    let mut tree = match kind {
+        WorkloadKind::Counter => VNode::element("div", props(&[("className", "counter".into())]), vec![
+            Some(VNode::element("span", HashMap::new(), vec![Some(VNode::text(format!("Count: {}", value)))])),
+            Some(VNode::element("button", HashMap::new(), vec![Some(VNode::text("+"))])),
+        ]),
+        WorkloadKind::Toggle => {
+            let open = value.as_bool().unwrap_or(false);
+            let label = if open { "Hide details" } else { "Show details" };
+            VNode::element("div", props(&[("className", if open { "panel open" } else { "panel" }.into())]), vec![
+                Some(VNode::element("button", HashMap::new(), vec![Some(VNode::text(label))])),
+            ])
+        }
+        WorkloadKind::List => {
+            let items = value.as_array().cloned().unwrap_or_default();
+            VNode::element("ul", HashMap::new(), items.iter().map(|item| {
+                let text = item.as_str().unwrap_or_default();
+                let key = text.trim_end_matches(" (edited)").to_string();
+                Some(VNode::keyed_element("li", key, HashMap::new(), vec![Some(VNode::text(text))]))
+            }).collect())
+        }
+        WorkloadKind::Route => {
+            let url = value.as_str().unwrap_or("/");
+            let title = match url.trim_start_matches('/') {
+                "" => "Home".to_string(),
+                page => format!("{}{}", page[..1].to_uppercase(), &page[1..]),
+            };
+            VNode::element("main", props(&[("data-route", url.to_string())]), vec![
+                Some(VNode::element("h1", HashMap::new(), vec![Some(VNode::text(title))])),
+            ])
+        }
+    };
+    assign_paths(&mut tree, HexPath::root());
+    tree
+}
+
+fn assign_paths(node: &mut VNode, path: HexPath) {
+    match node {
+        VNode::Element(element) => {
+            for (index, child) in element.children.iter_mut().enumerate() {
+                if let Some(child) = child {
+                    assign_paths(child, path.child(index));
+                }
+            }
+            element.path = path;
+        }
+        VNode::Text(text) => text.path = path,
+        VNode::Null(null) => null.path = path,
+        VNode::Lazy(lazy) => lazy.path = path,
+    }
+}
+
+/// Latency distribution in microseconds
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LatencyPercentiles {
+    pub p50_us: u64,
+    pub p90_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+
+impl LatencyPercentiles {
+    fn from_samples(samples: &[u64]) -> Self {
+        Self {
+            p50_us: percentile(samples, 0.5),
+            p90_us: percentile(samples, 0.9),
+            p99_us: percentile(samples, 0.99),
+            max_us: samples.iter().copied().max().unwrap_or(0),
+        }
+    }
+}
+
+/// Outcomes for one kind of component
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KindReport {
+    pub events: usize,
+    pub predictions: usize,
+    pub hits: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkloadReport {
+    pub events: usize,
+    /// Events the predictor offered patches for
+    pub predictions: usize,
+    pub hits: usize,
+    /// Hits over all events (a missing prediction counts as a miss)
+    pub hit_rate: f64,
+    /// Hits over predictions made
+    pub precision: f64,
+    pub predict_latency: LatencyPercentiles,
+    pub learn_latency: LatencyPercentiles,
+    /// Predictor's own estimate after the run
+    pub estimated_memory_bytes: usize,
+    pub total_patterns: usize,
+    pub by_kind: HashMap<WorkloadKind, KindReport>,
+}
+
+/// Play a generated workload through `predictor`: predict, score, then learn each event
+pub fn run_workload(predictor: &mut Predictor, config: &WorkloadConfig) -> Result<WorkloadReport> {
+    let mut report = WorkloadReport::default();
+    let mut predict_times = Vec::with_capacity(config.events);
+    let mut learn_times = Vec::with_capacity(config.events);
+
+    for event in WorkloadGenerator::new(config.clone()).take(config.events) {
+        let started = Instant::now();
+        let prediction = predictor.predict(&event.state_change, &event.old_tree);
+        predict_times.push(started.elapsed().as_micros() as u64);
+
+        let kind = report.by_kind.entry(event.kind).or_default();
+        kind.events += 1;
+        report.events += 1;
+        if let Some(prediction) = prediction {
+            kind.predictions += 1;
+            report.predictions += 1;
+            if predicts(&event.old_tree, &prediction.predicted_patches, &event.new_tree) {
+                kind.hits += 1;
+                report.hits += 1;
+            }
+        }
+
+        check_reconcile(&event)?;
+
+        let started = Instant::now();
+        predictor.learn(event.state_change, &event.old_tree, &event.new_tree, Some(&event.state))?;
+        learn_times.push(started.elapsed().as_micros() as u64);
+    }
+
+    let stats = predictor.stats();
+    report.hit_rate = ratio(report.hits, report.events);
+    report.precision = ratio(report.hits, report.predictions);
+    report.predict_latency = LatencyPercentiles::from_samples(&predict_times);
+    report.learn_latency = LatencyPercentiles::from_samples(&learn_times);
+    report.estimated_memory_bytes = stats.estimated_memory_bytes;
+    report.total_patterns = stats.total_patterns;
+    Ok(report)
+}
+
+/// Play the event's reconcile on a simulated client; its tree must end up as the new one
+fn check_reconcile(event: &WorkloadEvent) -> Result<()> {
+    let mut client = Simulator::new(event.old_tree.clone());
+    client.apply_batch(&reconcile(&event.old_tree, &event.new_tree)?)?;
+    if client.tree() != &event.new_tree {
+        return Err(MinimactError::InvalidVNode(format!(
+            "{} {:?} -> {:?}: reconcile patches don't reproduce the new tree",
+            event.state_change.component_id, event.state_change.old_value, event.state_change.new_value
+        )));
+    }
+    Ok(())
+}
+
+fn predicts(old_tree: &VNode, patches: &[crate::vdom::Patch], new_tree: &VNode) -> bool {
+    let mut tree = old_tree.clone();
+    apply_patches(&mut tree, patches).is_ok() && reconcile(&tree, new_tree).is_ok_and(|rest| rest.is_empty())
+}
+
+fn ratio(part: usize, whole: usize) -> f64 {
+    if whole == 0 { 0.0 } else { part as f64 / whole as f64 }
+}
+
+/// SplitMix64 - reproducible without a rand dependency
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n.max(1)
+    }
+
+    fn chance(&mut self, p: f64) -> bool {
+        ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_stream() {
+        let config = WorkloadConfig { events: 50, ..Default::default() };
+        let a: Vec<Value> = WorkloadGenerator::new(config.clone()).take(50).map(|e| e.state_change.new_value).collect();
+        let b: Vec<Value> = WorkloadGenerator::new(config).take(50).map(|e| e.state_change.new_value).collect();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn report_accounts_for_every_event() {
+        let config = WorkloadConfig { events: 300, ..Default::default() };
+        let report = run_workload(&mut Predictor::new(), &config).unwrap();
+
+        assert_eq!(report.events, 300);
+        assert_eq!(report.by_kind.values().map(|k| k.events).sum::<usize>(), 300);
+        assert_eq!(report.by_kind.len(), 4);
+        assert!(report.hits <= report.predictions && report.predictions <= report.events);
+        assert!(report.predict_latency.p50_us <= report.predict_latency.p99_us);
+        assert!(report.estimated_memory_bytes > 0);
+    }
+}
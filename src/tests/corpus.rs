@@ -0,0 +1,25 @@
+//! Reconcile every tree pair in corpus/ and hold it to its expected patch bounds
+
+use minimact::{load_corpus, run_corpus};
+use std::path::{Path, PathBuf};
+
+fn corpus_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("corpus")
+}
+
+#[test]
+fn corpus_cases_stay_within_bounds() {
+    let outcomes = run_corpus(&corpus_dir()).expect("corpus loads and reconciles");
+    let failures: Vec<String> = outcomes
+        .iter()
+        .filter(|outcome| !outcome.passed())
+        .map(|outcome| format!("{}: {}", outcome.name, outcome.violations.join("; ")))
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn corpus_covers_the_curated_components() {
+    let names: Vec<String> = load_corpus(&corpus_dir()).unwrap().into_iter().map(|case| case.name).collect();
+    assert_eq!(names, ["dashboard", "form-wizard", "todo-list"]);
+}